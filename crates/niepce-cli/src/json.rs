@@ -0,0 +1,94 @@
+/*
+ * niepce - niepce-cli/json.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The JSON output of the catalog objects.
+
+use std::path::Path;
+
+use serde_json::{Value, json};
+
+use npc_engine::catalog::{Album, Keyword, LibFile, LibFolder};
+use npc_engine::importer::ImportSummary;
+
+fn path(path: &Path) -> Value {
+    Value::String(path.to_string_lossy().into())
+}
+
+pub fn file(file: &LibFile) -> Value {
+    json!({
+        "id": file.id(),
+        "folder": file.folder_id(),
+        "name": file.name(),
+        "path": path(file.path()),
+        "type": <&str>::from(file.file_type()),
+        "rating": file.rating(),
+        "label": file.label(),
+        "flag": file.flag(),
+        "orientation": file.orientation(),
+        "virtual_copy": file.is_copy(),
+        "stack": file.stack_id(),
+    })
+}
+
+pub fn files(files: &[LibFile]) -> Value {
+    Value::Array(files.iter().map(file).collect())
+}
+
+pub fn folder(folder: &LibFolder) -> Value {
+    json!({
+        "id": folder.id(),
+        "name": folder.name(),
+        "path": folder.path(),
+        "parent": folder.parent(),
+    })
+}
+
+pub fn album(album: &Album) -> Value {
+    json!({
+        "id": album.id(),
+        "name": album.name(),
+        "parent": album.parent(),
+    })
+}
+
+pub fn keyword(keyword: &Keyword) -> Value {
+    json!({
+        "id": keyword.id(),
+        "keyword": keyword.keyword(),
+        "parent": keyword.parent(),
+        "exportable": keyword.exportable(),
+    })
+}
+
+pub fn import_summary(summary: &ImportSummary) -> Value {
+    json!({
+        "imported": summary.imported.iter().map(|p| path(p)).collect::<Vec<_>>(),
+        "skipped": summary.skipped.iter().map(|p| path(p)).collect::<Vec<_>>(),
+        "failed": summary
+            .failed
+            .iter()
+            .map(|(p, err)| json!({ "path": path(p), "error": err }))
+            .collect::<Vec<_>>(),
+        "warnings": summary
+            .warnings
+            .iter()
+            .map(|(p, warning)| json!({ "path": path(p), "warning": warning }))
+            .collect::<Vec<_>>(),
+    })
+}
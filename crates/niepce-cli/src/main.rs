@@ -0,0 +1,651 @@
+/*
+ * niepce - niepce-cli/main.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Command line access to a catalog, without the UI. Each subcommand
+//! runs the library commands on the catalog and prints the result as
+//! JSON on the standard output. Errors are printed as JSON on the
+//! standard error, with a non-zero exit code.
+
+mod json;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use serde_json::{Value, json};
+
+use npc_engine::catalog::props::NiepceProperties as Np;
+use npc_engine::catalog::props::NiepcePropertyIdx as NpI;
+use npc_engine::catalog::{CatalogDb, LibFile, LibraryId, SearchQuery, StackingOptions};
+use npc_engine::importer::ImportJob;
+use npc_engine::library::RenderEngine;
+use npc_engine::library::commands;
+use npc_engine::library::notification::LibNotification;
+use npc_fwk::utils::FileList;
+use npc_fwk::{ExempiManager, PropertyValue};
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Command line access to a Niepce catalog")]
+struct Args {
+    /// The catalog file. It is created if it doesn't exist.
+    #[arg(short = 'L', long)]
+    catalog: PathBuf,
+
+    /// Pretty print the JSON output.
+    #[arg(long)]
+    pretty: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Import the files of a directory, in place.
+    Import {
+        /// The directory.
+        dir: PathBuf,
+        /// Import the subdirectories.
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// List the folders.
+    Folders,
+    /// List the albums.
+    Albums,
+    /// List the keywords.
+    Keywords,
+    /// Query the files. All the criteria must match.
+    Query {
+        /// Rating, like `4` or `>=3`.
+        #[arg(long)]
+        rating: Option<String>,
+        /// Has the keyword. Can be passed multiple times.
+        #[arg(long)]
+        keyword: Vec<String>,
+        /// Has the label with that name.
+        #[arg(long)]
+        label: Option<String>,
+        /// Flag: `picked`, `rejected` or `none`.
+        #[arg(long)]
+        flag: Option<String>,
+        /// A search query, as in the search bar.
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// Set a metadata property on files.
+    Set {
+        property: Property,
+        /// The value. Keywords are separated with `,`.
+        value: String,
+        /// The files.
+        #[arg(required = true)]
+        ids: Vec<LibraryId>,
+    },
+    /// Write the metadata to the XMP sidecars.
+    WriteXmp {
+        /// The files.
+        #[arg(required_unless_present = "pending")]
+        ids: Vec<LibraryId>,
+        /// Write the files whose metadata changed instead.
+        #[arg(long, conflicts_with = "ids")]
+        pending: bool,
+    },
+    /// Search a directory for the missing files and relink them.
+    Relink {
+        /// The directory, searched recursively.
+        dir: PathBuf,
+    },
+    /// List the files missing on storage.
+    Verify,
+    /// Export the keywords in the Lightroom text format.
+    ExportKeywords {
+        /// The file to write.
+        file: PathBuf,
+    },
+    /// Export the images, rendered with their develop settings.
+    Export {
+        /// The directory to write the images to.
+        dest: PathBuf,
+        /// The files.
+        #[arg(required = true)]
+        ids: Vec<LibraryId>,
+        /// The image format.
+        #[arg(long, value_enum, default_value_t = ExportFormat::Jpeg)]
+        format: ExportFormat,
+        /// The JPEG quality, from 1 to 100.
+        #[arg(long, default_value_t = 90, value_parser = clap::value_parser!(u8).range(1..=100))]
+        quality: u8,
+        /// Render with RawTherapee instead of Niepce Camera Raw.
+        #[arg(long)]
+        rt: bool,
+    },
+}
+
+/// The image formats to export to.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportFormat {
+    Jpeg,
+    Png,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::Png => "png",
+        }
+    }
+}
+
+/// The path to export `file` to in `dest`. The virtual copies, and
+/// the files whose name is taken, get their id in the name. An
+/// existing file is never overwritten.
+fn export_path(dest: &Path, file: &LibFile, format: ExportFormat) -> Result<PathBuf, String> {
+    let stem = file
+        .path()
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let with_id = dest.join(format!("{stem}-{}.{}", file.id(), format.extension()));
+    let path = dest.join(format!("{stem}.{}", format.extension()));
+    if !file.is_copy() && !path.exists() {
+        Ok(path)
+    } else if !with_id.exists() {
+        Ok(with_id)
+    } else {
+        Err(format!("{with_id:?} already exists"))
+    }
+}
+
+/// The metadata properties that can be set.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Property {
+    Rating,
+    /// The label id.
+    Label,
+    /// -1 for rejected, 0 for none and 1 for picked.
+    Flag,
+    Headline,
+    Description,
+    Keywords,
+    Creator,
+    Rights,
+    Credit,
+    Source,
+    City,
+    State,
+    Country,
+}
+
+impl Property {
+    fn meta(&self) -> Np {
+        Np::Index(match *self {
+            Property::Rating => NpI::NpXmpRatingProp,
+            Property::Label => NpI::NpXmpLabelProp,
+            Property::Flag => NpI::NpNiepceFlagProp,
+            Property::Headline => NpI::NpIptcHeadlineProp,
+            Property::Description => NpI::NpIptcDescriptionProp,
+            Property::Keywords => NpI::NpIptcKeywordsProp,
+            Property::Creator => NpI::NpDcCreatorProp,
+            Property::Rights => NpI::NpDcRightsProp,
+            Property::Credit => NpI::NpIptcCreditProp,
+            Property::Source => NpI::NpIptcSourceProp,
+            Property::City => NpI::NpIptcCityProp,
+            Property::State => NpI::NpIptcStateProp,
+            Property::Country => NpI::NpIptcCountryProp,
+        })
+    }
+
+    /// Parse the `value` for the property.
+    fn value(&self, value: &str) -> Result<PropertyValue, String> {
+        match *self {
+            Property::Rating | Property::Label | Property::Flag => value
+                .parse::<i32>()
+                .map(PropertyValue::Int)
+                .map_err(|_| format!("Invalid value '{value}' for {self:?}")),
+            Property::Keywords => Ok(PropertyValue::StringArray(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(String::from)
+                    .collect(),
+            )),
+            _ => Ok(PropertyValue::String(value.to_string())),
+        }
+    }
+}
+
+/// Build the search query for the criteria.
+fn search_query(
+    rating: Option<&str>,
+    keywords: &[String],
+    label: Option<&str>,
+    flag: Option<&str>,
+    query: Option<&str>,
+) -> Result<String, String> {
+    let mut terms = vec![];
+    if let Some(rating) = rating {
+        terms.push(format!("rating:{rating}"));
+    }
+    terms.extend(
+        keywords
+            .iter()
+            .map(|keyword| format!("keyword:\"{keyword}\"")),
+    );
+    if let Some(label) = label {
+        terms.push(format!("label:\"{label}\""));
+    }
+    if let Some(flag) = flag {
+        terms.push(format!("flag:{flag}"));
+    }
+    if let Some(query) = query {
+        terms.push(query.to_string());
+    }
+    let query = terms.join(" ");
+    let parsed = query
+        .parse::<SearchQuery>()
+        .map_err(|err| format!("Invalid query '{query}': {err}"))?;
+    if parsed.is_empty() {
+        return Err("Empty query".into());
+    }
+    Ok(query)
+}
+
+/// The catalog and the receiving end of its notifications. The
+/// commands are run synchronously, so their notifications are
+/// already queued when they return.
+struct Cli {
+    catalog: CatalogDb,
+    sender: async_channel::Sender<LibNotification>,
+    receiver: async_channel::Receiver<LibNotification>,
+}
+
+impl Cli {
+    /// Open the catalog at `path`, upgrading it if needed.
+    fn open(path: &Path) -> Result<Cli, String> {
+        let (sender, receiver) = async_channel::unbounded();
+        let catalog = CatalogDb::new(path, sender.clone());
+        while let Ok(notification) = receiver.try_recv() {
+            match notification {
+                LibNotification::DatabaseNeedUpgrade(version) => {
+                    if !commands::cmd_upgrade_catalog_from(&catalog, version) {
+                        return Err(format!("Couldn't upgrade the catalog from {version}"));
+                    }
+                }
+                LibNotification::DatabaseReady => {
+                    return Ok(Cli {
+                        catalog,
+                        sender,
+                        receiver,
+                    });
+                }
+                _ => {}
+            }
+        }
+        Err(format!("Couldn't open the catalog {path:?}"))
+    }
+
+    fn notifications(&self) -> Vec<LibNotification> {
+        std::iter::from_fn(|| self.receiver.try_recv().ok()).collect()
+    }
+
+    /// Run the command `f`, named `name`, and return its notifications.
+    fn run<F>(&self, name: &str, f: F) -> Result<Vec<LibNotification>, String>
+    where
+        F: FnOnce(&CatalogDb) -> bool,
+    {
+        // Drop what earlier commands left.
+        self.notifications();
+        if f(&self.catalog) {
+            Ok(self.notifications())
+        } else {
+            Err(format!("{name} failed"))
+        }
+    }
+
+    /// The files `ids`, those not found left out.
+    fn files(&self, ids: &[LibraryId]) -> Result<Vec<LibFile>, String> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        self.run("get files", |catalog| {
+            commands::cmd_get_files(
+                catalog,
+                ids,
+                Box::new(move |files| {
+                    let _ = sender.send(files);
+                }),
+            )
+        })?;
+        receiver
+            .try_recv()
+            .map_err(|err| format!("get files failed: {err}"))
+    }
+
+    /// Render the file `file` and write it in `dest`. Return the path
+    /// written.
+    fn export_file(
+        &self,
+        file: &LibFile,
+        dest: &Path,
+        format: ExportFormat,
+        quality: u8,
+        engine: RenderEngine,
+    ) -> Result<PathBuf, String> {
+        let develop = self
+            .run("get metadata", |catalog| {
+                commands::cmd_request_metadata(catalog, file.id())
+            })?
+            .into_iter()
+            .find_map(|n| match n {
+                LibNotification::MetadataQueried(metadata) => Some(metadata.develop_settings()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let bitmap = npc_craw::render_file(file, engine, &develop)
+            .ok_or_else(|| format!("Couldn't render {:?}", file.path()))?;
+        let path = export_path(dest, file, format)?;
+        match format {
+            ExportFormat::Jpeg => bitmap.save_jpeg(&path, quality),
+            ExportFormat::Png => bitmap.save_png(&path),
+        }
+        .map_err(|err| format!("Couldn't write {path:?}: {err}"))?;
+        Ok(path)
+    }
+
+    fn execute(&self, command: Command) -> Result<Value, String> {
+        match command {
+            Command::Import { dir, recursive } => {
+                let dir = std::fs::canonicalize(&dir)
+                    .map_err(|err| format!("Invalid directory {dir:?}: {err}"))?;
+                let files = FileList::files_from_directory(&dir, |_| true, recursive, None);
+                let job = ImportJob::new(self.sender.clone(), || false);
+                let summary = self
+                    .run("import", |catalog| {
+                        commands::cmd_import_files(
+                            catalog,
+                            &dir,
+                            &files,
+                            &StackingOptions::default(),
+                            None,
+                            Some(&job),
+                        )
+                    })?
+                    .into_iter()
+                    .find_map(|n| match n {
+                        LibNotification::ImportFinished(summary) => Some(summary),
+                        _ => None,
+                    })
+                    .ok_or("import didn't finish")?;
+                Ok(json::import_summary(&summary))
+            }
+            Command::Folders => Ok(Value::Array(
+                self.run("list folders", |catalog| {
+                    commands::cmd_list_all_folders(catalog, None)
+                })?
+                .iter()
+                .filter_map(|n| match n {
+                    LibNotification::AddedFolder(folder) => Some(json::folder(folder)),
+                    _ => None,
+                })
+                .collect(),
+            )),
+            Command::Albums => Ok(Value::Array(
+                self.run("list albums", commands::cmd_list_all_albums)?
+                    .iter()
+                    .filter_map(|n| match n {
+                        LibNotification::AddedAlbum(album) => Some(json::album(album)),
+                        _ => None,
+                    })
+                    .collect(),
+            )),
+            Command::Keywords => Ok(Value::Array(
+                self.run("list keywords", |catalog| {
+                    commands::cmd_list_all_keywords(catalog, None)
+                })?
+                .iter()
+                .filter_map(|n| match n {
+                    LibNotification::AddedKeyword(keyword) => Some(json::keyword(keyword)),
+                    _ => None,
+                })
+                .collect(),
+            )),
+            Command::Query {
+                rating,
+                keyword,
+                label,
+                flag,
+                query,
+            } => {
+                let query = search_query(
+                    rating.as_deref(),
+                    &keyword,
+                    label.as_deref(),
+                    flag.as_deref(),
+                    query.as_deref(),
+                )?;
+                self.run("search", |catalog| commands::cmd_search(catalog, &query))?
+                    .iter()
+                    .find_map(|n| match n {
+                        LibNotification::SearchResultsQueried(content) => {
+                            Some(json::files(content.get_content()))
+                        }
+                        _ => None,
+                    })
+                    .ok_or_else(|| "search returned no results".into())
+            }
+            Command::Set {
+                property,
+                value,
+                ids,
+            } => {
+                let value = property.value(&value)?;
+                self.run("set metadata", |catalog| {
+                    commands::cmd_set_metadata_many(catalog, &ids, property.meta(), &value)
+                })?;
+                Ok(json!({ "updated": ids }))
+            }
+            Command::WriteXmp { ids, pending } => {
+                if pending {
+                    self.run("write pending XMP", |catalog| {
+                        commands::cmd_process_xmp_update_queue(catalog, true)
+                    })?;
+                } else {
+                    for id in &ids {
+                        self.run("write XMP", |catalog| {
+                            commands::cmd_write_metadata(catalog, *id)
+                        })?;
+                    }
+                }
+                Ok(json!({ "written": ids }))
+            }
+            Command::Relink { dir } => Ok(Value::Array(
+                self.run("relink", |catalog| {
+                    commands::cmd_relink_missing_files(catalog, &dir)
+                })?
+                .iter()
+                .filter_map(|n| match n {
+                    LibNotification::FilesRelocated(files) => Some(files),
+                    _ => None,
+                })
+                .flatten()
+                .map(json::file)
+                .collect(),
+            )),
+            Command::Verify => {
+                let missing = self
+                    .run("scan missing files", commands::cmd_scan_missing_files)?
+                    .into_iter()
+                    .filter_map(|n| match n {
+                        LibNotification::MissingFiles(ids) => Some(ids),
+                        _ => None,
+                    })
+                    .flatten()
+                    .collect::<Vec<_>>();
+                let files = if missing.is_empty() {
+                    vec![]
+                } else {
+                    self.files(&missing)?
+                };
+                Ok(json!({ "missing": json::files(&files) }))
+            }
+            Command::ExportKeywords { file } => {
+                self.run("export keywords", |catalog| {
+                    commands::cmd_export_keywords(catalog, &file)
+                })?;
+                Ok(json!({ "file": file.to_string_lossy() }))
+            }
+            Command::Export {
+                dest,
+                ids,
+                format,
+                quality,
+                rt,
+            } => {
+                if !dest.is_dir() {
+                    return Err(format!("Invalid directory {dest:?}"));
+                }
+                let engine = if rt {
+                    RenderEngine::Rt
+                } else {
+                    RenderEngine::Ncr
+                };
+                let files = self.files(&ids)?;
+                let mut exported = vec![];
+                let mut failed = vec![];
+                for id in ids {
+                    let result = files
+                        .iter()
+                        .find(|file| file.id() == id)
+                        .ok_or_else(|| format!("No file {id}"))
+                        .and_then(|file| self.export_file(file, &dest, format, quality, engine));
+                    match result {
+                        Ok(path) => {
+                            exported.push(json!({ "id": id, "path": path.to_string_lossy() }))
+                        }
+                        Err(err) => failed.push(json!({ "id": id, "error": err })),
+                    }
+                }
+                Ok(json!({ "exported": exported, "failed": failed }))
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    npc_fwk::init();
+    let _ = ExempiManager::new(None);
+
+    match Cli::open(&args.catalog).and_then(|cli| cli.execute(args.command)) {
+        Ok(value) => {
+            let output = if args.pretty {
+                serde_json::to_string_pretty(&value)
+            } else {
+                serde_json::to_string(&value)
+            };
+            println!("{}", output.unwrap_or_default());
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{}", json!({ "error": err }));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::CommandFactory;
+    use npc_fwk::PropertyValue;
+
+    use std::path::{Path, PathBuf};
+
+    use npc_engine::catalog::LibFile;
+
+    use super::{Args, ExportFormat, Property, export_path, search_query};
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_search_query() {
+        let keywords = vec!["beach".to_string(), "sunset sky".to_string()];
+        assert_eq!(
+            search_query(Some(">=4"), &keywords, None, Some("picked"), None),
+            Ok(r#"rating:>=4 keyword:"beach" keyword:"sunset sky" flag:picked"#.to_string())
+        );
+        assert_eq!(
+            search_query(None, &[], Some("Red"), None, Some("model:X-T4")),
+            Ok(r#"label:"Red" model:X-T4"#.to_string())
+        );
+        assert!(search_query(Some("great"), &[], None, None, None).is_err());
+        assert!(search_query(None, &[], None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_property_value() {
+        assert!(matches!(
+            Property::Rating.value("3"),
+            Ok(PropertyValue::Int(3))
+        ));
+        assert!(Property::Flag.value("picked").is_err());
+        assert!(matches!(
+            Property::Keywords.value("beach, sunset,"),
+            Ok(PropertyValue::StringArray(keywords)) if keywords == ["beach", "sunset"]
+        ));
+        assert!(matches!(
+            Property::City.value("Montréal"),
+            Ok(PropertyValue::String(city)) if city == "Montréal"
+        ));
+    }
+
+    #[test]
+    fn test_export_path() {
+        let file = LibFile::new(
+            2,
+            1,
+            3,
+            PathBuf::from("/photos/IMG_0001.CR2"),
+            "IMG_0001.CR2",
+        );
+        assert_eq!(
+            export_path(Path::new("/export"), &file, ExportFormat::Jpeg),
+            Ok(PathBuf::from("/export/IMG_0001.jpg"))
+        );
+        assert_eq!(
+            export_path(Path::new("/export"), &file, ExportFormat::Png),
+            Ok(PathBuf::from("/export/IMG_0001.png"))
+        );
+
+        // Files with the same name aren't overwritten.
+        let dest = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dest = dest.path();
+        std::fs::write(dest.join("IMG_0001.jpg"), "jpeg").expect("Couldn't write file");
+        assert_eq!(
+            export_path(dest, &file, ExportFormat::Jpeg),
+            Ok(dest.join("IMG_0001-2.jpg"))
+        );
+        std::fs::write(dest.join("IMG_0001-2.jpg"), "jpeg").expect("Couldn't write file");
+        assert!(export_path(dest, &file, ExportFormat::Jpeg).is_err());
+    }
+}
@@ -25,7 +25,10 @@ use std::sync::Once;
 
 use npc_fwk::gtk4::prelude::*;
 
+use npc_engine::catalog::{DevelopSettings, FileType, LibFile};
+use npc_engine::library::RenderEngine;
 use npc_fwk::dbg_out;
+use npc_fwk::toolkit::ImageBitmap;
 
 pub use render_worker::{RenderImpl, RenderWorker};
 
@@ -41,3 +44,27 @@ fn ncr_init() {
         gegl::init();
     });
 }
+
+/// Whether `file` is rendered as a RAW.
+fn is_raw(file: &LibFile) -> bool {
+    // currently we treat RAW + JPEG as RAW.
+    // TODO: have a way to actually choose the JPEG.
+    let file_type = file.file_type();
+    file_type == FileType::Raw || file_type == FileType::RawJpeg
+}
+
+/// Render `file` at full size, with `engine` and the `develop`
+/// settings. This is synchronous, for the export.
+pub fn render_file(
+    file: &LibFile,
+    engine: RenderEngine,
+    develop: &DevelopSettings,
+) -> Option<ImageBitmap> {
+    ncr_init();
+    let pipeline = pipeline::create(engine)?;
+    pipeline.set_develop_settings(develop);
+    let path = file.path().to_string_lossy();
+    dbg_out!("rendering {path}");
+    pipeline.reload(&path, is_raw(file), file.orientation());
+    pipeline.rendered_image()
+}
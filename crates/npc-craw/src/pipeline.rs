@@ -24,6 +24,7 @@ mod rt;
 
 use npc_fwk::gdk_pixbuf;
 
+use npc_engine::catalog::DevelopSettings;
 use npc_engine::library::RenderEngine;
 use npc_fwk::toolkit::ImageBitmap;
 
@@ -33,6 +34,9 @@ pub(crate) trait Pipeline {
     fn output_height(&self) -> u32;
     fn rendered_image(&self) -> Option<ImageBitmap>;
     fn reload(&self, path: &str, is_raw: bool, orientation: u32);
+    /// Set the develop settings. If an image is loaded they are
+    /// applied without reloading it.
+    fn set_develop_settings(&self, settings: &DevelopSettings);
     /// Set a placeholder to display.
     fn set_placeholder(&self, placeholder: gdk_pixbuf::Pixbuf);
 }
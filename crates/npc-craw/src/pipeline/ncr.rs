@@ -1,7 +1,7 @@
 /*
  * niepce - npc_craw/pipeline/ncr.rs
  *
- * Copyright (C) 2023-2024 Hubert Figuière
+ * Copyright (C) 2023-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -24,7 +24,9 @@ use std::cell::{Cell, RefCell};
 use gegl::Node as GeglNode;
 use npc_fwk::gdk_pixbuf;
 
+use npc_engine::catalog::{CropRect, DevelopSettings};
 use npc_fwk::MimeType;
+use npc_fwk::glib;
 use npc_fwk::toolkit::ImageBitmap;
 use npc_fwk::toolkit::mimetype::{ImgFormat, MType};
 use npc_fwk::{dbg_out, err_out};
@@ -38,6 +40,141 @@ enum ImageStatus {
     NotFound,
 }
 
+/// The nodes of the develop operations, in processing order.
+struct DevelopNodes {
+    /// gegl:exposure for exposure and black point.
+    exposure: GeglNode,
+    /// gegl:color-temperature.
+    temperature: GeglNode,
+    /// gegl:multiply with `tint_colour` as aux.
+    tint: GeglNode,
+    tint_colour: GeglNode,
+    /// gegl:shadows-highlights for recovery and fill light.
+    shadows_highlights: GeglNode,
+    brightness_contrast: GeglNode,
+    /// gegl:saturation for saturation and vibrance.
+    saturation: GeglNode,
+    /// gegl:contrast-curve for the tone curve.
+    curve: GeglNode,
+}
+
+impl DevelopNodes {
+    fn new(graph: &GeglNode) -> Option<DevelopNodes> {
+        let nodes = DevelopNodes {
+            exposure: graph.new_child(Some("gegl:exposure"), &[])?,
+            temperature: graph.new_child(Some("gegl:color-temperature"), &[])?,
+            tint: graph.new_child(Some("gegl:multiply"), &[])?,
+            tint_colour: graph.new_child(Some("gegl:color"), &[])?,
+            shadows_highlights: graph.new_child(Some("gegl:shadows-highlights"), &[])?,
+            brightness_contrast: graph.new_child(Some("gegl:brightness-contrast"), &[])?,
+            saturation: graph.new_child(Some("gegl:saturation"), &[])?,
+            curve: graph.new_child(Some("gegl:contrast-curve"), &[])?,
+        };
+        nodes.tint_colour.connect_to("output", &nodes.tint, "aux");
+        Some(nodes)
+    }
+
+    /// Link the nodes after `input`.
+    fn link(&self, input: &GeglNode) {
+        input.link_many(&[
+            &self.exposure,
+            &self.temperature,
+            &self.tint,
+            &self.shadows_highlights,
+            &self.brightness_contrast,
+            &self.saturation,
+            &self.curve,
+        ]);
+    }
+
+    /// The last node.
+    fn output(&self) -> &GeglNode {
+        &self.curve
+    }
+
+    /// Update the nodes whose values changed from `old` to `new`. If
+    /// `old` is `None` all the nodes are set.
+    fn update(&self, old: Option<&DevelopSettings>, new: &DevelopSettings) {
+        let changed = |f: fn(&DevelopSettings) -> f64| old.is_none_or(|old| f(old) != f(new));
+
+        if changed(|s| s.exposure) || changed(|s| s.blacks) {
+            set_op_property(&self.exposure, "exposure", new.exposure.into());
+            // gegl black-level is fraction of the range.
+            set_op_property(&self.exposure, "black-level", (new.blacks / 1000.0).into());
+        }
+        if changed(|s| s.temperature) {
+            // The original temperature is the one the image is shot in.
+            set_op_property(
+                &self.temperature,
+                "original-temperature",
+                new.temperature.into(),
+            );
+            set_op_property(&self.temperature, "intended-temperature", 6500_f64.into());
+        }
+        if changed(|s| s.tint) {
+            // Magenta is less green.
+            let green = 2_f64.powf(-new.tint / 100.0);
+            let colour = gegl::Color::new(&format!("rgb(1.0, {green}, 1.0)"));
+            set_op_property(&self.tint_colour, "value", colour.into());
+        }
+        if changed(|s| s.recovery) || changed(|s| s.fill_light) {
+            set_op_property(&self.shadows_highlights, "shadows", new.fill_light.into());
+            set_op_property(
+                &self.shadows_highlights,
+                "highlights",
+                (-new.recovery).into(),
+            );
+        }
+        if changed(|s| s.brightness) || changed(|s| s.contrast) {
+            set_op_property(
+                &self.brightness_contrast,
+                "brightness",
+                (new.brightness / 200.0).into(),
+            );
+            set_op_property(
+                &self.brightness_contrast,
+                "contrast",
+                (1.0 + new.contrast / 100.0).into(),
+            );
+        }
+        if changed(|s| s.saturation) || changed(|s| s.vibrance) {
+            // There is no vibrance operation: it is a gentler saturation.
+            let scale = (1.0 + new.saturation / 100.0 + new.vibrance / 200.0).max(0.0);
+            set_op_property(&self.saturation, "scale", scale.into());
+        }
+        if old.is_none_or(|old| old.tone_curve != new.tone_curve) {
+            let curve = gegl::Curve::new(0.0, 1.0);
+            if new.tone_curve.is_empty() {
+                curve.add_point(0.0, 0.0);
+                curve.add_point(1.0, 1.0);
+            } else {
+                for (x, y) in &new.tone_curve {
+                    curve.add_point(*x, *y);
+                }
+            }
+            set_op_property(&self.curve, "curve", curve.into());
+        }
+    }
+}
+
+/// Update `crop_n` for `crop` of the output of `rotate_n`.
+fn update_crop(crop_n: &GeglNode, rotate_n: &GeglNode, crop: Option<CropRect>) {
+    let bbox = rotate_n.bounding_box();
+    let (x, y, w, h) = crop
+        .and_then(|crop| crop.clamped())
+        .map(|crop| crop.to_pixels(bbox.width() as u32, bbox.height() as u32))
+        .unwrap_or((0, 0, bbox.width(), bbox.height()));
+    set_op_property(crop_n, "x", ((bbox.x() + x) as f64).into());
+    set_op_property(crop_n, "y", ((bbox.y() + y) as f64).into());
+    set_op_property(crop_n, "width", (w as f64).into());
+    set_op_property(crop_n, "height", (h as f64).into());
+}
+
+/// Set the operation property `name` of `node`.
+fn set_op_property(node: &GeglNode, name: &str, value: glib::Value) {
+    node.set_property(name, &value);
+}
+
 struct PipelineState {
     width: u32,
     height: u32,
@@ -45,8 +182,11 @@ struct PipelineState {
     vertical: bool,
     flip: bool,
     tilt: f64,
+    develop: DevelopSettings,
     graph: Option<GeglNode>,
+    develop_n: Option<DevelopNodes>,
     rotate_n: Option<GeglNode>,
+    crop_n: Option<GeglNode>,
     scale: Option<GeglNode>,
 
     pixbuf_cache: Option<gdk_pixbuf::Pixbuf>,
@@ -61,8 +201,11 @@ impl Default for PipelineState {
             vertical: false,
             flip: false,
             tilt: 0.0,
+            develop: DevelopSettings::default(),
             graph: None,
+            develop_n: None,
             rotate_n: None,
+            crop_n: None,
             scale: None,
             pixbuf_cache: None,
         }
@@ -154,6 +297,16 @@ impl NcrPipeline {
         }
     }
 
+    /// Create the develop nodes, set to the current develop settings.
+    fn develop_nodes(&self) -> Option<DevelopNodes> {
+        let state = self.state.borrow();
+        let nodes = state.graph.as_ref().and_then(DevelopNodes::new);
+        if nodes.is_none() {
+            err_out!("Failed to create the develop nodes");
+        }
+        nodes.inspect(|nodes| nodes.update(None, &state.develop))
+    }
+
     fn load_dcraw(&self, p: &str) -> Option<GeglNode> {
         self.state
             .borrow()
@@ -202,14 +355,38 @@ impl NcrPipeline {
         }
         let node = node.unwrap();
 
+        let develop_n = self.develop_nodes();
         let rotate_n = self.rotate_node(orientation);
+        let crop_n = self
+            .state
+            .borrow()
+            .graph
+            .as_ref()
+            .and_then(|graph| graph.new_child(Some("gegl:crop"), &[]));
         let scale = self.scale_node();
 
-        node.link_many(&[rotate_n.as_ref().unwrap(), scale.as_ref().unwrap()]);
+        let output = if let Some(ref develop_n) = develop_n {
+            develop_n.link(&node);
+            develop_n.output()
+        } else {
+            &node
+        };
+        output.link_many(&[
+            rotate_n.as_ref().unwrap(),
+            crop_n.as_ref().unwrap(),
+            scale.as_ref().unwrap(),
+        ]);
+        update_crop(
+            crop_n.as_ref().unwrap(),
+            rotate_n.as_ref().unwrap(),
+            self.state.borrow().develop.crop,
+        );
 
         {
             let mut state = self.state.borrow_mut();
+            state.develop_n = develop_n;
             state.rotate_n = rotate_n;
+            state.crop_n = crop_n;
             state.scale = scale;
         }
 
@@ -338,6 +515,29 @@ impl super::Pipeline for NcrPipeline {
         self.reload_node(load_file, orientation);
     }
 
+    fn set_develop_settings(&self, settings: &DevelopSettings) {
+        let mut state = self.state.borrow_mut();
+        // Only update what changed, GEGL will only reprocess from there.
+        if let Some(ref develop_n) = state.develop_n {
+            develop_n.update(Some(&state.develop), settings);
+        }
+        let tilt_changed = state.tilt != settings.tilt;
+        if tilt_changed {
+            state.tilt = settings.tilt;
+            if let Some(ref rotate_n) = state.rotate_n {
+                let rotate = state.orientation as f64 + state.tilt;
+                set_op_property(rotate_n, "degrees", rotate.into());
+            }
+        }
+        // The crop is relative to the rotated image.
+        if tilt_changed || state.develop.crop != settings.crop {
+            if let (Some(crop_n), Some(rotate_n)) = (&state.crop_n, &state.rotate_n) {
+                update_crop(crop_n, rotate_n, settings.crop);
+            }
+        }
+        state.develop = settings.clone();
+    }
+
     fn set_placeholder(&self, placeholder: gdk_pixbuf::Pixbuf) {
         self.reload_pixbuf(placeholder);
     }
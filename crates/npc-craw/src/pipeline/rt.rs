@@ -19,31 +19,171 @@
 
 /*! RawTherapee engine pipeline */
 
+use std::cell::RefCell;
+use std::path::PathBuf;
+
 use npc_fwk::gdk_pixbuf;
 
+use npc_engine::catalog::DevelopSettings;
 use npc_fwk::toolkit::ImageBitmap;
 use npc_fwk::{dbg_out, err_out, on_err_out};
+use rtengine::{ColourParams, CropParams, HighlightRecoveryParams, ProcParams, WhiteBalanceParams};
 
-pub(crate) struct RtPipeline(rtengine::RtEngine);
+pub(crate) struct RtPipeline {
+    engine: rtengine::RtEngine,
+    develop: RefCell<DevelopSettings>,
+    /// The path of the loaded image.
+    path: RefCell<Option<String>>,
+}
 
 impl RtPipeline {
     pub(crate) fn new() -> Self {
-        Self(rtengine::RtEngine::new())
+        Self {
+            engine: rtengine::RtEngine::new(),
+            develop: RefCell::default(),
+            path: RefCell::default(),
+        }
+    }
+
+    /// Set the params from the develop settings.
+    fn update_params(&self) {
+        let params = self.path.borrow().as_ref().and_then(|path| {
+            self.base_params(path).map(|mut params| {
+                apply_develop_settings(
+                    &mut params,
+                    &self.develop.borrow(),
+                    self.engine.image_size(),
+                );
+                params
+            })
+        });
+        self.engine.set_params(params);
+    }
+
+    /// The base params for `path`: the RawTherapee sidecar if there is
+    /// one, the default otherwise.
+    fn base_params(&self, path: &str) -> Option<ProcParams> {
+        let mut sidecar = PathBuf::from(path).into_os_string();
+        sidecar.push(".pp3");
+        if std::path::Path::new(&sidecar).exists() {
+            dbg_out!("Rt: loading sidecar {sidecar:?}");
+            match ProcParams::load(&sidecar) {
+                Ok(params) => return Some(params),
+                Err(err) => err_out!("Rt: failed to load {sidecar:?}: {err}"),
+            }
+        }
+        self.engine
+            .default_params()
+            .map_err(|err| {
+                err_out!("Rt: no default params {err}");
+                err
+            })
+            .ok()
+    }
+}
+
+/// Apply the develop `settings` to the RT `params`. Only the values
+/// changed from the default are applied, to not override a sidecar.
+/// `size` is the size of the image, for the crop.
+fn apply_develop_settings(
+    params: &mut ProcParams,
+    settings: &DevelopSettings,
+    size: Option<(u32, u32)>,
+) {
+    let default = DevelopSettings::default();
+
+    if let Some((width, height)) = size {
+        if let Some(crop) = settings.crop.and_then(|crop| crop.clamped()) {
+            let (x, y, w, h) = crop.to_pixels(width, height);
+            params.set_crop(&CropParams {
+                enabled: true,
+                x,
+                y,
+                w,
+                h,
+                ..params.crop()
+            });
+        }
+    }
+
+    if settings.tilt != default.tilt {
+        params.set_rotation(settings.tilt);
+    }
+
+    if settings.temperature != default.temperature || settings.tint != default.tint {
+        // RT green is a multiplier, 1.0 being neutral. Magenta is less green.
+        params.set_white_balance(&WhiteBalanceParams {
+            enabled: true,
+            method: "Custom".to_string(),
+            temperature: settings.temperature as i32,
+            green: 2_f64.powf(-settings.tint / 100.0),
+            ..params.white_balance()
+        });
+    }
+
+    let mut exposure = params.exposure();
+    if settings.exposure != default.exposure {
+        exposure.compensation = settings.exposure;
+    }
+    if settings.recovery != default.recovery {
+        // RT highlight compression goes to 500.
+        exposure.highlight_compression = (settings.recovery * 5.0) as i32;
+        params.set_highlight_recovery(&HighlightRecoveryParams {
+            enabled: true,
+            method: "Blend".to_string(),
+        });
+    }
+    if settings.fill_light != default.fill_light {
+        // No direct equivalent. Shadow compression is the closest.
+        exposure.shadow_compression = settings.fill_light as i32;
+    }
+    if settings.blacks != default.blacks {
+        exposure.black = (settings.blacks * 20.0) as i32;
+    }
+    if settings.brightness != default.brightness {
+        exposure.brightness = settings.brightness as i32;
+    }
+    if settings.contrast != default.contrast {
+        exposure.contrast = settings.contrast as i32;
+    }
+    if settings.saturation != default.saturation {
+        exposure.saturation = settings.saturation as i32;
+    }
+    params.set_exposure(&exposure);
+
+    if !settings.tone_curve.is_empty() {
+        // RT curve: the type (1 is spline) followed by the points.
+        let mut curve = vec![1.0];
+        for (x, y) in &settings.tone_curve {
+            curve.push(*x);
+            curve.push(*y);
+        }
+        params.set_tone_curve(&curve);
+    }
+
+    if settings.vibrance != default.vibrance {
+        params.set_colour(&ColourParams {
+            enabled: true,
+            pastels: settings.vibrance as i32,
+            saturated: settings.vibrance as i32,
+            linked: true,
+            ..params.colour()
+        });
     }
 }
 
 impl super::Pipeline for RtPipeline {
     fn output_width(&self) -> u32 {
-        self.0.width() as u32
+        self.engine.width() as u32
     }
 
     fn output_height(&self) -> u32 {
-        self.0.height() as u32
+        self.engine.height() as u32
     }
 
     fn rendered_image(&self) -> Option<ImageBitmap> {
         dbg_out!("Rt: rendering");
-        self.0
+        self.engine
             .process()
             .map_err(|err| {
                 err_out!("Rt processing error {err}");
@@ -54,7 +194,15 @@ impl super::Pipeline for RtPipeline {
 
     // Rt doesn't care about orientation.
     fn reload(&self, path: &str, is_raw: bool, _: u32) {
-        on_err_out!(self.0.set_file(path, is_raw));
+        on_err_out!(self.engine.set_file(path, is_raw));
+        self.path.replace(Some(path.to_string()));
+        self.update_params();
+    }
+
+    // RT always processes from the initial image.
+    fn set_develop_settings(&self, settings: &DevelopSettings) {
+        self.develop.replace(settings.clone());
+        self.update_params();
     }
 
     fn set_placeholder(&self, _placeholder: gdk_pixbuf::Pixbuf) {}
@@ -48,14 +48,9 @@ impl RenderImpl {
 
     fn reload(&self, pipeline: &dyn Pipeline) {
         if let Some(file) = self.imagefile.borrow().as_ref() {
-            // currently we treat RAW + JPEG as RAW.
-            // TODO: have a way to actually choose the JPEG.
-            let file_type = file.file_type();
-            let is_raw =
-                (file_type == catalog::FileType::Raw) || (file_type == catalog::FileType::RawJpeg);
             let path = file.path().to_string_lossy();
             dbg_out!("pipeline reload for {path}");
-            pipeline.reload(&path, is_raw, file.orientation());
+            pipeline.reload(&path, crate::is_raw(file), file.orientation());
         } else if let Ok(p) = gdk_pixbuf::Pixbuf::from_resource(
             "/net/figuiere/Niepce/pixmaps/niepce-image-generic.png",
         ) {
@@ -87,6 +82,7 @@ impl WorkerImpl for RenderImpl {
                     dbg_out!("Same image file, doing nothing");
                 } else {
                     self.imagefile.replace(file.as_deref().cloned());
+                    state.loaded = false;
                 }
             }
             Reload(params) => {
@@ -99,10 +95,18 @@ impl WorkerImpl for RenderImpl {
                         dbg_out!("creating pipeline, engine is {:?}", params.engine());
                         crate::pipeline::create(params.engine())
                     });
+                    state.loaded = false;
                 }
                 state.params = params;
                 if let Some(ref pipeline) = state.pipeline {
-                    self.reload(pipeline.deref());
+                    if let Some(ref params) = state.params {
+                        pipeline.set_develop_settings(params.develop_settings());
+                    }
+                    // If the image is loaded, the develop settings are enough.
+                    if !state.loaded {
+                        self.reload(pipeline.deref());
+                        state.loaded = true;
+                    }
                 }
             }
             GetBitmap(callback) => {
@@ -120,4 +124,6 @@ impl WorkerImpl for RenderImpl {
 pub struct RendererState {
     pipeline: Option<Box<dyn Pipeline>>,
     params: Option<RenderParams>,
+    /// The image is loaded in the pipeline.
+    loaded: bool,
 }
@@ -21,8 +21,8 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
-use npc_engine::catalog::CatalogDb;
-use npc_engine::importer::{DatePathFormat, Importer};
+use npc_engine::catalog::{CatalogDb, StackingOptions};
+use npc_engine::importer::{DatePathFormat, ImportSelection, Importer, RenameTemplate};
 use npc_engine::library::commands::cmd_import_files;
 use npc_engine::library::notification::LibNotification;
 use npc_fwk::ExempiManager;
@@ -50,6 +50,11 @@ struct Args {
     #[arg(short, long)]
     recursive: bool,
 
+    /// (Optional) Template to rename the files, like
+    /// `{date:%Y%m%d}-{camera}-{seq:4}-{orig}`.
+    #[arg(long)]
+    rename: Option<RenameTemplate>,
+
     /// Dry run.
     #[arg(short = 'n', long)]
     dry_run: bool,
@@ -91,7 +96,14 @@ fn main() {
 
         catalog
     });
-    let imports = Importer::get_imports(&source, &dest, format, args.recursive);
+    let imports = Importer::get_imports(
+        &source,
+        &dest,
+        format,
+        args.recursive,
+        args.rename.as_ref(),
+        &ImportSelection::default(),
+    );
     let only_dest_dirs = dates.map(|dates| dates.iter().map(|d| dest.join(d)).collect::<Vec<_>>());
     let mut size_to_copy = 0;
     for import in &imports {
@@ -130,7 +142,14 @@ fn main() {
     if !dry_run {
         let imports: Vec<PathBuf> = imports.into_iter().map(|elem| elem.1).collect();
         if let Some(catalog) = &catalog {
-            cmd_import_files(catalog, &dest, &imports);
+            cmd_import_files(
+                catalog,
+                &dest,
+                &imports,
+                &StackingOptions::default(),
+                None,
+                None,
+            );
         }
     }
 }
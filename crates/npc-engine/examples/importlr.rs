@@ -109,6 +109,9 @@ fn main() {
     }
 
     let before = std::time::Instant::now();
-    importer.import_library(&library).expect("Import Library");
+    let report = importer.import_library(&library).expect("Import Library");
     println!("Elapsed time: {:.2?}", before.elapsed());
+    for (item, reason) in report.not_converted() {
+        println!("Not converted: {item}: {reason}");
+    }
 }
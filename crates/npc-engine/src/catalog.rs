@@ -19,14 +19,22 @@
 
 pub mod album;
 pub(crate) mod db;
+pub mod develop;
 pub mod filebundle;
+pub mod fingerprint;
 pub mod fsfile;
 pub mod keyword;
 pub mod label;
 pub mod libfile;
 pub mod libfolder;
 pub mod libmetadata;
+pub mod metadata_preset;
+pub mod project;
 pub mod props;
+pub mod search;
+pub mod smart_collection;
+pub mod stack;
+pub mod watched_folder;
 
 pub type LibraryId = i64;
 
@@ -42,13 +50,20 @@ pub use album::Album;
 #[cfg(test)]
 pub(crate) use db::test as db_test;
 pub use db::{CatalogDb, Error as LibError, Result as LibResult};
+pub use develop::{CropRect, DevelopSettings};
 pub use keyword::Keyword;
 pub use label::Label;
 pub use libfile::{FileType, LibFile};
 pub use libfolder::LibFolder;
 pub use libmetadata::LibMetadata;
+pub use metadata_preset::MetadataPreset;
+pub use project::{Project, ProjectItem, ProjectState};
 pub use props::NiepceProperties;
 pub use props::NiepcePropertyIdx;
+pub use search::SearchQuery;
+pub use smart_collection::SmartCollection;
+pub use stack::StackingOptions;
+pub use watched_folder::WatchedFolder;
 
 pub trait FromDb: Sized {
     /// return the columns for reading from the DB.
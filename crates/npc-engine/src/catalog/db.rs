@@ -25,6 +25,8 @@ mod schema_test;
 mod sql;
 mod upgrade;
 
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::Write;
@@ -40,22 +42,46 @@ use super::{FromDb, LibraryId};
 use crate::NiepcePropertyBag;
 use crate::catalog::NiepcePropertyIdx as Npi;
 use crate::catalog::album::Album;
+use crate::catalog::develop::DevelopSettings;
 use crate::catalog::filebundle::{FileBundle, Sidecar};
-use crate::catalog::keyword::Keyword;
+use crate::catalog::fingerprint;
+use crate::catalog::fsfile::FsFile;
+use crate::catalog::keyword::{Keyword, KeywordNode};
 use crate::catalog::label::Label;
 use crate::catalog::libfile;
 use crate::catalog::libfile::LibFile;
 use crate::catalog::libfolder;
 use crate::catalog::libfolder::LibFolder;
 use crate::catalog::libmetadata::LibMetadata;
+use crate::catalog::metadata_preset::MetadataPreset;
+use crate::catalog::project::{Project, ProjectItem, ProjectState};
 use crate::catalog::props::NiepceProperties as Np;
+use crate::catalog::search::SearchQuery;
+use crate::catalog::smart_collection::{SmartCollection, SmartRule};
+use crate::catalog::stack::StackCandidate;
+use crate::catalog::watched_folder::WatchedFolder;
+use crate::library::journal::{JournalEntry, JournalOp};
 use crate::library::notification::{FolderReparent, LibNotification};
 use npc_fwk::PropertyValue;
 use npc_fwk::base::RgbColour;
 use npc_fwk::toolkit;
+use npc_fwk::utils::exempi::{KEYWORD_PATH_SEPARATOR, NS_DC, NS_PHOTOSHOP};
 use npc_fwk::{dbg_assert, dbg_out, err_out, on_err_out};
 
-const DB_SCHEMA_VERSION: i32 = 13;
+const DB_SCHEMA_VERSION: i32 = 26;
+
+/// Match the `path` column with `?1` or the paths under it.
+/// `LIKE` would treat `_` and `%` as wildcards.
+const PATH_UNDER_WHERE: &str = "(path = ?1 OR substr(path, 1, length(?1) + 1) = ?1 || '/')";
+
+/// Select the full text search index columns from `files`.
+/// `?1` is the Photoshop namespace, `?2` the Dublin Core namespace.
+const SEARCH_INDEX_SELECT: &str = "SELECT files.id, files.name, \
+     xmp_property(files.xmp, ?1, 'Headline'), \
+     xmp_property(files.xmp, ?2, 'description[1]'), \
+     (SELECT group_concat(keywords.keyword, ' ') FROM keywords, keywording \
+     WHERE keywording.file_id = files.id AND keywords.id = keywording.keyword_id) \
+     FROM files";
 
 /// Error from the library database
 #[derive(Error, Debug, PartialEq)]
@@ -84,6 +110,9 @@ pub enum Error {
     /// SQL Error
     #[error("rusqlite error: {0}")]
     SqlError(#[from] rusqlite::Error),
+    /// The directory of a folder isn't on the storage.
+    #[error("Folder missing on storage")]
+    MissingFolder,
     /// Strip Prefix
     #[error("Strip prefix error: {0}")]
     StripPrefix(#[from] std::path::StripPrefixError),
@@ -122,6 +151,10 @@ pub struct CatalogDb {
     inited: bool,
     /// Sender for notifications.
     sender: npc_fwk::toolkit::Sender<LibNotification>,
+    /// True if the journal recording is suspended, like while undoing.
+    journal_suspended: Cell<bool>,
+    /// The notifications held until the transaction is committed.
+    deferred_notifications: RefCell<Option<Vec<LibNotification>>>,
 }
 
 impl CatalogDb {
@@ -134,6 +167,8 @@ impl CatalogDb {
             dbfile: None,
             inited: false,
             sender,
+            journal_suspended: Cell::new(false),
+            deferred_notifications: RefCell::default(),
         };
 
         if let Ok(conn) = rusqlite::Connection::open_in_memory() {
@@ -151,6 +186,8 @@ impl CatalogDb {
             dbfile: Some(filename.to_path_buf()),
             inited: false,
             sender,
+            journal_suspended: Cell::new(false),
+            deferred_notifications: RefCell::default(),
         };
 
         match rusqlite::Connection::open(filename) {
@@ -189,6 +226,32 @@ impl CatalogDb {
                 err_out!("failed to create scalar function.");
                 return Err(Error::SqlError(err));
             }
+            // xmp_property(xmp, ns, property) return the value of the
+            // XMP property from the packet, or NULL.
+            if let Err(err) = conn.create_scalar_function(
+                "xmp_property",
+                3,
+                FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+                |ctx| {
+                    let xmp = ctx.get::<Option<String>>(0)?;
+                    let ns = ctx.get::<String>(1)?;
+                    let property = ctx.get::<String>(2)?;
+                    Ok(xmp.and_then(|xmp| {
+                        let mut meta = npc_fwk::XmpMeta::new();
+                        if !meta.unserialize(&xmp) {
+                            return None;
+                        }
+                        let mut flags = exempi2::PropFlags::default();
+                        meta.xmp
+                            .get_property(&ns, &property, &mut flags)
+                            .ok()
+                            .map(|value| String::from(&value))
+                    }))
+                },
+            ) {
+                err_out!("failed to create scalar function.");
+                return Err(Error::SqlError(err));
+            }
         } else {
             return Err(Error::NoSqlDb);
         }
@@ -393,12 +456,16 @@ impl CatalogDb {
                 [],
             )
             .unwrap();
+            // pre_file_delete_trigger changed in version 19.
             conn.execute_batch(
                 "BEGIN; \
                  CREATE TRIGGER pre_file_delete_trigger BEFORE DELETE ON files \
                  BEGIN \
-                 DELETE FROM fsfiles WHERE id = old.main_file \
-                 OR id = old.xmp_file OR id = old.jpeg_file; \
+                 DELETE FROM fsfiles WHERE (id = old.main_file \
+                 OR id = old.xmp_file OR id = old.jpeg_file) \
+                 AND NOT EXISTS (SELECT 1 FROM files WHERE files.id != old.id \
+                 AND (files.main_file = fsfiles.id OR files.xmp_file = fsfiles.id \
+                 OR files.jpeg_file = fsfiles.id)); \
                  END; \
                  CREATE TRIGGER file_delete_trigger AFTER DELETE ON files \
                  BEGIN \
@@ -434,6 +501,142 @@ impl CatalogDb {
             .unwrap();
             conn.execute("CREATE TABLE xmp_update_queue (id INTEGER UNIQUE)", [])
                 .unwrap();
+            // version 14
+            conn.execute(
+                "CREATE TABLE smart_collections (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 name TEXT, rules TEXT, match_all INTEGER DEFAULT 1)",
+                [],
+            )
+            .unwrap();
+            // version 15
+            conn.execute_batch(
+                "CREATE VIRTUAL TABLE files_fts USING fts5(name, headline, description, keywords); \
+                 CREATE TRIGGER file_delete_fts_trigger AFTER DELETE ON files \
+                 BEGIN \
+                 DELETE FROM files_fts WHERE rowid = old.id; \
+                 END;",
+            )
+            .unwrap();
+            // version 16
+            conn.execute_batch(
+                "CREATE TABLE projects (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 name TEXT, creation_date INTEGER); \
+                 CREATE TABLE project_files (project_id INTEGER, file_id INTEGER, \
+                 state INTEGER DEFAULT 0, UNIQUE(project_id, file_id)); \
+                 CREATE TABLE project_items (project_id INTEGER, item_type INTEGER, \
+                 item_id INTEGER, UNIQUE(project_id, item_type, item_id)); \
+                 CREATE TRIGGER project_delete_trigger AFTER DELETE ON projects \
+                 BEGIN \
+                 DELETE FROM project_files WHERE project_id = old.id; \
+                 DELETE FROM project_items WHERE project_id = old.id; \
+                 END; \
+                 CREATE TRIGGER project_item_delete_trigger AFTER DELETE ON project_items \
+                 WHEN old.item_type = 3 \
+                 BEGIN \
+                 DELETE FROM fsfiles WHERE id = old.item_id; \
+                 END; \
+                 CREATE TRIGGER file_delete_project_trigger AFTER DELETE ON files \
+                 BEGIN \
+                 DELETE FROM project_files WHERE file_id = old.id; \
+                 END; \
+                 CREATE TRIGGER album_delete_project_trigger AFTER DELETE ON albums \
+                 BEGIN \
+                 DELETE FROM project_items WHERE item_type = 1 AND item_id = old.id; \
+                 END; \
+                 CREATE TRIGGER folder_delete_project_trigger AFTER DELETE ON folders \
+                 BEGIN \
+                 DELETE FROM project_items WHERE item_type = 2 AND item_id = old.id; \
+                 END;",
+            )
+            .unwrap();
+            // version 17
+            conn.execute(
+                "CREATE TABLE journal (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 date INTEGER, label TEXT, forward TEXT, inverse TEXT, \
+                 undone INTEGER DEFAULT 0)",
+                [],
+            )
+            .unwrap();
+            // version 18
+            conn.execute_batch(
+                "ALTER TABLE keywords ADD COLUMN export INTEGER DEFAULT 1; \
+                 CREATE TABLE keyword_synonyms (keyword_id INTEGER, synonym TEXT, \
+                 UNIQUE(keyword_id, synonym)); \
+                 CREATE TRIGGER keyword_delete_synonym_trigger AFTER DELETE ON keywords \
+                 BEGIN \
+                 DELETE FROM keyword_synonyms WHERE keyword_id = old.id; \
+                 END;",
+            )
+            .unwrap();
+            // version 19
+            conn.execute_batch(
+                "ALTER TABLE files ADD COLUMN master_id INTEGER DEFAULT 0; \
+                 CREATE TRIGGER file_delete_master_trigger AFTER DELETE ON files \
+                 WHEN old.master_id = 0 \
+                 BEGIN \
+                 UPDATE files SET master_id = \
+                 (SELECT MIN(id) FROM files WHERE master_id = old.id) \
+                 WHERE master_id = old.id; \
+                 UPDATE files SET master_id = 0 WHERE id = master_id; \
+                 END;",
+            )
+            .unwrap();
+            // version 20
+            conn.execute_batch(
+                "CREATE TABLE stacks (id INTEGER PRIMARY KEY AUTOINCREMENT, top_id INTEGER); \
+                 ALTER TABLE files ADD COLUMN stack_id INTEGER DEFAULT 0; \
+                 ALTER TABLE files ADD COLUMN stack_position INTEGER DEFAULT 0; \
+                 CREATE INDEX files_stack_index ON files(stack_id); \
+                 CREATE TRIGGER stack_delete_trigger AFTER DELETE ON stacks \
+                 BEGIN \
+                 UPDATE files SET stack_id = 0, stack_position = 0 WHERE stack_id = old.id; \
+                 END; \
+                 CREATE TRIGGER file_delete_stack_trigger AFTER DELETE ON files \
+                 WHEN old.stack_id > 0 \
+                 BEGIN \
+                 UPDATE stacks SET top_id = \
+                 (SELECT id FROM files WHERE stack_id = old.stack_id \
+                 ORDER BY stack_position LIMIT 1) \
+                 WHERE id = old.stack_id AND top_id = old.id; \
+                 DELETE FROM stacks WHERE id = old.stack_id \
+                 AND (SELECT COUNT(*) FROM files WHERE stack_id = old.stack_id) < 2; \
+                 END;",
+            )
+            .unwrap();
+            // version 21
+            conn.execute("ALTER TABLE fsfiles ADD COLUMN size INTEGER", [])
+                .unwrap();
+            // version 22
+            conn.execute(
+                "CREATE TABLE metadata_presets (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 name TEXT NOT NULL UNIQUE, properties TEXT)",
+                [],
+            )
+            .unwrap();
+            // version 23
+            conn.execute("ALTER TABLE fsfiles ADD COLUMN fingerprint TEXT", [])
+                .unwrap();
+            conn.execute(
+                "CREATE INDEX fsfiles_fingerprint_index ON fsfiles(fingerprint)",
+                [],
+            )
+            .unwrap();
+            // version 24
+            conn.execute(
+                "CREATE TABLE watched_folders (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+                 path TEXT NOT NULL UNIQUE, dest TEXT, preset_id INTEGER DEFAULT 0)",
+                [],
+            )
+            .unwrap();
+            conn.execute(
+                "CREATE TRIGGER metadata_preset_delete_watched_trigger \
+                 AFTER DELETE ON metadata_presets \
+                 BEGIN \
+                 UPDATE watched_folders SET preset_id = 0 WHERE preset_id = old.id; \
+                 END",
+                [],
+            )
+            .unwrap();
             conn.execute(
                 "CREATE TRIGGER file_update_trigger UPDATE ON files \
                  BEGIN \
@@ -451,6 +654,16 @@ impl CatalogDb {
                 [],
             )
             .unwrap();
+            // version 25
+            conn.execute_batch(
+                "CREATE TABLE develop_settings (file_id INTEGER PRIMARY KEY, \
+                 version INTEGER, settings TEXT); \
+                 CREATE TRIGGER file_delete_develop_trigger AFTER DELETE ON files \
+                 BEGIN \
+                 DELETE FROM develop_settings WHERE file_id = old.id; \
+                 END;",
+            )
+            .unwrap();
 
             if self.notify(LibNotification::LibCreated).is_err() {
                 err_out!("Error sending LibCreated notification");
@@ -481,9 +694,36 @@ impl CatalogDb {
         &self,
         notif: LibNotification,
     ) -> std::result::Result<(), async_channel::SendError<LibNotification>> {
+        if let Some(deferred) = self.deferred_notifications.borrow_mut().as_mut() {
+            deferred.push(notif);
+            return Ok(());
+        }
         toolkit::thread_context().block_on(self.sender.send(notif))
     }
 
+    /// Run `f` holding the notifications. They are sent if `f`
+    /// succeeds, and dropped if it fails, like when the transaction is
+    /// rolled back.
+    pub(crate) fn with_deferred_notifications<T, F: FnOnce() -> Result<T>>(
+        &self,
+        f: F,
+    ) -> Result<T> {
+        let outer = self.deferred_notifications.replace(Some(vec![]));
+        let result = f();
+        let deferred = self
+            .deferred_notifications
+            .replace(outer)
+            .unwrap_or_default();
+        if result.is_ok() {
+            for notif in deferred {
+                if let Err(err) = self.notify(notif) {
+                    err_out!("Failed to send deferred notification: {err:?}");
+                }
+            }
+        }
+        result
+    }
+
     /// Set preference value
     ///
     /// It is a programing error to have an empty `key`.
@@ -601,6 +841,15 @@ impl CatalogDb {
     }
 
     fn get_content(&self, id: LibraryId, sql_where: &str) -> Result<Vec<LibFile>> {
+        self.query_content(sql_where, params![id])
+    }
+
+    /// Query the files matching `sql_where` with `params`.
+    fn query_content<P: rusqlite::Params>(
+        &self,
+        sql_where: &str,
+        params: P,
+    ) -> Result<Vec<LibFile>> {
         if let Some(ref conn) = self.dbconn {
             let sql = format!(
                 "SELECT {} FROM {} \
@@ -611,7 +860,7 @@ impl CatalogDb {
                 sql_where
             );
             let mut stmt = conn.prepare(&sql)?;
-            let mut rows = stmt.query(params![id])?;
+            let mut rows = stmt.query(params)?;
             let mut files: Vec<LibFile> = vec![];
             while let Ok(Some(row)) = rows.next() {
                 files.push(LibFile::read_from(row)?);
@@ -694,6 +943,22 @@ impl CatalogDb {
         Err(Error::NoSqlDb)
     }
 
+    /// Get the folder `id`.
+    pub(crate) fn get_folder_by_id(&self, id: LibraryId) -> Result<LibFolder> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE id=?1",
+            LibFolder::read_db_columns(),
+            LibFolder::read_db_tables()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(LibFolder::read_from(row)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
     /// Reparent a `folder` to the `new_parent`.
     fn reparent_folder(&self, id: LibraryId, new_parent: LibraryId) -> Result<()> {
         let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
@@ -902,10 +1167,25 @@ impl CatalogDb {
 
     /// Add an album to the library
     pub(crate) fn add_album(&self, name: &str, parent: LibraryId) -> Result<Album> {
+        self.insert_album(None, name, parent)
+    }
+
+    /// Add an album to the library with a specific `id`. This is to
+    /// restore a deleted album.
+    pub(crate) fn add_album_with_id(
+        &self,
+        id: LibraryId,
+        name: &str,
+        parent: LibraryId,
+    ) -> Result<Album> {
+        self.insert_album(Some(id), name, parent)
+    }
+
+    fn insert_album(&self, id: Option<LibraryId>, name: &str, parent: LibraryId) -> Result<Album> {
         if let Some(ref conn) = self.dbconn {
             let c = conn.execute(
-                "INSERT INTO albums (name,parent_id) VALUES(?1, ?2)",
-                params![name, parent],
+                "INSERT INTO albums (id,name,parent_id) VALUES(?1, ?2, ?3)",
+                params![id, name, parent],
             )?;
             if c != 1 {
                 return Err(Error::InvalidResult);
@@ -939,6 +1219,33 @@ impl CatalogDb {
         Err(Error::NoSqlDb)
     }
 
+    /// Get the album `id`.
+    pub(crate) fn get_album(&self, id: LibraryId) -> Result<Album> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}=?1",
+            Album::read_db_columns(),
+            Album::read_db_tables(),
+            Album::read_db_where_id()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Album::read_from(row)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Get the ids of the files in album `id`.
+    pub(crate) fn get_album_file_ids(&self, id: LibraryId) -> Result<Vec<LibraryId>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare("SELECT file_id FROM albuming WHERE album_id=?1")?;
+        let ids = stmt
+            .query_map(params![id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<LibraryId>>>()?;
+        Ok(ids)
+    }
+
     /// Get all the albums.
     pub(crate) fn get_all_albums(&self) -> Result<Vec<Album>> {
         if let Some(ref conn) = self.dbconn {
@@ -1015,121 +1322,616 @@ impl CatalogDb {
         Err(Error::NoSqlDb)
     }
 
-    fn add_fs_file<P: AsRef<Path>>(&self, f: P) -> Result<LibraryId> {
-        if let Some(ref conn) = self.dbconn {
-            let file = f.as_ref().to_string_lossy();
-            let c = conn.execute("INSERT INTO fsfiles (path) VALUES(?1)", params![file])?;
-            if c != 1 {
-                return Err(Error::InvalidResult);
-            }
-            return Ok(conn.last_insert_rowid());
-        }
+    /// Add a project to the library
+    pub(crate) fn add_project(&self, name: &str) -> Result<Project> {
+        self.insert_project(None, name)
+    }
 
-        Err(Error::NoSqlDb)
+    /// Add a project to the library with a specific `id`. This is to
+    /// restore a deleted project.
+    pub(crate) fn add_project_with_id(&self, id: LibraryId, name: &str) -> Result<Project> {
+        self.insert_project(Some(id), name)
     }
 
-    fn get_fs_file(&self, id: LibraryId) -> Result<String> {
-        if let Some(ref conn) = self.dbconn {
-            let mut stmt = conn.prepare("SELECT path FROM fsfiles WHERE id=?1")?;
-            let mut rows = stmt.query(params![id])?;
-            return match rows.next() {
-                Ok(Some(row)) => Ok(row.get(0)?),
-                Err(err) => Err(Error::from(err)),
-                Ok(None) => Err(Error::NotFound),
-            };
+    fn insert_project(&self, id: Option<LibraryId>, name: &str) -> Result<Project> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "INSERT INTO projects (id, name, creation_date) VALUES(?1, ?2, ?3)",
+            params![id, name, Utc::now().timestamp()],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidResult);
         }
-        Err(Error::NoSqlDb)
+        let id = conn.last_insert_rowid();
+        Ok(Project::new(id, name))
     }
 
-    pub(crate) fn add_bundle(
-        &self,
-        folder_id: LibraryId,
-        bundle: &FileBundle,
-    ) -> Result<LibraryId> {
-        let file_id = self.add_file(folder_id, bundle.main(), Some(bundle))?;
-        if file_id <= 0 {
-            err_out!("add_file returned {}", file_id);
-            return Err(Error::InvalidResult);
+    /// Get the project `id`.
+    pub(crate) fn get_project(&self, id: LibraryId) -> Result<Project> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}=?1",
+            Project::read_db_columns(),
+            Project::read_db_tables(),
+            Project::read_db_where_id()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Project::read_from(row)?),
+            None => Err(Error::NotFound),
         }
-        if !bundle.xmp_sidecar().as_os_str().is_empty() {
-            let fsfile_id = self.add_fs_file(bundle.xmp_sidecar())?;
-            if fsfile_id > 0 {
-                self.add_xmp_sidecar_to_bundle(file_id, fsfile_id)?;
-                self.add_sidecar_fsfile_to_bundle(
-                    file_id,
-                    fsfile_id,
-                    Sidecar::Xmp(PathBuf::new()).to_int(),
-                    "xmp",
-                )?;
-            }
+    }
+
+    pub(crate) fn delete_project(&self, id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM projects WHERE id=?1", params![id])?;
+        if c == 1 {
+            return Ok(());
         }
-        if !bundle.jpeg().as_os_str().is_empty() {
-            let fsfile_id = self.add_fs_file(bundle.jpeg())?;
-            if fsfile_id > 0 {
-                self.add_jpeg_file_to_bundle(file_id, fsfile_id)?;
-                self.add_sidecar_fsfile_to_bundle(
-                    file_id,
-                    fsfile_id,
-                    Sidecar::Jpeg(PathBuf::new()).to_int(),
-                    "jpg",
-                )?;
-            }
+        Err(Error::InvalidResult)
+    }
+
+    /// Rename project `id` to `name`.
+    pub(crate) fn rename_project(&self, id: LibraryId, name: &str) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("UPDATE projects SET name=?2 WHERE id=?1", params![id, name])?;
+        if c == 1 {
+            return Ok(());
         }
+        Err(Error::InvalidResult)
+    }
 
-        Ok(file_id)
+    /// Get all the projects.
+    pub(crate) fn get_all_projects(&self) -> Result<Vec<Project>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {}",
+            Project::read_db_columns(),
+            Project::read_db_tables()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        let mut projects: Vec<Project> = vec![];
+        while let Ok(Some(row)) = rows.next() {
+            projects.push(Project::read_from(row)?);
+        }
+        Ok(projects)
     }
 
-    fn add_file<P: AsRef<Path> + AsRef<OsStr>>(
-        &self,
-        folder_id: LibraryId,
-        file: P,
-        bundle: Option<&FileBundle>,
-    ) -> Result<LibraryId> {
-        dbg_assert!(folder_id != -1, "invalid folder ID");
-        let file_path: &Path = file.as_ref();
-        let mime = npc_fwk::MimeType::new(file_path);
-        let file_type = libfile::mimetype_to_filetype(&mime);
-        let label_id: LibraryId = 0;
-        let orientation: i32;
-        let rating: i32;
-        //let label: String; // XXX fixme
-        let flag: i32;
-        let creation_date: npc_fwk::Time;
-        let xmp: String;
+    pub(crate) fn count_project(&self, id: LibraryId) -> Result<i64> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(
+            "SELECT COUNT(file_id) FROM project_files \
+             WHERE project_id=?1;",
+        )?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next() {
+            Ok(Some(row)) => Ok(row.get(0)?),
+            Err(err) => Err(Error::from(err)),
+            Ok(None) => Err(Error::NotFound),
+        }
+    }
 
-        // Until we get better metadata support for RAW files, we use the Exif reconcile
-        // from the sidecar JPEG to get the initial metadata.
-        let meta = if let Some(bundle) = bundle {
-            if bundle.bundle_type() == libfile::FileType::RawJpeg {
-                npc_fwk::XmpMeta::new_from_file(bundle.jpeg(), false)
-            } else {
-                npc_fwk::XmpMeta::new_from_file(file_path, false)
-            }
-        } else {
-            npc_fwk::XmpMeta::new_from_file(file_path, false)
-        };
+    pub(crate) fn get_project_content(&self, project_id: LibraryId) -> Result<Vec<LibFile>> {
+        self.get_content(
+            project_id,
+            "files.id IN \
+             (SELECT file_id FROM project_files \
+             WHERE project_id=?1) ",
+        )
+    }
 
-        if let Some(ref meta) = meta {
-            orientation = meta.orientation().unwrap_or(0);
-            rating = meta.rating().unwrap_or(0);
-            //label = meta.label().unwrap_or(String::from(""));
-            flag = meta.flag().unwrap_or(0);
-            if let Some(ref date) = meta.creation_date() {
-                creation_date = date.timestamp();
-            } else {
-                creation_date = 0
+    /// Attach `images` to a `project`. Images already attached are
+    /// left untouched.
+    pub(crate) fn add_to_project(&self, images: &[LibraryId], project: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        self.in_transaction(|| {
+            let mut stmt = conn.prepare(
+                "INSERT OR IGNORE INTO project_files (project_id, file_id) VALUES(?1, ?2)",
+            )?;
+            for image in images {
+                stmt.execute(params![project, image])?;
             }
-            xmp = meta.serialize_inline();
-        } else {
-            orientation = 0;
-            rating = 0;
-            //label = String::from("");
-            flag = 0;
-            creation_date = 0;
-            xmp = String::from("");
-        }
+            Ok(())
+        })
+    }
 
-        let filename = file_path
+    /// Detach `images` from a `project`.
+    pub(crate) fn remove_from_project(
+        &self,
+        images: &[LibraryId],
+        project: LibraryId,
+    ) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        self.in_transaction(|| {
+            let mut stmt =
+                conn.prepare("DELETE FROM project_files WHERE (project_id=?1 AND file_id=?2)")?;
+            for image in images {
+                let c = stmt.execute(params![project, image])?;
+                if c != 1 {
+                    return Err(Error::InvalidResult);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Set the `state` of `image` in `project`. The image must be
+    /// attached to the project.
+    pub(crate) fn set_project_state(
+        &self,
+        project: LibraryId,
+        image: LibraryId,
+        state: ProjectState,
+    ) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE project_files SET state=?3 WHERE project_id=?1 AND file_id=?2",
+            params![project, image, i32::from(state)],
+        )?;
+        if c == 1 {
+            return Ok(());
+        }
+        Err(Error::NotFound)
+    }
+
+    /// Get the state of each image in `project`.
+    pub(crate) fn get_project_states(
+        &self,
+        project: LibraryId,
+    ) -> Result<Vec<(LibraryId, ProjectState)>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt =
+            conn.prepare("SELECT file_id, state FROM project_files WHERE project_id=?1")?;
+        let mut rows = stmt.query(params![project])?;
+        let mut states = vec![];
+        while let Ok(Some(row)) = rows.next() {
+            let state: i32 = row.get(1)?;
+            states.push((row.get(0)?, ProjectState::from(state)));
+        }
+        Ok(states)
+    }
+
+    /// Attach `item` to `project`.
+    pub(crate) fn add_project_item(&self, project: LibraryId, item: &ProjectItem) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let item_id = match item {
+            ProjectItem::Album(id) | ProjectItem::Folder(id) => *id,
+            ProjectItem::Output(path) => self.add_fs_file(path)?,
+        };
+        let c = conn.execute(
+            "INSERT OR IGNORE INTO project_items (project_id, item_type, item_id) \
+             VALUES(?1, ?2, ?3)",
+            params![project, item.item_type(), item_id],
+        )?;
+        if c == 0 && matches!(item, ProjectItem::Output(_)) {
+            // Already attached: don't leak the fsfile.
+            conn.execute("DELETE FROM fsfiles WHERE id=?1", params![item_id])?;
+        }
+        Ok(())
+    }
+
+    /// Detach `item` from `project`.
+    pub(crate) fn remove_project_item(&self, project: LibraryId, item: &ProjectItem) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = match item {
+            ProjectItem::Album(id) | ProjectItem::Folder(id) => conn.execute(
+                "DELETE FROM project_items WHERE project_id=?1 AND item_type=?2 AND item_id=?3",
+                params![project, item.item_type(), id],
+            )?,
+            ProjectItem::Output(path) => conn.execute(
+                "DELETE FROM project_items WHERE project_id=?1 AND item_type=?2 \
+                 AND item_id IN (SELECT id FROM fsfiles WHERE path=?3)",
+                params![project, item.item_type(), path.to_string_lossy()],
+            )?,
+        };
+        if c == 0 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Get the items attached to `project`.
+    pub(crate) fn get_project_items(&self, project: LibraryId) -> Result<Vec<ProjectItem>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(
+            "SELECT item_type, item_id, fsfiles.path FROM project_items \
+             LEFT JOIN fsfiles ON (item_type = 3 AND fsfiles.id = item_id) \
+             WHERE project_id=?1",
+        )?;
+        let mut rows = stmt.query(params![project])?;
+        let mut items = vec![];
+        while let Ok(Some(row)) = rows.next() {
+            let item_type: i32 = row.get(0)?;
+            let item = match item_type {
+                1 => ProjectItem::Album(row.get(1)?),
+                2 => ProjectItem::Folder(row.get(1)?),
+                3 => {
+                    let path: String = row.get(2)?;
+                    ProjectItem::Output(PathBuf::from(path))
+                }
+                _ => {
+                    err_out!("Unknown project item type {item_type}");
+                    continue;
+                }
+            };
+            items.push(item);
+        }
+        Ok(items)
+    }
+
+    /// Add a smart collection to the library
+    pub(crate) fn add_smart_collection(
+        &self,
+        name: &str,
+        rules: &[SmartRule],
+        match_all: bool,
+    ) -> Result<SmartCollection> {
+        self.insert_smart_collection(None, name, rules, match_all)
+    }
+
+    /// Add a smart collection with a specific `id`. This is to restore
+    /// a deleted smart collection.
+    pub(crate) fn add_smart_collection_with_id(
+        &self,
+        id: LibraryId,
+        name: &str,
+        rules: &[SmartRule],
+        match_all: bool,
+    ) -> Result<SmartCollection> {
+        self.insert_smart_collection(Some(id), name, rules, match_all)
+    }
+
+    fn insert_smart_collection(
+        &self,
+        id: Option<LibraryId>,
+        name: &str,
+        rules: &[SmartRule],
+        match_all: bool,
+    ) -> Result<SmartCollection> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "INSERT INTO smart_collections (id, name, rules, match_all) VALUES(?1, ?2, ?3, ?4)",
+            params![id, name, SmartCollection::rules_to_string(rules), match_all],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidResult);
+        }
+        let id = conn.last_insert_rowid();
+        Ok(SmartCollection::new(id, name, rules.to_vec(), match_all))
+    }
+
+    /// Update smart collection `id` with `name` and `rules`.
+    pub(crate) fn update_smart_collection(
+        &self,
+        id: LibraryId,
+        name: &str,
+        rules: &[SmartRule],
+        match_all: bool,
+    ) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE smart_collections SET name=?2, rules=?3, match_all=?4 WHERE id=?1",
+            params![id, name, SmartCollection::rules_to_string(rules), match_all],
+        )?;
+        if c == 1 {
+            return Ok(());
+        }
+        Err(Error::InvalidResult)
+    }
+
+    pub(crate) fn delete_smart_collection(&self, id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM smart_collections WHERE id=?1", params![id])?;
+        if c == 1 {
+            return Ok(());
+        }
+        Err(Error::InvalidResult)
+    }
+
+    /// Get the smart collection `id`.
+    pub(crate) fn get_smart_collection(&self, id: LibraryId) -> Result<SmartCollection> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}=?1",
+            SmartCollection::read_db_columns(),
+            SmartCollection::read_db_tables(),
+            SmartCollection::read_db_where_id()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(SmartCollection::read_from(row)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Get all the smart collections.
+    pub(crate) fn get_all_smart_collections(&self) -> Result<Vec<SmartCollection>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {}",
+            SmartCollection::read_db_columns(),
+            SmartCollection::read_db_tables()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        let mut collections: Vec<SmartCollection> = vec![];
+        while let Ok(Some(row)) = rows.next() {
+            collections.push(SmartCollection::read_from(row)?);
+        }
+        Ok(collections)
+    }
+
+    /// Save the metadata preset `name` with `properties`. A preset
+    /// with the same name is replaced.
+    pub(crate) fn save_metadata_preset(
+        &self,
+        name: &str,
+        properties: &[(Np, PropertyValue)],
+    ) -> Result<MetadataPreset> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let id = conn.query_row(
+            "INSERT INTO metadata_presets (name, properties) VALUES(?1, ?2) \
+             ON CONFLICT(name) DO UPDATE SET properties=excluded.properties \
+             RETURNING id",
+            params![name, MetadataPreset::properties_to_string(properties)],
+            |row| row.get(0),
+        )?;
+        Ok(MetadataPreset::new(id, name, properties.to_vec()))
+    }
+
+    pub(crate) fn delete_metadata_preset(&self, id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM metadata_presets WHERE id=?1", params![id])?;
+        if c == 1 {
+            return Ok(());
+        }
+        Err(Error::NotFound)
+    }
+
+    /// Get all the metadata presets, by name.
+    pub(crate) fn get_all_metadata_presets(&self) -> Result<Vec<MetadataPreset>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} ORDER BY name",
+            MetadataPreset::read_db_columns(),
+            MetadataPreset::read_db_tables()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        let mut presets: Vec<MetadataPreset> = vec![];
+        while let Some(row) = rows.next()? {
+            presets.push(MetadataPreset::read_from(row)?);
+        }
+        Ok(presets)
+    }
+
+    /// Save the watched folder for `path`. A watched folder with the
+    /// same path is replaced.
+    pub(crate) fn save_watched_folder(
+        &self,
+        path: &Path,
+        dest: Option<&Path>,
+        preset_id: LibraryId,
+    ) -> Result<WatchedFolder> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let id = conn.query_row(
+            "INSERT INTO watched_folders (path, dest, preset_id) VALUES(?1, ?2, ?3) \
+             ON CONFLICT(path) DO UPDATE SET dest=excluded.dest, preset_id=excluded.preset_id \
+             RETURNING id",
+            params![
+                path.to_string_lossy(),
+                dest.map(|dest| dest.to_string_lossy()),
+                preset_id
+            ],
+            |row| row.get(0),
+        )?;
+        Ok(WatchedFolder::new(id, path, dest, preset_id))
+    }
+
+    pub(crate) fn delete_watched_folder(&self, id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM watched_folders WHERE id=?1", params![id])?;
+        if c == 1 {
+            return Ok(());
+        }
+        Err(Error::NotFound)
+    }
+
+    /// Get all the watched folders, by path.
+    pub(crate) fn get_all_watched_folders(&self) -> Result<Vec<WatchedFolder>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} ORDER BY path",
+            WatchedFolder::read_db_columns(),
+            WatchedFolder::read_db_tables()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        let mut folders: Vec<WatchedFolder> = vec![];
+        while let Some(row) = rows.next()? {
+            folders.push(WatchedFolder::read_from(row)?);
+        }
+        Ok(folders)
+    }
+
+    /// Evaluate the smart collection `id` and return its content.
+    pub(crate) fn get_smart_collection_content(&self, id: LibraryId) -> Result<Vec<LibFile>> {
+        let collection = self.get_smart_collection(id)?;
+        let (sql_where, params) = collection.to_sql();
+        self.query_content(&sql_where, rusqlite::params_from_iter(params))
+    }
+
+    pub(crate) fn count_smart_collection(&self, id: LibraryId) -> Result<i64> {
+        let collection = self.get_smart_collection(id)?;
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let (sql_where, params) = collection.to_sql();
+        let sql = format!("SELECT COUNT(files.id) FROM files WHERE {sql_where};");
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params))?;
+        match rows.next() {
+            Ok(Some(row)) => Ok(row.get(0)?),
+            Err(err) => Err(Error::from(err)),
+            Ok(None) => Err(Error::NotFound),
+        }
+    }
+
+    /// Update the full text search index for `file_id`.
+    fn update_search_index(&self, file_id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute("DELETE FROM files_fts WHERE rowid=?1", params![file_id])?;
+        conn.execute(
+            &format!(
+                "INSERT INTO files_fts (rowid, name, headline, description, keywords) \
+                 {SEARCH_INDEX_SELECT} WHERE files.id=?3"
+            ),
+            params![NS_PHOTOSHOP, NS_DC, file_id],
+        )?;
+        Ok(())
+    }
+
+    /// Rebuild the full text search index for all the files.
+    pub(crate) fn rebuild_search_index(&self) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute("DELETE FROM files_fts", [])?;
+        conn.execute(
+            &format!(
+                "INSERT INTO files_fts (rowid, name, headline, description, keywords) \
+                 {SEARCH_INDEX_SELECT}"
+            ),
+            params![NS_PHOTOSHOP, NS_DC],
+        )?;
+        Ok(())
+    }
+
+    /// Search the catalog with `query`.
+    pub(crate) fn search(&self, query: &SearchQuery) -> Result<Vec<LibFile>> {
+        let (sql_where, params) = query.to_sql();
+        self.query_content(&sql_where, rusqlite::params_from_iter(params))
+    }
+
+    fn add_fs_file<P: AsRef<Path>>(&self, f: P) -> Result<LibraryId> {
+        if let Some(ref conn) = self.dbconn {
+            let file = f.as_ref().to_string_lossy();
+            let size = std::fs::metadata(f.as_ref()).ok().map(|m| m.len() as i64);
+            let fingerprint = fingerprint::fingerprint(f.as_ref());
+            let c = conn.execute(
+                "INSERT INTO fsfiles (path, size, fingerprint) VALUES(?1, ?2, ?3)",
+                params![file, size, fingerprint],
+            )?;
+            if c != 1 {
+                return Err(Error::InvalidResult);
+            }
+            return Ok(conn.last_insert_rowid());
+        }
+
+        Err(Error::NoSqlDb)
+    }
+
+    fn get_fs_file(&self, id: LibraryId) -> Result<String> {
+        if let Some(ref conn) = self.dbconn {
+            let mut stmt = conn.prepare("SELECT path FROM fsfiles WHERE id=?1")?;
+            let mut rows = stmt.query(params![id])?;
+            return match rows.next() {
+                Ok(Some(row)) => Ok(row.get(0)?),
+                Err(err) => Err(Error::from(err)),
+                Ok(None) => Err(Error::NotFound),
+            };
+        }
+        Err(Error::NoSqlDb)
+    }
+
+    pub(crate) fn add_bundle(
+        &self,
+        folder_id: LibraryId,
+        bundle: &FileBundle,
+    ) -> Result<LibraryId> {
+        let file_id = self.add_file(folder_id, bundle.main(), Some(bundle))?;
+        if file_id <= 0 {
+            err_out!("add_file returned {}", file_id);
+            return Err(Error::InvalidResult);
+        }
+        if !bundle.xmp_sidecar().as_os_str().is_empty() {
+            let fsfile_id = self.add_fs_file(bundle.xmp_sidecar())?;
+            if fsfile_id > 0 {
+                self.add_xmp_sidecar_to_bundle(file_id, fsfile_id)?;
+                self.add_sidecar_fsfile_to_bundle(
+                    file_id,
+                    fsfile_id,
+                    Sidecar::Xmp(PathBuf::new()).to_int(),
+                    "xmp",
+                )?;
+            }
+        }
+        if !bundle.jpeg().as_os_str().is_empty() {
+            let fsfile_id = self.add_fs_file(bundle.jpeg())?;
+            if fsfile_id > 0 {
+                self.add_jpeg_file_to_bundle(file_id, fsfile_id)?;
+                self.add_sidecar_fsfile_to_bundle(
+                    file_id,
+                    fsfile_id,
+                    Sidecar::Jpeg(PathBuf::new()).to_int(),
+                    "jpg",
+                )?;
+            }
+        }
+
+        Ok(file_id)
+    }
+
+    fn add_file<P: AsRef<Path> + AsRef<OsStr>>(
+        &self,
+        folder_id: LibraryId,
+        file: P,
+        bundle: Option<&FileBundle>,
+    ) -> Result<LibraryId> {
+        dbg_assert!(folder_id != -1, "invalid folder ID");
+        let file_path: &Path = file.as_ref();
+        let mime = npc_fwk::MimeType::new(file_path);
+        let file_type = libfile::mimetype_to_filetype(&mime);
+        let label_id: LibraryId = 0;
+        let orientation: i32;
+        let rating: i32;
+        //let label: String; // XXX fixme
+        let flag: i32;
+        let creation_date: npc_fwk::Time;
+        let xmp: String;
+
+        // Until we get better metadata support for RAW files, we use the Exif reconcile
+        // from the sidecar JPEG to get the initial metadata.
+        let meta = if let Some(bundle) = bundle {
+            if bundle.bundle_type() == libfile::FileType::RawJpeg {
+                npc_fwk::XmpMeta::new_from_file(bundle.jpeg(), false)
+            } else {
+                npc_fwk::XmpMeta::new_from_file(file_path, false)
+            }
+        } else {
+            npc_fwk::XmpMeta::new_from_file(file_path, false)
+        };
+
+        if let Some(ref meta) = meta {
+            orientation = meta.orientation().unwrap_or(0);
+            rating = meta.rating().unwrap_or(0);
+            //label = meta.label().unwrap_or(String::from(""));
+            flag = meta.flag().unwrap_or(0);
+            if let Some(ref date) = meta.creation_date() {
+                creation_date = date.timestamp();
+            } else {
+                creation_date = 0
+            }
+            xmp = meta.serialize_inline();
+        } else {
+            orientation = 0;
+            rating = 0;
+            //label = String::from("");
+            flag = 0;
+            creation_date = 0;
+            xmp = String::from("");
+        }
+
+        let filename = file_path
             .file_name()
             .map(|s| s.to_string_lossy())
             .unwrap_or_default();
@@ -1166,69 +1968,314 @@ impl CatalogDb {
                 ],
             )?;
 
-            if c == 1 {
-                let id = conn.last_insert_rowid();
-                if let Some(mut meta) = meta {
-                    let keywords = meta.keywords();
-                    for k in keywords {
-                        let kwid = self.make_keyword(k)?;
-                        if kwid != -1 {
-                            self.assign_keyword(kwid, id)?;
-                        }
-                    }
-                }
-                return Ok(id);
+            if c == 1 {
+                let id = conn.last_insert_rowid();
+                if let Some(mut meta) = meta {
+                    let keywords = self.xmp_keyword_paths(&meta)?;
+                    for k in &keywords {
+                        let kwid = self.make_keyword_path(k)?;
+                        if kwid != -1 {
+                            self.assign_keyword(kwid, id)?;
+                        }
+                    }
+                }
+                self.update_search_index(id)?;
+                return Ok(id);
+            }
+            return Err(Error::InvalidResult);
+        }
+
+        Err(Error::NoSqlDb)
+    }
+
+    /// Make the keyword `keyword` under `parent`, unless it already exists.
+    /// Return its id.
+    pub(crate) fn make_keyword(&self, keyword: &str, parent: LibraryId) -> Result<LibraryId> {
+        if let Some(ref conn) = self.dbconn {
+            let mut stmt = conn.prepare(
+                "SELECT id FROM keywords WHERE \
+                 keyword=?1 AND parent_id=?2;",
+            )?;
+            let mut rows = stmt.query(params![keyword, parent])?;
+            if let Ok(Some(row)) = rows.next() {
+                let keyword_id = row.get(0)?;
+                if keyword_id > 0 {
+                    return Ok(keyword_id);
+                }
+            }
+
+            let c = conn.execute(
+                "INSERT INTO keywords (keyword, parent_id) VALUES(?1, ?2);",
+                params![keyword, parent],
+            )?;
+            if c != 1 {
+                return Err(Error::InvalidResult);
+            }
+            let keyword_id = conn.last_insert_rowid();
+            if self
+                .notify(LibNotification::AddedKeyword(Keyword::new(
+                    keyword_id, keyword, parent,
+                )))
+                .is_err()
+            {
+                err_out!("Failed to send AddedKeyword notification");
+            }
+            return Ok(keyword_id);
+        }
+        Err(Error::NoSqlDb)
+    }
+
+    /// Make the keywords for `path`, the keywords separated by
+    /// `KEYWORD_PATH_SEPARATOR` from the top level. Return the id of the
+    /// last one, or -1 if the path is empty.
+    pub(crate) fn make_keyword_path(&self, path: &str) -> Result<LibraryId> {
+        let mut keyword_id = -1;
+        let mut parent = 0;
+        for keyword in path
+            .split(KEYWORD_PATH_SEPARATOR)
+            .map(str::trim)
+            .filter(|keyword| !keyword.is_empty())
+        {
+            keyword_id = self.make_keyword(keyword, parent)?;
+            parent = keyword_id;
+        }
+        Ok(keyword_id)
+    }
+
+    pub(crate) fn get_keyword(&self, id: LibraryId) -> Result<Keyword> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}=?1",
+            Keyword::read_db_columns(),
+            Keyword::read_db_tables(),
+            Keyword::read_db_where_id()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Keyword::read_from(row)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Get the keyword `id` and its ancestors, from the top level.
+    fn get_keyword_ancestry(&self, id: LibraryId) -> Result<Vec<Keyword>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        // The depth limit protect against cycles.
+        let mut stmt = conn.prepare(
+            "WITH RECURSIVE ancestry(id, keyword, parent_id, export, depth) AS (\
+             SELECT id, keyword, parent_id, export, 0 FROM keywords WHERE id=?1 \
+             UNION ALL \
+             SELECT keywords.id, keywords.keyword, keywords.parent_id, keywords.export, \
+             ancestry.depth + 1 FROM keywords, ancestry \
+             WHERE keywords.id = ancestry.parent_id AND ancestry.depth < 64) \
+             SELECT id, keyword, parent_id, export FROM ancestry ORDER BY depth DESC",
+        )?;
+        let ancestry = stmt
+            .query_map(params![id], Keyword::read_from)?
+            .collect::<rusqlite::Result<Vec<Keyword>>>()?;
+        if ancestry.is_empty() {
+            return Err(Error::NotFound);
+        }
+        Ok(ancestry)
+    }
+
+    /// Get the path of keyword `id`.
+    pub(crate) fn get_keyword_path(&self, id: LibraryId) -> Result<String> {
+        Ok(self
+            .get_keyword_ancestry(id)?
+            .iter()
+            .map(Keyword::keyword)
+            .collect::<Vec<_>>()
+            .join(&KEYWORD_PATH_SEPARATOR.to_string()))
+    }
+
+    pub(crate) fn set_keyword_exportable(&self, id: LibraryId, exportable: bool) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE keywords SET export=?1 WHERE id=?2;",
+            params![exportable, id],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidResult);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_keyword_synonyms(&self, id: LibraryId) -> Result<Vec<String>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn
+            .prepare("SELECT synonym FROM keyword_synonyms WHERE keyword_id=?1 ORDER BY rowid")?;
+        let synonyms = stmt
+            .query_map(params![id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(synonyms)
+    }
+
+    /// The names of the keywords that have `synonym`.
+    fn get_synonym_keywords(&self, synonym: &str) -> Result<Vec<String>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(
+            "SELECT keywords.keyword FROM keyword_synonyms \
+             JOIN keywords ON keywords.id = keyword_synonyms.keyword_id \
+             WHERE keyword_synonyms.synonym=?1",
+        )?;
+        let keywords = stmt
+            .query_map(params![synonym], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(keywords)
+    }
+
+    /// The keyword paths to import from the XMP `meta`. The flat
+    /// keywords that are synonyms of the other keywords are skipped:
+    /// they are written to `dc:subject` with them.
+    fn xmp_keyword_paths(&self, meta: &npc_fwk::XmpMeta) -> Result<Vec<String>> {
+        let paths = meta.keyword_paths();
+        let names = paths
+            .iter()
+            .flat_map(|path| path.split(KEYWORD_PATH_SEPARATOR))
+            .map(|name| name.trim().to_string())
+            .collect::<Vec<_>>();
+        let mut keywords = vec![];
+        for path in &paths {
+            if !path.contains(KEYWORD_PATH_SEPARATOR)
+                && self
+                    .get_synonym_keywords(path.trim())?
+                    .iter()
+                    .any(|keyword| names.contains(keyword))
+            {
+                continue;
             }
-            return Err(Error::InvalidResult);
+            keywords.push(path.clone());
         }
+        Ok(keywords)
+    }
 
-        Err(Error::NoSqlDb)
+    fn add_keyword_synonym(&self, id: LibraryId, synonym: &str) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO keyword_synonyms (keyword_id, synonym) VALUES(?1, ?2);",
+            params![id, synonym],
+        )?;
+        Ok(())
     }
 
-    pub(crate) fn make_keyword(&self, keyword: &str) -> Result<LibraryId> {
-        if let Some(ref conn) = self.dbconn {
-            let mut stmt = conn.prepare(
-                "SELECT id FROM keywords WHERE \
-                 keyword=?1;",
-            )?;
-            let mut rows = stmt.query(params![keyword])?;
-            if let Ok(Some(row)) = rows.next() {
-                let keyword_id = row.get(0)?;
-                if keyword_id > 0 {
-                    return Ok(keyword_id);
-                }
+    /// Replace the synonyms of keyword `id`.
+    pub(crate) fn set_keyword_synonyms(&self, id: LibraryId, synonyms: &[String]) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute(
+            "DELETE FROM keyword_synonyms WHERE keyword_id=?1;",
+            params![id],
+        )?;
+        for synonym in synonyms {
+            self.add_keyword_synonym(id, synonym)?;
+        }
+        Ok(())
+    }
+
+    /// Get the whole keyword tree.
+    pub(crate) fn get_keyword_tree(&self) -> Result<Vec<KeywordNode>> {
+        let mut by_parent = BTreeMap::<LibraryId, Vec<Keyword>>::new();
+        for keyword in self.get_all_keywords()? {
+            by_parent.entry(keyword.parent()).or_default().push(keyword);
+        }
+
+        fn build(
+            catalog: &CatalogDb,
+            parent: LibraryId,
+            by_parent: &mut BTreeMap<LibraryId, Vec<Keyword>>,
+        ) -> Result<Vec<KeywordNode>> {
+            // Removing the entry protect against cycles.
+            let Some(keywords) = by_parent.remove(&parent) else {
+                return Ok(vec![]);
+            };
+            keywords
+                .iter()
+                .map(|keyword| {
+                    let mut node = KeywordNode::new(keyword.keyword());
+                    node.exportable = keyword.exportable();
+                    node.synonyms = catalog.get_keyword_synonyms(keyword.id())?;
+                    node.children = build(catalog, keyword.id(), by_parent)?;
+                    Ok(node)
+                })
+                .collect()
+        }
+
+        build(self, 0, &mut by_parent)
+    }
+
+    /// Import the keyword tree `nodes` under `parent`. Existing keywords
+    /// are updated and the synonyms are merged.
+    pub(crate) fn import_keyword_tree(
+        &self,
+        nodes: &[KeywordNode],
+        parent: LibraryId,
+    ) -> Result<()> {
+        for node in nodes {
+            let id = self.make_keyword(&node.name, parent)?;
+            self.set_keyword_exportable(id, node.exportable)?;
+            for synonym in &node.synonyms {
+                self.add_keyword_synonym(id, synonym)?;
             }
+            self.import_keyword_tree(&node.children, id)?;
+        }
+        Ok(())
+    }
 
-            let c = conn.execute(
-                "INSERT INTO keywords (keyword, parent_id) VALUES(?1, 0);",
-                params![keyword],
-            )?;
-            if c != 1 {
-                return Err(Error::InvalidResult);
+    /// Get the ids of the keywords assigned to `file_id`.
+    fn get_file_keyword_ids(&self, file_id: LibraryId) -> Result<Vec<LibraryId>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt =
+            conn.prepare("SELECT keyword_id FROM keywording WHERE file_id=?1 ORDER BY rowid")?;
+        let ids = stmt
+            .query_map(params![file_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<LibraryId>>>()?;
+        Ok(ids)
+    }
+
+    /// Get the keywords to write to the XMP for `file_id`: the subjects
+    /// (`dc:subject`) and the hierarchical subjects
+    /// (`lr:hierarchicalSubject`). The subjects include the ancestors
+    /// and the synonyms. Keywords not exportable are skipped.
+    fn get_xmp_keywords(&self, file_id: LibraryId) -> Result<(Vec<String>, Vec<String>)> {
+        let mut subjects: Vec<String> = vec![];
+        let mut hierarchical: Vec<String> = vec![];
+        for id in self.get_file_keyword_ids(file_id)? {
+            let ancestry = self.get_keyword_ancestry(id)?;
+            if !ancestry.last().map(Keyword::exportable).unwrap_or(false) {
+                continue;
             }
-            let keyword_id = conn.last_insert_rowid();
-            if self
-                .notify(LibNotification::AddedKeyword(Keyword::new(
-                    keyword_id, keyword,
-                )))
-                .is_err()
-            {
-                err_out!("Failed to send AddedKeyword notification");
+            let mut path: Vec<&str> = vec![];
+            for keyword in ancestry.iter().filter(|keyword| keyword.exportable()) {
+                path.push(keyword.keyword());
+                let names = std::iter::once(keyword.keyword().to_string())
+                    .chain(self.get_keyword_synonyms(keyword.id())?);
+                for name in names {
+                    if !subjects.contains(&name) {
+                        subjects.push(name);
+                    }
+                }
+            }
+            if path.len() > 1 {
+                let path = path.join(&KEYWORD_PATH_SEPARATOR.to_string());
+                if !hierarchical.contains(&path) {
+                    hierarchical.push(path);
+                }
             }
-            return Ok(keyword_id);
         }
-        Err(Error::NoSqlDb)
+        Ok((subjects, hierarchical))
     }
 
+    /// Assign keyword `kw_id` to `file_id`. The search index is updated.
     fn assign_keyword(&self, kw_id: LibraryId, file_id: LibraryId) -> Result<()> {
         if let Some(ref conn) = self.dbconn {
             conn.execute(
                 "INSERT OR IGNORE INTO keywording\
                  (file_id, keyword_id) \
                  VALUES(?1, ?2)",
-                params![kw_id, file_id],
+                params![file_id, kw_id],
             )?;
-            Ok(())
+            self.update_search_index(file_id)
         } else {
             Err(Error::NoSqlDb)
         }
@@ -1265,6 +2312,7 @@ impl CatalogDb {
                     while let Ok(Some(row)) = rows.next() {
                         metadata.sidecars.push(row.get(0)?);
                     }
+                    metadata.develop = self.get_develop_settings(file_id)?;
                     Ok(metadata)
                 }
             };
@@ -1281,14 +2329,15 @@ impl CatalogDb {
             )?;
             // we don't really know how many rows are supposed to be impacted
             // even 0 is valid.
-            return Ok(());
+            return self.update_search_index(file_id);
         }
         Err(Error::NoSqlDb)
     }
 
     /// Set properties for an image.
     ///
-    /// XXX only the XMP Packet is currently supported.
+    /// The XMP Packet is set first, then the other properties are set
+    /// like with `set_metadata()`, without being journaled.
     pub fn set_image_properties(
         &self,
         image_id: LibraryId,
@@ -1299,6 +2348,15 @@ impl CatalogDb {
             {
                 let mut stmt = conn.prepare("UPDATE files SET xmp=?1 WHERE id=?2;")?;
                 stmt.execute(params![xmp, image_id])?;
+                self.update_search_index(image_id)?;
+            }
+            for key in props
+                .keys()
+                .filter(|key| **key != Np::Index(Npi::NpNiepceXmpPacket))
+            {
+                if let Some(value) = props.get(key) {
+                    self.set_metadata(image_id, *key, value)?;
+                }
             }
             return Ok(());
         }
@@ -1372,7 +2430,7 @@ impl CatalogDb {
                 match *value {
                     PropertyValue::StringArray(ref keywords) => {
                         for kw in keywords {
-                            let id = self.make_keyword(kw)?;
+                            let id = self.make_keyword_path(kw)?;
                             if id != -1 {
                                 self.assign_keyword(id, file_id)?;
                             }
@@ -1391,10 +2449,572 @@ impl CatalogDb {
         metablock.set_metadata(meta, value);
         metablock.touch();
         self.set_metadata_block(file_id, &metablock)?;
+        self.update_search_index(file_id)?;
+
+        Ok(())
+    }
+
+    /// Set the develop settings of `file_id`. They are also set in
+    /// the XMP.
+    pub(crate) fn set_develop_settings(
+        &self,
+        file_id: LibraryId,
+        settings: &DevelopSettings,
+    ) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO develop_settings (file_id, version, settings) \
+             VALUES(?1, ?2, ?3)",
+            params![file_id, settings.version, settings.to_json()],
+        )?;
+        let mut metablock = self.get_metadata(file_id)?;
+        metablock.set_develop_settings(settings);
+        metablock.touch();
+        self.set_metadata_block(file_id, &metablock)
+    }
+
+    /// Get the develop settings of `file_id`, if they were set.
+    pub(crate) fn get_develop_settings(
+        &self,
+        file_id: LibraryId,
+    ) -> Result<Option<DevelopSettings>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare("SELECT settings FROM develop_settings WHERE file_id=?1")?;
+        let mut rows = stmt.query(params![file_id])?;
+        match rows.next()? {
+            Some(row) => Ok(DevelopSettings::from_json(&row.get::<_, String>(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the current value of metadata `meta` for `file_id`, as
+    /// set by `set_metadata()`. `PropertyValue::Empty` if not set.
+    pub(crate) fn get_metadata_value(&self, file_id: LibraryId, meta: Np) -> Result<PropertyValue> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let column = match meta {
+            Np::Index(Npi::NpXmpRatingProp) => Some("rating"),
+            Np::Index(Npi::NpXmpLabelProp) => Some("label"),
+            Np::Index(Npi::NpTiffOrientationProp) => Some("orientation"),
+            Np::Index(Npi::NpNiepceFlagProp) => Some("flag"),
+            _ => None,
+        };
+        if let Some(column) = column {
+            let mut stmt = conn.prepare(&format!("SELECT {column} FROM files WHERE id=?1"))?;
+            let mut rows = stmt.query(params![file_id])?;
+            return match rows.next()? {
+                Some(row) => Ok(PropertyValue::Int(
+                    row.get::<_, Option<i32>>(0)?.unwrap_or(0),
+                )),
+                None => Err(Error::NotFound),
+            };
+        }
+        if matches!(meta, Np::Index(Npi::NpIptcKeywordsProp)) {
+            let keywords = self
+                .get_file_keyword_ids(file_id)?
+                .iter()
+                .map(|id| self.get_keyword_path(*id))
+                .collect::<Result<Vec<String>>>()?;
+            return Ok(PropertyValue::StringArray(keywords));
+        }
+        Ok(self
+            .get_metadata(file_id)?
+            .get_metadata(meta)
+            .unwrap_or(PropertyValue::Empty))
+    }
+
+    /// Get the id of the folder containing `file_id`.
+    pub(crate) fn get_file_folder_id(&self, file_id: LibraryId) -> Result<LibraryId> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare("SELECT parent_id FROM files WHERE id=?1")?;
+        let mut rows = stmt.query(params![file_id])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Get the file `file_id`.
+    pub(crate) fn get_file(&self, file_id: LibraryId) -> Result<LibFile> {
+        self.get_content(file_id, "files.id = ?1")?
+            .pop()
+            .ok_or(Error::NotFound)
+    }
+
+    /// Get the virtual copies of the master `file_id`.
+    pub(crate) fn get_virtual_copies(&self, file_id: LibraryId) -> Result<Vec<LibFile>> {
+        self.get_content(file_id, "files.master_id = ?1")
+    }
+
+    /// Create a virtual copy of `file_id`. It shares the fsfiles of the
+    /// file, and gets a copy of its metadata and keywords. A copy of a
+    /// copy has the same master. Return the id of the copy.
+    pub(crate) fn create_virtual_copy(&self, file_id: LibraryId) -> Result<LibraryId> {
+        self.insert_virtual_copy(file_id, None)
+    }
+
+    /// Create the virtual copy of `file_id` with `id`. This is used to
+    /// restore a copy.
+    pub(crate) fn create_virtual_copy_with_id(
+        &self,
+        file_id: LibraryId,
+        id: LibraryId,
+    ) -> Result<LibraryId> {
+        self.insert_virtual_copy(file_id, Some(id))
+    }
+
+    fn insert_virtual_copy(&self, file_id: LibraryId, id: Option<LibraryId>) -> Result<LibraryId> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let time = Utc::now().timestamp();
+        let c = conn.execute(
+            "INSERT INTO files (\
+             id, main_file, name, parent_id, \
+             import_date, mod_date, \
+             orientation, file_date, rating, label, \
+             file_type, flag, xmp, jpeg_file, master_id) \
+             SELECT ?1, main_file, name, parent_id, ?2, ?2, \
+             orientation, file_date, rating, label, \
+             file_type, flag, xmp, jpeg_file, \
+             CASE WHEN master_id > 0 THEN master_id ELSE id END \
+             FROM files WHERE id = ?3",
+            params![id, time, file_id],
+        )?;
+        if c != 1 {
+            return Err(Error::NotFound);
+        }
+        let copy_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO keywording (file_id, keyword_id) \
+             SELECT ?1, keyword_id FROM keywording WHERE file_id = ?2",
+            params![copy_id, file_id],
+        )?;
+        self.update_search_index(copy_id)?;
+
+        Ok(copy_id)
+    }
+
+    /// Delete the virtual copy `file_id`. Masters can't be deleted this way.
+    pub(crate) fn delete_virtual_copy(&self, file_id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "DELETE FROM files WHERE id = ?1 AND master_id > 0",
+            params![file_id],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidArg);
+        }
+        Ok(())
+    }
+
+    /// Create a stack with `files`, in order. The first file is the
+    /// top. The files can't be in a stack already. Return the id of
+    /// the stack.
+    pub(crate) fn create_stack(&self, files: &[LibraryId]) -> Result<LibraryId> {
+        self.insert_stack(None, files)
+    }
+
+    /// Create the stack `id` with `files`. This is used to restore a stack.
+    pub(crate) fn create_stack_with_id(
+        &self,
+        id: LibraryId,
+        files: &[LibraryId],
+    ) -> Result<LibraryId> {
+        self.insert_stack(Some(id), files)
+    }
+
+    fn insert_stack(&self, id: Option<LibraryId>, files: &[LibraryId]) -> Result<LibraryId> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        if files.len() < 2 {
+            return Err(Error::InvalidArg);
+        }
+        let mut stmt = conn.prepare("SELECT stack_id FROM files WHERE id = ?1")?;
+        for file_id in files {
+            let mut rows = stmt.query(params![file_id])?;
+            match rows.next()? {
+                Some(row) => {
+                    if row.get::<_, LibraryId>(0)? != 0 {
+                        return Err(Error::InvalidArg);
+                    }
+                }
+                None => return Err(Error::NotFound),
+            }
+        }
+
+        conn.execute(
+            "INSERT INTO stacks (id, top_id) VALUES (?1, ?2)",
+            params![id, files[0]],
+        )?;
+        let stack_id = conn.last_insert_rowid();
+        let mut stmt =
+            conn.prepare("UPDATE files SET stack_id = ?1, stack_position = ?2 WHERE id = ?3")?;
+        for (position, file_id) in files.iter().enumerate() {
+            stmt.execute(params![stack_id, position as i64, file_id])?;
+        }
+
+        Ok(stack_id)
+    }
+
+    /// Get the files of the stack `stack_id`, in order.
+    pub(crate) fn get_stack_files(&self, stack_id: LibraryId) -> Result<Vec<LibraryId>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt =
+            conn.prepare("SELECT id FROM files WHERE stack_id = ?1 ORDER BY stack_position")?;
+        let mut rows = stmt.query(params![stack_id])?;
+        let mut files = vec![];
+        while let Some(row) = rows.next()? {
+            files.push(row.get(0)?);
+        }
+        Ok(files)
+    }
+
+    /// Get the top file of the stack `stack_id`.
+    pub(crate) fn get_stack_top(&self, stack_id: LibraryId) -> Result<LibraryId> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare("SELECT top_id FROM stacks WHERE id = ?1")?;
+        let mut rows = stmt.query(params![stack_id])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Set `file_id` as the top of the stack `stack_id`. It must be in
+    /// the stack.
+    pub(crate) fn set_stack_top(&self, stack_id: LibraryId, file_id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE stacks SET top_id = ?2 WHERE id = ?1 \
+             AND EXISTS (SELECT 1 FROM files WHERE id = ?2 AND stack_id = ?1)",
+            params![stack_id, file_id],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidArg);
+        }
+        Ok(())
+    }
+
+    /// Delete the stack `stack_id`. The files are left unstacked.
+    pub(crate) fn delete_stack(&self, stack_id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM stacks WHERE id = ?1", params![stack_id])?;
+        if c != 1 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Get the automatic stacking candidates for `files`.
+    pub(crate) fn get_stack_candidates(&self, files: &[LibraryId]) -> Result<Vec<StackCandidate>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare("SELECT file_date, xmp FROM files WHERE id = ?1")?;
+        let mut candidates = vec![];
+        for file_id in files {
+            let mut rows = stmt.query(params![file_id])?;
+            if let Some(row) = rows.next()? {
+                let date: Option<i64> = row.get(0)?;
+                let xmp: Option<String> = row.get(1)?;
+                let meta = xmp.map(|xmp| {
+                    let mut meta = npc_fwk::XmpMeta::new();
+                    meta.unserialize(&xmp);
+                    meta
+                });
+                candidates.push(StackCandidate::new(
+                    *file_id,
+                    date.unwrap_or(0),
+                    meta.as_ref(),
+                ));
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Find a file whose main file has the `fingerprint`, the `size`
+    /// and the capture `date` (time_t, 0 if unknown). The fingerprint
+    /// of the candidates that have none is computed first.
+    pub(crate) fn find_duplicate(
+        &self,
+        fingerprint: &str,
+        size: i64,
+        date: i64,
+    ) -> Result<Option<LibraryId>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(
+            "SELECT fsfiles.id, fsfiles.path FROM files, fsfiles \
+             WHERE files.main_file = fsfiles.id AND fsfiles.fingerprint IS NULL \
+             AND fsfiles.size = ?1 AND files.file_date = ?2",
+        )?;
+        let unknown = stmt
+            .query_map(params![size, date], |row| {
+                Ok((row.get::<_, LibraryId>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, path) in unknown {
+            if let Some(fingerprint) = fingerprint::fingerprint(&path) {
+                conn.execute(
+                    "UPDATE fsfiles SET fingerprint = ?2 WHERE id = ?1",
+                    params![id, fingerprint],
+                )?;
+            }
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT files.id FROM files, fsfiles \
+             WHERE files.main_file = fsfiles.id AND fsfiles.fingerprint = ?1 \
+             AND fsfiles.size = ?2 AND files.file_date = ?3 \
+             ORDER BY files.id LIMIT 1",
+        )?;
+        let mut rows = stmt.query(params![fingerprint, size, date])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the main fsfile of every file, as `(file_id, fsfile)`.
+    pub(crate) fn get_main_fs_files(&self) -> Result<Vec<(LibraryId, FsFile)>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(
+            "SELECT files.id, fsfiles.id, fsfiles.path, fsfiles.size FROM files, fsfiles \
+             WHERE files.main_file = fsfiles.id ORDER BY files.id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let path: String = row.get(2)?;
+            let size: Option<i64> = row.get(3)?;
+            Ok((
+                row.get(0)?,
+                FsFile::with_size(row.get(1)?, PathBuf::from(path), size.map(|s| s as u64)),
+            ))
+        })?;
+        Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+    }
+
+    /// Relink the fsfile `fsfile_id` to `path`. The sidecars of the
+    /// files it backs are relinked too if they are found next to `path`.
+    /// Return the fsfiles relinked, as `(fsfile_id, old_path, new_path)`.
+    pub(crate) fn relink_fs_file(
+        &self,
+        fsfile_id: LibraryId,
+        path: &Path,
+    ) -> Result<Vec<(LibraryId, String, String)>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let old_path: String = conn
+            .query_row(
+                "SELECT path FROM fsfiles WHERE id = ?1",
+                params![fsfile_id],
+                |row| row.get(0),
+            )
+            .map_err(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+                _ => Error::from(err),
+            })?;
+        let mut update = conn.prepare("UPDATE fsfiles SET path = ?2, size = ?3 WHERE id = ?1")?;
+        let size = std::fs::metadata(path).ok().map(|m| m.len() as i64);
+        if update.execute(params![fsfile_id, path.to_string_lossy(), size])? != 1 {
+            return Err(Error::NotFound);
+        }
+        let mut relinked = vec![(fsfile_id, old_path, path.to_string_lossy().to_string())];
+        let dir = path.parent().ok_or(Error::InvalidArg)?;
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT fsfiles.id, fsfiles.path FROM files, sidecars, fsfiles \
+             WHERE files.main_file = ?1 AND sidecars.file_id = files.id \
+             AND fsfiles.id = sidecars.fsfile_id AND fsfiles.id != ?1",
+        )?;
+        let sidecars = stmt
+            .query_map(params![fsfile_id], |row| {
+                let path: String = row.get(1)?;
+                Ok((row.get::<_, LibraryId>(0)?, PathBuf::from(path)))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, old_path) in sidecars {
+            let Some(name) = old_path.file_name() else {
+                continue;
+            };
+            let sidecar = dir.join(name);
+            if let Ok(metadata) = std::fs::metadata(&sidecar) {
+                update.execute(params![
+                    id,
+                    sidecar.to_string_lossy(),
+                    metadata.len() as i64
+                ])?;
+                relinked.push((
+                    id,
+                    old_path.to_string_lossy().to_string(),
+                    sidecar.to_string_lossy().to_string(),
+                ));
+            }
+        }
+        Ok(relinked)
+    }
+
+    /// Set the path of the fsfile `fsfile_id`, as is.
+    pub(crate) fn set_fs_file_path(&self, fsfile_id: LibraryId, path: &str) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE fsfiles SET path = ?2 WHERE id = ?1",
+            params![fsfile_id, path],
+        )?;
+        if c != 1 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Remap the folder `folder_id` to `path`, with its subfolders and
+    /// the fsfiles they contain. Return the old path.
+    pub(crate) fn remap_folder(&self, folder_id: LibraryId, path: &str) -> Result<String> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let old_path: Option<String> = conn
+            .query_row(
+                "SELECT path FROM folders WHERE id = ?1",
+                params![folder_id],
+                |row| row.get(0),
+            )
+            .map_err(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+                _ => Error::from(err),
+            })?;
+        let old_path = old_path
+            .filter(|old_path| !old_path.is_empty())
+            .ok_or(Error::InvalidArg)?;
+        conn.execute(
+            &format!(
+                "UPDATE folders SET path = ?2 || substr(path, length(?1) + 1) \
+                 WHERE {PATH_UNDER_WHERE}"
+            ),
+            params![old_path, path],
+        )?;
+        conn.execute(
+            &format!(
+                "UPDATE fsfiles SET path = ?2 || substr(path, length(?1) + 1) \
+                 WHERE {PATH_UNDER_WHERE}"
+            ),
+            params![old_path, path],
+        )?;
+        Ok(old_path)
+    }
+
+    /// Get the paths of the fsfiles under `dir`.
+    pub(crate) fn get_fs_file_paths(&self, dir: &str) -> Result<BTreeSet<PathBuf>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT path FROM fsfiles WHERE {PATH_UNDER_WHERE}"
+        ))?;
+        let rows = stmt.query_map(params![dir], |row| row.get::<_, String>(0))?;
+        let mut paths = BTreeSet::new();
+        for path in rows {
+            paths.insert(PathBuf::from(path?));
+        }
+        Ok(paths)
+    }
+
+    /// Get the XMP sidecar of `file_id`, if any, and the date the XMP
+    /// was last in sync with it (time_t): when it was written, reloaded
+    /// or imported.
+    pub(crate) fn get_xmp_sidecar(&self, file_id: LibraryId) -> Result<(Option<PathBuf>, i64)> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.query_row(
+            "SELECT fsfiles.path, COALESCE(files.xmp_date, files.import_date, 0) \
+             FROM files LEFT JOIN fsfiles ON fsfiles.id = files.xmp_file \
+             WHERE files.id = ?1",
+            params![file_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?.map(PathBuf::from),
+                    row.get(1)?,
+                ))
+            },
+        )
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+            _ => Error::from(err),
+        })
+    }
+
+    /// Attach the XMP sidecar `path` to `file_id`.
+    pub(crate) fn add_xmp_sidecar(&self, file_id: LibraryId, path: &Path) -> Result<()> {
+        let fsfile_id = self.add_fs_file(path)?;
+        self.add_xmp_sidecar_to_bundle(file_id, fsfile_id)?;
+        self.add_sidecar_fsfile_to_bundle(
+            file_id,
+            fsfile_id,
+            Sidecar::Xmp(PathBuf::new()).to_int(),
+            "xmp",
+        )
+    }
+
+    /// Reload the XMP of `file_id` from `meta`, read from the sidecar
+    /// modified at `date` (time_t). The sidecar wins, the properties it
+    /// doesn't have are kept. It isn't queued to be rewritten.
+    pub(crate) fn reload_xmp(
+        &self,
+        file_id: LibraryId,
+        meta: npc_fwk::XmpMeta,
+        date: i64,
+    ) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let mut meta = meta;
+        let xmp: Option<String> = conn
+            .query_row(
+                "SELECT xmp FROM files WHERE id = ?1",
+                params![file_id],
+                |row| row.get(0),
+            )
+            .map_err(|err| match err {
+                rusqlite::Error::QueryReturnedNoRows => Error::NotFound,
+                _ => Error::from(err),
+            })?;
+        if let Some(xmp) = xmp {
+            let mut current = npc_fwk::XmpMeta::new();
+            if current.unserialize(&xmp) {
+                current.copy_missing_into_xmp(&mut meta);
+            }
+        }
+        conn.execute(
+            "UPDATE files SET xmp = ?2, orientation = ?3, rating = ?4, flag = ?5, \
+             xmp_date = ?6 WHERE id = ?1",
+            params![
+                file_id,
+                meta.serialize_inline(),
+                meta.orientation().unwrap_or(0),
+                meta.rating().unwrap_or(0),
+                meta.flag().unwrap_or(0),
+                date
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM xmp_update_queue WHERE id = ?1",
+            params![file_id],
+        )?;
+        self.unassign_all_keywords_for_file(file_id)?;
+        for path in self.xmp_keyword_paths(&meta)? {
+            let keyword_id = self.make_keyword_path(&path)?;
+            if keyword_id != -1 {
+                self.assign_keyword(keyword_id, file_id)?;
+            }
+        }
+        self.update_search_index(file_id)
+    }
 
+    /// Delete the file `file_id` from the catalog. Its fsfiles are
+    /// deleted unless they are shared.
+    pub(crate) fn delete_file(&self, file_id: LibraryId) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute("DELETE FROM files WHERE id = ?1", params![file_id])?;
+        if c != 1 {
+            return Err(Error::NotFound);
+        }
         Ok(())
     }
 
+    /// Get the files in `folder_id` and its subfolders.
+    pub(crate) fn get_folder_tree_content(&self, folder_id: LibraryId) -> Result<Vec<LibFile>> {
+        self.get_content(
+            folder_id,
+            "files.parent_id IN (WITH RECURSIVE tree(id) AS \
+             (SELECT ?1 UNION SELECT folders.id FROM folders, tree \
+             WHERE folders.parent_id = tree.id) SELECT id FROM tree)",
+        )
+    }
+
     pub(crate) fn move_file_to_folder(
         &self,
         file_id: LibraryId,
@@ -1435,11 +3055,30 @@ impl CatalogDb {
     }
 
     pub(crate) fn add_label(&self, name: &str, colour: &RgbColour) -> Result<LibraryId> {
+        self.insert_label(None, name, colour)
+    }
+
+    /// Add a label with a specific `id`. This is to restore a deleted label.
+    pub(crate) fn add_label_with_id(
+        &self,
+        id: LibraryId,
+        name: &str,
+        colour: &RgbColour,
+    ) -> Result<LibraryId> {
+        self.insert_label(Some(id), name, colour)
+    }
+
+    fn insert_label(
+        &self,
+        id: Option<LibraryId>,
+        name: &str,
+        colour: &RgbColour,
+    ) -> Result<LibraryId> {
         if let Some(ref conn) = self.dbconn {
             let colour = colour.to_string();
             let c = conn.execute(
-                "INSERT INTO  labels (name,color) VALUES (?1, ?2);",
-                params![name, &colour],
+                "INSERT INTO labels (id,name,color) VALUES (?1, ?2, ?3);",
+                params![id, name, &colour],
             )?;
             if c != 1 {
                 return Err(Error::InvalidResult);
@@ -1471,6 +3110,23 @@ impl CatalogDb {
         Err(Error::NoSqlDb)
     }
 
+    /// Get the label `id`.
+    pub(crate) fn get_label(&self, id: LibraryId) -> Result<Label> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}=?1",
+            Label::read_db_columns(),
+            Label::read_db_tables(),
+            Label::read_db_where_id()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query(params![id])?;
+        match rows.next()? {
+            Some(row) => Ok(Label::read_from(row)?),
+            None => Err(Error::NotFound),
+        }
+    }
+
     pub(crate) fn delete_label(&self, label_id: LibraryId) -> Result<()> {
         if let Some(ref conn) = self.dbconn {
             let c = conn.execute("DELETE FROM labels WHERE id=?1;", [&label_id])?;
@@ -1482,6 +3138,108 @@ impl CatalogDb {
         Err(Error::NoSqlDb)
     }
 
+    /// Run `f` in a single transaction. If `f` fails, everything is
+    /// rolled back. Transactions can be nested.
+    pub(crate) fn in_transaction<T, F: FnOnce() -> Result<T>>(&self, f: F) -> Result<T> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute_batch("SAVEPOINT batch")?;
+        match f() {
+            Ok(result) => {
+                conn.execute_batch("RELEASE batch")?;
+                Ok(result)
+            }
+            Err(err) => {
+                conn.execute_batch("ROLLBACK TO batch; RELEASE batch")?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Run `f` without recording in the journal.
+    pub(crate) fn without_journal<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        let suspended = self.journal_suspended.replace(true);
+        let result = f();
+        self.journal_suspended.set(suspended);
+        result
+    }
+
+    /// Add an entry to the journal, with the `forward` operations and
+    /// their `inverse`. The undone entries are discarded as they can't
+    /// be redone anymore.
+    ///
+    /// Return the id of the entry, or `None` if the journal is suspended.
+    pub(crate) fn add_journal_entry(
+        &self,
+        label: &str,
+        forward: &[JournalOp],
+        inverse: &[JournalOp],
+    ) -> Result<Option<LibraryId>> {
+        if self.journal_suspended.get() {
+            return Ok(None);
+        }
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        conn.execute("DELETE FROM journal WHERE undone=1", [])?;
+        let c = conn.execute(
+            "INSERT INTO journal (date, label, forward, inverse) VALUES(?1, ?2, ?3, ?4)",
+            params![
+                Utc::now().timestamp(),
+                label,
+                JournalEntry::ops_to_string(forward),
+                JournalEntry::ops_to_string(inverse)
+            ],
+        )?;
+        if c != 1 {
+            return Err(Error::InvalidResult);
+        }
+        Ok(Some(conn.last_insert_rowid()))
+    }
+
+    /// Get the journal entries, most recent first.
+    pub(crate) fn get_journal_entries(&self) -> Result<Vec<JournalEntry>> {
+        self.query_journal("1 ORDER BY id DESC")
+    }
+
+    /// Get the most recent entry that can be undone. An entry that
+    /// isn't reversible is a barrier: the entries before it were
+    /// recorded against a catalog it changed, so none can be undone.
+    pub(crate) fn get_journal_undo_entry(&self) -> Result<Option<JournalEntry>> {
+        self.query_journal("undone=0 ORDER BY id DESC LIMIT 1")
+            .map(|mut entries| entries.pop().filter(JournalEntry::is_reversible))
+    }
+
+    /// Get the oldest entry that can be redone.
+    pub(crate) fn get_journal_redo_entry(&self) -> Result<Option<JournalEntry>> {
+        self.query_journal("undone=1 ORDER BY id ASC LIMIT 1")
+            .map(|mut entries| entries.pop())
+    }
+
+    fn query_journal(&self, sql_where: &str) -> Result<Vec<JournalEntry>> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {sql_where}",
+            JournalEntry::read_db_columns(),
+            JournalEntry::read_db_tables(),
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let entries = stmt
+            .query_map([], JournalEntry::read_from)?
+            .collect::<rusqlite::Result<Vec<JournalEntry>>>()?;
+        Ok(entries)
+    }
+
+    /// Mark the journal entry `id` as `undone`.
+    pub(crate) fn set_journal_undone(&self, id: LibraryId, undone: bool) -> Result<()> {
+        let conn = self.dbconn.as_ref().ok_or(Error::NoSqlDb)?;
+        let c = conn.execute(
+            "UPDATE journal SET undone=?2 WHERE id=?1",
+            params![id, undone],
+        )?;
+        if c != 1 {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
     fn get_xmp_ids_in_queue(&self) -> Result<Vec<LibraryId>> {
         if let Some(ref conn) = self.dbconn {
             let mut stmt = conn.prepare("SELECT id FROM xmp_update_queue;")?;
@@ -1517,7 +3275,7 @@ impl CatalogDb {
                     return Ok(());
                 }
                 if let Ok(mut stmt) = conn.prepare(
-                    "SELECT xmp, main_file, xmp_file FROM files \
+                    "SELECT xmp, main_file, xmp_file, master_id FROM files \
                      WHERE id=?1;",
                 ) {
                     let mut rows = stmt.query([&id])?;
@@ -1526,6 +3284,7 @@ impl CatalogDb {
                         let main_file_id: LibraryId = row.get(1)?;
                         // In case of error we assume 0.
                         let xmp_file_id: LibraryId = row.get(2).unwrap_or(0);
+                        let master_id: LibraryId = row.get(3).unwrap_or(0);
                         let p = self.get_fs_file(main_file_id);
                         let spath = if let Ok(ref p) = p {
                             PathBuf::from(p)
@@ -1543,7 +3302,12 @@ impl CatalogDb {
                             dbg_assert!(p.is_some(), "couldn't find the xmp file path");
                         }
                         if p.is_none() {
-                            p = Some(spath.with_extension("xmp"));
+                            // A virtual copy has its own sidecar.
+                            p = Some(if master_id > 0 {
+                                spath.with_extension(format!("{id}.xmp"))
+                            } else {
+                                spath.with_extension("xmp")
+                            });
                             dbg_assert!(
                                 *p.as_ref().unwrap() != spath,
                                 "path must have been changed"
@@ -1555,9 +3319,21 @@ impl CatalogDb {
                         }
                         let mut xmppacket = npc_fwk::XmpMeta::new();
                         xmppacket.unserialize(&xmp_buffer);
+                        let (subjects, hierarchical) = self.get_xmp_keywords(id)?;
+                        if let Err(err) = xmppacket.set_keywords(&subjects, &hierarchical) {
+                            err_out!("Couldn't set the keywords: {err}");
+                        }
                         if let Ok(mut f) = File::create(p.clone()) {
                             let sidecar = xmppacket.serialize();
-                            if f.write(sidecar.as_bytes()).is_ok() && (xmp_file_id <= 0) {
+                            let written = f.write(sidecar.as_bytes()).is_ok();
+                            if written {
+                                conn.execute(
+                                    "UPDATE files SET xmp_date = strftime('%s','now') \
+                                     WHERE id = ?1",
+                                    [&id],
+                                )?;
+                            }
+                            if written && (xmp_file_id <= 0) {
                                 let xmp_file_id = self.add_fs_file(&p)?;
                                 dbg_assert!(xmp_file_id > 0, "couldn't add xmp_file");
                                 // XXX handle error
@@ -1592,11 +3368,15 @@ impl CatalogDb {
 
 #[cfg(test)]
 pub(crate) mod test {
+    use std::path::{Path, PathBuf};
+
     use crate::NiepcePropertyBag;
     use crate::catalog::NiepceProperties as Np;
     use crate::catalog::NiepcePropertyIdx as Npi;
     use crate::catalog::filebundle::FileBundle;
+    use crate::catalog::fingerprint;
     use crate::library::notification::LibNotification;
+    use npc_fwk::PropertyValue;
 
     use super::{CatalogDb, DB_SCHEMA_VERSION, Error};
 
@@ -1717,17 +3497,17 @@ pub(crate) mod test {
         assert_eq!(fl.len(), count as usize);
         assert_eq!(fl[0].id(), file_id);
 
-        let kwid1 = catalog.make_keyword("foo");
+        let kwid1 = catalog.make_keyword("foo", 0);
         assert!(kwid1.is_ok());
         let kwid1 = kwid1.ok().unwrap();
         assert!(kwid1 > 0);
-        let kwid2 = catalog.make_keyword("bar");
+        let kwid2 = catalog.make_keyword("bar", 0);
         assert!(kwid2.is_ok());
         let kwid2 = kwid2.ok().unwrap();
         assert!(kwid2 > 0);
 
         // duplicate keyword
-        let kwid3 = catalog.make_keyword("foo");
+        let kwid3 = catalog.make_keyword("foo", 0);
         assert!(kwid3.is_ok());
         let kwid3 = kwid3.ok().unwrap();
         // should return kwid1 because it already exists.
@@ -1811,6 +3591,30 @@ pub(crate) mod test {
             xmp_packet.as_str(),
             original_xmp_packet.serialize_inline().as_str()
         );
+
+        // The other properties go to the columns and the keywords.
+        let mut props = NiepcePropertyBag::default();
+        props.set_value(Np::Index(Npi::NpXmpRatingProp), PropertyValue::Int(4));
+        props.set_value(Np::Index(Npi::NpNiepceFlagProp), PropertyValue::Int(-1));
+        props.set_value(
+            Np::Index(Npi::NpIptcKeywordsProp),
+            PropertyValue::StringArray(vec!["places|France".to_string()]),
+        );
+        catalog
+            .set_image_properties(bundle_id, &props)
+            .expect("Setting the properties works");
+        assert!(matches!(
+            catalog.get_metadata_value(bundle_id, Np::Index(Npi::NpXmpRatingProp)),
+            Ok(PropertyValue::Int(4))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(bundle_id, Np::Index(Npi::NpNiepceFlagProp)),
+            Ok(PropertyValue::Int(-1))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(bundle_id, Np::Index(Npi::NpIptcKeywordsProp)),
+            Ok(PropertyValue::StringArray(keywords)) if keywords == vec!["places|France"]
+        ));
     }
 
     #[test]
@@ -1945,6 +3749,773 @@ pub(crate) mod test {
         assert_eq!(root1.parent(), folder1.id(), "Root1 parent isn't folder1");
     }
 
+    #[test]
+    fn find_duplicate() {
+        let catalog = test_catalog(None);
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let folder = catalog
+            .add_folder_into("dups", Some(dir.to_string_lossy().to_string()), 0)
+            .expect("Couldn't add folder");
+        std::fs::write(dir.join("file1.jpg"), "jpeg").expect("Couldn't write file");
+        let file1 = catalog
+            .add_file(folder.id(), dir.join("file1.jpg"), None)
+            .expect("Couldn't add file");
+
+        let fingerprint =
+            fingerprint::fingerprint(dir.join("file1.jpg")).expect("Couldn't fingerprint");
+        assert_eq!(catalog.find_duplicate(&fingerprint, 4, 0), Ok(Some(file1)));
+        assert_eq!(catalog.find_duplicate(&fingerprint, 5, 0), Ok(None));
+        assert_eq!(catalog.find_duplicate(&fingerprint, 4, 1000), Ok(None));
+        assert_eq!(catalog.find_duplicate("0123", 4, 0), Ok(None));
+
+        // Added before the fingerprints.
+        catalog
+            .dbconn
+            .as_ref()
+            .unwrap()
+            .execute("UPDATE fsfiles SET fingerprint = NULL", [])
+            .expect("Couldn't clear fingerprints");
+        assert_eq!(catalog.find_duplicate(&fingerprint, 4, 0), Ok(Some(file1)));
+    }
+
+    #[test]
+    fn metadata_presets() {
+        let catalog = test_catalog(None);
+
+        let rights = (
+            Np::Index(Npi::NpDcRightsProp),
+            PropertyValue::String("© Me".into()),
+        );
+        let preset = catalog
+            .save_metadata_preset("Job", &[rights.clone()])
+            .expect("Couldn't save preset");
+        assert!(preset.id() > 0);
+        let other = catalog
+            .save_metadata_preset("Another job", &[])
+            .expect("Couldn't save preset");
+
+        // Saving with the same name replace the preset.
+        let creator = (
+            Np::Index(Npi::NpDcCreatorProp),
+            PropertyValue::StringArray(vec!["Me".into()]),
+        );
+        let replaced = catalog
+            .save_metadata_preset("Job", &[rights, creator])
+            .expect("Couldn't save preset");
+        assert_eq!(replaced.id(), preset.id());
+
+        let presets = catalog
+            .get_all_metadata_presets()
+            .expect("Couldn't get presets");
+        assert_eq!(presets.len(), 2);
+        assert_eq!(presets[0].name(), "Another job");
+        assert_eq!(presets[1].name(), "Job");
+        assert_eq!(presets[1].properties().len(), 2);
+
+        catalog
+            .delete_metadata_preset(other.id())
+            .expect("Couldn't delete preset");
+        assert_eq!(
+            catalog.delete_metadata_preset(other.id()),
+            Err(Error::NotFound)
+        );
+        assert_eq!(catalog.get_all_metadata_presets().map(|p| p.len()), Ok(1));
+    }
+
+    #[test]
+    fn develop_settings() {
+        use crate::catalog::DevelopSettings;
+
+        let _ = npc_fwk::ExempiManager::new(None);
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file_id = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+        assert_eq!(catalog.get_develop_settings(file_id), Ok(None));
+        let metadata = catalog
+            .get_metadata(file_id)
+            .expect("Couldn't get metadata");
+        assert!(metadata.develop_settings().is_default());
+
+        let settings = DevelopSettings {
+            exposure: 1.3,
+            contrast: 15.0,
+            ..DevelopSettings::default()
+        };
+        catalog
+            .set_develop_settings(file_id, &settings)
+            .expect("Couldn't set develop settings");
+        assert_eq!(
+            catalog.get_develop_settings(file_id),
+            Ok(Some(settings.clone()))
+        );
+        let metadata = catalog
+            .get_metadata(file_id)
+            .expect("Couldn't get metadata");
+        assert_eq!(metadata.develop_settings(), settings);
+
+        // The XMP has them too.
+        let mut xmp = npc_fwk::XmpMeta::new();
+        assert!(xmp.unserialize(&metadata.serialize_inline()));
+        assert_eq!(DevelopSettings::from_xmp(&xmp), Some(settings));
+
+        catalog.delete_file(file_id).expect("Couldn't delete file");
+        assert_eq!(catalog.get_develop_settings(file_id), Ok(None));
+    }
+
+    #[test]
+    fn watched_folders() {
+        let catalog = test_catalog(None);
+
+        let preset = catalog
+            .save_metadata_preset("Studio", &[])
+            .expect("Couldn't save preset");
+        let tethered = catalog
+            .save_watched_folder(Path::new("/tmp/tethered"), None, preset.id())
+            .expect("Couldn't save watched folder");
+        assert!(tethered.id() > 0);
+        assert!(!tethered.copy());
+        let other = catalog
+            .save_watched_folder(
+                Path::new("/tmp/hot"),
+                Some(Path::new("/home/user/Pictures")),
+                0,
+            )
+            .expect("Couldn't save watched folder");
+
+        // Saving with the same path replace the watched folder.
+        let replaced = catalog
+            .save_watched_folder(
+                Path::new("/tmp/tethered"),
+                Some(Path::new("/home/user/Pictures/Studio")),
+                preset.id(),
+            )
+            .expect("Couldn't save watched folder");
+        assert_eq!(replaced.id(), tethered.id());
+
+        let folders = catalog
+            .get_all_watched_folders()
+            .expect("Couldn't get watched folders");
+        assert_eq!(folders, vec![other.clone(), replaced]);
+
+        // Deleting the preset unset it.
+        catalog
+            .delete_metadata_preset(preset.id())
+            .expect("Couldn't delete preset");
+        let folders = catalog
+            .get_all_watched_folders()
+            .expect("Couldn't get watched folders");
+        assert_eq!(folders[1].preset_id(), 0);
+
+        catalog
+            .delete_watched_folder(other.id())
+            .expect("Couldn't delete watched folder");
+        assert_eq!(
+            catalog.delete_watched_folder(other.id()),
+            Err(Error::NotFound)
+        );
+        assert_eq!(catalog.get_all_watched_folders().map(|f| f.len()), Ok(1));
+    }
+
+    #[test]
+    fn smart_collections() {
+        use crate::catalog::smart_collection::{Comparison, SmartRule};
+
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file1");
+        let file2 = catalog
+            .add_file(folder.id(), "foo/file2.jpg", None)
+            .expect("Couldn't add file2");
+        catalog
+            .set_internal_metadata(file1, "rating", 4)
+            .expect("Couldn't set rating");
+        catalog
+            .set_internal_metadata(file2, "rating", 1)
+            .expect("Couldn't set rating");
+        let kwid = catalog
+            .make_keyword("beach", 0)
+            .expect("Couldn't add keyword");
+        catalog
+            .assign_keyword(kwid, file2)
+            .expect("Couldn't assign keyword");
+
+        let collection = catalog
+            .add_smart_collection(
+                "Best",
+                &[SmartRule::Rating(Comparison::GreaterOrEqual, 3)],
+                true,
+            )
+            .expect("Couldn't add smart collection");
+        assert!(collection.id() > 0);
+
+        let content = catalog
+            .get_smart_collection_content(collection.id())
+            .expect("Couldn't get content");
+        assert_eq!(content.len(), 1);
+        assert_eq!(content[0].id(), file1);
+        assert_eq!(catalog.count_smart_collection(collection.id()), Ok(1));
+
+        // Match any.
+        let rules = vec![
+            SmartRule::Rating(Comparison::GreaterOrEqual, 3),
+            SmartRule::Keywords(vec![kwid]),
+        ];
+        catalog
+            .update_smart_collection(collection.id(), "Best or beach", &rules, false)
+            .expect("Couldn't update smart collection");
+        let collection = catalog
+            .get_smart_collection(collection.id())
+            .expect("Couldn't get smart collection");
+        assert_eq!(collection.name(), "Best or beach");
+        assert_eq!(collection.rules(), rules.as_slice());
+        assert_eq!(catalog.count_smart_collection(collection.id()), Ok(2));
+
+        // Match all.
+        catalog
+            .update_smart_collection(collection.id(), "Best and beach", &rules, true)
+            .expect("Couldn't update smart collection");
+        assert_eq!(catalog.count_smart_collection(collection.id()), Ok(0));
+
+        // Camera model is from the XMP, and these files have none.
+        let collection2 = catalog
+            .add_smart_collection("X-T4", &[SmartRule::CameraModel("X-T4".into())], true)
+            .expect("Couldn't add smart collection");
+        assert_eq!(catalog.count_smart_collection(collection2.id()), Ok(0));
+
+        let all = catalog
+            .get_all_smart_collections()
+            .expect("Couldn't get smart collections");
+        assert_eq!(all.len(), 2);
+
+        catalog
+            .delete_smart_collection(collection.id())
+            .expect("Couldn't delete smart collection");
+        assert_eq!(
+            catalog.get_smart_collection(collection.id()).err(),
+            Some(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn search() {
+        use crate::catalog::SearchQuery;
+
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "foo/DSC_0001.jpg", None)
+            .expect("Couldn't add file1");
+        let file2 = catalog
+            .add_file(folder.id(), "foo/IMG_0002.jpg", None)
+            .expect("Couldn't add file2");
+        catalog
+            .set_internal_metadata(file1, "rating", 4)
+            .expect("Couldn't set rating");
+        let kwid = catalog
+            .make_keyword("beachfront", 0)
+            .expect("Couldn't add keyword");
+        catalog
+            .assign_keyword(kwid, file2)
+            .expect("Couldn't assign keyword");
+
+        let search = |query: &str| {
+            let query = query.parse::<SearchQuery>().expect("Invalid query");
+            catalog
+                .search(&query)
+                .expect("Search failed")
+                .iter()
+                .map(|f| f.id())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search("name:DSC*"), vec![file1]);
+        assert_eq!(search("name:dsc_000?.JPG"), vec![file1]);
+        assert_eq!(search("rating:>=4"), vec![file1]);
+        assert_eq!(search("keyword:\"Beachfront\""), vec![file2]);
+        // Full text search does prefix matching.
+        assert_eq!(search("beach"), vec![file2]);
+        assert!(search("beach rating:>=4").is_empty());
+        assert!(search("").is_empty());
+
+        // Deleting the file remove it from the index.
+        catalog
+            .dbconn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM files WHERE id=?1", [file2])
+            .expect("Couldn't delete file");
+        let count: i64 = catalog
+            .dbconn
+            .as_ref()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM files_fts", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn projects() {
+        use crate::catalog::{ProjectItem, ProjectState};
+
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file1");
+        let file2 = catalog
+            .add_file(folder.id(), "foo/file2.jpg", None)
+            .expect("Couldn't add file2");
+        let album = catalog.add_album("album", -1).expect("Couldn't add album");
+
+        let project = catalog
+            .add_project("Wedding")
+            .expect("Couldn't add project");
+        assert!(project.id() > 0);
+        catalog
+            .rename_project(project.id(), "Wedding 2025")
+            .expect("Couldn't rename project");
+        let projects = catalog.get_all_projects().expect("Couldn't get projects");
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].name(), "Wedding 2025");
+
+        catalog
+            .add_to_project(&[file1, file2], project.id())
+            .expect("Couldn't add to project");
+        // Adding twice is harmless.
+        catalog
+            .add_to_project(&[file1], project.id())
+            .expect("Couldn't add to project");
+        assert_eq!(catalog.count_project(project.id()), Ok(2));
+        let content = catalog
+            .get_project_content(project.id())
+            .expect("Couldn't get project content");
+        assert_eq!(content.len(), 2);
+
+        catalog
+            .set_project_state(project.id(), file1, ProjectState::Picked)
+            .expect("Couldn't set state");
+        catalog
+            .set_project_state(project.id(), file2, ProjectState::Delivered)
+            .expect("Couldn't set state");
+        let mut states = catalog
+            .get_project_states(project.id())
+            .expect("Couldn't get states");
+        states.sort_by_key(|s| s.0);
+        assert_eq!(
+            states,
+            vec![
+                (file1, ProjectState::Picked),
+                (file2, ProjectState::Delivered)
+            ]
+        );
+
+        let items = vec![
+            ProjectItem::Album(album.id()),
+            ProjectItem::Folder(folder.id()),
+            ProjectItem::Output("/bar/out/file1.tif".into()),
+        ];
+        for item in &items {
+            catalog
+                .add_project_item(project.id(), item)
+                .expect("Couldn't add project item");
+        }
+        assert_eq!(catalog.get_project_items(project.id()).as_ref(), Ok(&items));
+        catalog
+            .remove_project_item(project.id(), &items[2])
+            .expect("Couldn't remove project item");
+        catalog
+            .delete_album(album.id())
+            .expect("Couldn't delete album");
+        assert_eq!(
+            catalog.get_project_items(project.id()),
+            Ok(vec![ProjectItem::Folder(folder.id())])
+        );
+
+        // file2 isn't attached the second time: nothing is removed.
+        assert_eq!(
+            catalog.remove_from_project(&[file2, file2], project.id()),
+            Err(Error::InvalidResult)
+        );
+        assert_eq!(catalog.count_project(project.id()), Ok(2));
+        catalog
+            .remove_from_project(&[file2], project.id())
+            .expect("Couldn't remove from project");
+        assert_eq!(catalog.count_project(project.id()), Ok(1));
+
+        catalog
+            .delete_project(project.id())
+            .expect("Couldn't delete project");
+        assert_eq!(catalog.get_all_projects().map(|p| p.len()), Ok(0));
+        assert_eq!(catalog.count_project(project.id()), Ok(0));
+    }
+
+    #[test]
+    fn test_keyword_hierarchy() {
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+
+        let france = catalog
+            .make_keyword_path("Places|Europe|France")
+            .expect("Couldn't make keyword path");
+        let keyword = catalog.get_keyword(france).expect("Couldn't get keyword");
+        assert_eq!(keyword.keyword(), "France");
+        assert!(keyword.exportable());
+        let europe = keyword.parent();
+        assert_eq!(
+            catalog.get_keyword_path(france),
+            Ok("Places|Europe|France".to_string())
+        );
+        // Same keyword in a different branch.
+        let team = catalog
+            .make_keyword_path("Sports | France ")
+            .expect("Couldn't make keyword path");
+        assert_ne!(team, france);
+        assert_eq!(
+            catalog.make_keyword_path("Places|Europe|France"),
+            Ok(france)
+        );
+        assert_eq!(catalog.make_keyword_path(" | "), Ok(-1));
+        assert_eq!(catalog.get_all_keywords().map(|k| k.len()), Ok(5));
+
+        catalog
+            .set_keyword_synonyms(france, &["Frankreich".to_string()])
+            .expect("Couldn't set synonyms");
+        assert_eq!(
+            catalog.get_keyword_synonyms(france),
+            Ok(vec!["Frankreich".to_string()])
+        );
+        catalog
+            .set_keyword_exportable(europe, false)
+            .expect("Couldn't set exportable");
+        let private = catalog
+            .make_keyword_path("Private")
+            .expect("Couldn't make keyword");
+        catalog
+            .set_keyword_exportable(private, false)
+            .expect("Couldn't set exportable");
+
+        catalog
+            .assign_keyword(france, file)
+            .expect("Couldn't assign");
+        catalog
+            .assign_keyword(private, file)
+            .expect("Couldn't assign");
+        let value = catalog
+            .get_metadata_value(file, Np::Index(Npi::NpIptcKeywordsProp))
+            .expect("Couldn't get keywords");
+        assert!(
+            matches!(value, PropertyValue::StringArray(ref keywords) if keywords == &["Places|Europe|France", "Private"])
+        );
+        // Europe and Private aren't exported.
+        assert_eq!(
+            catalog.get_xmp_keywords(file),
+            Ok((
+                vec![
+                    "Places".to_string(),
+                    "France".to_string(),
+                    "Frankreich".to_string()
+                ],
+                vec!["Places|France".to_string()]
+            ))
+        );
+        // The synonyms written aren't read back as keywords.
+        let _ = npc_fwk::ExempiManager::new(None);
+        let (subjects, hierarchical) = catalog.get_xmp_keywords(file).unwrap();
+        let mut meta = npc_fwk::XmpMeta::new();
+        meta.set_keywords(&subjects, &hierarchical)
+            .expect("Couldn't set keywords");
+        assert_eq!(
+            catalog.xmp_keyword_paths(&meta),
+            Ok(vec!["Places|France".to_string()])
+        );
+        // Unless the keyword isn't there.
+        meta.set_keywords(&["Frankreich".to_string()], &[])
+            .expect("Couldn't set keywords");
+        assert_eq!(
+            catalog.xmp_keyword_paths(&meta),
+            Ok(vec!["Frankreich".to_string()])
+        );
+
+        let tree = catalog.get_keyword_tree().expect("Couldn't get tree");
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree[0].name, "Places");
+        assert!(!tree[0].children[0].exportable);
+        assert_eq!(tree[0].children[0].children[0].synonyms, vec!["Frankreich"]);
+
+        // Import in a new catalog.
+        let catalog2 = test_catalog(None);
+        catalog2
+            .import_keyword_tree(&tree, 0)
+            .expect("Couldn't import tree");
+        assert_eq!(catalog2.get_keyword_tree(), Ok(tree));
+    }
+
+    #[test]
+    fn test_virtual_copy() {
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let master = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+        catalog
+            .set_internal_metadata(master, "rating", 3)
+            .expect("Couldn't set rating");
+        let kwid = catalog
+            .make_keyword("beach", 0)
+            .expect("Couldn't add keyword");
+        catalog
+            .assign_keyword(kwid, master)
+            .expect("Couldn't assign keyword");
+
+        let copy1 = catalog
+            .create_virtual_copy(master)
+            .expect("Couldn't create copy");
+        let file = catalog.get_file(copy1).expect("Couldn't get copy");
+        assert!(file.is_copy());
+        assert_eq!(file.master_id(), master);
+        assert_eq!(file.rating(), 3);
+        assert_eq!(file.path(), std::path::Path::new("foo/file1.jpg"));
+        assert_eq!(catalog.get_keyword_content(kwid).map(|c| c.len()), Ok(2));
+
+        // A copy of a copy has the same master.
+        catalog
+            .set_internal_metadata(copy1, "rating", 5)
+            .expect("Couldn't set rating");
+        let copy2 = catalog
+            .create_virtual_copy(copy1)
+            .expect("Couldn't create copy");
+        let file = catalog.get_file(copy2).expect("Couldn't get copy");
+        assert_eq!(file.master_id(), master);
+        assert_eq!(file.rating(), 5);
+        let file = catalog.get_file(master).expect("Couldn't get master");
+        assert!(!file.is_copy());
+        assert_eq!(file.copy_count(), 2);
+        assert_eq!(
+            catalog
+                .get_virtual_copies(master)
+                .map(|copies| copies.iter().map(|f| f.id()).collect::<Vec<_>>()),
+            Ok(vec![copy1, copy2])
+        );
+        assert_eq!(
+            catalog.get_folder_content(folder.id()).map(|c| c.len()),
+            Ok(3)
+        );
+
+        assert_eq!(catalog.delete_virtual_copy(master), Err(Error::InvalidArg));
+
+        let fsfile_count = || -> i64 {
+            catalog
+                .dbconn
+                .as_ref()
+                .unwrap()
+                .query_row("SELECT COUNT(*) FROM fsfiles", [], |row| row.get(0))
+                .unwrap()
+        };
+        assert_eq!(fsfile_count(), 1);
+
+        // Deleting the master keeps the file, and promote the first copy.
+        catalog
+            .dbconn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM files WHERE id=?1", [master])
+            .expect("Couldn't delete file");
+        assert_eq!(fsfile_count(), 1);
+        let file = catalog.get_file(copy1).expect("Couldn't get copy");
+        assert!(!file.is_copy());
+        let file = catalog.get_file(copy2).expect("Couldn't get copy");
+        assert_eq!(file.master_id(), copy1);
+
+        catalog
+            .delete_virtual_copy(copy2)
+            .expect("Couldn't delete copy");
+        assert_eq!(fsfile_count(), 1);
+        catalog
+            .dbconn
+            .as_ref()
+            .unwrap()
+            .execute("DELETE FROM files WHERE id=?1", [copy1])
+            .expect("Couldn't delete file");
+        assert_eq!(fsfile_count(), 0);
+    }
+
+    #[test]
+    fn test_stack() {
+        let catalog = test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let files = (1..=4)
+            .map(|n| {
+                catalog
+                    .add_file(folder.id(), format!("foo/file{n}.jpg"), None)
+                    .expect("Couldn't add file")
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(catalog.create_stack(&files[0..1]), Err(Error::InvalidArg));
+        let stack = catalog
+            .create_stack(&[files[2], files[0], files[1]])
+            .expect("Couldn't create stack");
+        assert_eq!(
+            catalog.get_stack_files(stack),
+            Ok(vec![files[2], files[0], files[1]])
+        );
+        assert_eq!(catalog.get_stack_top(stack), Ok(files[2]));
+        // Already stacked.
+        assert_eq!(
+            catalog.create_stack(&[files[0], files[3]]),
+            Err(Error::InvalidArg)
+        );
+
+        let file = catalog.get_file(files[2]).expect("Couldn't get file");
+        assert_eq!(file.stack_id(), stack);
+        assert!(file.is_stack_top());
+        assert_eq!(file.stack_count(), 3);
+        let file = catalog.get_file(files[0]).expect("Couldn't get file");
+        assert!(!file.is_stack_top());
+        let file = catalog.get_file(files[3]).expect("Couldn't get file");
+        assert_eq!(file.stack_id(), 0);
+        assert_eq!(file.stack_count(), 0);
+
+        assert_eq!(
+            catalog.set_stack_top(stack, files[3]),
+            Err(Error::InvalidArg)
+        );
+        assert!(catalog.set_stack_top(stack, files[0]).is_ok());
+        assert_eq!(catalog.get_stack_top(stack), Ok(files[0]));
+
+        // Deleting the top file makes the first one the top.
+        let delete_file = |id| {
+            catalog
+                .dbconn
+                .as_ref()
+                .unwrap()
+                .execute("DELETE FROM files WHERE id=?1", [id])
+                .expect("Couldn't delete file")
+        };
+        delete_file(files[0]);
+        assert_eq!(catalog.get_stack_top(stack), Ok(files[2]));
+        // A stack of one is removed.
+        delete_file(files[2]);
+        assert_eq!(catalog.get_stack_top(stack), Err(Error::NotFound));
+        let file = catalog.get_file(files[1]).expect("Couldn't get file");
+        assert_eq!(file.stack_id(), 0);
+
+        let stack = catalog
+            .create_stack_with_id(42, &[files[1], files[3]])
+            .expect("Couldn't create stack");
+        assert_eq!(stack, 42);
+        assert!(catalog.delete_stack(stack).is_ok());
+        assert_eq!(catalog.delete_stack(stack), Err(Error::NotFound));
+        assert_eq!(catalog.get_stack_files(stack), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_remap_folder() {
+        let catalog = test_catalog(None);
+
+        let foo = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let sub = catalog
+            .add_folder_into("sub", Some("/bar/foo/sub".to_string()), foo.id())
+            .expect("Couldn't add folder");
+        let foobar = catalog
+            .add_folder_into("foobar", Some("/bar/foobar".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(foo.id(), "/bar/foo/file1.jpg", None)
+            .expect("Couldn't add file");
+        let file2 = catalog
+            .add_file(sub.id(), "/bar/foo/sub/file2.jpg", None)
+            .expect("Couldn't add file");
+        let file3 = catalog
+            .add_file(foobar.id(), "/bar/foobar/file3.jpg", None)
+            .expect("Couldn't add file");
+
+        let main_fs_files = catalog.get_main_fs_files().expect("Couldn't get fsfiles");
+        assert_eq!(main_fs_files.len(), 3);
+        assert_eq!(main_fs_files[0].0, file1);
+        // Not on disk.
+        assert_eq!(main_fs_files[0].1.size(), None);
+
+        assert_eq!(catalog.remap_folder(1000, "/baz"), Err(Error::NotFound));
+        assert_eq!(
+            catalog.remap_folder(foo.id(), "/media/disk/foo"),
+            Ok("/bar/foo".to_string())
+        );
+        assert!(catalog.get_folder("/media/disk/foo").is_ok());
+        assert_eq!(
+            catalog.get_folder("/media/disk/foo/sub").map(|f| f.id()),
+            Ok(sub.id())
+        );
+        assert!(catalog.get_folder("/bar/foobar").is_ok());
+        let path = |id| catalog.get_file(id).map(|f| f.path().to_path_buf());
+        assert_eq!(path(file1), Ok(PathBuf::from("/media/disk/foo/file1.jpg")));
+        assert_eq!(
+            path(file2),
+            Ok(PathBuf::from("/media/disk/foo/sub/file2.jpg"))
+        );
+        assert_eq!(path(file3), Ok(PathBuf::from("/bar/foobar/file3.jpg")));
+
+        let content = catalog
+            .get_folder_tree_content(foo.id())
+            .expect("Couldn't get content");
+        assert_eq!(
+            content.iter().map(|f| f.id()).collect::<Vec<_>>(),
+            vec![file1, file2]
+        );
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let new_path = tmpdir.path().join("file3.jpg");
+        std::fs::write(&new_path, "jpeg").expect("Couldn't write file");
+        let fsfile = main_fs_files[2].1.id();
+        assert_eq!(
+            catalog.relink_fs_file(fsfile, &new_path),
+            Ok(vec![(
+                fsfile,
+                "/bar/foobar/file3.jpg".to_string(),
+                new_path.to_string_lossy().to_string()
+            )])
+        );
+        assert_eq!(path(file3), Ok(new_path));
+        let main_fs_files = catalog.get_main_fs_files().expect("Couldn't get fsfiles");
+        assert_eq!(main_fs_files[2].1.size(), Some(4));
+        assert_eq!(
+            catalog.relink_fs_file(1000, tmpdir.path()),
+            Err(Error::NotFound)
+        );
+    }
+
     #[test]
     fn preferences() {
         let catalog = test_catalog(None);
@@ -248,5 +248,152 @@ fn test_upgrade_9_to() {
         let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
         upgrade::perform_upgrade_13(&conn, schema_version).expect("Upgrade to 13");
         assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_14(&conn, schema_version).expect("Upgrade to 14");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let smart_collections =
+            sql::table_sql(&conn, "smart_collections").expect("Smart collections sql failed");
+        assert_eq!(
+            smart_collections,
+            "CREATE TABLE smart_collections (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             name TEXT, rules TEXT, match_all INTEGER DEFAULT 1)"
+        );
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_15(&conn, schema_version).expect("Upgrade to 15");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let files_fts = sql::table_sql(&conn, "files_fts").expect("files_fts sql failed");
+        assert_eq!(
+            files_fts,
+            "CREATE VIRTUAL TABLE files_fts USING fts5(name, headline, description, keywords)"
+        );
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_16(&conn, schema_version).expect("Upgrade to 16");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let project_files =
+            sql::table_sql(&conn, "project_files").expect("project_files sql failed");
+        assert_eq!(
+            project_files,
+            "CREATE TABLE project_files (project_id INTEGER, file_id INTEGER, \
+             state INTEGER DEFAULT 0, UNIQUE(project_id, file_id))"
+        );
+        assert!(sql::trigger_sql(&conn, "project_delete_trigger").is_ok());
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_17(&conn, schema_version).expect("Upgrade to 17");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let journal = sql::table_sql(&conn, "journal").expect("journal sql failed");
+        assert_eq!(
+            journal,
+            "CREATE TABLE journal (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             date INTEGER, label TEXT, forward TEXT, inverse TEXT, \
+             undone INTEGER DEFAULT 0)"
+        );
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_18(&conn, schema_version).expect("Upgrade to 18");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let keyword_synonyms =
+            sql::table_sql(&conn, "keyword_synonyms").expect("keyword_synonyms sql failed");
+        assert_eq!(
+            keyword_synonyms,
+            "CREATE TABLE keyword_synonyms (keyword_id INTEGER, synonym TEXT, \
+             UNIQUE(keyword_id, synonym))"
+        );
+        assert!(sql::trigger_sql(&conn, "keyword_delete_synonym_trigger").is_ok());
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_19(&conn, schema_version).expect("Upgrade to 19");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let trigger =
+            sql::trigger_sql(&conn, "pre_file_delete_trigger").expect("trigger sql failed");
+        assert_eq!(
+            trigger,
+            "CREATE TRIGGER pre_file_delete_trigger BEFORE DELETE ON files \
+             BEGIN \
+             DELETE FROM fsfiles WHERE (id = old.main_file \
+             OR id = old.xmp_file OR id = old.jpeg_file) \
+             AND NOT EXISTS (SELECT 1 FROM files WHERE files.id != old.id \
+             AND (files.main_file = fsfiles.id OR files.xmp_file = fsfiles.id \
+             OR files.jpeg_file = fsfiles.id)); \
+             END"
+        );
+        assert!(sql::trigger_sql(&conn, "file_delete_master_trigger").is_ok());
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_20(&conn, schema_version).expect("Upgrade to 20");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let stacks = sql::table_sql(&conn, "stacks").expect("stacks sql failed");
+        assert_eq!(
+            stacks,
+            "CREATE TABLE stacks (id INTEGER PRIMARY KEY AUTOINCREMENT, top_id INTEGER)"
+        );
+        assert!(sql::trigger_sql(&conn, "stack_delete_trigger").is_ok());
+        assert!(sql::trigger_sql(&conn, "file_delete_stack_trigger").is_ok());
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_21(&conn, schema_version).expect("Upgrade to 21");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let fsfiles = sql::table_sql(&conn, "fsfiles").expect("fsfiles sql failed");
+        assert!(fsfiles.ends_with("size INTEGER)"));
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_22(&conn, schema_version).expect("Upgrade to 22");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let presets = sql::table_sql(&conn, "metadata_presets").expect("presets sql failed");
+        assert_eq!(
+            presets,
+            "CREATE TABLE metadata_presets (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             name TEXT NOT NULL UNIQUE, properties TEXT)"
+        );
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_23(&conn, schema_version).expect("Upgrade to 23");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let fsfiles = sql::table_sql(&conn, "fsfiles").expect("fsfiles sql failed");
+        assert!(fsfiles.ends_with("fingerprint TEXT)"));
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_24(&conn, schema_version).expect("Upgrade to 24");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let watched = sql::table_sql(&conn, "watched_folders").expect("watched sql failed");
+        assert_eq!(
+            watched,
+            "CREATE TABLE watched_folders (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+             path TEXT NOT NULL UNIQUE, dest TEXT, preset_id INTEGER DEFAULT 0)"
+        );
+        assert!(sql::trigger_sql(&conn, "metadata_preset_delete_watched_trigger").is_ok());
+
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_25(&conn, schema_version).expect("Upgrade to 25");
+        assert!(sql::pragma_schema_version(&conn).expect("pragma schema version") > schema_version);
+        let develop = sql::table_sql(&conn, "develop_settings").expect("develop sql failed");
+        assert_eq!(
+            develop,
+            "CREATE TABLE develop_settings (file_id INTEGER PRIMARY KEY, \
+             version INTEGER, settings TEXT)"
+        );
+        assert!(sql::trigger_sql(&conn, "file_delete_develop_trigger").is_ok());
+
+        // Keywording rows: one valid, one transposed, one invalid.
+        conn.execute_batch(
+            "INSERT INTO files (id, name) VALUES (100, 'a'), (101, 'b'); \
+             INSERT INTO keywords (id, keyword) VALUES (1, 'beach'), (2, 'sunset'); \
+             INSERT INTO keywording (file_id, keyword_id) VALUES (100, 1), (2, 101), (3, 102);",
+        )
+        .expect("keywording rows");
+        let schema_version = sql::pragma_schema_version(&conn).expect("pragma schema version");
+        upgrade::perform_upgrade_26(&conn, schema_version).expect("Upgrade to 26");
+        let mut stmt = conn
+            .prepare("SELECT file_id, keyword_id FROM keywording ORDER BY file_id")
+            .expect("prepare keywording");
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)))
+            .expect("query keywording")
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .expect("keywording rows");
+        assert_eq!(rows, vec![(100, 1), (101, 2)]);
     }
 }
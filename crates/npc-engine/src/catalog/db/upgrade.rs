@@ -21,7 +21,7 @@
 #![doc = include_str!("../../../../../doc/database_upgrade.md")]
 
 use super::{CatalogDb, Error, Result, sql};
-use npc_fwk::dbg_out;
+use npc_fwk::{dbg_out, err_out};
 
 /// Upgrade catalog `from` version `to` version
 /// Will run the step by step upgrade
@@ -73,6 +73,100 @@ pub(crate) fn catalog_to(catalog: &CatalogDb, from: i32, to: i32) -> Result<()>
                     catalog.set_db_version(13).expect("set_db_version failed");
                 }
             }
+            14 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_14(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(14).expect("set_db_version failed");
+                }
+            }
+            15 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_15(conn, schema_version).expect("Upgrade failed");
+                    catalog.rebuild_search_index()?;
+                    catalog.set_db_version(15).expect("set_db_version failed");
+                }
+            }
+            16 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_16(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(16).expect("set_db_version failed");
+                }
+            }
+            17 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_17(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(17).expect("set_db_version failed");
+                }
+            }
+            18 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_18(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(18).expect("set_db_version failed");
+                }
+            }
+            19 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_19(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(19).expect("set_db_version failed");
+                }
+            }
+            20 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_20(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(20).expect("set_db_version failed");
+                }
+            }
+            21 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_21(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(21).expect("set_db_version failed");
+                }
+            }
+            22 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_22(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(22).expect("set_db_version failed");
+                }
+            }
+            23 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_23(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(23).expect("set_db_version failed");
+                }
+            }
+            24 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_24(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(24).expect("set_db_version failed");
+                }
+            }
+            25 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_25(conn, schema_version).expect("Upgrade failed");
+                    catalog.set_db_version(25).expect("set_db_version failed");
+                }
+            }
+            26 => {
+                if let Some(conn) = &catalog.dbconn {
+                    let schema_version = sql::pragma_schema_version(conn)?;
+                    perform_upgrade_26(conn, schema_version).expect("Upgrade failed");
+                    // The keywords are part of the search index.
+                    catalog.rebuild_search_index()?;
+                    catalog.set_db_version(26).expect("set_db_version failed");
+                }
+            }
             _ => {}
         }
     }
@@ -80,6 +174,269 @@ pub(crate) fn catalog_to(catalog: &CatalogDb, from: i32, to: i32) -> Result<()>
     Ok(())
 }
 
+/// Repair the `keywording` rows written with the file and the keyword
+/// transposed. Only the rows that are invalid as is, and valid once
+/// swapped, can be told apart. The remaining rows pointing to a
+/// missing file or keyword are removed. The counts are reported.
+pub(crate) fn perform_upgrade_26(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 26");
+    let tx = conn.unchecked_transaction()?;
+    let repaired = tx.execute(
+        "UPDATE OR IGNORE keywording SET file_id = keyword_id, keyword_id = file_id \
+         WHERE (file_id NOT IN (SELECT id FROM files) \
+         OR keyword_id NOT IN (SELECT id FROM keywords)) \
+         AND keyword_id IN (SELECT id FROM files) \
+         AND file_id IN (SELECT id FROM keywords)",
+        [],
+    )?;
+    let removed = tx.execute(
+        "DELETE FROM keywording WHERE file_id NOT IN (SELECT id FROM files) \
+         OR keyword_id NOT IN (SELECT id FROM keywords)",
+        [],
+    )?;
+    tx.commit()?;
+    if repaired != 0 || removed != 0 {
+        err_out!("Keywording: repaired {repaired} transposed rows, removed {removed} invalid rows");
+    }
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_25(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 25");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE TABLE develop_settings (file_id INTEGER PRIMARY KEY, \
+         version INTEGER, settings TEXT); \
+         CREATE TRIGGER file_delete_develop_trigger AFTER DELETE ON files \
+         BEGIN \
+         DELETE FROM develop_settings WHERE file_id = old.id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_24(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 24");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE TABLE watched_folders (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         path TEXT NOT NULL UNIQUE, dest TEXT, preset_id INTEGER DEFAULT 0); \
+         CREATE TRIGGER metadata_preset_delete_watched_trigger \
+         AFTER DELETE ON metadata_presets \
+         BEGIN \
+         UPDATE watched_folders SET preset_id = 0 WHERE preset_id = old.id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_23(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 23");
+    // The fingerprint of the existing fsfiles is computed on demand.
+    conn.execute_batch(
+        "BEGIN;\
+         ALTER TABLE fsfiles ADD COLUMN fingerprint TEXT; \
+         CREATE INDEX fsfiles_fingerprint_index ON fsfiles(fingerprint); \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_22(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 22");
+    conn.execute(
+        "CREATE TABLE metadata_presets (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         name TEXT NOT NULL UNIQUE, properties TEXT)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_21(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 21");
+    // The size is unknown for the existing fsfiles.
+    conn.execute_batch(
+        "BEGIN;\
+         ALTER TABLE fsfiles ADD COLUMN size INTEGER; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_20(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 20");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE TABLE stacks (id INTEGER PRIMARY KEY AUTOINCREMENT, top_id INTEGER); \
+         ALTER TABLE files ADD COLUMN stack_id INTEGER DEFAULT 0; \
+         ALTER TABLE files ADD COLUMN stack_position INTEGER DEFAULT 0; \
+         CREATE INDEX files_stack_index ON files(stack_id); \
+         CREATE TRIGGER stack_delete_trigger AFTER DELETE ON stacks \
+         BEGIN \
+         UPDATE files SET stack_id = 0, stack_position = 0 WHERE stack_id = old.id; \
+         END; \
+         CREATE TRIGGER file_delete_stack_trigger AFTER DELETE ON files \
+         WHEN old.stack_id > 0 \
+         BEGIN \
+         UPDATE stacks SET top_id = \
+         (SELECT id FROM files WHERE stack_id = old.stack_id \
+         ORDER BY stack_position LIMIT 1) \
+         WHERE id = old.stack_id AND top_id = old.id; \
+         DELETE FROM stacks WHERE id = old.stack_id \
+         AND (SELECT COUNT(*) FROM files WHERE stack_id = old.stack_id) < 2; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_19(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 19");
+    // The fsfiles are shared by the virtual copies.
+    conn.execute_batch(
+        "BEGIN;\
+         ALTER TABLE files ADD COLUMN master_id INTEGER DEFAULT 0; \
+         DROP TRIGGER IF EXISTS pre_file_delete_trigger; \
+         CREATE TRIGGER pre_file_delete_trigger BEFORE DELETE ON files \
+         BEGIN \
+         DELETE FROM fsfiles WHERE (id = old.main_file \
+         OR id = old.xmp_file OR id = old.jpeg_file) \
+         AND NOT EXISTS (SELECT 1 FROM files WHERE files.id != old.id \
+         AND (files.main_file = fsfiles.id OR files.xmp_file = fsfiles.id \
+         OR files.jpeg_file = fsfiles.id)); \
+         END; \
+         CREATE TRIGGER file_delete_master_trigger AFTER DELETE ON files \
+         WHEN old.master_id = 0 \
+         BEGIN \
+         UPDATE files SET master_id = \
+         (SELECT MIN(id) FROM files WHERE master_id = old.id) \
+         WHERE master_id = old.id; \
+         UPDATE files SET master_id = 0 WHERE id = master_id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_18(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 18");
+    conn.execute_batch(
+        "BEGIN;\
+         ALTER TABLE keywords ADD COLUMN export INTEGER DEFAULT 1; \
+         CREATE TABLE keyword_synonyms (keyword_id INTEGER, synonym TEXT, \
+         UNIQUE(keyword_id, synonym)); \
+         CREATE TRIGGER keyword_delete_synonym_trigger AFTER DELETE ON keywords \
+         BEGIN \
+         DELETE FROM keyword_synonyms WHERE keyword_id = old.id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_17(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 17");
+    conn.execute(
+        "CREATE TABLE journal (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         date INTEGER, label TEXT, forward TEXT, inverse TEXT, \
+         undone INTEGER DEFAULT 0)",
+        [],
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_16(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 16");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE TABLE projects (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         name TEXT, creation_date INTEGER); \
+         CREATE TABLE project_files (project_id INTEGER, file_id INTEGER, \
+         state INTEGER DEFAULT 0, UNIQUE(project_id, file_id)); \
+         CREATE TABLE project_items (project_id INTEGER, item_type INTEGER, \
+         item_id INTEGER, UNIQUE(project_id, item_type, item_id)); \
+         CREATE TRIGGER project_delete_trigger AFTER DELETE ON projects \
+         BEGIN \
+         DELETE FROM project_files WHERE project_id = old.id; \
+         DELETE FROM project_items WHERE project_id = old.id; \
+         END; \
+         CREATE TRIGGER project_item_delete_trigger AFTER DELETE ON project_items \
+         WHEN old.item_type = 3 \
+         BEGIN \
+         DELETE FROM fsfiles WHERE id = old.item_id; \
+         END; \
+         CREATE TRIGGER file_delete_project_trigger AFTER DELETE ON files \
+         BEGIN \
+         DELETE FROM project_files WHERE file_id = old.id; \
+         END; \
+         CREATE TRIGGER album_delete_project_trigger AFTER DELETE ON albums \
+         BEGIN \
+         DELETE FROM project_items WHERE item_type = 1 AND item_id = old.id; \
+         END; \
+         CREATE TRIGGER folder_delete_project_trigger AFTER DELETE ON folders \
+         BEGIN \
+         DELETE FROM project_items WHERE item_type = 2 AND item_id = old.id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+/// Create the full text search table. It needs to be populated
+/// with `CatalogDb::rebuild_search_index()`.
+pub(crate) fn perform_upgrade_15(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 15");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE VIRTUAL TABLE files_fts USING fts5(name, headline, description, keywords); \
+         CREATE TRIGGER file_delete_fts_trigger AFTER DELETE ON files \
+         BEGIN \
+         DELETE FROM files_fts WHERE rowid = old.id; \
+         END; \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
+pub(crate) fn perform_upgrade_14(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
+    dbg_out!("schema_version {}", schema_version);
+    dbg_out!("upgrade 14");
+    conn.execute_batch(
+        "BEGIN;\
+         CREATE TABLE smart_collections (id INTEGER PRIMARY KEY AUTOINCREMENT, \
+         name TEXT, rules TEXT, match_all INTEGER DEFAULT 1); \
+         COMMIT;",
+    )?;
+
+    Ok(())
+}
+
 pub(crate) fn perform_upgrade_13(conn: &rusqlite::Connection, schema_version: i64) -> Result<()> {
     dbg_out!("schema_version {}", schema_version);
     dbg_out!("upgrade 13");
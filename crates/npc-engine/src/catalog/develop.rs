@@ -0,0 +1,468 @@
+/*
+ * niepce - npc-engine/src/catalog/develop.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The develop settings: the non-destructive edits of an image.
+//!
+//! They are stored in the catalog, and in the XMP with the Niepce
+//! namespace. The render engines apply them.
+
+use serde::{Deserialize, Serialize};
+
+use npc_fwk::XmpMeta;
+use npc_fwk::utils::exempi::NIEPCE_XMP_NAMESPACE;
+use npc_fwk::{dbg_out, err_out};
+
+/// The current version of the develop settings. Increase it when the
+/// meaning of a value changes.
+pub const DEVELOP_SETTINGS_VERSION: u32 = 1;
+
+/// The XMP property for the version. The other values are only read
+/// if it is present.
+const XMP_VERSION: &str = "DevelopVersion";
+/// The XMP property for the tone curve.
+const XMP_TONE_CURVE: &str = "ToneCurve";
+/// The XMP property for the crop.
+const XMP_CROP: &str = "Crop";
+
+/// A crop rectangle. The values are fractions, from 0 to 1, of the
+/// image after rotation and tilt.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl CropRect {
+    /// The crop rectangle clamped to the image. `None` if empty.
+    pub fn clamped(&self) -> Option<CropRect> {
+        let x = self.x.clamp(0.0, 1.0);
+        let y = self.y.clamp(0.0, 1.0);
+        // The right and bottom edges stay in place.
+        let w = (self.x + self.w).min(1.0) - x;
+        let h = (self.y + self.h).min(1.0) - y;
+        if w <= 0.0 || h <= 0.0 {
+            return None;
+        }
+        Some(CropRect { x, y, w, h })
+    }
+
+    /// Whether it covers the whole image.
+    pub fn is_full(&self) -> bool {
+        self.x <= 0.0 && self.y <= 0.0 && self.w >= 1.0 && self.h >= 1.0
+    }
+
+    /// The crop in pixels for an image of `width` x `height`:
+    /// x, y, w, h.
+    pub fn to_pixels(&self, width: u32, height: u32) -> (i32, i32, i32, i32) {
+        let (width, height) = (width as f64, height as f64);
+        (
+            (self.x * width).round() as i32,
+            (self.y * height).round() as i32,
+            (self.w * width).round() as i32,
+            (self.h * height).round() as i32,
+        )
+    }
+}
+
+impl std::fmt::Display for CropRect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.w, self.h)
+    }
+}
+
+impl std::str::FromStr for CropRect {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<CropRect, ()> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>().map_err(|_| ()))
+            .collect::<Result<Vec<_>, _>>()?;
+        match values[..] {
+            [x, y, w, h] => Ok(CropRect { x, y, w, h }),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The develop settings of an image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevelopSettings {
+    /// The version the settings were saved with.
+    pub version: u32,
+    /// Rotation in degrees, -45 to 45.
+    pub tilt: f64,
+    /// White balance temperature in Kelvin.
+    pub temperature: f64,
+    /// White balance tint, -100 (green) to 100 (magenta).
+    pub tint: f64,
+    /// Exposure compensation in EV.
+    pub exposure: f64,
+    /// Highlight recovery, 0 to 100.
+    pub recovery: f64,
+    /// Shadow fill light, 0 to 100.
+    pub fill_light: f64,
+    /// Black point, 0 to 100.
+    pub blacks: f64,
+    /// -100 to 100.
+    pub brightness: f64,
+    /// -100 to 100.
+    pub contrast: f64,
+    /// -100 to 100.
+    pub saturation: f64,
+    /// -100 to 100.
+    pub vibrance: f64,
+    /// The tone curve control points, input and output from 0 to 1.
+    /// Empty is the identity.
+    pub tone_curve: Vec<(f64, f64)>,
+    /// The crop. `None` is uncropped.
+    pub crop: Option<CropRect>,
+}
+
+impl Default for DevelopSettings {
+    fn default() -> DevelopSettings {
+        DevelopSettings {
+            version: DEVELOP_SETTINGS_VERSION,
+            tilt: 0.0,
+            // D65, the white point of sRGB.
+            temperature: 6500.0,
+            tint: 0.0,
+            exposure: 0.0,
+            recovery: 0.0,
+            fill_light: 0.0,
+            blacks: 0.0,
+            brightness: 0.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            vibrance: 0.0,
+            tone_curve: vec![],
+            crop: None,
+        }
+    }
+}
+
+impl DevelopSettings {
+    /// The values with their XMP property name.
+    fn values(&self) -> [(&'static str, f64); 11] {
+        [
+            ("Tilt", self.tilt),
+            ("Temperature", self.temperature),
+            ("Tint", self.tint),
+            ("Exposure", self.exposure),
+            ("Recovery", self.recovery),
+            ("FillLight", self.fill_light),
+            ("Blacks", self.blacks),
+            ("Brightness", self.brightness),
+            ("Contrast", self.contrast),
+            ("Saturation", self.saturation),
+            ("Vibrance", self.vibrance),
+        ]
+    }
+
+    fn values_mut(&mut self) -> [(&'static str, &mut f64); 11] {
+        [
+            ("Tilt", &mut self.tilt),
+            ("Temperature", &mut self.temperature),
+            ("Tint", &mut self.tint),
+            ("Exposure", &mut self.exposure),
+            ("Recovery", &mut self.recovery),
+            ("FillLight", &mut self.fill_light),
+            ("Blacks", &mut self.blacks),
+            ("Brightness", &mut self.brightness),
+            ("Contrast", &mut self.contrast),
+            ("Saturation", &mut self.saturation),
+            ("Vibrance", &mut self.vibrance),
+        ]
+    }
+
+    /// Whether nothing is changed from the original.
+    pub fn is_default(&self) -> bool {
+        self.values() == DevelopSettings::default().values()
+            && self.tone_curve.is_empty()
+            && self.crop.is_none()
+    }
+
+    /// The tone curve as a string for the XMP: "x,y" pairs separated
+    /// by spaces.
+    fn tone_curve_to_string(&self) -> String {
+        self.tone_curve
+            .iter()
+            .map(|(x, y)| format!("{x},{y}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Parse the tone curve from the XMP. `None` if invalid.
+    fn tone_curve_from_str(s: &str) -> Option<Vec<(f64, f64)>> {
+        s.split_whitespace()
+            .map(|point| {
+                let (x, y) = point.split_once(',')?;
+                Some((x.parse().ok()?, y.parse().ok()?))
+            })
+            .collect()
+    }
+
+    /// Serialize for storage in the catalog.
+    pub(crate) fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_else(|err| {
+            err_out!("Failed to serialize develop settings: {err}");
+            "{}".to_string()
+        })
+    }
+
+    /// Parse the settings stored in the catalog. The missing values
+    /// are the defaults.
+    pub(crate) fn from_json(s: &str) -> Option<DevelopSettings> {
+        let settings = match serde_json::from_str::<DevelopSettings>(s) {
+            Ok(settings) => settings,
+            Err(err) => {
+                err_out!("Invalid develop settings '{s}': {err}");
+                return None;
+            }
+        };
+        if settings.version > DEVELOP_SETTINGS_VERSION {
+            err_out!(
+                "Develop settings version {} is newer than {}",
+                settings.version,
+                DEVELOP_SETTINGS_VERSION
+            );
+        }
+        Some(settings)
+    }
+
+    /// Write the settings into `xmp`.
+    pub fn to_xmp(&self, xmp: &mut XmpMeta) -> bool {
+        let mut ok = xmp
+            .xmp
+            .set_property_i32(
+                NIEPCE_XMP_NAMESPACE,
+                XMP_VERSION,
+                self.version as i32,
+                exempi2::PropFlags::NONE,
+            )
+            .is_ok();
+        for (name, value) in self.values() {
+            ok &= xmp
+                .xmp
+                .set_property(
+                    NIEPCE_XMP_NAMESPACE,
+                    name,
+                    &value.to_string(),
+                    exempi2::PropFlags::NONE,
+                )
+                .is_ok();
+        }
+        ok &= xmp
+            .xmp
+            .set_property(
+                NIEPCE_XMP_NAMESPACE,
+                XMP_TONE_CURVE,
+                &self.tone_curve_to_string(),
+                exempi2::PropFlags::NONE,
+            )
+            .is_ok();
+        ok &= xmp
+            .xmp
+            .set_property(
+                NIEPCE_XMP_NAMESPACE,
+                XMP_CROP,
+                &self.crop.map(|crop| crop.to_string()).unwrap_or_default(),
+                exempi2::PropFlags::NONE,
+            )
+            .is_ok();
+        if !ok {
+            err_out!("Failed to write the develop settings to the XMP");
+        }
+        ok
+    }
+
+    /// Read the settings from `xmp`. `None` if there are none.
+    pub fn from_xmp(xmp: &XmpMeta) -> Option<DevelopSettings> {
+        let mut flags = exempi2::PropFlags::default();
+        let version = xmp
+            .xmp
+            .get_property_i32(NIEPCE_XMP_NAMESPACE, XMP_VERSION, &mut flags)
+            .ok()?;
+        let mut settings = DevelopSettings {
+            version: version as u32,
+            ..DevelopSettings::default()
+        };
+        for (name, value) in settings.values_mut() {
+            let mut flags = exempi2::PropFlags::default();
+            if let Some(v) = xmp
+                .xmp
+                .get_property(NIEPCE_XMP_NAMESPACE, name, &mut flags)
+                .ok()
+                .and_then(|v| String::from(&v).parse().ok())
+            {
+                *value = v;
+            } else {
+                dbg_out!("No develop value {name} in the XMP");
+            }
+        }
+        let mut flags = exempi2::PropFlags::default();
+        if let Some(curve) = xmp
+            .xmp
+            .get_property(NIEPCE_XMP_NAMESPACE, XMP_TONE_CURVE, &mut flags)
+            .ok()
+            .and_then(|v| DevelopSettings::tone_curve_from_str(&String::from(&v)))
+        {
+            settings.tone_curve = curve;
+        }
+        let mut flags = exempi2::PropFlags::default();
+        settings.crop = xmp
+            .xmp
+            .get_property(NIEPCE_XMP_NAMESPACE, XMP_CROP, &mut flags)
+            .ok()
+            .and_then(|v| String::from(&v).parse().ok());
+        Some(settings)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use npc_fwk::{ExempiManager, XmpMeta};
+
+    use super::{CropRect, DEVELOP_SETTINGS_VERSION, DevelopSettings};
+
+    #[test]
+    fn test_json() {
+        let settings = DevelopSettings {
+            exposure: 0.7,
+            tilt: -1.5,
+            ..DevelopSettings::default()
+        };
+        assert!(DevelopSettings::default().is_default());
+        assert!(!settings.is_default());
+        let curve = DevelopSettings {
+            tone_curve: vec![(0.25, 0.2), (0.75, 0.8)],
+            ..DevelopSettings::default()
+        };
+        assert!(!curve.is_default());
+        assert_eq!(DevelopSettings::from_json(&curve.to_json()), Some(curve));
+
+        let s = settings.to_json();
+        assert_eq!(DevelopSettings::from_json(&s), Some(settings));
+
+        // Missing values are the defaults.
+        let settings =
+            DevelopSettings::from_json(r#"{"version": 1, "contrast": 20.0}"#).expect("parse");
+        assert_eq!(settings.contrast, 20.0);
+        assert_eq!(settings.temperature, 6500.0);
+        assert!(DevelopSettings::from_json("garbage").is_none());
+    }
+
+    #[test]
+    fn test_xmp() {
+        let _ = ExempiManager::new(None);
+
+        let mut xmp = XmpMeta::new();
+        assert!(DevelopSettings::from_xmp(&xmp).is_none());
+
+        let settings = DevelopSettings {
+            temperature: 5200.0,
+            exposure: -0.3,
+            vibrance: 12.0,
+            tone_curve: vec![(0.0, 0.1), (0.5, 0.6), (1.0, 1.0)],
+            crop: Some(CropRect {
+                x: 0.1,
+                y: 0.2,
+                w: 0.5,
+                h: 0.25,
+            }),
+            ..DevelopSettings::default()
+        };
+        assert!(settings.to_xmp(&mut xmp));
+        let read = DevelopSettings::from_xmp(&xmp).expect("No develop settings");
+        assert_eq!(read.version, DEVELOP_SETTINGS_VERSION);
+        assert_eq!(read, settings);
+
+        assert_eq!(
+            DevelopSettings::tone_curve_from_str("0,0 0.5,0.7"),
+            Some(vec![(0.0, 0.0), (0.5, 0.7)])
+        );
+        assert_eq!(DevelopSettings::tone_curve_from_str(""), Some(vec![]));
+        assert!(DevelopSettings::tone_curve_from_str("0.5").is_none());
+    }
+
+    #[test]
+    fn test_crop() {
+        let crop = CropRect {
+            x: 0.25,
+            y: 0.5,
+            w: 0.5,
+            h: 0.25,
+        };
+        assert_eq!(crop.to_string().parse::<CropRect>(), Ok(crop));
+        assert!("0.1,0.2".parse::<CropRect>().is_err());
+        assert!("".parse::<CropRect>().is_err());
+        assert_eq!(crop.to_pixels(400, 200), (100, 100, 200, 50));
+        assert!(!crop.is_full());
+
+        let crop = CropRect {
+            x: -0.1,
+            y: 0.5,
+            w: 1.5,
+            h: 0.25,
+        };
+        let clamped = crop.clamped().expect("Empty crop");
+        assert_eq!(clamped.x, 0.0);
+        assert_eq!(clamped.w, 1.0);
+        assert!(!clamped.is_full());
+        // Past the top left corner.
+        assert_eq!(
+            CropRect {
+                x: -0.25,
+                y: -0.5,
+                w: 0.5,
+                h: 0.75,
+            }
+            .clamped(),
+            Some(CropRect {
+                x: 0.0,
+                y: 0.0,
+                w: 0.25,
+                h: 0.25,
+            })
+        );
+        assert!(
+            CropRect {
+                x: -0.5,
+                y: 0.0,
+                w: 0.5,
+                h: 0.5
+            }
+            .clamped()
+            .is_none()
+        );
+        assert!(
+            CropRect {
+                x: 1.0,
+                y: 0.0,
+                w: 0.5,
+                h: 0.5
+            }
+            .clamped()
+            .is_none()
+        );
+    }
+}
@@ -65,7 +65,7 @@ impl From<(i32, PathBuf)> for Sidecar {
 
 /// FileBundle is a set of physical files group as one item.
 /// Mostly sticking to the DCF specification.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct FileBundle {
     /// Type of bundle
     bundle_type: FileType,
@@ -0,0 +1,92 @@
+/*
+ * niepce - npc-engine/catalog/fingerprint.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Content fingerprint of the files, to detect duplicates.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use md5::Digest;
+
+/// Size of the chunks hashed at the start and the end of the file.
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// Compute the fingerprint of the file at `path`. This is a hash of
+/// the size and of the chunks at the start and the end of the file,
+/// as hashing whole RAW files is too slow. It is meant to be used
+/// along with the size and the capture date. Return `None` if the
+/// file can't be read.
+pub fn fingerprint<P: AsRef<Path>>(path: P) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+
+    let mut hasher = md5::Md5::new();
+    hasher.update(size.to_le_bytes());
+    let mut buffer = vec![];
+    (&mut file).take(CHUNK_SIZE).read_to_end(&mut buffer).ok()?;
+    hasher.update(&buffer);
+    if size > CHUNK_SIZE * 2 {
+        buffer.clear();
+        file.seek(SeekFrom::End(-(CHUNK_SIZE as i64))).ok()?;
+        file.read_to_end(&mut buffer).ok()?;
+        hasher.update(&buffer);
+    } else if size > CHUNK_SIZE {
+        buffer.clear();
+        file.read_to_end(&mut buffer).ok()?;
+        hasher.update(&buffer);
+    }
+
+    Some(
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CHUNK_SIZE, fingerprint};
+
+    #[test]
+    fn test_fingerprint() {
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+
+        let content = (0..CHUNK_SIZE * 3).map(|i| i as u8).collect::<Vec<u8>>();
+        std::fs::write(dir.join("file1"), &content).expect("Couldn't write file");
+        std::fs::write(dir.join("file2"), &content).expect("Couldn't write file");
+        let mut changed = content.clone();
+        if let Some(last) = changed.last_mut() {
+            *last = last.wrapping_add(1);
+        }
+        std::fs::write(dir.join("file3"), &changed).expect("Couldn't write file");
+        std::fs::write(dir.join("file4"), "small").expect("Couldn't write file");
+
+        let fingerprint1 = fingerprint(dir.join("file1"));
+        assert!(fingerprint1.is_some());
+        assert_eq!(fingerprint1.as_ref().map(String::len), Some(32));
+        assert_eq!(fingerprint(dir.join("file2")), fingerprint1);
+        assert_ne!(fingerprint(dir.join("file3")), fingerprint1);
+        assert!(fingerprint(dir.join("file4")).is_some());
+        assert!(fingerprint(dir.join("nonexistent")).is_none());
+    }
+}
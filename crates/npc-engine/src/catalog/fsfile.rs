@@ -26,11 +26,21 @@ use super::LibraryId;
 pub struct FsFile {
     id: LibraryId,
     path: PathBuf,
+    /// Size in bytes, if known.
+    size: Option<u64>,
 }
 
 impl FsFile {
     pub fn new(id: LibraryId, path: PathBuf) -> FsFile {
-        FsFile { id, path }
+        FsFile {
+            id,
+            path,
+            size: None,
+        }
+    }
+
+    pub fn with_size(id: LibraryId, path: PathBuf, size: Option<u64>) -> FsFile {
+        FsFile { id, path, size }
     }
 
     pub fn id(&self) -> LibraryId {
@@ -40,4 +50,9 @@ impl FsFile {
     pub fn path(&self) -> &Path {
         self.path.as_path()
     }
+
+    /// The size of the file when it was added, if known.
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
 }
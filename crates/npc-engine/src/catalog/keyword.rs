@@ -1,7 +1,7 @@
 /*
  * niepce - engine/db/keyword.rs
  *
- * Copyright (C) 2017-2022 Hubert Figuière
+ * Copyright (C) 2017-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -17,6 +17,22 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+//! Keywords. They are hierarchical, and can be imported and exported
+//! in the Lightroom keyword text format.
+//!
+//! In that format, there is one keyword per line, and the hierarchy is
+//! indicated by tab indentation. A keyword in `[]` isn't exported, and
+//! `{}` indicate a synonym of the parent keyword. In the example below
+//! `\t` is a tab.
+//!
+//! ```text
+//! Places
+//! \tEurope
+//! \t\tFrance
+//! \t\t\t{Frankreich}
+//! [Private]
+//! ```
+
 use super::FromDb;
 use super::LibraryId;
 
@@ -24,13 +40,19 @@ use super::LibraryId;
 pub struct Keyword {
     id: LibraryId,
     keyword: String,
+    /// Parent keyword. 0 is top level.
+    parent: LibraryId,
+    /// Whether the keyword is exported to XMP.
+    exportable: bool,
 }
 
 impl Keyword {
-    pub fn new(id: LibraryId, keyword: &str) -> Keyword {
+    pub fn new(id: LibraryId, keyword: &str, parent: LibraryId) -> Keyword {
         Keyword {
             id,
             keyword: String::from(keyword),
+            parent,
+            exportable: true,
         }
     }
 
@@ -41,11 +63,19 @@ impl Keyword {
     pub fn keyword(&self) -> &str {
         &self.keyword
     }
+
+    pub fn parent(&self) -> LibraryId {
+        self.parent
+    }
+
+    pub fn exportable(&self) -> bool {
+        self.exportable
+    }
 }
 
 impl FromDb for Keyword {
     fn read_db_columns() -> &'static str {
-        "id,keyword"
+        "id,keyword,parent_id,export"
     }
 
     fn read_db_tables() -> &'static str {
@@ -58,6 +88,141 @@ impl FromDb for Keyword {
 
     fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
         let kw: String = row.get(1)?;
-        Ok(Keyword::new(row.get(0)?, &kw))
+        let mut keyword = Keyword::new(row.get(0)?, &kw, row.get(2)?);
+        keyword.exportable = row.get(3)?;
+        Ok(keyword)
+    }
+}
+
+/// A node in a keyword tree, for import and export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeywordNode {
+    pub name: String,
+    pub synonyms: Vec<String>,
+    pub exportable: bool,
+    pub children: Vec<KeywordNode>,
+}
+
+impl KeywordNode {
+    pub fn new(name: &str) -> KeywordNode {
+        KeywordNode {
+            name: name.to_string(),
+            synonyms: vec![],
+            exportable: true,
+            children: vec![],
+        }
+    }
+}
+
+/// Parse the Lightroom keyword text format into a keyword tree.
+/// Invalid lines are ignored.
+pub fn parse_lightroom_keywords(text: &str) -> Vec<KeywordNode> {
+    let mut roots: Vec<KeywordNode> = vec![];
+    // The current branch, one node per level.
+    let mut branch: Vec<KeywordNode> = vec![];
+
+    fn close_to(depth: usize, branch: &mut Vec<KeywordNode>, roots: &mut Vec<KeywordNode>) {
+        while branch.len() > depth {
+            let node = branch.pop().unwrap();
+            if let Some(parent) = branch.last_mut() {
+                parent.children.push(node);
+            } else {
+                roots.push(node);
+            }
+        }
+    }
+
+    for line in text.lines() {
+        let depth = line.chars().take_while(|c| *c == '\t').count();
+        let content = line.trim();
+        if content.is_empty() {
+            continue;
+        }
+        if let Some(synonym) = content
+            .strip_prefix('{')
+            .and_then(|content| content.strip_suffix('}'))
+        {
+            if depth == 0 || depth > branch.len() {
+                continue;
+            }
+            close_to(depth, &mut branch, &mut roots);
+            if let Some(node) = branch.last_mut() {
+                node.synonyms.push(synonym.to_string());
+            }
+            continue;
+        }
+        let (name, exportable) = match content
+            .strip_prefix('[')
+            .and_then(|content| content.strip_suffix(']'))
+        {
+            Some(name) => (name, false),
+            None => (content, true),
+        };
+        close_to(depth.min(branch.len()), &mut branch, &mut roots);
+        let mut node = KeywordNode::new(name);
+        node.exportable = exportable;
+        branch.push(node);
+    }
+    close_to(0, &mut branch, &mut roots);
+
+    roots
+}
+
+/// Write the keyword tree in the Lightroom keyword text format.
+pub fn write_lightroom_keywords(nodes: &[KeywordNode]) -> String {
+    fn write_nodes(nodes: &[KeywordNode], depth: usize, out: &mut String) {
+        for node in nodes {
+            let indent = "\t".repeat(depth);
+            if node.exportable {
+                out.push_str(&format!("{indent}{}\n", node.name));
+            } else {
+                out.push_str(&format!("{indent}[{}]\n", node.name));
+            }
+            for synonym in &node.synonyms {
+                out.push_str(&format!("{indent}\t{{{synonym}}}\n"));
+            }
+            write_nodes(&node.children, depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    write_nodes(nodes, 0, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeywordNode, parse_lightroom_keywords, write_lightroom_keywords};
+
+    #[test]
+    fn test_lightroom_keywords() {
+        let text = "Places\n\
+                    \tEurope\n\
+                    \t\tFrance\n\
+                    \t\t\t{Frankreich}\n\
+                    \t\tSpain\n\
+                    [Private]\n\
+                    \tFamily\n";
+        let tree = parse_lightroom_keywords(text);
+
+        let mut france = KeywordNode::new("France");
+        france.synonyms.push("Frankreich".into());
+        let mut europe = KeywordNode::new("Europe");
+        europe.children = vec![france, KeywordNode::new("Spain")];
+        let mut places = KeywordNode::new("Places");
+        places.children = vec![europe];
+        let mut private = KeywordNode::new("Private");
+        private.exportable = false;
+        private.children = vec![KeywordNode::new("Family")];
+        assert_eq!(tree, vec![places, private]);
+
+        assert_eq!(write_lightroom_keywords(&tree), text);
+
+        // Skipped levels are attached to the last parent, orphan
+        // synonyms are ignored.
+        let tree = parse_lightroom_keywords("{orphan}\nA\n\t\t\tB\r\n\n");
+        let mut a = KeywordNode::new("A");
+        a.children = vec![KeywordNode::new("B")];
+        assert_eq!(tree, vec![a]);
     }
 }
@@ -107,6 +107,16 @@ pub struct LibFile {
     label: i32,
     flag: i32,
     file_type: FileType,
+    /// The master if this is a virtual copy. 0 otherwise.
+    master_id: LibraryId,
+    /// Number of virtual copies, if this is a master.
+    copy_count: u32,
+    /// The stack the file belongs to. 0 if none.
+    stack_id: LibraryId,
+    /// Whether this is the top of its stack.
+    stack_top: bool,
+    /// The number of files in the stack.
+    stack_count: u32,
     pub metadata: Option<LibMetadata>,
 }
 
@@ -129,6 +139,11 @@ impl LibFile {
             label: 0,
             flag: 0,
             file_type: FileType::Unknown,
+            master_id: 0,
+            copy_count: 0,
+            stack_id: 0,
+            stack_top: false,
+            stack_count: 0,
             metadata: None,
         }
     }
@@ -193,6 +208,40 @@ impl LibFile {
         self.file_type = ft;
     }
 
+    /// The id of the master file if this is a virtual copy, 0 otherwise.
+    pub fn master_id(&self) -> LibraryId {
+        self.master_id
+    }
+
+    /// Whether this is a virtual copy.
+    pub fn is_copy(&self) -> bool {
+        self.master_id > 0
+    }
+
+    /// The number of virtual copies of this master.
+    pub fn copy_count(&self) -> u32 {
+        self.copy_count
+    }
+
+    pub fn set_copy_count(&mut self, count: u32) {
+        self.copy_count = count;
+    }
+
+    /// The id of the stack, 0 if not stacked.
+    pub fn stack_id(&self) -> LibraryId {
+        self.stack_id
+    }
+
+    /// Whether this is the top of its stack.
+    pub fn is_stack_top(&self) -> bool {
+        self.stack_id > 0 && self.stack_top
+    }
+
+    /// The number of files in the stack.
+    pub fn stack_count(&self) -> u32 {
+        self.stack_count
+    }
+
     pub fn property(&self, idx: Np) -> i32 {
         match idx {
             Np::Index(Npi::NpTiffOrientationProp) => self.orientation() as i32,
@@ -224,7 +273,12 @@ impl LibFile {
 impl FromDb for LibFile {
     fn read_db_columns() -> &'static str {
         "files.id,parent_id,fsfiles.path,\
-         name,orientation,rating,label,file_type,fsfiles.id,flag"
+         name,orientation,rating,label,file_type,fsfiles.id,flag,files.master_id,\
+         (SELECT COUNT(*) FROM files AS copies WHERE copies.master_id = files.id),\
+         files.stack_id,\
+         (SELECT top_id FROM stacks WHERE stacks.id = files.stack_id) = files.id,\
+         (SELECT COUNT(*) FROM files AS stacked \
+         WHERE files.stack_id > 0 AND stacked.stack_id = files.stack_id)"
     }
 
     fn read_db_tables() -> &'static str {
@@ -236,7 +290,7 @@ impl FromDb for LibFile {
     }
 
     fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
-        //DBG_ASSERT(dbdrv->get_number_of_columns() == 10, "wrong number of columns");
+        //DBG_ASSERT(dbdrv->get_number_of_columns() == 15, "wrong number of columns");
         let id = row.get(0)?;
         let fid = row.get(1)?;
         let path: String = row.get(2)?;
@@ -250,6 +304,11 @@ impl FromDb for LibFile {
         file.set_flag(row.get(9)?);
         let file_type: i32 = row.get(7)?;
         file.set_file_type(FileType::from(file_type));
+        file.master_id = row.get(10)?;
+        file.copy_count = row.get(11)?;
+        file.stack_id = row.get(12)?;
+        file.stack_top = row.get::<_, Option<bool>>(13)?.unwrap_or(false);
+        file.stack_count = row.get(14)?;
 
         Ok(file)
     }
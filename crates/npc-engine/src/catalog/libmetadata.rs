@@ -18,11 +18,12 @@
  */
 
 use super::NiepceProperties as Np;
+use super::develop::DevelopSettings;
 use super::libfile::FileType;
 use super::props;
 use super::{FromDb, LibraryId};
 use crate::NiepcePropertyBag;
-use npc_fwk::utils::exempi::{NS_DC, NS_XAP};
+use npc_fwk::utils::exempi::NS_XAP;
 use npc_fwk::{DateExt, PropertySet, PropertyValue, XmpMeta};
 use npc_fwk::{dbg_out, err_out};
 
@@ -39,6 +40,8 @@ pub struct LibMetadata {
     pub name: String,
     /// Folder name for display
     pub folder: String,
+    /// The develop settings stored in the catalog.
+    pub develop: Option<DevelopSettings>,
 }
 
 struct IndexToXmp {
@@ -62,6 +65,7 @@ impl LibMetadata {
             file_type: FileType::Unknown,
             name: String::new(),
             folder: String::new(),
+            develop: None,
         }
     }
 
@@ -74,6 +78,7 @@ impl LibMetadata {
             file_type: FileType::Unknown,
             name: String::new(),
             folder: String::new(),
+            develop: None,
         }
     }
 
@@ -81,6 +86,21 @@ impl LibMetadata {
         self.id
     }
 
+    /// The develop settings. Those stored in the catalog, or else
+    /// those from the XMP, or else the defaults.
+    pub fn develop_settings(&self) -> DevelopSettings {
+        self.develop
+            .clone()
+            .or_else(|| DevelopSettings::from_xmp(&self.xmp_meta))
+            .unwrap_or_default()
+    }
+
+    /// Set the develop settings, in the XMP too.
+    pub fn set_develop_settings(&mut self, settings: &DevelopSettings) -> bool {
+        self.develop = Some(settings.clone());
+        settings.to_xmp(&mut self.xmp_meta)
+    }
+
     pub fn serialize_inline(&self) -> String {
         self.xmp_meta.serialize_inline()
     }
@@ -108,6 +128,29 @@ impl LibMetadata {
     }
 
     pub fn set_metadata(&mut self, meta: Np, value: &PropertyValue) -> bool {
+        use super::NiepcePropertyIdx as Npi;
+
+        // Keywords are paths, that go to both `dc:subject` and
+        // `lr:hierarchicalSubject`.
+        if let (Np::Index(Npi::NpIptcKeywordsProp), PropertyValue::StringArray(paths)) =
+            (meta, value)
+        {
+            if let Err(err) = self.xmp_meta.set_keyword_paths(paths) {
+                err_out!("Error setting keywords: {err}");
+                return false;
+            }
+            return true;
+        }
+        // `dc:creator` is an ordered array.
+        if let (Np::Index(Npi::NpDcCreatorProp), PropertyValue::StringArray(creators)) =
+            (meta, value)
+        {
+            if let Err(err) = self.xmp_meta.set_creators(creators) {
+                err_out!("Error setting creators: {err}");
+                return false;
+            }
+            return true;
+        }
         if let Some(ix) = property_index_to_xmp(meta) {
             match *value {
                 PropertyValue::Empty => {
@@ -231,18 +274,15 @@ impl LibMetadata {
                     }
                 }
                 Np::Index(Npi::NpIptcKeywordsProp) => {
-                    let iter = exempi2::XmpIterator::new(
-                        &self.xmp_meta.xmp,
-                        NS_DC,
-                        "subject",
-                        exempi2::IterFlags::JUST_LEAF_NODES,
-                    );
-                    let mut keywords: Vec<String> = vec![];
-                    for v in iter {
-                        keywords.push(String::from(&v.value));
-                    }
+                    let keywords = self.xmp_meta.keyword_paths();
                     props.set_value(*prop_id, PropertyValue::StringArray(keywords));
                 }
+                Np::Index(Npi::NpDcCreatorProp) => {
+                    let creators = self.xmp_meta.creators();
+                    if !creators.is_empty() {
+                        props.set_value(*prop_id, PropertyValue::StringArray(creators));
+                    }
+                }
                 Np::Index(Npi::NpFileNameProp) => {
                     props.set_value(*prop_id, PropertyValue::String(self.name.clone()));
                 }
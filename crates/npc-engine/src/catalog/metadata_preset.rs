@@ -0,0 +1,197 @@
+/*
+ * niepce - npc-engine/catalog/metadata_preset.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Metadata presets: named sets of metadata applied to the files at
+//! import.
+
+use super::FromDb;
+use super::LibraryId;
+use super::NiepcePropertyIdx as Npi;
+use super::props::NiepceProperties as Np;
+use crate::library::journal::JournalValue;
+use npc_fwk::{PropertyValue, err_out};
+
+/// The properties a preset can set.
+pub const PRESET_PROPERTIES: [Npi; 11] = [
+    Npi::NpDcCreatorProp,
+    Npi::NpDcRightsProp,
+    Npi::NpIptcCreditProp,
+    Npi::NpIptcSourceProp,
+    Npi::NpIptcHeadlineProp,
+    Npi::NpIptcDescriptionProp,
+    Npi::NpIptcKeywordsProp,
+    Npi::NpIptcCityProp,
+    Npi::NpIptcStateProp,
+    Npi::NpIptcCountryProp,
+    Npi::NpXmpLabelProp,
+];
+
+/// A metadata preset.
+#[derive(Clone, Debug)]
+pub struct MetadataPreset {
+    /// Preset ID. 0 if not in the catalog.
+    id: LibraryId,
+    /// Preset name as displayed
+    name: String,
+    /// The properties to set. The label is a `labels.id` and the
+    /// keywords are paths.
+    properties: Vec<(Np, PropertyValue)>,
+}
+
+impl MetadataPreset {
+    pub fn new(id: LibraryId, name: &str, properties: Vec<(Np, PropertyValue)>) -> Self {
+        MetadataPreset {
+            id,
+            name: name.to_owned(),
+            properties,
+        }
+    }
+
+    pub fn id(&self) -> LibraryId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn properties(&self) -> &[(Np, PropertyValue)] {
+        &self.properties
+    }
+
+    /// Set the property `meta` to `value`. An empty value removes it.
+    pub fn set_property(&mut self, meta: Np, value: PropertyValue) {
+        self.properties.retain(|(m, _)| *m != meta);
+        if !is_empty_value(&value) {
+            self.properties.push((meta, value));
+        }
+    }
+
+    /// Whether the preset doesn't set anything.
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    /// Serialize the properties for storage.
+    pub(crate) fn properties_to_string(properties: &[(Np, PropertyValue)]) -> String {
+        let properties = properties
+            .iter()
+            .map(|(meta, value)| (u32::from(*meta), JournalValue::from(value)))
+            .collect::<Vec<_>>();
+        serde_json::to_string(&properties).unwrap_or_else(|err| {
+            err_out!("Failed to serialize preset properties: {err}");
+            "[]".to_string()
+        })
+    }
+
+    /// Parse the stored properties. Those that aren't allowed in a
+    /// preset are ignored.
+    pub(crate) fn properties_from_string(s: &str) -> Vec<(Np, PropertyValue)> {
+        serde_json::from_str::<Vec<(u32, JournalValue)>>(s)
+            .unwrap_or_else(|err| {
+                err_out!("Invalid preset properties '{s}': {err}");
+                vec![]
+            })
+            .iter()
+            .filter_map(|(meta, value)| match Np::from(*meta) {
+                Np::Index(idx) if PRESET_PROPERTIES.contains(&idx) => {
+                    Some((Np::Index(idx), PropertyValue::from(value)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Whether `value` is empty, and wouldn't change anything.
+fn is_empty_value(value: &PropertyValue) -> bool {
+    match value {
+        PropertyValue::Empty => true,
+        PropertyValue::String(s) => s.is_empty(),
+        PropertyValue::StringArray(sa) => sa.is_empty(),
+        PropertyValue::Int(_) | PropertyValue::Date(_) => false,
+    }
+}
+
+impl FromDb for MetadataPreset {
+    fn read_db_columns() -> &'static str {
+        "id,name,properties"
+    }
+
+    fn read_db_tables() -> &'static str {
+        "metadata_presets"
+    }
+
+    fn read_db_where_id() -> &'static str {
+        "id"
+    }
+
+    fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let name: String = row.get(1)?;
+        let properties: String = row.get(2)?;
+        Ok(MetadataPreset::new(
+            row.get(0)?,
+            &name,
+            MetadataPreset::properties_from_string(&properties),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{MetadataPreset, Np, Npi};
+    use npc_fwk::PropertyValue;
+
+    #[test]
+    fn test_properties_roundtrip() {
+        let mut preset = MetadataPreset::new(0, "Job", vec![]);
+        assert!(preset.is_empty());
+        preset.set_property(
+            Np::Index(Npi::NpDcRightsProp),
+            PropertyValue::String("© 2025 Me".into()),
+        );
+        preset.set_property(
+            Np::Index(Npi::NpIptcKeywordsProp),
+            PropertyValue::StringArray(vec!["Places|Ottawa".into()]),
+        );
+        preset.set_property(Np::Index(Npi::NpXmpLabelProp), PropertyValue::Int(2));
+        // Empty values are removed.
+        preset.set_property(
+            Np::Index(Npi::NpIptcCityProp),
+            PropertyValue::String("Ottawa".into()),
+        );
+        preset.set_property(
+            Np::Index(Npi::NpIptcCityProp),
+            PropertyValue::String(String::new()),
+        );
+        assert_eq!(preset.properties().len(), 3);
+
+        let s = MetadataPreset::properties_to_string(preset.properties());
+        let properties = MetadataPreset::properties_from_string(&s);
+        assert_eq!(properties.len(), 3);
+        assert_eq!(properties[0].0, Np::Index(Npi::NpDcRightsProp));
+        assert!(matches!(&properties[0].1, PropertyValue::String(s) if s == "© 2025 Me"));
+        assert!(matches!(properties[2].1, PropertyValue::Int(2)));
+
+        // Not a preset property.
+        let s = format!("[[{}, {{\"Int\": 5}}]]", Npi::NpXmpRatingProp as u32);
+        assert!(MetadataPreset::properties_from_string(&s).is_empty());
+        assert!(MetadataPreset::properties_from_string("garbage").is_empty());
+    }
+}
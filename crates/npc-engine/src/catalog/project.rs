@@ -0,0 +1,123 @@
+/*
+ * niepce - npc-engine/catalog/project.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Projects: group albums, folders, files and outputs for one job.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::FromDb;
+use super::LibraryId;
+
+/// State of an image within a project.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ProjectState {
+    #[default]
+    None = 0,
+    Picked = 1,
+    Rejected = 2,
+    Delivered = 3,
+}
+
+impl From<i32> for ProjectState {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => Self::Picked,
+            2 => Self::Rejected,
+            3 => Self::Delivered,
+            _ => Self::None,
+        }
+    }
+}
+
+impl From<ProjectState> for i32 {
+    fn from(v: ProjectState) -> i32 {
+        v as i32
+    }
+}
+
+/// An item attached to a project, beside the files.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProjectItem {
+    /// An album (`albums.id`)
+    Album(LibraryId),
+    /// A folder (`folders.id`)
+    Folder(LibraryId),
+    /// A derived output, on the filesystem.
+    Output(PathBuf),
+}
+
+impl ProjectItem {
+    /// The value stored in `project_items.item_type`.
+    pub(crate) fn item_type(&self) -> i32 {
+        match self {
+            Self::Album(_) => 1,
+            Self::Folder(_) => 2,
+            Self::Output(_) => 3,
+        }
+    }
+}
+
+/// A project. A named collection of files and items for one job.
+#[derive(Clone, Debug)]
+pub struct Project {
+    /// Project ID
+    id: LibraryId,
+    /// Project name as displayed
+    name: String,
+}
+
+impl Project {
+    pub fn new(id: LibraryId, name: &str) -> Self {
+        Project {
+            id,
+            name: name.to_owned(),
+        }
+    }
+
+    /// Get the project ID
+    pub fn id(&self) -> LibraryId {
+        self.id
+    }
+
+    /// Get the project name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl FromDb for Project {
+    fn read_db_columns() -> &'static str {
+        "id,name"
+    }
+
+    fn read_db_tables() -> &'static str {
+        "projects"
+    }
+
+    fn read_db_where_id() -> &'static str {
+        "id"
+    }
+
+    fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let name: String = row.get(1)?;
+        Ok(Project::new(row.get(0)?, &name))
+    }
+}
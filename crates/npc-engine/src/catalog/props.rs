@@ -57,6 +57,13 @@ pub enum NiepcePropertyIdx {
     NpNiepceFlagProp,
     NpNiepceRenderEngineProp,
     NpNiepceXmpPacket,
+    NpDcCreatorProp,
+    NpDcRightsProp,
+    NpIptcCreditProp,
+    NpIptcSourceProp,
+    NpIptcCityProp,
+    NpIptcStateProp,
+    NpIptcCountryProp,
     // Always keep this last.
     _NpPropertyEnd,
 }
@@ -112,5 +119,12 @@ lazy_static! {
     NiepceProperties::Index(NiepcePropertyIdx::NpIptcKeywordsProp) => (NS_DC, "subject"),
     NiepceProperties::Index(NiepcePropertyIdx::NpNiepceFlagProp) => (xmp::NIEPCE_XMP_NAMESPACE, "Flag"),
     NiepceProperties::Index(NiepcePropertyIdx::NpNiepceRenderEngineProp) => (xmp::NIEPCE_XMP_NAMESPACE, "RenderEngine"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpDcCreatorProp) => (NS_DC, "creator"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpDcRightsProp) => (NS_DC, "rights"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpIptcCreditProp) => (NS_PHOTOSHOP, "Credit"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpIptcSourceProp) => (NS_PHOTOSHOP, "Source"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpIptcCityProp) => (NS_PHOTOSHOP, "City"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpIptcStateProp) => (NS_PHOTOSHOP, "State"),
+    NiepceProperties::Index(NiepcePropertyIdx::NpIptcCountryProp) => (NS_PHOTOSHOP, "Country"),
     };
 }
@@ -0,0 +1,354 @@
+/*
+ * niepce - npc-engine/catalog/search.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Search query language.
+//!
+//! A query is a list of terms separated by spaces. All the terms must
+//! match. Values can be quoted with `"`.
+//!
+//! | Term                    | Description                                       |
+//! |-------------------------|---------------------------------------------------|
+//! | `rating:>=4`            | Rating comparison. `rating:4` is equality.        |
+//! | `keyword:"beach"`       | Has the keyword.                                  |
+//! | `model:"X-T4"`          | Camera model.                                     |
+//! | `lens:"XF23mmF2 R WR"`  | Lens.                                             |
+//! | `label:"Red"`           | Has the label with that name.                     |
+//! | `flag:picked`           | Flag: `picked`, `rejected`, `none` or a number.   |
+//! | `date:2023-06..2023-08` | Date range. `YYYY`, `YYYY-MM` or `YYYY-MM-DD`.    |
+//! | `name:DSC*`             | File name, with `*` and `?` wildcards.            |
+//! | anything else           | Full text search in name, headline, description and keywords. |
+
+use chrono::{Datelike, NaiveDate};
+use rusqlite::types::Value;
+use thiserror::Error;
+
+use super::smart_collection::{Comparison, SmartRule};
+
+/// Error parsing a query.
+#[derive(Error, Debug, PartialEq)]
+pub enum QueryError {
+    /// Unterminated quote
+    #[error("Unterminated quote")]
+    UnterminatedQuote,
+    /// Unknown field
+    #[error("Unknown field {0}")]
+    UnknownField(String),
+    /// Invalid value for field
+    #[error("Invalid value '{1}' for {0}")]
+    InvalidValue(String, String),
+}
+
+/// A search term.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchTerm {
+    /// A rule, like for smart collections.
+    Rule(SmartRule),
+    /// Has the keyword by name.
+    Keyword(String),
+    /// Has the label by name.
+    Label(String),
+    /// File name pattern, with `*` and `?` wildcards.
+    Name(String),
+    /// Full text.
+    Text(String),
+}
+
+impl SearchTerm {
+    fn to_sql(&self) -> (String, Vec<Value>) {
+        match self {
+            Self::Rule(rule) => rule.to_sql(),
+            Self::Keyword(keyword) => (
+                "files.id IN (SELECT file_id FROM keywording, keywords \
+                 WHERE keywording.keyword_id = keywords.id AND keywords.keyword = ? COLLATE NOCASE)"
+                    .to_string(),
+                vec![Value::Text(keyword.clone())],
+            ),
+            Self::Label(label) => (
+                "files.label IN (SELECT id FROM labels WHERE name = ? COLLATE NOCASE)".to_string(),
+                vec![Value::Text(label.clone())],
+            ),
+            Self::Name(pattern) => {
+                let pattern = pattern
+                    .replace('\\', "\\\\")
+                    .replace('%', "\\%")
+                    .replace('_', "\\_")
+                    .replace('*', "%")
+                    .replace('?', "_");
+                (
+                    "files.name LIKE ? ESCAPE '\\'".to_string(),
+                    vec![Value::Text(pattern)],
+                )
+            }
+            Self::Text(text) => (
+                "files.id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?)".to_string(),
+                vec![Value::Text(fts_phrase(text))],
+            ),
+        }
+    }
+}
+
+/// Make a FTS5 prefix phrase out of `text`.
+fn fts_phrase(text: &str) -> String {
+    format!("\"{}\"*", text.replace('"', "\"\""))
+}
+
+/// A parsed search query.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<SearchTerm>,
+}
+
+impl SearchQuery {
+    pub fn terms(&self) -> &[SearchTerm] {
+        &self.terms
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Return the SQL where clause and parameters for the query.
+    /// An empty query matches nothing.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        if self.terms.is_empty() {
+            return ("0".to_string(), vec![]);
+        }
+        let mut params = vec![];
+        let clauses = self
+            .terms
+            .iter()
+            .map(|term| {
+                let (clause, mut p) = term.to_sql();
+                params.append(&mut p);
+                format!("({clause})")
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        (format!("({clauses})"), params)
+    }
+
+    /// Parse a term `field:value`.
+    fn parse_field(field: &str, value: String) -> Result<SearchTerm, QueryError> {
+        let invalid = |value: &str| QueryError::InvalidValue(field.to_string(), value.to_string());
+        match field {
+            "rating" => {
+                let split = value
+                    .find(|c: char| c.is_ascii_digit() || c == '-')
+                    .unwrap_or(value.len());
+                let (cmp, rating) = value.split_at(split);
+                let cmp = if cmp.is_empty() {
+                    Comparison::Equal
+                } else {
+                    cmp.parse().map_err(|_| invalid(&value))?
+                };
+                let rating = rating.parse().map_err(|_| invalid(&value))?;
+                Ok(SearchTerm::Rule(SmartRule::Rating(cmp, rating)))
+            }
+            "flag" => {
+                let flag = match value.as_str() {
+                    "picked" | "pick" => 1,
+                    "rejected" | "reject" => -1,
+                    "none" => 0,
+                    _ => value.parse().map_err(|_| invalid(&value))?,
+                };
+                Ok(SearchTerm::Rule(SmartRule::Flag(flag)))
+            }
+            "keyword" => Ok(SearchTerm::Keyword(value)),
+            "label" => Ok(SearchTerm::Label(value)),
+            "model" => Ok(SearchTerm::Rule(SmartRule::CameraModel(value))),
+            "lens" => Ok(SearchTerm::Rule(SmartRule::Lens(value))),
+            "name" => Ok(SearchTerm::Name(value)),
+            "date" => {
+                let (start, end) = value.split_once("..").unwrap_or((&value, &value));
+                let start = parse_date(start, false).ok_or_else(|| invalid(&value))?;
+                let end = parse_date(end, true).ok_or_else(|| invalid(&value))?;
+                Ok(SearchTerm::Rule(SmartRule::DateRange(start, end)))
+            }
+            _ => Err(QueryError::UnknownField(field.to_string())),
+        }
+    }
+}
+
+/// Parse a partial date `YYYY`, `YYYY-MM` or `YYYY-MM-DD` into a
+/// timestamp (UTC). If `end` is true, the timestamp is the last second
+/// of the period.
+fn parse_date(s: &str, end: bool) -> Option<i64> {
+    let mut parts = s.trim().split('-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let month: Option<u32> = match parts.next() {
+        Some(m) => Some(m.parse().ok()?),
+        None => None,
+    };
+    let day: Option<u32> = match parts.next() {
+        Some(d) => Some(d.parse().ok()?),
+        None => None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let first = NaiveDate::from_ymd_opt(year, month.unwrap_or(1), day.unwrap_or(1))?;
+    let date = if !end || day.is_some() {
+        first
+    } else if month.is_some() {
+        if first.month() == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, first.month() + 1, 1)?
+        }
+        .pred_opt()?
+    } else {
+        NaiveDate::from_ymd_opt(year, 12, 31)?
+    };
+    let time = if end {
+        date.and_hms_opt(23, 59, 59)?
+    } else {
+        date.and_hms_opt(0, 0, 0)?
+    };
+    Some(time.and_utc().timestamp())
+}
+
+/// Split the query into tokens, honouring the quotes. Quotes are removed.
+fn tokenize(s: &str) -> Result<Vec<String>, QueryError> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut in_quote = false;
+    let mut has_token = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                in_quote = !in_quote;
+                has_token = true;
+            }
+            c if c.is_whitespace() && !in_quote => {
+                if has_token {
+                    tokens.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            _ => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if in_quote {
+        return Err(QueryError::UnterminatedQuote);
+    }
+    if has_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+impl std::str::FromStr for SearchQuery {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(s)?
+            .into_iter()
+            .filter(|token| !token.is_empty())
+            .map(|token| match token.split_once(':') {
+                Some((field, value)) if !field.is_empty() && !field.contains(' ') => {
+                    Self::parse_field(field, value.to_string())
+                }
+                _ => Ok(SearchTerm::Text(token)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(SearchQuery { terms })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{QueryError, SearchQuery, SearchTerm, parse_date};
+    use crate::catalog::smart_collection::{Comparison, SmartRule};
+
+    #[test]
+    fn test_parse_date() {
+        // 2023-06-01T00:00:00Z
+        assert_eq!(parse_date("2023-06", false), Some(1685577600));
+        // 2023-08-31T23:59:59Z
+        assert_eq!(parse_date("2023-08", true), Some(1693526399));
+        // 2023-12-31T23:59:59Z
+        assert_eq!(parse_date("2023-12", true), parse_date("2023", true));
+        assert_eq!(parse_date("2023-02-29", false), None);
+        assert_eq!(parse_date("June", false), None);
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = r#"rating:>=4 keyword:"beach" model:"X-T4" date:2023-06..2023-08 name:DSC* "sunset sky" flag:picked"#
+            .parse::<SearchQuery>()
+            .expect("Failed to parse query");
+        assert_eq!(
+            query.terms(),
+            &[
+                SearchTerm::Rule(SmartRule::Rating(Comparison::GreaterOrEqual, 4)),
+                SearchTerm::Keyword("beach".into()),
+                SearchTerm::Rule(SmartRule::CameraModel("X-T4".into())),
+                SearchTerm::Rule(SmartRule::DateRange(1685577600, 1693526399)),
+                SearchTerm::Name("DSC*".into()),
+                SearchTerm::Text("sunset sky".into()),
+                SearchTerm::Rule(SmartRule::Flag(1)),
+            ]
+        );
+
+        let query = "rating:3".parse::<SearchQuery>().expect("Failed to parse");
+        assert_eq!(
+            query.terms(),
+            &[SearchTerm::Rule(SmartRule::Rating(Comparison::Equal, 3))]
+        );
+
+        assert_eq!(
+            "camera:foo".parse::<SearchQuery>(),
+            Err(QueryError::UnknownField("camera".into()))
+        );
+        assert_eq!(
+            "rating:>=high".parse::<SearchQuery>(),
+            Err(QueryError::InvalidValue("rating".into(), ">=high".into()))
+        );
+        assert_eq!(
+            "keyword:\"beach".parse::<SearchQuery>(),
+            Err(QueryError::UnterminatedQuote)
+        );
+        assert!("  ".parse::<SearchQuery>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_to_sql() {
+        let query = "name:DSC_* beach"
+            .parse::<SearchQuery>()
+            .expect("Failed to parse query");
+        let (sql, params) = query.to_sql();
+        assert_eq!(
+            sql,
+            "((files.name LIKE ? ESCAPE '\\') AND \
+             (files.id IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?)))"
+        );
+        assert_eq!(
+            params,
+            vec![
+                rusqlite::types::Value::Text("DSC\\_%".into()),
+                rusqlite::types::Value::Text("\"beach\"*".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,358 @@
+/*
+ * niepce - npc-engine/catalog/smart_collection.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Smart collections: saved rule based queries over the catalog.
+
+use rusqlite::types::Value;
+
+use super::FromDb;
+use super::LibraryId;
+use npc_fwk::utils::exempi::{NS_AUX, NS_TIFF};
+
+/// Comparison operator for numerical rules.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl Comparison {
+    /// The SQL operator.
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+        }
+    }
+}
+
+impl std::str::FromStr for Comparison {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "=" | "==" => Ok(Self::Equal),
+            "!=" => Ok(Self::NotEqual),
+            "<" => Ok(Self::Less),
+            "<=" => Ok(Self::LessOrEqual),
+            ">" => Ok(Self::Greater),
+            ">=" => Ok(Self::GreaterOrEqual),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A rule for a smart collection.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmartRule {
+    /// Compare the rating.
+    Rating(Comparison, i32),
+    /// Has label (`labels.id`).
+    Label(LibraryId),
+    /// Has flag (-1, 0, 1).
+    Flag(i32),
+    /// Has any of the keywords (`keywords.id`).
+    Keywords(Vec<LibraryId>),
+    /// Camera model is.
+    CameraModel(String),
+    /// Lens is.
+    Lens(String),
+    /// File date is within the range (time_t), inclusive.
+    DateRange(i64, i64),
+}
+
+impl SmartRule {
+    /// Return the SQL where clause and the parameters for the rule.
+    /// Parameters are positional (`?`), in the order of the returned vector.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        match self {
+            Self::Rating(cmp, rating) => (
+                format!("files.rating {} ?", cmp.as_sql()),
+                vec![Value::Integer(*rating as i64)],
+            ),
+            Self::Label(label) => ("files.label = ?".to_string(), vec![Value::Integer(*label)]),
+            Self::Flag(flag) => (
+                "files.flag = ?".to_string(),
+                vec![Value::Integer(*flag as i64)],
+            ),
+            Self::Keywords(keywords) => {
+                if keywords.is_empty() {
+                    return ("0".to_string(), vec![]);
+                }
+                let placeholders = vec!["?"; keywords.len()].join(",");
+                (
+                    format!(
+                        "files.id IN (SELECT file_id FROM keywording WHERE keyword_id IN ({placeholders}))"
+                    ),
+                    keywords.iter().map(|k| Value::Integer(*k)).collect(),
+                )
+            }
+            Self::CameraModel(model) => (
+                "xmp_property(files.xmp, ?, 'Model') = ?".to_string(),
+                vec![Value::Text(NS_TIFF.to_string()), Value::Text(model.clone())],
+            ),
+            Self::Lens(lens) => (
+                "xmp_property(files.xmp, ?, 'Lens') = ?".to_string(),
+                vec![Value::Text(NS_AUX.to_string()), Value::Text(lens.clone())],
+            ),
+            Self::DateRange(start, end) => (
+                "files.file_date BETWEEN ? AND ?".to_string(),
+                vec![Value::Integer(*start), Value::Integer(*end)],
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for SmartRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Rating(cmp, rating) => write!(f, "rating {} {rating}", cmp.as_sql()),
+            Self::Label(label) => write!(f, "label = {label}"),
+            Self::Flag(flag) => write!(f, "flag = {flag}"),
+            Self::Keywords(keywords) => write!(
+                f,
+                "keyword in {}",
+                keywords
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Self::CameraModel(model) => write!(f, "model = {model}"),
+            Self::Lens(lens) => write!(f, "lens = {lens}"),
+            Self::DateRange(start, end) => write!(f, "date {start}..{end}"),
+        }
+    }
+}
+
+impl std::str::FromStr for SmartRule {
+    type Err = ();
+
+    /// Parse a rule as output by `Display`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, rest) = s.trim().split_once(' ').ok_or(())?;
+        let rest = rest.trim();
+        match key {
+            "rating" => {
+                let (cmp, value) = rest.split_once(' ').ok_or(())?;
+                Ok(Self::Rating(
+                    cmp.parse()?,
+                    value.trim().parse().map_err(|_| ())?,
+                ))
+            }
+            "label" | "flag" | "model" | "lens" => {
+                let value = rest.strip_prefix('=').ok_or(())?.trim();
+                match key {
+                    "label" => Ok(Self::Label(value.parse().map_err(|_| ())?)),
+                    "flag" => Ok(Self::Flag(value.parse().map_err(|_| ())?)),
+                    "model" => Ok(Self::CameraModel(value.to_string())),
+                    "lens" => Ok(Self::Lens(value.to_string())),
+                    _ => unreachable!(),
+                }
+            }
+            "keyword" => {
+                let value = rest.strip_prefix("in").ok_or(())?.trim();
+                let keywords = value
+                    .split(',')
+                    .filter(|k| !k.is_empty())
+                    .map(|k| k.trim().parse::<LibraryId>().map_err(|_| ()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Self::Keywords(keywords))
+            }
+            "date" => {
+                let (start, end) = rest.split_once("..").ok_or(())?;
+                Ok(Self::DateRange(
+                    start.trim().parse().map_err(|_| ())?,
+                    end.trim().parse().map_err(|_| ())?,
+                ))
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+/// A smart collection: a named set of rules evaluated on demand.
+#[derive(Clone, Debug)]
+pub struct SmartCollection {
+    /// Smart collection ID
+    id: LibraryId,
+    /// Name as displayed
+    name: String,
+    /// The rules.
+    rules: Vec<SmartRule>,
+    /// Whether all the rules have to match, or any.
+    match_all: bool,
+}
+
+impl SmartCollection {
+    pub fn new(id: LibraryId, name: &str, rules: Vec<SmartRule>, match_all: bool) -> Self {
+        SmartCollection {
+            id,
+            name: name.to_owned(),
+            rules,
+            match_all,
+        }
+    }
+
+    /// Get the smart collection ID
+    pub fn id(&self) -> LibraryId {
+        self.id
+    }
+
+    /// Get the smart collection name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn rules(&self) -> &[SmartRule] {
+        &self.rules
+    }
+
+    pub fn match_all(&self) -> bool {
+        self.match_all
+    }
+
+    /// Serialize the rules for storage, one per line.
+    pub fn rules_to_string(rules: &[SmartRule]) -> String {
+        rules
+            .iter()
+            .map(|rule| rule.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Parse the rules from storage. Invalid rules are ignored.
+    pub fn rules_from_string(s: &str) -> Vec<SmartRule> {
+        s.lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| {
+                line.parse::<SmartRule>()
+                    .inspect_err(|_| npc_fwk::err_out!("Invalid smart rule '{line}'"))
+                    .ok()
+            })
+            .collect()
+    }
+
+    /// Return the SQL where clause and parameters for the collection.
+    /// No rules will match nothing.
+    pub(crate) fn to_sql(&self) -> (String, Vec<Value>) {
+        if self.rules.is_empty() {
+            return ("0".to_string(), vec![]);
+        }
+        let op = if self.match_all { " AND " } else { " OR " };
+        let mut params = vec![];
+        let clauses = self
+            .rules
+            .iter()
+            .map(|rule| {
+                let (clause, mut p) = rule.to_sql();
+                params.append(&mut p);
+                format!("({clause})")
+            })
+            .collect::<Vec<_>>()
+            .join(op);
+        (format!("({clauses})"), params)
+    }
+}
+
+impl FromDb for SmartCollection {
+    fn read_db_columns() -> &'static str {
+        "id,name,rules,match_all"
+    }
+
+    fn read_db_tables() -> &'static str {
+        "smart_collections"
+    }
+
+    fn read_db_where_id() -> &'static str {
+        "id"
+    }
+
+    fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let name: String = row.get(1)?;
+        let rules: String = row.get(2)?;
+        let match_all: bool = row.get(3)?;
+        Ok(SmartCollection::new(
+            row.get(0)?,
+            &name,
+            SmartCollection::rules_from_string(&rules),
+            match_all,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Comparison, SmartCollection, SmartRule};
+
+    #[test]
+    fn test_rules_roundtrip() {
+        let rules = vec![
+            SmartRule::Rating(Comparison::GreaterOrEqual, 3),
+            SmartRule::Label(2),
+            SmartRule::Flag(-1),
+            SmartRule::Keywords(vec![1, 4, 5]),
+            SmartRule::CameraModel("X-T4".to_string()),
+            SmartRule::Lens("XF 23mm F2 R WR".to_string()),
+            SmartRule::DateRange(1685577600, 1693526399),
+        ];
+        let s = SmartCollection::rules_to_string(&rules);
+        assert_eq!(
+            s.lines().next(),
+            Some("rating >= 3"),
+            "Rating rule incorrectly serialized"
+        );
+        let parsed = SmartCollection::rules_from_string(&s);
+        assert_eq!(parsed, rules);
+
+        // Invalid rules are skipped.
+        let parsed = SmartCollection::rules_from_string("rating ~ 3\nflag = 1\n\nfoo bar");
+        assert_eq!(parsed, vec![SmartRule::Flag(1)]);
+    }
+
+    #[test]
+    fn test_to_sql() {
+        let collection = SmartCollection::new(1, "test", vec![], true);
+        assert_eq!(collection.to_sql().0, "0");
+
+        let collection = SmartCollection::new(
+            1,
+            "test",
+            vec![
+                SmartRule::Rating(Comparison::Greater, 2),
+                SmartRule::Keywords(vec![1, 2]),
+            ],
+            false,
+        );
+        let (sql, params) = collection.to_sql();
+        assert_eq!(
+            sql,
+            "((files.rating > ?) OR (files.id IN (SELECT file_id FROM keywording WHERE keyword_id IN (?,?))))"
+        );
+        assert_eq!(params.len(), 3);
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * niepce - npc-engine/catalog/stack.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Stacks: an ordered group of files, shown as one "top" file.
+
+use super::LibraryId;
+use npc_fwk::XmpMeta;
+
+/// Maximum time between two shots of an exposure bracket, in seconds.
+const BRACKET_MAX_GAP: i64 = 10;
+
+/// Options for automatic stacking.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StackingOptions {
+    /// Stack the files taken at most this number of seconds after the
+    /// previous one. `None` to disable.
+    pub time_gap: Option<i64>,
+    /// Stack the exposure brackets.
+    pub brackets: bool,
+}
+
+impl StackingOptions {
+    /// Whether automatic stacking is enabled at all.
+    pub fn is_enabled(&self) -> bool {
+        self.time_gap.is_some() || self.brackets
+    }
+}
+
+/// A file to consider for automatic stacking.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackCandidate {
+    pub id: LibraryId,
+    /// Capture date (time_t). 0 if unknown.
+    pub date: i64,
+    /// Exposure bias in EV.
+    pub exposure_bias: Option<f64>,
+    /// Whether this was shot as an auto exposure bracket.
+    pub bracket: bool,
+}
+
+impl StackCandidate {
+    pub(crate) fn new(id: LibraryId, date: i64, xmp: Option<&XmpMeta>) -> StackCandidate {
+        StackCandidate {
+            id,
+            date,
+            exposure_bias: xmp.and_then(XmpMeta::exposure_bias),
+            bracket: xmp.is_some_and(XmpMeta::is_auto_bracket),
+        }
+    }
+}
+
+/// Group the `candidates` into stacks following `options`. Files are
+/// ordered by capture date and only consecutive files are grouped.
+/// A bracket ends when its exposure bias repeats. Each stack returned
+/// has at least two files, the first being the top.
+pub fn auto_stack(candidates: &[StackCandidate], options: &StackingOptions) -> Vec<Vec<LibraryId>> {
+    let mut sorted = candidates.iter().filter(|c| c.date > 0).collect::<Vec<_>>();
+    sorted.sort_by_key(|c| (c.date, c.id));
+
+    let mut stacks = vec![];
+    let mut current: Vec<&StackCandidate> = vec![];
+    for candidate in sorted {
+        let joins = current.last().is_some_and(|prev| {
+            let gap = candidate.date - prev.date;
+            if options.brackets && prev.bracket && candidate.bracket {
+                gap <= BRACKET_MAX_GAP
+                    && !current
+                        .iter()
+                        .any(|c| c.exposure_bias == candidate.exposure_bias)
+            } else {
+                options.time_gap.is_some_and(|time_gap| gap <= time_gap)
+            }
+        });
+        if !joins {
+            if current.len() > 1 {
+                stacks.push(current.iter().map(|c| c.id).collect());
+            }
+            current.clear();
+        }
+        current.push(candidate);
+    }
+    if current.len() > 1 {
+        stacks.push(current.iter().map(|c| c.id).collect());
+    }
+
+    stacks
+}
+
+#[cfg(test)]
+mod test {
+    use super::{StackCandidate, StackingOptions, auto_stack};
+
+    fn candidate(id: i64, date: i64, bias: f64, bracket: bool) -> StackCandidate {
+        StackCandidate {
+            id,
+            date,
+            exposure_bias: Some(bias),
+            bracket,
+        }
+    }
+
+    #[test]
+    fn test_auto_stack() {
+        let candidates = vec![
+            // A burst, out of order.
+            candidate(2, 1001, 0.0, false),
+            candidate(1, 1000, 0.0, false),
+            candidate(3, 1002, 0.0, false),
+            // Alone.
+            candidate(4, 1100, 0.0, false),
+            // Two brackets, back to back.
+            candidate(5, 1200, 0.0, true),
+            candidate(6, 1200, -2.0, true),
+            candidate(7, 1201, 2.0, true),
+            candidate(8, 1202, 0.0, true),
+            candidate(9, 1202, -2.0, true),
+            candidate(10, 1203, 2.0, true),
+            // No date.
+            candidate(11, 0, 0.0, false),
+        ];
+
+        assert!(auto_stack(&candidates, &StackingOptions::default()).is_empty());
+
+        let options = StackingOptions {
+            time_gap: Some(2),
+            brackets: false,
+        };
+        assert_eq!(
+            auto_stack(&candidates, &options),
+            vec![vec![1, 2, 3], vec![5, 6, 7, 8, 9, 10]]
+        );
+
+        let options = StackingOptions {
+            time_gap: None,
+            brackets: true,
+        };
+        assert_eq!(
+            auto_stack(&candidates, &options),
+            vec![vec![5, 6, 7], vec![8, 9, 10]]
+        );
+
+        let options = StackingOptions {
+            time_gap: Some(2),
+            brackets: true,
+        };
+        assert_eq!(
+            auto_stack(&candidates, &options),
+            vec![vec![1, 2, 3], vec![5, 6, 7], vec![8, 9, 10]]
+        );
+    }
+}
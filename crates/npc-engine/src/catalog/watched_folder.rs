@@ -0,0 +1,97 @@
+/*
+ * niepce - npc-engine/src/catalog/watched_folder.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Watched folders: directories whose new files are imported
+//! automatically, like for tethered shooting.
+
+use std::path::{Path, PathBuf};
+
+use super::FromDb;
+use super::LibraryId;
+
+/// A watched folder.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchedFolder {
+    /// ID. 0 if not in the catalog.
+    id: LibraryId,
+    /// The directory watched.
+    path: PathBuf,
+    /// The directory to copy the new files to, that is the catalog
+    /// folder they are imported in. `None` to import them in place.
+    dest: Option<PathBuf>,
+    /// The metadata preset to apply (`metadata_presets.id`). 0 for none.
+    preset_id: LibraryId,
+}
+
+impl WatchedFolder {
+    pub fn new(id: LibraryId, path: &Path, dest: Option<&Path>, preset_id: LibraryId) -> Self {
+        WatchedFolder {
+            id,
+            path: path.to_path_buf(),
+            dest: dest.map(Path::to_path_buf),
+            preset_id,
+        }
+    }
+
+    pub fn id(&self) -> LibraryId {
+        self.id
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn dest(&self) -> Option<&Path> {
+        self.dest.as_deref()
+    }
+
+    /// Whether the new files are copied.
+    pub fn copy(&self) -> bool {
+        self.dest.is_some()
+    }
+
+    pub fn preset_id(&self) -> LibraryId {
+        self.preset_id
+    }
+}
+
+impl FromDb for WatchedFolder {
+    fn read_db_columns() -> &'static str {
+        "id,path,dest,preset_id"
+    }
+
+    fn read_db_tables() -> &'static str {
+        "watched_folders"
+    }
+
+    fn read_db_where_id() -> &'static str {
+        "id"
+    }
+
+    fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let path: String = row.get(1)?;
+        let dest: Option<String> = row.get(2)?;
+        Ok(WatchedFolder::new(
+            row.get(0)?,
+            Path::new(&path),
+            dest.as_deref().map(Path::new),
+            row.get(3)?,
+        ))
+    }
+}
@@ -18,16 +18,29 @@
  */
 
 mod camera_importer;
+pub mod copy_job;
+pub mod darktable_importer;
+pub mod digikam_importer;
 mod directory_importer;
+mod folder_watcher;
+mod import_job;
 mod imported_file;
+mod library_content;
 pub mod libraryimporter;
 pub mod lrimporter;
+mod rename;
 
 pub use camera_importer::CameraImporter;
+pub use copy_job::{CopyJob, CopyReport};
+pub use darktable_importer::DarktableImporter;
+pub use digikam_importer::DigikamImporter;
 pub use directory_importer::DirectoryImporter;
+pub use folder_watcher::{FolderWatcher, NewFilesCallback};
+pub use import_job::{ImportJob, ImportJobId, ImportProgress, ImportSummary};
 pub use imported_file::ImportedFile;
-pub use libraryimporter::{LibraryImporter, LibraryImporterProbe};
+pub use libraryimporter::{ImportReport, LibraryImporter, LibraryImporterProbe};
 pub use lrimporter::LrImporter;
+pub use rename::{BundleRenamer, RenameError, RenameTemplate};
 
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -35,15 +48,20 @@ use std::rc::Rc;
 use num_derive::{FromPrimitive, ToPrimitive};
 
 use crate::catalog::filebundle::FileBundle;
+use crate::catalog::{MetadataPreset, StackingOptions};
 use npc_fwk::base::Executor;
 use npc_fwk::glib;
 use npc_fwk::toolkit::thumbnail::Thumbnail;
 use npc_fwk::utils::FileList;
-use npc_fwk::{Date, DateExt, XmpMeta, dbg_out};
+use npc_fwk::{Date, DateExt, XmpMeta, dbg_out, err_out};
 
 pub fn find_importer(path: &std::path::Path) -> Option<Box<dyn LibraryImporter>> {
     if LrImporter::can_import_library(path) {
         Some(Box::new(LrImporter::new()))
+    } else if DarktableImporter::can_import_library(path) {
+        Some(Box::new(DarktableImporter::new()))
+    } else if DigikamImporter::can_import_library(path) {
+        Some(Box::new(DigikamImporter::new()))
     } else {
         None
     }
@@ -56,6 +74,50 @@ pub fn default_import_destdir() -> PathBuf {
         .unwrap_or_else(|| PathBuf::from("~/Pictures"))
 }
 
+/// The selection of the source files to import. The files are
+/// selected by bundle, with their main file.
+#[derive(Clone, Debug, Default)]
+pub struct ImportSelection {
+    /// The files to import. `None` for all of them.
+    selected: Option<Vec<PathBuf>>,
+    /// The files not to import, like the duplicates.
+    skipped: Vec<PathBuf>,
+}
+
+impl ImportSelection {
+    pub fn new(selected: Option<Vec<PathBuf>>, skipped: Vec<PathBuf>) -> ImportSelection {
+        ImportSelection { selected, skipped }
+    }
+
+    pub fn selected(&self) -> Option<&[PathBuf]> {
+        self.selected.as_deref()
+    }
+
+    pub fn skipped(&self) -> &[PathBuf] {
+        &self.skipped
+    }
+
+    /// Whether the bundle with `main` file is to be imported.
+    pub fn includes(&self, main: &Path) -> bool {
+        self.selected
+            .as_ref()
+            .is_none_or(|selected| selected.iter().any(|file| file == main))
+            && !self.skipped.iter().any(|file| file == main)
+    }
+
+    /// Return the `files` of the bundles to import.
+    pub fn filter(&self, files: &[PathBuf]) -> Vec<PathBuf> {
+        if self.selected.is_none() && self.skipped.is_empty() {
+            return files.to_vec();
+        }
+        FileBundle::filter_bundles(files)
+            .iter()
+            .filter(|bundle| self.includes(bundle.main()))
+            .flat_map(FileBundle::all_files)
+            .collect()
+    }
+}
+
 /// An import request
 #[derive(Clone)]
 pub struct ImportRequest {
@@ -64,6 +126,17 @@ pub struct ImportRequest {
     dest: PathBuf,
     /// Which way to sort the pictures.
     sorting: DatePathFormat,
+    /// How to stack the imported files.
+    stacking: StackingOptions,
+    /// The metadata preset to apply to the imported files.
+    preset: Option<MetadataPreset>,
+    /// The template to rename the files copied. `None` to keep the
+    /// original names.
+    rename: Option<RenameTemplate>,
+    /// The source files to import.
+    selection: ImportSelection,
+    /// The backup destination of the files copied.
+    backup: Option<PathBuf>,
     importer: Rc<dyn ImportBackend>,
 }
 
@@ -74,6 +147,11 @@ impl ImportRequest {
             recursive: false,
             dest: dest.as_ref().to_path_buf(),
             sorting: DatePathFormat::default(),
+            stacking: StackingOptions::default(),
+            preset: None,
+            rename: None,
+            selection: ImportSelection::default(),
+            backup: None,
             importer,
         }
     }
@@ -94,6 +172,74 @@ impl ImportRequest {
         self.sorting
     }
 
+    /// Builder: set the automatic stacking.
+    pub fn set_stacking(mut self, stacking: StackingOptions) -> Self {
+        self.stacking = stacking;
+        self
+    }
+
+    pub fn stacking(&self) -> &StackingOptions {
+        &self.stacking
+    }
+
+    /// Builder: set the metadata preset to apply.
+    pub fn set_metadata_preset(mut self, preset: Option<MetadataPreset>) -> Self {
+        self.preset = preset;
+        self
+    }
+
+    pub fn metadata_preset(&self) -> Option<&MetadataPreset> {
+        self.preset.as_ref()
+    }
+
+    /// Builder: set the template to rename the files.
+    pub fn set_rename_template(mut self, rename: Option<RenameTemplate>) -> Self {
+        self.rename = rename;
+        self
+    }
+
+    pub fn rename_template(&self) -> Option<&RenameTemplate> {
+        self.rename.as_ref()
+    }
+
+    /// Builder: set the source files not to import. The bundles whose
+    /// main file is skipped are skipped.
+    pub fn set_skipped_files(mut self, skipped: Vec<PathBuf>) -> Self {
+        self.selection.skipped = skipped;
+        self
+    }
+
+    pub fn skipped_files(&self) -> &[PathBuf] {
+        self.selection.skipped()
+    }
+
+    /// Builder: set the source files to import. `None` to import all
+    /// of them. The bundles whose main file isn't selected are left
+    /// out.
+    pub fn set_selected_files(mut self, selected: Option<Vec<PathBuf>>) -> Self {
+        self.selection.selected = selected;
+        self
+    }
+
+    pub fn selected_files(&self) -> Option<&[PathBuf]> {
+        self.selection.selected()
+    }
+
+    pub fn selection(&self) -> &ImportSelection {
+        &self.selection
+    }
+
+    /// Builder: set the directory to put a backup of the files
+    /// copied. It is organized like the destination.
+    pub fn set_backup_dir(mut self, backup: Option<PathBuf>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn backup_dir(&self) -> Option<&Path> {
+        self.backup.as_deref()
+    }
+
     pub fn set_source(mut self, source: &str) -> Self {
         self.source = source.into();
         self
@@ -113,9 +259,13 @@ impl ImportRequest {
 }
 
 type SourceContentReady = Box<dyn Fn(Vec<Box<dyn ImportedFile>>) + Send>;
-/// Called when a preview is ready. Passing None for the path finishes.
-type PreviewReady = Box<dyn Fn(Option<String>, Option<Thumbnail>, Option<Date>) + Send>;
-type FileImporter = Box<dyn Fn(&Path, &FileList) + Send>;
+/// Called when a preview is ready, with the date and the camera
+/// model. Passing None for the path finishes.
+type PreviewReady =
+    Box<dyn Fn(Option<String>, Option<Thumbnail>, Option<Date>, Option<String>) + Send>;
+/// Called to import the files into the catalog, with the report
+/// of the copy if the files were copied.
+type FileImporter = Box<dyn Fn(&Path, &FileList, Option<&CopyReport>) + Send>;
 
 /// Trait for file importers backends.
 pub trait ImportBackend {
@@ -137,7 +287,15 @@ pub trait ImportBackend {
 
     /// Do the import. This just copy (if needed) the files to the destination
     /// and call `callback` that should perform the import into the library.
-    fn do_import(&self, request: &ImportRequest, callback: FileImporter);
+    /// Only the files successfully copied are passed. The copy is run by
+    /// `executor` and its progress reported to `job`.
+    fn do_import(
+        &self,
+        executor: &Executor,
+        request: &ImportRequest,
+        job: &ImportJob,
+        callback: FileImporter,
+    );
 }
 
 /// Date path format for import destination
@@ -182,9 +340,11 @@ impl Importer {
         }
     }
 
-    /// Get the date from the `source`.
-    fn date_from(source: &Path) -> Option<npc_fwk::Date> {
-        XmpMeta::new_from_file(source, false)
+    /// Get the date and the camera model from the `source`.
+    fn date_and_camera_from(source: &Path) -> (Option<npc_fwk::Date>, Option<String>) {
+        let xmp = XmpMeta::new_from_file(source, false);
+        let date = xmp
+            .as_ref()
             .and_then(|xmp| xmp.creation_date())
             .or_else(|| {
                 std::fs::metadata(source)
@@ -196,28 +356,44 @@ impl Importer {
                         date
                     })
                     .ok()
-            })
+            });
+
+        (date, xmp.and_then(|xmp| xmp.camera_model()))
     }
 
     /// Get the imports from `source`. It will create the bundles.  It
     /// will list the files to import recursively if the imorter is
     /// recursive and the `dest` path. They will be sorted out
-    /// according to `format`.
+    /// according to `format`, and renamed with `rename` if any. The
+    /// names from the manifest of an interrupted copy to `dest` are
+    /// kept. Only the bundles included in `selection` are imported.
     pub fn get_imports(
         source: &Path,
         dest: &Path,
         format: DatePathFormat,
         recursive: bool,
+        rename: Option<&RenameTemplate>,
+        selection: &ImportSelection,
     ) -> Vec<(PathBuf, PathBuf)> {
         let entries =
             FileList::files_from_directory(source, FileList::file_is_media, recursive, None);
         let bundles = FileBundle::filter_bundles(&entries);
+        let previous = if rename.is_some() {
+            CopyJob::previous_imports(dest)
+        } else {
+            vec![]
+        };
+        let mut renamer = rename.map(|rename| BundleRenamer::new(rename).set_previous(&previous));
         bundles
             .iter()
+            .filter(|bundle| selection.includes(bundle.main()))
             .flat_map(|bundle| {
-                //
-                let date = Self::date_from(bundle.main()).or_else(|| Some(Date::now()));
+                let (date, camera) = Self::date_and_camera_from(bundle.main());
+                let date = date.or_else(|| Some(Date::now()));
                 let dest_dir = Self::dest_dir_for_date(dest, date.as_ref(), format);
+                if let Some(renamer) = renamer.as_mut() {
+                    return renamer.rename(bundle, &dest_dir, date.as_ref(), camera.as_deref());
+                }
                 bundle
                     .all_files()
                     .iter()
@@ -229,6 +405,31 @@ impl Importer {
             })
             .collect()
     }
+
+    /// Rename the `files` already in `dir` with `template`. Return the
+    /// old and new paths. Files that failed to be renamed keep their
+    /// path.
+    pub fn rename_in_place(
+        dir: &Path,
+        files: &[PathBuf],
+        template: &RenameTemplate,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        let mut renamer = BundleRenamer::new(template);
+        FileBundle::filter_bundles(files)
+            .iter()
+            .flat_map(|bundle| {
+                let (date, camera) = Self::date_and_camera_from(bundle.main());
+                renamer.rename(bundle, dir, date.as_ref(), camera.as_deref())
+            })
+            .map(|(from, to)| match std::fs::rename(&from, &to) {
+                Ok(_) => (from, to),
+                Err(err) => {
+                    err_out!("Couldn't rename {from:?} to {to:?}: {err}");
+                    (from.clone(), from)
+                }
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -239,7 +440,7 @@ mod test {
     use chrono;
     use chrono::{Offset, TimeZone};
 
-    use super::{DatePathFormat, Importer};
+    use super::{CopyJob, DatePathFormat, ImportSelection, Importer, RenameTemplate, copy_job};
 
     #[test]
     fn test_dest_dir_for_date() {
@@ -289,4 +490,93 @@ mod test {
             expected_dir
         );
     }
+
+    #[test]
+    fn test_import_selection() {
+        let files = [
+            "/src/IMG_0001.CR2",
+            "/src/IMG_0001.JPG",
+            "/src/IMG_0002.CR2",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+
+        let selection = ImportSelection::default();
+        assert!(selection.includes(&files[0]));
+        assert_eq!(selection.filter(&files), files);
+
+        // The bundle is selected with its main file.
+        let selection = ImportSelection::new(Some(vec![files[0].clone()]), vec![]);
+        assert!(!selection.includes(&files[2]));
+        let mut selected = selection.filter(&files);
+        selected.sort();
+        selected.dedup();
+        assert_eq!(selected, &files[..2]);
+
+        let selection = ImportSelection::new(
+            Some(vec![files[0].clone(), files[2].clone()]),
+            vec![files[2].clone()],
+        );
+        assert!(!selection.includes(&files[2]));
+        assert!(selection.filter(&files).contains(&files[1]));
+        assert!(!selection.filter(&files).contains(&files[2]));
+
+        let selection = ImportSelection::new(Some(vec![]), vec![]);
+        assert!(selection.filter(&files).is_empty());
+    }
+
+    #[test]
+    fn test_get_imports_resume() {
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let source = tmpdir.path().join("card");
+        let dest = tmpdir.path().join("dest");
+        std::fs::create_dir_all(&source).expect("Couldn't create dir");
+        std::fs::write(source.join("DSC_0001.NEF"), "raw1").expect("Couldn't write file");
+        std::fs::write(source.join("DSC_0002.NEF"), "raw2").expect("Couldn't write file");
+        let template = "trip-{orig}"
+            .parse::<RenameTemplate>()
+            .expect("Invalid template");
+        let get_imports = || {
+            let mut imports = Importer::get_imports(
+                &source,
+                &dest,
+                DatePathFormat::NoPath,
+                false,
+                Some(&template),
+                &ImportSelection::default(),
+            );
+            imports.sort();
+            imports
+        };
+
+        let imports = get_imports();
+        assert_eq!(imports[0].1, dest.join("trip-DSC_0001.NEF"));
+        let fail_second = |from: &std::path::Path, to: &std::path::Path| {
+            if from.ends_with("DSC_0002.NEF") {
+                Err(std::io::Error::other("Card pulled"))
+            } else {
+                copy_job::copy_file(from, to)
+            }
+        };
+        let report = CopyJob::new(&dest, None, &imports).run(&fail_second, None);
+        assert_eq!(report.failed.len(), 1);
+
+        // Resume: the names are the same, and only the file that
+        // failed is copied.
+        let resumed = get_imports();
+        assert_eq!(resumed, imports);
+        let fetched = std::cell::RefCell::new(vec![]);
+        let report = CopyJob::new(&dest, None, &resumed).run(
+            &|from, to| {
+                fetched.borrow_mut().push(from.to_path_buf());
+                copy_job::copy_file(from, to)
+            },
+            None,
+        );
+        assert!(report.is_success());
+        assert_eq!(report.copied.len(), 2);
+        assert_eq!(*fetched.borrow(), vec![source.join("DSC_0002.NEF")]);
+        assert!(!dest.join("trip-DSC_0001-1.NEF").exists());
+    }
 }
@@ -18,15 +18,16 @@
  */
 
 use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 
 use super::{
-    DirectoryImporter, FileImporter, ImportBackend, ImportRequest, ImportedFile, PreviewReady,
-    SourceContentReady,
+    CopyJob, DirectoryImporter, FileImporter, ImportBackend, ImportJob, ImportRequest,
+    ImportedFile, Importer, PreviewReady, SourceContentReady,
 };
 use npc_fwk::base::{Executor, WorkerStatus};
 use npc_fwk::toolkit::{GpCamera, GpDeviceList};
 use npc_fwk::utils::{FileList, exiv2};
-use npc_fwk::{Date, dbg_out, err_out, on_err_out};
+use npc_fwk::{Date, dbg_out, err_out};
 
 #[derive(Clone, Default)]
 pub struct CameraImportedFile {
@@ -154,12 +155,13 @@ impl CameraImporter {
                     .as_ref()
                     .and_then(|xmp| xmp.creation_date())
                     .or_else(|| thumbnail.clone().and_then(|t| t.1));
+                let camera = xmp.as_ref().and_then(|xmp| xmp.camera_model());
                 if thumbnail.is_some() || date.is_some() {
-                    callback(Some(path.to_string()), thumbnail.map(|t| t.0), date);
+                    callback(Some(path.to_string()), thumbnail.map(|t| t.0), date, camera);
                 }
             }
         });
-        callback(None, None, None);
+        callback(None, None, None, None);
     }
 }
 
@@ -211,42 +213,84 @@ impl ImportBackend for CameraImporter {
         }
     }
 
-    fn do_import(&self, request: &ImportRequest, callback: FileImporter) {
+    fn do_import(
+        &self,
+        executor: &Executor,
+        request: &ImportRequest,
+        job: &ImportJob,
+        callback: FileImporter,
+    ) {
         match self.ensure_camera_open(request.source()) {
             CameraBackend::Gphoto2(camera) => {
                 let dest_dir = request.dest_dir().to_path_buf();
-                on_err_out!(
-                    std::thread::Builder::new()
-                        .name("camera import".to_string())
-                        .spawn(move || {
-                            let file_list = Self::list_content_for_camera(None, &camera);
-                            // XXX we likely need to handle this error better
-                            on_err_out!(std::fs::create_dir_all(&dest_dir));
-                            let files = file_list
-                                .iter()
-                                .filter_map(|file| {
-                                    let name = file.name();
-                                    let output_path = dest_dir.join(name);
-                                    if camera.download_file(
-                                        file.folder(),
-                                        name,
-                                        &output_path.to_string_lossy(),
-                                    ) {
-                                        return Some(output_path);
-                                    }
-
-                                    None
-                                })
-                                .collect();
-                            callback(&dest_dir, &FileList(files));
+                let rename = request.rename_template().cloned();
+                let backup = request.backup_dir().map(Path::to_path_buf);
+                let selection = request.selection().clone();
+                let job = job.clone();
+                executor.run(move || {
+                    let file_list = Self::list_content_for_camera(None, &camera)
+                        .iter()
+                        .map(|file| PathBuf::from(file.folder()).join(file.name()))
+                        .collect::<Vec<_>>();
+                    let imports = selection
+                        .filter(&file_list)
+                        .into_iter()
+                        .filter_map(|file| {
+                            let dest = dest_dir.join(file.file_name()?);
+                            Some((file, dest))
                         })
-                );
+                        .collect::<Vec<_>>();
+                    // The source can't be read for verification.
+                    let mut copy = CopyJob::new(&dest_dir, None, &imports).set_verify_source(false);
+                    let mut report = copy.run(
+                        &|from: &Path, to: &Path| {
+                            let folder = from.parent().unwrap_or(Path::new("/"));
+                            let name = from.file_name().unwrap_or_default();
+                            if camera.download_file(
+                                &folder.to_string_lossy(),
+                                &name.to_string_lossy(),
+                                &to.to_string_lossy(),
+                            ) {
+                                Ok(())
+                            } else {
+                                Err(std::io::Error::other("Camera download failed"))
+                            }
+                        },
+                        Some(&job),
+                    );
+                    // The camera files are renamed once downloaded,
+                    // as the bundles can only be made then. Those
+                    // renamed by a previous run are already.
+                    if let Some(ref template) = rename {
+                        let renames =
+                            Importer::rename_in_place(&dest_dir, &copy.not_renamed(), template);
+                        copy.set_renamed(&renames);
+                        for file in report.copied.iter_mut() {
+                            if let Some((_, to)) = renames.iter().find(|(from, _)| from == file) {
+                                *file = to.clone();
+                            }
+                        }
+                    }
+                    // Hence the backup is done afterward.
+                    if let Some(ref backup) = backup {
+                        let backup_report = CopyJob::mirror(&dest_dir, backup, &report.copied);
+                        report.failed.extend(
+                            backup_report
+                                .failed
+                                .into_iter()
+                                .map(|(file, err)| (file, format!("Backup: {err}"))),
+                        );
+                        report.manifest = report.manifest.or(backup_report.manifest);
+                    }
+                    callback(&dest_dir, &FileList(report.copied.clone()), Some(&report));
+                    WorkerStatus::Stop
+                });
             }
             CameraBackend::File => {
                 if let Some(backend) = self.file_backend.borrow_mut().take() {
                     let source = &request.source()[5..];
                     let request = request.clone().set_source(source);
-                    backend.do_import(&request, callback);
+                    backend.do_import(executor, &request, job, callback);
                 }
             }
             CameraBackend::Error => {}
@@ -0,0 +1,559 @@
+/*
+ * niepce - npc-engine/importer/copy_job.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The copy job of an import. Each copy is verified with a checksum
+//! and can be mirrored to a backup destination. The progress is kept
+//! in a manifest so that an interrupted import can be resumed.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use md5::Digest;
+use serde::{Deserialize, Serialize};
+
+use super::ImportJob;
+use npc_fwk::{dbg_out, err_out, on_err_out};
+
+/// Name of the manifest file, in the destination directory.
+pub const MANIFEST_NAME: &str = ".niepce-import.json";
+
+/// Compute the checksum of the whole file at `path`.
+pub fn checksum<P: AsRef<Path>>(path: P) -> std::io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = md5::Md5::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let len = file.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buffer[..len]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// State of a file in the manifest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CopyState {
+    Pending,
+    /// Copied and verified.
+    Done,
+    /// Copied and verified, but the backup failed, with the error.
+    /// Only the backup is retried on resume.
+    BackupFailed(String),
+    /// Failed, with the error.
+    Failed(String),
+}
+
+/// A file in the manifest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CopyEntry {
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    /// The backup copy, if any.
+    pub backup: Option<PathBuf>,
+    /// The checksum of the copy, once done.
+    pub checksum: Option<String>,
+    pub state: CopyState,
+    /// Where the copy was renamed to once done, if it was.
+    #[serde(default)]
+    pub renamed: Option<PathBuf>,
+}
+
+impl CopyEntry {
+    /// The path of the copy, once done.
+    pub fn copied(&self) -> &Path {
+        self.renamed.as_deref().unwrap_or(&self.dest)
+    }
+}
+
+/// The report of a copy job.
+#[derive(Clone, Debug, Default)]
+pub struct CopyReport {
+    /// The destinations copied. This includes the files copied by a
+    /// previous interrupted run.
+    pub copied: Vec<PathBuf>,
+    /// The sources that failed, with the error.
+    pub failed: Vec<(PathBuf, String)>,
+    /// The manifest, if kept because of the failures.
+    pub manifest: Option<PathBuf>,
+    /// Whether the copy was cancelled before the end.
+    pub cancelled: bool,
+}
+
+impl CopyReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Fetch a source file to a destination.
+pub type Fetcher = dyn Fn(&Path, &Path) -> std::io::Result<()>;
+
+/// The `Fetcher` for local files.
+pub fn copy_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    npc_fwk::utils::copy(from, to).map(|_| ())
+}
+
+/// A copy job.
+pub struct CopyJob {
+    dest: PathBuf,
+    backup: Option<PathBuf>,
+    /// Whether the source can be read to verify the copy. This isn't
+    /// the case for a camera.
+    verify_source: bool,
+    entries: Vec<CopyEntry>,
+}
+
+impl CopyJob {
+    /// Create the copy job of `imports` (source, destination), the
+    /// destinations being under `dest`. Their backup is put at the
+    /// same place under `backup`. The manifest of an interrupted job
+    /// in `dest` is used to skip what is already done.
+    pub fn new(dest: &Path, backup: Option<&Path>, imports: &[(PathBuf, PathBuf)]) -> CopyJob {
+        let previous = Self::load_manifest(&dest.join(MANIFEST_NAME));
+        let entries = imports
+            .iter()
+            .map(|(source, file_dest)| {
+                let backup = backup
+                    .map(|backup| backup.join(file_dest.strip_prefix(dest).unwrap_or(file_dest)));
+                previous
+                    .iter()
+                    .find(|entry| {
+                        entry.source == *source
+                            && entry.dest == *file_dest
+                            && entry.backup == backup
+                    })
+                    .filter(|entry| match entry.state {
+                        CopyState::Done => Self::is_intact(entry),
+                        CopyState::BackupFailed(_) => Self::matches(entry, entry.copied()),
+                        _ => false,
+                    })
+                    .cloned()
+                    .unwrap_or_else(|| CopyEntry {
+                        source: source.clone(),
+                        dest: file_dest.clone(),
+                        backup,
+                        checksum: None,
+                        state: CopyState::Pending,
+                        renamed: None,
+                    })
+            })
+            .collect();
+
+        CopyJob {
+            dest: dest.to_path_buf(),
+            backup: backup.map(Path::to_path_buf),
+            verify_source: true,
+            entries,
+        }
+    }
+
+    /// Copy the `files` already in `dir` to `backup`, for when they
+    /// couldn't be backed up as they were copied. The manifest is
+    /// kept in `backup`.
+    pub fn mirror(dir: &Path, backup: &Path, files: &[PathBuf]) -> CopyReport {
+        let imports = files
+            .iter()
+            .map(|file| {
+                (
+                    file.clone(),
+                    backup.join(file.strip_prefix(dir).unwrap_or(file)),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        CopyJob::new(backup, None, &imports).run(&copy_file, None)
+    }
+
+    /// Builder: whether the source can be read to verify the copy.
+    pub fn set_verify_source(mut self, verify_source: bool) -> Self {
+        self.verify_source = verify_source;
+        self
+    }
+
+    pub fn entries(&self) -> &[CopyEntry] {
+        &self.entries
+    }
+
+    /// The imports (source, destination) listed in the manifest of
+    /// an interrupted job in `dest`.
+    pub fn previous_imports(dest: &Path) -> Vec<(PathBuf, PathBuf)> {
+        Self::load_manifest(&dest.join(MANIFEST_NAME))
+            .into_iter()
+            .map(|entry| (entry.source, entry.dest))
+            .collect()
+    }
+
+    /// The copies done that weren't renamed yet.
+    pub fn not_renamed(&self) -> Vec<PathBuf> {
+        self.entries
+            .iter()
+            .filter(|entry| {
+                entry.renamed.is_none()
+                    && matches!(entry.state, CopyState::Done | CopyState::BackupFailed(_))
+            })
+            .map(|entry| entry.dest.clone())
+            .collect()
+    }
+
+    /// The copies were renamed with `renames` (from, to) after the
+    /// run. The manifest kept to resume is updated, so that the
+    /// renamed copies are found.
+    pub fn set_renamed(&mut self, renames: &[(PathBuf, PathBuf)]) {
+        for entry in &mut self.entries {
+            if let Some((_, to)) = renames.iter().find(|(from, _)| *from == entry.dest) {
+                entry.renamed = Some(to.clone());
+            }
+        }
+        if self.manifest_path().exists() {
+            self.save_manifest();
+        }
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.dest.join(MANIFEST_NAME)
+    }
+
+    fn load_manifest(path: &Path) -> Vec<CopyEntry> {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|manifest| {
+                serde_json::from_str(&manifest)
+                    .inspect_err(|err| err_out!("Invalid manifest {path:?}: {err}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    fn save_manifest(&self) {
+        let path = self.manifest_path();
+        match serde_json::to_string_pretty(&self.entries) {
+            Ok(manifest) => on_err_out!(std::fs::write(&path, manifest)),
+            Err(err) => err_out!("Couldn't serialize manifest: {err}"),
+        }
+    }
+
+    /// Whether the copies of a done `entry` are still there.
+    fn is_intact(entry: &CopyEntry) -> bool {
+        std::iter::once(entry.copied())
+            .chain(entry.backup.as_deref())
+            .all(|file| Self::matches(entry, file))
+    }
+
+    /// Whether `file` matches the checksum of `entry`.
+    fn matches(entry: &CopyEntry, file: &Path) -> bool {
+        entry
+            .checksum
+            .as_ref()
+            .is_some_and(|sum| checksum(file).is_ok_and(|file_sum| file_sum == *sum))
+    }
+
+    /// Copy `from` to `to` with `fetch`, through a temporary file,
+    /// and verify it matches `expected` if any. Return the checksum.
+    fn copy_verified(
+        fetch: &Fetcher,
+        from: &Path,
+        to: &Path,
+        expected: Option<&str>,
+    ) -> Result<String, String> {
+        let parent = to
+            .parent()
+            .ok_or_else(|| format!("No parent directory for {to:?}"))?;
+        std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        let mut part = to.as_os_str().to_os_string();
+        part.push(".part");
+        let part = PathBuf::from(part);
+        fetch(from, &part).map_err(|err| err.to_string())?;
+        let sum = checksum(&part).map_err(|err| err.to_string());
+        match sum {
+            Ok(sum) if expected.is_none_or(|expected| expected == sum) => {
+                std::fs::rename(&part, to).map_err(|err| err.to_string())?;
+                Ok(sum)
+            }
+            Ok(_) => {
+                on_err_out!(std::fs::remove_file(&part));
+                Err("Checksum mismatch".to_string())
+            }
+            Err(err) => {
+                on_err_out!(std::fs::remove_file(&part));
+                Err(err)
+            }
+        }
+    }
+
+    /// Copy the source of `entry` to its destination. An existing
+    /// destination is accepted if it matches the source. Return the
+    /// checksum.
+    fn copy_main(&self, entry: &CopyEntry, fetch: &Fetcher) -> Result<String, String> {
+        let expected = if self.verify_source {
+            Some(checksum(&entry.source).map_err(|err| err.to_string())?)
+        } else {
+            None
+        };
+        if entry.dest.exists() {
+            // Left by an interrupted copy.
+            return match expected {
+                Some(expected) if checksum(&entry.dest).is_ok_and(|sum| sum == expected) => {
+                    Ok(expected)
+                }
+                _ => Err(format!("{:?} already exists", entry.dest)),
+            };
+        }
+        Self::copy_verified(fetch, &entry.source, &entry.dest, expected.as_deref())
+    }
+
+    /// Copy the backup of `entry`, from the verified copy with `sum`.
+    /// An existing backup is accepted if it matches.
+    fn copy_backup(entry: &CopyEntry, backup: &Path, sum: &str) -> Result<(), String> {
+        if checksum(backup).is_ok_and(|backup_sum| backup_sum == sum) {
+            return Ok(());
+        }
+        Self::copy_verified(&copy_file, &entry.dest, backup, Some(sum))
+            .map(|_| ())
+            .map_err(|err| format!("Backup: {err}"))
+    }
+
+    /// Copy `entry`, and its backup. Return the checksum, if the main
+    /// copy is done, and the new state.
+    fn copy_entry(&self, entry: &CopyEntry, fetch: &Fetcher) -> (Option<String>, CopyState) {
+        let main = match (&entry.state, &entry.checksum) {
+            // Only the backup is left to do.
+            (CopyState::BackupFailed(_), Some(sum)) => Ok(sum.clone()),
+            _ => self.copy_main(entry, fetch),
+        };
+        match main {
+            Ok(sum) => {
+                let state = match entry.backup {
+                    Some(ref backup) => match Self::copy_backup(entry, backup, &sum) {
+                        Ok(_) => CopyState::Done,
+                        Err(err) => CopyState::BackupFailed(err),
+                    },
+                    None => CopyState::Done,
+                };
+                (Some(sum), state)
+            }
+            Err(err) => (None, CopyState::Failed(err)),
+        }
+    }
+
+    /// Run the copy, using `fetch` to get each source file. The
+    /// manifest is updated after each file. It is removed if all the
+    /// files are copied. The progress is reported to `job`, and the
+    /// copy stops if it is cancelled.
+    pub fn run(&mut self, fetch: &Fetcher, job: Option<&ImportJob>) -> CopyReport {
+        if let Some(ref backup) = self.backup {
+            dbg_out!("Backup to {backup:?}");
+        }
+        on_err_out!(std::fs::create_dir_all(&self.dest));
+        if let Some(job) = job {
+            job.copy_started(self.entries.len());
+        }
+        let mut report = CopyReport::default();
+        for idx in 0..self.entries.len() {
+            if job.is_some_and(ImportJob::is_cancelled) {
+                dbg_out!("Copy cancelled");
+                report.cancelled = true;
+                break;
+            }
+            if self.entries[idx].state != CopyState::Done {
+                let (sum, state) = self.copy_entry(&self.entries[idx], fetch);
+                let entry = &mut self.entries[idx];
+                if let CopyState::Failed(ref err) | CopyState::BackupFailed(ref err) = state {
+                    err_out!("Copy of {:?} failed: {err}", entry.source);
+                }
+                entry.checksum = sum;
+                entry.state = state;
+                self.save_manifest();
+            }
+            let entry = &self.entries[idx];
+            if matches!(entry.state, CopyState::Done | CopyState::BackupFailed(_)) {
+                if let Some(job) = job {
+                    job.file_copied(
+                        std::fs::metadata(entry.copied())
+                            .map(|metadata| metadata.len())
+                            .unwrap_or(0),
+                    );
+                }
+                report.copied.push(entry.copied().to_path_buf())
+            }
+            if let CopyState::Failed(ref err) | CopyState::BackupFailed(ref err) = entry.state {
+                report.failed.push((entry.source.clone(), err.clone()))
+            }
+        }
+        if report.is_success() && !report.cancelled {
+            let manifest = self.manifest_path();
+            if manifest.exists() {
+                on_err_out!(std::fs::remove_file(&manifest));
+            }
+        } else {
+            // Keep it to resume.
+            self.save_manifest();
+            report.manifest = Some(self.manifest_path());
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{CopyJob, CopyState, MANIFEST_NAME, copy_file};
+    use crate::importer::ImportJob;
+
+    #[test]
+    fn test_copy_job() {
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let source = dir.join("card");
+        let dest = dir.join("dest");
+        let backup = dir.join("backup");
+        std::fs::create_dir_all(&source).expect("Couldn't create dir");
+        std::fs::write(source.join("DSC_0001.NEF"), "raw1").expect("Couldn't write file");
+        std::fs::write(source.join("DSC_0002.NEF"), "raw2").expect("Couldn't write file");
+
+        let imports = vec![
+            (source.join("DSC_0001.NEF"), dest.join("2025/DSC_0001.NEF")),
+            (source.join("DSC_0002.NEF"), dest.join("2025/DSC_0002.NEF")),
+            (source.join("DSC_0003.NEF"), dest.join("2025/DSC_0003.NEF")),
+        ];
+        // The card was pulled: DSC_0003.NEF is missing.
+        let mut job = CopyJob::new(&dest, Some(&backup), &imports);
+        let report = job.run(&copy_file, None);
+        assert_eq!(report.copied.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, source.join("DSC_0003.NEF"));
+        assert_eq!(report.manifest, Some(dest.join(MANIFEST_NAME)));
+        assert!(dest.join("2025/DSC_0001.NEF").exists());
+        assert!(backup.join("2025/DSC_0002.NEF").exists());
+        assert!(!dest.join("2025/DSC_0003.NEF.part").exists());
+
+        // Resume: what is done is skipped.
+        std::fs::write(source.join("DSC_0003.NEF"), "raw3").expect("Couldn't write file");
+        let mut job = CopyJob::new(&dest, Some(&backup), &imports);
+        assert_eq!(job.entries()[0].state, CopyState::Done);
+        assert_eq!(job.entries()[2].state, CopyState::Pending);
+        let report = job.run(&copy_file, None);
+        assert!(report.is_success());
+        assert_eq!(report.copied.len(), 3);
+        assert!(report.manifest.is_none());
+        assert!(!dest.join(MANIFEST_NAME).exists());
+        assert!(backup.join("2025/DSC_0003.NEF").exists());
+
+        // A corrupted copy is detected.
+        let corrupt = |from: &Path, to: &Path| {
+            copy_file(from, to)?;
+            std::fs::write(to, "corrupted")
+        };
+        let imports = vec![(source.join("DSC_0001.NEF"), dest.join("DSC_0001.NEF"))];
+        let report = CopyJob::new(&dest, None, &imports).run(&corrupt, None);
+        assert_eq!(report.failed[0].1, "Checksum mismatch");
+        assert!(!dest.join("DSC_0001.NEF").exists());
+
+        // Cancelled, the manifest is kept to resume.
+        let import_job = ImportJob::new(async_channel::unbounded().0, || true);
+        let report = CopyJob::new(&dest, None, &imports).run(&copy_file, Some(&import_job));
+        assert!(report.cancelled);
+        assert!(report.copied.is_empty());
+        assert!(dest.join(MANIFEST_NAME).exists());
+    }
+
+    #[test]
+    fn test_copy_job_backup_failed() {
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let source = dir.join("card");
+        let dest = dir.join("dest");
+        let backup = dir.join("backup");
+        std::fs::create_dir_all(&source).expect("Couldn't create dir");
+        std::fs::create_dir_all(&backup).expect("Couldn't create dir");
+        std::fs::write(source.join("DSC_0001.NEF"), "raw1").expect("Couldn't write file");
+        // The backup directory can't be created.
+        std::fs::write(backup.join("2025"), "").expect("Couldn't write file");
+
+        let imports = vec![(source.join("DSC_0001.NEF"), dest.join("2025/DSC_0001.NEF"))];
+        let report = CopyJob::new(&dest, Some(&backup), &imports).run(&copy_file, None);
+        assert_eq!(report.copied, vec![dest.join("2025/DSC_0001.NEF")]);
+        assert_eq!(report.failed.len(), 1);
+        assert!(report.failed[0].1.starts_with("Backup: "));
+        assert!(dest.join(MANIFEST_NAME).exists());
+
+        // Resume: only the backup is retried.
+        std::fs::remove_file(backup.join("2025")).expect("Couldn't remove file");
+        let mut job = CopyJob::new(&dest, Some(&backup), &imports);
+        assert!(matches!(job.entries()[0].state, CopyState::BackupFailed(_)));
+        let no_card = |_: &Path, _: &Path| Err(std::io::Error::other("No card"));
+        let report = job.run(&no_card, None);
+        assert!(report.is_success());
+        assert!(backup.join("2025/DSC_0001.NEF").exists());
+        assert!(!dest.join(MANIFEST_NAME).exists());
+
+        // Without the manifest, the existing copy is accepted if it
+        // matches.
+        let report = CopyJob::new(&dest, None, &imports).run(&copy_file, None);
+        assert!(report.is_success());
+        std::fs::write(dest.join("2025/DSC_0001.NEF"), "other").expect("Couldn't write file");
+        let report = CopyJob::new(&dest, None, &imports).run(&copy_file, None);
+        assert!(report.failed[0].1.ends_with("already exists"));
+    }
+
+    #[test]
+    fn test_copy_job_renamed() {
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let source = dir.join("card");
+        let dest = dir.join("dest");
+        std::fs::create_dir_all(&source).expect("Couldn't create dir");
+        std::fs::write(source.join("DSC_0001.NEF"), "raw1").expect("Couldn't write file");
+
+        let imports = vec![
+            (source.join("DSC_0001.NEF"), dest.join("DSC_0001.NEF")),
+            (source.join("DSC_0002.NEF"), dest.join("DSC_0002.NEF")),
+        ];
+        let mut job = CopyJob::new(&dest, None, &imports);
+        let report = job.run(&copy_file, None);
+        assert_eq!(report.failed.len(), 1);
+        // Renamed after the copy, like from a camera.
+        assert_eq!(job.not_renamed(), vec![dest.join("DSC_0001.NEF")]);
+        std::fs::rename(dest.join("DSC_0001.NEF"), dest.join("trip-0001.NEF"))
+            .expect("Couldn't rename");
+        job.set_renamed(&[(dest.join("DSC_0001.NEF"), dest.join("trip-0001.NEF"))]);
+
+        // Resume: the renamed copy is found.
+        std::fs::write(source.join("DSC_0002.NEF"), "raw2").expect("Couldn't write file");
+        let mut job = CopyJob::new(&dest, None, &imports);
+        assert_eq!(job.entries()[0].state, CopyState::Done);
+        assert!(job.not_renamed().is_empty());
+        let report = job.run(&copy_file, None);
+        assert!(report.is_success());
+        assert_eq!(
+            report.copied,
+            vec![dest.join("trip-0001.NEF"), dest.join("DSC_0002.NEF")]
+        );
+        assert_eq!(job.not_renamed(), vec![dest.join("DSC_0002.NEF")]);
+    }
+}
@@ -0,0 +1,344 @@
+/*
+ * niepce - npc-engine/importer/darktable_importer.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+use npc_fwk::{dbg_out, err_out};
+
+use super::library_content::{
+    ColourLabel, ContentFolder, ContentImage, LibraryContent, has_tables,
+};
+use super::libraryimporter::{Error, ImportReport, LibraryImporter, LibraryImporterProbe, Result};
+use crate::libraryclient::LibraryClient;
+
+/// The rating bits of `images.flags`.
+const RATING_MASK: i64 = 0x7;
+/// The rating value of the rejected images, before darktable 3.0.
+const RATING_REJECTED: i64 = 6;
+/// The flag of the rejected images.
+const FLAG_REJECTED: i64 = 0x8;
+/// The prefix of the tags darktable sets itself.
+const INTERNAL_TAGS: &str = "darktable|";
+
+/// Library importer for darktable.
+///
+/// The film rolls are the folders. The tags are stored in `data.db`,
+/// next to `library.db`, and are already hierarchical.
+#[derive(Default)]
+pub struct DarktableImporter {
+    content: Option<LibraryContent>,
+    /// The root folder mapping table
+    root_folder_map: BTreeMap<String, String>,
+}
+
+impl DarktableImporter {
+    pub fn new() -> DarktableImporter {
+        DarktableImporter::default()
+    }
+
+    /// The root folders of the film roll `folders`: their parents,
+    /// without those inside another.
+    fn find_roots(folders: &[String]) -> Vec<String> {
+        let parents = folders
+            .iter()
+            .map(|folder| {
+                Path::new(folder)
+                    .parent()
+                    .map(|parent| parent.to_string_lossy().to_string())
+                    .unwrap_or_else(|| folder.clone())
+            })
+            .collect::<BTreeSet<_>>();
+        parents
+            .iter()
+            .filter(|parent| {
+                !parents
+                    .iter()
+                    .any(|other| other != *parent && Path::new(parent).starts_with(other))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn colour_label(color: i64) -> Option<ColourLabel> {
+        match color {
+            0 => Some(ColourLabel::Red),
+            1 => Some(ColourLabel::Yellow),
+            2 => Some(ColourLabel::Green),
+            3 => Some(ColourLabel::Blue),
+            4 => Some(ColourLabel::Purple),
+            _ => None,
+        }
+    }
+
+    /// The tag names by id, from `data.db`. Empty if it's not there.
+    fn load_tags(conn: &Connection, path: &Path) -> rusqlite::Result<BTreeMap<i64, String>> {
+        let data_db = path.with_file_name("data.db");
+        if !data_db.exists() {
+            err_out!("No darktable data.db: tags not imported");
+            return Ok(BTreeMap::new());
+        }
+        conn.execute("ATTACH DATABASE ?1 AS data", [data_db.to_string_lossy()])?;
+        let mut stmt = conn.prepare("SELECT id, name FROM data.tags")?;
+        let tags = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+            .collect::<rusqlite::Result<BTreeMap<i64, String>>>()?;
+        Ok(tags
+            .into_iter()
+            .filter(|(_, name)| !name.starts_with(INTERNAL_TAGS))
+            .collect())
+    }
+
+    fn load(conn: &Connection, path: &Path) -> rusqlite::Result<LibraryContent> {
+        let mut content = LibraryContent::default();
+
+        let mut stmt = conn.prepare("SELECT id, folder FROM film_rolls ORDER BY folder")?;
+        let film_rolls = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let folders = film_rolls
+            .iter()
+            .map(|(_, folder)| folder.clone())
+            .collect::<Vec<_>>();
+        let roots = Self::find_roots(&folders);
+        content.folders = film_rolls
+            .into_iter()
+            .filter_map(|(id, folder)| {
+                let root = roots
+                    .iter()
+                    .find(|root| Path::new(&folder).starts_with(root))?;
+                Some(ContentFolder {
+                    id,
+                    path_from_root: folder.strip_prefix(root.trim_end_matches('/'))?.to_string(),
+                    root: root.clone(),
+                })
+            })
+            .collect();
+
+        let tags = Self::load_tags(conn, path)?;
+        let mut image_tags = BTreeMap::<i64, Vec<String>>::new();
+        if !tags.is_empty() {
+            let mut stmt = conn.prepare("SELECT imgid, tagid FROM tagged_images")?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                if let Some(tag) = tags.get(&row.get::<_, i64>(1)?) {
+                    image_tags.entry(row.get(0)?).or_default().push(tag.clone());
+                }
+            }
+        }
+        content.keywords = tags.into_values().collect();
+
+        let mut labels = BTreeMap::<i64, ColourLabel>::new();
+        let mut stmt = conn.prepare("SELECT imgid, color FROM color_labels ORDER BY color")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            if let Some(label) = Self::colour_label(row.get(1)?) {
+                // The first colour is kept.
+                labels.entry(row.get(0)?).or_insert(label);
+            }
+        }
+
+        // The duplicates are the same file: the first is imported, and
+        // the others as its virtual copies.
+        let mut seen = BTreeMap::<(i64, String), usize>::new();
+        let mut stmt =
+            conn.prepare("SELECT id, film_id, filename, flags FROM images ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let folder: i64 = row.get(1)?;
+            let name: String = row.get(2)?;
+            let flags: i64 = row.get(3)?;
+            let rating = flags & RATING_MASK;
+            let rejected = rating == RATING_REJECTED || flags & FLAG_REJECTED != 0;
+            let image = ContentImage {
+                folder,
+                name: name.clone(),
+                rating: (!rejected && rating > 0).then_some(rating as i32),
+                label: labels.get(&id).copied(),
+                flag: if rejected { -1 } else { 0 },
+                keywords: image_tags.remove(&id).unwrap_or_default(),
+                copies: vec![],
+            };
+            if let Some(index) = seen.get(&(folder, name.clone())) {
+                dbg_out!("Duplicate {}", &name);
+                content.images[*index].copies.push(image);
+            } else {
+                seen.insert((folder, name), content.images.len());
+                content.images.push(image);
+            }
+        }
+
+        Ok(content)
+    }
+}
+
+impl LibraryImporter for DarktableImporter {
+    fn name(&self) -> &'static str {
+        "darktable"
+    }
+
+    fn init_importer(&mut self, path: &Path) -> Result<()> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|_| Error::NoInput)?;
+        let content = Self::load(&conn, path).map_err(|err| {
+            err_out!("Failed to read darktable library: {err}");
+            Error::UnsupportedFormat
+        })?;
+        self.content = Some(content);
+        Ok(())
+    }
+
+    fn import_library(&mut self, libclient: &LibraryClient) -> Result<ImportReport> {
+        let content = self.content.as_ref().ok_or(Error::NoInput)?;
+        Ok(content.import(&self.root_folder_map, libclient))
+    }
+
+    fn root_folders(&mut self) -> Vec<String> {
+        self.content
+            .as_ref()
+            .map(|content| {
+                content
+                    .root_folders()
+                    .into_iter()
+                    .map(|root| self.root_folder_map.get(&root).cloned().unwrap_or(root))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn map_root_folder(&mut self, orig: &str, dest: &str) {
+        self.root_folder_map
+            .insert(orig.to_string(), dest.to_string());
+    }
+}
+
+impl LibraryImporterProbe for DarktableImporter {
+    /// Detect if this is a darktable library
+    fn can_import_library(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "db")
+            && has_tables(path, &["film_rolls", "images", "color_labels"])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use rusqlite::Connection;
+
+    use super::DarktableImporter;
+    use crate::importer::library_content::{ColourLabel, ContentFolder};
+    use crate::importer::{LibraryImporter, LibraryImporterProbe};
+
+    fn create_library(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("library.db");
+        let conn = Connection::open(&path).expect("library created");
+        conn.execute_batch(
+            "CREATE TABLE film_rolls (id INTEGER PRIMARY KEY, access_timestamp INTEGER, \
+             folder VARCHAR(1024) NOT NULL); \
+             CREATE TABLE images (id INTEGER PRIMARY KEY AUTOINCREMENT, group_id INTEGER, \
+             film_id INTEGER, filename VARCHAR, flags INTEGER, version INTEGER); \
+             CREATE TABLE color_labels (imgid INTEGER, color INTEGER); \
+             CREATE TABLE tagged_images (imgid INTEGER, tagid INTEGER, position INTEGER); \
+             INSERT INTO film_rolls (id, folder) VALUES (1, '/home/user/Pictures/2024/trip'); \
+             INSERT INTO film_rolls (id, folder) VALUES (2, '/home/user/Pictures/2024/home'); \
+             INSERT INTO film_rolls (id, folder) VALUES (3, '/media/disk/old'); \
+             INSERT INTO images VALUES (1, 1, 1, 'IMG_0001.CR2', 4, 0); \
+             INSERT INTO images VALUES (2, 2, 1, 'IMG_0001.CR2', 1, 1); \
+             INSERT INTO images VALUES (3, 3, 2, 'IMG_0002.CR2', 9, 0); \
+             INSERT INTO images VALUES (4, 4, 3, 'IMG_0003.CR2', 6, 0); \
+             INSERT INTO color_labels VALUES (1, 2); \
+             INSERT INTO color_labels VALUES (1, 0); \
+             INSERT INTO tagged_images VALUES (1, 1, 0); \
+             INSERT INTO tagged_images VALUES (1, 2, 0);",
+        )
+        .expect("library filled");
+
+        let data = Connection::open(dir.join("data.db")).expect("data created");
+        data.execute_batch(
+            "CREATE TABLE tags (id INTEGER PRIMARY KEY, name VARCHAR, synonyms VARCHAR, \
+             flags INTEGER); \
+             INSERT INTO tags (id, name) VALUES (1, 'places|France|Paris'); \
+             INSERT INTO tags (id, name) VALUES (2, 'darktable|format|cr2'); \
+             INSERT INTO tags (id, name) VALUES (3, 'people');",
+        )
+        .expect("data filled");
+        path
+    }
+
+    #[test]
+    fn test_darktable_importer() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        assert!(!DarktableImporter::can_import_library(
+            &dir.path().join("library.db")
+        ));
+        let path = create_library(dir.path());
+        assert!(DarktableImporter::can_import_library(&path));
+        assert!(!DarktableImporter::can_import_library(
+            &dir.path().join("data.db")
+        ));
+
+        let mut importer = DarktableImporter::new();
+        assert_eq!(importer.name(), "darktable");
+        importer.init_importer(&path).expect("library loaded");
+
+        assert_eq!(
+            importer.root_folders(),
+            vec!["/home/user/Pictures/2024", "/media/disk"]
+        );
+        importer.map_root_folder("/media/disk", "/mnt/backup");
+        assert_eq!(
+            importer.root_folders(),
+            vec!["/home/user/Pictures/2024", "/mnt/backup"]
+        );
+
+        let content = importer.content.as_ref().unwrap();
+        assert_eq!(
+            content.folders[0],
+            ContentFolder {
+                id: 2,
+                root: "/home/user/Pictures/2024".into(),
+                path_from_root: "/home".into(),
+            }
+        );
+        assert_eq!(content.keywords, vec!["places|France|Paris", "people"]);
+
+        // The duplicate is a copy.
+        assert_eq!(content.images.len(), 3);
+        let image = &content.images[0];
+        assert_eq!(image.name, "IMG_0001.CR2");
+        assert_eq!(image.rating, Some(4));
+        assert_eq!(image.label, Some(ColourLabel::Red));
+        assert_eq!(image.flag, 0);
+        assert_eq!(image.keywords, vec!["places|France|Paris"]);
+        assert_eq!(image.copies.len(), 1);
+        assert_eq!(image.copies[0].rating, Some(1));
+        assert_eq!(image.copies[0].label, None);
+
+        // Rejected, with the flag or the legacy rating.
+        assert_eq!(content.images[1].flag, -1);
+        assert_eq!(content.images[1].rating, None);
+        assert_eq!(content.images[2].flag, -1);
+    }
+}
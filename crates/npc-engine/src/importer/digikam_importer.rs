@@ -0,0 +1,385 @@
+/*
+ * niepce - npc-engine/importer/digikam_importer.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::{Connection, OpenFlags};
+
+use npc_fwk::err_out;
+use npc_fwk::utils::exempi::KEYWORD_PATH_SEPARATOR;
+
+use super::library_content::{
+    ColourLabel, ContentFolder, ContentImage, LibraryContent, has_tables,
+};
+use super::libraryimporter::{Error, ImportReport, LibraryImporter, LibraryImporterProbe, Result};
+use crate::libraryclient::LibraryClient;
+
+/// The top level tag of the tags digiKam uses for colour and pick labels.
+const INTERNAL_TAGS: &str = "_Digikam_Internal_Tags_";
+/// The `Images.status` of the files removed from the disk.
+const STATUS_OBSOLETE: i64 = 3;
+
+/// What an internal tag sets.
+enum InternalTag {
+    Label(ColourLabel),
+    Flag(i32),
+}
+
+/// Library importer for digiKam.
+///
+/// The albums are directories: they are imported as folders. The
+/// album roots are the root folders.
+#[derive(Default)]
+pub struct DigikamImporter {
+    content: Option<LibraryContent>,
+    /// The root folder mapping table
+    root_folder_map: BTreeMap<String, String>,
+}
+
+impl DigikamImporter {
+    pub fn new() -> DigikamImporter {
+        DigikamImporter::default()
+    }
+
+    /// Decode the percent encoding of the album root identifier.
+    fn percent_decode(s: &str) -> String {
+        let mut bytes = vec![];
+        let mut iter = s.bytes();
+        while let Some(b) = iter.next() {
+            if b == b'%' {
+                let hex = iter.next().zip(iter.next()).and_then(|(h, l)| {
+                    u8::from_str_radix(std::str::from_utf8(&[h, l]).ok()?, 16).ok()
+                });
+                if let Some(hex) = hex {
+                    bytes.push(hex);
+                    continue;
+                }
+            }
+            bytes.push(b);
+        }
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+
+    /// The path of the album root. The `identifier` has the path of
+    /// the volume, unless it's identified by UUID: then it's assumed
+    /// to be mounted on `/`. `specific_path` is relative to the volume.
+    fn album_root_path(identifier: &str, specific_path: &str) -> String {
+        let volume = identifier
+            .split_once('?')
+            .map(|(_, query)| query)
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|param| {
+                param
+                    .strip_prefix("path=")
+                    .or_else(|| param.strip_prefix("mountpath="))
+            })
+            .map(Self::percent_decode)
+            .unwrap_or_default();
+        let path = format!("{}{}", volume.trim_end_matches('/'), specific_path);
+        match path.trim_end_matches('/') {
+            "" => "/".to_string(),
+            path => path.to_string(),
+        }
+    }
+
+    fn internal_tag(name: &str) -> Option<InternalTag> {
+        match name {
+            "Color Label Red" => Some(InternalTag::Label(ColourLabel::Red)),
+            "Color Label Orange" => Some(InternalTag::Label(ColourLabel::Orange)),
+            "Color Label Yellow" => Some(InternalTag::Label(ColourLabel::Yellow)),
+            "Color Label Green" => Some(InternalTag::Label(ColourLabel::Green)),
+            "Color Label Blue" => Some(InternalTag::Label(ColourLabel::Blue)),
+            "Color Label Magenta" => Some(InternalTag::Label(ColourLabel::Purple)),
+            "Pick Label Rejected" => Some(InternalTag::Flag(-1)),
+            "Pick Label Accepted" => Some(InternalTag::Flag(1)),
+            _ => None,
+        }
+    }
+
+    /// Load the tags. Return the keyword paths by id, and the internal
+    /// tags by id.
+    fn load_tags(
+        conn: &Connection,
+    ) -> rusqlite::Result<(BTreeMap<i64, String>, BTreeMap<i64, InternalTag>)> {
+        let mut stmt = conn.prepare("SELECT id, pid, name FROM Tags")?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, (row.get::<_, i64>(1)?, row.get(2)?)))
+            })?
+            .collect::<rusqlite::Result<BTreeMap<i64, (i64, String)>>>()?;
+
+        let mut keywords = BTreeMap::new();
+        let mut internal = BTreeMap::new();
+        for (id, (pid, name)) in &tags {
+            let mut path = vec![name.as_str()];
+            let mut parent = *pid;
+            // Bound the walk to not loop on a broken tree.
+            while parent != 0 && path.len() <= tags.len() {
+                let Some((pid, name)) = tags.get(&parent) else {
+                    break;
+                };
+                path.insert(0, name.as_str());
+                parent = *pid;
+            }
+            if path[0] == INTERNAL_TAGS {
+                if let Some(tag) = Self::internal_tag(name) {
+                    internal.insert(*id, tag);
+                }
+            } else {
+                keywords.insert(*id, path.join(&KEYWORD_PATH_SEPARATOR.to_string()));
+            }
+        }
+        Ok((keywords, internal))
+    }
+
+    fn load(conn: &Connection) -> rusqlite::Result<LibraryContent> {
+        let mut content = LibraryContent::default();
+
+        let mut stmt = conn.prepare("SELECT id, identifier, specificPath FROM AlbumRoots")?;
+        let roots = stmt
+            .query_map([], |row| {
+                let identifier: Option<String> = row.get(1)?;
+                let specific_path: Option<String> = row.get(2)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    Self::album_root_path(
+                        identifier.as_deref().unwrap_or_default(),
+                        specific_path.as_deref().unwrap_or_default(),
+                    ),
+                ))
+            })?
+            .collect::<rusqlite::Result<BTreeMap<i64, String>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, albumRoot, relativePath FROM Albums ORDER BY albumRoot, relativePath",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let Some(root) = roots.get(&row.get::<_, i64>(1)?) else {
+                continue;
+            };
+            let relative_path: String = row.get(2)?;
+            content.folders.push(ContentFolder {
+                id: row.get(0)?,
+                root: root.clone(),
+                path_from_root: relative_path.trim_end_matches('/').to_string(),
+            });
+        }
+
+        let (keywords, internal) = Self::load_tags(conn)?;
+        let mut image_tags = BTreeMap::<i64, Vec<i64>>::new();
+        let mut stmt = conn.prepare("SELECT imageid, tagid FROM ImageTags")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            image_tags.entry(row.get(0)?).or_default().push(row.get(1)?);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT Images.id, album, name, rating FROM Images \
+             LEFT JOIN ImageInformation ON Images.id = ImageInformation.imageid \
+             WHERE album IS NOT NULL AND status != ?1 ORDER BY Images.id",
+        )?;
+        let mut rows = stmt.query([STATUS_OBSOLETE])?;
+        while let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let rating: Option<i32> = row.get(3)?;
+            let mut image = ContentImage {
+                folder: row.get(1)?,
+                name: row.get(2)?,
+                // -1 is for not rated.
+                rating: rating.filter(|rating| *rating > 0),
+                ..Default::default()
+            };
+            for tag in image_tags.remove(&id).unwrap_or_default() {
+                match internal.get(&tag) {
+                    Some(InternalTag::Label(label)) => image.label = Some(*label),
+                    Some(InternalTag::Flag(flag)) => image.flag = *flag,
+                    None => {
+                        if let Some(keyword) = keywords.get(&tag) {
+                            image.keywords.push(keyword.clone());
+                        }
+                    }
+                }
+            }
+            content.images.push(image);
+        }
+        content.keywords = keywords.into_values().collect();
+
+        Ok(content)
+    }
+}
+
+impl LibraryImporter for DigikamImporter {
+    fn name(&self) -> &'static str {
+        "digiKam"
+    }
+
+    fn init_importer(&mut self, path: &Path) -> Result<()> {
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|_| Error::NoInput)?;
+        let content = Self::load(&conn).map_err(|err| {
+            err_out!("Failed to read digiKam library: {err}");
+            Error::UnsupportedFormat
+        })?;
+        self.content = Some(content);
+        Ok(())
+    }
+
+    fn import_library(&mut self, libclient: &LibraryClient) -> Result<ImportReport> {
+        let content = self.content.as_ref().ok_or(Error::NoInput)?;
+        Ok(content.import(&self.root_folder_map, libclient))
+    }
+
+    fn root_folders(&mut self) -> Vec<String> {
+        self.content
+            .as_ref()
+            .map(|content| {
+                content
+                    .root_folders()
+                    .into_iter()
+                    .map(|root| self.root_folder_map.get(&root).cloned().unwrap_or(root))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn map_root_folder(&mut self, orig: &str, dest: &str) {
+        self.root_folder_map
+            .insert(orig.to_string(), dest.to_string());
+    }
+}
+
+impl LibraryImporterProbe for DigikamImporter {
+    /// Detect if this is a digiKam library
+    fn can_import_library(path: &Path) -> bool {
+        path.extension().is_some_and(|ext| ext == "db")
+            && has_tables(path, &["AlbumRoots", "Albums", "Images"])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use rusqlite::Connection;
+
+    use super::DigikamImporter;
+    use crate::importer::library_content::{ColourLabel, ContentFolder};
+    use crate::importer::{LibraryImporter, LibraryImporterProbe};
+
+    fn create_library(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("digikam4.db");
+        let conn = Connection::open(&path).expect("library created");
+        conn.execute_batch(
+            "CREATE TABLE AlbumRoots (id INTEGER PRIMARY KEY, label TEXT, status INTEGER, \
+             type INTEGER, identifier TEXT, specificPath TEXT); \
+             CREATE TABLE Albums (id INTEGER PRIMARY KEY, albumRoot INTEGER, \
+             relativePath TEXT, date DATE, caption TEXT, collection TEXT, icon INTEGER); \
+             CREATE TABLE Images (id INTEGER PRIMARY KEY, album INTEGER, name TEXT, \
+             status INTEGER, category INTEGER); \
+             CREATE TABLE ImageInformation (imageid INTEGER PRIMARY KEY, rating INTEGER); \
+             CREATE TABLE Tags (id INTEGER PRIMARY KEY, pid INTEGER, name TEXT); \
+             CREATE TABLE ImageTags (imageid INTEGER, tagid INTEGER); \
+             INSERT INTO AlbumRoots VALUES (1, 'Pictures', 0, 1, \
+             'volumeid:?uuid=8a7f2c1e', '/home/user/Pictures'); \
+             INSERT INTO AlbumRoots VALUES (2, 'Disk', 0, 2, \
+             'volumeid:?path=%2Fmedia%2Fdisk', '/'); \
+             INSERT INTO Albums (id, albumRoot, relativePath) VALUES (1, 1, '/'); \
+             INSERT INTO Albums (id, albumRoot, relativePath) VALUES (2, 1, '/2024/trip'); \
+             INSERT INTO Albums (id, albumRoot, relativePath) VALUES (3, 2, '/old'); \
+             INSERT INTO Images VALUES (1, 2, 'IMG_0001.CR2', 1, 1); \
+             INSERT INTO Images VALUES (2, 3, 'IMG_0002.CR2', 1, 1); \
+             INSERT INTO Images VALUES (3, NULL, 'IMG_0003.CR2', 3, 1); \
+             INSERT INTO ImageInformation VALUES (1, 3); \
+             INSERT INTO ImageInformation VALUES (2, -1); \
+             INSERT INTO Tags VALUES (1, 0, 'places'); \
+             INSERT INTO Tags VALUES (2, 1, 'France'); \
+             INSERT INTO Tags VALUES (3, 0, '_Digikam_Internal_Tags_'); \
+             INSERT INTO Tags VALUES (4, 3, 'Color Label Green'); \
+             INSERT INTO Tags VALUES (5, 3, 'Pick Label Rejected'); \
+             INSERT INTO ImageTags VALUES (1, 2); \
+             INSERT INTO ImageTags VALUES (1, 4); \
+             INSERT INTO ImageTags VALUES (2, 5);",
+        )
+        .expect("library filled");
+        path
+    }
+
+    #[test]
+    fn test_digikam_importer() {
+        assert_eq!(
+            DigikamImporter::album_root_path("volumeid:?path=%2Fmedia%2Fdisk", "/"),
+            "/media/disk"
+        );
+        assert_eq!(
+            DigikamImporter::album_root_path("volumeid:?uuid=8a7f2c1e", "/Photos/"),
+            "/Photos"
+        );
+
+        let dir = tempfile::tempdir().expect("temp dir");
+        let path = create_library(dir.path());
+        assert!(DigikamImporter::can_import_library(&path));
+        assert!(!DigikamImporter::can_import_library(Path::new(
+            "/tmp/catalog.lrcat"
+        )));
+
+        let mut importer = DigikamImporter::new();
+        assert_eq!(importer.name(), "digiKam");
+        importer.init_importer(&path).expect("library loaded");
+
+        assert_eq!(
+            importer.root_folders(),
+            vec!["/home/user/Pictures", "/media/disk"]
+        );
+        importer.map_root_folder("/home/user/Pictures", "/home/other/Pictures");
+        assert_eq!(
+            importer.root_folders(),
+            vec!["/home/other/Pictures", "/media/disk"]
+        );
+
+        let content = importer.content.as_ref().unwrap();
+        assert_eq!(content.folders.len(), 3);
+        assert_eq!(content.folders[0].path_from_root, "");
+        assert_eq!(
+            content.folders[2],
+            ContentFolder {
+                id: 3,
+                root: "/media/disk".into(),
+                path_from_root: "/old".into(),
+            }
+        );
+        // The internal tags aren't keywords.
+        assert_eq!(content.keywords, vec!["places", "places|France"]);
+
+        // The removed file isn't there.
+        assert_eq!(content.images.len(), 2);
+        let image = &content.images[0];
+        assert_eq!(image.folder, 2);
+        assert_eq!(image.rating, Some(3));
+        assert_eq!(image.label, Some(ColourLabel::Green));
+        assert_eq!(image.flag, 0);
+        assert_eq!(image.keywords, vec!["places|France"]);
+
+        let image = &content.images[1];
+        assert_eq!(image.rating, None);
+        assert_eq!(image.flag, -1);
+    }
+}
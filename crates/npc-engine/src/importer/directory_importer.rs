@@ -21,10 +21,13 @@ use std::path::Path;
 
 use npc_fwk::base::{Executor, WorkerStatus};
 use npc_fwk::utils::FileList;
-use npc_fwk::{Date, XmpMeta, dbg_out, err_out, on_err_out};
+use npc_fwk::{Date, XmpMeta, dbg_out, err_out};
 
 use super::{ImportRequest, ImportedFile};
-use crate::importer::{FileImporter, ImportBackend, Importer, PreviewReady, SourceContentReady};
+use crate::importer::{
+    CopyJob, FileImporter, ImportBackend, ImportJob, Importer, PreviewReady, SourceContentReady,
+    copy_job,
+};
 
 #[derive(Clone)]
 pub struct DirectoryImportedFile {
@@ -131,59 +134,61 @@ impl ImportBackend for DirectoryImporter {
                 dbg_out!("path {}", path);
                 let xmp = XmpMeta::new_from_file(path, false);
                 let date = xmp.as_ref().and_then(|xmp| xmp.creation_date());
+                let camera = xmp.as_ref().and_then(|xmp| xmp.camera_model());
                 let orientation = xmp
                     .as_ref()
                     .and_then(|xmp| xmp.orientation())
                     .map(|orientation| orientation as u32);
                 let thumbnail =
                     npc_fwk::toolkit::Thumbnail::thumbnail_file(path, 160, 160, orientation);
-                callback(Some(path.to_string()), thumbnail, date);
+                callback(Some(path.to_string()), thumbnail, date, camera);
                 if terminate() {
                     err_out!("Terminated thumbnailing");
                     break;
                 }
             }
-            callback(None, None, None);
+            callback(None, None, None, None);
             WorkerStatus::Stop
         });
     }
 
     /// Do the import
-    fn do_import(&self, request: &ImportRequest, callback: FileImporter) {
+    fn do_import(
+        &self,
+        executor: &Executor,
+        request: &ImportRequest,
+        job: &ImportJob,
+        callback: FileImporter,
+    ) {
         if self.copy {
             let dest_dir = request.dest_dir().to_path_buf();
             let source = std::path::PathBuf::from(request.source());
             let sorting = request.sorting();
+            let rename = request.rename_template().cloned();
+            let selection = request.selection().clone();
+            let backup = request.backup_dir().map(Path::to_path_buf);
             let recursive = self.recursive;
-            on_err_out!(
-                std::thread::Builder::new()
-                    .name("import copy files".to_string())
-                    .spawn(move || {
-                        let imports = Importer::get_imports(&source, &dest_dir, sorting, recursive);
-                        let files = FileList(
-                            imports
-                                .iter()
-                                .filter_map(|import| {
-                                    std::fs::create_dir_all(
-                                        import.1.parent().expect("No parent, bailing out."),
-                                    )
-                                    .inspect_err(|err| {
-                                        err_out!("Couldn't create directories: {err:?}");
-                                    })
-                                    .ok()?;
-                                    npc_fwk::utils::copy(&import.0, &import.1)
-                                        .expect("Couldn't copy files.");
-                                    Some(import.1.clone())
-                                })
-                                .collect(),
-                        );
-                        callback(&dest_dir, &files);
-                    })
-            );
+            let job = job.clone();
+            executor.run(move || {
+                let imports = Importer::get_imports(
+                    &source,
+                    &dest_dir,
+                    sorting,
+                    recursive,
+                    rename.as_ref(),
+                    &selection,
+                );
+                let mut copy = CopyJob::new(&dest_dir, backup.as_deref(), &imports);
+                let report = copy.run(&copy_job::copy_file, Some(&job));
+                let files = FileList(report.copied.clone());
+                callback(&dest_dir, &files, Some(&report));
+                WorkerStatus::Stop
+            });
         } else {
             let files =
                 FileList::files_from_directory(request.source(), |_| true, self.recursive, None);
-            callback(&std::path::PathBuf::from(request.source()), &files);
+            let files = FileList(request.selection().filter(&files.0));
+            callback(&std::path::PathBuf::from(request.source()), &files, None);
         }
     }
 }
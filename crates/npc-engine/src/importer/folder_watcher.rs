@@ -0,0 +1,318 @@
+/*
+ * niepce - npc-engine/importer/folder_watcher.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Watch the folders to import the new files automatically.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use std::time::{Duration, Instant};
+
+use gio::prelude::*;
+use npc_fwk::{gio, glib};
+
+use crate::catalog::WatchedFolder;
+use crate::catalog::filebundle::FileBundle;
+use npc_fwk::utils::FileList;
+use npc_fwk::{dbg_out, err_out};
+
+/// How long the files of a bundle must be left alone before being
+/// imported. A RAW+JPEG pair written seconds apart is imported
+/// together.
+const SETTLE_DELAY: Duration = Duration::from_secs(3);
+/// How often the pending files are checked.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The new files waiting to settle, by bundle.
+#[derive(Default)]
+struct PendingFiles {
+    /// The files by bundle key, with the last time one changed.
+    bundles: HashMap<PathBuf, (Instant, Vec<PathBuf>)>,
+}
+
+impl PendingFiles {
+    /// The key of the bundle of `path`: the path up to the first
+    /// dot of the file name, so that `IMG_0001.CR2.xmp` goes with
+    /// `IMG_0001.CR2`.
+    fn bundle_key(path: &Path) -> Option<PathBuf> {
+        let name = path.file_name()?.to_str()?;
+        let stem = name.split('.').next().filter(|stem| !stem.is_empty())?;
+        Some(path.with_file_name(stem))
+    }
+
+    /// The file at `path` was created at `now`.
+    fn add(&mut self, path: &Path, now: Instant) {
+        let Some(key) = Self::bundle_key(path) else {
+            return;
+        };
+        let (time, files) = self.bundles.entry(key).or_default();
+        *time = now;
+        if !files.iter().any(|file| file == path) {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    /// The file at `path` was changed at `now`. Only the files
+    /// already pending are concerned: the others aren't new.
+    fn touch(&mut self, path: &Path, now: Instant) {
+        if let Some(key) = Self::bundle_key(path)
+            && let Some((time, files)) = self.bundles.get_mut(&key)
+            && files.iter().any(|file| file == path)
+        {
+            *time = now;
+        }
+    }
+
+    /// The file at `path` is gone.
+    fn remove(&mut self, path: &Path) {
+        if let Some(key) = Self::bundle_key(path)
+            && let Some((_, files)) = self.bundles.get_mut(&key)
+        {
+            files.retain(|file| file != path);
+            if files.is_empty() {
+                self.bundles.remove(&key);
+            }
+        }
+    }
+
+    /// Take the files of the bundles left alone for `delay` at `now`.
+    fn take_settled(&mut self, now: Instant, delay: Duration) -> Vec<PathBuf> {
+        let settled = self
+            .bundles
+            .iter()
+            .filter(|(_, (time, _))| now.duration_since(*time) >= delay)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        settled
+            .iter()
+            .filter_map(|key| self.bundles.remove(key))
+            .flat_map(|(_, files)| files)
+            .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bundles.is_empty()
+    }
+}
+
+/// Return the files of the media bundles among `files`. The other
+/// files and those that are gone are left out.
+fn media_bundles(files: &[PathBuf]) -> Vec<PathBuf> {
+    let files = files
+        .iter()
+        .filter(|file| file.is_file())
+        .cloned()
+        .collect::<Vec<_>>();
+    FileBundle::filter_bundles(&files)
+        .iter()
+        .filter(|bundle| FileList::file_is_media(bundle.main()))
+        .flat_map(FileBundle::all_files)
+        .collect()
+}
+
+/// Called with the watched folder and the new files to import.
+pub type NewFilesCallback = Box<dyn Fn(&WatchedFolder, Vec<PathBuf>)>;
+
+struct Watch {
+    folder: WatchedFolder,
+    _monitor: gio::FileMonitor,
+    pending: Rc<RefCell<PendingFiles>>,
+}
+
+/// Watch the folders with GIO file monitors. The new files are
+/// passed to the callback once settled, by bundle. Only the top
+/// level of the folders is watched. It must run on the main context.
+pub struct FolderWatcher {
+    watches: RefCell<Vec<Watch>>,
+    callback: NewFilesCallback,
+    timeout: RefCell<Option<glib::SourceId>>,
+}
+
+impl Drop for FolderWatcher {
+    fn drop(&mut self) {
+        if let Some(timeout) = self.timeout.take() {
+            timeout.remove();
+        }
+    }
+}
+
+impl FolderWatcher {
+    pub fn new(callback: NewFilesCallback) -> Rc<FolderWatcher> {
+        Rc::new(FolderWatcher {
+            watches: RefCell::default(),
+            callback,
+            timeout: RefCell::default(),
+        })
+    }
+
+    /// Watch `folders`, and stop watching the others. The files
+    /// pending in the folders still watched are kept.
+    pub fn set_folders(self: &Rc<Self>, folders: &[WatchedFolder]) {
+        let mut watches = self.watches.take();
+        watches.retain(|watch| folders.contains(&watch.folder));
+        for folder in folders {
+            if watches.iter().any(|watch| &watch.folder == folder) {
+                continue;
+            }
+            match Self::watch(folder) {
+                Ok(watch) => watches.push(watch),
+                Err(err) => err_out!("Couldn't watch {:?}: {err}", folder.path()),
+            }
+        }
+        let is_empty = watches.is_empty();
+        self.watches.replace(watches);
+
+        if is_empty {
+            if let Some(timeout) = self.timeout.take() {
+                timeout.remove();
+            }
+        } else if self.timeout.borrow().is_none() {
+            let weak = Rc::downgrade(self);
+            let timeout = glib::timeout_add_local(CHECK_INTERVAL, move || {
+                Self::check_pending(&weak);
+                glib::ControlFlow::Continue
+            });
+            self.timeout.replace(Some(timeout));
+        }
+    }
+
+    fn watch(folder: &WatchedFolder) -> Result<Watch, glib::Error> {
+        dbg_out!("Watching {:?}", folder.path());
+        let monitor = gio::File::for_path(folder.path())
+            .monitor_directory(gio::FileMonitorFlags::WATCH_MOVES, gio::Cancellable::NONE)?;
+        let pending = Rc::new(RefCell::new(PendingFiles::default()));
+        let weak_pending = Rc::downgrade(&pending);
+        monitor.connect_changed(move |_, file, other_file, event| {
+            let Some(pending) = weak_pending.upgrade() else {
+                return;
+            };
+            let mut pending = pending.borrow_mut();
+            use gio::FileMonitorEvent::*;
+            match event {
+                Created | MovedIn => {
+                    if let Some(path) = file.path() {
+                        pending.add(&path, Instant::now());
+                    }
+                }
+                // A new file still being written. A change to a file
+                // already there doesn't make it new.
+                Changed | ChangesDoneHint => {
+                    if let Some(path) = file.path() {
+                        pending.touch(&path, Instant::now());
+                    }
+                }
+                // Written to a temporary file, then renamed.
+                Renamed => {
+                    if let Some(path) = file.path() {
+                        pending.remove(&path);
+                    }
+                    if let Some(path) = other_file.and_then(|file| file.path()) {
+                        pending.add(&path, Instant::now());
+                    }
+                }
+                Deleted | MovedOut => {
+                    if let Some(path) = file.path() {
+                        pending.remove(&path);
+                    }
+                }
+                _ => {}
+            }
+        });
+        Ok(Watch {
+            folder: folder.clone(),
+            _monitor: monitor,
+            pending,
+        })
+    }
+
+    /// Pass the settled files to the callback.
+    fn check_pending(weak: &Weak<FolderWatcher>) {
+        let Some(this) = weak.upgrade() else {
+            return;
+        };
+        let now = Instant::now();
+        let settled = this
+            .watches
+            .borrow()
+            .iter()
+            .filter(|watch| !watch.pending.borrow().is_empty())
+            .map(|watch| {
+                let files = watch.pending.borrow_mut().take_settled(now, SETTLE_DELAY);
+                (watch.folder.clone(), media_bundles(&files))
+            })
+            .filter(|(_, files)| !files.is_empty())
+            .collect::<Vec<_>>();
+        for (folder, files) in settled {
+            dbg_out!("{} new files in {:?}", files.len(), folder.path());
+            (this.callback)(&folder, files);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+    use std::time::{Duration, Instant};
+
+    use super::PendingFiles;
+
+    #[test]
+    fn test_pending_files() {
+        let delay = Duration::from_secs(3);
+        let start = Instant::now();
+        let mut pending = PendingFiles::default();
+
+        assert_eq!(
+            PendingFiles::bundle_key(Path::new("/tether/IMG_0001.CR2.xmp")),
+            Some(PathBuf::from("/tether/IMG_0001"))
+        );
+        assert_eq!(PendingFiles::bundle_key(Path::new("/tether/.hidden")), None);
+
+        pending.add(Path::new("/tether/IMG_0001.CR2"), start);
+        pending.add(Path::new("/tether/IMG_0002.CR2"), start);
+        // The JPEG arrives two seconds after its RAW.
+        let later = start + Duration::from_secs(2);
+        pending.add(Path::new("/tether/IMG_0001.JPG"), later);
+        pending.add(Path::new("/tether/IMG_0003.CR2"), later);
+        pending.remove(Path::new("/tether/IMG_0003.CR2"));
+        // Changing a file that isn't pending doesn't add it.
+        pending.touch(Path::new("/tether/IMG_0004.CR2"), later);
+
+        assert!(pending.take_settled(later, delay).is_empty());
+
+        let settled = pending.take_settled(start + delay, delay);
+        assert_eq!(settled, vec![PathBuf::from("/tether/IMG_0002.CR2")]);
+
+        // The RAW is still being written.
+        pending.touch(Path::new("/tether/IMG_0001.CR2"), later + delay);
+        assert!(pending.take_settled(later + delay, delay).is_empty());
+
+        let mut settled = pending.take_settled(later + delay + delay, delay);
+        settled.sort();
+        assert_eq!(
+            settled,
+            vec![
+                PathBuf::from("/tether/IMG_0001.CR2"),
+                PathBuf::from("/tether/IMG_0001.JPG")
+            ]
+        );
+        assert!(pending.is_empty());
+    }
+}
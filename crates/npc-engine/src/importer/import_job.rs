@@ -0,0 +1,266 @@
+/*
+ * niepce - npc-engine/importer/import_job.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The import job, tracking the progress of an import from the
+//! copy to the addition into the catalog.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use super::CopyReport;
+use crate::library::notification::{LcChannel, LibNotification};
+use npc_fwk::err_out;
+
+/// The ID of an import job.
+pub type ImportJobId = u64;
+
+/// The progress of an import job.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportProgress {
+    pub job: ImportJobId,
+    /// The number of files to copy. 0 if the files aren't copied.
+    pub files_total: usize,
+    pub files_copied: usize,
+    pub bytes_copied: u64,
+    /// The number of bundles to add to the catalog. 0 until known.
+    pub bundles_total: usize,
+    pub bundles_added: usize,
+}
+
+/// The summary of a finished import job.
+#[derive(Clone, Debug, Default)]
+pub struct ImportSummary {
+    pub job: ImportJobId,
+    /// The main file of the bundles imported.
+    pub imported: Vec<PathBuf>,
+    /// The files not imported, like the duplicates.
+    pub skipped: Vec<PathBuf>,
+    /// The files that failed, with the error.
+    pub failed: Vec<(PathBuf, String)>,
+    /// The files imported with a problem, with the warning.
+    pub warnings: Vec<(PathBuf, String)>,
+    /// Whether the job was cancelled.
+    pub cancelled: bool,
+    /// The manifest to resume the copy, if kept.
+    pub manifest: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct JobState {
+    progress: ImportProgress,
+    summary: ImportSummary,
+}
+
+/// An import job. It is shared by the importer and the catalog, and
+/// notifies its progress with `LibNotification::ImportProgress` and
+/// its end with `LibNotification::ImportFinished`.
+#[derive(Clone)]
+pub struct ImportJob {
+    id: ImportJobId,
+    state: Arc<Mutex<JobState>>,
+    notifier: LcChannel,
+    /// Tell if the job is cancelled, like `Executor::terminator()`.
+    cancelled: Arc<dyn Fn() -> bool + Send + Sync>,
+}
+
+impl ImportJob {
+    /// Create a new import job. `cancelled` tells whether it was
+    /// cancelled.
+    pub fn new<F>(notifier: LcChannel, cancelled: F) -> ImportJob
+    where
+        F: Fn() -> bool + Send + Sync + 'static,
+    {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let mut state = JobState::default();
+        state.progress.job = id;
+        state.summary.job = id;
+        ImportJob {
+            id,
+            state: Arc::new(Mutex::new(state)),
+            notifier,
+            cancelled: Arc::new(cancelled),
+        }
+    }
+
+    pub fn id(&self) -> ImportJobId {
+        self.id
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        (self.cancelled)()
+    }
+
+    fn notify(&self, notification: LibNotification) {
+        if self.notifier.send_blocking(notification).is_err() {
+            err_out!("Failed to notify import job {}", self.id);
+        }
+    }
+
+    /// Update the progress with `f` and notify it.
+    fn update_progress<F: FnOnce(&mut ImportProgress)>(&self, f: F) {
+        let progress = self.state.lock().ok().map(|mut state| {
+            f(&mut state.progress);
+            state.progress.clone()
+        });
+        if let Some(progress) = progress {
+            self.notify(LibNotification::ImportProgress(progress));
+        }
+    }
+
+    fn update_summary<F: FnOnce(&mut ImportSummary)>(&self, f: F) {
+        if let Ok(mut state) = self.state.lock() {
+            f(&mut state.summary);
+        }
+    }
+
+    pub fn progress(&self) -> ImportProgress {
+        self.state
+            .lock()
+            .map(|state| state.progress.clone())
+            .unwrap_or_default()
+    }
+
+    /// The `files` won't be imported.
+    pub fn skip(&self, files: &[PathBuf]) {
+        self.update_summary(|summary| summary.skipped.extend_from_slice(files));
+    }
+
+    /// The copy of `total` files is starting.
+    pub fn copy_started(&self, total: usize) {
+        self.update_progress(|progress| progress.files_total = total);
+    }
+
+    /// A file of `bytes` was copied.
+    pub fn file_copied(&self, bytes: u64) {
+        self.update_progress(|progress| {
+            progress.files_copied += 1;
+            progress.bytes_copied += bytes;
+        });
+    }
+
+    /// The copy is finished, as reported.
+    pub fn copy_finished(&self, report: &CopyReport) {
+        self.update_summary(|summary| {
+            summary.failed.extend_from_slice(&report.failed);
+            summary.manifest = report.manifest.clone();
+            summary.cancelled |= report.cancelled;
+        });
+    }
+
+    /// The addition of `total` bundles to the catalog is starting.
+    pub fn bundles_started(&self, total: usize) {
+        self.update_progress(|progress| progress.bundles_total = total);
+    }
+
+    /// The bundle with `main` file was added to the catalog.
+    pub fn bundle_added(&self, main: &Path) {
+        self.update_summary(|summary| summary.imported.push(main.to_path_buf()));
+        self.update_progress(|progress| progress.bundles_added += 1);
+    }
+
+    /// The bundle with `main` file failed to be added.
+    pub fn bundle_failed(&self, main: &Path, err: String) {
+        self.update_summary(|summary| summary.failed.push((main.to_path_buf(), err)));
+    }
+
+    /// The bundle with `main` file was added, but not entirely.
+    pub fn bundle_warning(&self, main: &Path, warning: String) {
+        self.update_summary(|summary| summary.warnings.push((main.to_path_buf(), warning)));
+    }
+
+    /// The job is finished. Notify the summary.
+    pub fn finish(&self) {
+        let cancelled = self.is_cancelled();
+        let summary = self.state.lock().ok().map(|mut state| {
+            state.summary.cancelled |= cancelled;
+            state.summary.clone()
+        });
+        if let Some(summary) = summary {
+            self.notify(LibNotification::ImportFinished(Box::new(summary)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::ImportJob;
+    use crate::importer::CopyReport;
+    use crate::library::notification::LibNotification;
+
+    #[test]
+    fn test_import_job() {
+        let (sender, receiver) = async_channel::unbounded();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let job = ImportJob::new(sender, {
+            let cancel = cancel.clone();
+            move || cancel.load(Ordering::Acquire)
+        });
+        let other = ImportJob::new(async_channel::unbounded().0, || false);
+        assert_ne!(job.id(), other.id());
+
+        job.skip(&[PathBuf::from("/src/DSC_0001.NEF")]);
+        job.copy_started(2);
+        job.file_copied(100);
+        job.copy_finished(&CopyReport {
+            copied: vec![PathBuf::from("/dest/DSC_0002.NEF")],
+            failed: vec![(PathBuf::from("/src/DSC_0003.NEF"), "Gone".into())],
+            manifest: Some(PathBuf::from("/dest/.niepce-import.json")),
+            cancelled: false,
+        });
+        job.bundles_started(1);
+        job.bundle_warning(&PathBuf::from("/dest/DSC_0002.NEF"), "No preset".into());
+        job.bundle_added(&PathBuf::from("/dest/DSC_0002.NEF"));
+        cancel.store(true, Ordering::Release);
+        assert!(job.is_cancelled());
+        job.finish();
+
+        let progress = job.progress();
+        assert_eq!(progress.files_total, 2);
+        assert_eq!(progress.files_copied, 1);
+        assert_eq!(progress.bytes_copied, 100);
+        assert_eq!(progress.bundles_added, 1);
+
+        let mut notifications = vec![];
+        while let Ok(notification) = receiver.try_recv() {
+            notifications.push(notification);
+        }
+        assert_eq!(notifications.len(), 5);
+        assert!(matches!(
+            notifications[0],
+            LibNotification::ImportProgress(ref progress) if progress.files_total == 2
+        ));
+        let LibNotification::ImportFinished(ref summary) = notifications[4] else {
+            panic!("Not ImportFinished");
+        };
+        assert_eq!(summary.job, job.id());
+        assert_eq!(summary.imported.len(), 1);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.warnings.len(), 1);
+        assert!(summary.cancelled);
+        assert!(summary.manifest.is_some());
+    }
+}
@@ -0,0 +1,399 @@
+/*
+ * niepce - npc-engine/importer/library_content.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The content read from the library of another application, to be
+//! imported in the catalog. The library importers that read a
+//! database directly fill it.
+
+use gettextrs::gettext as i18n;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use npc_fwk::base::RgbColour;
+use npc_fwk::utils::exempi::KEYWORD_PATH_SEPARATOR;
+use npc_fwk::{PropertyValue, dbg_out};
+
+use crate::NiepcePropertyBag;
+use crate::catalog::filebundle::FileBundle;
+use crate::catalog::props::NiepceProperties as Np;
+use crate::catalog::props::NiepcePropertyIdx as NpI;
+use crate::catalog::{Label, LibraryId};
+use crate::importer::ImportReport;
+use crate::libraryclient::{ClientInterface, ClientInterfaceSync, LibraryClient};
+
+/// Whether the SQLite database at `path` has all the `tables`.
+pub(crate) fn has_tables(path: &Path, tables: &[&str]) -> bool {
+    let Ok(conn) =
+        rusqlite::Connection::open_with_flags(path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+    else {
+        return false;
+    };
+    tables.iter().all(|table| {
+        conn.query_row(
+            "SELECT count(*) FROM sqlite_master WHERE type='table' AND name=?1",
+            [table],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count > 0)
+        .unwrap_or(false)
+    })
+}
+
+/// The colour labels of the other applications.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ColourLabel {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl ColourLabel {
+    /// The name of the label to create for the colour.
+    fn name(&self) -> String {
+        match *self {
+            ColourLabel::Red => i18n("Red"),
+            ColourLabel::Orange => i18n("Orange"),
+            ColourLabel::Yellow => i18n("Yellow"),
+            ColourLabel::Green => i18n("Green"),
+            ColourLabel::Blue => i18n("Blue"),
+            ColourLabel::Purple => i18n("Purple"),
+        }
+    }
+
+    /// The colour. Red, green, blue and purple are those of the labels
+    /// created with a new catalog.
+    fn colour(&self) -> RgbColour {
+        match *self {
+            ColourLabel::Red => RgbColour::new(55769, 9509, 4369),
+            ColourLabel::Orange => RgbColour::new(55769, 35209, 4369),
+            ColourLabel::Yellow => RgbColour::new(55769, 55769, 4369),
+            ColourLabel::Green => RgbColour::new(24929, 55769, 4369),
+            ColourLabel::Blue => RgbColour::new(4369, 50629, 55769),
+            ColourLabel::Purple => RgbColour::new(35209, 4369, 55769),
+        }
+    }
+
+    /// Find the label for the colour in `labels`: the one with the
+    /// colour name, or else the one closest in colour.
+    pub(crate) fn find_label(&self, labels: &[Label]) -> Option<LibraryId> {
+        // How far each component can be from the colour.
+        const TOLERANCE: i32 = 16384;
+
+        let name = self.name();
+        let english = format!("{self:?}");
+        if let Some(label) = labels.iter().find(|label| {
+            label.label().eq_ignore_ascii_case(&name)
+                || label.label().eq_ignore_ascii_case(&english)
+        }) {
+            return Some(label.id());
+        }
+        let colour = self.colour();
+        labels
+            .iter()
+            .filter_map(|label| {
+                let c = label.colour();
+                let distance = [(c.r, colour.r), (c.g, colour.g), (c.b, colour.b)]
+                    .iter()
+                    .map(|(a, b)| (*a as i32 - *b as i32).abs())
+                    .max()
+                    .unwrap_or_default();
+                (distance <= TOLERANCE).then_some((distance, label.id()))
+            })
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, id)| id)
+    }
+}
+
+/// The labels of the catalog for the colour labels. Those missing
+/// are created.
+#[derive(Default)]
+pub(crate) struct ColourLabels {
+    /// The labels of the catalog. Loaded when needed.
+    labels: Option<Vec<Label>>,
+    ids: Vec<(ColourLabel, Option<LibraryId>)>,
+}
+
+impl ColourLabels {
+    /// The id of the label for `colour`, created if there is none.
+    pub(crate) fn label_id(
+        &mut self,
+        colour: ColourLabel,
+        libclient: &LibraryClient,
+    ) -> Option<LibraryId> {
+        if let Some((_, id)) = self.ids.iter().find(|(c, _)| *c == colour) {
+            return *id;
+        }
+        let labels = self
+            .labels
+            .get_or_insert_with(|| libclient.get_all_labels_sync());
+        let id = colour.find_label(labels).or_else(|| {
+            let id = libclient.create_label_sync(colour.name(), colour.colour());
+            (id > 0).then_some(id)
+        });
+        self.ids.push((colour, id));
+        id
+    }
+}
+
+/// A folder of the library, as a root folder and a path relative to it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct ContentFolder {
+    /// The id in the library.
+    pub id: i64,
+    /// The root folder. Can be remapped.
+    pub root: String,
+    /// The path from the root, starting with `/`, or empty for the root.
+    pub path_from_root: String,
+}
+
+/// An image of the library.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct ContentImage {
+    /// The id of the `ContentFolder`.
+    pub folder: i64,
+    /// The file name.
+    pub name: String,
+    /// 0 to 5 stars. `None` if not rated.
+    pub rating: Option<i32>,
+    pub label: Option<ColourLabel>,
+    /// -1 for rejected, 1 for picked.
+    pub flag: i32,
+    /// The keyword paths.
+    pub keywords: Vec<String>,
+    /// The duplicates of the image, imported as virtual copies.
+    pub copies: Vec<ContentImage>,
+}
+
+impl ContentImage {
+    /// The properties to set on the imported image, with the `label`
+    /// for its colour label.
+    fn properties(&self, label: Option<LibraryId>) -> NiepcePropertyBag {
+        let mut props = NiepcePropertyBag::default();
+        if let Some(rating) = self.rating {
+            props.set_value(Np::Index(NpI::NpXmpRatingProp), rating.into());
+        }
+        if let Some(label) = label {
+            props.set_value(Np::Index(NpI::NpXmpLabelProp), (label as i32).into());
+        }
+        if self.flag != 0 {
+            props.set_value(Np::Index(NpI::NpNiepceFlagProp), self.flag.into());
+        }
+        if !self.keywords.is_empty() {
+            props.set_value(
+                Np::Index(NpI::NpIptcKeywordsProp),
+                PropertyValue::StringArray(self.keywords.clone()),
+            );
+        }
+        props
+    }
+}
+
+/// The content of a library.
+#[derive(Debug, Default)]
+pub(crate) struct LibraryContent {
+    pub folders: Vec<ContentFolder>,
+    /// The keyword paths, separated with `KEYWORD_PATH_SEPARATOR`.
+    pub keywords: Vec<String>,
+    pub images: Vec<ContentImage>,
+}
+
+impl LibraryContent {
+    /// The root folders, in order.
+    pub(crate) fn root_folders(&self) -> Vec<String> {
+        let mut roots: Vec<String> = vec![];
+        for folder in &self.folders {
+            if !roots.contains(&folder.root) {
+                roots.push(folder.root.clone());
+            }
+        }
+        roots
+    }
+
+    /// Import the content with `libclient`. The root folders are
+    /// remapped with `root_folder_map`. Return what couldn't be converted.
+    pub(crate) fn import(
+        &self,
+        root_folder_map: &BTreeMap<String, String>,
+        libclient: &LibraryClient,
+    ) -> ImportReport {
+        let mut report = ImportReport::default();
+        let mut labels = ColourLabels::default();
+        let mut folder_map = BTreeMap::new();
+        for folder in &self.folders {
+            let root = root_folder_map.get(&folder.root).unwrap_or(&folder.root);
+            let path = if folder.path_from_root.is_empty() {
+                root.clone()
+            } else {
+                format!("{}{}", root.trim_end_matches('/'), folder.path_from_root)
+            };
+            let name = Path::new(&path)
+                .file_name()
+                .map(|name| String::from(name.to_string_lossy()))
+                .unwrap_or_else(|| i18n("Untitled"));
+            let nid = libclient.create_folder_sync(name, Some(path.clone()));
+            folder_map.insert(folder.id, (nid, path));
+        }
+
+        let mut keyword_map = BTreeMap::<String, LibraryId>::new();
+        for keyword in &self.keywords {
+            let mut parent = 0;
+            let mut path = String::new();
+            for name in keyword.split(KEYWORD_PATH_SEPARATOR) {
+                if !path.is_empty() {
+                    path.push(KEYWORD_PATH_SEPARATOR);
+                }
+                path.push_str(name);
+                parent = *keyword_map
+                    .entry(path.clone())
+                    .or_insert_with(|| libclient.create_keyword_sync(name.to_string(), parent));
+            }
+        }
+
+        for image in &self.images {
+            let Some((folder_id, folder_path)) = folder_map.get(&image.folder) else {
+                continue;
+            };
+            let main_file = format!("{folder_path}/{}", image.name);
+            let mut bundle = FileBundle::new();
+            dbg_out!("Adding {}", &main_file);
+            // Both darktable and digiKam name the sidecar after the
+            // whole file name.
+            let xmp_file = format!("{main_file}.xmp");
+            bundle.add(main_file);
+            if Path::new(&xmp_file).exists() {
+                dbg_out!("Adding XMP {}", &xmp_file);
+                bundle.add(xmp_file);
+            }
+            let nid = libclient.add_bundle_sync(&bundle, *folder_id);
+
+            let path = format!("{folder_path}/{}", image.name);
+            let mut set_properties = |id, image: &ContentImage| {
+                let label = image
+                    .label
+                    .and_then(|colour| labels.label_id(colour, libclient));
+                if image.label.is_some() && label.is_none() {
+                    report.add_not_converted(&path, &i18n("No label for the colour label"));
+                }
+                let props = image.properties(label);
+                if !props.is_empty() {
+                    libclient.set_image_properties(id, &props);
+                }
+            };
+            set_properties(nid, image);
+            for copy in &image.copies {
+                let copy_id = libclient.create_virtual_copy_sync(nid);
+                if copy_id > 0 {
+                    set_properties(copy_id, copy);
+                } else {
+                    report.add_not_converted(&path, &i18n("Duplicate"));
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use npc_fwk::PropertyValue;
+    use npc_fwk::base::RgbColour;
+
+    use super::{ColourLabel, ContentFolder, ContentImage, LibraryContent};
+    use crate::catalog::Label;
+    use crate::catalog::props::NiepceProperties as Np;
+    use crate::catalog::props::NiepcePropertyIdx as NpI;
+
+    #[test]
+    fn test_library_content() {
+        let content = LibraryContent {
+            folders: vec![
+                ContentFolder {
+                    id: 1,
+                    root: "/home/user/Pictures".into(),
+                    path_from_root: "/2024".into(),
+                },
+                ContentFolder {
+                    id: 2,
+                    root: "/media/disk".into(),
+                    path_from_root: "".into(),
+                },
+                ContentFolder {
+                    id: 3,
+                    root: "/home/user/Pictures".into(),
+                    path_from_root: "/2025".into(),
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            content.root_folders(),
+            vec!["/home/user/Pictures", "/media/disk"]
+        );
+
+        let image = ContentImage {
+            folder: 1,
+            name: "IMG_0001.CR2".into(),
+            rating: Some(3),
+            label: Some(ColourLabel::Orange),
+            flag: -1,
+            ..Default::default()
+        };
+        // No label for the colour label.
+        let props = image.properties(None);
+        assert_eq!(props.len(), 2);
+        assert!(matches!(
+            props.get(&Np::Index(NpI::NpXmpRatingProp)),
+            Some(PropertyValue::Int(3))
+        ));
+        assert!(matches!(
+            props.get(&Np::Index(NpI::NpNiepceFlagProp)),
+            Some(PropertyValue::Int(-1))
+        ));
+    }
+
+    #[test]
+    fn test_colour_label() {
+        // The labels of a new catalog.
+        let labels = vec![
+            Label::new(1, "Label 1", RgbColour::new(55769, 9509, 4369)),
+            Label::new(2, "Label 2", RgbColour::new(24929, 55769, 4369)),
+            Label::new(3, "Label 3", RgbColour::new(4369, 50629, 55769)),
+            Label::new(4, "Label 4", RgbColour::new(35209, 4369, 55769)),
+            Label::new(5, "Label 5", RgbColour::new(55769, 55769, 4369)),
+        ];
+        assert_eq!(ColourLabel::Red.find_label(&labels), Some(1));
+        assert_eq!(ColourLabel::Green.find_label(&labels), Some(2));
+        assert_eq!(ColourLabel::Blue.find_label(&labels), Some(3));
+        assert_eq!(ColourLabel::Purple.find_label(&labels), Some(4));
+        assert_eq!(ColourLabel::Yellow.find_label(&labels), Some(5));
+        assert_eq!(ColourLabel::Orange.find_label(&labels), None);
+
+        // Renamed and recoloured, the name wins.
+        let labels = vec![
+            Label::new(7, "Label 1", RgbColour::new(55769, 9509, 4369)),
+            Label::new(8, "red", RgbColour::new(0, 0, 0)),
+        ];
+        assert_eq!(ColourLabel::Red.find_label(&labels), Some(8));
+        assert_eq!(ColourLabel::Green.find_label(&labels), None);
+    }
+}
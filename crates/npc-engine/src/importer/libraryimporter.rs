@@ -37,6 +37,29 @@ pub enum Error {
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// The report of a library import: what couldn't be converted.
+#[derive(Clone, Debug, Default)]
+pub struct ImportReport {
+    /// The items not converted, with the reason.
+    not_converted: Vec<(String, String)>,
+}
+
+impl ImportReport {
+    /// `item` couldn't be converted because of `reason`.
+    pub fn add_not_converted(&mut self, item: &str, reason: &str) {
+        self.not_converted
+            .push((item.to_string(), reason.to_string()));
+    }
+
+    pub fn not_converted(&self) -> &[(String, String)] {
+        &self.not_converted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.not_converted.is_empty()
+    }
+}
+
 /// Interface trait for a library importer.
 ///
 /// Once constructed, call `init_importer` with a `Path`.
@@ -47,7 +70,8 @@ pub trait LibraryImporter {
 
     /// import the library at path.
     /// if can_import_library returned false this should return an error
-    fn import_library(&mut self, libclient: &LibraryClient) -> Result<()>;
+    /// Return the report of what couldn't be converted.
+    fn import_library(&mut self, libclient: &LibraryClient) -> Result<ImportReport>;
 
     /// Return the root folders. They can then me remapped using `map_root_folder`.
     fn root_folders(&mut self) -> Vec<String>;
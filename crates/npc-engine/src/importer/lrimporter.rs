@@ -21,23 +21,395 @@ use gettextrs::gettext as i18n;
 
 use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use lrcat::{
     Catalog, CatalogVersion, Collection, Folder, Folders, Image, Keyword, KeywordTree, LibraryFile,
     LrId, LrObject,
 };
+use rusqlite::{Connection, OpenFlags};
 
-use npc_fwk::{dbg_out, err_out};
+use npc_fwk::base::RgbColour;
+use npc_fwk::utils::exempi::gps_coord_to_xmp;
+use npc_fwk::{PropertyValue, dbg_out, err_out};
 
-use super::libraryimporter::{Error, LibraryImporter, LibraryImporterProbe, Result};
+use super::library_content::{ColourLabel, ColourLabels};
+use super::libraryimporter::{Error, ImportReport, LibraryImporter, LibraryImporterProbe, Result};
 use crate::NiepcePropertyBag;
 use crate::catalog::LibraryId;
+use crate::catalog::develop::{CropRect, DevelopSettings};
 use crate::catalog::filebundle::FileBundle;
 use crate::catalog::props::NiepceProperties as Np;
 use crate::catalog::props::NiepcePropertyIdx as NpI;
 use crate::libraryclient::{ClientInterface, ClientInterfaceSync, LibraryClient};
 
+/// The properties of an image that `lrcat` doesn't read.
+#[derive(Debug, Default, PartialEq)]
+struct ImageExtras {
+    rating: Option<i32>,
+    /// The name of the colour label.
+    colour_label: Option<String>,
+    caption: Option<String>,
+    /// Latitude and longitude.
+    gps: Option<(f64, f64)>,
+    /// Has develop settings.
+    developed: bool,
+    /// The develop settings that could be converted.
+    develop: Option<DevelopSettings>,
+    /// The keys of the develop settings that couldn't be.
+    develop_not_converted: Vec<String>,
+}
+
+/// A value of the Lr develop settings.
+#[derive(Debug, PartialEq)]
+enum LrValue {
+    Number(f64),
+    Bool(bool),
+    String(String),
+    /// A table of numbers, like the tone curve.
+    List(Vec<f64>),
+    /// Anything else, like the local adjustments.
+    Other,
+}
+
+/// Split `s` at the commas outside of the tables and the strings.
+fn split_lr_table(s: &str) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&s[start..]);
+    items
+        .into_iter()
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parse the Lr develop settings, the Lua table in the `text` column
+/// of `Adobe_imageDevelopSettings`: `s = { Exposure2012 = 0.5, ... }`.
+/// Only the top level values are returned.
+fn parse_lr_develop(text: &str) -> BTreeMap<String, LrValue> {
+    let Some(table) = text
+        .find('{')
+        .zip(text.rfind('}'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &text[start + 1..end])
+    else {
+        return BTreeMap::default();
+    };
+    split_lr_table(table)
+        .into_iter()
+        .filter_map(|item| {
+            let (key, value) = item.split_once('=')?;
+            let value = value.trim();
+            let value = if let Some(list) = value
+                .strip_prefix('{')
+                .and_then(|value| value.strip_suffix('}'))
+            {
+                split_lr_table(list)
+                    .into_iter()
+                    .map(|v| v.parse::<f64>().ok())
+                    .collect::<Option<Vec<_>>>()
+                    .map(LrValue::List)
+                    .unwrap_or(LrValue::Other)
+            } else if let Some(string) = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+            {
+                LrValue::String(string.to_string())
+            } else if let Ok(value) = value.parse::<bool>() {
+                LrValue::Bool(value)
+            } else {
+                value
+                    .parse::<f64>()
+                    .map(LrValue::Number)
+                    .unwrap_or(LrValue::Other)
+            };
+            Some((key.trim().to_string(), value))
+        })
+        .collect()
+}
+
+/// The Lr develop settings converted by `lr_develop_settings`.
+const LR_CONVERTED: &[&str] = &[
+    "Blacks2012",
+    "Contrast2012",
+    "CropAngle",
+    "CropBottom",
+    "CropLeft",
+    "CropRight",
+    "CropTop",
+    "Exposure2012",
+    "Highlights2012",
+    "Saturation",
+    "Shadows2012",
+    "Temperature",
+    "Tint",
+    "ToneCurvePV2012",
+    "Vibrance",
+    "WhiteBalance",
+];
+
+/// The Lr develop settings whose default isn't zero.
+const LR_NUMBER_DEFAULTS: &[(&str, f64)] = &[
+    ("Brightness", 50.0),
+    ("ColorNoiseReduction", 25.0),
+    ("ColorNoiseReductionDetail", 50.0),
+    ("ColorNoiseReductionSmoothness", 50.0),
+    ("Contrast", 25.0),
+    ("DefringeGreenHueHi", 60.0),
+    ("DefringeGreenHueLo", 40.0),
+    ("DefringePurpleHueHi", 70.0),
+    ("DefringePurpleHueLo", 30.0),
+    ("GrainFrequency", 50.0),
+    ("GrainSize", 25.0),
+    ("LensProfileChromaticAberrationScale", 100.0),
+    ("LensProfileDistortionScale", 100.0),
+    ("LensProfileVignettingScale", 100.0),
+    ("LuminanceNoiseReductionDetail", 50.0),
+    ("PerspectiveScale", 100.0),
+    ("PostCropVignetteFeather", 50.0),
+    ("PostCropVignetteMidpoint", 50.0),
+    ("Shadows", 5.0),
+    ("SharpenDetail", 25.0),
+    ("SharpenRadius", 1.0),
+    ("Sharpness", 25.0),
+    ("VignetteMidpoint", 50.0),
+];
+
+/// The default tone curve of the 2010 process version, medium contrast.
+const LR_TONE_CURVE_PV2010: &[f64] = &[
+    0.0, 0.0, 32.0, 22.0, 64.0, 56.0, 128.0, 128.0, 192.0, 196.0, 255.0, 255.0,
+];
+
+/// Whether the Lr develop setting `key` is set to its default, or
+/// isn't an adjustment, like the names and the profiles.
+fn lr_is_default(key: &str, value: &LrValue) -> bool {
+    match value {
+        LrValue::Number(value) => {
+            let default = LR_NUMBER_DEFAULTS
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, default)| *default)
+                .unwrap_or_default();
+            *value == default
+        }
+        LrValue::Bool(value) => key != "ConvertToGrayscale" || !value,
+        LrValue::String(value) => key != "Treatment" || value == "Color",
+        LrValue::List(list) => {
+            list.chunks_exact(2).all(|point| point[0] == point[1])
+                || (key == "ToneCurve" && list == LR_TONE_CURVE_PV2010)
+        }
+        LrValue::Other => false,
+    }
+}
+
+/// Convert the Lr develop settings `text` to `DevelopSettings`. Only
+/// the basic adjustments of the 2012 process version are converted:
+/// white balance, exposure, tone, presence, tone curve and crop.
+/// `None` if there is nothing to convert. The keys of the other
+/// settings not at their default are returned too.
+fn lr_develop_settings(text: &str) -> (Option<DevelopSettings>, Vec<String>) {
+    let values = parse_lr_develop(text);
+    let not_converted = values
+        .iter()
+        .filter(|(key, value)| !LR_CONVERTED.contains(&key.as_str()) && !lr_is_default(key, value))
+        .map(|(key, _)| key.clone())
+        .collect();
+    let number = |key: &str| match values.get(key) {
+        Some(LrValue::Number(value)) => Some(*value),
+        _ => None,
+    };
+    let mut settings = DevelopSettings::default();
+    if let Some(exposure) = number("Exposure2012") {
+        settings.exposure = exposure;
+    }
+    // The white balance is only converted if it was changed.
+    if matches!(values.get("WhiteBalance"), Some(LrValue::String(wb)) if wb != "As Shot") {
+        if let Some(temperature) = number("Temperature") {
+            settings.temperature = temperature;
+        }
+        if let Some(tint) = number("Tint") {
+            settings.tint = tint.clamp(-100.0, 100.0);
+        }
+    }
+    if let Some(contrast) = number("Contrast2012") {
+        settings.contrast = contrast;
+    }
+    // Lr darkens the highlights and the blacks with negative values.
+    if let Some(highlights) = number("Highlights2012") {
+        settings.recovery = (-highlights).clamp(0.0, 100.0);
+    }
+    if let Some(shadows) = number("Shadows2012") {
+        settings.fill_light = shadows.clamp(0.0, 100.0);
+    }
+    if let Some(blacks) = number("Blacks2012") {
+        settings.blacks = (-blacks).clamp(0.0, 100.0);
+    }
+    if let Some(saturation) = number("Saturation") {
+        settings.saturation = saturation;
+    }
+    if let Some(vibrance) = number("Vibrance") {
+        settings.vibrance = vibrance;
+    }
+    // The control points, from 0 to 255, x and y in sequence.
+    if let Some(LrValue::List(curve)) = values.get("ToneCurvePV2012") {
+        let curve = curve
+            .chunks_exact(2)
+            .map(|point| (point[0] / 255.0, point[1] / 255.0))
+            .collect::<Vec<_>>();
+        if curve.iter().any(|(x, y)| x != y) {
+            settings.tone_curve = curve;
+        }
+    }
+    if let Some(angle) = number("CropAngle") {
+        settings.tilt = angle.clamp(-45.0, 45.0);
+    }
+    if let (Some(left), Some(top), Some(right), Some(bottom)) = (
+        number("CropLeft"),
+        number("CropTop"),
+        number("CropRight"),
+        number("CropBottom"),
+    ) {
+        settings.crop = CropRect {
+            x: left,
+            y: top,
+            w: right - left,
+            h: bottom - top,
+        }
+        .clamped()
+        .filter(|crop| !crop.is_full());
+    }
+    (
+        Some(settings).filter(|settings| !settings.is_default()),
+        not_converted,
+    )
+}
+
+/// What is read from the catalog database directly, as `lrcat`
+/// doesn't.
+#[derive(Debug, Default)]
+struct CatalogExtras {
+    /// By image LrId.
+    images: BTreeMap<LrId, ImageExtras>,
+    /// The virtual copies, with their master image.
+    virtual_copies: BTreeMap<LrId, LrId>,
+    /// The images of each stack, from the top.
+    stacks: Vec<Vec<LrId>>,
+}
+
+impl CatalogExtras {
+    /// Run the query `sql` and call `f` for each row. A missing table
+    /// or column, as from an older version, isn't fatal.
+    fn for_each_row<F>(conn: &Connection, sql: &str, mut f: F)
+    where
+        F: FnMut(&rusqlite::Row) -> rusqlite::Result<()>,
+    {
+        let result = conn.prepare(sql).and_then(|mut stmt| {
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                f(row)?;
+            }
+            Ok(())
+        });
+        if let Err(err) = result {
+            err_out!("Lr catalog query failed: {err}");
+        }
+    }
+
+    fn load(conn: &Connection) -> CatalogExtras {
+        let mut extras = CatalogExtras::default();
+        Self::for_each_row(
+            conn,
+            "SELECT id_local, rating, colorLabels, masterImage FROM Adobe_images",
+            |row| {
+                let id: LrId = row.get(0)?;
+                let image = extras.images.entry(id).or_default();
+                image.rating = row
+                    .get::<_, Option<f64>>(1)?
+                    .map(|rating| rating as i32)
+                    .filter(|rating| *rating > 0);
+                image.colour_label = row
+                    .get::<_, Option<String>>(2)?
+                    .filter(|label| !label.is_empty());
+                if let Some(master) = row.get::<_, Option<LrId>>(3)? {
+                    extras.virtual_copies.insert(id, master);
+                }
+                Ok(())
+            },
+        );
+        Self::for_each_row(
+            conn,
+            "SELECT image, caption FROM AgLibraryIPTC WHERE caption != ''",
+            |row| {
+                let id: LrId = row.get(0)?;
+                extras.images.entry(id).or_default().caption = row.get(1)?;
+                Ok(())
+            },
+        );
+        Self::for_each_row(
+            conn,
+            "SELECT image, gpsLatitude, gpsLongitude FROM AgHarvestedExifMetadata \
+             WHERE gpsLatitude IS NOT NULL AND gpsLongitude IS NOT NULL",
+            |row| {
+                let id: LrId = row.get(0)?;
+                extras.images.entry(id).or_default().gps = Some((row.get(1)?, row.get(2)?));
+                Ok(())
+            },
+        );
+        Self::for_each_row(
+            conn,
+            "SELECT image, text FROM Adobe_imageDevelopSettings \
+             WHERE hasDevelopAdjustments > 0",
+            |row| {
+                let id: LrId = row.get(0)?;
+                let image = extras.images.entry(id).or_default();
+                image.developed = true;
+                (image.develop, image.develop_not_converted) = row
+                    .get::<_, Option<String>>(1)?
+                    .as_deref()
+                    .map(lr_develop_settings)
+                    .unwrap_or_default();
+                Ok(())
+            },
+        );
+        let mut stacks = BTreeMap::<LrId, Vec<LrId>>::new();
+        Self::for_each_row(
+            conn,
+            "SELECT stack, image FROM AgLibraryFolderStackImage ORDER BY stack, position",
+            |row| {
+                stacks.entry(row.get(0)?).or_default().push(row.get(1)?);
+                Ok(())
+            },
+        );
+        extras.stacks = stacks.into_values().collect();
+        extras
+    }
+}
+
 /// Library importer for Lightroom™
 #[derive(Default)]
 pub struct LrImporter {
@@ -56,6 +428,17 @@ pub struct LrImporter {
     /// XXX longer term is to have an image table.
     image_map: RefCell<BTreeMap<LrId, LibraryId>>,
 
+    /// map Lr colour label names to label LibraryId
+    label_map: RefCell<BTreeMap<String, LibraryId>>,
+    /// The labels for the colour names.
+    colour_labels: RefCell<ColourLabels>,
+    /// What `lrcat` doesn't read.
+    extras: CatalogExtras,
+    /// The path of the catalog.
+    path: Option<PathBuf>,
+    /// What couldn't be converted.
+    report: RefCell<ImportReport>,
+
     /// The root folder mapping table
     root_folder_map: BTreeMap<String, String>,
 }
@@ -65,20 +448,22 @@ impl LrImporter {
         LrImporter::default()
     }
 
-    /// Import keyword with `id`. `keywords` is all the Lr keywords, `tree`
-    /// is the hierarchy tree as returned by `Catalog::load_keywords_tree()`
+    /// Import keyword with `id` under the keyword `parent`. `keywords`
+    /// is all the Lr keywords, `tree` is the hierarchy tree as returned
+    /// by `Catalog::load_keywords_tree()`
     fn import_keyword(
         &self,
         id: LrId,
+        parent: LibraryId,
         libclient: &LibraryClient,
         keywords: &BTreeMap<LrId, Keyword>,
         tree: &KeywordTree,
     ) {
         if let Some(keyword) = keywords.get(&id) {
-            let nid = libclient.create_keyword_sync(keyword.name.clone());
+            let nid = libclient.create_keyword_sync(keyword.name.clone(), parent);
             self.keyword_map.borrow_mut().insert(id, nid);
             tree.children_for(id).iter().for_each(|child| {
-                self.import_keyword(*child, libclient, keywords, tree);
+                self.import_keyword(*child, nid, libclient, keywords, tree);
             });
         }
     }
@@ -127,7 +512,7 @@ impl LrImporter {
         let mut jpeg_file: Option<String> = None;
         let sidecar_exts = file.sidecar_extensions.split(',');
         sidecar_exts.for_each(|ext| {
-            if !ext.is_empty() {
+            if ext.is_empty() {
                 return;
             }
             if ext.to_lowercase() == "xmp" {
@@ -149,6 +534,109 @@ impl LrImporter {
         }
     }
 
+    /// The label for the Lr colour label `name`. The colour names map
+    /// to the labels of the same colour, created if missing, the other
+    /// names to a new label.
+    fn label_for(&self, name: &str, libclient: &LibraryClient) -> Option<LibraryId> {
+        let colour = match name.to_lowercase().as_str() {
+            "red" => Some(ColourLabel::Red),
+            "yellow" => Some(ColourLabel::Yellow),
+            "green" => Some(ColourLabel::Green),
+            "blue" => Some(ColourLabel::Blue),
+            "purple" => Some(ColourLabel::Purple),
+            _ => None,
+        };
+        if let Some(colour) = colour {
+            return self.colour_labels.borrow_mut().label_id(colour, libclient);
+        }
+        let id = *self
+            .label_map
+            .borrow_mut()
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                libclient.create_label_sync(name.to_string(), RgbColour::new(32768, 32768, 32768))
+            });
+        (id > 0).then_some(id)
+    }
+
+    /// The properties for `image`. `name` is for the report.
+    fn image_properties(
+        &self,
+        image: &Image,
+        name: &str,
+        libclient: &LibraryClient,
+    ) -> NiepcePropertyBag {
+        let mut metadata = NiepcePropertyBag::default();
+        metadata.set_value(Np::Index(NpI::NpNiepceXmpPacket), image.xmp.as_str().into());
+        metadata.set_value(
+            Np::Index(NpI::NpTiffOrientationProp),
+            image.exif_orientation().into(),
+        );
+        metadata.set_value(Np::Index(NpI::NpNiepceFlagProp), (image.pick as i32).into());
+        if let Some(extras) = self.extras.images.get(&image.id()) {
+            if let Some(rating) = extras.rating {
+                metadata.set_value(Np::Index(NpI::NpXmpRatingProp), rating.into());
+            }
+            if let Some(ref label) = extras.colour_label {
+                if let Some(label) = self.label_for(label, libclient) {
+                    metadata.set_value(Np::Index(NpI::NpXmpLabelProp), (label as i32).into());
+                } else {
+                    self.report
+                        .borrow_mut()
+                        .add_not_converted(name, &i18n("Colour label"));
+                }
+            }
+            if let Some(ref caption) = extras.caption {
+                metadata.set_value(
+                    Np::Index(NpI::NpIptcDescriptionProp),
+                    caption.as_str().into(),
+                );
+            }
+            if let Some((latitude, longitude)) = extras.gps {
+                metadata.set_value(
+                    Np::Index(NpI::NpExifGpsLatProp),
+                    PropertyValue::String(gps_coord_to_xmp(latitude, true)),
+                );
+                metadata.set_value(
+                    Np::Index(NpI::NpExifGpsLongProp),
+                    PropertyValue::String(gps_coord_to_xmp(longitude, false)),
+                );
+            }
+        }
+        metadata
+    }
+
+    /// Set the develop settings of `image` to the file `nid`. Those
+    /// that couldn't be converted are reported.
+    fn import_develop_settings(
+        &self,
+        image: &Image,
+        nid: LibraryId,
+        name: &str,
+        libclient: &LibraryClient,
+    ) {
+        let Some(extras) = self.extras.images.get(&image.id()) else {
+            return;
+        };
+        if let Some(ref settings) = extras.develop {
+            libclient.set_develop_settings(nid, settings);
+        }
+        if !extras.develop_not_converted.is_empty() {
+            self.report.borrow_mut().add_not_converted(
+                name,
+                &format!(
+                    "{}: {}",
+                    i18n("Develop settings"),
+                    extras.develop_not_converted.join(", ")
+                ),
+            );
+        } else if extras.develop.is_none() && extras.developed {
+            self.report
+                .borrow_mut()
+                .add_not_converted(name, &i18n("Develop settings"));
+        }
+    }
+
     /// Import a library file. `image` is the optional imager from Lr, includes
     /// metadata.
     fn import_library_file(
@@ -161,36 +649,63 @@ impl LrImporter {
             let main_file = format!("{}/{}.{}", &folder_id.1, &file.basename, &file.extension);
             let mut bundle = FileBundle::new();
             dbg_out!("Adding {}", &main_file);
-            bundle.add(main_file);
 
+            let metadata = image.map(|image| self.image_properties(image, &main_file, libclient));
+
+            bundle.add(main_file);
             if !file.sidecar_extensions.is_empty() {
                 Self::populate_bundle(file, &folder_id.1, &mut bundle);
             }
 
-            let metadata = if let Some(image) = image {
-                let mut metadata = NiepcePropertyBag::default();
-                metadata.set_value(
-                    Np::Index(NpI::NpTiffOrientationProp),
-                    image.exif_orientation().into(),
-                );
-                metadata.set_value(Np::Index(NpI::NpNiepceFlagProp), (image.pick as i32).into());
-                metadata.set_value(Np::Index(NpI::NpNiepceXmpPacket), image.xmp.as_str().into());
-                Some(metadata)
-            } else {
-                None
-            };
-
             let nid = libclient.add_bundle_sync(&bundle, folder_id.0);
             if let Some(ref props) = metadata {
                 libclient.set_image_properties(nid, props);
             }
             self.file_map.borrow_mut().insert(file.id(), nid);
             if let Some(image) = image {
+                self.import_develop_settings(image, nid, &main_file, libclient);
                 self.image_map.borrow_mut().insert(image.id(), nid);
             }
         }
     }
 
+    /// Import the virtual copy `image` of `master`.
+    fn import_virtual_copy(&self, image: &Image, master: LrId, libclient: &LibraryClient) {
+        let name = format!("{} {}", i18n("Virtual copy"), image.id());
+        let Some(master_id) = self.image_map.borrow().get(&master).cloned() else {
+            self.report
+                .borrow_mut()
+                .add_not_converted(&name, &i18n("Master image not imported"));
+            return;
+        };
+        let nid = libclient.create_virtual_copy_sync(master_id);
+        if nid <= 0 {
+            self.report
+                .borrow_mut()
+                .add_not_converted(&name, &i18n("Virtual copy"));
+            return;
+        }
+        let props = self.image_properties(image, &name, libclient);
+        libclient.set_image_properties(nid, &props);
+        self.import_develop_settings(image, nid, &name, libclient);
+        self.image_map.borrow_mut().insert(image.id(), nid);
+    }
+
+    /// Import the stacks. Need the images imported.
+    fn import_stacks(&self, libclient: &LibraryClient) {
+        for stack in &self.extras.stacks {
+            let files = stack
+                .iter()
+                .filter_map(|id| self.image_map.borrow().get(id).cloned())
+                .collect::<Vec<_>>();
+            if files.len() > 1 {
+                libclient.create_stack(&files);
+            } else {
+                dbg_out!("Stack with {} image imported", files.len());
+            }
+        }
+    }
+
     /// Remap a folder path based on the root folder remapping
     /// If the folder isnt't found, it's the equivalent of
     /// `Folders.resolve_folder_path()`
@@ -222,10 +737,11 @@ impl LibraryImporter for LrImporter {
         }
 
         self.catalog = Some(catalog);
+        self.path = Some(path.to_path_buf());
         Ok(())
     }
 
-    fn import_library(&mut self, libclient: &LibraryClient) -> Result<()> {
+    fn import_library(&mut self, libclient: &LibraryClient) -> Result<ImportReport> {
         if let Some(ref mut catalog) = self.catalog {
             catalog.load_version();
             if catalog.catalog_version != CatalogVersion::Lr4 {
@@ -241,6 +757,13 @@ impl LibraryImporter for LrImporter {
             return Err(Error::NoInput);
         }
 
+        if let Some(ref path) = self.path {
+            match Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY) {
+                Ok(conn) => self.extras = CatalogExtras::load(&conn),
+                Err(err) => err_out!("Couldn't open the Lr catalog database: {err}"),
+            }
+        }
+
         if let Some(ref catalog) = self.catalog {
             let folders = catalog.folders();
             folders
@@ -254,11 +777,17 @@ impl LibraryImporter for LrImporter {
             let keywords = catalog.keywords();
             let mut keywordtree = KeywordTree::new();
             keywordtree.add_children(keywords);
-            self.import_keyword(root_keyword_id, libclient, keywords, &keywordtree);
+            // The root keyword isn't imported, its children are top level.
+            keywordtree
+                .children_for(root_keyword_id)
+                .iter()
+                .for_each(|id| self.import_keyword(*id, 0, libclient, keywords, &keywordtree));
 
             let images = catalog.images();
+            // The virtual copies share the file of their master.
             let image_to_libfile: BTreeMap<LrId, &Image> = images
                 .iter()
+                .filter(|image| !self.extras.virtual_copies.contains_key(&image.id()))
                 .map(|image| (image.root_file, image))
                 .collect();
             let library_files = catalog.libfiles();
@@ -266,6 +795,12 @@ impl LibraryImporter for LrImporter {
                 let image = image_to_libfile.get(&library_file.id());
                 self.import_library_file(library_file, image.copied(), libclient);
             });
+            images.iter().for_each(|image| {
+                if let Some(master) = self.extras.virtual_copies.get(&image.id()) {
+                    self.import_virtual_copy(image, *master, libclient);
+                }
+            });
+            self.import_stacks(libclient);
 
             let collections = catalog.collections();
             collections.iter().for_each(|collection| {
@@ -280,7 +815,7 @@ impl LibraryImporter for LrImporter {
                 }
             });
 
-            Ok(())
+            Ok(self.report.take())
         } else {
             Err(Error::NoInput)
         }
@@ -329,7 +864,10 @@ impl LibraryImporterProbe for LrImporter {
 
 #[cfg(test)]
 mod test {
-    use super::LrImporter;
+    use rusqlite::Connection;
+
+    use super::{CatalogExtras, ImageExtras, LrImporter, LrValue, parse_lr_develop};
+    use crate::catalog::{CropRect, DevelopSettings};
     use crate::importer::{LibraryImporter, LibraryImporterProbe};
 
     #[test]
@@ -345,4 +883,149 @@ mod test {
 
         assert_eq!(importer.name(), "Adobe Lightroom™");
     }
+
+    #[test]
+    fn test_catalog_extras() {
+        let conn = Connection::open_in_memory().expect("database created");
+        conn.execute_batch(
+            "CREATE TABLE Adobe_images (id_local INTEGER PRIMARY KEY, rating, \
+             colorLabels NOT NULL DEFAULT '', masterImage INTEGER); \
+             CREATE TABLE AgLibraryIPTC (id_local INTEGER PRIMARY KEY, caption, image INTEGER); \
+             CREATE TABLE Adobe_imageDevelopSettings (id_local INTEGER PRIMARY KEY, \
+             hasDevelopAdjustments, image INTEGER, text); \
+             CREATE TABLE AgLibraryFolderStackImage (id_local INTEGER PRIMARY KEY, \
+             collapsed INTEGER, image INTEGER, position, stack INTEGER); \
+             INSERT INTO Adobe_images VALUES (10, 4.0, 'Red', NULL); \
+             INSERT INTO Adobe_images VALUES (11, NULL, '', NULL); \
+             INSERT INTO Adobe_images VALUES (12, 2, 'Purple', 10); \
+             INSERT INTO AgLibraryIPTC VALUES (1, 'Sunset', 10); \
+             INSERT INTO AgLibraryIPTC VALUES (2, '', 11); \
+             INSERT INTO Adobe_imageDevelopSettings VALUES (1, 1.0, 10, \
+             's = { Exposure2012 = 0.5, WhiteBalance = \"As Shot\", Temperature = 5200 }'); \
+             INSERT INTO Adobe_imageDevelopSettings VALUES (2, 0.0, 11, NULL); \
+             INSERT INTO Adobe_images VALUES (13, NULL, '', NULL); \
+             INSERT INTO Adobe_imageDevelopSettings VALUES (3, 1.0, 13, \
+             's = { RetouchInfo = {} }'); \
+             INSERT INTO AgLibraryFolderStackImage VALUES (1, 0, 11, 2.0, 100); \
+             INSERT INTO AgLibraryFolderStackImage VALUES (2, 0, 10, 1.0, 100);",
+        )
+        .expect("database filled");
+
+        // AgHarvestedExifMetadata is missing.
+        let extras = CatalogExtras::load(&conn);
+        assert_eq!(
+            extras.images.get(&10),
+            Some(&ImageExtras {
+                rating: Some(4),
+                colour_label: Some("Red".into()),
+                caption: Some("Sunset".into()),
+                gps: None,
+                developed: true,
+                develop: Some(DevelopSettings {
+                    exposure: 0.5,
+                    ..DevelopSettings::default()
+                }),
+            })
+        );
+        // Nothing to convert.
+        assert_eq!(
+            extras.images.get(&13),
+            Some(&ImageExtras {
+                developed: true,
+                ..ImageExtras::default()
+            })
+        );
+        assert_eq!(extras.images.get(&11), Some(&ImageExtras::default()));
+        assert_eq!(extras.virtual_copies.get(&12), Some(&10));
+        assert_eq!(extras.stacks, vec![vec![10, 11]]);
+    }
+
+    #[test]
+    fn test_lr_develop_settings() {
+        let text = "s = { AutoLateralCA = 0,\n\
+                    \tBlacks2012 = -20,\n\
+                    \tCameraProfile = \"Adobe Standard\",\n\
+                    \tContrast2012 = 15,\n\
+                    \tCropAngle = 1.5,\n\
+                    \tCropBottom = 0.9,\n\
+                    \tCropLeft = 0.1,\n\
+                    \tCropRight = 1,\n\
+                    \tCropTop = 0,\n\
+                    \tExposure2012 = -0.35,\n\
+                    \tHighlights2012 = -40,\n\
+                    \tPaintBasedCorrections = { { What = \"Correction\", Amount = 1 } },\n\
+                    \tShadows2012 = 30,\n\
+                    \tTemperature = 5200,\n\
+                    \tTint = -160,\n\
+                    \tToneCurvePV2012 = { 0, 0, 128, 140, 255, 255 },\n\
+                    \tVibrance = 10,\n\
+                    \tWhiteBalance = \"Custom\",\n\
+                    }\n";
+
+        let values = parse_lr_develop(text);
+        assert_eq!(values.get("Exposure2012"), Some(&LrValue::Number(-0.35)));
+        assert_eq!(
+            values.get("WhiteBalance"),
+            Some(&LrValue::String("Custom".into()))
+        );
+        assert_eq!(values.get("PaintBasedCorrections"), Some(&LrValue::Other));
+        assert_eq!(values.get("What"), None);
+
+        let (settings, not_converted) = super::lr_develop_settings(text);
+        let settings = settings.expect("settings converted");
+        assert_eq!(not_converted, vec!["PaintBasedCorrections"]);
+        assert_eq!(
+            settings,
+            DevelopSettings {
+                tilt: 1.5,
+                temperature: 5200.0,
+                tint: -100.0,
+                exposure: -0.35,
+                recovery: 40.0,
+                fill_light: 30.0,
+                blacks: 20.0,
+                contrast: 15.0,
+                vibrance: 10.0,
+                tone_curve: vec![(0.0, 0.0), (128.0 / 255.0, 140.0 / 255.0), (1.0, 1.0)],
+                crop: Some(CropRect {
+                    x: 0.1,
+                    y: 0.0,
+                    w: 0.9,
+                    h: 0.9,
+                }),
+                ..DevelopSettings::default()
+            }
+        );
+
+        assert_eq!(
+            super::lr_develop_settings("s = { ToneCurvePV2012 = { 0, 0, 255, 255 } }"),
+            (None, vec![])
+        );
+
+        // The 2010 process version, HSL and sharpening aren't converted.
+        let text = "s = { Brightness = 50,\n\
+                    \tContrast = 40,\n\
+                    \tConvertToGrayscale = false,\n\
+                    \tEnableDetail = true,\n\
+                    \tExposure = 0.5,\n\
+                    \tHueAdjustmentRed = 10,\n\
+                    \tProcessVersion = \"5.0\",\n\
+                    \tSharpness = 40,\n\
+                    \tSharpenRadius = 1,\n\
+                    \tToneCurve = { 0, 0, 32, 22, 64, 56, 128, 128, 192, 196, 255, 255 },\n\
+                    \tTreatment = \"Color\",\n\
+                    }\n";
+        assert_eq!(
+            super::lr_develop_settings(text),
+            (
+                None,
+                vec![
+                    "Contrast".to_string(),
+                    "Exposure".to_string(),
+                    "HueAdjustmentRed".to_string(),
+                    "Sharpness".to_string()
+                ]
+            )
+        );
+    }
 }
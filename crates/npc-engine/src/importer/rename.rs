@@ -0,0 +1,405 @@
+/*
+ * niepce - npc-engine/importer/rename.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Renaming of the files at import, using a template like
+//! `{date:%Y%m%d}-{camera}-{seq:4}-{orig}`.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use thiserror::Error;
+
+use crate::catalog::filebundle::FileBundle;
+use npc_fwk::Date;
+
+/// Default format for the `{date}` token.
+const DEFAULT_DATE_FORMAT: &str = "%Y%m%d";
+/// Substitute for `{camera}` if the camera is unknown.
+const UNKNOWN_CAMERA: &str = "unknown";
+
+/// Error parsing a rename template.
+#[derive(Error, Debug, PartialEq)]
+pub enum RenameError {
+    /// The template is empty.
+    #[error("Empty template")]
+    Empty,
+    /// A `{` isn't closed.
+    #[error("Unclosed token")]
+    UnclosedToken,
+    /// The token is unknown.
+    #[error("Unknown token {0}")]
+    UnknownToken(String),
+    /// The date format is invalid.
+    #[error("Invalid date format {0}")]
+    InvalidDateFormat(String),
+    /// The sequence width is invalid.
+    #[error("Invalid sequence width {0}")]
+    InvalidSeqWidth(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Text(String),
+    /// The capture date, with the strftime format.
+    Date(String),
+    /// The camera model.
+    Camera,
+    /// The sequence number in the import, zero padded to width.
+    Seq(usize),
+    /// The original file name, without extension.
+    Orig,
+}
+
+/// A template to rename the files.
+///
+/// The tokens are:
+/// - `{date}` or `{date:FORMAT}`: the capture date, FORMAT being a
+///   strftime format. Default is `%Y%m%d`.
+/// - `{camera}`: the camera model.
+/// - `{seq}` or `{seq:WIDTH}`: the sequence number in the import,
+///   zero padded to WIDTH.
+/// - `{orig}`: the original file name, without the extension.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenameTemplate {
+    template: String,
+    tokens: Vec<Token>,
+}
+
+impl FromStr for RenameTemplate {
+    type Err = RenameError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        if template.is_empty() {
+            return Err(RenameError::Empty);
+        }
+        let mut tokens = vec![];
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                tokens.push(Token::Text(rest[..start].to_string()));
+            }
+            let end = rest[start..].find('}').ok_or(RenameError::UnclosedToken)? + start;
+            let token = &rest[start + 1..end];
+            let (name, arg) = token
+                .split_once(':')
+                .map(|(name, arg)| (name, Some(arg)))
+                .unwrap_or((token, None));
+            tokens.push(match (name, arg) {
+                ("date", arg) => {
+                    let format = arg.unwrap_or(DEFAULT_DATE_FORMAT);
+                    if format.is_empty()
+                        || StrftimeItems::new(format).any(|item| matches!(item, Item::Error))
+                    {
+                        return Err(RenameError::InvalidDateFormat(format.to_string()));
+                    }
+                    Token::Date(format.to_string())
+                }
+                ("camera", None) => Token::Camera,
+                ("seq", arg) => Token::Seq(
+                    arg.map(|width| {
+                        width
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|width| *width <= 10)
+                            .ok_or_else(|| RenameError::InvalidSeqWidth(width.to_string()))
+                    })
+                    .transpose()?
+                    .unwrap_or(1),
+                ),
+                ("orig", None) => Token::Orig,
+                _ => return Err(RenameError::UnknownToken(token.to_string())),
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            tokens.push(Token::Text(rest.to_string()));
+        }
+
+        Ok(RenameTemplate {
+            template: template.to_string(),
+            tokens,
+        })
+    }
+}
+
+impl std::fmt::Display for RenameTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+impl RenameTemplate {
+    /// Format the base name, without extension. `orig` is the
+    /// original base name. Path separators are replaced.
+    pub fn format(
+        &self,
+        orig: &str,
+        date: Option<&Date>,
+        camera: Option<&str>,
+        seq: u32,
+    ) -> String {
+        let name = self
+            .tokens
+            .iter()
+            .map(|token| match token {
+                Token::Text(text) => text.clone(),
+                Token::Date(format) => date
+                    .map(|date| date.format(format).to_string())
+                    .unwrap_or_default(),
+                Token::Camera => camera
+                    .map(str::trim)
+                    .filter(|camera| !camera.is_empty())
+                    .unwrap_or(UNKNOWN_CAMERA)
+                    .replace(' ', "_"),
+                Token::Seq(width) => format!("{seq:0width$}"),
+                Token::Orig => orig.to_string(),
+            })
+            .collect::<String>();
+
+        name.replace(['/', '\\'], "_")
+    }
+}
+
+/// Rename the bundles for an import. All the files of a bundle get
+/// the same base name, and the names are made unique by adding a
+/// suffix.
+pub struct BundleRenamer<'a> {
+    template: &'a RenameTemplate,
+    /// The sequence number of the next bundle.
+    seq: u32,
+    /// The destinations already used.
+    used: HashSet<PathBuf>,
+    /// The destinations of a previous run, by source.
+    previous: HashMap<PathBuf, PathBuf>,
+}
+
+impl<'a> BundleRenamer<'a> {
+    pub fn new(template: &'a RenameTemplate) -> Self {
+        BundleRenamer {
+            template,
+            seq: 1,
+            used: HashSet::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    /// Builder: the `previous` imports (source, destination) of an
+    /// interrupted run, to resume it. Their bundles keep the same
+    /// names, and the other bundles don't take them.
+    pub fn set_previous(mut self, previous: &[(PathBuf, PathBuf)]) -> Self {
+        self.used
+            .extend(previous.iter().map(|(_, dest)| dest.clone()));
+        self.previous = previous.iter().cloned().collect();
+        self
+    }
+
+    /// The original base name of the `bundle`.
+    fn orig_name(bundle: &FileBundle) -> String {
+        bundle
+            .main()
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    }
+
+    /// The extension of `file` in `bundle`, with the dot. This can be
+    /// like `.NEF.xmp` for a sidecar.
+    fn extension(orig: &str, file: &Path) -> String {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if let Some(extension) = name.strip_prefix(orig)
+            && (extension.is_empty() || extension.starts_with('.'))
+        {
+            extension.to_string()
+        } else {
+            file.extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default()
+        }
+    }
+
+    /// Return the source and destination for each file of `bundle`,
+    /// to put into `dest_dir`.
+    pub fn rename(
+        &mut self,
+        bundle: &FileBundle,
+        dest_dir: &Path,
+        date: Option<&Date>,
+        camera: Option<&str>,
+    ) -> Vec<(PathBuf, PathBuf)> {
+        let orig = Self::orig_name(bundle);
+        let base = self.template.format(&orig, date, camera, self.seq);
+        self.seq += 1;
+        let files = bundle
+            .all_files()
+            .into_iter()
+            .map(|file| {
+                let extension = Self::extension(&orig, &file);
+                (file, extension)
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(imports) = files
+            .iter()
+            .map(|(file, _)| {
+                self.previous
+                    .get(file)
+                    .map(|dest| (file.clone(), dest.clone()))
+            })
+            .collect::<Option<Vec<_>>>()
+        {
+            return imports;
+        }
+
+        let mut suffix = 0;
+        loop {
+            let name = if suffix == 0 {
+                base.clone()
+            } else {
+                format!("{base}-{suffix}")
+            };
+            let imports = files
+                .iter()
+                .map(|(file, extension)| {
+                    (file.clone(), dest_dir.join(format!("{name}{extension}")))
+                })
+                .collect::<Vec<_>>();
+            // Renaming in place to the same name isn't a collision.
+            if imports
+                .iter()
+                .all(|(file, dest)| !self.used.contains(dest) && (dest == file || !dest.exists()))
+            {
+                self.used
+                    .extend(imports.iter().map(|(_, dest)| dest.clone()));
+                return imports;
+            }
+            suffix += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use chrono::{Offset, TimeZone};
+
+    use super::{BundleRenamer, RenameError, RenameTemplate};
+    use crate::catalog::filebundle::FileBundle;
+
+    #[test]
+    fn test_parse_template() {
+        assert!(
+            "{date:%Y%m%d}-{camera}-{seq:4}-{orig}"
+                .parse::<RenameTemplate>()
+                .is_ok()
+        );
+        assert!("vacation".parse::<RenameTemplate>().is_ok());
+        assert_eq!("".parse::<RenameTemplate>(), Err(RenameError::Empty));
+        assert_eq!(
+            "{orig".parse::<RenameTemplate>(),
+            Err(RenameError::UnclosedToken)
+        );
+        assert_eq!(
+            "{lens}".parse::<RenameTemplate>(),
+            Err(RenameError::UnknownToken("lens".into()))
+        );
+        assert_eq!(
+            "{date:%Q}".parse::<RenameTemplate>(),
+            Err(RenameError::InvalidDateFormat("%Q".into()))
+        );
+        assert_eq!(
+            "{seq:x}".parse::<RenameTemplate>(),
+            Err(RenameError::InvalidSeqWidth("x".into()))
+        );
+    }
+
+    #[test]
+    fn test_format() {
+        let date = chrono::Utc
+            .fix()
+            .with_ymd_and_hms(2021, 1, 6, 12, 12, 12)
+            .single()
+            .expect("Date no constructed");
+        let template = "{date:%Y%m%d}-{camera}-{seq:4}-{orig}"
+            .parse::<RenameTemplate>()
+            .expect("Invalid template");
+        assert_eq!(
+            template.format("DSC_9999", Some(&date), Some("NIKON D750"), 12),
+            "20210106-NIKON_D750-0012-DSC_9999"
+        );
+        assert_eq!(
+            template.format("DSC_9999", None, None, 1),
+            "-unknown-0001-DSC_9999"
+        );
+        let template = "{date:%Y/%m}-{seq}"
+            .parse::<RenameTemplate>()
+            .expect("Invalid template");
+        assert_eq!(template.format("", Some(&date), None, 3), "2021_01-3");
+    }
+
+    #[test]
+    fn test_bundle_renamer() {
+        let bundles = FileBundle::filter_bundles(&[
+            PathBuf::from("/src/DSC_9999.NEF"),
+            PathBuf::from("/src/DSC_9999.JPG"),
+            PathBuf::from("/src/DSC_9999.xmp"),
+        ]);
+        assert_eq!(bundles.len(), 1);
+        // From another camera body.
+        let other = FileBundle::filter_bundles(&[PathBuf::from("/src/other/DSC_9999.JPG")]);
+
+        let template = "trip-{orig}"
+            .parse::<RenameTemplate>()
+            .expect("Invalid template");
+        let mut renamer = BundleRenamer::new(&template);
+        let dest = PathBuf::from("/nonexistent/dest");
+        let mut imports = renamer.rename(&bundles[0], &dest, None, None);
+        imports.sort();
+        assert_eq!(
+            imports,
+            vec![
+                (
+                    PathBuf::from("/src/DSC_9999.JPG"),
+                    dest.join("trip-DSC_9999.JPG")
+                ),
+                (
+                    PathBuf::from("/src/DSC_9999.NEF"),
+                    dest.join("trip-DSC_9999.NEF")
+                ),
+                (
+                    PathBuf::from("/src/DSC_9999.xmp"),
+                    dest.join("trip-DSC_9999.xmp")
+                ),
+            ]
+        );
+        let imports = renamer.rename(&other[0], &dest, None, None);
+        assert_eq!(
+            imports,
+            vec![(
+                PathBuf::from("/src/other/DSC_9999.JPG"),
+                dest.join("trip-DSC_9999-1.JPG")
+            )]
+        );
+    }
+}
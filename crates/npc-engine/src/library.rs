@@ -18,11 +18,13 @@
  */
 
 pub mod commands;
+pub mod journal;
 pub mod notification;
 pub mod op;
 mod preferences;
 mod previewer;
 pub mod queriedcontent;
+pub mod synchronize;
 pub mod thumbnail_cache;
 
 pub use preferences::CatalogPreferences;
@@ -19,24 +19,59 @@
 
 mod import;
 
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
+use gettextrs::gettext as i18n;
+
+use super::journal::{JournalOp, JournalValue};
 use super::notification::LibNotification;
-use super::notification::{Count, FileMove, MetadataChange};
+use super::notification::{Count, FileMove, MetadataChange, ProjectStateChange};
 use super::queriedcontent::QueriedContent;
+use super::synchronize::{self, SyncOptions};
 use crate::NiepcePropertyBag;
 use crate::catalog::LibraryId;
+use crate::catalog::NiepcePropertyIdx as Npi;
 use crate::catalog::filebundle::FileBundle;
-use crate::catalog::keyword::Keyword;
+use crate::catalog::fingerprint;
+use crate::catalog::keyword::{self, Keyword};
 use crate::catalog::label::Label;
+use crate::catalog::libfile::{FileType, LibFile};
 use crate::catalog::libfolder::LibFolder;
+use crate::catalog::project::{ProjectItem, ProjectState};
 use crate::catalog::props::NiepceProperties as Np;
-use crate::catalog::{CatalogDb, LibError};
+use crate::catalog::search::SearchQuery;
+use crate::catalog::smart_collection::{SmartCollection, SmartRule};
+use crate::catalog::stack::{self, StackingOptions};
+use crate::catalog::{
+    CatalogDb, DevelopSettings, LibError, LibResult, MetadataPreset, WatchedFolder,
+};
+use crate::importer::ImportJob;
 use crate::libraryclient::ClientCallback;
 use import::CatalogDbImportHelper;
-use npc_fwk::PropertyValue;
 use npc_fwk::base::RgbColour;
-use npc_fwk::{err_out, err_out_line};
+use npc_fwk::utils::FileList;
+use npc_fwk::{PropertyValue, XmpMeta};
+use npc_fwk::{dbg_out, err_out, err_out_line};
+
+/// Record the operation in the journal, with its `inverse`. An empty
+/// `inverse` means it can't be undone.
+fn record_journal(
+    catalog: &CatalogDb,
+    label: &str,
+    forward: Vec<JournalOp>,
+    inverse: Vec<JournalOp>,
+) {
+    match catalog.add_journal_entry(label, &forward, &inverse) {
+        Ok(Some(_)) => {
+            if catalog.notify(LibNotification::JournalChanged).is_err() {
+                err_out!("Failed to notify JournalChanged");
+            }
+        }
+        Ok(None) => {}
+        Err(err) => err_out_line!("Recording journal entry failed {:?}", err),
+    }
+}
 
 pub fn cmd_list_all_preferences(catalog: &CatalogDb) -> bool {
     match catalog.get_all_preferences() {
@@ -73,9 +108,16 @@ pub fn cmd_set_preference(catalog: &CatalogDb, key: &str, value: &str) -> bool {
     }
 }
 
-pub fn cmd_list_all_keywords(catalog: &CatalogDb) -> bool {
+pub fn cmd_list_all_keywords(
+    catalog: &CatalogDb,
+    callback: Option<ClientCallback<Vec<Keyword>>>,
+) -> bool {
     match catalog.get_all_keywords() {
         Ok(list) => {
+            if let Some(callback) = callback {
+                callback(list);
+                return true;
+            }
             // XXX change this to "LoadKeywords"
             for kw in list {
                 if let Err(err) = catalog.notify(LibNotification::AddedKeyword(kw)) {
@@ -137,21 +179,69 @@ pub fn cmd_list_all_folders(
 /// Import a list of files into the library.
 /// It will build the bundles. If you already have the bundles,
 /// call `cmd_import_bundles`
-pub fn cmd_import_files(catalog: &CatalogDb, base: &Path, files: &[PathBuf]) -> bool {
+pub fn cmd_import_files(
+    catalog: &CatalogDb,
+    base: &Path,
+    files: &[PathBuf],
+    stacking: &StackingOptions,
+    preset: Option<&MetadataPreset>,
+    job: Option<&ImportJob>,
+) -> bool {
     let bundles = FileBundle::filter_bundles(files);
 
-    cmd_import_bundles(catalog, base, &bundles)
+    cmd_import_bundles(catalog, base, &bundles, stacking, preset, job)
+}
+
+/// Import a list of bundles into the library. The metadata `preset`
+/// is applied to each imported file, and they are then stacked
+/// following `stacking`. The progress is reported to `job`, that
+/// is finished at the end, and the import stops if it is cancelled.
+pub fn cmd_import_bundles(
+    catalog: &CatalogDb,
+    base: &Path,
+    bundles: &[FileBundle],
+    stacking: &StackingOptions,
+    preset: Option<&MetadataPreset>,
+    job: Option<&ImportJob>,
+) -> bool {
+    let result = import_bundles(catalog, base, bundles, stacking, preset, job);
+    if let Some(job) = job {
+        job.finish();
+    }
+    result
 }
 
-/// Import a list of bundles into the library.
-pub fn cmd_import_bundles(catalog: &CatalogDb, base: &Path, bundles: &[FileBundle]) -> bool {
+fn import_bundles(
+    catalog: &CatalogDb,
+    base: &Path,
+    bundles: &[FileBundle],
+    stacking: &StackingOptions,
+    preset: Option<&MetadataPreset>,
+    job: Option<&ImportJob>,
+) -> bool {
     let base_folders = catalog.get_folder_for_import(base);
     if let Err(err) = base_folders {
         err_out!("Couldn't get folder for import {base:?}: {err}");
+        if let Some(job) = job {
+            for bundle in bundles {
+                job.bundle_failed(bundle.main(), err.to_string());
+            }
+        }
         return false;
     }
 
+    if let Some(job) = job {
+        job.bundles_started(bundles.len());
+    }
+    // Imported files per folder.
+    let mut imported = BTreeMap::<LibraryId, Vec<LibraryId>>::new();
     for bundle in bundles {
+        if let Some(job) = job
+            && job.is_cancelled()
+        {
+            dbg_out!("Import cancelled");
+            break;
+        }
         match bundle
             .main()
             .parent()
@@ -160,20 +250,174 @@ pub fn cmd_import_bundles(catalog: &CatalogDb, base: &Path, bundles: &[FileBundl
         {
             Ok(libfolders) => {
                 let folder_id = libfolders.last().unwrap().id();
-                // XXX properly handle this error. Should be a failure.
-                if let Err(err) = catalog.add_bundle(folder_id, bundle) {
-                    err_out!("Add bundle failed: {:?}", err);
+                match catalog.add_bundle(folder_id, bundle) {
+                    Ok(id) => {
+                        if let Some(preset) = preset {
+                            if let Err(err) = apply_metadata_preset(catalog, id, preset) {
+                                err_out_line!("Apply preset failed: {:?}", err);
+                                if let Some(job) = job {
+                                    job.bundle_warning(
+                                        bundle.main(),
+                                        format!("{}: {err}", i18n("Metadata preset not applied")),
+                                    );
+                                }
+                            }
+                        }
+                        if let Some(job) = job {
+                            job.bundle_added(bundle.main());
+                        }
+                        imported.entry(folder_id).or_default().push(id)
+                    }
+                    Err(err) => {
+                        err_out!("Add bundle failed: {:?}", err);
+                        if let Some(job) = job {
+                            job.bundle_failed(bundle.main(), err.to_string());
+                        }
+                    }
                 }
                 if catalog.notify(LibNotification::AddedFiles).is_err() {
                     err_out!("Failed to notify AddedFiles");
                 }
             }
-            Err(err) => err_out_line!("Get folder for import {err:?}"),
+            Err(err) => {
+                err_out_line!("Get folder for import {err:?}");
+                if let Some(job) = job {
+                    job.bundle_failed(bundle.main(), err.to_string());
+                }
+            }
+        }
+    }
+
+    if stacking.is_enabled() {
+        for files in imported.values() {
+            auto_stack_files(catalog, files, stacking);
+        }
+    }
+    true
+}
+
+/// Apply the metadata `preset` to `file`. The keywords are added to
+/// those of the file, the other properties are replaced.
+fn apply_metadata_preset(
+    catalog: &CatalogDb,
+    file: LibraryId,
+    preset: &MetadataPreset,
+) -> crate::catalog::db::Result<()> {
+    catalog.in_transaction(|| {
+        for (meta, value) in preset.properties() {
+            let value = match (meta, value) {
+                (Np::Index(Npi::NpIptcKeywordsProp), PropertyValue::StringArray(keywords)) => {
+                    let mut paths = match catalog.get_metadata_value(file, *meta)? {
+                        PropertyValue::StringArray(paths) => paths,
+                        _ => vec![],
+                    };
+                    for keyword in keywords {
+                        if !paths.contains(keyword) {
+                            paths.push(keyword.clone());
+                        }
+                    }
+                    PropertyValue::StringArray(paths)
+                }
+                _ => value.clone(),
+            };
+            catalog.set_metadata(file, *meta, &value)?;
+        }
+        Ok(())
+    })
+}
+
+/// Stack `files` automatically following `options`.
+fn auto_stack_files(catalog: &CatalogDb, files: &[LibraryId], options: &StackingOptions) {
+    let candidates = match catalog.get_stack_candidates(files) {
+        Ok(candidates) => candidates,
+        Err(err) => {
+            err_out_line!("get_stack_candidates failed: {:?}", err);
+            return;
+        }
+    };
+    for files in stack::auto_stack(&candidates, options) {
+        match catalog.create_stack(&files) {
+            Ok(_) => notify_stack_changed(catalog, &files),
+            Err(err) => err_out_line!("create_stack failed: {:?}", err),
+        }
+    }
+}
+
+/// Get the files `ids`. `callback` is called with those found, in
+/// order.
+pub fn cmd_get_files(
+    catalog: &CatalogDb,
+    ids: &[LibraryId],
+    callback: ClientCallback<Vec<LibFile>>,
+) -> bool {
+    let files = ids
+        .iter()
+        .filter_map(|id| match catalog.get_file(*id) {
+            Ok(file) => Some(file),
+            Err(LibError::NotFound) => None,
+            Err(err) => {
+                err_out_line!("get_file failed: {:?}", err);
+                None
+            }
+        })
+        .collect();
+    callback(files);
+    true
+}
+
+/// Find the `files` to import that are already in the catalog, by
+/// content fingerprint, size and capture date. All the files of a
+/// bundle are duplicates if its main file is. `callback` is called
+/// with the duplicates.
+pub fn cmd_find_duplicates(
+    catalog: &CatalogDb,
+    files: &[PathBuf],
+    callback: ClientCallback<Vec<PathBuf>>,
+) -> bool {
+    let mut duplicates = vec![];
+    for bundle in FileBundle::filter_bundles(files) {
+        match find_duplicate(catalog, &bundle) {
+            Ok(Some(id)) => {
+                dbg_out!("{:?} is a duplicate of {id}", bundle.main());
+                duplicates.extend(bundle.all_files());
+            }
+            Ok(None) => {}
+            Err(err) => {
+                err_out_line!("find_duplicate failed: {:?}", err);
+                return false;
+            }
         }
     }
+    callback(duplicates);
     true
 }
 
+/// Find the file in the catalog that `bundle` is a duplicate of.
+fn find_duplicate(
+    catalog: &CatalogDb,
+    bundle: &FileBundle,
+) -> crate::catalog::db::Result<Option<LibraryId>> {
+    let main = bundle.main();
+    let Some(fingerprint) = fingerprint::fingerprint(main) else {
+        return Ok(None);
+    };
+    let size = std::fs::metadata(main)
+        .map(|metadata| metadata.len() as i64)
+        .unwrap_or(0);
+    // The capture date as `add_file()` records it.
+    let meta_file = if bundle.bundle_type() == FileType::RawJpeg {
+        bundle.jpeg()
+    } else {
+        main
+    };
+    let date = XmpMeta::new_from_file(meta_file, false)
+        .and_then(|xmp| xmp.creation_date())
+        .map(|date| date.timestamp())
+        .unwrap_or(0);
+
+    catalog.find_duplicate(&fingerprint, size, date)
+}
+
 pub fn cmd_add_bundle(catalog: &CatalogDb, bundle: &FileBundle, folder: LibraryId) -> LibraryId {
     match catalog.add_bundle(folder, bundle) {
         Ok(id) => {
@@ -207,7 +451,20 @@ pub fn cmd_create_folder(catalog: &CatalogDb, name: &str, path: Option<String>)
 }
 
 pub fn cmd_delete_folder(catalog: &CatalogDb, id: LibraryId, recursive: bool) -> bool {
-    delete_folder(catalog, id, recursive).is_ok()
+    if delete_folder(catalog, id, recursive).is_err() {
+        return false;
+    }
+    // The files are gone, it can't be undone.
+    record_journal(
+        catalog,
+        &i18n("Delete Folder"),
+        vec![JournalOp::DeleteFolder {
+            folder: id,
+            recursive,
+        }],
+        vec![],
+    );
+    true
 }
 
 fn delete_one_folder(catalog: &CatalogDb, id: LibraryId) -> crate::catalog::db::Result<()> {
@@ -246,6 +503,347 @@ fn delete_folder(
     }
 }
 
+/// Notify the files whose main file is missing on storage.
+fn notify_missing_files(catalog: &CatalogDb, files: Vec<LibraryId>) {
+    if catalog
+        .notify(LibNotification::MissingFiles(files))
+        .is_err()
+    {
+        err_out!("Failed to notify MissingFiles");
+    }
+}
+
+/// Check the main file of every file in the catalog and notify the
+/// missing ones.
+pub fn cmd_scan_missing_files(catalog: &CatalogDb) -> bool {
+    match catalog.get_main_fs_files() {
+        Ok(fsfiles) => {
+            let missing = fsfiles
+                .iter()
+                .filter(|(_, fsfile)| !fsfile.path().is_file())
+                .map(|(id, _)| *id)
+                .collect();
+            notify_missing_files(catalog, missing);
+            true
+        }
+        Err(err) => {
+            err_out_line!("get_main_fs_files failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Remap the `folder` and its subfolders to `path`, like when the
+/// drive was renamed.
+pub fn cmd_remap_folder(catalog: &CatalogDb, folder: LibraryId, path: &str) -> bool {
+    let path = path.trim_end_matches('/');
+    if path.is_empty() {
+        err_out!("Can't remap folder {folder} to the root");
+        return false;
+    }
+    match catalog.in_transaction(|| catalog.remap_folder(folder, path)) {
+        Ok(old_path) => {
+            record_journal(
+                catalog,
+                &i18n("Find Missing Folder"),
+                vec![JournalOp::RemapFolder {
+                    folder,
+                    path: path.to_string(),
+                }],
+                vec![JournalOp::RemapFolder {
+                    folder,
+                    path: old_path,
+                }],
+            );
+            match catalog.get_folder_tree_content(folder) {
+                Ok(files) => {
+                    let missing = files
+                        .iter()
+                        .filter(|file| !file.path().is_file())
+                        .map(LibFile::id)
+                        .collect();
+                    if catalog
+                        .notify(LibNotification::FilesRelocated(files))
+                        .is_err()
+                    {
+                        err_out!("Failed to notify FilesRelocated");
+                    }
+                    notify_missing_files(catalog, missing);
+                }
+                Err(err) => err_out_line!("get_folder_tree_content failed: {:?}", err),
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("remap_folder failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Search `dir` recursively for the missing files, by name and by
+/// size if it is known, and relink them. A file with more than one
+/// match is left alone.
+pub fn cmd_relink_missing_files(catalog: &CatalogDb, dir: &Path) -> bool {
+    let fsfiles = match catalog.get_main_fs_files() {
+        Ok(fsfiles) => fsfiles,
+        Err(err) => {
+            err_out_line!("get_main_fs_files failed: {:?}", err);
+            return false;
+        }
+    };
+    // The virtual copies share the fsfile.
+    let mut missing = BTreeMap::new();
+    for (id, fsfile) in fsfiles {
+        if !fsfile.path().is_file() {
+            missing
+                .entry(fsfile.id())
+                .or_insert_with(|| (fsfile, vec![]))
+                .1
+                .push(id);
+        }
+    }
+    if missing.is_empty() {
+        return true;
+    }
+
+    let found = FileList::files_from_directory(dir, |_| true, true, None);
+    let mut by_name = BTreeMap::new();
+    for path in found.iter() {
+        if let Some(name) = path.file_name() {
+            by_name.entry(name).or_insert_with(Vec::new).push(path);
+        }
+    }
+    let mut relocated = vec![];
+    let mut relinked = vec![];
+    for (fsfile, ids) in missing.values() {
+        let Some(candidates) = fsfile.path().file_name().and_then(|name| by_name.get(name)) else {
+            continue;
+        };
+        let candidates = candidates
+            .iter()
+            .filter(|path| {
+                fsfile.size().is_none_or(|size| {
+                    path.metadata()
+                        .map(|metadata| metadata.len() == size)
+                        .unwrap_or(false)
+                })
+            })
+            .collect::<Vec<_>>();
+        match candidates[..] {
+            [path] => match catalog.in_transaction(|| catalog.relink_fs_file(fsfile.id(), path)) {
+                Ok(paths) => {
+                    relinked.extend(paths);
+                    relocated.extend(ids.iter().filter_map(|id| catalog.get_file(*id).ok()));
+                }
+                Err(err) => err_out_line!("relink_fs_file failed: {:?}", err),
+            },
+            [] => {}
+            _ => dbg_out!("More than one match for {:?}", fsfile.path()),
+        }
+    }
+    if !relinked.is_empty() {
+        record_journal(
+            catalog,
+            &i18n("Relink Missing Files"),
+            vec![JournalOp::SetFsFilePaths {
+                paths: relinked
+                    .iter()
+                    .map(|(id, _, path)| (*id, path.clone()))
+                    .collect(),
+            }],
+            vec![JournalOp::SetFsFilePaths {
+                paths: relinked
+                    .into_iter()
+                    .map(|(id, old_path, _)| (id, old_path))
+                    .collect(),
+            }],
+        );
+    }
+    if !relocated.is_empty()
+        && catalog
+            .notify(LibNotification::FilesRelocated(relocated))
+            .is_err()
+    {
+        err_out!("Failed to notify FilesRelocated");
+    }
+    true
+}
+
+/// Set the `paths` of the fsfiles, as `(fsfile, path)`. This undoes
+/// and redoes `cmd_relink_missing_files`.
+pub(crate) fn cmd_set_fs_file_paths(catalog: &CatalogDb, paths: &[(LibraryId, String)]) -> bool {
+    let result = catalog.in_transaction(|| {
+        for (fsfile, path) in paths {
+            catalog.set_fs_file_path(*fsfile, path)?;
+        }
+        Ok(())
+    });
+    if let Err(err) = result {
+        err_out_line!("set_fs_file_path failed: {:?}", err);
+        return false;
+    }
+    match catalog.get_main_fs_files() {
+        Ok(fsfiles) => {
+            let relocated = fsfiles
+                .iter()
+                .filter(|(_, fsfile)| paths.iter().any(|(id, _)| *id == fsfile.id()))
+                .filter_map(|(id, _)| catalog.get_file(*id).ok())
+                .collect::<Vec<_>>();
+            if !relocated.is_empty()
+                && catalog
+                    .notify(LibNotification::FilesRelocated(relocated))
+                    .is_err()
+            {
+                err_out!("Failed to notify FilesRelocated");
+            }
+        }
+        Err(err) => err_out_line!("get_main_fs_files failed: {:?}", err),
+    }
+    true
+}
+
+/// Synchronize the folder `folder_id`, and its subfolders if
+/// `recursive`, with the storage: import the new files, mark the
+/// missing ones or remove them, and reload the XMP sidecars edited by
+/// other tools. With `options.dry_run` only the report is notified.
+pub fn cmd_synchronize_folder(
+    catalog: &CatalogDb,
+    folder_id: LibraryId,
+    recursive: bool,
+    options: &SyncOptions,
+) -> bool {
+    let mut report = match synchronize::diff_folder(catalog, folder_id, recursive) {
+        Ok(report) => report,
+        Err(LibError::MissingFolder) => {
+            dbg_out!("Folder {folder_id} is missing on storage");
+            if catalog
+                .notify(LibNotification::FolderMissing(folder_id))
+                .is_err()
+            {
+                err_out!("Failed to notify FolderMissing");
+            }
+            return false;
+        }
+        Err(err) => {
+            err_out_line!("diff_folder failed: {:?}", err);
+            return false;
+        }
+    };
+    if !options.dry_run {
+        if !report.added.is_empty() {
+            if let Ok(folder) = catalog.get_folder_by_id(folder_id) {
+                let base = PathBuf::from(folder.path().unwrap_or_default());
+                cmd_import_bundles(
+                    catalog,
+                    &base,
+                    &report.added,
+                    &StackingOptions::default(),
+                    None,
+                    None,
+                );
+            }
+        }
+        if options.remove_missing {
+            remove_missing_files(catalog, &report.missing);
+        } else {
+            notify_missing_files(catalog, report.missing.clone());
+        }
+        for (id, sidecar) in &report.xmp_changed {
+            reload_xmp_sidecar(catalog, *id, sidecar);
+        }
+        report.applied = true;
+        report.removed = options.remove_missing;
+        // The imported and removed files can't be restored.
+        record_journal(
+            catalog,
+            &i18n("Synchronize Folder"),
+            vec![JournalOp::SynchronizeFolder {
+                folder: folder_id,
+                recursive,
+                remove_missing: options.remove_missing,
+            }],
+            vec![],
+        );
+    }
+    if catalog
+        .notify(LibNotification::FolderSynchronized(Box::new(report)))
+        .is_err()
+    {
+        err_out!("Failed to notify FolderSynchronized");
+    }
+    true
+}
+
+/// Remove the `files` from the catalog.
+fn remove_missing_files(catalog: &CatalogDb, files: &[LibraryId]) {
+    for id in files {
+        let removed = catalog.in_transaction(|| {
+            let folder = catalog.get_file_folder_id(*id)?;
+            catalog.delete_file(*id)?;
+            Ok(folder)
+        });
+        match removed {
+            Ok(folder) => {
+                if catalog.notify(LibNotification::FileDeleted(*id)).is_err() {
+                    err_out!("Failed to notify FileDeleted");
+                }
+                if catalog
+                    .notify(LibNotification::FolderCountChanged(Count {
+                        id: folder,
+                        count: -1,
+                    }))
+                    .is_err()
+                {
+                    err_out!("Failed to notify FolderCountChanged");
+                }
+            }
+            Err(err) => err_out_line!("delete_file failed: {:?}", err),
+        }
+    }
+}
+
+/// Reload the XMP of the file `id` from its `sidecar`.
+fn reload_xmp_sidecar(catalog: &CatalogDb, id: LibraryId, sidecar: &Path) {
+    let mut meta = npc_fwk::XmpMeta::new();
+    let loaded = std::fs::read_to_string(sidecar)
+        .map(|buf| meta.unserialize(&buf))
+        .unwrap_or(false);
+    let Some(date) = synchronize::modified_time(sidecar).filter(|_| loaded) else {
+        err_out!("Couldn't read the XMP sidecar {sidecar:?}");
+        return;
+    };
+    let reloaded = catalog.in_transaction(|| {
+        if catalog.get_xmp_sidecar(id)?.0.is_none() {
+            catalog.add_xmp_sidecar(id, sidecar)?;
+        }
+        catalog.reload_xmp(id, meta, date)?;
+        catalog.get_file(id)
+    });
+    match reloaded {
+        Ok(file) => {
+            for meta in [
+                Npi::NpXmpRatingProp,
+                Npi::NpNiepceFlagProp,
+                Npi::NpTiffOrientationProp,
+            ] {
+                let meta = Np::Index(meta);
+                if catalog
+                    .notify(LibNotification::MetadataChanged(MetadataChange::new(
+                        id,
+                        meta,
+                        PropertyValue::Int(file.property(meta)),
+                    )))
+                    .is_err()
+                {
+                    err_out!("Failed to notify MetadataChange");
+                }
+            }
+        }
+        Err(err) => err_out_line!("reload_xmp failed: {:?}", err),
+    }
+}
+
 pub fn cmd_list_all_albums(catalog: &CatalogDb) -> bool {
     match catalog.get_all_albums() {
         Ok(albums) => {
@@ -292,6 +890,17 @@ pub fn cmd_create_album(catalog: &CatalogDb, name: &str, parent: LibraryId) -> L
             if catalog.notify(LibNotification::AddedAlbum(album)).is_err() {
                 err_out!("Failed to notify AddedAlbum");
             }
+            record_journal(
+                catalog,
+                &i18n("Create Album"),
+                vec![JournalOp::CreateAlbum {
+                    album: id,
+                    name: name.to_string(),
+                    parent,
+                    images: vec![],
+                }],
+                vec![JournalOp::DeleteAlbum { album: id }],
+            );
             id
         }
         Err(err) => {
@@ -302,11 +911,28 @@ pub fn cmd_create_album(catalog: &CatalogDb, name: &str, parent: LibraryId) -> L
 }
 
 pub fn cmd_delete_album(catalog: &CatalogDb, id: LibraryId) -> bool {
+    let inverse = catalog
+        .get_album(id)
+        .and_then(|album| {
+            Ok(vec![JournalOp::CreateAlbum {
+                album: id,
+                name: album.name().to_string(),
+                parent: album.parent(),
+                images: catalog.get_album_file_ids(id)?,
+            }])
+        })
+        .unwrap_or_default();
     match catalog.delete_album(id) {
         Ok(_) => {
             if catalog.notify(LibNotification::AlbumDeleted(id)).is_err() {
                 err_out!("Failed to notify AlbumDeleted");
             }
+            record_journal(
+                catalog,
+                &i18n("Delete Album"),
+                vec![JournalOp::DeleteAlbum { album: id }],
+                inverse,
+            );
             true
         }
         Err(err) => {
@@ -318,8 +944,27 @@ pub fn cmd_delete_album(catalog: &CatalogDb, id: LibraryId) -> bool {
 
 /// Command to add `images` to an `album`.
 pub fn cmd_add_to_album(catalog: &CatalogDb, images: Vec<LibraryId>, album: LibraryId) -> bool {
+    // Only remove what wasn't already in the album.
+    let existing = catalog.get_album_file_ids(album).unwrap_or_default();
+    let added = images
+        .iter()
+        .filter(|id| !existing.contains(*id))
+        .copied()
+        .collect::<Vec<_>>();
     match catalog.add_to_album(&images, album) {
         Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Add to Album"),
+                vec![JournalOp::AddToAlbum {
+                    images: images.clone(),
+                    album,
+                }],
+                vec![JournalOp::RemoveFromAlbum {
+                    images: added,
+                    album,
+                }],
+            );
             if catalog
                 .notify(LibNotification::AddedToAlbum(images, album))
                 .is_err()
@@ -346,8 +991,26 @@ pub fn cmd_remove_from_album(
     images: Vec<LibraryId>,
     album: LibraryId,
 ) -> bool {
+    let existing = catalog.get_album_file_ids(album).unwrap_or_default();
+    let removed = images
+        .iter()
+        .filter(|id| existing.contains(*id))
+        .copied()
+        .collect::<Vec<_>>();
     match catalog.remove_from_album(&images, album) {
         Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Remove from Album"),
+                vec![JournalOp::RemoveFromAlbum {
+                    images: images.clone(),
+                    album,
+                }],
+                vec![JournalOp::AddToAlbum {
+                    images: removed,
+                    album,
+                }],
+            );
             if catalog
                 .notify(LibNotification::RemovedFromAlbum(images, album))
                 .is_err()
@@ -369,8 +1032,22 @@ pub fn cmd_remove_from_album(
 }
 
 pub fn cmd_rename_album(catalog: &CatalogDb, album: LibraryId, name: &str) -> bool {
+    let old_name = catalog
+        .get_album(album)
+        .map(|album| album.name().to_string());
     match catalog.rename_album(album, name) {
         Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Rename Album"),
+                vec![JournalOp::RenameAlbum {
+                    album,
+                    name: name.to_string(),
+                }],
+                old_name
+                    .map(|name| vec![JournalOp::RenameAlbum { album, name }])
+                    .unwrap_or_default(),
+            );
             if catalog
                 .notify(LibNotification::AlbumRenamed(album, name.to_string()))
                 .is_err()
@@ -412,135 +1089,1152 @@ pub fn cmd_query_album_content(catalog: &CatalogDb, album_id: LibraryId) -> bool
     }
 }
 
-pub fn cmd_request_metadata(catalog: &CatalogDb, file_id: LibraryId) -> bool {
-    match catalog.get_metadata(file_id) {
-        Ok(lm) => {
-            // This time it's a fatal error since the purpose of this comand
-            // is to retrieve.
-            match catalog.notify(LibNotification::MetadataQueried(lm)) {
-                Err(err) => {
-                    err_out!("Failed to notify Metadata {:?}", err);
-                    false
+pub fn cmd_list_all_projects(catalog: &CatalogDb) -> bool {
+    match catalog.get_all_projects() {
+        Ok(projects) => {
+            for project in projects {
+                if let Err(err) = catalog.notify(LibNotification::AddedProject(project)) {
+                    err_out!("Failed to notify AddedProject {:?}", err);
+                    return false;
                 }
-                Ok(_) => true,
             }
+            true
         }
         Err(err) => {
-            err_out_line!("Get metadata failed {:?}", err);
+            err_out_line!("get_all_projects failed: {:?}", err);
             false
         }
     }
 }
 
-/// Command to set image properties.
-pub fn cmd_set_image_properties(
-    catalog: &CatalogDb,
-    image_id: LibraryId,
-    props: &NiepcePropertyBag,
-) -> bool {
-    match catalog.set_image_properties(image_id, props) {
-        Ok(_) => {
-            // XXX set the image properties.
+pub fn cmd_count_project(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.count_project(id) {
+        Ok(count) => {
+            if let Err(err) = catalog.notify(LibNotification::ProjectCounted(Count { id, count })) {
+                err_out!("Failed to notify ProjectCounted {:?}", err);
+                return false;
+            }
             true
         }
         Err(err) => {
-            err_out_line!("Setting image metadata failed {:?}", err);
+            err_out_line!("count_project failed: {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_query_folder_content(catalog: &CatalogDb, folder_id: LibraryId) -> bool {
-    match catalog.get_folder_content(folder_id) {
-        Ok(fl) => {
-            let mut content = QueriedContent::new(folder_id);
-            for f in fl {
-                content.push(f);
-            }
-            let value = LibNotification::FolderContentQueried(content);
-
-            // This time it's a fatal error since the purpose of this comand
-            // is to retrieve.
-            match catalog.notify(value) {
-                Err(err) => {
-                    err_out!("Failed to notify FolderContent {:?}", err);
-                    false
-                }
-                Ok(_) => true,
+pub fn cmd_create_project(catalog: &CatalogDb, name: &str) -> LibraryId {
+    match catalog.add_project(name) {
+        Ok(project) => {
+            let id = project.id();
+            if catalog
+                .notify(LibNotification::AddedProject(project))
+                .is_err()
+            {
+                err_out!("Failed to notify AddedProject");
             }
+            record_journal(
+                catalog,
+                &i18n("Create Project"),
+                vec![JournalOp::CreateProject {
+                    project: id,
+                    name: name.to_string(),
+                }],
+                vec![JournalOp::DeleteProject { project: id }],
+            );
+            id
         }
         Err(err) => {
-            err_out_line!("Get folder content failed {:?}", err);
-            false
+            err_out_line!("Project creation failed {:?}", err);
+            -1
         }
     }
 }
 
-pub fn cmd_set_metadata(
-    catalog: &CatalogDb,
-    id: LibraryId,
-    meta: Np,
-    value: &PropertyValue,
-) -> bool {
-    match catalog.set_metadata(id, meta, value) {
+/// The operations to restore the project `id`: its files with their
+/// state and its items.
+fn project_restore_ops(catalog: &CatalogDb, id: LibraryId) -> LibResult<Vec<JournalOp>> {
+    let project = catalog.get_project(id)?;
+    let states = catalog.get_project_states(id)?;
+    let mut ops = vec![
+        JournalOp::CreateProject {
+            project: id,
+            name: project.name().to_string(),
+        },
+        JournalOp::AddToProject {
+            images: states.iter().map(|(file, _)| *file).collect(),
+            project: id,
+        },
+    ];
+    ops.extend(
+        states
+            .into_iter()
+            .filter(|(_, state)| *state != ProjectState::default())
+            .map(|(file, state)| JournalOp::SetProjectState {
+                project: id,
+                file,
+                state: state.into(),
+            }),
+    );
+    ops.extend(
+        catalog
+            .get_project_items(id)?
+            .into_iter()
+            .map(|item| JournalOp::AddProjectItem { project: id, item }),
+    );
+    Ok(ops)
+}
+
+pub fn cmd_delete_project(catalog: &CatalogDb, id: LibraryId) -> bool {
+    // The inverse is applied in reverse order.
+    let inverse = project_restore_ops(catalog, id)
+        .map(|ops| ops.into_iter().rev().collect())
+        .unwrap_or_default();
+    match catalog.delete_project(id) {
         Ok(_) => {
-            if catalog
-                .notify(LibNotification::MetadataChanged(MetadataChange::new(
-                    id,
-                    meta,
-                    value.clone(),
-                )))
-                .is_err()
-            {
-                err_out!("Failed to notify MetadataChange");
+            if catalog.notify(LibNotification::ProjectDeleted(id)).is_err() {
+                err_out!("Failed to notify ProjectDeleted");
             }
+            record_journal(
+                catalog,
+                &i18n("Delete Project"),
+                vec![JournalOp::DeleteProject { project: id }],
+                inverse,
+            );
             true
         }
         Err(err) => {
-            err_out_line!("set_matadata failed: {:?}", err);
+            err_out_line!("Delete project failed {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_count_folder(catalog: &CatalogDb, id: LibraryId) -> bool {
-    match catalog.count_folder(id) {
-        Ok(count) => {
-            // This time it's a fatal error since the purpose of this comand
-            // is to retrieve.
-            match catalog.notify(LibNotification::FolderCounted(Count { id, count })) {
-                Err(err) => {
-                    err_out!("Failed to notify FolderCounted {:?}", err);
-                    false
-                }
-                Ok(_) => true,
+pub fn cmd_rename_project(catalog: &CatalogDb, project: LibraryId, name: &str) -> bool {
+    let old_name = catalog
+        .get_project(project)
+        .map(|project| project.name().to_string());
+    match catalog.rename_project(project, name) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Rename Project"),
+                vec![JournalOp::RenameProject {
+                    project,
+                    name: name.to_string(),
+                }],
+                old_name
+                    .map(|name| vec![JournalOp::RenameProject { project, name }])
+                    .unwrap_or_default(),
+            );
+            if catalog
+                .notify(LibNotification::ProjectRenamed(project, name.to_string()))
+                .is_err()
+            {
+                err_out!("Failed to notify ProjectRenamed");
             }
+            true
         }
         Err(err) => {
-            err_out_line!("count_folder failed: {:?}", err);
+            err_out_line!("Renaming project {} to {} failed {:?}", project, name, err);
             false
         }
     }
 }
 
-/// Add a keyword. Return `LibraryId` of the keyword, already existing
-/// or created.
-pub fn cmd_add_keyword(catalog: &CatalogDb, keyword: &str) -> LibraryId {
-    match catalog.make_keyword(keyword) {
-        Ok(id) => {
+/// Command to attach `images` to a `project`.
+pub fn cmd_add_to_project(catalog: &CatalogDb, images: Vec<LibraryId>, project: LibraryId) -> bool {
+    // Only remove what wasn't already in the project.
+    let existing = catalog.get_project_states(project).unwrap_or_default();
+    let added = images
+        .iter()
+        .filter(|id| !existing.iter().any(|(file, _)| file == *id))
+        .copied()
+        .collect::<Vec<_>>();
+    match catalog.add_to_project(&images, project) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Add to Project"),
+                vec![JournalOp::AddToProject {
+                    images: images.clone(),
+                    project,
+                }],
+                vec![JournalOp::RemoveFromProject {
+                    images: added,
+                    project,
+                }],
+            );
             if catalog
-                .notify(LibNotification::AddedKeyword(Keyword::new(id, keyword)))
+                .notify(LibNotification::AddedToProject(images, project))
                 .is_err()
             {
-                err_out!("Failed to notify AddedKeyword");
+                err_out!("Failed to notify AddedToProject");
             }
-            id
+            true
         }
         Err(err) => {
-            err_out_line!("make_keyword failed: {:?}", err);
-            -1
-        }
-    }
+            err_out_line!(
+                "Adding images {:?} to project {} failed {:?}",
+                images,
+                project,
+                err
+            );
+            false
+        }
+    }
+}
+
+/// Command to detach `images` from a `project`.
+pub fn cmd_remove_from_project(
+    catalog: &CatalogDb,
+    images: Vec<LibraryId>,
+    project: LibraryId,
+) -> bool {
+    // The inverse restores the membership and the states.
+    let existing = catalog.get_project_states(project).unwrap_or_default();
+    let removed = existing
+        .iter()
+        .filter(|(file, _)| images.contains(file))
+        .collect::<Vec<_>>();
+    let mut inverse = vec![JournalOp::AddToProject {
+        images: removed.iter().map(|(file, _)| *file).collect(),
+        project,
+    }];
+    inverse.extend(
+        removed
+            .iter()
+            .filter(|(_, state)| *state != ProjectState::None)
+            .map(|(file, state)| JournalOp::SetProjectState {
+                project,
+                file: *file,
+                state: (*state).into(),
+            }),
+    );
+    match catalog.remove_from_project(&images, project) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Remove from Project"),
+                vec![JournalOp::RemoveFromProject {
+                    images: images.clone(),
+                    project,
+                }],
+                inverse,
+            );
+            if catalog
+                .notify(LibNotification::RemovedFromProject(images, project))
+                .is_err()
+            {
+                err_out!("Failed to notify RemovedFromProject");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!(
+                "Removing images {:?} from project {} failed {:?}",
+                images,
+                project,
+                err
+            );
+            false
+        }
+    }
+}
+
+pub fn cmd_query_project_content(catalog: &CatalogDb, project_id: LibraryId) -> bool {
+    match catalog.get_project_content(project_id) {
+        Ok(fl) => {
+            let mut content = QueriedContent::new(project_id);
+            for f in fl {
+                content.push(f);
+            }
+            match catalog.notify(LibNotification::ProjectContentQueried(content)) {
+                Err(err) => {
+                    err_out!("Failed to notify ProjectContentQueried {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Get project content failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// Command to set the `state` of `file` in `project`.
+pub fn cmd_set_project_state(
+    catalog: &CatalogDb,
+    project: LibraryId,
+    file: LibraryId,
+    state: ProjectState,
+) -> bool {
+    let old_state = catalog.get_project_states(project).map(|states| {
+        states
+            .iter()
+            .find(|(id, _)| *id == file)
+            .map(|(_, state)| *state)
+            .unwrap_or_default()
+    });
+    match catalog.set_project_state(project, file, state) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Set Project State"),
+                vec![JournalOp::SetProjectState {
+                    project,
+                    file,
+                    state: state.into(),
+                }],
+                old_state
+                    .map(|old_state| {
+                        vec![JournalOp::SetProjectState {
+                            project,
+                            file,
+                            state: old_state.into(),
+                        }]
+                    })
+                    .unwrap_or_default(),
+            );
+            if catalog
+                .notify(LibNotification::ProjectStateChanged(ProjectStateChange {
+                    project,
+                    file,
+                    state,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify ProjectStateChanged");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!(
+                "Setting state {:?} for {} in project {} failed {:?}",
+                state,
+                file,
+                project,
+                err
+            );
+            false
+        }
+    }
+}
+
+pub fn cmd_query_project_states(catalog: &CatalogDb, project: LibraryId) -> bool {
+    match catalog.get_project_states(project) {
+        Ok(states) => {
+            match catalog.notify(LibNotification::ProjectStatesQueried(project, states)) {
+                Err(err) => {
+                    err_out!("Failed to notify ProjectStatesQueried {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Get project states failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// Command to attach an album, a folder or an output to a `project`.
+pub fn cmd_add_project_item(catalog: &CatalogDb, project: LibraryId, item: &ProjectItem) -> bool {
+    let attached = catalog
+        .get_project_items(project)
+        .map(|items| items.contains(item))
+        .unwrap_or(false);
+    match catalog.add_project_item(project, item) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Add to Project"),
+                vec![JournalOp::AddProjectItem {
+                    project,
+                    item: item.clone(),
+                }],
+                if attached {
+                    vec![]
+                } else {
+                    vec![JournalOp::RemoveProjectItem {
+                        project,
+                        item: item.clone(),
+                    }]
+                },
+            );
+            true
+        }
+        Err(err) => {
+            err_out_line!("Adding {:?} to project {} failed {:?}", item, project, err);
+            false
+        }
+    }
+}
+
+pub fn cmd_remove_project_item(
+    catalog: &CatalogDb,
+    project: LibraryId,
+    item: &ProjectItem,
+) -> bool {
+    match catalog.remove_project_item(project, item) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Remove from Project"),
+                vec![JournalOp::RemoveProjectItem {
+                    project,
+                    item: item.clone(),
+                }],
+                vec![JournalOp::AddProjectItem {
+                    project,
+                    item: item.clone(),
+                }],
+            );
+            true
+        }
+        Err(err) => {
+            err_out_line!(
+                "Removing {:?} from project {} failed {:?}",
+                item,
+                project,
+                err
+            );
+            false
+        }
+    }
+}
+
+pub fn cmd_query_project_items(catalog: &CatalogDb, project: LibraryId) -> bool {
+    match catalog.get_project_items(project) {
+        Ok(items) => match catalog.notify(LibNotification::ProjectItemsQueried(project, items)) {
+            Err(err) => {
+                err_out!("Failed to notify ProjectItemsQueried {:?}", err);
+                false
+            }
+            Ok(_) => true,
+        },
+        Err(err) => {
+            err_out_line!("Get project items failed {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_list_all_smart_collections(catalog: &CatalogDb) -> bool {
+    match catalog.get_all_smart_collections() {
+        Ok(collections) => {
+            for collection in collections {
+                if let Err(err) = catalog.notify(LibNotification::AddedSmartCollection(collection))
+                {
+                    err_out!("Failed to notify AddedSmartCollection {:?}", err);
+                    return false;
+                }
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("get_all_smart_collections failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Create a smart collection with `name` and `rules`. Return the id
+/// of the collection or -1 on error.
+pub fn cmd_create_smart_collection(
+    catalog: &CatalogDb,
+    name: &str,
+    rules: &[SmartRule],
+    match_all: bool,
+) -> LibraryId {
+    match catalog.add_smart_collection(name, rules, match_all) {
+        Ok(collection) => {
+            let id = collection.id();
+            if catalog
+                .notify(LibNotification::AddedSmartCollection(collection))
+                .is_err()
+            {
+                err_out!("Failed to notify AddedSmartCollection");
+            }
+            record_journal(
+                catalog,
+                &i18n("Create Smart Collection"),
+                vec![JournalOp::CreateSmartCollection {
+                    collection: id,
+                    name: name.to_string(),
+                    rules: SmartCollection::rules_to_string(rules),
+                    match_all,
+                }],
+                vec![JournalOp::DeleteSmartCollection { collection: id }],
+            );
+            id
+        }
+        Err(err) => {
+            err_out_line!("Smart collection creation failed {:?}", err);
+            -1
+        }
+    }
+}
+
+pub fn cmd_update_smart_collection(
+    catalog: &CatalogDb,
+    id: LibraryId,
+    name: &str,
+    rules: &[SmartRule],
+    match_all: bool,
+) -> bool {
+    let old = catalog.get_smart_collection(id);
+    match catalog
+        .update_smart_collection(id, name, rules, match_all)
+        .and_then(|_| catalog.get_smart_collection(id))
+    {
+        Ok(collection) => {
+            record_journal(
+                catalog,
+                &i18n("Edit Smart Collection"),
+                vec![JournalOp::UpdateSmartCollection {
+                    collection: id,
+                    name: name.to_string(),
+                    rules: SmartCollection::rules_to_string(rules),
+                    match_all,
+                }],
+                old.map(|old| {
+                    vec![JournalOp::UpdateSmartCollection {
+                        collection: id,
+                        name: old.name().to_string(),
+                        rules: SmartCollection::rules_to_string(old.rules()),
+                        match_all: old.match_all(),
+                    }]
+                })
+                .unwrap_or_default(),
+            );
+            if catalog
+                .notify(LibNotification::SmartCollectionChanged(collection))
+                .is_err()
+            {
+                err_out!("Failed to notify SmartCollectionChanged");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("Updating smart collection {} failed {:?}", id, err);
+            false
+        }
+    }
+}
+
+pub fn cmd_delete_smart_collection(catalog: &CatalogDb, id: LibraryId) -> bool {
+    let inverse = catalog
+        .get_smart_collection(id)
+        .map(|collection| {
+            vec![JournalOp::CreateSmartCollection {
+                collection: id,
+                name: collection.name().to_string(),
+                rules: SmartCollection::rules_to_string(collection.rules()),
+                match_all: collection.match_all(),
+            }]
+        })
+        .unwrap_or_default();
+    match catalog.delete_smart_collection(id) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Delete Smart Collection"),
+                vec![JournalOp::DeleteSmartCollection { collection: id }],
+                inverse,
+            );
+            if catalog
+                .notify(LibNotification::SmartCollectionDeleted(id))
+                .is_err()
+            {
+                err_out!("Failed to notify SmartCollectionDeleted");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("Delete smart collection failed {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_query_smart_collection_content(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.get_smart_collection_content(id) {
+        Ok(fl) => {
+            let mut content = QueriedContent::new(id);
+            for f in fl {
+                content.push(f);
+            }
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::SmartCollectionContentQueried(content)) {
+                Err(err) => {
+                    err_out!("Failed to notify SmartCollectionContentQueried {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Get smart collection content failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// Search the catalog with `query`, as parsed by `SearchQuery`.
+/// The results are notified with an id of 0.
+pub fn cmd_search(catalog: &CatalogDb, query: &str) -> bool {
+    let query = match query.parse::<SearchQuery>() {
+        Ok(query) => query,
+        Err(err) => {
+            err_out!("Invalid search query '{query}': {err}");
+            return false;
+        }
+    };
+    match catalog.search(&query) {
+        Ok(fl) => {
+            let mut content = QueriedContent::new(0);
+            for f in fl {
+                content.push(f);
+            }
+            match catalog.notify(LibNotification::SearchResultsQueried(content)) {
+                Err(err) => {
+                    err_out!("Failed to notify SearchResultsQueried {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Search failed {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_count_smart_collection(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.count_smart_collection(id) {
+        Ok(count) => {
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::SmartCollectionCounted(Count { id, count })) {
+                Err(err) => {
+                    err_out!("Failed to notify SmartCollectionCounted {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("count_smart_collection failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_list_all_metadata_presets(
+    catalog: &CatalogDb,
+    callback: ClientCallback<Vec<MetadataPreset>>,
+) -> bool {
+    match catalog.get_all_metadata_presets() {
+        Ok(presets) => {
+            callback(presets);
+            true
+        }
+        Err(err) => {
+            err_out_line!("get_all_metadata_presets failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Save the metadata preset `name` with `properties`, replacing the
+/// preset with the same name. Return the id of the preset, or 0 on error.
+pub fn cmd_save_metadata_preset(
+    catalog: &CatalogDb,
+    name: &str,
+    properties: &[(Np, PropertyValue)],
+) -> LibraryId {
+    match catalog.save_metadata_preset(name, properties) {
+        Ok(preset) => preset.id(),
+        Err(err) => {
+            err_out_line!("save_metadata_preset failed: {:?}", err);
+            0
+        }
+    }
+}
+
+pub fn cmd_delete_metadata_preset(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.delete_metadata_preset(id) {
+        Ok(_) => true,
+        Err(err) => {
+            err_out_line!("delete_metadata_preset failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_list_all_watched_folders(
+    catalog: &CatalogDb,
+    callback: ClientCallback<Vec<WatchedFolder>>,
+) -> bool {
+    match catalog.get_all_watched_folders() {
+        Ok(folders) => {
+            callback(folders);
+            true
+        }
+        Err(err) => {
+            err_out_line!("get_all_watched_folders failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Save the watched folder for `path`, replacing the one with the
+/// same path. Return the id of the watched folder, or 0 on error.
+pub fn cmd_save_watched_folder(
+    catalog: &CatalogDb,
+    path: &Path,
+    dest: Option<&Path>,
+    preset_id: LibraryId,
+) -> LibraryId {
+    match catalog.save_watched_folder(path, dest, preset_id) {
+        Ok(folder) => folder.id(),
+        Err(err) => {
+            err_out_line!("save_watched_folder failed: {:?}", err);
+            0
+        }
+    }
+}
+
+pub fn cmd_delete_watched_folder(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.delete_watched_folder(id) {
+        Ok(_) => true,
+        Err(err) => {
+            err_out_line!("delete_watched_folder failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_request_metadata(catalog: &CatalogDb, file_id: LibraryId) -> bool {
+    match catalog.get_metadata(file_id) {
+        Ok(lm) => {
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::MetadataQueried(lm)) {
+                Err(err) => {
+                    err_out!("Failed to notify Metadata {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Get metadata failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// Command to set image properties.
+pub fn cmd_set_image_properties(
+    catalog: &CatalogDb,
+    image_id: LibraryId,
+    props: &NiepcePropertyBag,
+) -> bool {
+    let xmp_packet = Np::Index(Npi::NpNiepceXmpPacket);
+    let old = props
+        .keys()
+        .map(|key| {
+            let value = if *key == xmp_packet {
+                catalog
+                    .get_metadata(image_id)
+                    .map(|meta| PropertyValue::String(meta.serialize_inline()))
+            } else {
+                catalog.get_metadata_value(image_id, *key)
+            };
+            value.map(|value| (u32::from(*key), JournalValue::from(&value)))
+        })
+        .collect::<LibResult<Vec<_>>>();
+    match catalog.set_image_properties(image_id, props) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Set Image Properties"),
+                vec![JournalOp::SetImageProperties {
+                    file: image_id,
+                    props: props
+                        .keys()
+                        .filter_map(|key| {
+                            props
+                                .get(key)
+                                .map(|value| (u32::from(*key), JournalValue::from(value)))
+                        })
+                        .collect(),
+                }],
+                old.map(|props| {
+                    vec![JournalOp::SetImageProperties {
+                        file: image_id,
+                        props,
+                    }]
+                })
+                .unwrap_or_default(),
+            );
+            true
+        }
+        Err(err) => {
+            err_out_line!("Setting image metadata failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// Set the develop settings of the file `id`.
+pub fn cmd_set_develop_settings(
+    catalog: &CatalogDb,
+    id: LibraryId,
+    settings: &DevelopSettings,
+) -> bool {
+    let old = catalog
+        .get_develop_settings(id)
+        .map(|old| old.unwrap_or_default());
+    match catalog.set_develop_settings(id, settings) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Develop"),
+                vec![JournalOp::SetDevelopSettings {
+                    file: id,
+                    settings: settings.clone(),
+                }],
+                old.map(|settings| vec![JournalOp::SetDevelopSettings { file: id, settings }])
+                    .unwrap_or_default(),
+            );
+            if catalog
+                .notify(LibNotification::DevelopSettingsChanged(
+                    id,
+                    Box::new(settings.clone()),
+                ))
+                .is_err()
+            {
+                err_out!("Failed to notify DevelopSettingsChanged");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("set_develop_settings failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_query_folder_content(catalog: &CatalogDb, folder_id: LibraryId) -> bool {
+    match catalog.get_folder_content(folder_id) {
+        Ok(fl) => {
+            let mut content = QueriedContent::new(folder_id);
+            for f in fl {
+                content.push(f);
+            }
+            let value = LibNotification::FolderContentQueried(content);
+
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(value) {
+                Err(err) => {
+                    err_out!("Failed to notify FolderContent {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("Get folder content failed {:?}", err);
+            false
+        }
+    }
+}
+
+/// The journal label for setting `meta`.
+fn metadata_journal_label(meta: Np) -> String {
+    match meta {
+        Np::Index(Npi::NpXmpRatingProp) => i18n("Set Rating"),
+        Np::Index(Npi::NpXmpLabelProp) => i18n("Set Label"),
+        Np::Index(Npi::NpNiepceFlagProp) => i18n("Set Flag"),
+        _ => i18n("Set Metadata"),
+    }
+}
+
+/// Set the metadata `meta` of the file `id` to `value`. The old value
+/// is read in the same transaction, for the journal: if it can't be,
+/// nothing is changed.
+pub fn cmd_set_metadata(
+    catalog: &CatalogDb,
+    id: LibraryId,
+    meta: Np,
+    value: &PropertyValue,
+) -> bool {
+    cmd_set_metadata_values(catalog, meta, &[(id, value.clone())])
+}
+
+/// Set the metadata `meta` to `value` for all the files `ids`, in
+/// one transaction. It is recorded as one journal entry.
+pub fn cmd_set_metadata_many(
+    catalog: &CatalogDb,
+    ids: &[LibraryId],
+    meta: Np,
+    value: &PropertyValue,
+) -> bool {
+    let values = ids
+        .iter()
+        .map(|id| (*id, value.clone()))
+        .collect::<Vec<_>>();
+    cmd_set_metadata_values(catalog, meta, &values)
+}
+
+/// Set the metadata `meta` to a value per file from `values`, in one
+/// transaction. It is recorded as one journal entry.
+pub fn cmd_set_metadata_values(
+    catalog: &CatalogDb,
+    meta: Np,
+    values: &[(LibraryId, PropertyValue)],
+) -> bool {
+    let entries = values
+        .iter()
+        .map(|(id, value)| (*id, meta, value.clone()))
+        .collect::<Vec<_>>();
+    set_metadata_entries(catalog, &metadata_journal_label(meta), &entries)
+}
+
+/// Set the properties `props` for all the files `ids`, in one
+/// transaction. It is recorded as one journal entry.
+pub fn cmd_set_properties_many(
+    catalog: &CatalogDb,
+    ids: &[LibraryId],
+    props: &[(Np, PropertyValue)],
+) -> bool {
+    let label = match props {
+        [(meta, _)] => metadata_journal_label(*meta),
+        _ => i18n("Set Metadata"),
+    };
+    let entries = ids
+        .iter()
+        .flat_map(|id| {
+            props
+                .iter()
+                .map(|(meta, value)| (*id, *meta, value.clone()))
+        })
+        .collect::<Vec<_>>();
+    set_metadata_entries(catalog, &label, &entries)
+}
+
+/// Set the metadata `entries`, as `(file, meta, value)`, in one
+/// transaction recorded as one journal entry with `label`.
+fn set_metadata_entries(
+    catalog: &CatalogDb,
+    label: &str,
+    entries: &[(LibraryId, Np, PropertyValue)],
+) -> bool {
+    let result = catalog.in_transaction(|| {
+        entries
+            .iter()
+            .map(|(id, meta, value)| {
+                let old_value = catalog.get_metadata_value(*id, *meta)?;
+                catalog.set_metadata(*id, *meta, value)?;
+                Ok(JournalOp::SetMetadata {
+                    file: *id,
+                    meta: (*meta).into(),
+                    value: (&old_value).into(),
+                })
+            })
+            .collect::<crate::catalog::db::Result<Vec<_>>>()
+    });
+    match result {
+        Ok(inverse) => {
+            for (id, meta, value) in entries {
+                if catalog
+                    .notify(LibNotification::MetadataChanged(MetadataChange::new(
+                        *id,
+                        *meta,
+                        value.clone(),
+                    )))
+                    .is_err()
+                {
+                    err_out!("Failed to notify MetadataChange");
+                }
+            }
+            let forward = entries
+                .iter()
+                .map(|(id, meta, value)| JournalOp::SetMetadata {
+                    file: *id,
+                    meta: (*meta).into(),
+                    value: value.into(),
+                })
+                .collect();
+            record_journal(catalog, label, forward, inverse);
+            true
+        }
+        Err(err) => {
+            err_out_line!("set_metadata_values failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_count_folder(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.count_folder(id) {
+        Ok(count) => {
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::FolderCounted(Count { id, count })) {
+                Err(err) => {
+                    err_out!("Failed to notify FolderCounted {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("count_folder failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Add a keyword under `parent`. Return `LibraryId` of the keyword,
+/// already existing or created.
+pub fn cmd_add_keyword(catalog: &CatalogDb, keyword: &str, parent: LibraryId) -> LibraryId {
+    match catalog.make_keyword(keyword, parent) {
+        Ok(id) => {
+            if catalog
+                .notify(LibNotification::AddedKeyword(Keyword::new(
+                    id, keyword, parent,
+                )))
+                .is_err()
+            {
+                err_out!("Failed to notify AddedKeyword");
+            }
+            id
+        }
+        Err(err) => {
+            err_out_line!("make_keyword failed: {:?}", err);
+            -1
+        }
+    }
+}
+
+pub fn cmd_set_keyword_synonyms(catalog: &CatalogDb, id: LibraryId, synonyms: &[String]) -> bool {
+    let old = catalog.get_keyword_synonyms(id);
+    match catalog.set_keyword_synonyms(id, synonyms) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Set Keyword Synonyms"),
+                vec![JournalOp::SetKeywordSynonyms {
+                    keyword: id,
+                    synonyms: synonyms.to_vec(),
+                }],
+                old.map(|synonyms| {
+                    vec![JournalOp::SetKeywordSynonyms {
+                        keyword: id,
+                        synonyms,
+                    }]
+                })
+                .unwrap_or_default(),
+            );
+            true
+        }
+        Err(err) => {
+            err_out_line!("set_keyword_synonyms failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Set whether the keyword is exported to XMP.
+pub fn cmd_set_keyword_exportable(catalog: &CatalogDb, id: LibraryId, exportable: bool) -> bool {
+    let old = catalog.get_keyword(id).map(|keyword| keyword.exportable());
+    match catalog.set_keyword_exportable(id, exportable) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Set Keyword Export"),
+                vec![JournalOp::SetKeywordExportable {
+                    keyword: id,
+                    exportable,
+                }],
+                old.map(|exportable| {
+                    vec![JournalOp::SetKeywordExportable {
+                        keyword: id,
+                        exportable,
+                    }]
+                })
+                .unwrap_or_default(),
+            );
+            true
+        }
+        Err(err) => {
+            err_out_line!("set_keyword_exportable failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Import the keywords from the file at `path`, in the Lightroom
+/// keyword text format.
+pub fn cmd_import_keywords(catalog: &CatalogDb, path: &Path) -> bool {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) => {
+            err_out!("Couldn't read keywords from {path:?}: {err}");
+            return false;
+        }
+    };
+    if !import_keywords(catalog, &text) {
+        return false;
+    }
+    // The created keywords can't be deleted.
+    record_journal(
+        catalog,
+        &i18n("Import Keywords"),
+        vec![JournalOp::ImportKeywords { keywords: text }],
+        vec![],
+    );
+    true
+}
+
+/// Import the keywords from `text`, in the Lightroom keyword text format.
+pub(crate) fn import_keywords(catalog: &CatalogDb, text: &str) -> bool {
+    let tree = keyword::parse_lightroom_keywords(text);
+    match catalog.in_transaction(|| catalog.import_keyword_tree(&tree, 0)) {
+        Ok(_) => true,
+        Err(err) => {
+            err_out_line!("import_keyword_tree failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Export all the keywords to the file at `path`, in the Lightroom
+/// keyword text format.
+pub fn cmd_export_keywords(catalog: &CatalogDb, path: &Path) -> bool {
+    match catalog.get_keyword_tree() {
+        Ok(tree) => {
+            if let Err(err) = std::fs::write(path, keyword::write_lightroom_keywords(&tree)) {
+                err_out!("Couldn't write keywords to {path:?}: {err}");
+                return false;
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("get_keyword_tree failed: {:?}", err);
+            false
+        }
+    }
 }
 
 pub fn cmd_query_keyword_content(catalog: &CatalogDb, keyword_id: LibraryId) -> bool {
@@ -550,101 +2244,469 @@ pub fn cmd_query_keyword_content(catalog: &CatalogDb, keyword_id: LibraryId) ->
             for f in fl {
                 content.push(f);
             }
-            // This time it's a fatal error since the purpose of this comand
-            // is to retrieve.
-            match catalog.notify(LibNotification::KeywordContentQueried(content)) {
-                Err(err) => {
-                    err_out!("Failed to notify KeywordContentQueried {:?}", err);
-                    false
-                }
-                Ok(_) => true,
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::KeywordContentQueried(content)) {
+                Err(err) => {
+                    err_out!("Failed to notify KeywordContentQueried {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("get_keyword_content failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_count_keyword(catalog: &CatalogDb, id: LibraryId) -> bool {
+    match catalog.count_keyword(id) {
+        Ok(count) => {
+            // This time it's a fatal error since the purpose of this comand
+            // is to retrieve.
+            match catalog.notify(LibNotification::KeywordCounted(Count { id, count })) {
+                Err(err) => {
+                    err_out!("Failed to notify KeywordCounted {:?}", err);
+                    false
+                }
+                Ok(_) => true,
+            }
+        }
+        Err(err) => {
+            err_out_line!("count_keyword failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_write_metadata(catalog: &CatalogDb, file_id: LibraryId) -> bool {
+    match catalog.write_metadata(file_id) {
+        Ok(_) => true,
+        Err(err) => {
+            err_out_line!("write_metadata failed: {:?}", err);
+            false
+        }
+    }
+}
+
+pub fn cmd_move_file_to_folder(
+    catalog: &CatalogDb,
+    file_id: LibraryId,
+    from: LibraryId,
+    to: LibraryId,
+) -> bool {
+    match catalog.move_file_to_folder(file_id, to) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Move to Folder"),
+                vec![JournalOp::MoveFile {
+                    file: file_id,
+                    from,
+                    to,
+                }],
+                vec![JournalOp::MoveFile {
+                    file: file_id,
+                    from: to,
+                    to: from,
+                }],
+            );
+            if catalog
+                .notify(LibNotification::FileMoved(FileMove {
+                    file: file_id,
+                    from,
+                    to,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FileMoved");
+            }
+            if catalog
+                .notify(LibNotification::FolderCountChanged(Count {
+                    id: from,
+                    count: -1,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FileMoved");
+            }
+            if catalog
+                .notify(LibNotification::FolderCountChanged(Count {
+                    id: to,
+                    count: 1,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FileMoved");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("move file to folder failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Move all the `files` to folder `to`, in one transaction. It is
+/// recorded as one journal entry.
+pub fn cmd_move_files_to_folder(catalog: &CatalogDb, files: &[LibraryId], to: LibraryId) -> bool {
+    let result = catalog.in_transaction(|| {
+        files
+            .iter()
+            .map(|file| {
+                let from = catalog.get_file_folder_id(*file)?;
+                catalog.move_file_to_folder(*file, to)?;
+                Ok((*file, from))
+            })
+            .collect::<crate::catalog::db::Result<Vec<_>>>()
+    });
+    match result {
+        Ok(moved) => {
+            for (file, from) in &moved {
+                if catalog
+                    .notify(LibNotification::FileMoved(FileMove {
+                        file: *file,
+                        from: *from,
+                        to,
+                    }))
+                    .is_err()
+                {
+                    err_out!("Failed to notify FileMoved");
+                }
+                if catalog
+                    .notify(LibNotification::FolderCountChanged(Count {
+                        id: *from,
+                        count: -1,
+                    }))
+                    .is_err()
+                {
+                    err_out!("Failed to notify FileMoved");
+                }
+            }
+            if catalog
+                .notify(LibNotification::FolderCountChanged(Count {
+                    id: to,
+                    count: moved.len() as i64,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FileMoved");
+            }
+            let (forward, inverse) = moved
+                .iter()
+                .map(|(file, from)| {
+                    (
+                        JournalOp::MoveFile {
+                            file: *file,
+                            from: *from,
+                            to,
+                        },
+                        JournalOp::MoveFile {
+                            file: *file,
+                            from: to,
+                            to: *from,
+                        },
+                    )
+                })
+                .unzip();
+            record_journal(catalog, &i18n("Move to Folder"), forward, inverse);
+            true
+        }
+        Err(err) => {
+            err_out_line!("move files to folder failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Notify the virtual copy `copy` was added.
+fn notify_added_virtual_copy(catalog: &CatalogDb, copy: LibraryId) {
+    match catalog.get_file(copy) {
+        Ok(file) => {
+            let folder = file.folder_id();
+            if catalog
+                .notify(LibNotification::AddedVirtualCopy(Box::new(file)))
+                .is_err()
+            {
+                err_out!("Failed to notify AddedVirtualCopy");
+            }
+            if catalog
+                .notify(LibNotification::FolderCountChanged(Count {
+                    id: folder,
+                    count: 1,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FolderCountChanged");
+            }
+        }
+        Err(err) => err_out_line!("get_file failed: {:?}", err),
+    }
+}
+
+/// Create a virtual copy of `file_id`. Return the id of the copy, or -1
+/// on error.
+pub fn cmd_create_virtual_copy(catalog: &CatalogDb, file_id: LibraryId) -> LibraryId {
+    match catalog.create_virtual_copy(file_id) {
+        Ok(copy) => {
+            record_journal(
+                catalog,
+                &i18n("Create Virtual Copy"),
+                vec![JournalOp::CreateVirtualCopy {
+                    file: file_id,
+                    copy,
+                }],
+                vec![JournalOp::DeleteVirtualCopy { copy }],
+            );
+            notify_added_virtual_copy(catalog, copy);
+            copy
+        }
+        Err(err) => {
+            err_out_line!("create_virtual_copy failed: {:?}", err);
+            -1
+        }
+    }
+}
+
+/// Restore the virtual copy `copy` of `file_id`, with the same id.
+pub(crate) fn cmd_restore_virtual_copy(
+    catalog: &CatalogDb,
+    file_id: LibraryId,
+    copy: LibraryId,
+) -> bool {
+    match catalog.create_virtual_copy_with_id(file_id, copy) {
+        Ok(copy) => {
+            notify_added_virtual_copy(catalog, copy);
+            true
+        }
+        Err(err) => {
+            err_out_line!("create_virtual_copy_with_id failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// The operations to restore the virtual copy `copy`: recreate it
+/// from its master, then set its metadata, keywords and develop
+/// settings.
+fn virtual_copy_restore_ops(
+    catalog: &CatalogDb,
+    copy: &LibFile,
+) -> crate::catalog::db::Result<Vec<JournalOp>> {
+    let mut ops = vec![JournalOp::CreateVirtualCopy {
+        file: copy.master_id(),
+        copy: copy.id(),
+    }];
+    for idx in [
+        Npi::NpXmpRatingProp,
+        Npi::NpXmpLabelProp,
+        Npi::NpNiepceFlagProp,
+        Npi::NpTiffOrientationProp,
+        Npi::NpIptcKeywordsProp,
+    ] {
+        let value = catalog.get_metadata_value(copy.id(), Np::Index(idx))?;
+        ops.push(JournalOp::SetMetadata {
+            file: copy.id(),
+            meta: Np::Index(idx).into(),
+            value: (&value).into(),
+        });
+    }
+    if let Some(settings) = catalog.get_develop_settings(copy.id())? {
+        ops.push(JournalOp::SetDevelopSettings {
+            file: copy.id(),
+            settings,
+        });
+    }
+    Ok(ops)
+}
+
+/// Delete the virtual copy `copy`. Undoing it restores the copy with
+/// its metadata.
+pub fn cmd_delete_virtual_copy(catalog: &CatalogDb, copy: LibraryId) -> bool {
+    let file = match catalog.get_file(copy) {
+        Ok(file) => file,
+        Err(err) => {
+            err_out_line!("get_file failed: {:?}", err);
+            return false;
+        }
+    };
+    let folder = file.folder_id();
+    let inverse = match virtual_copy_restore_ops(catalog, &file) {
+        Ok(inverse) => inverse,
+        Err(err) => {
+            err_out_line!("Getting the virtual copy state failed: {:?}", err);
+            return false;
+        }
+    };
+    match catalog.delete_virtual_copy(copy) {
+        Ok(_) => {
+            // The restore operations are applied in reverse order.
+            let inverse = inverse.into_iter().rev().collect();
+            record_journal(
+                catalog,
+                &i18n("Delete Virtual Copy"),
+                vec![JournalOp::DeleteVirtualCopy { copy }],
+                inverse,
+            );
+            if catalog.notify(LibNotification::FileDeleted(copy)).is_err() {
+                err_out!("Failed to notify FileDeleted");
+            }
+            if catalog
+                .notify(LibNotification::FolderCountChanged(Count {
+                    id: folder,
+                    count: -1,
+                }))
+                .is_err()
+            {
+                err_out!("Failed to notify FolderCountChanged");
             }
+            true
         }
         Err(err) => {
-            err_out_line!("get_keyword_content failed: {:?}", err);
+            err_out_line!("delete_virtual_copy failed: {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_count_keyword(catalog: &CatalogDb, id: LibraryId) -> bool {
-    match catalog.count_keyword(id) {
-        Ok(count) => {
-            // This time it's a fatal error since the purpose of this comand
-            // is to retrieve.
-            match catalog.notify(LibNotification::KeywordCounted(Count { id, count })) {
-                Err(err) => {
-                    err_out!("Failed to notify KeywordCounted {:?}", err);
-                    false
-                }
-                Ok(_) => true,
-            }
+/// Notify that the stack of `files` changed.
+fn notify_stack_changed(catalog: &CatalogDb, files: &[LibraryId]) {
+    let files = files
+        .iter()
+        .filter_map(|id| catalog.get_file(*id).ok())
+        .collect();
+    if catalog
+        .notify(LibNotification::StackChanged(files))
+        .is_err()
+    {
+        err_out!("Failed to notify StackChanged");
+    }
+}
+
+/// Stack `files`, in order. The first one is the top. Return the id of
+/// the stack, or -1 on error.
+pub fn cmd_create_stack(catalog: &CatalogDb, files: &[LibraryId]) -> LibraryId {
+    match catalog.in_transaction(|| catalog.create_stack(files)) {
+        Ok(stack) => {
+            record_journal(
+                catalog,
+                &i18n("Stack"),
+                vec![JournalOp::CreateStack {
+                    stack,
+                    files: files.to_vec(),
+                    top: files[0],
+                }],
+                vec![JournalOp::DeleteStack { stack }],
+            );
+            notify_stack_changed(catalog, files);
+            stack
         }
         Err(err) => {
-            err_out_line!("count_keyword failed: {:?}", err);
+            err_out_line!("create_stack failed: {:?}", err);
+            -1
+        }
+    }
+}
+
+/// Restore the stack `stack` with `files` and `top`.
+pub(crate) fn cmd_restore_stack(
+    catalog: &CatalogDb,
+    stack: LibraryId,
+    files: &[LibraryId],
+    top: LibraryId,
+) -> bool {
+    match catalog.in_transaction(|| {
+        catalog.create_stack_with_id(stack, files)?;
+        catalog.set_stack_top(stack, top)
+    }) {
+        Ok(_) => {
+            notify_stack_changed(catalog, files);
+            true
+        }
+        Err(err) => {
+            err_out_line!("restore stack failed: {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_write_metadata(catalog: &CatalogDb, file_id: LibraryId) -> bool {
-    match catalog.write_metadata(file_id) {
-        Ok(_) => true,
+/// Unstack the files of `stack`.
+pub fn cmd_delete_stack(catalog: &CatalogDb, stack: LibraryId) -> bool {
+    let stacked = catalog
+        .get_stack_files(stack)
+        .and_then(|files| Ok((files, catalog.get_stack_top(stack)?)));
+    let (files, top) = match stacked {
+        Ok(stacked) => stacked,
         Err(err) => {
-            err_out_line!("write_metadata failed: {:?}", err);
+            err_out_line!("get stack failed: {:?}", err);
+            return false;
+        }
+    };
+    match catalog.delete_stack(stack) {
+        Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Unstack"),
+                vec![JournalOp::DeleteStack { stack }],
+                vec![JournalOp::CreateStack {
+                    stack,
+                    files: files.clone(),
+                    top,
+                }],
+            );
+            notify_stack_changed(catalog, &files);
+            true
+        }
+        Err(err) => {
+            err_out_line!("delete_stack failed: {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_move_file_to_folder(
-    catalog: &CatalogDb,
-    file_id: LibraryId,
-    from: LibraryId,
-    to: LibraryId,
-) -> bool {
-    match catalog.move_file_to_folder(file_id, to) {
+/// Set `file` as the top of `stack`.
+pub fn cmd_set_stack_top(catalog: &CatalogDb, stack: LibraryId, file: LibraryId) -> bool {
+    let old_top = match catalog.get_stack_top(stack) {
+        Ok(top) => top,
+        Err(err) => {
+            err_out_line!("get_stack_top failed: {:?}", err);
+            return false;
+        }
+    };
+    match catalog.set_stack_top(stack, file) {
         Ok(_) => {
-            if catalog
-                .notify(LibNotification::FileMoved(FileMove {
-                    file: file_id,
-                    from,
-                    to,
-                }))
-                .is_err()
-            {
-                err_out!("Failed to notify FileMoved");
-            }
-            if catalog
-                .notify(LibNotification::FolderCountChanged(Count {
-                    id: from,
-                    count: -1,
-                }))
-                .is_err()
-            {
-                err_out!("Failed to notify FileMoved");
-            }
-            if catalog
-                .notify(LibNotification::FolderCountChanged(Count {
-                    id: to,
-                    count: 1,
-                }))
-                .is_err()
-            {
-                err_out!("Failed to notify FileMoved");
-            }
+            record_journal(
+                catalog,
+                &i18n("Set Stack Top"),
+                vec![JournalOp::SetStackTop { stack, file }],
+                vec![JournalOp::SetStackTop {
+                    stack,
+                    file: old_top,
+                }],
+            );
+            notify_stack_changed(catalog, &[old_top, file]);
             true
         }
         Err(err) => {
-            err_out_line!("move file to folder failed: {:?}", err);
+            err_out_line!("set_stack_top failed: {:?}", err);
             false
         }
     }
 }
 
-pub fn cmd_list_all_labels(catalog: &CatalogDb) -> bool {
+pub fn cmd_list_all_labels(
+    catalog: &CatalogDb,
+    callback: Option<ClientCallback<Vec<Label>>>,
+) -> bool {
     match catalog.get_all_labels() {
         Ok(l) => {
+            if let Some(callback) = callback {
+                callback(l);
+                return true;
+            }
             // XXX change this notification type
             for label in l {
                 if let Err(err) = catalog.notify(LibNotification::AddedLabel(label)) {
@@ -666,6 +2728,16 @@ pub fn cmd_list_all_labels(catalog: &CatalogDb) -> bool {
 pub fn cmd_create_label(catalog: &CatalogDb, name: &str, colour: &RgbColour) -> LibraryId {
     match catalog.add_label(name, colour) {
         Ok(id) => {
+            record_journal(
+                catalog,
+                &i18n("Create Label"),
+                vec![JournalOp::CreateLabel {
+                    label: id,
+                    name: name.to_string(),
+                    colour: colour.to_string(),
+                }],
+                vec![JournalOp::DeleteLabel { label: id }],
+            );
             let l = Label::new(id, name, colour.clone());
             if catalog.notify(LibNotification::AddedLabel(l)).is_err() {
                 err_out!("Failed to notify AddedLabel");
@@ -680,8 +2752,23 @@ pub fn cmd_create_label(catalog: &CatalogDb, name: &str, colour: &RgbColour) ->
 }
 
 pub fn cmd_delete_label(catalog: &CatalogDb, label_id: LibraryId) -> bool {
+    let old_label = catalog.get_label(label_id);
     match catalog.delete_label(label_id) {
         Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Delete Label"),
+                vec![JournalOp::DeleteLabel { label: label_id }],
+                old_label
+                    .map(|label| {
+                        vec![JournalOp::CreateLabel {
+                            label: label_id,
+                            name: label.label().to_string(),
+                            colour: label.colour().to_string(),
+                        }]
+                    })
+                    .unwrap_or_default(),
+            );
             if catalog
                 .notify(LibNotification::LabelDeleted(label_id))
                 .is_err()
@@ -703,8 +2790,27 @@ pub fn cmd_update_label(
     name: &str,
     colour: &RgbColour,
 ) -> bool {
+    let old_label = catalog.get_label(label_id);
     match catalog.update_label(label_id, name, colour) {
         Ok(_) => {
+            record_journal(
+                catalog,
+                &i18n("Change Label"),
+                vec![JournalOp::UpdateLabel {
+                    label: label_id,
+                    name: name.to_string(),
+                    colour: colour.to_string(),
+                }],
+                old_label
+                    .map(|label| {
+                        vec![JournalOp::UpdateLabel {
+                            label: label_id,
+                            name: label.label().to_string(),
+                            colour: label.colour().to_string(),
+                        }]
+                    })
+                    .unwrap_or_default(),
+            );
             let label = Label::new(label_id, name, colour.clone());
             if catalog
                 .notify(LibNotification::LabelChanged(label))
@@ -721,6 +2827,98 @@ pub fn cmd_update_label(
     }
 }
 
+/// List the journal entries, most recent first.
+pub fn cmd_list_journal(catalog: &CatalogDb) -> bool {
+    match catalog.get_journal_entries() {
+        Ok(entries) => match catalog.notify(LibNotification::JournalListed(entries)) {
+            Err(err) => {
+                err_out!("Failed to notify JournalListed {:?}", err);
+                false
+            }
+            Ok(_) => true,
+        },
+        Err(err) => {
+            err_out_line!("get_journal_entries failed: {:?}", err);
+            false
+        }
+    }
+}
+
+/// Undo the most recent journal entry by applying its inverse. Nothing
+/// is undone past an entry that can't be undone.
+pub fn cmd_undo_journal(catalog: &CatalogDb) -> bool {
+    let entry = match catalog.get_journal_undo_entry() {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return false,
+        Err(err) => {
+            err_out_line!("get_journal_undo_entry failed: {:?}", err);
+            return false;
+        }
+    };
+    if !apply_journal_ops(catalog, entry.inverse().iter().rev()) {
+        err_out!("Undoing journal entry {} failed", entry.id());
+        if let Err(err) = catalog.notify(LibNotification::UndoFailed(entry.label().to_string())) {
+            err_out!("Failed to notify UndoFailed {:?}", err);
+        }
+        return false;
+    }
+    set_journal_undone(catalog, entry.id(), true)
+}
+
+/// Redo the oldest undone journal entry.
+pub fn cmd_redo_journal(catalog: &CatalogDb) -> bool {
+    let entry = match catalog.get_journal_redo_entry() {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return false,
+        Err(err) => {
+            err_out_line!("get_journal_redo_entry failed: {:?}", err);
+            return false;
+        }
+    };
+    if !apply_journal_ops(catalog, entry.forward().iter()) {
+        err_out!("Redoing journal entry {} failed", entry.id());
+        if let Err(err) = catalog.notify(LibNotification::RedoFailed(entry.label().to_string())) {
+            err_out!("Failed to notify RedoFailed {:?}", err);
+        }
+        return false;
+    }
+    set_journal_undone(catalog, entry.id(), false)
+}
+
+/// Apply the journal `ops` in one transaction. The notifications are
+/// only sent once it is committed.
+fn apply_journal_ops<'a>(
+    catalog: &CatalogDb,
+    mut ops: impl Iterator<Item = &'a JournalOp>,
+) -> bool {
+    catalog
+        .with_deferred_notifications(|| {
+            catalog.in_transaction(|| {
+                if ops.all(|op| op.apply(catalog)) {
+                    Ok(())
+                } else {
+                    Err(LibError::InvalidResult)
+                }
+            })
+        })
+        .is_ok()
+}
+
+fn set_journal_undone(catalog: &CatalogDb, id: LibraryId, undone: bool) -> bool {
+    match catalog.set_journal_undone(id, undone) {
+        Ok(_) => {
+            if catalog.notify(LibNotification::JournalChanged).is_err() {
+                err_out!("Failed to notify JournalChanged");
+            }
+            true
+        }
+        Err(err) => {
+            err_out_line!("set_journal_undone failed: {:?}", err);
+            false
+        }
+    }
+}
+
 pub fn cmd_process_xmp_update_queue(catalog: &CatalogDb, write_xmp: bool) -> bool {
     match catalog.process_xmp_update_queue(write_xmp) {
         Ok(_) => true,
@@ -745,7 +2943,27 @@ pub fn cmd_upgrade_catalog_from(catalog: &CatalogDb, version: i32) -> bool {
 mod test {
     use crate::catalog::{db::Error, db_test};
 
-    use super::{cmd_delete_folder, import::CatalogDbImportHelper};
+    use super::{
+        cmd_add_project_item, cmd_add_to_album, cmd_add_to_project, cmd_create_album,
+        cmd_create_project, cmd_create_smart_collection, cmd_create_stack, cmd_create_virtual_copy,
+        cmd_delete_album, cmd_delete_folder, cmd_delete_project, cmd_delete_smart_collection,
+        cmd_delete_stack, cmd_delete_virtual_copy, cmd_export_keywords, cmd_find_duplicates,
+        cmd_get_files, cmd_import_files, cmd_import_keywords, cmd_move_files_to_folder,
+        cmd_redo_journal, cmd_relink_missing_files, cmd_remap_folder, cmd_set_develop_settings,
+        cmd_set_keyword_exportable, cmd_set_keyword_synonyms, cmd_set_metadata,
+        cmd_set_metadata_many, cmd_set_metadata_values, cmd_set_project_state,
+        cmd_set_properties_many, cmd_set_stack_top, cmd_synchronize_folder, cmd_undo_journal,
+        cmd_update_smart_collection, import::CatalogDbImportHelper,
+    };
+    use crate::catalog::NiepcePropertyIdx as Npi;
+    use crate::catalog::project::{ProjectItem, ProjectState};
+    use crate::catalog::props::NiepceProperties as Np;
+    use crate::catalog::smart_collection::{Comparison, SmartRule};
+    use crate::catalog::{DevelopSettings, MetadataPreset, StackingOptions};
+    use crate::importer::ImportJob;
+    use crate::library::notification::LibNotification;
+    use crate::library::synchronize::{self, SyncOptions};
+    use npc_fwk::PropertyValue;
 
     #[test]
     fn test_delete_folder() {
@@ -785,4 +3003,715 @@ mod test {
         let found3 = catalog.get_folder("Pictures/2023");
         assert!(matches!(found3, Err(Error::NotFound)));
     }
+
+    #[test]
+    fn test_journal() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file1");
+        let file2 = catalog
+            .add_file(folder.id(), "foo/file2.jpg", None)
+            .expect("Couldn't add file2");
+
+        // Nothing to undo.
+        assert!(!cmd_undo_journal(&catalog));
+
+        let rating = Np::Index(Npi::NpXmpRatingProp);
+        assert!(cmd_set_metadata(
+            &catalog,
+            file1,
+            rating,
+            &PropertyValue::Int(4)
+        ));
+        let album = cmd_create_album(&catalog, "album", -1);
+        assert!(album > 0);
+        assert!(cmd_add_to_album(&catalog, vec![file1, file2], album));
+        assert!(cmd_delete_album(&catalog, album));
+        assert_eq!(catalog.get_journal_entries().unwrap().len(), 4);
+
+        // Undo the delete: the album is back with its content.
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_album(album).unwrap().name(), "album");
+        assert_eq!(catalog.count_album(album), Ok(2));
+        // Undoing doesn't add entries.
+        assert_eq!(catalog.get_journal_entries().unwrap().len(), 4);
+
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.count_album(album), Ok(0));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(catalog.get_album(album), Err(Error::NotFound)));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(file1, rating),
+            Ok(PropertyValue::Int(0))
+        ));
+        assert!(!cmd_undo_journal(&catalog));
+
+        assert!(cmd_redo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(file1, rating),
+            Ok(PropertyValue::Int(4))
+        ));
+
+        // A new entry discards the undone ones.
+        assert!(cmd_set_metadata(
+            &catalog,
+            file2,
+            rating,
+            &PropertyValue::Int(2)
+        ));
+        assert!(!cmd_redo_journal(&catalog));
+        let entries = catalog.get_journal_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| !entry.is_undone()));
+
+        // Deleting a folder can't be undone: nothing before it can be
+        // undone either.
+        let album = cmd_create_album(&catalog, "album 2", -1);
+        assert!(album > 0);
+        let folder2 = catalog
+            .add_folder_into("empty", Some("/bar/empty".to_string()), 0)
+            .expect("Couldn't add folder");
+        assert!(cmd_delete_folder(&catalog, folder2.id(), false));
+        assert!(!cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_album(album).unwrap().name(), "album 2");
+        assert!(
+            catalog
+                .get_journal_entries()
+                .unwrap()
+                .iter()
+                .all(|entry| !entry.is_undone())
+        );
+
+        // What comes after it can.
+        assert!(cmd_set_metadata(
+            &catalog,
+            file2,
+            rating,
+            &PropertyValue::Int(5)
+        ));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(file2, rating),
+            Ok(PropertyValue::Int(2))
+        ));
+        assert!(!cmd_undo_journal(&catalog));
+    }
+
+    #[test]
+    fn test_journal_projects_and_collections() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+
+        let project = cmd_create_project(&catalog, "Wedding");
+        assert!(project > 0);
+        assert!(cmd_add_to_project(&catalog, vec![file], project));
+        assert!(cmd_set_project_state(
+            &catalog,
+            project,
+            file,
+            ProjectState::Picked
+        ));
+        let item = ProjectItem::Folder(folder.id());
+        assert!(cmd_add_project_item(&catalog, project, &item));
+        assert!(cmd_delete_project(&catalog, project));
+        assert!(matches!(catalog.get_project(project), Err(Error::NotFound)));
+
+        // Undoing the delete restores the files, their state and the items.
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_project(project).unwrap().name(), "Wedding");
+        assert_eq!(
+            catalog.get_project_states(project),
+            Ok(vec![(file, ProjectState::Picked)])
+        );
+        assert_eq!(catalog.get_project_items(project), Ok(vec![item]));
+        assert!(cmd_redo_journal(&catalog));
+        assert!(matches!(catalog.get_project(project), Err(Error::NotFound)));
+
+        let rules = vec![SmartRule::Rating(Comparison::GreaterOrEqual, 3)];
+        let collection = cmd_create_smart_collection(&catalog, "Best", &rules, true);
+        assert!(collection > 0);
+        assert!(cmd_update_smart_collection(
+            &catalog,
+            collection,
+            "Picked",
+            &[SmartRule::Flag(1)],
+            false
+        ));
+        assert!(cmd_undo_journal(&catalog));
+        let restored = catalog.get_smart_collection(collection).unwrap();
+        assert_eq!(restored.name(), "Best");
+        assert_eq!(restored.rules(), &rules[..]);
+        assert!(restored.match_all());
+        assert!(cmd_delete_smart_collection(&catalog, collection));
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(
+            catalog.get_smart_collection(collection).unwrap().name(),
+            "Best"
+        );
+
+        let settings = DevelopSettings {
+            exposure: 1.5,
+            ..DevelopSettings::default()
+        };
+        assert!(cmd_set_develop_settings(&catalog, file, &settings));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(
+            catalog
+                .get_develop_settings(file)
+                .unwrap()
+                .unwrap()
+                .is_default()
+        );
+    }
+
+    #[test]
+    fn test_batch() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let folder2 = catalog
+            .add_folder_into("bar", Some("/bar/bar".to_string()), 0)
+            .expect("Couldn't add folder");
+        let files = (0..5)
+            .map(|n| {
+                catalog
+                    .add_file(folder.id(), &format!("foo/file{n}.jpg"), None)
+                    .expect("Couldn't add file")
+            })
+            .collect::<Vec<_>>();
+
+        let rating = Np::Index(Npi::NpXmpRatingProp);
+        assert!(cmd_set_metadata(
+            &catalog,
+            files[0],
+            rating,
+            &PropertyValue::Int(1)
+        ));
+        assert!(cmd_set_metadata_many(
+            &catalog,
+            &files,
+            rating,
+            &PropertyValue::Int(5)
+        ));
+        assert!(files.iter().all(|file| matches!(
+            catalog.get_metadata_value(*file, rating),
+            Ok(PropertyValue::Int(5))
+        )));
+        assert!(cmd_move_files_to_folder(
+            &catalog,
+            &files[1..3],
+            folder2.id()
+        ));
+        assert_eq!(catalog.count_folder(folder2.id()), Ok(2));
+        let entries = catalog.get_journal_entries().unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].label(), "Set Rating");
+
+        // One undo per batch.
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.count_folder(folder2.id()), Ok(0));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(files[0], rating),
+            Ok(PropertyValue::Int(1))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(files[4], rating),
+            Ok(PropertyValue::Int(0))
+        ));
+
+        // Several properties for several files, one entry.
+        let label = Np::Index(Npi::NpXmpLabelProp);
+        assert!(cmd_set_properties_many(
+            &catalog,
+            &files[0..2],
+            &[
+                (rating, PropertyValue::Int(5)),
+                (label, PropertyValue::Int(2))
+            ]
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(files[1], label),
+            Ok(PropertyValue::Int(2))
+        ));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(files[0], rating),
+            Ok(PropertyValue::Int(1))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(files[1], label),
+            Ok(PropertyValue::Int(0))
+        ));
+
+        // Per file values, one entry.
+        assert!(cmd_set_metadata_values(
+            &catalog,
+            rating,
+            &[
+                (files[0], PropertyValue::Int(3)),
+                (files[1], PropertyValue::Int(4))
+            ]
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(files[1], rating),
+            Ok(PropertyValue::Int(4))
+        ));
+        assert!(cmd_undo_journal(&catalog));
+        assert!(matches!(
+            catalog.get_metadata_value(files[0], rating),
+            Ok(PropertyValue::Int(1))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(files[1], rating),
+            Ok(PropertyValue::Int(0))
+        ));
+
+        // A failure rolls back the whole batch.
+        assert!(!cmd_move_files_to_folder(
+            &catalog,
+            &[files[0], 1000],
+            folder2.id()
+        ));
+        assert_eq!(catalog.count_folder(folder2.id()), Ok(0));
+        assert_eq!(catalog.get_file_folder_id(files[0]), Ok(folder.id()));
+    }
+
+    #[test]
+    fn test_import_export_keywords() {
+        let catalog = db_test::test_catalog(None);
+        let tmpdir = tempfile::tempdir().expect("Couldn't create temp dir");
+        let source = tmpdir.path().join("keywords.txt");
+        let text = "Places\n\
+                    \tEurope\n\
+                    \t\tFrance\n\
+                    \t\t\t{Frankreich}\n\
+                    [Private]\n";
+        std::fs::write(&source, text).expect("Couldn't write keywords");
+
+        assert!(cmd_import_keywords(&catalog, &source));
+        // Importing again doesn't duplicate.
+        assert!(cmd_import_keywords(&catalog, &source));
+        assert_eq!(catalog.get_all_keywords().map(|k| k.len()), Ok(4));
+        let france = catalog
+            .make_keyword_path("Places|Europe|France")
+            .expect("Couldn't find keyword");
+        assert_eq!(
+            catalog.get_keyword_synonyms(france),
+            Ok(vec!["Frankreich".to_string()])
+        );
+
+        let dest = tmpdir.path().join("export.txt");
+        assert!(cmd_export_keywords(&catalog, &dest));
+        assert_eq!(std::fs::read_to_string(&dest).ok().as_deref(), Some(text));
+
+        assert!(cmd_set_keyword_synonyms(
+            &catalog,
+            france,
+            &["Francia".to_string()]
+        ));
+        assert!(cmd_set_keyword_exportable(&catalog, france, false));
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(
+            catalog.get_keyword(france).map(|k| k.exportable()),
+            Ok(true)
+        );
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(
+            catalog.get_keyword_synonyms(france),
+            Ok(vec!["Frankreich".to_string()])
+        );
+        // The import can't be undone.
+        assert!(!cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_all_keywords().map(|k| k.len()), Ok(4));
+
+        assert!(!cmd_import_keywords(
+            &catalog,
+            &tmpdir.path().join("nothere.txt")
+        ));
+    }
+
+    #[test]
+    fn test_virtual_copy() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+
+        let copy = cmd_create_virtual_copy(&catalog, file);
+        assert!(copy > 0);
+        assert_eq!(cmd_create_virtual_copy(&catalog, 1000), -1);
+        assert_eq!(catalog.count_folder(folder.id()), Ok(2));
+
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_file(copy).map(|f| f.id()), Err(Error::NotFound));
+        assert!(cmd_redo_journal(&catalog));
+        assert_eq!(catalog.get_file(copy).map(|f| f.master_id()), Ok(file));
+
+        // Only copies can be deleted.
+        assert!(!cmd_delete_virtual_copy(&catalog, file));
+        let rating = Np::Index(Npi::NpXmpRatingProp);
+        assert!(cmd_set_metadata(
+            &catalog,
+            copy,
+            rating,
+            &PropertyValue::Int(4)
+        ));
+        let settings = DevelopSettings {
+            exposure: 1.0,
+            ..DevelopSettings::default()
+        };
+        assert!(cmd_set_develop_settings(&catalog, copy, &settings));
+        assert!(cmd_delete_virtual_copy(&catalog, copy));
+        assert_eq!(catalog.count_folder(folder.id()), Ok(1));
+
+        // Undoing the delete restores the copy and its metadata.
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_file(copy).map(|f| f.master_id()), Ok(file));
+        assert_eq!(catalog.count_folder(folder.id()), Ok(2));
+        assert!(matches!(
+            catalog.get_metadata_value(copy, rating),
+            Ok(PropertyValue::Int(4))
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(file, rating),
+            Ok(PropertyValue::Int(0))
+        ));
+        assert_eq!(catalog.get_develop_settings(copy), Ok(Some(settings)));
+        assert!(cmd_redo_journal(&catalog));
+        assert_eq!(catalog.get_file(copy).map(|f| f.id()), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn test_stack() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "foo/file1.jpg", None)
+            .expect("Couldn't add file");
+        let file2 = catalog
+            .add_file(folder.id(), "foo/file2.jpg", None)
+            .expect("Couldn't add file");
+
+        assert_eq!(cmd_create_stack(&catalog, &[file1]), -1);
+        let stack = cmd_create_stack(&catalog, &[file1, file2]);
+        assert!(stack > 0);
+        assert_eq!(catalog.get_stack_top(stack), Ok(file1));
+
+        assert!(cmd_set_stack_top(&catalog, stack, file2));
+        assert_eq!(catalog.get_stack_top(stack), Ok(file2));
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_stack_top(stack), Ok(file1));
+
+        assert!(cmd_delete_stack(&catalog, stack));
+        assert_eq!(catalog.get_stack_top(stack), Err(Error::NotFound));
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(catalog.get_stack_files(stack), Ok(vec![file1, file2]));
+        assert_eq!(catalog.get_stack_top(stack), Ok(file1));
+    }
+
+    #[test]
+    fn test_relink() {
+        let catalog = db_test::test_catalog(None);
+
+        let folder = catalog
+            .add_folder_into("foo", Some("/bar/foo".to_string()), 0)
+            .expect("Couldn't add folder");
+        let file1 = catalog
+            .add_file(folder.id(), "/bar/foo/file1.jpg", None)
+            .expect("Couldn't add file");
+        let file2 = catalog
+            .add_file(folder.id(), "/bar/foo/file2.jpg", None)
+            .expect("Couldn't add file");
+        let path = |id| catalog.get_file(id).map(|f| f.path().to_path_buf());
+
+        assert!(!cmd_remap_folder(&catalog, folder.id(), "/"));
+        assert!(cmd_remap_folder(&catalog, folder.id(), "/media/disk/foo/"));
+        assert_eq!(path(file1), Ok("/media/disk/foo/file1.jpg".into()));
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(path(file1), Ok("/bar/foo/file1.jpg".into()));
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let a = tmpdir.path().join("a");
+        let b = tmpdir.path().join("b");
+        std::fs::create_dir_all(&a).expect("Couldn't create dir");
+        std::fs::create_dir_all(&b).expect("Couldn't create dir");
+        std::fs::write(a.join("file1.jpg"), "jpeg").expect("Couldn't write file");
+        // file2.jpg is found twice.
+        std::fs::write(a.join("file2.jpg"), "jpeg").expect("Couldn't write file");
+        std::fs::write(b.join("file2.jpg"), "jpeg").expect("Couldn't write file");
+
+        assert!(cmd_relink_missing_files(&catalog, tmpdir.path()));
+        assert_eq!(path(file1), Ok(a.join("file1.jpg")));
+        let (sender, receiver) = std::sync::mpsc::channel();
+        assert!(cmd_get_files(
+            &catalog,
+            &[file2, 1000, file1],
+            Box::new(move |files| sender.send(files).unwrap())
+        ));
+        let files = receiver.recv().expect("No files");
+        assert_eq!(
+            files.iter().map(|f| f.id()).collect::<Vec<_>>(),
+            vec![file2, file1]
+        );
+        assert_eq!(path(file2), Ok("/bar/foo/file2.jpg".into()));
+
+        assert!(cmd_undo_journal(&catalog));
+        assert_eq!(path(file1), Ok("/bar/foo/file1.jpg".into()));
+        assert!(cmd_redo_journal(&catalog));
+        assert_eq!(path(file1), Ok(a.join("file1.jpg")));
+    }
+
+    #[test]
+    fn test_synchronize_folder() {
+        let catalog = db_test::test_catalog(None);
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let folder = catalog
+            .add_folder_into("sync", Some(dir.to_string_lossy().to_string()), 0)
+            .expect("Couldn't add folder");
+        std::fs::write(dir.join("file1.jpg"), "jpeg").expect("Couldn't write file");
+        let file1 = catalog
+            .add_file(folder.id(), dir.join("file1.jpg"), None)
+            .expect("Couldn't add file");
+        let file2 = catalog
+            .add_file(folder.id(), dir.join("file2.jpg"), None)
+            .expect("Couldn't add file");
+        // A new file.
+        std::fs::write(dir.join("file3.jpg"), "jpeg").expect("Couldn't write file");
+        // file1.jpg sidecar edited by another tool.
+        let sidecar = dir.join("file1.xmp");
+        std::fs::write(
+            &sidecar,
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+<rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+<rdf:Description rdf:about="" xmlns:xmp="http://ns.adobe.com/xap/1.0/" xmp:Rating="3"/>
+</rdf:RDF>
+</x:xmpmeta>"#,
+        )
+        .expect("Couldn't write sidecar");
+        std::fs::File::options()
+            .write(true)
+            .open(&sidecar)
+            .and_then(|f| {
+                f.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(3600))
+            })
+            .expect("Couldn't set the sidecar time");
+
+        let report =
+            synchronize::diff_folder(&catalog, folder.id(), false).expect("diff_folder failed");
+        assert!(!report.applied);
+        assert_eq!(report.added.len(), 1);
+        assert_eq!(report.added[0].main(), dir.join("file3.jpg"));
+        assert_eq!(report.missing, vec![file2]);
+        assert_eq!(report.xmp_changed, vec![(file1, sidecar.clone())]);
+
+        let rating = |id| {
+            catalog
+                .get_file(id)
+                .map(|f| f.property(Np::Index(Npi::NpXmpRatingProp)))
+        };
+        // A dry run changes nothing.
+        let options = SyncOptions {
+            dry_run: true,
+            remove_missing: true,
+        };
+        assert!(cmd_synchronize_folder(
+            &catalog,
+            folder.id(),
+            false,
+            &options
+        ));
+        assert!(catalog.get_file(file2).is_ok());
+        assert_ne!(rating(file1), Ok(3));
+
+        let options = SyncOptions {
+            dry_run: false,
+            remove_missing: true,
+        };
+        assert!(cmd_synchronize_folder(
+            &catalog,
+            folder.id(),
+            false,
+            &options
+        ));
+        assert_eq!(catalog.get_file(file2).err(), Some(Error::NotFound));
+        assert_eq!(rating(file1), Ok(3));
+        assert_eq!(
+            catalog.get_xmp_sidecar(file1).map(|s| s.0),
+            Ok(Some(sidecar))
+        );
+
+        let report =
+            synchronize::diff_folder(&catalog, folder.id(), false).expect("diff_folder failed");
+        assert!(report.is_empty());
+
+        // The drive is unmounted: the files aren't removed.
+        let moved = tmpdir.path().with_extension("unmounted");
+        std::fs::rename(dir, &moved).expect("Couldn't rename dir");
+        assert_eq!(
+            synchronize::diff_folder(&catalog, folder.id(), false).err(),
+            Some(Error::MissingFolder)
+        );
+        assert!(!cmd_synchronize_folder(
+            &catalog,
+            folder.id(),
+            false,
+            &options
+        ));
+        assert!(catalog.get_file(file1).is_ok());
+        std::fs::rename(&moved, dir).expect("Couldn't rename dir");
+    }
+
+    #[test]
+    fn test_import_metadata_preset() {
+        let catalog = db_test::test_catalog(None);
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let file = dir.join("file1.jpg");
+        std::fs::write(&file, "jpeg").expect("Couldn't write file");
+
+        let mut preset = MetadataPreset::new(0, "Job", vec![]);
+        preset.set_property(
+            Np::Index(Npi::NpDcRightsProp),
+            PropertyValue::String("© 2025 Me".into()),
+        );
+        preset.set_property(
+            Np::Index(Npi::NpIptcKeywordsProp),
+            PropertyValue::StringArray(vec!["Places|Ottawa".into(), "Work".into()]),
+        );
+        assert!(cmd_import_files(
+            &catalog,
+            dir,
+            &[file],
+            &StackingOptions::default(),
+            Some(&preset),
+            None
+        ));
+
+        let files = catalog.get_main_fs_files().expect("Couldn't get files");
+        assert_eq!(files.len(), 1);
+        let id = files[0].0;
+        assert!(matches!(
+            catalog.get_metadata_value(id, Np::Index(Npi::NpDcRightsProp)),
+            Ok(PropertyValue::String(rights)) if rights == "© 2025 Me"
+        ));
+        assert!(matches!(
+            catalog.get_metadata_value(id, Np::Index(Npi::NpIptcKeywordsProp)),
+            Ok(PropertyValue::StringArray(keywords)) if keywords == ["Places|Ottawa", "Work"]
+        ));
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let catalog = db_test::test_catalog(None);
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        let imported = dir.join("imported");
+        let card = dir.join("card");
+        std::fs::create_dir_all(&imported).expect("Couldn't create dir");
+        std::fs::create_dir_all(&card).expect("Couldn't create dir");
+        std::fs::write(imported.join("DSC_0001.jpg"), "jpeg").expect("Couldn't write file");
+        assert!(cmd_import_files(
+            &catalog,
+            &imported,
+            &[imported.join("DSC_0001.jpg")],
+            &StackingOptions::default(),
+            None,
+            None
+        ));
+
+        // The same card inserted again, and a new file.
+        std::fs::write(card.join("DSC_0001.jpg"), "jpeg").expect("Couldn't write file");
+        std::fs::write(card.join("DSC_0002.jpg"), "jpeg2").expect("Couldn't write file");
+        let duplicates = std::sync::Arc::new(std::sync::Mutex::new(vec![]));
+        let result = duplicates.clone();
+        assert!(cmd_find_duplicates(
+            &catalog,
+            &[card.join("DSC_0001.jpg"), card.join("DSC_0002.jpg")],
+            Box::new(move |files| *result.lock().unwrap() = files),
+        ));
+        assert_eq!(*duplicates.lock().unwrap(), vec![card.join("DSC_0001.jpg")]);
+    }
+
+    #[test]
+    fn test_import_job() {
+        let catalog = db_test::test_catalog(None);
+
+        let tmpdir = tempfile::tempdir().expect("Couldn't create tmpdir");
+        let dir = tmpdir.path();
+        std::fs::write(dir.join("DSC_0001.jpg"), "jpeg1").expect("Couldn't write file");
+        std::fs::write(dir.join("DSC_0002.jpg"), "jpeg2").expect("Couldn't write file");
+        let files = [dir.join("DSC_0001.jpg"), dir.join("DSC_0002.jpg")];
+
+        // Cancelled: nothing is imported.
+        let (sender, receiver) = async_channel::unbounded();
+        let job = ImportJob::new(sender.clone(), || true);
+        assert!(cmd_import_files(
+            &catalog,
+            dir,
+            &files,
+            &StackingOptions::default(),
+            None,
+            Some(&job)
+        ));
+        assert_eq!(catalog.get_main_fs_files().map(|f| f.len()).ok(), Some(0));
+        let mut summary = None;
+        while let Ok(notification) = receiver.try_recv() {
+            if let LibNotification::ImportFinished(finished) = notification {
+                summary = Some(finished);
+            }
+        }
+        let summary = summary.expect("No summary");
+        assert!(summary.cancelled);
+        assert!(summary.imported.is_empty());
+
+        let job = ImportJob::new(sender, || false);
+        assert!(cmd_import_files(
+            &catalog,
+            dir,
+            &files,
+            &StackingOptions::default(),
+            None,
+            Some(&job)
+        ));
+        assert_eq!(catalog.get_main_fs_files().map(|f| f.len()).ok(), Some(2));
+        let progress = job.progress();
+        assert_eq!(progress.bundles_total, 2);
+        assert_eq!(progress.bundles_added, 2);
+        let mut summary = None;
+        while let Ok(notification) = receiver.try_recv() {
+            if let LibNotification::ImportFinished(finished) = notification {
+                summary = Some(finished);
+            }
+        }
+        let summary = summary.expect("No summary");
+        assert!(!summary.cancelled);
+        assert_eq!(summary.imported.len(), 2);
+        assert!(summary.failed.is_empty());
+    }
 }
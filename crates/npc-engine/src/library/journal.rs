@@ -0,0 +1,560 @@
+/*
+ * niepce - npc-engine/library/journal.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The journal records the mutating commands with their inverse, so
+//! that they can be undone and redone, even after a restart.
+
+use serde::{Deserialize, Serialize};
+
+use super::commands;
+use super::notification::LibNotification;
+use super::synchronize::SyncOptions;
+use crate::NiepcePropertyBag;
+use crate::catalog::project::{ProjectItem, ProjectState};
+use crate::catalog::props::NiepceProperties as Np;
+use crate::catalog::{
+    Album, CatalogDb, DevelopSettings, FromDb, Label, LibraryId, Project, SmartCollection,
+};
+use npc_fwk::base::RgbColour;
+use npc_fwk::{PropertyValue, err_out};
+
+/// A `PropertyValue` that can be stored in the journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JournalValue {
+    Empty,
+    Int(i32),
+    String(String),
+    StringArray(Vec<String>),
+    /// Date, RFC 3339.
+    Date(String),
+}
+
+impl From<&PropertyValue> for JournalValue {
+    fn from(v: &PropertyValue) -> JournalValue {
+        match v {
+            PropertyValue::Empty => JournalValue::Empty,
+            PropertyValue::Int(i) => JournalValue::Int(*i),
+            PropertyValue::String(s) => JournalValue::String(s.clone()),
+            PropertyValue::StringArray(sa) => JournalValue::StringArray(sa.clone()),
+            PropertyValue::Date(d) => JournalValue::Date(d.to_rfc3339()),
+        }
+    }
+}
+
+impl From<&JournalValue> for PropertyValue {
+    fn from(v: &JournalValue) -> PropertyValue {
+        match v {
+            JournalValue::Empty => PropertyValue::Empty,
+            JournalValue::Int(i) => PropertyValue::Int(*i),
+            JournalValue::String(s) => PropertyValue::String(s.clone()),
+            JournalValue::StringArray(sa) => PropertyValue::StringArray(sa.clone()),
+            JournalValue::Date(d) => chrono::DateTime::parse_from_rfc3339(d)
+                .map(PropertyValue::Date)
+                .unwrap_or_else(|_| PropertyValue::String(d.clone())),
+        }
+    }
+}
+
+/// An operation recorded in the journal.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JournalOp {
+    /// Set the metadata `meta` (`NiepceProperties` as u32) for `file`.
+    SetMetadata {
+        file: LibraryId,
+        meta: u32,
+        value: JournalValue,
+    },
+    MoveFile {
+        file: LibraryId,
+        from: LibraryId,
+        to: LibraryId,
+    },
+    /// Create the album, with this `id`, and add `images`.
+    CreateAlbum {
+        album: LibraryId,
+        name: String,
+        parent: LibraryId,
+        images: Vec<LibraryId>,
+    },
+    DeleteAlbum {
+        album: LibraryId,
+    },
+    RenameAlbum {
+        album: LibraryId,
+        name: String,
+    },
+    AddToAlbum {
+        images: Vec<LibraryId>,
+        album: LibraryId,
+    },
+    RemoveFromAlbum {
+        images: Vec<LibraryId>,
+        album: LibraryId,
+    },
+    AddToProject {
+        images: Vec<LibraryId>,
+        project: LibraryId,
+    },
+    RemoveFromProject {
+        images: Vec<LibraryId>,
+        project: LibraryId,
+    },
+    SetProjectState {
+        project: LibraryId,
+        file: LibraryId,
+        state: i32,
+    },
+    /// Create the project, with this `id`.
+    CreateProject {
+        project: LibraryId,
+        name: String,
+    },
+    DeleteProject {
+        project: LibraryId,
+    },
+    RenameProject {
+        project: LibraryId,
+        name: String,
+    },
+    AddProjectItem {
+        project: LibraryId,
+        item: ProjectItem,
+    },
+    RemoveProjectItem {
+        project: LibraryId,
+        item: ProjectItem,
+    },
+    /// Create the smart collection, with this `id`. The `rules` are
+    /// one per line.
+    CreateSmartCollection {
+        collection: LibraryId,
+        name: String,
+        rules: String,
+        match_all: bool,
+    },
+    UpdateSmartCollection {
+        collection: LibraryId,
+        name: String,
+        rules: String,
+        match_all: bool,
+    },
+    DeleteSmartCollection {
+        collection: LibraryId,
+    },
+    SetDevelopSettings {
+        file: LibraryId,
+        settings: DevelopSettings,
+    },
+    /// Set the properties (`NiepceProperties` as u32) of `file`.
+    SetImageProperties {
+        file: LibraryId,
+        props: Vec<(u32, JournalValue)>,
+    },
+    SetKeywordSynonyms {
+        keyword: LibraryId,
+        synonyms: Vec<String>,
+    },
+    SetKeywordExportable {
+        keyword: LibraryId,
+        exportable: bool,
+    },
+    /// Create the label, with this `id`.
+    CreateLabel {
+        label: LibraryId,
+        name: String,
+        colour: String,
+    },
+    UpdateLabel {
+        label: LibraryId,
+        name: String,
+        colour: String,
+    },
+    DeleteLabel {
+        label: LibraryId,
+    },
+    /// Create the virtual copy of `file`, with the id `copy`.
+    CreateVirtualCopy {
+        file: LibraryId,
+        copy: LibraryId,
+    },
+    DeleteVirtualCopy {
+        copy: LibraryId,
+    },
+    /// Create the stack, with this `id`, with `files` in order.
+    CreateStack {
+        stack: LibraryId,
+        files: Vec<LibraryId>,
+        top: LibraryId,
+    },
+    DeleteStack {
+        stack: LibraryId,
+    },
+    SetStackTop {
+        stack: LibraryId,
+        file: LibraryId,
+    },
+    /// Remap the folder, and its subfolders, to `path`.
+    RemapFolder {
+        folder: LibraryId,
+        path: String,
+    },
+    /// Set the `paths` of the fsfiles, as `(fsfile, path)`.
+    SetFsFilePaths {
+        paths: Vec<(LibraryId, String)>,
+    },
+    /// Delete a folder and its content. This has no inverse.
+    DeleteFolder {
+        folder: LibraryId,
+        recursive: bool,
+    },
+    /// Import the `keywords`, in the Lightroom keyword text format.
+    /// This has no inverse.
+    ImportKeywords {
+        keywords: String,
+    },
+    /// Synchronize the folder with the storage. This has no inverse.
+    SynchronizeFolder {
+        folder: LibraryId,
+        recursive: bool,
+        remove_missing: bool,
+    },
+}
+
+impl JournalOp {
+    /// Apply the operation to the catalog. This doesn't record it in
+    /// the journal.
+    pub(crate) fn apply(&self, catalog: &CatalogDb) -> bool {
+        catalog.without_journal(|| match self {
+            Self::SetMetadata { file, meta, value } => {
+                commands::cmd_set_metadata(catalog, *file, Np::from(*meta), &value.into())
+            }
+            Self::MoveFile { file, from, to } => {
+                commands::cmd_move_file_to_folder(catalog, *file, *from, *to)
+            }
+            Self::CreateAlbum {
+                album,
+                name,
+                parent,
+                images,
+            } => {
+                if let Err(err) = catalog.add_album_with_id(*album, name, *parent) {
+                    err_out!("Failed to restore album {album}: {err:?}");
+                    return false;
+                }
+                if catalog
+                    .notify(LibNotification::AddedAlbum(Album::new(
+                        *album, name, *parent,
+                    )))
+                    .is_err()
+                {
+                    err_out!("Failed to notify AddedAlbum");
+                }
+                (images.is_empty() || commands::cmd_add_to_album(catalog, images.clone(), *album))
+                    && commands::cmd_count_album(catalog, *album)
+            }
+            Self::DeleteAlbum { album } => commands::cmd_delete_album(catalog, *album),
+            Self::RenameAlbum { album, name } => commands::cmd_rename_album(catalog, *album, name),
+            Self::AddToAlbum { images, album } => {
+                commands::cmd_add_to_album(catalog, images.clone(), *album)
+                    && commands::cmd_count_album(catalog, *album)
+            }
+            Self::RemoveFromAlbum { images, album } => {
+                commands::cmd_remove_from_album(catalog, images.clone(), *album)
+                    && commands::cmd_count_album(catalog, *album)
+            }
+            Self::AddToProject { images, project } => {
+                commands::cmd_add_to_project(catalog, images.clone(), *project)
+                    && commands::cmd_count_project(catalog, *project)
+            }
+            Self::RemoveFromProject { images, project } => {
+                commands::cmd_remove_from_project(catalog, images.clone(), *project)
+                    && commands::cmd_count_project(catalog, *project)
+            }
+            Self::SetProjectState {
+                project,
+                file,
+                state,
+            } => commands::cmd_set_project_state(
+                catalog,
+                *project,
+                *file,
+                ProjectState::from(*state),
+            ),
+            Self::CreateProject { project, name } => {
+                if let Err(err) = catalog.add_project_with_id(*project, name) {
+                    err_out!("Failed to restore project {project}: {err:?}");
+                    return false;
+                }
+                if catalog
+                    .notify(LibNotification::AddedProject(Project::new(*project, name)))
+                    .is_err()
+                {
+                    err_out!("Failed to notify AddedProject");
+                }
+                true
+            }
+            Self::DeleteProject { project } => commands::cmd_delete_project(catalog, *project),
+            Self::RenameProject { project, name } => {
+                commands::cmd_rename_project(catalog, *project, name)
+            }
+            Self::AddProjectItem { project, item } => {
+                commands::cmd_add_project_item(catalog, *project, item)
+            }
+            Self::RemoveProjectItem { project, item } => {
+                commands::cmd_remove_project_item(catalog, *project, item)
+            }
+            Self::CreateSmartCollection {
+                collection,
+                name,
+                rules,
+                match_all,
+            } => {
+                let rules = SmartCollection::rules_from_string(rules);
+                match catalog.add_smart_collection_with_id(*collection, name, &rules, *match_all) {
+                    Ok(collection) => {
+                        if catalog
+                            .notify(LibNotification::AddedSmartCollection(collection))
+                            .is_err()
+                        {
+                            err_out!("Failed to notify AddedSmartCollection");
+                        }
+                        true
+                    }
+                    Err(err) => {
+                        err_out!("Failed to restore smart collection {collection}: {err:?}");
+                        false
+                    }
+                }
+            }
+            Self::UpdateSmartCollection {
+                collection,
+                name,
+                rules,
+                match_all,
+            } => commands::cmd_update_smart_collection(
+                catalog,
+                *collection,
+                name,
+                &SmartCollection::rules_from_string(rules),
+                *match_all,
+            ),
+            Self::DeleteSmartCollection { collection } => {
+                commands::cmd_delete_smart_collection(catalog, *collection)
+            }
+            Self::SetDevelopSettings { file, settings } => {
+                commands::cmd_set_develop_settings(catalog, *file, settings)
+            }
+            Self::SetImageProperties { file, props } => {
+                let mut bag = NiepcePropertyBag::default();
+                for (meta, value) in props {
+                    bag.set_value(Np::from(*meta), value.into());
+                }
+                commands::cmd_set_image_properties(catalog, *file, &bag)
+            }
+            Self::SetKeywordSynonyms { keyword, synonyms } => {
+                commands::cmd_set_keyword_synonyms(catalog, *keyword, synonyms)
+            }
+            Self::SetKeywordExportable {
+                keyword,
+                exportable,
+            } => commands::cmd_set_keyword_exportable(catalog, *keyword, *exportable),
+            Self::CreateLabel {
+                label,
+                name,
+                colour,
+            } => {
+                let colour = colour.parse::<RgbColour>().unwrap_or_default();
+                if let Err(err) = catalog.add_label_with_id(*label, name, &colour) {
+                    err_out!("Failed to restore label {label}: {err:?}");
+                    return false;
+                }
+                if catalog
+                    .notify(LibNotification::AddedLabel(Label::new(
+                        *label, name, colour,
+                    )))
+                    .is_err()
+                {
+                    err_out!("Failed to notify AddedLabel");
+                }
+                true
+            }
+            Self::UpdateLabel {
+                label,
+                name,
+                colour,
+            } => {
+                let colour = colour.parse::<RgbColour>().unwrap_or_default();
+                commands::cmd_update_label(catalog, *label, name, &colour)
+            }
+            Self::DeleteLabel { label } => commands::cmd_delete_label(catalog, *label),
+            Self::CreateVirtualCopy { file, copy } => {
+                commands::cmd_restore_virtual_copy(catalog, *file, *copy)
+            }
+            Self::DeleteVirtualCopy { copy } => commands::cmd_delete_virtual_copy(catalog, *copy),
+            Self::CreateStack { stack, files, top } => {
+                commands::cmd_restore_stack(catalog, *stack, files, *top)
+            }
+            Self::DeleteStack { stack } => commands::cmd_delete_stack(catalog, *stack),
+            Self::SetStackTop { stack, file } => {
+                commands::cmd_set_stack_top(catalog, *stack, *file)
+            }
+            Self::RemapFolder { folder, path } => {
+                commands::cmd_remap_folder(catalog, *folder, path)
+            }
+            Self::SetFsFilePaths { paths } => commands::cmd_set_fs_file_paths(catalog, paths),
+            Self::DeleteFolder { folder, recursive } => {
+                commands::cmd_delete_folder(catalog, *folder, *recursive)
+            }
+            Self::ImportKeywords { keywords } => commands::import_keywords(catalog, keywords),
+            Self::SynchronizeFolder {
+                folder,
+                recursive,
+                remove_missing,
+            } => commands::cmd_synchronize_folder(
+                catalog,
+                *folder,
+                *recursive,
+                &SyncOptions {
+                    dry_run: false,
+                    remove_missing: *remove_missing,
+                },
+            ),
+        })
+    }
+}
+
+/// An entry in the journal. Undoing it apply the `inverse`
+/// operations in reverse order. Redoing it apply the `forward`
+/// operations.
+#[derive(Clone, Debug)]
+pub struct JournalEntry {
+    id: LibraryId,
+    /// Time of the entry (time_t)
+    date: i64,
+    /// Label, to be displayed.
+    label: String,
+    forward: Vec<JournalOp>,
+    inverse: Vec<JournalOp>,
+    undone: bool,
+}
+
+impl JournalEntry {
+    pub fn id(&self) -> LibraryId {
+        self.id
+    }
+
+    pub fn date(&self) -> i64 {
+        self.date
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn forward(&self) -> &[JournalOp] {
+        &self.forward
+    }
+
+    pub fn inverse(&self) -> &[JournalOp] {
+        &self.inverse
+    }
+
+    /// Whether the entry can be undone.
+    pub fn is_reversible(&self) -> bool {
+        !self.inverse.is_empty()
+    }
+
+    /// Whether the entry has been undone.
+    pub fn is_undone(&self) -> bool {
+        self.undone
+    }
+
+    /// Serialize the operations for storage.
+    pub(crate) fn ops_to_string(ops: &[JournalOp]) -> String {
+        serde_json::to_string(ops).unwrap_or_else(|err| {
+            err_out!("Failed to serialize journal ops: {err}");
+            "[]".to_string()
+        })
+    }
+
+    fn ops_from_string(s: &str) -> Vec<JournalOp> {
+        serde_json::from_str(s).unwrap_or_else(|err| {
+            err_out!("Invalid journal ops '{s}': {err}");
+            vec![]
+        })
+    }
+}
+
+impl FromDb for JournalEntry {
+    fn read_db_columns() -> &'static str {
+        "id,date,label,forward,inverse,undone"
+    }
+
+    fn read_db_tables() -> &'static str {
+        "journal"
+    }
+
+    fn read_db_where_id() -> &'static str {
+        "id"
+    }
+
+    fn read_from(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        let forward: String = row.get(3)?;
+        let inverse: String = row.get(4)?;
+        Ok(JournalEntry {
+            id: row.get(0)?,
+            date: row.get(1)?,
+            label: row.get(2)?,
+            forward: JournalEntry::ops_from_string(&forward),
+            inverse: JournalEntry::ops_from_string(&inverse),
+            undone: row.get(5)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JournalEntry, JournalOp, JournalValue};
+
+    #[test]
+    fn test_ops_roundtrip() {
+        let ops = vec![
+            JournalOp::SetMetadata {
+                file: 1,
+                meta: 2,
+                value: JournalValue::StringArray(vec!["beach".into(), "sunset".into()]),
+            },
+            JournalOp::MoveFile {
+                file: 1,
+                from: 2,
+                to: 3,
+            },
+            JournalOp::CreateAlbum {
+                album: 4,
+                name: "Best \"of\"".into(),
+                parent: -1,
+                images: vec![1, 2],
+            },
+        ];
+        let s = JournalEntry::ops_to_string(&ops);
+        assert_eq!(JournalEntry::ops_from_string(&s), ops);
+
+        assert!(JournalEntry::ops_from_string("garbage").is_empty());
+    }
+}
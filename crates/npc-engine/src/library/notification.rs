@@ -17,12 +17,18 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use super::journal::JournalEntry;
 use super::queriedcontent::QueriedContent;
+use super::synchronize::SyncReport;
 use crate::catalog::libfile::FileStatus;
-use crate::catalog::{Album, Keyword, Label, LibFolder, LibMetadata, LibraryId, NiepceProperties};
+use crate::catalog::{
+    Album, DevelopSettings, Keyword, Label, LibFile, LibFolder, LibMetadata, LibraryId,
+    NiepceProperties, Project, ProjectItem, ProjectState, SmartCollection,
+};
+use crate::importer::{ImportProgress, ImportSummary};
 use npc_fwk::PropertyValue;
-use npc_fwk::toolkit::ImageBitmap;
 use npc_fwk::toolkit::thumbnail;
+use npc_fwk::toolkit::{Histogram, ImageBitmap};
 
 /// Library client channel sender, to send `LibNotification`.
 pub type LcChannel = async_channel::Sender<LibNotification>;
@@ -49,6 +55,13 @@ pub struct Count {
     pub count: i64,
 }
 
+#[derive(Clone, Debug)]
+pub struct ProjectStateChange {
+    pub project: LibraryId,
+    pub file: LibraryId,
+    pub state: ProjectState,
+}
+
 #[derive(Clone, Debug)]
 pub struct FolderReparent {
     pub id: LibraryId,
@@ -79,6 +92,8 @@ pub struct Thumbnail {
 pub struct ImageRendered {
     pub id: LibraryId,
     pub image: ImageBitmap,
+    /// The histogram of `image`, computed off the UI thread.
+    pub histogram: Option<Box<Histogram>>,
 }
 
 #[derive(Clone, Debug)]
@@ -96,8 +111,25 @@ pub enum LibNotification {
     AlbumCountChanged(Count),
     AlbumDeleted(LibraryId),
     AlbumRenamed(LibraryId, String),
+    /// A virtual copy was created.
+    AddedVirtualCopy(Box<LibFile>),
+    FileDeleted(LibraryId),
+    /// The stack of these files changed.
+    StackChanged(Vec<LibFile>),
     FileMoved(FileMove),
     FileStatusChanged(FileStatusChange),
+    /// The main file of these files is missing on storage.
+    MissingFiles(Vec<LibraryId>),
+    /// The directory of the folder is missing on the storage.
+    FolderMissing(LibraryId),
+    /// These files were relinked or their folder was remapped.
+    FilesRelocated(Vec<LibFile>),
+    /// A folder was synchronized, or would be for a dry run.
+    FolderSynchronized(Box<SyncReport>),
+    /// The progress of an import job.
+    ImportProgress(ImportProgress),
+    /// An import job finished.
+    ImportFinished(Box<ImportSummary>),
     FolderContentQueried(QueriedContent),
     FolderCounted(Count),
     FolderCountChanged(Count),
@@ -109,10 +141,36 @@ pub enum LibNotification {
     LabelChanged(Label),
     LabelDeleted(LibraryId),
     LibCreated,
+    AddedSmartCollection(SmartCollection),
+    SmartCollectionChanged(SmartCollection),
+    SmartCollectionContentQueried(QueriedContent),
+    SmartCollectionCounted(Count),
+    SmartCollectionDeleted(LibraryId),
+    SearchResultsQueried(QueriedContent),
+    AddedProject(Project),
+    AddedToProject(Vec<LibraryId>, LibraryId),
+    RemovedFromProject(Vec<LibraryId>, LibraryId),
+    ProjectContentQueried(QueriedContent),
+    ProjectCounted(Count),
+    ProjectDeleted(LibraryId),
+    ProjectRenamed(LibraryId, String),
+    ProjectItemsQueried(LibraryId, Vec<ProjectItem>),
+    ProjectStateChanged(ProjectStateChange),
+    ProjectStatesQueried(LibraryId, Vec<(LibraryId, ProjectState)>),
     DatabaseNeedUpgrade(i32),
     DatabaseReady,
+    /// An entry was added, undone or redone.
+    JournalChanged,
+    /// The journal entries, most recent first.
+    JournalListed(Vec<JournalEntry>),
+    /// Undoing the entry with the label failed.
+    UndoFailed(String),
+    /// Redoing the entry with the label failed.
+    RedoFailed(String),
     MetadataChanged(MetadataChange),
     MetadataQueried(Box<LibMetadata>),
+    /// The develop settings of the file changed.
+    DevelopSettingsChanged(LibraryId, Box<DevelopSettings>),
     XmpNeedsUpdate,
     ThumbnailLoaded(Box<Thumbnail>),
     ImageRendered(ImageRendered),
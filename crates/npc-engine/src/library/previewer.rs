@@ -23,6 +23,7 @@ use md5::Digest;
 use num_derive::{FromPrimitive, ToPrimitive};
 
 use crate::catalog;
+use crate::catalog::DevelopSettings;
 pub(crate) use cache::{Cache, DbMessage};
 use npc_fwk::base::Size;
 use npc_fwk::err_out;
@@ -35,6 +36,36 @@ trait ParamDigest {
     fn digest_update(&self, digest: &mut RenderDigest);
 }
 
+impl ParamDigest for DevelopSettings {
+    fn digest_update(&self, digest: &mut RenderDigest) {
+        digest.update(self.version.to_le_bytes());
+        for value in [
+            self.tilt,
+            self.temperature,
+            self.tint,
+            self.exposure,
+            self.recovery,
+            self.fill_light,
+            self.blacks,
+            self.brightness,
+            self.contrast,
+            self.saturation,
+            self.vibrance,
+        ] {
+            digest.update(value.to_le_bytes());
+        }
+        for (x, y) in &self.tone_curve {
+            digest.update(x.to_le_bytes());
+            digest.update(y.to_le_bytes());
+        }
+        if let Some(crop) = self.crop {
+            for value in [crop.x, crop.y, crop.w, crop.h] {
+                digest.update(value.to_le_bytes());
+            }
+        }
+    }
+}
+
 impl ParamDigest for Size {
     fn digest_update(&self, digest: &mut RenderDigest) {
         digest.update(self.w.to_le_bytes());
@@ -130,6 +161,8 @@ pub struct RenderParams {
     /// dimensions should be a square.
     pub(super) dimensions: Size,
     id: catalog::LibraryId,
+    /// The develop settings to apply.
+    develop: DevelopSettings,
 }
 
 impl RenderParams {
@@ -139,6 +172,7 @@ impl RenderParams {
             engine: RenderEngine::Thumbnailer,
             dimensions,
             id,
+            develop: DevelopSettings::default(),
         }
     }
 
@@ -151,11 +185,16 @@ impl RenderParams {
         if file.metadata.is_none() {
             err_out!("new preview, metadata is none");
         }
+        let develop = file
+            .metadata()
+            .map(|metadata| metadata.develop_settings())
+            .unwrap_or_default();
         RenderParams {
             type_: RenderType::Preview,
             engine,
             dimensions,
             id,
+            develop,
         }
     }
 
@@ -167,6 +206,14 @@ impl RenderParams {
         self.engine
     }
 
+    pub fn develop_settings(&self) -> &DevelopSettings {
+        &self.develop
+    }
+
+    pub fn set_develop_settings(&mut self, develop: DevelopSettings) {
+        self.develop = develop;
+    }
+
     pub fn key(&self) -> String {
         self.digest()
     }
@@ -177,6 +224,10 @@ impl RenderParams {
         self.engine.digest_update(&mut hasher);
         self.dimensions.digest_update(&mut hasher);
         hasher.update(self.id.to_le_bytes());
+        // Unedited images keep the key they had before develop settings.
+        if !self.develop.is_default() {
+            self.develop.digest_update(&mut hasher);
+        }
 
         let result = hasher.finalize();
         format!("{result:x}")
@@ -186,7 +237,7 @@ impl RenderParams {
 #[cfg(test)]
 mod test {
     use super::{RenderEngine, RenderParams};
-    use crate::catalog::LibFile;
+    use crate::catalog::{DevelopSettings, LibFile};
     use npc_fwk::base::Size;
 
     #[test]
@@ -204,5 +255,13 @@ mod test {
             RenderParams::new_preview(&file, RenderEngine::Rt, Size { w: 1600, h: 1200 });
 
         assert_ne!(preview1.digest(), preview2.digest());
+
+        let mut preview3 = preview1.clone();
+        assert_eq!(preview1.digest(), preview3.digest());
+        preview3.set_develop_settings(DevelopSettings {
+            exposure: 1.0,
+            ..DevelopSettings::default()
+        });
+        assert_ne!(preview1.digest(), preview3.digest());
     }
 }
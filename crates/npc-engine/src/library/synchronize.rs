@@ -0,0 +1,127 @@
+/*
+ * niepce - npc-engine/library/synchronize.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Synchronize a catalog folder with the storage, to pick up the
+//! changes made by other tools.
+
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::catalog::filebundle::FileBundle;
+use crate::catalog::{CatalogDb, LibError, LibResult, LibraryId};
+use npc_fwk::utils::FileList;
+
+/// Options for synchronizing a folder.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncOptions {
+    /// Only report what would change.
+    pub dry_run: bool,
+    /// Remove the missing files from the catalog, instead of marking
+    /// them missing.
+    pub remove_missing: bool,
+}
+
+/// The changes between a folder and the storage.
+#[derive(Clone, Debug, Default)]
+pub struct SyncReport {
+    pub folder: LibraryId,
+    /// Whether the subfolders were included.
+    pub recursive: bool,
+    /// Whether the changes were applied. `false` for a dry run.
+    pub applied: bool,
+    /// The new files.
+    pub added: Vec<FileBundle>,
+    /// The files missing on the storage.
+    pub missing: Vec<LibraryId>,
+    /// Whether the missing files were removed from the catalog.
+    pub removed: bool,
+    /// The files whose XMP sidecar was edited since, with the sidecar.
+    pub xmp_changed: Vec<(LibraryId, PathBuf)>,
+}
+
+impl SyncReport {
+    /// Whether the folder is in sync.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.missing.is_empty() && self.xmp_changed.is_empty()
+    }
+}
+
+/// The modification time of `path` (time_t), if it exists.
+pub(crate) fn modified_time(path: &Path) -> Option<i64> {
+    let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+    modified
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs() as i64)
+}
+
+/// Diff the folder `folder_id`, and its subfolders if `recursive`,
+/// with the storage. Nothing is changed. If the directory of the
+/// folder is missing, like on an unmounted drive, it is an error
+/// rather than all the files missing.
+pub(crate) fn diff_folder(
+    catalog: &CatalogDb,
+    folder_id: LibraryId,
+    recursive: bool,
+) -> LibResult<SyncReport> {
+    let folder = catalog.get_folder_by_id(folder_id)?;
+    let dir = folder.path().ok_or(LibError::InvalidArg)?;
+    if !Path::new(dir).is_dir() {
+        return Err(LibError::MissingFolder);
+    }
+
+    let known = catalog.get_fs_file_paths(dir)?;
+    let on_disk = FileList::files_from_directory(dir, FileList::file_is_media, recursive, None);
+    let added = FileBundle::filter_bundles(&on_disk)
+        .into_iter()
+        .filter(|bundle| !bundle.all_files().iter().any(|f| known.contains(f)))
+        .collect();
+
+    let files = if recursive {
+        catalog.get_folder_tree_content(folder_id)?
+    } else {
+        catalog.get_folder_content(folder_id)?
+    };
+    let mut missing = vec![];
+    let mut xmp_changed = vec![];
+    for file in files {
+        if !file.path().is_file() {
+            missing.push(file.id());
+            continue;
+        }
+        // The virtual copies have their own sidecar, never read.
+        if file.is_copy() {
+            continue;
+        }
+        let (sidecar, in_sync) = catalog.get_xmp_sidecar(file.id())?;
+        let sidecar = sidecar.unwrap_or_else(|| file.path().with_extension("xmp"));
+        if modified_time(&sidecar).is_some_and(|date| date > in_sync) {
+            xmp_changed.push((file.id(), sidecar));
+        }
+    }
+
+    Ok(SyncReport {
+        folder: folder_id,
+        recursive,
+        added,
+        missing,
+        xmp_changed,
+        ..SyncReport::default()
+    })
+}
@@ -31,8 +31,8 @@ use crate::library::notification::{FileStatusChange, LcChannel};
 use crate::library::previewer::{Cache, RenderMsg, RenderParams, RenderSender, RenderType};
 use npc_fwk::base::Size;
 use npc_fwk::toolkit;
-use npc_fwk::toolkit::ImageBitmap;
 use npc_fwk::toolkit::thumbnail::Thumbnail;
+use npc_fwk::toolkit::{Histogram, ImageBitmap};
 use npc_fwk::{dbg_out, err_out, on_err_out};
 
 /// Suffix to add to the stem catalog file name.
@@ -136,6 +136,7 @@ fn get_preview(cache: &Cache, task: &Task, sender: &LcChannel) -> Option<ImageBi
                 notification::ImageRendered {
                     id,
                     image: pix.clone(),
+                    histogram: Histogram::from_bitmap(&pix).map(Box::new),
                 },
             ))) {
                 err_out!("Sending image rendered notification failed: {}", err);
@@ -263,8 +264,13 @@ impl ThumbnailCache {
             RenderType::Preview => {
                 if let Some(pix) = get_preview(cache, task, sender) {
                     dbg_out!("Got the preview from the cache");
+                    let histogram = Histogram::from_bitmap(&pix).map(Box::new);
                     if let Err(err) = toolkit::thread_context().block_on(sender.send(
-                        ImageRendered(notification::ImageRendered { id, image: pix }),
+                        ImageRendered(notification::ImageRendered {
+                            id,
+                            image: pix,
+                            histogram,
+                        }),
                     )) {
                         err_out!("Sending image rendered notification failed: {}", err);
                     }
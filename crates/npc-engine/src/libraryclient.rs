@@ -32,11 +32,18 @@ use std::thread;
 
 use crate::NiepcePropertyBag;
 use crate::catalog::filebundle::FileBundle;
+use crate::catalog::project::{ProjectItem, ProjectState};
 use crate::catalog::props::NiepceProperties as Np;
-use crate::catalog::{CatalogDb, LibFolder, LibraryId};
+use crate::catalog::smart_collection::SmartRule;
+use crate::catalog::{
+    CatalogDb, DevelopSettings, Keyword, Label, LibFolder, LibraryId, MetadataPreset,
+    StackingOptions, WatchedFolder,
+};
+use crate::importer::ImportJob;
 use crate::library::commands;
 use crate::library::notification::LcChannel;
 use crate::library::op::Op;
+use crate::library::synchronize::SyncOptions;
 use npc_fwk::base::{PropertyValue, RgbColour};
 use npc_fwk::on_err_out;
 
@@ -52,6 +59,8 @@ pub struct LibraryClient {
     trash_id: atomic::AtomicI64,
     /// This is what will implement the interface.
     sender: LibraryClientSender,
+    /// The notification channel, for the jobs running outside.
+    notif_sender: LcChannel,
     catalog_file: PathBuf,
 }
 
@@ -72,6 +81,7 @@ impl LibraryClient {
     pub fn new(filename: PathBuf, sender: LcChannel) -> LibraryClient {
         let (task_sender, task_receiver) = mpsc::channel::<Request>();
         let catalog_file = filename.clone();
+        let notif_sender = sender.clone();
         let thread = thread::Builder::new()
             .name("library client".to_string())
             .spawn(move || {
@@ -83,6 +93,7 @@ impl LibraryClient {
         LibraryClient {
             thread,
             sender: LibraryClientSender(task_sender),
+            notif_sender,
             trash_id: atomic::AtomicI64::new(0),
             catalog_file,
         }
@@ -137,6 +148,11 @@ impl LibraryClient {
     pub fn sender(&self) -> &LibraryClientSender {
         &self.sender
     }
+
+    /// The notification channel, to create an `ImportJob`.
+    pub fn notif_sender(&self) -> &LcChannel {
+        &self.notif_sender
+    }
 }
 
 #[derive(Clone)]
@@ -166,8 +182,8 @@ impl ClientInterface for LibraryClientSender {
     }
 
     /// get all the keywords
-    fn get_all_keywords(&self) {
-        self.schedule_op(commands::cmd_list_all_keywords);
+    fn get_all_keywords(&self, callback: Option<ClientCallback<Vec<Keyword>>>) {
+        self.schedule_op(move |catalog| commands::cmd_list_all_keywords(catalog, callback));
     }
 
     fn query_keyword_content(&self, keyword_id: LibraryId) {
@@ -178,6 +194,24 @@ impl ClientInterface for LibraryClientSender {
         self.schedule_op(move |catalog| commands::cmd_count_keyword(catalog, id));
     }
 
+    fn set_keyword_synonyms(&self, id: LibraryId, synonyms: Vec<String>) {
+        self.schedule_op(move |catalog| commands::cmd_set_keyword_synonyms(catalog, id, &synonyms));
+    }
+
+    fn set_keyword_exportable(&self, id: LibraryId, exportable: bool) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_set_keyword_exportable(catalog, id, exportable)
+        });
+    }
+
+    fn import_keywords(&self, path: PathBuf) {
+        self.schedule_op(move |catalog| commands::cmd_import_keywords(catalog, &path));
+    }
+
+    fn export_keywords(&self, path: PathBuf) {
+        self.schedule_op(move |catalog| commands::cmd_export_keywords(catalog, &path));
+    }
+
     /// Get the root folder.
     fn get_root_folders(&self, callback: ClientCallback<Vec<LibFolder>>) {
         self.schedule_op(move |catalog| commands::cmd_list_root_folders(catalog, callback));
@@ -208,6 +242,26 @@ impl ClientInterface for LibraryClientSender {
         self.schedule_op(move |catalog| commands::cmd_delete_folder(catalog, id, true));
     }
 
+    fn remap_folder(&self, id: LibraryId, path: PathBuf) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_remap_folder(catalog, id, &path.to_string_lossy())
+        });
+    }
+
+    fn scan_missing_files(&self) {
+        self.schedule_op(commands::cmd_scan_missing_files);
+    }
+
+    fn relink_missing_files(&self, dir: PathBuf) {
+        self.schedule_op(move |catalog| commands::cmd_relink_missing_files(catalog, &dir));
+    }
+
+    fn synchronize_folder(&self, id: LibraryId, recursive: bool, options: SyncOptions) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_synchronize_folder(catalog, id, recursive, &options)
+        });
+    }
+
     /// get all the albums
     fn get_all_albums(&self) {
         self.schedule_op(commands::cmd_list_all_albums);
@@ -262,6 +316,113 @@ impl ClientInterface for LibraryClientSender {
         self.schedule_op(move |catalog| commands::cmd_query_album_content(catalog, album_id));
     }
 
+    fn get_all_projects(&self) {
+        self.schedule_op(commands::cmd_list_all_projects);
+    }
+
+    fn count_project(&self, project_id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_count_project(catalog, project_id));
+    }
+
+    fn create_project(&self, name: String) {
+        self.schedule_op(move |catalog| commands::cmd_create_project(catalog, &name) != -1);
+    }
+
+    fn delete_project(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_project(catalog, id));
+    }
+
+    fn rename_project(&self, project_id: LibraryId, name: String) {
+        self.schedule_op(move |catalog| commands::cmd_rename_project(catalog, project_id, &name));
+    }
+
+    fn add_to_project(&self, images: &[LibraryId], project_id: LibraryId) {
+        let images = images.to_vec();
+        self.schedule_op(move |catalog| {
+            commands::cmd_add_to_project(catalog, images, project_id)
+                && commands::cmd_count_project(catalog, project_id)
+        });
+    }
+
+    fn remove_from_project(&self, images: &[LibraryId], project_id: LibraryId) {
+        let images = images.to_vec();
+        self.schedule_op(move |catalog| {
+            commands::cmd_remove_from_project(catalog, images, project_id)
+                && commands::cmd_count_project(catalog, project_id)
+        });
+    }
+
+    fn query_project_content(&self, project_id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_query_project_content(catalog, project_id));
+    }
+
+    fn set_project_state(&self, project_id: LibraryId, id: LibraryId, state: ProjectState) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_set_project_state(catalog, project_id, id, state)
+        });
+    }
+
+    fn query_project_states(&self, project_id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_query_project_states(catalog, project_id));
+    }
+
+    fn add_project_item(&self, project_id: LibraryId, item: ProjectItem) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_add_project_item(catalog, project_id, &item)
+                && commands::cmd_query_project_items(catalog, project_id)
+        });
+    }
+
+    fn remove_project_item(&self, project_id: LibraryId, item: ProjectItem) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_remove_project_item(catalog, project_id, &item)
+                && commands::cmd_query_project_items(catalog, project_id)
+        });
+    }
+
+    fn query_project_items(&self, project_id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_query_project_items(catalog, project_id));
+    }
+
+    fn get_all_smart_collections(&self) {
+        self.schedule_op(commands::cmd_list_all_smart_collections);
+    }
+
+    fn create_smart_collection(&self, name: String, rules: Vec<SmartRule>, match_all: bool) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_create_smart_collection(catalog, &name, &rules, match_all) != -1
+        });
+    }
+
+    fn update_smart_collection(
+        &self,
+        id: LibraryId,
+        name: String,
+        rules: Vec<SmartRule>,
+        match_all: bool,
+    ) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_update_smart_collection(catalog, id, &name, &rules, match_all)
+                && commands::cmd_count_smart_collection(catalog, id)
+        });
+    }
+
+    fn delete_smart_collection(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_smart_collection(catalog, id));
+    }
+
+    fn query_smart_collection_content(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_query_smart_collection_content(catalog, id));
+    }
+
+    fn count_smart_collection(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_count_smart_collection(catalog, id));
+    }
+
+    fn search(&self, query: String) {
+        self.schedule_op(move |catalog| commands::cmd_search(catalog, &query));
+    }
+
     fn request_metadata(&self, file_id: LibraryId) {
         self.schedule_op(move |catalog| commands::cmd_request_metadata(catalog, file_id));
     }
@@ -274,6 +435,23 @@ impl ClientInterface for LibraryClientSender {
         });
     }
 
+    fn set_metadata_many(&self, ids: &[LibraryId], meta: Np, value: &PropertyValue) {
+        let ids = ids.to_vec();
+        let value = value.clone();
+        self.schedule_op(move |catalog| {
+            commands::cmd_set_metadata_many(catalog, &ids, meta, &value)
+        });
+    }
+
+    fn set_metadata_values(&self, meta: Np, values: Vec<(LibraryId, PropertyValue)>) {
+        self.schedule_op(move |catalog| commands::cmd_set_metadata_values(catalog, meta, &values));
+    }
+
+    fn set_properties_many(&self, ids: &[LibraryId], props: Vec<(Np, PropertyValue)>) {
+        let ids = ids.to_vec();
+        self.schedule_op(move |catalog| commands::cmd_set_properties_many(catalog, &ids, &props));
+    }
+
     fn set_image_properties(&self, image_id: LibraryId, props: &NiepcePropertyBag) {
         let props = props.clone();
         self.schedule_op(move |catalog| {
@@ -285,15 +463,48 @@ impl ClientInterface for LibraryClientSender {
         self.schedule_op(move |catalog| commands::cmd_write_metadata(catalog, file_id));
     }
 
+    fn set_develop_settings(&self, file_id: LibraryId, settings: &DevelopSettings) {
+        let settings = settings.clone();
+        self.schedule_op(move |catalog| {
+            commands::cmd_set_develop_settings(catalog, file_id, &settings)
+        });
+    }
+
     fn move_file_to_folder(&self, file_id: LibraryId, from: LibraryId, to: LibraryId) {
         self.schedule_op(move |catalog| {
             commands::cmd_move_file_to_folder(catalog, file_id, from, to)
         });
     }
 
+    fn move_files_to_folder(&self, files: &[LibraryId], to: LibraryId) {
+        let files = files.to_vec();
+        self.schedule_op(move |catalog| commands::cmd_move_files_to_folder(catalog, &files, to));
+    }
+
+    fn create_stack(&self, files: &[LibraryId]) {
+        let files = files.to_vec();
+        self.schedule_op(move |catalog| commands::cmd_create_stack(catalog, &files) > 0);
+    }
+
+    fn delete_stack(&self, stack: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_stack(catalog, stack));
+    }
+
+    fn set_stack_top(&self, stack: LibraryId, file: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_set_stack_top(catalog, stack, file));
+    }
+
+    fn create_virtual_copy(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_create_virtual_copy(catalog, id) > 0);
+    }
+
+    fn delete_virtual_copy(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_virtual_copy(catalog, id));
+    }
+
     /// get all the labels
-    fn get_all_labels(&self) {
-        self.schedule_op(commands::cmd_list_all_labels);
+    fn get_all_labels(&self, callback: Option<ClientCallback<Vec<Label>>>) {
+        self.schedule_op(move |catalog| commands::cmd_list_all_labels(catalog, callback));
     }
 
     fn create_label(&self, name: String, colour: RgbColour) {
@@ -311,18 +522,98 @@ impl ClientInterface for LibraryClientSender {
         });
     }
 
+    fn get_all_metadata_presets(&self, callback: ClientCallback<Vec<MetadataPreset>>) {
+        self.schedule_op(move |catalog| commands::cmd_list_all_metadata_presets(catalog, callback));
+    }
+
+    fn save_metadata_preset(&self, name: String, properties: Vec<(Np, PropertyValue)>) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_save_metadata_preset(catalog, &name, &properties) != 0
+        });
+    }
+
+    fn delete_metadata_preset(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_metadata_preset(catalog, id));
+    }
+
+    fn get_all_watched_folders(&self, callback: ClientCallback<Vec<WatchedFolder>>) {
+        self.schedule_op(move |catalog| commands::cmd_list_all_watched_folders(catalog, callback));
+    }
+
+    fn save_watched_folder(&self, path: PathBuf, dest: Option<PathBuf>, preset_id: LibraryId) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_save_watched_folder(catalog, &path, dest.as_deref(), preset_id) != 0
+        });
+    }
+
+    fn delete_watched_folder(&self, id: LibraryId) {
+        self.schedule_op(move |catalog| commands::cmd_delete_watched_folder(catalog, id));
+    }
+
     /// tell to process the Xmp update Queue
     fn process_xmp_update_queue(&self, write_xmp: bool) {
         self.schedule_op(move |catalog| commands::cmd_process_xmp_update_queue(catalog, write_xmp));
     }
 
+    fn get_journal(&self) {
+        self.schedule_op(commands::cmd_list_journal);
+    }
+
+    fn undo_journal(&self) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_undo_journal(catalog) && commands::cmd_list_journal(catalog)
+        });
+    }
+
+    fn redo_journal(&self) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_redo_journal(catalog) && commands::cmd_list_journal(catalog)
+        });
+    }
+
     /// Import files in place.
-    fn import_files(&self, base: PathBuf, files: Vec<PathBuf>) {
-        self.schedule_op(move |catalog| commands::cmd_import_files(catalog, &base, &files));
+    fn import_files(
+        &self,
+        base: PathBuf,
+        files: Vec<PathBuf>,
+        stacking: StackingOptions,
+        preset: Option<MetadataPreset>,
+        job: Option<ImportJob>,
+    ) {
+        self.schedule_op(move |catalog| {
+            commands::cmd_import_files(
+                catalog,
+                &base,
+                &files,
+                &stacking,
+                preset.as_ref(),
+                job.as_ref(),
+            )
+        });
+    }
+
+    fn find_duplicates(&self, files: Vec<PathBuf>, callback: ClientCallback<Vec<PathBuf>>) {
+        self.schedule_op(move |catalog| commands::cmd_find_duplicates(catalog, &files, callback));
     }
 }
 
 impl ClientInterfaceSync for LibraryClientSender {
+    fn get_all_labels_sync(&self) -> Vec<Label> {
+        let (tx, rx) = mpsc::sync_channel::<Vec<Label>>(1);
+
+        self.schedule_op(move |catalog| {
+            let tx = tx.clone();
+            commands::cmd_list_all_labels(
+                catalog,
+                Some(Box::new(move |labels| {
+                    on_err_out!(tx.send(labels));
+                })),
+            )
+        });
+
+        rx.recv().unwrap_or_default()
+    }
+
     fn create_label_sync(&self, name: String, colour: RgbColour) -> LibraryId {
         // can't use futures::sync::oneshot
         let (tx, rx) = mpsc::sync_channel::<LibraryId>(1);
@@ -336,12 +627,12 @@ impl ClientInterfaceSync for LibraryClientSender {
         rx.recv().unwrap()
     }
 
-    fn create_keyword_sync(&self, keyword: String) -> LibraryId {
+    fn create_keyword_sync(&self, keyword: String, parent: LibraryId) -> LibraryId {
         // can't use futures::sync::oneshot
         let (tx, rx) = mpsc::sync_channel::<LibraryId>(1);
 
         self.schedule_op(move |catalog| {
-            tx.send(commands::cmd_add_keyword(catalog, &keyword))
+            tx.send(commands::cmd_add_keyword(catalog, &keyword, parent))
                 .unwrap();
             true
         });
@@ -388,6 +679,18 @@ impl ClientInterfaceSync for LibraryClientSender {
         rx.recv().unwrap()
     }
 
+    fn create_virtual_copy_sync(&self, id: LibraryId) -> LibraryId {
+        let (tx, rx) = mpsc::sync_channel::<LibraryId>(1);
+
+        self.schedule_op(move |catalog| {
+            tx.send(commands::cmd_create_virtual_copy(catalog, id))
+                .unwrap();
+            true
+        });
+
+        rx.recv().unwrap()
+    }
+
     fn upgrade_catalog_from_sync(&self, version: i32) -> bool {
         let (tx, rx) = mpsc::sync_channel::<bool>(1);
 
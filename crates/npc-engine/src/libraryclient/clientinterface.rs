@@ -21,8 +21,15 @@ use std::path::PathBuf;
 
 use crate::NiepcePropertyBag;
 use crate::catalog::filebundle::FileBundle;
+use crate::catalog::project::{ProjectItem, ProjectState};
 use crate::catalog::props::NiepceProperties as Np;
-use crate::catalog::{LibFolder, LibraryId};
+use crate::catalog::smart_collection::SmartRule;
+use crate::catalog::{
+    DevelopSettings, Keyword, Label, LibFolder, LibraryId, MetadataPreset, StackingOptions,
+    WatchedFolder,
+};
+use crate::importer::ImportJob;
+use crate::library::synchronize::SyncOptions;
 use npc_fwk::base::{PropertyValue, RgbColour};
 
 /// Callback for a local library request.
@@ -34,10 +41,18 @@ pub trait ClientInterface {
     fn get_all_preferences(&self);
     fn set_preference(&self, key: String, value: String);
 
-    /// get all the keywords
-    fn get_all_keywords(&self);
+    /// get all the keywords. If `callback` is `None`, notify them.
+    fn get_all_keywords(&self, callback: Option<ClientCallback<Vec<Keyword>>>);
     fn query_keyword_content(&self, id: LibraryId);
     fn count_keyword(&self, id: LibraryId);
+    /// Set the synonyms of the keyword.
+    fn set_keyword_synonyms(&self, id: LibraryId, synonyms: Vec<String>);
+    /// Set whether the keyword is exported to XMP.
+    fn set_keyword_exportable(&self, id: LibraryId, exportable: bool);
+    /// Import keywords from a file in the Lightroom keyword text format.
+    fn import_keywords(&self, path: PathBuf);
+    /// Export the keywords to a file in the Lightroom keyword text format.
+    fn export_keywords(&self, path: PathBuf);
 
     /// Get the root folders.
     fn get_root_folders(&self, callback: ClientCallback<Vec<LibFolder>>);
@@ -47,6 +62,14 @@ pub trait ClientInterface {
     fn count_folder(&self, id: LibraryId);
     fn create_folder(&self, name: String, path: Option<String>);
     fn delete_folder(&self, id: LibraryId);
+    /// Remap the folder `id` and its subfolders to `path`.
+    fn remap_folder(&self, id: LibraryId, path: PathBuf);
+    /// Check the storage for missing files.
+    fn scan_missing_files(&self);
+    /// Relink the missing files found under `dir`.
+    fn relink_missing_files(&self, dir: PathBuf);
+    /// Synchronize the folder `id` with the storage.
+    fn synchronize_folder(&self, id: LibraryId, recursive: bool, options: SyncOptions);
 
     /// get all the albums
     fn get_all_albums(&self);
@@ -64,34 +87,134 @@ pub trait ClientInterface {
     /// Query content for album.
     fn query_album_content(&self, album_id: LibraryId);
 
+    /// Get all the projects.
+    fn get_all_projects(&self);
+    /// Count the files in a project.
+    fn count_project(&self, project_id: LibraryId);
+    fn create_project(&self, name: String);
+    fn delete_project(&self, id: LibraryId);
+    /// Rename project `project_id` to `name`.
+    fn rename_project(&self, project_id: LibraryId, name: String);
+    /// Attach images to a project.
+    fn add_to_project(&self, images: &[LibraryId], project_id: LibraryId);
+    /// Detach images from a project.
+    fn remove_from_project(&self, images: &[LibraryId], project_id: LibraryId);
+    /// Query the files in a project.
+    fn query_project_content(&self, project_id: LibraryId);
+    /// Set the state of image `id` in the project.
+    fn set_project_state(&self, project_id: LibraryId, id: LibraryId, state: ProjectState);
+    /// Query the state of each image in the project.
+    fn query_project_states(&self, project_id: LibraryId);
+    /// Attach an item (album, folder, output) to the project.
+    fn add_project_item(&self, project_id: LibraryId, item: ProjectItem);
+    /// Detach an item from the project.
+    fn remove_project_item(&self, project_id: LibraryId, item: ProjectItem);
+    /// Query the items attached to the project.
+    fn query_project_items(&self, project_id: LibraryId);
+
+    /// Get all the smart collections.
+    fn get_all_smart_collections(&self);
+    /// Create a smart collection.
+    fn create_smart_collection(&self, name: String, rules: Vec<SmartRule>, match_all: bool);
+    /// Update the smart collection `id` name and rules.
+    fn update_smart_collection(
+        &self,
+        id: LibraryId,
+        name: String,
+        rules: Vec<SmartRule>,
+        match_all: bool,
+    );
+    fn delete_smart_collection(&self, id: LibraryId);
+    /// Query the content for the smart collection by evaluating its rules.
+    fn query_smart_collection_content(&self, id: LibraryId);
+    fn count_smart_collection(&self, id: LibraryId);
+    /// Search the catalog. See `SearchQuery` for the syntax.
+    fn search(&self, query: String);
+
     fn request_metadata(&self, id: LibraryId);
     /// set the metadata
     fn set_metadata(&self, id: LibraryId, meta: Np, value: &PropertyValue);
+    /// set the metadata for several files at once.
+    fn set_metadata_many(&self, ids: &[LibraryId], meta: Np, value: &PropertyValue);
+    /// set the metadata to a value per file, at once.
+    fn set_metadata_values(&self, meta: Np, values: Vec<(LibraryId, PropertyValue)>);
+    /// set the properties for several files at once.
+    fn set_properties_many(&self, ids: &[LibraryId], props: Vec<(Np, PropertyValue)>);
     /// set some properties for an image.
     fn set_image_properties(&self, id: LibraryId, props: &NiepcePropertyBag);
     fn write_metadata(&self, id: LibraryId);
+    /// Set the develop settings for an image.
+    fn set_develop_settings(&self, id: LibraryId, settings: &DevelopSettings);
 
     fn move_file_to_folder(&self, file_id: LibraryId, from: LibraryId, to: LibraryId);
+    /// Move several files to the folder `to`.
+    fn move_files_to_folder(&self, files: &[LibraryId], to: LibraryId);
+    /// Create a virtual copy of the file `id`.
+    fn create_virtual_copy(&self, id: LibraryId);
+    /// Delete the virtual copy `id`.
+    fn delete_virtual_copy(&self, id: LibraryId);
+    /// Stack `files`, in order. The first is the top.
+    fn create_stack(&self, files: &[LibraryId]);
+    /// Unstack the files of `stack`.
+    fn delete_stack(&self, stack: LibraryId);
+    /// Set `file` as the top of `stack`.
+    fn set_stack_top(&self, stack: LibraryId, file: LibraryId);
     /// get all the labels
-    fn get_all_labels(&self);
+    fn get_all_labels(&self, callback: Option<ClientCallback<Vec<Label>>>);
     fn create_label(&self, label: String, colour: RgbColour);
     fn delete_label(&self, id: LibraryId);
     /// update a label
     fn update_label(&self, id: LibraryId, new_name: String, new_colour: RgbColour);
 
+    /// Get all the metadata presets.
+    fn get_all_metadata_presets(&self, callback: ClientCallback<Vec<MetadataPreset>>);
+    /// Save the metadata preset `name`, replacing the one with the same name.
+    fn save_metadata_preset(&self, name: String, properties: Vec<(Np, PropertyValue)>);
+    fn delete_metadata_preset(&self, id: LibraryId);
+
+    /// Get all the watched folders.
+    fn get_all_watched_folders(&self, callback: ClientCallback<Vec<WatchedFolder>>);
+    /// Save the watched folder for `path`, replacing the one with the
+    /// same path. `dest` is where to copy the files, `None` to import
+    /// in place.
+    fn save_watched_folder(&self, path: PathBuf, dest: Option<PathBuf>, preset_id: LibraryId);
+    fn delete_watched_folder(&self, id: LibraryId);
+
     /// tell to process the Xmp update Queue
     fn process_xmp_update_queue(&self, write_xmp: bool);
 
+    /// List the journal entries.
+    fn get_journal(&self);
+    /// Undo the most recent journal entry.
+    fn undo_journal(&self);
+    /// Redo the last undone journal entry.
+    fn redo_journal(&self);
+
     /// Import files in place.
     /// @param files the files to import
-    fn import_files(&self, base: PathBuf, files: Vec<PathBuf>);
+    /// @param stacking how to stack the imported files
+    /// @param preset the metadata preset to apply to the imported files
+    /// @param job the import job to report to
+    fn import_files(
+        &self,
+        base: PathBuf,
+        files: Vec<PathBuf>,
+        stacking: StackingOptions,
+        preset: Option<MetadataPreset>,
+        job: Option<ImportJob>,
+    );
+    /// Find the `files` to import that are already in the catalog.
+    fn find_duplicates(&self, files: Vec<PathBuf>, callback: ClientCallback<Vec<PathBuf>>);
 }
 
 /// Sync client interface
 pub trait ClientInterfaceSync {
-    /// Create a keyword. Return the id for the keyword.
-    /// If the keyword already exists, return its `LibraryId`.
-    fn create_keyword_sync(&self, keyword: String) -> LibraryId;
+    /// Create a keyword under `parent` (0 for top level). Return the id
+    /// for the keyword. If the keyword already exists, return its `LibraryId`.
+    fn create_keyword_sync(&self, keyword: String, parent: LibraryId) -> LibraryId;
+
+    /// Get all the labels.
+    fn get_all_labels_sync(&self) -> Vec<Label>;
 
     /// Create a label. Return the id of the newly created label.
     fn create_label_sync(&self, name: String, colour: RgbColour) -> LibraryId;
@@ -105,6 +228,10 @@ pub trait ClientInterfaceSync {
     /// Add a bundle.
     fn add_bundle_sync(&self, bundle: &FileBundle, folder: LibraryId) -> LibraryId;
 
+    /// Create a virtual copy of the file `id`. Return the id of the
+    /// copy, or -1 on error.
+    fn create_virtual_copy_sync(&self, id: LibraryId) -> LibraryId;
+
     /// Upgrade the library from `version`. Note that the version is just a suggestion.
     /// Return true if successful.
     fn upgrade_catalog_from_sync(&self, version: i32) -> bool;
@@ -83,9 +83,15 @@ impl Executor {
         }
     }
 
-    /// Cancel the executor.
-    pub fn cancel(&self) {
+    /// Request the executor to stop, without waiting for the task to
+    /// finish. The task should check the `terminator()`.
+    pub fn request_cancel(&self) {
         self.terminate.store(true, Ordering::Release);
+    }
+
+    /// Cancel the executor, and wait for the task to finish.
+    pub fn cancel(&self) {
+        self.request_cancel();
         if let Some(thread) = self.thread.take() {
             on_err_out!(thread.join());
         }
@@ -1,7 +1,7 @@
 /*
  * niepce - fwk/base/rgbcolour.rs
  *
- * Copyright (C) 2017-2024 Hubert Figuière
+ * Copyright (C) 2017-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -98,3 +98,47 @@ impl From<RgbColour> for gdk4::RGBA {
         )
     }
 }
+
+/// Convert an 8 bits sRGB value to CIE L*a*b* (D65).
+pub fn srgb_to_lab(r: u8, g: u8, b: u8) -> (f64, f64, f64) {
+    let linear = |v: u8| {
+        let v = v as f64 / 255.0;
+        if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(r), linear(g), linear(b));
+    // D65 white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    (116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+}
+
+#[cfg(test)]
+mod test {
+    use super::srgb_to_lab;
+
+    #[test]
+    fn test_srgb_to_lab() {
+        let (l, a, b) = srgb_to_lab(0, 0, 0);
+        assert!(l.abs() < 0.01 && a.abs() < 0.01 && b.abs() < 0.01);
+        let (l, a, b) = srgb_to_lab(255, 255, 255);
+        assert!((l - 100.0).abs() < 0.01 && a.abs() < 0.05 && b.abs() < 0.05);
+        // Pure red is L 53.2, a 80.1, b 67.2.
+        let (l, a, b) = srgb_to_lab(255, 0, 0);
+        assert!((l - 53.24).abs() < 0.1);
+        assert!((a - 80.09).abs() < 0.2);
+        assert!((b - 67.20).abs() < 0.2);
+    }
+}
@@ -28,6 +28,7 @@ mod controller;
 mod gphoto;
 pub mod gtk_utils;
 pub mod heif;
+mod histogram;
 mod image_bitmap;
 mod list_view;
 mod map_controller;
@@ -47,6 +48,7 @@ pub use channels::{Receiver, Sender, channel, send_async_any, send_async_local};
 pub use combo_model::ComboModel;
 pub use controller::{Controller, ControllerImpl, ControllerImplCell};
 pub use gphoto::{GpCamera, GpDevice, GpDeviceList};
+pub use histogram::{Histogram, clipping_overlay};
 pub use image_bitmap::ImageBitmap;
 pub use list_view::ListViewRow;
 pub use map_controller::MapController;
@@ -52,6 +52,10 @@ impl<T: Clone + std::cmp::PartialEq + 'static> ComboModel<T> {
         self.map.borrow().is_empty()
     }
 
+    pub fn len(&self) -> usize {
+        self.map.borrow().len()
+    }
+
     /// Push a value.
     pub fn push(&self, key: &str, value: T) {
         self.map.borrow_mut().push(value);
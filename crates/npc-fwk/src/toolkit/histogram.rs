@@ -0,0 +1,169 @@
+/*
+ * niepce - npc_fwk/toolkit/histogram.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Histogram of an 8 bits RGB bitmap.
+
+use super::ImageBitmap;
+
+/// Above that number of pixels, the bitmap is subsampled.
+const MAX_SAMPLES: usize = 1 << 20;
+
+/// Luminance of a RGB value, Rec. 709 weights.
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    (0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64).round() as u8
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// A 256 bins histogram, per channel and for the luminance.
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+    pub luma: [u32; 256],
+    /// Number of pixels sampled.
+    pub count: u32,
+}
+
+impl Default for Histogram {
+    fn default() -> Histogram {
+        Histogram {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            luma: [0; 256],
+            count: 0,
+        }
+    }
+}
+
+impl Histogram {
+    /// Compute the histogram of an RGB8 `buffer`. Large buffers are
+    /// subsampled.
+    pub fn from_rgb8(buffer: &[u8]) -> Histogram {
+        let mut histogram = Histogram::default();
+        let pixels = buffer.len() / 3;
+        let step = pixels.div_ceil(MAX_SAMPLES).max(1);
+        for pixel in buffer.chunks_exact(3).step_by(step) {
+            let (r, g, b) = (pixel[0], pixel[1], pixel[2]);
+            histogram.red[r as usize] += 1;
+            histogram.green[g as usize] += 1;
+            histogram.blue[b as usize] += 1;
+            histogram.luma[luma(r, g, b) as usize] += 1;
+            histogram.count += 1;
+        }
+        histogram
+    }
+
+    /// Compute the histogram of `bitmap`. Return `None` if the pixels
+    /// can't be obtained.
+    pub fn from_bitmap(bitmap: &ImageBitmap) -> Option<Histogram> {
+        bitmap
+            .rgb_buffer()
+            .map_err(|err| err_out!("Histogram: can't get the pixels: {err}"))
+            .ok()
+            .map(|buffer| Histogram::from_rgb8(&buffer))
+    }
+
+    /// The highest bin value of all the channels.
+    pub fn max(&self) -> u32 {
+        [&self.red, &self.green, &self.blue, &self.luma]
+            .iter()
+            .flat_map(|channel| channel.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Fraction of pixels with at least one channel in the top bin.
+    /// Approximated as the largest of the per channel fractions.
+    pub fn clipped_highlights(&self) -> f64 {
+        self.clipped(255)
+    }
+
+    /// Fraction of pixels with at least one channel in the bottom bin.
+    /// Approximated as the largest of the per channel fractions.
+    pub fn clipped_shadows(&self) -> f64 {
+        self.clipped(0)
+    }
+
+    fn clipped(&self, bin: usize) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let clipped = self.red[bin].max(self.green[bin]).max(self.blue[bin]);
+        clipped as f64 / self.count as f64
+    }
+}
+
+/// Build an RGBA8 overlay, the size of the RGB8 `buffer`, marking the
+/// clipped `highlights` in red and clipped `shadows` in blue. Other
+/// pixels are transparent.
+pub fn clipping_overlay(buffer: &[u8], highlights: bool, shadows: bool) -> Vec<u8> {
+    let mut overlay = Vec::with_capacity(buffer.len() / 3 * 4);
+    for pixel in buffer.chunks_exact(3) {
+        if highlights && pixel.contains(&255) {
+            overlay.extend_from_slice(&[255, 0, 0, 255]);
+        } else if shadows && pixel.contains(&0) {
+            overlay.extend_from_slice(&[0, 0, 255, 255]);
+        } else {
+            overlay.extend_from_slice(&[0, 0, 0, 0]);
+        }
+    }
+    overlay
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Histogram, clipping_overlay};
+
+    #[test]
+    fn test_histogram() {
+        let buffer = [0, 0, 0, 255, 255, 255, 255, 0, 0, 10, 20, 30];
+        let histogram = Histogram::from_rgb8(&buffer);
+        assert_eq!(histogram.count, 4);
+        assert_eq!(histogram.red[255], 2);
+        assert_eq!(histogram.red[0], 1);
+        assert_eq!(histogram.green[0], 2);
+        assert_eq!(histogram.blue[30], 1);
+        assert_eq!(histogram.luma[0], 1);
+        assert_eq!(histogram.luma[255], 1);
+        assert_eq!(histogram.luma[54], 1);
+        assert_eq!(histogram.max(), 2);
+        assert_eq!(histogram.clipped_highlights(), 0.5);
+        assert_eq!(histogram.clipped_shadows(), 0.5);
+
+        let empty = Histogram::from_rgb8(&[]);
+        assert_eq!(empty.count, 0);
+        assert_eq!(empty.max(), 0);
+        assert_eq!(empty.clipped_highlights(), 0.0);
+    }
+
+    #[test]
+    fn test_clipping_overlay() {
+        let buffer = [0, 10, 10, 255, 10, 10, 10, 10, 10];
+        let overlay = clipping_overlay(&buffer, true, true);
+        assert_eq!(
+            overlay,
+            [0, 0, 255, 255, 255, 0, 0, 255, 0, 0, 0, 0].to_vec()
+        );
+        let overlay = clipping_overlay(&buffer, true, false);
+        assert_eq!(&overlay[0..4], &[0, 0, 0, 0]);
+        assert_eq!(&overlay[4..8], &[255, 0, 0, 255]);
+    }
+}
@@ -17,6 +17,7 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::borrow::Cow;
 use std::io::{Read, Write};
 use std::sync::Arc;
 
@@ -126,6 +127,23 @@ impl ImageBitmap {
         Ok(())
     }
 
+    /// Save as JPEG with `quality`, from 1 to 100.
+    pub fn save_jpeg<P>(&self, file: P, quality: u8) -> Result<()>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let buffer = self.rgb_buffer()?;
+        let f = std::fs::File::create(&file)?;
+        let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(f, quality);
+        encoder.write_image(
+            &buffer,
+            self.size.w,
+            self.size.h,
+            image::ExtendedColorType::Rgb8,
+        )?;
+        Ok(())
+    }
+
     /// The width of the image in pixels
     pub fn original_width(&self) -> u32 {
         self.size.w
@@ -136,6 +154,17 @@ impl ImageBitmap {
         self.size.h
     }
 
+    /// The pixels as 8 bits RGB. A PNG will be decoded.
+    pub fn rgb_buffer(&self) -> Result<Cow<'_, [u8]>> {
+        match *self.buffer {
+            BitmapType::Rgb(ref buffer) => Ok(Cow::Borrowed(buffer)),
+            BitmapType::Png(ref buffer) => {
+                let image = image::load_from_memory_with_format(buffer, image::ImageFormat::Png)?;
+                Ok(Cow::Owned(image.into_rgb8().into_raw()))
+            }
+        }
+    }
+
     /// Create a gdk4::Texture from the image for display.
     /// Caveat: there don't seem to be a way to consume the data, so it's duplicated.
     pub fn to_gdk_texture(&self) -> gdk4::Texture {
@@ -19,6 +19,7 @@
 
 mod dock;
 mod editable_hscale;
+mod histogram_widget;
 mod metadata_widget;
 pub mod rating_label;
 mod token_text_view;
@@ -27,6 +28,7 @@ mod toolbox_item;
 // Re-exports
 pub use dock::Dock;
 pub use editable_hscale::EditableHScale;
+pub use histogram_widget::HistogramWidget;
 pub use metadata_widget::{
     MetaDT, MetadataFormat, MetadataPropertyBag, MetadataSectionFormat, MetadataWidget,
 };
@@ -41,6 +41,21 @@ impl EditableHScale {
         imp.adj.set_step_increment(step);
         obj
     }
+
+    /// Connect to the signal `value-changed`, emitted when the user
+    /// is done changing the value.
+    pub fn connect_value_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, f64) + 'static,
+    {
+        self.connect_closure(
+            "value-changed",
+            true,
+            glib::closure_local!(move |w, value| {
+                f(&w, value);
+            }),
+        )
+    }
 }
 
 mod imp {
@@ -56,9 +71,12 @@ mod imp {
     #[derive(glib::Properties)]
     #[properties(wrapper_type = super::EditableHScale)]
     pub struct EditableHScale {
-        #[property(get, set, default_value = 0.0)]
+        /// Setting the value doesn't emit the signals.
+        #[property(get, set = Self::set_value, default_value = 0.0)]
         value: Cell<f64>,
         dirty: Cell<bool>,
+        /// The value is being set programmatically.
+        setting: Cell<bool>,
         pub(super) adj: gtk4::Adjustment,
         scale: gtk4::Scale,
         entry: gtk4::SpinButton,
@@ -75,10 +93,22 @@ mod imp {
             }
         }
 
-        fn on_adj_value_changed(&self) {
+        fn set_value(&self, value: f64) {
+            self.setting.set(true);
+            self.adj.set_value(value);
+            self.setting.set(false);
+            self.value.set(self.adj.value());
             self.dirty.set(false);
-            let obj = self.obj();
+        }
+
+        fn on_adj_value_changed(&self) {
             let value = self.adj.value();
+            self.value.set(value);
+            if self.setting.get() {
+                return;
+            }
+            self.dirty.set(true);
+            let obj = self.obj();
             obj.emit_by_name::<()>("value-changing", &[&value]);
         }
     }
@@ -102,6 +132,7 @@ mod imp {
                 scale,
                 entry,
                 dirty: Cell::default(),
+                setting: Cell::default(),
                 value: Cell::default(),
             }
         }
@@ -136,6 +167,13 @@ mod imp {
                 }
             ));
             self.entry.add_controller(gesture);
+            self.entry.connect_activate(glib::clone!(
+                #[weak]
+                obj,
+                move |_| {
+                    obj.imp().on_button_press();
+                }
+            ));
             self.adj.connect_value_changed(glib::clone!(
                 #[weak]
                 obj,
@@ -0,0 +1,133 @@
+/*
+ * niepce - npc_fwk/toolkit/widgets/histogram_widget.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::cell::RefCell;
+
+use crate::cairo;
+use crate::glib;
+use crate::graphene;
+use crate::gtk4;
+use glib::subclass::prelude::*;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+
+use crate::toolkit::Histogram;
+
+/// Height requested for the widget.
+const HEIGHT: i32 = 100;
+
+glib::wrapper! {
+    /// Display a `Histogram`: the luminance in grey with the red, green
+    /// and blue channels on top.
+    pub struct HistogramWidget(
+        ObjectSubclass<HistogramWidgetPriv>)
+        @extends gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
+
+#[derive(Default)]
+pub struct HistogramWidgetPriv {
+    histogram: RefCell<Option<Histogram>>,
+}
+
+impl HistogramWidgetPriv {
+    /// Draw `channel` as a filled curve. `max` is the square root of
+    /// the highest bin.
+    fn draw_channel(
+        cr: &cairo::Context,
+        channel: &[u32; 256],
+        max: f64,
+        w: f64,
+        h: f64,
+        colour: (f64, f64, f64, f64),
+    ) {
+        let step = w / 255.0;
+        cr.move_to(0.0, h);
+        for (i, value) in channel.iter().enumerate() {
+            cr.line_to(i as f64 * step, h - ((*value as f64).sqrt() / max) * h);
+        }
+        cr.line_to(w, h);
+        cr.close_path();
+        cr.set_source_rgba(colour.0, colour.1, colour.2, colour.3);
+        on_err_out!(cr.fill());
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for HistogramWidgetPriv {
+    const NAME: &'static str = "HistogramWidget";
+    type Type = HistogramWidget;
+    type ParentType = gtk4::Widget;
+}
+
+impl ObjectImpl for HistogramWidgetPriv {}
+
+impl WidgetImpl for HistogramWidgetPriv {
+    fn measure(&self, orientation: gtk4::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+        match orientation {
+            gtk4::Orientation::Vertical => (HEIGHT, HEIGHT, -1, -1),
+            _ => (64, 256, -1, -1),
+        }
+    }
+
+    fn snapshot(&self, snapshot: &gtk4::Snapshot) {
+        let widget = self.obj();
+        let w = widget.width() as f32;
+        let h = widget.height() as f32;
+        let cr = snapshot.append_cairo(&graphene::Rect::new(0.0, 0.0, w, h));
+        let (w, h) = (w as f64, h as f64);
+
+        cr.set_source_rgb(0.1, 0.1, 0.1);
+        cr.rectangle(0.0, 0.0, w, h);
+        on_err_out!(cr.fill());
+
+        let histogram = self.histogram.borrow();
+        let Some(histogram) = histogram.as_ref() else {
+            return;
+        };
+        // Scale on a square root to keep the smaller bins visible.
+        let max = (histogram.max() as f64).sqrt();
+        if max == 0.0 {
+            return;
+        }
+        Self::draw_channel(&cr, &histogram.luma, max, w, h, (0.6, 0.6, 0.6, 0.5));
+        cr.set_operator(cairo::Operator::Add);
+        Self::draw_channel(&cr, &histogram.red, max, w, h, (0.8, 0.0, 0.0, 0.5));
+        Self::draw_channel(&cr, &histogram.green, max, w, h, (0.0, 0.8, 0.0, 0.5));
+        Self::draw_channel(&cr, &histogram.blue, max, w, h, (0.0, 0.0, 0.8, 0.5));
+    }
+}
+
+impl Default for HistogramWidget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HistogramWidget {
+    pub fn new() -> Self {
+        glib::Object::new()
+    }
+
+    /// Set the `histogram` to display. `None` will clear it.
+    pub fn set_histogram(&self, histogram: Option<Histogram>) {
+        self.imp().histogram.replace(histogram);
+        self.queue_draw();
+    }
+}
@@ -27,6 +27,7 @@ use anyhow::Context;
 use exempi2::Xmp;
 
 use super::exiv2;
+use crate::base::fractions::fraction_to_decimal;
 use crate::toolkit::heif;
 use crate::{Date, DateExt};
 
@@ -34,6 +35,7 @@ pub const NIEPCE_XMP_NAMESPACE: &str = "http://xmlns.figuiere.net/ns/niepce/1.0"
 pub const NIEPCE_XMP_NS_PREFIX: &str = "niepce";
 const UFRAW_INTEROP_NAMESPACE: &str = "http://xmlns.figuiere.net/ns/ufraw_interop/1.0";
 const UFRAW_INTEROP_NS_PREFIX: &str = "ufrint";
+const LIGHTROOM_NS_PREFIX: &str = "lr";
 
 pub const NS_TIFF: &str = "http://ns.adobe.com/tiff/1.0/";
 pub const NS_XAP: &str = "http://ns.adobe.com/xap/1.0/";
@@ -42,6 +44,11 @@ pub const NS_EXIF_EX: &str = "http://cipa.jp/exif/1.0/";
 pub const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
 pub const NS_AUX: &str = "http://ns.adobe.com/exif/1.0/aux/";
 pub const NS_PHOTOSHOP: &str = "http://ns.adobe.com/photoshop/1.0/";
+pub const NS_LR: &str = "http://ns.adobe.com/lightroom/1.0/";
+
+/// Separator for the hierarchical keywords path, like in
+/// `lr:hierarchicalSubject`.
+pub const KEYWORD_PATH_SEPARATOR: char = '|';
 
 const XMP_TRUE: &str = "True";
 const XMP_FALSE: &str = "False";
@@ -139,6 +146,7 @@ impl ExempiManager {
             UFRAW_INTEROP_NAMESPACE,
             UFRAW_INTEROP_NS_PREFIX
         ));
+        on_err_out!(exempi2::register_namespace(NS_LR, LIGHTROOM_NS_PREFIX));
 
         if let Some(nslist) = namespaces {
             for nsdef in nslist {
@@ -317,6 +325,14 @@ impl XmpMeta {
             return false;
         }
 
+        self.copy_missing_into_xmp(dest);
+        true
+    }
+
+    /// Copy the properties from self (source) missing in `dest`,
+    /// regardless of the metadata dates. Struct and array are
+    /// considered missing as a whole.
+    pub fn copy_missing_into_xmp(&self, dest: &mut XmpMeta) {
         // Properties in source but not in destination gets copied over.
         let mut iter = exempi2::XmpIterator::new(&self.xmp, "", "", exempi2::IterFlags::PROPERTIES);
         while let Some(v) = iter.next() {
@@ -343,8 +359,6 @@ impl XmpMeta {
                 }
             }
         }
-
-        true
     }
 
     pub fn serialize_inline(&self) -> String {
@@ -428,6 +442,15 @@ impl XmpMeta {
         Some(Date::from_exempi(&date))
     }
 
+    /// Get the camera model, from TIFF `Model`.
+    pub fn camera_model(&self) -> Option<String> {
+        let mut flags: exempi2::PropFlags = exempi2::PropFlags::empty();
+        self.xmp
+            .get_property(NS_TIFF, "Model", &mut flags)
+            .ok()
+            .map(|model| String::from(&model))
+    }
+
     /// Same as `creation_date()` but the original string is returned instead.
     pub fn creation_date_str(&self) -> Option<String> {
         let mut flags: exempi2::PropFlags = exempi2::PropFlags::empty();
@@ -442,6 +465,26 @@ impl XmpMeta {
         Some(String::from(&xmpstring))
     }
 
+    /// Get the exposure bias in EV, from Exif `ExposureBiasValue`.
+    pub fn exposure_bias(&self) -> Option<f64> {
+        let mut flags: exempi2::PropFlags = exempi2::PropFlags::default();
+        let xmpstring = self
+            .xmp
+            .get_property(NS_EXIF, "ExposureBiasValue", &mut flags)
+            .ok()?;
+        let value = String::from(&xmpstring);
+        fraction_to_decimal(&value).or_else(|| value.parse().ok())
+    }
+
+    /// Whether the image is part of an auto exposure bracket, from
+    /// Exif `ExposureMode`.
+    pub fn is_auto_bracket(&self) -> bool {
+        let mut flags: exempi2::PropFlags = exempi2::PropFlags::default();
+        self.xmp
+            .get_property_i32(NS_EXIF, "ExposureMode", &mut flags)
+            .is_ok_and(|mode| mode == 2)
+    }
+
     /// Get the date property and return an `Option<DateTime<Utc>>`.
     /// Uses XMP to parse the date.
     pub fn get_date_property(&self, ns: &str, propname: &str) -> Option<Date> {
@@ -469,6 +512,119 @@ impl XmpMeta {
         }
         &self.keywords
     }
+
+    /// The hierarchical keywords (`lr:hierarchicalSubject`), as paths
+    /// separated by `KEYWORD_PATH_SEPARATOR`.
+    pub fn hierarchical_keywords(&self) -> Vec<String> {
+        exempi2::XmpIterator::new(
+            &self.xmp,
+            NS_LR,
+            "hierarchicalSubject",
+            exempi2::IterFlags::JUST_LEAF_NODES,
+        )
+        .map(|v| String::from(&v.value))
+        .collect()
+    }
+
+    /// The keywords as paths: the hierarchical keywords, and the flat
+    /// keywords that aren't part of any of them.
+    pub fn keyword_paths(&self) -> Vec<String> {
+        let mut paths = self.hierarchical_keywords();
+        let flat = exempi2::XmpIterator::new(
+            &self.xmp,
+            NS_DC,
+            "subject",
+            exempi2::IterFlags::JUST_LEAF_NODES,
+        )
+        .map(|v| String::from(&v.value))
+        .filter(|keyword| {
+            !paths
+                .iter()
+                .any(|path| path.split(KEYWORD_PATH_SEPARATOR).any(|k| k == keyword))
+        })
+        .collect::<Vec<_>>();
+        paths.extend(flat);
+        paths
+    }
+
+    /// The creators (`dc:creator`), in order.
+    pub fn creators(&self) -> Vec<String> {
+        exempi2::XmpIterator::new(
+            &self.xmp,
+            NS_DC,
+            "creator",
+            exempi2::IterFlags::JUST_LEAF_NODES,
+        )
+        .map(|v| String::from(&v.value))
+        .collect()
+    }
+
+    /// Set the creators (`dc:creator`). An empty list removes the
+    /// property.
+    pub fn set_creators(&mut self, creators: &[String]) -> anyhow::Result<()> {
+        // Deleting a property that doesn't exist isn't an error.
+        let _ = self.xmp.delete_property(NS_DC, "creator");
+        for creator in creators {
+            self.xmp
+                .append_array_item(
+                    NS_DC,
+                    "creator",
+                    exempi2::PropFlags::VALUE_IS_ARRAY | exempi2::PropFlags::ARRAY_IS_ORDERED,
+                    creator,
+                    exempi2::PropFlags::NONE,
+                )
+                .with_context(|| format!("Error appending {creator} to creator"))?;
+        }
+        Ok(())
+    }
+
+    /// Set the keywords: `subjects` for `dc:subject` and `hierarchical`
+    /// for `lr:hierarchicalSubject`. Empty lists remove the properties.
+    pub fn set_keywords(
+        &mut self,
+        subjects: &[String],
+        hierarchical: &[String],
+    ) -> anyhow::Result<()> {
+        for (ns, property, values) in [
+            (NS_DC, "subject", subjects),
+            (NS_LR, "hierarchicalSubject", hierarchical),
+        ] {
+            // Deleting a property that doesn't exist isn't an error.
+            let _ = self.xmp.delete_property(ns, property);
+            for value in values {
+                self.xmp
+                    .append_array_item(
+                        ns,
+                        property,
+                        exempi2::PropFlags::VALUE_IS_ARRAY,
+                        value,
+                        exempi2::PropFlags::NONE,
+                    )
+                    .with_context(|| format!("Error appending {value} to {property}"))?;
+            }
+        }
+        self.keywords.clear();
+        self.keywords_fetched = false;
+        Ok(())
+    }
+
+    /// Set the keywords from `paths`. The leaves go to `dc:subject`
+    /// and the paths with more than one level to `lr:hierarchicalSubject`.
+    pub fn set_keyword_paths(&mut self, paths: &[String]) -> anyhow::Result<()> {
+        let mut subjects: Vec<String> = vec![];
+        let mut hierarchical: Vec<String> = vec![];
+        for path in paths {
+            if let Some(leaf) = path.rsplit(KEYWORD_PATH_SEPARATOR).next() {
+                if !leaf.is_empty() && !subjects.iter().any(|s| s == leaf) {
+                    subjects.push(leaf.to_string());
+                }
+            }
+            if path.contains(KEYWORD_PATH_SEPARATOR) && !hierarchical.contains(path) {
+                hierarchical.push(path.clone());
+            }
+        }
+        self.set_keywords(&subjects, &hierarchical)
+    }
 }
 
 pub fn gps_coord_from_xmp(xmps: &str) -> Option<f64> {
@@ -523,6 +679,21 @@ pub fn gps_coord_from_xmp(xmps: &str) -> Option<f64> {
     Some(deg)
 }
 
+/// Format the GPS coordinate `coord` in degrees for XMP, in the
+/// `DD,mm.mmR` format. `latitude` tells the reference: N/S or E/W.
+pub fn gps_coord_to_xmp(coord: f64, latitude: bool) -> String {
+    let reference = match (latitude, coord < 0.0) {
+        (true, false) => 'N',
+        (true, true) => 'S',
+        (false, false) => 'E',
+        (false, true) => 'W',
+    };
+    let coord = coord.abs();
+    let degrees = coord.trunc();
+    let minutes = (coord - degrees) * 60.0;
+    format!("{},{:.10}{}", degrees as u32, minutes, reference)
+}
+
 /// Get and XMP date from an Exif date string
 /// XXX Currently assume it is UTC.
 pub fn xmp_date_from_exif(d: &str, offset: Option<&str>) -> Option<exempi2::DateTime> {
@@ -661,6 +832,44 @@ mod tests {
         assert_eq!(value.unwrap().to_str(), Ok(expected_value));
     }
 
+    #[test]
+    fn test_keyword_paths() {
+        let _xmp_manager = ExempiManager::new(None);
+
+        let mut meta = XmpMeta::new();
+        assert!(
+            meta.set_keyword_paths(&[
+                "Places|Europe|France".to_string(),
+                "beach".to_string(),
+                "Places|Europe|Spain".to_string(),
+            ])
+            .is_ok()
+        );
+        assert_eq!(meta.keywords(), &vec!["France", "beach", "Spain"]);
+        assert_eq!(
+            meta.hierarchical_keywords(),
+            vec!["Places|Europe|France", "Places|Europe|Spain"]
+        );
+        assert_eq!(
+            meta.keyword_paths(),
+            vec!["Places|Europe|France", "Places|Europe|Spain", "beach"]
+        );
+
+        // Flat keywords that are also part of a path aren't repeated.
+        assert!(
+            meta.set_keywords(
+                &[
+                    "France".to_string(),
+                    "Europe".to_string(),
+                    "sea".to_string()
+                ],
+                &["Places|Europe|France".to_string()],
+            )
+            .is_ok()
+        );
+        assert_eq!(meta.keyword_paths(), vec!["Places|Europe|France", "sea"]);
+    }
+
     #[test]
     fn test_merge_missing_into_xmp() {
         let dir = get_xmp_sample_path();
@@ -748,6 +957,17 @@ mod tests {
         assert_eq!(output.unwrap(), 45.491_791_666_666_664);
     }
 
+    #[test]
+    fn gps_coord_to_works() {
+        use super::{gps_coord_from_xmp, gps_coord_to_xmp};
+
+        assert_eq!(gps_coord_to_xmp(45.5, true), "45,30.0000000000N");
+        assert_eq!(gps_coord_to_xmp(-73.25, false), "73,15.0000000000W");
+
+        let coord = gps_coord_from_xmp(&gps_coord_to_xmp(-33.868_82, true));
+        assert!((coord.unwrap() + 33.868_82).abs() < 1e-9);
+    }
+
     #[test]
     fn test_xmp_date_from_exif() {
         let d = xmp_date_from_exif("2012:02:17 11:10:49", None);
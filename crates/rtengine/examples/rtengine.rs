@@ -17,7 +17,7 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use rtengine::RtEngine;
+use rtengine::{ProcParams, RtEngine};
 
 fn main() {
     let mut args = std::env::args();
@@ -25,16 +25,27 @@ fn main() {
         println!("Filename is needed");
         std::process::exit(1);
     }
-    if args.len() > 2 {
+    if args.len() > 3 {
         println!("Ignoring extra arguments");
     }
     args.next();
     let filename = args.next().expect("Expect an argument");
+    // Optional .pp3 profile.
+    let profile = args.next();
 
     let engine = RtEngine::new();
     if engine.set_file(filename, true /* is_raw */).is_err() {
         std::process::exit(3);
     }
+    if let Some(profile) = profile {
+        match ProcParams::load(&profile) {
+            Ok(params) => engine.set_params(Some(params)),
+            Err(error) => {
+                println!("Error, couldn't load profile {profile}: {error}");
+                std::process::exit(4);
+            }
+        }
+    }
 
     match engine.process() {
         Err(error) => {
@@ -1,7 +1,7 @@
 /*
  * niepce - bridge.rs
  *
- * Copyright (C) 2023-2024 Hubert Figuière
+ * Copyright (C) 2023-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -42,6 +42,126 @@ pub(crate) mod ffi {
         type LcMode;
     }
 
+    /// Exposure and tone.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct ExposureParams {
+        /// Automatic exposure.
+        auto_exposure: bool,
+        /// Clipped fraction for automatic exposure.
+        clip: f64,
+        /// Exposure compensation in EV.
+        compensation: f64,
+        /// -100 to 100.
+        brightness: i32,
+        /// Black level.
+        black: i32,
+        /// -100 to 100.
+        contrast: i32,
+        /// -100 to 100.
+        saturation: i32,
+        /// Shadow compression, 0 to 100.
+        shadow_compression: i32,
+        /// Highlight compression, 0 to 500.
+        highlight_compression: i32,
+        /// Highlight compression threshold, 0 to 100.
+        highlight_compression_threshold: i32,
+    }
+
+    /// White balance.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct WhiteBalanceParams {
+        enabled: bool,
+        /// "Camera", "Custom", "autold", etc.
+        method: String,
+        /// Temperature in Kelvin.
+        temperature: i32,
+        /// Green multiplier. 1.0 is neutral.
+        green: f64,
+        /// Blue / red equalizer. 1.0 is neutral.
+        equal: f64,
+    }
+
+    /// Highlight reconstruction.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct HighlightRecoveryParams {
+        enabled: bool,
+        /// "Luminance", "CIELab blending", "Color", "Blend" or "Coloropp".
+        method: String,
+    }
+
+    /// Crop.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct CropParams {
+        enabled: bool,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        /// Whether the ratio is fixed.
+        fixed_ratio: bool,
+        /// The ratio, like "3:2".
+        ratio: String,
+    }
+
+    /// Sharpening.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct SharpeningParams {
+        enabled: bool,
+        /// "usm" (unsharp mask) or "rld" (deconvolution).
+        method: String,
+        /// Contrast threshold, 0 to 200.
+        contrast: f64,
+        /// Unsharp mask radius.
+        radius: f64,
+        /// Unsharp mask amount, 0 to 1000.
+        amount: i32,
+        /// Deconvolution radius.
+        deconv_radius: f64,
+        /// Deconvolution amount, 0 to 100.
+        deconv_amount: i32,
+        /// Deconvolution iterations.
+        deconv_iterations: i32,
+    }
+
+    /// Noise reduction.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct NoiseReductionParams {
+        enabled: bool,
+        /// Luminance, 0 to 100.
+        luma: f64,
+        /// Luminance detail, 0 to 100.
+        luma_detail: f64,
+        /// Chrominance (master), 0 to 100.
+        chroma: f64,
+        /// Red-green chrominance, -100 to 100.
+        red_chroma: f64,
+        /// Blue-yellow chrominance, -100 to 100.
+        blue_chroma: f64,
+        /// Gamma, 1 to 3.
+        gamma: f64,
+    }
+
+    /// Colour, the vibrance tool.
+    #[namespace = "rtengine::npc"]
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct ColourParams {
+        enabled: bool,
+        /// Pastel tones, -100 to 100.
+        pastels: i32,
+        /// Saturated tones, -100 to 100.
+        saturated: i32,
+        /// Link pastel and saturated tones.
+        linked: bool,
+        protect_skins: bool,
+        avoid_colour_shift: bool,
+    }
+
     extern "C++" {
         type FramesMetaData;
     }
@@ -61,6 +181,13 @@ pub(crate) mod ffi {
         ) -> UniquePtr<InitialImage>;
         #[cxx_name = "getMetaData"]
         fn get_meta_data(&self) -> *const FramesMetaData;
+        #[cxx_name = "InitialImage_full_size"]
+        /// The size of the image, after the raw rotation.
+        fn initial_image_full_size(
+            image: Pin<&mut InitialImage>,
+            width: &mut i32,
+            height: &mut i32,
+        );
         /// Takes ownership
         unsafe fn decrease_ref(image: *mut InitialImage);
     }
@@ -122,8 +249,57 @@ pub(crate) mod ffi {
 
         #[cxx_name = "ProcParams_new"]
         fn proc_params_new() -> UniquePtr<ProcParams>;
+        #[cxx_name = "ProcParams_clone"]
+        fn proc_params_clone(params: &ProcParams) -> UniquePtr<ProcParams>;
+        #[cxx_name = "ProcParams_load"]
+        /// Load from a .pp3 file. Return 0 on success.
+        fn proc_params_load(params: Pin<&mut ProcParams>, fname: &CxxString) -> i32;
+        #[cxx_name = "ProcParams_save"]
+        /// Save to a .pp3 file. Return 0 on success.
+        fn proc_params_save(params: &ProcParams, fname: &CxxString) -> i32;
+        #[cxx_name = "ProcParams_lcmode"]
+        fn proc_params_lcmode(params: &ProcParams) -> LcMode;
         #[cxx_name = "ProcParams_set_lcmode"]
         /// Set the lens correction mode.
         fn proc_params_set_lcmode(params: Pin<&mut ProcParams>, mode: LcMode);
+        #[cxx_name = "ProcParams_exposure"]
+        fn proc_params_exposure(params: &ProcParams) -> ExposureParams;
+        #[cxx_name = "ProcParams_set_exposure"]
+        fn proc_params_set_exposure(params: Pin<&mut ProcParams>, exposure: &ExposureParams);
+        #[cxx_name = "ProcParams_tone_curve"]
+        fn proc_params_tone_curve(params: &ProcParams) -> Vec<f64>;
+        #[cxx_name = "ProcParams_set_tone_curve"]
+        fn proc_params_set_tone_curve(params: Pin<&mut ProcParams>, curve: &[f64]);
+        #[cxx_name = "ProcParams_white_balance"]
+        fn proc_params_white_balance(params: &ProcParams) -> WhiteBalanceParams;
+        #[cxx_name = "ProcParams_set_white_balance"]
+        fn proc_params_set_white_balance(params: Pin<&mut ProcParams>, wb: &WhiteBalanceParams);
+        #[cxx_name = "ProcParams_highlight_recovery"]
+        fn proc_params_highlight_recovery(params: &ProcParams) -> HighlightRecoveryParams;
+        #[cxx_name = "ProcParams_set_highlight_recovery"]
+        fn proc_params_set_highlight_recovery(
+            params: Pin<&mut ProcParams>,
+            hr: &HighlightRecoveryParams,
+        );
+        #[cxx_name = "ProcParams_crop"]
+        fn proc_params_crop(params: &ProcParams) -> CropParams;
+        #[cxx_name = "ProcParams_set_crop"]
+        fn proc_params_set_crop(params: Pin<&mut ProcParams>, crop: &CropParams);
+        #[cxx_name = "ProcParams_rotation"]
+        fn proc_params_rotation(params: &ProcParams) -> f64;
+        #[cxx_name = "ProcParams_set_rotation"]
+        fn proc_params_set_rotation(params: Pin<&mut ProcParams>, degrees: f64);
+        #[cxx_name = "ProcParams_sharpening"]
+        fn proc_params_sharpening(params: &ProcParams) -> SharpeningParams;
+        #[cxx_name = "ProcParams_set_sharpening"]
+        fn proc_params_set_sharpening(params: Pin<&mut ProcParams>, sharpening: &SharpeningParams);
+        #[cxx_name = "ProcParams_noise_reduction"]
+        fn proc_params_noise_reduction(params: &ProcParams) -> NoiseReductionParams;
+        #[cxx_name = "ProcParams_set_noise_reduction"]
+        fn proc_params_set_noise_reduction(params: Pin<&mut ProcParams>, nr: &NoiseReductionParams);
+        #[cxx_name = "ProcParams_colour"]
+        fn proc_params_colour(params: &ProcParams) -> ColourParams;
+        #[cxx_name = "ProcParams_set_colour"]
+        fn proc_params_set_colour(params: Pin<&mut ProcParams>, colour: &ColourParams);
     }
 }
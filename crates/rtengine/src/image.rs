@@ -87,6 +87,14 @@ impl InitialImage {
         }
     }
 
+    /// The size of the image, width and height.
+    pub fn full_size(&mut self) -> (i32, i32) {
+        let mut width = 0_i32;
+        let mut height = 0_i32;
+        ffi::initial_image_full_size(self.0.pin_mut(), &mut width, &mut height);
+        (width, height)
+    }
+
     /// Get the metadata for the image.
     pub fn meta_data(&mut self) -> FramesMetaData {
         FramesMetaData(self.0.pin_mut().get_meta_data())
@@ -1,7 +1,7 @@
 /*
  * niepce - lib.rs
  *
- * Copyright (C) 2023-2024 Hubert Figuière
+ * Copyright (C) 2023-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -30,7 +30,11 @@ use std::sync::Once;
 use npc_fwk::toolkit::ImageBitmap;
 
 use bridge::ffi;
-pub use ffi::LcMode;
+pub use ffi::{
+    ColourParams, CropParams, ExposureParams, HighlightRecoveryParams, LcMode,
+    NoiseReductionParams, SharpeningParams, WhiteBalanceParams,
+};
+pub use params::ProcParams;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -51,6 +55,10 @@ pub enum Error {
 
     #[error("No image to process")]
     NoImage,
+    #[error("Cannot read profile")]
+    CannotReadProfile,
+    #[error("Cannot write profile")]
+    CannotWriteProfile,
     #[error("Unknow Error")]
     Unknown,
 }
@@ -100,6 +108,8 @@ impl EngineState {
 /// RawTherapee rendering engine
 pub struct RtEngine {
     state: RefCell<Option<EngineState>>,
+    /// The processing params. If `None` use the default.
+    params: RefCell<Option<ProcParams>>,
 }
 
 impl Default for RtEngine {
@@ -115,6 +125,7 @@ impl RtEngine {
 
         RtEngine {
             state: RefCell::new(None),
+            params: RefCell::new(None),
         }
     }
 
@@ -140,19 +151,49 @@ impl RtEngine {
         Ok(())
     }
 
-    /// Process the image using rtengine and return an ImageBitmap
-    /// Currently it uses the default profiles and enable `LcMode::LensFunAutoMatch`.
-    pub fn process(&self) -> Result<ImageBitmap> {
+    /// The size of the current file, before crop.
+    pub fn image_size(&self) -> Option<(u32, u32)> {
+        let mut state = self.state.borrow_mut();
+        let image = state.as_mut()?.initial_image.as_mut()?;
+        let (width, height) = image.full_size();
+        if width <= 0 || height <= 0 {
+            return None;
+        }
+        Some((width as u32, height as u32))
+    }
+
+    /// The default params for the current file: the dynamic profile
+    /// with `LcMode::LensFunAutoMatch`.
+    pub fn default_params(&self) -> Result<ProcParams> {
         if let Some(ref mut state) = *self.state.borrow_mut() {
             if let Some(ref mut image) = state.initial_image {
-                let mut proc_params = params::ProcParams::new();
+                let mut proc_params = ProcParams::new();
                 let raw_params = params::ProfileStore::load_dynamic_profile(
                     &image.meta_data(),
                     &state.input_file,
                 );
                 raw_params.apply_to(&mut proc_params, false);
                 proc_params.set_lcmode(ffi::LcMode::LensFunAutoMatch);
+                return Ok(proc_params);
+            }
+        }
+        Err(Error::NoImage)
+    }
+
+    /// Set the params to process with. `None` to use the default params.
+    pub fn set_params(&self, params: Option<ProcParams>) {
+        self.params.replace(params);
+    }
 
+    /// Process the image using rtengine and return an ImageBitmap
+    /// If no params are set, it uses the default params.
+    pub fn process(&self) -> Result<ImageBitmap> {
+        let proc_params = match *self.params.borrow() {
+            Some(ref params) => params.clone(),
+            None => self.default_params()?,
+        };
+        if let Some(ref mut state) = *self.state.borrow_mut() {
+            if let Some(ref mut image) = state.initial_image {
                 let job = processing::ProcessingJob::new(image, &proc_params, false);
                 return job
                     .process_image(false)
@@ -1,8 +1,9 @@
-
 #include <glibmm/ustring.h>
 #include <giomm/init.h>
 #include "npc_rtconfig.h"
 #include "npc_rtengine.h"
+#include "rtengine/imagesource.h"
+#include "rtengine/src/bridge.rs.h"
 
 extern Glib::ustring argv0;
 
@@ -11,4 +12,164 @@ namespace rtengine {
     argv0 = DATA_SEARCH_PATH;
     Gio::init();
   }
+
+  void InitialImage_full_size(InitialImage& image, int& width, int& height) {
+    width = height = 0;
+    if (auto source = image.getImageSource()) {
+      source->getFullSize(width, height);
+    }
+  }
+
+  namespace procparams {
+
+    namespace {
+      rust::String to_rust(const Glib::ustring& s) {
+        return rust::String(s.raw());
+      }
+
+      Glib::ustring from_rust(const rust::String& s) {
+        return Glib::ustring(std::string(s));
+      }
+    }
+
+    int ProcParams_load(ProcParams& params, const std::string& fname) {
+      return params.load(fname);
+    }
+
+    int ProcParams_save(const ProcParams& params, const std::string& fname) {
+      return params.save(fname);
+    }
+
+    npc::ExposureParams ProcParams_exposure(const ProcParams& params) {
+      const auto& tc = params.toneCurve;
+      return npc::ExposureParams {
+        tc.autoexp, tc.clip, tc.expcomp, tc.brightness, tc.black, tc.contrast,
+        tc.saturation, tc.shcompr, tc.hlcompr, tc.hlcomprthresh
+      };
+    }
+
+    void ProcParams_set_exposure(ProcParams& params, const npc::ExposureParams& exposure) {
+      auto& tc = params.toneCurve;
+      tc.autoexp = exposure.auto_exposure;
+      tc.clip = exposure.clip;
+      tc.expcomp = exposure.compensation;
+      tc.brightness = exposure.brightness;
+      tc.black = exposure.black;
+      tc.contrast = exposure.contrast;
+      tc.saturation = exposure.saturation;
+      tc.shcompr = exposure.shadow_compression;
+      tc.hlcompr = exposure.highlight_compression;
+      tc.hlcomprthresh = exposure.highlight_compression_threshold;
+    }
+
+    rust::Vec<double> ProcParams_tone_curve(const ProcParams& params) {
+      rust::Vec<double> curve;
+      for (auto v : params.toneCurve.curve) {
+        curve.push_back(v);
+      }
+      return curve;
+    }
+
+    void ProcParams_set_tone_curve(ProcParams& params, rust::Slice<const double> curve) {
+      params.toneCurve.curve.assign(curve.begin(), curve.end());
+    }
+
+    npc::WhiteBalanceParams ProcParams_white_balance(const ProcParams& params) {
+      const auto& wb = params.wb;
+      return npc::WhiteBalanceParams {
+        wb.enabled, to_rust(wb.method), wb.temperature, wb.green, wb.equal
+      };
+    }
+
+    void ProcParams_set_white_balance(ProcParams& params, const npc::WhiteBalanceParams& wb) {
+      params.wb.enabled = wb.enabled;
+      params.wb.method = from_rust(wb.method);
+      params.wb.temperature = wb.temperature;
+      params.wb.green = wb.green;
+      params.wb.equal = wb.equal;
+    }
+
+    npc::HighlightRecoveryParams ProcParams_highlight_recovery(const ProcParams& params) {
+      return npc::HighlightRecoveryParams {
+        params.toneCurve.hrenabled, to_rust(params.toneCurve.method)
+      };
+    }
+
+    void ProcParams_set_highlight_recovery(ProcParams& params, const npc::HighlightRecoveryParams& hr) {
+      params.toneCurve.hrenabled = hr.enabled;
+      params.toneCurve.method = from_rust(hr.method);
+    }
+
+    npc::CropParams ProcParams_crop(const ProcParams& params) {
+      const auto& crop = params.crop;
+      return npc::CropParams {
+        crop.enabled, crop.x, crop.y, crop.w, crop.h, crop.fixratio, to_rust(crop.ratio)
+      };
+    }
+
+    void ProcParams_set_crop(ProcParams& params, const npc::CropParams& crop) {
+      params.crop.enabled = crop.enabled;
+      params.crop.x = crop.x;
+      params.crop.y = crop.y;
+      params.crop.w = crop.w;
+      params.crop.h = crop.h;
+      params.crop.fixratio = crop.fixed_ratio;
+      params.crop.ratio = from_rust(crop.ratio);
+    }
+
+    npc::SharpeningParams ProcParams_sharpening(const ProcParams& params) {
+      const auto& sh = params.sharpening;
+      return npc::SharpeningParams {
+        sh.enabled, to_rust(sh.method), sh.contrast, sh.radius, sh.amount,
+        sh.deconvradius, sh.deconvamount, sh.deconviter
+      };
+    }
+
+    void ProcParams_set_sharpening(ProcParams& params, const npc::SharpeningParams& sharpening) {
+      auto& sh = params.sharpening;
+      sh.enabled = sharpening.enabled;
+      sh.method = from_rust(sharpening.method);
+      sh.contrast = sharpening.contrast;
+      sh.radius = sharpening.radius;
+      sh.amount = sharpening.amount;
+      sh.deconvradius = sharpening.deconv_radius;
+      sh.deconvamount = sharpening.deconv_amount;
+      sh.deconviter = sharpening.deconv_iterations;
+    }
+
+    npc::NoiseReductionParams ProcParams_noise_reduction(const ProcParams& params) {
+      const auto& nr = params.dirpyrDenoise;
+      return npc::NoiseReductionParams {
+        nr.enabled, nr.luma, nr.Ldetail, nr.chroma, nr.redchro, nr.bluechro, nr.gamma
+      };
+    }
+
+    void ProcParams_set_noise_reduction(ProcParams& params, const npc::NoiseReductionParams& nr) {
+      auto& dn = params.dirpyrDenoise;
+      dn.enabled = nr.enabled;
+      dn.luma = nr.luma;
+      dn.Ldetail = nr.luma_detail;
+      dn.chroma = nr.chroma;
+      dn.redchro = nr.red_chroma;
+      dn.bluechro = nr.blue_chroma;
+      dn.gamma = nr.gamma;
+    }
+
+    npc::ColourParams ProcParams_colour(const ProcParams& params) {
+      const auto& v = params.vibrance;
+      return npc::ColourParams {
+        v.enabled, v.pastels, v.saturated, v.pastsattog, v.protectskins, v.avoidcolorshift
+      };
+    }
+
+    void ProcParams_set_colour(ProcParams& params, const npc::ColourParams& colour) {
+      auto& v = params.vibrance;
+      v.enabled = colour.enabled;
+      v.pastels = colour.pastels;
+      v.saturated = colour.saturated;
+      v.pastsattog = colour.linked;
+      v.protectskins = colour.protect_skins;
+      v.avoidcolorshift = colour.avoid_colour_shift;
+    }
+  }
 }
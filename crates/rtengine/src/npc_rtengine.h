@@ -1,7 +1,7 @@
 /*
  * niepce - npc_rtengine.h
  *
- * Copyright (C) 2023-2024 Hubert Figuière
+ * Copyright (C) 2023-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -25,9 +25,21 @@
 #include "rtengine/imageio.h"
 #include "rtengine/profilestore.h"
 #include "rtgui/options.h"
+#include "rust/cxx.h"
 
 namespace rtengine {
 
+  // Shared with Rust, defined by the bridge.
+  namespace npc {
+    struct ExposureParams;
+    struct WhiteBalanceParams;
+    struct HighlightRecoveryParams;
+    struct CropParams;
+    struct SharpeningParams;
+    struct NoiseReductionParams;
+    struct ColourParams;
+  }
+
   void init_();
 
   inline
@@ -41,6 +53,8 @@ namespace rtengine {
     return std::unique_ptr<InitialImage>(InitialImage::load(fname, isRaw, &errorCode, nullptr));
   }
 
+  void InitialImage_full_size(InitialImage& image, int& width, int& height);
+
   inline
   void decrease_ref(InitialImage* image) {
     image->decreaseRef();
@@ -86,11 +100,52 @@ namespace rtengine {
       profile->applyTo(&params, from_last_saved);
     }
 
+    inline
+    LcMode ProcParams_lcmode(const ProcParams& params) {
+      return params.lensProf.lcMode;
+    }
+
     inline
     void ProcParams_set_lcmode(ProcParams& params, LcMode mode) {
       params.lensProf.lcMode = mode;
     }
 
+    inline
+    std::unique_ptr<ProcParams> ProcParams_clone(const ProcParams& params) {
+      return std::make_unique<ProcParams>(params);
+    }
+
+    int ProcParams_load(ProcParams& params, const std::string& fname);
+    int ProcParams_save(const ProcParams& params, const std::string& fname);
+
+    npc::ExposureParams ProcParams_exposure(const ProcParams& params);
+    void ProcParams_set_exposure(ProcParams& params, const npc::ExposureParams& exposure);
+    rust::Vec<double> ProcParams_tone_curve(const ProcParams& params);
+    void ProcParams_set_tone_curve(ProcParams& params, rust::Slice<const double> curve);
+    npc::WhiteBalanceParams ProcParams_white_balance(const ProcParams& params);
+    void ProcParams_set_white_balance(ProcParams& params, const npc::WhiteBalanceParams& wb);
+    npc::HighlightRecoveryParams ProcParams_highlight_recovery(const ProcParams& params);
+    void ProcParams_set_highlight_recovery(ProcParams& params, const npc::HighlightRecoveryParams& hr);
+    npc::CropParams ProcParams_crop(const ProcParams& params);
+    void ProcParams_set_crop(ProcParams& params, const npc::CropParams& crop);
+
+    inline
+    double ProcParams_rotation(const ProcParams& params) {
+      return params.rotate.degree;
+    }
+
+    inline
+    void ProcParams_set_rotation(ProcParams& params, double degrees) {
+      params.rotate.degree = degrees;
+    }
+
+    npc::SharpeningParams ProcParams_sharpening(const ProcParams& params);
+    void ProcParams_set_sharpening(ProcParams& params, const npc::SharpeningParams& sharpening);
+    npc::NoiseReductionParams ProcParams_noise_reduction(const ProcParams& params);
+    void ProcParams_set_noise_reduction(ProcParams& params, const npc::NoiseReductionParams& nr);
+    npc::ColourParams ProcParams_colour(const ProcParams& params);
+    void ProcParams_set_colour(ProcParams& params, const npc::ColourParams& colour);
+
     inline
     std::unique_ptr<procparams::ProcParams> ProcParams_new() {
       return std::make_unique<procparams::ProcParams>();
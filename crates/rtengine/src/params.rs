@@ -1,7 +1,7 @@
 /*
  * niepce - params.rs
  *
- * Copyright (C) 2023-2024 Hubert Figuière
+ * Copyright (C) 2023-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -17,25 +17,142 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use crate::LcMode;
 use crate::ffi;
 use crate::image;
+use crate::{
+    ColourParams, CropParams, Error, ExposureParams, HighlightRecoveryParams, LcMode,
+    NoiseReductionParams, Result, SharpeningParams, WhiteBalanceParams,
+};
 
 use std::ffi::OsStr;
+use std::path::Path;
 
 /// Processing parameters.
-pub(crate) struct ProcParams(pub cxx::UniquePtr<ffi::ProcParams>);
+pub struct ProcParams(pub(crate) cxx::UniquePtr<ffi::ProcParams>);
+
+impl Default for ProcParams {
+    fn default() -> ProcParams {
+        ProcParams::new()
+    }
+}
+
+impl Clone for ProcParams {
+    fn clone(&self) -> ProcParams {
+        ProcParams(ffi::proc_params_clone(&self.0))
+    }
+}
 
 impl ProcParams {
-    /// New empty ProcParams
+    /// New ProcParams with the RawTherapee defaults.
     pub fn new() -> ProcParams {
         ProcParams(ffi::proc_params_new())
     }
 
+    /// Load the parameters from a `.pp3` profile.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ProcParams> {
+        let mut params = ProcParams::new();
+        cxx::let_cxx_string!(fname = path.as_ref().as_os_str().as_encoded_bytes());
+        if ffi::proc_params_load(params.0.pin_mut(), &fname) != 0 {
+            return Err(Error::CannotReadProfile);
+        }
+        Ok(params)
+    }
+
+    /// Save the parameters to a `.pp3` profile.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        cxx::let_cxx_string!(fname = path.as_ref().as_os_str().as_encoded_bytes());
+        if ffi::proc_params_save(&self.0, &fname) != 0 {
+            return Err(Error::CannotWriteProfile);
+        }
+        Ok(())
+    }
+
+    /// The lens correction mode.
+    pub fn lcmode(&self) -> LcMode {
+        ffi::proc_params_lcmode(&self.0)
+    }
+
     /// Set the lens correction mode.
     pub fn set_lcmode(&mut self, mode: LcMode) {
         ffi::proc_params_set_lcmode(self.0.pin_mut(), mode)
     }
+
+    pub fn exposure(&self) -> ExposureParams {
+        ffi::proc_params_exposure(&self.0)
+    }
+
+    pub fn set_exposure(&mut self, exposure: &ExposureParams) {
+        ffi::proc_params_set_exposure(self.0.pin_mut(), exposure)
+    }
+
+    /// The tone curve, in the RawTherapee format: the curve type
+    /// followed by the points.
+    pub fn tone_curve(&self) -> Vec<f64> {
+        ffi::proc_params_tone_curve(&self.0)
+    }
+
+    /// Set the tone curve. See [`Self::tone_curve`].
+    pub fn set_tone_curve(&mut self, curve: &[f64]) {
+        ffi::proc_params_set_tone_curve(self.0.pin_mut(), curve)
+    }
+
+    pub fn white_balance(&self) -> WhiteBalanceParams {
+        ffi::proc_params_white_balance(&self.0)
+    }
+
+    pub fn set_white_balance(&mut self, wb: &WhiteBalanceParams) {
+        ffi::proc_params_set_white_balance(self.0.pin_mut(), wb)
+    }
+
+    pub fn highlight_recovery(&self) -> HighlightRecoveryParams {
+        ffi::proc_params_highlight_recovery(&self.0)
+    }
+
+    pub fn set_highlight_recovery(&mut self, hr: &HighlightRecoveryParams) {
+        ffi::proc_params_set_highlight_recovery(self.0.pin_mut(), hr)
+    }
+
+    pub fn crop(&self) -> CropParams {
+        ffi::proc_params_crop(&self.0)
+    }
+
+    pub fn set_crop(&mut self, crop: &CropParams) {
+        ffi::proc_params_set_crop(self.0.pin_mut(), crop)
+    }
+
+    /// The rotation in degrees.
+    pub fn rotation(&self) -> f64 {
+        ffi::proc_params_rotation(&self.0)
+    }
+
+    /// Set the rotation in degrees.
+    pub fn set_rotation(&mut self, degrees: f64) {
+        ffi::proc_params_set_rotation(self.0.pin_mut(), degrees)
+    }
+
+    pub fn sharpening(&self) -> SharpeningParams {
+        ffi::proc_params_sharpening(&self.0)
+    }
+
+    pub fn set_sharpening(&mut self, sharpening: &SharpeningParams) {
+        ffi::proc_params_set_sharpening(self.0.pin_mut(), sharpening)
+    }
+
+    pub fn noise_reduction(&self) -> NoiseReductionParams {
+        ffi::proc_params_noise_reduction(&self.0)
+    }
+
+    pub fn set_noise_reduction(&mut self, nr: &NoiseReductionParams) {
+        ffi::proc_params_set_noise_reduction(self.0.pin_mut(), nr)
+    }
+
+    pub fn colour(&self) -> ColourParams {
+        ffi::proc_params_colour(&self.0)
+    }
+
+    pub fn set_colour(&mut self, colour: &ColourParams) {
+        ffi::proc_params_set_colour(self.0.pin_mut(), colour)
+    }
 }
 
 /// Partial process parameters from a profile.
@@ -17,6 +17,7 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+mod crop;
 mod dr_item;
 pub(super) mod image_canvas;
 mod toolbox_controller;
@@ -27,26 +28,32 @@ use std::rc::Rc;
 use gettextrs::gettext as i18n;
 use gtk4::prelude::*;
 use i18n_format::i18n_format;
-use npc_fwk::{adw, gtk4};
+use npc_fwk::{adw, glib, gtk4};
 
 use crate::niepce::ui::LibraryModule;
+use dr_item::DrItem;
 use image_canvas::ImageCanvas;
 use npc_craw::{RenderImpl, RenderWorker};
 use npc_engine::catalog::NiepceProperties as Np;
 use npc_engine::catalog::NiepcePropertyIdx as Npi;
-use npc_engine::catalog::{self, LibMetadata, LibraryId};
+use npc_engine::catalog::{self, CropRect, DevelopSettings, LibMetadata, LibraryId};
 use npc_engine::library::notification::{ImageRendered, LibNotification, MetadataChange};
 use npc_engine::library::{RenderEngine, RenderMsg, RenderParams};
 use npc_engine::libraryclient::{ClientInterface, LibraryClientHost};
-use npc_fwk::base::Size;
-use npc_fwk::toolkit::widgets::Dock;
+use npc_fwk::base::{Size, srgb_to_lab};
+use npc_fwk::toolkit::widgets::{Dock, HistogramWidget};
 use npc_fwk::toolkit::{ComboModel, Controller, ControllerImplCell, UiController};
 use npc_fwk::{dbg_out, on_err_out};
-use toolbox_controller::ToolboxController;
+use toolbox_controller::{ToolboxController, ToolboxMsg};
 
 pub enum Msg {
     SelectionChanged(Option<Box<catalog::LibFile>>),
     SetRenderEngine(RenderEngine),
+    Toolbox(ToolboxMsg),
+    /// The crop was changed on the canvas.
+    CropChanged(CropRect),
+    /// A line was drawn to straighten, with its angle.
+    Straightened(f64),
 }
 
 pub struct DarkroomModule {
@@ -58,10 +65,15 @@ pub struct DarkroomModule {
     overlay: adw::ToastOverlay,
     engine_combo: gtk4::DropDown,
     engine_combo_model: Rc<ComboModel<RenderEngine>>,
-    toolbox_controller: ToolboxController,
+    toolbox_controller: Rc<ToolboxController>,
+    histogram: HistogramWidget,
+    /// The value of the pixel under the pointer.
+    readout: gtk4::Label,
     file: RefCell<Option<catalog::LibFile>>,
     render_params: RefCell<Option<RenderParams>>,
     need_reload: Cell<bool>,
+    /// The crop tool is active: the image is displayed uncropped.
+    crop_mode: Cell<bool>,
     active: Cell<bool>,
     loading_toast: RefCell<Option<adw::Toast>>,
 }
@@ -75,7 +87,6 @@ impl Controller for DarkroomModule {
     fn dispatch(&self, msg: Msg) {
         match msg {
             Msg::SetRenderEngine(ref engine) => {
-                // XXX make this a command with undo
                 dbg_out!("Render engine changed in UI");
                 if let Some(ref file) = *self.file.borrow() {
                     self.client.client().set_metadata(
@@ -86,6 +97,22 @@ impl Controller for DarkroomModule {
                 }
             }
             Msg::SelectionChanged(file) => self.set_image(file.as_deref()),
+            Msg::Toolbox(msg) => self.toolbox_msg(msg),
+            Msg::CropChanged(crop) => {
+                if let Some(mut settings) = self.develop_settings() {
+                    settings.crop = crop.clamped().filter(|crop| !crop.is_full());
+                    self.toolbox_controller.set_develop_settings(&settings);
+                    self.develop_changed(settings);
+                }
+            }
+            Msg::Straightened(angle) => {
+                if let Some(mut settings) = self.develop_settings() {
+                    // Positive tilt is counter clockwise.
+                    settings.tilt = (settings.tilt + angle).clamp(-45.0, 45.0);
+                    self.toolbox_controller.set_develop_settings(&settings);
+                    self.develop_changed(settings);
+                }
+            }
         }
     }
 }
@@ -138,9 +165,12 @@ impl DarkroomModule {
             engine_combo_model: Rc::default(),
             worker,
             toolbox_controller,
+            histogram: HistogramWidget::new(),
+            readout: gtk4::Label::new(None),
             file: RefCell::new(None),
             render_params: RefCell::new(None),
             need_reload: Cell::new(true),
+            crop_mode: Cell::new(false),
             active: Cell::new(false),
             loading_toast: RefCell::new(None),
         };
@@ -203,8 +233,9 @@ impl DarkroomModule {
     fn rendered_image_received(&self, rendered: &ImageRendered) {
         dbg_out!("Got bitmap");
         if self.is_current_file_id(rendered.id) {
-            let b = rendered.image.clone();
-            self.imagecanvas.set_image(b);
+            self.histogram
+                .set_histogram(rendered.histogram.as_deref().cloned());
+            self.imagecanvas.set_image(rendered.image.clone());
             self.remove_loading_toast();
         } else {
             dbg_out!("Received bitmap for {}, not the current", rendered.id);
@@ -246,6 +277,8 @@ impl DarkroomModule {
 
             let key = params.engine();
             self.set_active_engine(Some(key));
+            self.toolbox_controller
+                .set_develop_settings(params.develop_settings());
 
             self.need_reload.set(true);
             params
@@ -288,16 +321,87 @@ impl DarkroomModule {
             npc_fwk::toolkit::send_async_local!(Msg::SetRenderEngine(id), tx);
         });
         dock.vbox().append(&self.engine_combo);
+        dock.vbox().append(&self.build_histogram_item());
+        let tx = self.sender();
+        self.toolbox_controller
+            .set_forwarder(Some(Box::new(move |msg| {
+                npc_fwk::toolkit::send_async_local!(Msg::Toolbox(msg), tx);
+            })));
+        let tx = self.sender();
+        self.imagecanvas.connect_crop_changed(move |_, crop| {
+            npc_fwk::toolkit::send_async_local!(Msg::CropChanged(crop), tx);
+        });
+        let tx = self.sender();
+        self.imagecanvas.connect_straightened(move |_, angle| {
+            npc_fwk::toolkit::send_async_local!(Msg::Straightened(angle), tx);
+        });
         let toolbox = self.toolbox_controller.widget();
         dock.vbox().append(toolbox);
         splitview.set_end_child(Some(&dock));
         splitview.set_resize_end_child(false);
     }
 
+    /// Build the dock item with the histogram, the clipping toggles
+    /// and the pixel readout.
+    fn build_histogram_item(&self) -> DrItem {
+        let item = DrItem::new(&i18n("Histogram"));
+        item.append(&self.histogram);
+
+        let hbox = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+        let highlights = gtk4::ToggleButton::with_label(&i18n("Highlights"));
+        highlights.set_tooltip_text(Some(&i18n("Show the clipped highlights")));
+        let shadows = gtk4::ToggleButton::with_label(&i18n("Shadows"));
+        shadows.set_tooltip_text(Some(&i18n("Show the clipped shadows")));
+        let on_toggled = glib::clone!(
+            #[weak(rename_to = canvas)]
+            self.imagecanvas,
+            #[weak]
+            highlights,
+            #[weak]
+            shadows,
+            move |_: &gtk4::ToggleButton| {
+                canvas.set_clipping(highlights.is_active(), shadows.is_active());
+            }
+        );
+        highlights.connect_toggled(on_toggled.clone());
+        shadows.connect_toggled(on_toggled);
+        hbox.append(&highlights);
+        hbox.append(&shadows);
+        item.add_widget(&i18n("Clipping"), &hbox);
+
+        self.readout.set_xalign(0.0);
+        self.readout.add_css_class("monospace");
+        item.add_widget(&i18n("Pixel"), &self.readout);
+        let readout = self.readout.clone();
+        self.imagecanvas.connect_pixel_hovered(move |_, rgb| {
+            let text = rgb
+                .map(|(r, g, b)| {
+                    let lab = srgb_to_lab(r, g, b);
+                    format!(
+                        "R {r:3} G {g:3} B {b:3} · L {:5.1} a {:6.1} b {:6.1}",
+                        lab.0, lab.1, lab.2
+                    )
+                })
+                .unwrap_or_default();
+            readout.set_text(&text);
+        });
+
+        item
+    }
+
     fn reload_image(&self, params: Option<RenderParams>) {
         if !self.need_reload.get() {
             return;
         }
+        // The crop tool needs the whole image.
+        let params = params.map(|mut params| {
+            if self.crop_mode.get() {
+                let mut settings = params.develop_settings().clone();
+                settings.crop = None;
+                params.set_develop_settings(settings);
+            }
+            params
+        });
         if let Some(ref file) = *self.file.borrow() {
             self.show_loading_toast(file.path());
             on_err_out!(self.worker.send(RenderMsg::Reload(params.clone())));
@@ -338,6 +442,7 @@ impl DarkroomModule {
     pub fn set_image(&self, file: Option<&catalog::LibFile>) {
         self.need_reload.set(true);
         self.file.replace(file.cloned());
+        self.histogram.set_histogram(None);
 
         if let Some(file) = file {
             on_err_out!(
@@ -350,6 +455,8 @@ impl DarkroomModule {
 
                 let key = params.engine();
                 self.set_active_engine(Some(key));
+                self.toolbox_controller
+                    .set_develop_settings(params.develop_settings());
 
                 if self.need_reload.get() && self.active.get() {
                     self.reload_image(Some(params));
@@ -358,6 +465,58 @@ impl DarkroomModule {
         }
     }
 
+    /// The current develop settings.
+    fn develop_settings(&self) -> Option<DevelopSettings> {
+        self.render_params
+            .borrow()
+            .as_ref()
+            .map(|params| params.develop_settings().clone())
+    }
+
+    fn toolbox_msg(&self, msg: ToolboxMsg) {
+        match msg {
+            ToolboxMsg::DevelopChanged(settings) => self.develop_changed(settings),
+            ToolboxMsg::CropMode(crop_mode) => {
+                self.crop_mode.set(crop_mode);
+                self.imagecanvas
+                    .set_crop(self.develop_settings().and_then(|settings| settings.crop));
+                self.imagecanvas.set_crop_mode(crop_mode);
+                self.need_reload.set(true);
+                self.reload_image(self.render_params.borrow().clone());
+            }
+            ToolboxMsg::AspectRatio(aspect) => self.imagecanvas.set_aspect_ratio(aspect),
+            ToolboxMsg::Guides(guides) => self.imagecanvas.set_guides(guides),
+            ToolboxMsg::Straighten(straighten) => self.imagecanvas.set_straighten_mode(straighten),
+            ToolboxMsg::ResetCrop => {
+                if let Some(mut settings) = self.develop_settings() {
+                    settings.crop = None;
+                    self.imagecanvas.set_crop(None);
+                    self.toolbox_controller.set_develop_settings(&settings);
+                    self.develop_changed(settings);
+                }
+            }
+        }
+    }
+
+    /// The develop settings were changed: save them and render again.
+    fn develop_changed(&self, settings: DevelopSettings) {
+        if let Some(ref mut file) = *self.file.borrow_mut() {
+            self.client
+                .client()
+                .set_develop_settings(file.id(), &settings);
+            if let Some(ref mut metadata) = file.metadata {
+                metadata.set_develop_settings(&settings);
+            }
+        } else {
+            return;
+        }
+        if let Some(ref mut params) = *self.render_params.borrow_mut() {
+            params.set_develop_settings(settings);
+            self.need_reload.set(true);
+        }
+        self.reload_image(self.render_params.borrow().clone());
+    }
+
     fn set_engine(&self, engine: &str) {
         if let Some(engine) = RenderEngine::from_key(engine) {
             if let Some(ref mut params) = *self.render_params.borrow_mut() {
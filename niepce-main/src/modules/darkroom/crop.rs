@@ -0,0 +1,401 @@
+/*
+ * niepce - modules/darkroom/crop.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Geometry of the crop tool.
+
+use npc_engine::catalog::CropRect;
+
+/// Smallest size of the crop, in canvas pixels.
+const MIN_SIZE: f64 = 16.0;
+
+/// Aspect ratio of the crop.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AspectRatio {
+    /// Not constrained.
+    #[default]
+    Custom,
+    /// The ratio of the image.
+    Original,
+    Square,
+    R3x2,
+    R4x5,
+    R16x9,
+}
+
+impl AspectRatio {
+    /// The ratio width / height for a landscape crop. `None` if not
+    /// constrained. `image_ratio` is the ratio of the image.
+    pub fn ratio(&self, image_ratio: f64) -> Option<f64> {
+        match self {
+            Self::Custom => None,
+            Self::Original => Some(image_ratio.max(1.0 / image_ratio)),
+            Self::Square => Some(1.0),
+            Self::R3x2 => Some(3.0 / 2.0),
+            Self::R4x5 => Some(5.0 / 4.0),
+            Self::R16x9 => Some(16.0 / 9.0),
+        }
+    }
+}
+
+/// The guides to draw in the crop.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Guides {
+    None,
+    /// Rule of thirds.
+    #[default]
+    Thirds,
+    GoldenRatio,
+}
+
+impl Guides {
+    /// The position of the lines as a fraction of the crop.
+    pub fn lines(&self) -> &'static [f64] {
+        match self {
+            Self::None => &[],
+            Self::Thirds => &[1.0 / 3.0, 2.0 / 3.0],
+            Self::GoldenRatio => &[0.381_966, 0.618_034],
+        }
+    }
+}
+
+/// What is dragged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Handle {
+    /// The whole crop.
+    Move,
+    /// An edge or a corner. For `h`, -1 is the left edge and 1 the
+    /// right edge, 0 neither. Same for `v` with top and bottom.
+    Resize { h: i8, v: i8 },
+}
+
+/// A rectangle in canvas coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Frame {
+    pub fn new(x: f64, y: f64, w: f64, h: f64) -> Frame {
+        Frame {
+            left: x,
+            top: y,
+            right: x + w,
+            bottom: y + h,
+        }
+    }
+
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f64 {
+        self.bottom - self.top
+    }
+
+    /// The frame of `crop` inside the image frame `self`.
+    pub fn crop_frame(&self, crop: Option<CropRect>) -> Frame {
+        match crop {
+            Some(crop) => Frame::new(
+                self.left + crop.x * self.width(),
+                self.top + crop.y * self.height(),
+                crop.w * self.width(),
+                crop.h * self.height(),
+            ),
+            None => *self,
+        }
+    }
+
+    /// The crop of `frame` inside the image frame `self`.
+    pub fn crop_rect(&self, frame: &Frame) -> CropRect {
+        CropRect {
+            x: (frame.left - self.left) / self.width(),
+            y: (frame.top - self.top) / self.height(),
+            w: frame.width() / self.width(),
+            h: frame.height() / self.height(),
+        }
+    }
+
+    /// Scale around the anchor point `(ax, ay)` to fit in `bounds`.
+    fn fit(&self, bounds: &Frame, ax: f64, ay: f64) -> Frame {
+        let mut scale = 1.0_f64;
+        if self.left < bounds.left && ax > self.left {
+            scale = scale.min((ax - bounds.left) / (ax - self.left));
+        }
+        if self.right > bounds.right && self.right > ax {
+            scale = scale.min((bounds.right - ax) / (self.right - ax));
+        }
+        if self.top < bounds.top && ay > self.top {
+            scale = scale.min((ay - bounds.top) / (ay - self.top));
+        }
+        if self.bottom > bounds.bottom && self.bottom > ay {
+            scale = scale.min((bounds.bottom - ay) / (self.bottom - ay));
+        }
+        let scale = scale.max(0.0);
+        Frame {
+            left: ax + (self.left - ax) * scale,
+            top: ay + (self.top - ay) * scale,
+            right: ax + (self.right - ax) * scale,
+            bottom: ay + (self.bottom - ay) * scale,
+        }
+    }
+}
+
+/// Find the handle of `crop` at `(x, y)`.
+pub fn hit_test(crop: &Frame, x: f64, y: f64, tolerance: f64) -> Option<Handle> {
+    if x < crop.left - tolerance
+        || x > crop.right + tolerance
+        || y < crop.top - tolerance
+        || y > crop.bottom + tolerance
+    {
+        return None;
+    }
+    let side = |v: f64, low: f64, high: f64| {
+        if (v - low).abs() <= tolerance {
+            -1
+        } else if (v - high).abs() <= tolerance {
+            1
+        } else {
+            0
+        }
+    };
+    let h = side(x, crop.left, crop.right);
+    let v = side(y, crop.top, crop.bottom);
+    if h == 0 && v == 0 {
+        Some(Handle::Move)
+    } else {
+        Some(Handle::Resize { h, v })
+    }
+}
+
+/// Drag `handle` of the `start` crop by `(dx, dy)`, inside `bounds`.
+/// `ratio` is the aspect ratio to keep.
+pub fn drag(
+    start: &Frame,
+    handle: Handle,
+    dx: f64,
+    dy: f64,
+    bounds: &Frame,
+    ratio: Option<f64>,
+) -> Frame {
+    let (h, v) = match handle {
+        Handle::Move => {
+            // Not `clamp()` as it panics if the crop is out of bounds.
+            let dx = dx
+                .min(bounds.right - start.right)
+                .max(bounds.left - start.left);
+            let dy = dy
+                .min(bounds.bottom - start.bottom)
+                .max(bounds.top - start.top);
+            return Frame {
+                left: start.left + dx,
+                top: start.top + dy,
+                right: start.right + dx,
+                bottom: start.bottom + dy,
+            };
+        }
+        Handle::Resize { h, v } => (h, v),
+    };
+
+    let mut frame = *start;
+    match h {
+        -1 => frame.left = (start.left + dx).min(start.right - MIN_SIZE),
+        1 => frame.right = (start.right + dx).max(start.left + MIN_SIZE),
+        _ => {}
+    }
+    match v {
+        -1 => frame.top = (start.top + dy).min(start.bottom - MIN_SIZE),
+        1 => frame.bottom = (start.bottom + dy).max(start.top + MIN_SIZE),
+        _ => {}
+    }
+
+    // The anchor is the opposite edge, or the centre.
+    let ax = match h {
+        -1 => start.right,
+        1 => start.left,
+        _ => (start.left + start.right) / 2.0,
+    };
+    let ay = match v {
+        -1 => start.bottom,
+        1 => start.top,
+        _ => (start.top + start.bottom) / 2.0,
+    };
+
+    if let Some(ratio) = ratio {
+        // Keep the orientation of the crop.
+        let ratio = if start.width() >= start.height() {
+            ratio
+        } else {
+            1.0 / ratio
+        };
+        let mut w = frame.width();
+        let mut hh = frame.height();
+        if v == 0 || (h != 0 && w / hh > ratio) {
+            hh = w / ratio;
+        } else {
+            w = hh * ratio;
+        }
+        frame.left = match h {
+            1 => ax,
+            -1 => ax - w,
+            _ => ax - w / 2.0,
+        };
+        frame.right = frame.left + w;
+        frame.top = match v {
+            1 => ay,
+            -1 => ay - hh,
+            _ => ay - hh / 2.0,
+        };
+        frame.bottom = frame.top + hh;
+        // Scale to keep the ratio.
+        frame.fit(bounds, ax, ay)
+    } else {
+        Frame {
+            left: frame.left.max(bounds.left),
+            top: frame.top.max(bounds.top),
+            right: frame.right.min(bounds.right),
+            bottom: frame.bottom.min(bounds.bottom),
+        }
+    }
+}
+
+/// Apply the aspect `ratio` to `frame` keeping its centre, inside `bounds`.
+pub fn constrain(frame: &Frame, ratio: Option<f64>, bounds: &Frame) -> Frame {
+    let Some(ratio) = ratio else {
+        return *frame;
+    };
+    let ratio = if frame.width() >= frame.height() {
+        ratio
+    } else {
+        1.0 / ratio
+    };
+    let (mut w, mut h) = (frame.width(), frame.height());
+    if w / h > ratio {
+        w = h * ratio;
+    } else {
+        h = w / ratio;
+    }
+    let cx = (frame.left + frame.right) / 2.0;
+    let cy = (frame.top + frame.bottom) / 2.0;
+    Frame::new(cx - w / 2.0, cy - h / 2.0, w, h).fit(bounds, cx, cy)
+}
+
+/// The angle in degrees, clockwise, of the line from `(x0, y0)` to
+/// `(x1, y1)` relative to the closest of horizontal or vertical.
+/// `None` if the line is too short.
+pub fn straighten_angle(x0: f64, y0: f64, x1: f64, y1: f64) -> Option<f64> {
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    if dx.hypot(dy) < 10.0 {
+        return None;
+    }
+    // The y axis goes down, so positive is clockwise.
+    let mut angle = dy.atan2(dx).to_degrees();
+    if angle > 90.0 {
+        angle -= 180.0;
+    } else if angle < -90.0 {
+        angle += 180.0;
+    }
+    if angle > 45.0 {
+        angle -= 90.0;
+    } else if angle < -45.0 {
+        angle += 90.0;
+    }
+    Some(angle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Frame, Handle, constrain, drag, hit_test, straighten_angle};
+
+    #[test]
+    fn test_hit_test() {
+        let crop = Frame::new(100.0, 100.0, 200.0, 100.0);
+        assert_eq!(hit_test(&crop, 10.0, 10.0, 5.0), None);
+        assert_eq!(hit_test(&crop, 150.0, 150.0, 5.0), Some(Handle::Move));
+        assert_eq!(
+            hit_test(&crop, 98.0, 102.0, 5.0),
+            Some(Handle::Resize { h: -1, v: -1 })
+        );
+        assert_eq!(
+            hit_test(&crop, 301.0, 150.0, 5.0),
+            Some(Handle::Resize { h: 1, v: 0 })
+        );
+    }
+
+    #[test]
+    fn test_drag() {
+        let bounds = Frame::new(0.0, 0.0, 400.0, 300.0);
+        let crop = Frame::new(100.0, 100.0, 200.0, 100.0);
+
+        // Move is kept in the bounds.
+        let moved = drag(&crop, Handle::Move, 500.0, -20.0, &bounds, None);
+        assert_eq!(moved, Frame::new(200.0, 80.0, 200.0, 100.0));
+
+        // Free resize.
+        let resize = Handle::Resize { h: 1, v: 1 };
+        let resized = drag(&crop, resize, 50.0, 20.0, &bounds, None);
+        assert_eq!(resized, Frame::new(100.0, 100.0, 250.0, 120.0));
+
+        // Resize with 2:1 keeps the top left corner.
+        let resized = drag(&crop, resize, 50.0, 20.0, &bounds, Some(2.0));
+        assert_eq!(resized.left, 100.0);
+        assert_eq!(resized.top, 100.0);
+        assert_eq!(resized.width() / resized.height(), 2.0);
+
+        // The bounds are respected.
+        let resized = drag(&crop, resize, 500.0, 0.0, &bounds, None);
+        assert_eq!(resized.right, 400.0);
+        let resized = drag(&crop, resize, 500.0, 0.0, &bounds, Some(2.0));
+        assert!(resized.right <= 400.0 && resized.bottom <= 300.0);
+        assert!((resized.width() / resized.height() - 2.0).abs() < 1e-9);
+
+        // Left edge.
+        let resized = drag(
+            &crop,
+            Handle::Resize { h: -1, v: 0 },
+            -50.0,
+            0.0,
+            &bounds,
+            None,
+        );
+        assert_eq!(resized, Frame::new(50.0, 100.0, 250.0, 100.0));
+    }
+
+    #[test]
+    fn test_constrain() {
+        let bounds = Frame::new(0.0, 0.0, 400.0, 300.0);
+        let square = constrain(&bounds, Some(1.0), &bounds);
+        assert_eq!(square, Frame::new(50.0, 0.0, 300.0, 300.0));
+        assert_eq!(constrain(&bounds, None, &bounds), bounds);
+    }
+
+    #[test]
+    fn test_straighten_angle() {
+        assert_eq!(straighten_angle(0.0, 0.0, 1.0, 1.0), None);
+        assert_eq!(straighten_angle(0.0, 0.0, 100.0, 0.0), Some(0.0));
+        assert_eq!(straighten_angle(100.0, 0.0, 0.0, 0.0), Some(0.0));
+        let angle = straighten_angle(0.0, 0.0, 100.0, 100.0 * 5_f64.to_radians().tan());
+        assert!((angle.unwrap() - 5.0).abs() < 1e-9);
+        // Near vertical.
+        let angle = straighten_angle(0.0, 0.0, -100.0 * 5_f64.to_radians().tan(), 100.0);
+        assert!((angle.unwrap() - 5.0).abs() < 1e-9);
+    }
+}
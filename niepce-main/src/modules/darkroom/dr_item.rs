@@ -41,6 +41,11 @@ impl DrItem {
         obj
     }
 
+    /// Add a `widget` without a label.
+    pub fn append(&self, widget: &impl IsA<gtk4::Widget>) {
+        self.imp().vbox.append(widget);
+    }
+
     pub fn add_widget(&self, label: &str, widget: &impl IsA<gtk4::Widget>) {
         let label = gtk4::Label::new(Some(label));
         label.set_xalign(0.0);
@@ -21,8 +21,11 @@ use gtk4::prelude::*;
 use gtk4::subclass::prelude::*;
 use npc_fwk::{glib, gtk4};
 
+use npc_engine::catalog::CropRect;
 use npc_fwk::toolkit::ImageBitmap;
 
+use super::crop::{AspectRatio, Guides};
+
 #[derive(Clone, Copy, Default, glib::Enum)]
 #[enum_type(name = "NcrZoomMode")]
 enum ZoomMode {
@@ -57,30 +60,135 @@ impl ImageCanvas {
         let imp = self.imp();
         imp.request_redisplay();
         imp.image.replace(Some(image));
+        imp.clear_pixels();
         self.queue_draw();
     }
 
     pub fn set_image_none(&self) {
         self.imp().request_redisplay();
         self.imp().image.replace(None);
+        self.imp().clear_pixels();
         self.queue_draw();
     }
+
+    /// Show the clipped `highlights` and `shadows` over the image.
+    pub fn set_clipping(&self, highlights: bool, shadows: bool) {
+        let imp = self.imp();
+        imp.clip_highlights.set(highlights);
+        imp.clip_shadows.set(shadows);
+        imp.overlay.replace(None);
+        imp.redraw();
+    }
+
+    /// Show the crop tool.
+    pub fn set_crop_mode(&self, crop_mode: bool) {
+        let imp = self.imp();
+        imp.crop_mode.set(crop_mode);
+        if !crop_mode {
+            imp.straighten.set(false);
+        }
+        imp.redraw();
+    }
+
+    /// Set the crop to display in the crop tool.
+    pub fn set_crop(&self, crop: Option<CropRect>) {
+        self.imp().crop.set(crop);
+        self.imp().redraw();
+    }
+
+    /// Set the aspect ratio of the crop. The current crop is
+    /// adjusted and `crop-changed` is emitted.
+    pub fn set_aspect_ratio(&self, aspect: AspectRatio) {
+        let imp = self.imp();
+        imp.aspect.set(aspect);
+        imp.constrain_crop();
+    }
+
+    pub fn set_guides(&self, guides: Guides) {
+        self.imp().guides.set(guides);
+        self.imp().redraw();
+    }
+
+    /// In straighten mode, dragging draws a line to level.
+    pub fn set_straighten_mode(&self, straighten: bool) {
+        self.imp().straighten.set(straighten);
+    }
+
+    /// Connect to the signal `crop-changed`, emitted when the crop
+    /// was changed by the user.
+    pub fn connect_crop_changed<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, CropRect) + 'static,
+    {
+        self.connect_closure(
+            "crop-changed",
+            true,
+            glib::closure_local!(move |w, x, y, width, height| {
+                f(
+                    &w,
+                    CropRect {
+                        x,
+                        y,
+                        w: width,
+                        h: height,
+                    },
+                );
+            }),
+        )
+    }
+
+    /// Connect to the signal `pixel-hovered`, emitted with the RGB
+    /// value of the image pixel under the pointer, or `None` when
+    /// leaving the image.
+    pub fn connect_pixel_hovered<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, Option<(u8, u8, u8)>) + 'static,
+    {
+        self.connect_closure(
+            "pixel-hovered",
+            true,
+            glib::closure_local!(move |w, r: i32, g: i32, b: i32| {
+                let rgb = (r >= 0).then_some((r as u8, g as u8, b as u8));
+                f(&w, rgb);
+            }),
+        )
+    }
+
+    /// Connect to the signal `straightened`, emitted with the angle
+    /// in degrees, clockwise, of the line drawn in straighten mode.
+    pub fn connect_straightened<F>(&self, f: F) -> glib::SignalHandlerId
+    where
+        F: Fn(&Self, f64) + 'static,
+    {
+        self.connect_closure(
+            "straightened",
+            true,
+            glib::closure_local!(move |w, angle| {
+                f(&w, angle);
+            }),
+        )
+    }
 }
 
 mod imp {
     use std::cell::{Cell, RefCell};
 
+    use glib::subclass::Signal;
     use gtk4::prelude::*;
     use gtk4::subclass::prelude::*;
     use npc_fwk::{cairo, gdk4, glib, graphene, gtk4};
 
+    use super::super::crop::{self, AspectRatio, Frame, Guides, Handle};
     use super::ZoomMode;
+    use npc_engine::catalog::CropRect;
     use npc_fwk::base::Rect;
-    use npc_fwk::toolkit::ImageBitmap;
-    use npc_fwk::{dbg_out, on_err_out};
+    use npc_fwk::toolkit::{ImageBitmap, clipping_overlay};
+    use npc_fwk::{dbg_out, err_out, on_err_out};
 
     const IMAGE_INSET: f64 = 6.0;
     const SHADOW_OFFSET: f64 = 3.0;
+    /// Size of the crop handles.
+    const HANDLE_SIZE: f64 = 8.0;
 
     lazy_static::lazy_static! {
         static ref ERROR_PLACEHOLDER: gdk4::Texture = gdk4::Texture::from_resource("/net/figuiere/Niepce/pixmaps/niepce-image-generic.png");
@@ -93,6 +201,27 @@ mod imp {
         resized: Cell<bool>,
         zoom_mode: ZoomMode,
         pub(super) image: RefCell<Option<ImageBitmap>>,
+        /// Where the image was drawn.
+        image_frame: Cell<Option<Frame>>,
+        pub(super) crop_mode: Cell<bool>,
+        pub(super) crop: Cell<Option<CropRect>>,
+        pub(super) aspect: Cell<AspectRatio>,
+        pub(super) guides: Cell<Guides>,
+        pub(super) straighten: Cell<bool>,
+        /// The handle being dragged and the crop at the start.
+        drag: Cell<Option<(Handle, Frame)>>,
+        /// The crop being dragged.
+        drag_frame: Cell<Option<Frame>>,
+        /// The straighten line being drawn.
+        straighten_line: Cell<Option<(f64, f64, f64, f64)>>,
+        pub(super) clip_highlights: Cell<bool>,
+        pub(super) clip_shadows: Cell<bool>,
+        /// The RGB pixels of the image, for the clipping and the readout.
+        pixels: RefCell<Option<Vec<u8>>>,
+        /// The clipping overlay texture.
+        pub(super) overlay: RefCell<Option<gdk4::Texture>>,
+        /// The pixel under the pointer.
+        hovered: Cell<Option<(u8, u8, u8)>>,
     }
 
     impl ImageCanvas {
@@ -100,6 +229,225 @@ mod imp {
             self.need_redisplay.set(true);
         }
 
+        pub(super) fn redraw(&self) {
+            self.request_redisplay();
+            self.obj().queue_draw();
+        }
+
+        /// Forget what was computed from the image.
+        pub(super) fn clear_pixels(&self) {
+            self.pixels.replace(None);
+            self.overlay.replace(None);
+        }
+
+        /// Get the RGB pixels of the image, decoding them the first
+        /// time.
+        fn with_pixels<T>(&self, f: impl FnOnce(&[u8]) -> T) -> Option<T> {
+            if self.pixels.borrow().is_none() {
+                let pixels = self.image.borrow().as_ref().and_then(|image| {
+                    image
+                        .rgb_buffer()
+                        .map_err(|err| err_out!("Can't get the image pixels: {err}"))
+                        .ok()
+                        .map(|buffer| buffer.into_owned())
+                });
+                self.pixels.replace(pixels);
+            }
+            self.pixels.borrow().as_deref().map(f)
+        }
+
+        /// The clipping overlay, if any is to be shown.
+        fn overlay_texture(&self) -> Option<gdk4::Texture> {
+            let (highlights, shadows) = (self.clip_highlights.get(), self.clip_shadows.get());
+            if !highlights && !shadows {
+                return None;
+            }
+            if self.overlay.borrow().is_none() {
+                let (w, h) = self
+                    .image
+                    .borrow()
+                    .as_ref()
+                    .map(|image| (image.original_width(), image.original_height()))?;
+                let overlay =
+                    self.with_pixels(|pixels| clipping_overlay(pixels, highlights, shadows))?;
+                let bytes = glib::Bytes::from_owned(overlay);
+                let texture = gdk4::MemoryTexture::new(
+                    w as i32,
+                    h as i32,
+                    gdk4::MemoryFormat::R8g8b8a8,
+                    &bytes,
+                    (w * 4) as usize,
+                );
+                self.overlay.replace(Some(texture.into()));
+            }
+            self.overlay.borrow().clone()
+        }
+
+        /// The pointer moved to `x`, `y`: emit the pixel under.
+        fn on_motion(&self, x: f64, y: f64) {
+            let rgb = self.image_frame.get().and_then(|frame| {
+                let (w, h) = self
+                    .image
+                    .borrow()
+                    .as_ref()
+                    .map(|image| (image.original_width(), image.original_height()))?;
+                if x < frame.left || x >= frame.right || y < frame.top || y >= frame.bottom {
+                    return None;
+                }
+                let px = ((x - frame.left) / frame.width() * w as f64) as usize;
+                let py = ((y - frame.top) / frame.height() * h as f64) as usize;
+                let offset = (py.min(h as usize - 1) * w as usize + px.min(w as usize - 1)) * 3;
+                self.with_pixels(|pixels| {
+                    pixels.get(offset..offset + 3).map(|p| (p[0], p[1], p[2]))
+                })
+                .flatten()
+            });
+            self.set_hovered(rgb);
+        }
+
+        fn set_hovered(&self, rgb: Option<(u8, u8, u8)>) {
+            if self.hovered.replace(rgb) == rgb {
+                return;
+            }
+            let (r, g, b) = rgb
+                .map(|(r, g, b)| (r as i32, g as i32, b as i32))
+                .unwrap_or((-1, -1, -1));
+            self.obj()
+                .emit_by_name::<()>("pixel-hovered", &[&r, &g, &b]);
+        }
+
+        /// The aspect ratio to apply to the crop.
+        fn ratio(&self, image: &Frame) -> Option<f64> {
+            self.aspect.get().ratio(image.width() / image.height())
+        }
+
+        /// The crop frame currently displayed.
+        fn crop_frame(&self, image: &Frame) -> Frame {
+            self.drag_frame
+                .get()
+                .unwrap_or_else(|| image.crop_frame(self.crop.get()))
+        }
+
+        /// Constrain the crop to the aspect ratio.
+        pub(super) fn constrain_crop(&self) {
+            if !self.crop_mode.get() {
+                return;
+            }
+            if let Some(image) = self.image_frame.get() {
+                let frame = crop::constrain(&self.crop_frame(&image), self.ratio(&image), &image);
+                self.set_crop_frame(&image, &frame);
+            }
+            self.redraw();
+        }
+
+        /// Set the crop from `frame` and emit the signal.
+        fn set_crop_frame(&self, image: &Frame, frame: &Frame) {
+            let crop = image.crop_rect(frame);
+            let crop = crop.clamped().filter(|crop| !crop.is_full());
+            self.crop.set(crop);
+            let crop = crop.unwrap_or(CropRect {
+                x: 0.0,
+                y: 0.0,
+                w: 1.0,
+                h: 1.0,
+            });
+            self.obj()
+                .emit_by_name::<()>("crop-changed", &[&crop.x, &crop.y, &crop.w, &crop.h]);
+        }
+
+        fn on_drag_begin(&self, x: f64, y: f64) {
+            if !self.crop_mode.get() {
+                return;
+            }
+            if self.straighten.get() {
+                self.straighten_line.set(Some((x, y, x, y)));
+            } else if let Some(image) = self.image_frame.get() {
+                let frame = self.crop_frame(&image);
+                self.drag
+                    .set(crop::hit_test(&frame, x, y, HANDLE_SIZE).map(|handle| (handle, frame)));
+            }
+        }
+
+        fn on_drag_update(&self, dx: f64, dy: f64) {
+            if let Some((x0, y0, _, _)) = self.straighten_line.get() {
+                self.straighten_line.set(Some((x0, y0, x0 + dx, y0 + dy)));
+                self.redraw();
+            } else if let (Some((handle, start)), Some(image)) =
+                (self.drag.get(), self.image_frame.get())
+            {
+                let frame = crop::drag(&start, handle, dx, dy, &image, self.ratio(&image));
+                self.drag_frame.set(Some(frame));
+                self.redraw();
+            }
+        }
+
+        fn on_drag_end(&self) {
+            if let Some((x0, y0, x1, y1)) = self.straighten_line.take() {
+                if let Some(angle) = crop::straighten_angle(x0, y0, x1, y1) {
+                    self.obj().emit_by_name::<()>("straightened", &[&angle]);
+                }
+                self.redraw();
+            } else if let (Some(frame), Some(image)) =
+                (self.drag_frame.take(), self.image_frame.get())
+            {
+                self.set_crop_frame(&image, &frame);
+                self.redraw();
+            }
+            self.drag.set(None);
+        }
+
+        /// Draw the crop tool over the `image`.
+        fn draw_crop(&self, context: &cairo::Context, image: &Frame) {
+            let frame = self.crop_frame(image);
+
+            // Darken what is cropped out.
+            context.set_fill_rule(cairo::FillRule::EvenOdd);
+            context.rectangle(image.left, image.top, image.width(), image.height());
+            context.rectangle(frame.left, frame.top, frame.width(), frame.height());
+            context.set_source_rgba(0.0, 0.0, 0.0, 0.5);
+            on_err_out!(context.fill());
+            context.set_fill_rule(cairo::FillRule::Winding);
+
+            context.set_line_width(1.0);
+            context.set_source_rgba(1.0, 1.0, 1.0, 0.5);
+            for f in self.guides.get().lines() {
+                let x = frame.left + f * frame.width();
+                context.move_to(x, frame.top);
+                context.line_to(x, frame.bottom);
+                let y = frame.top + f * frame.height();
+                context.move_to(frame.left, y);
+                context.line_to(frame.right, y);
+            }
+            on_err_out!(context.stroke());
+
+            context.set_source_rgb(1.0, 1.0, 1.0);
+            context.rectangle(frame.left, frame.top, frame.width(), frame.height());
+            on_err_out!(context.stroke());
+            let xs = [frame.left, (frame.left + frame.right) / 2.0, frame.right];
+            let ys = [frame.top, (frame.top + frame.bottom) / 2.0, frame.bottom];
+            for (i, x) in xs.iter().enumerate() {
+                for (j, y) in ys.iter().enumerate() {
+                    // Not the centre.
+                    if i != 1 || j != 1 {
+                        context.rectangle(
+                            x - HANDLE_SIZE / 2.0,
+                            y - HANDLE_SIZE / 2.0,
+                            HANDLE_SIZE,
+                            HANDLE_SIZE,
+                        );
+                    }
+                }
+            }
+            on_err_out!(context.fill());
+
+            if let Some((x0, y0, x1, y1)) = self.straighten_line.get() {
+                context.set_source_rgb(1.0, 0.8, 0.0);
+                context.move_to(x0, y0);
+                context.line_to(x1, y1);
+                on_err_out!(context.stroke());
+            }
+        }
+
         fn calc_image_scale(&self, img_w: u32, img_h: u32) -> f64 {
             let obj = self.obj();
             let b_w = obj.width() as f64 - (IMAGE_INSET * 2.0);
@@ -161,10 +509,19 @@ mod imp {
                     let snapshot = gtk4::Snapshot::new();
                     snapshot.translate(&graphene::Point::new(x as f32, y as f32));
                     texture.snapshot(&snapshot, out_w, out_h);
+                    if let Some(overlay) = self.overlay_texture() {
+                        overlay.snapshot(&snapshot, out_w, out_h);
+                    }
                     if let Some(node) = snapshot.to_node() {
                         node.draw(context);
                     }
                 }
+
+                let image_frame = Frame::new(x, y, out_w, out_h);
+                self.image_frame.set(Some(image_frame));
+                if self.crop_mode.get() && self.image.borrow().is_some() {
+                    self.draw_crop(context, &image_frame);
+                }
             }
 
             self.need_redisplay.set(false);
@@ -210,6 +567,68 @@ mod imp {
             obj.connect_resize(|this, _, _| {
                 this.imp().resized.set(true);
             });
+
+            let gesture = gtk4::GestureDrag::new();
+            gesture.set_button(1);
+            gesture.connect_drag_begin(glib::clone!(
+                #[weak]
+                obj,
+                move |_, x, y| obj.imp().on_drag_begin(x, y)
+            ));
+            gesture.connect_drag_update(glib::clone!(
+                #[weak]
+                obj,
+                move |_, dx, dy| obj.imp().on_drag_update(dx, dy)
+            ));
+            gesture.connect_drag_end(glib::clone!(
+                #[weak]
+                obj,
+                move |_, _, _| obj.imp().on_drag_end()
+            ));
+            obj.add_controller(gesture);
+
+            let motion = gtk4::EventControllerMotion::new();
+            motion.connect_motion(glib::clone!(
+                #[weak]
+                obj,
+                move |_, x, y| obj.imp().on_motion(x, y)
+            ));
+            motion.connect_leave(glib::clone!(
+                #[weak]
+                obj,
+                move |_| obj.imp().set_hovered(None)
+            ));
+            obj.add_controller(motion);
+        }
+
+        fn signals() -> &'static [Signal] {
+            use std::sync::LazyLock;
+            static SIGNALS: LazyLock<Vec<Signal>> = LazyLock::new(|| {
+                vec![
+                    Signal::builder("crop-changed")
+                        .param_types([
+                            <f64>::static_type(),
+                            <f64>::static_type(),
+                            <f64>::static_type(),
+                            <f64>::static_type(),
+                        ])
+                        .run_last()
+                        .build(),
+                    Signal::builder("straightened")
+                        .param_types([<f64>::static_type()])
+                        .run_last()
+                        .build(),
+                    Signal::builder("pixel-hovered")
+                        .param_types([
+                            <i32>::static_type(),
+                            <i32>::static_type(),
+                            <i32>::static_type(),
+                        ])
+                        .run_last()
+                        .build(),
+                ]
+            });
+            SIGNALS.as_ref()
         }
     }
 
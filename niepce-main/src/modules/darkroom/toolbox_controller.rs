@@ -1,7 +1,7 @@
 /*
  * niepce - modules/darkroom/toolbox_controller.rs
  *
- * Copyright (C) 2024 Hubert Figuière
+ * Copyright (C) 2024-2025 Hubert Figuière
  *
  * This program is free software: you can redistribute it and/or modify
  * it under the terms of the GNU General Public License as published by
@@ -17,21 +17,45 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use gettextrs::gettext as i18n;
 use gtk4::prelude::*;
-use npc_fwk::gtk4;
+use npc_fwk::{glib, gtk4};
 
+use npc_engine::catalog::DevelopSettings;
 use npc_fwk::toolkit::widgets::EditableHScale;
-use npc_fwk::toolkit::{Controller, ControllerImplCell, UiController};
+use npc_fwk::toolkit::{ComboModel, Controller, ControllerImplCell, UiController};
 
+use super::crop::{AspectRatio, Guides};
 use super::dr_item::DrItem;
 
+/// Messages from the toolbox.
+pub enum ToolboxMsg {
+    /// The develop settings were changed.
+    DevelopChanged(DevelopSettings),
+    /// Toggle the crop tool.
+    CropMode(bool),
+    AspectRatio(AspectRatio),
+    Guides(Guides),
+    /// Toggle straightening in the crop tool.
+    Straighten(bool),
+    /// Remove the crop.
+    ResetCrop,
+}
+
+/// Accessor for a develop setting value.
+type SettingFn = fn(&mut DevelopSettings) -> &mut f64;
+
 pub struct ToolboxController {
-    imp_: ControllerImplCell<(), ()>,
+    imp_: ControllerImplCell<(), ToolboxMsg>,
     _name: &'static str,
     _long_name: String,
     _icon_name: &'static str,
     box_: gtk4::Box,
+    settings: RefCell<DevelopSettings>,
+    scales: RefCell<Vec<(EditableHScale, SettingFn)>>,
 }
 
 impl UiController for ToolboxController {
@@ -42,51 +66,170 @@ impl UiController for ToolboxController {
 
 impl Controller for ToolboxController {
     type InMsg = ();
-    type OutMsg = ();
+    type OutMsg = ToolboxMsg;
 
     npc_fwk::controller_imp_imp!(imp_);
 }
 
 impl ToolboxController {
-    pub fn new() -> ToolboxController {
+    pub fn new() -> Rc<ToolboxController> {
         let box_ = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
+        let ctrl = Rc::new(ToolboxController {
+            imp_: ControllerImplCell::default(),
+            _name: "tools",
+            _long_name: i18n("Develop"),
+            _icon_name: "apply",
+            box_,
+            settings: RefCell::default(),
+            scales: RefCell::default(),
+        });
 
         let item = DrItem::new(&i18n("Crop"));
-        box_.append(&item);
-        let s = EditableHScale::new(-45.0, 45.0, 0.5);
-        item.add_widget(&i18n("Tilt"), &s);
+        ctrl.box_.append(&item);
+        ctrl.add_crop_tools(&item);
+        ctrl.add_scale(&item, &i18n("Tilt"), (-45.0, 45.0, 0.5), |s| &mut s.tilt);
 
         let item = DrItem::new(&i18n("White balance"));
-        box_.append(&item);
-
-        let s = EditableHScale::new(0.0, 100.0, 1.0);
-        item.add_widget(&i18n("Color temperature"), &s);
+        ctrl.box_.append(&item);
+        ctrl.add_scale(
+            &item,
+            &i18n("Color temperature"),
+            (2000.0, 12000.0, 50.0),
+            |s| &mut s.temperature,
+        );
+        ctrl.add_scale(&item, &i18n("Tint"), (-100.0, 100.0, 1.0), |s| &mut s.tint);
 
         let item = DrItem::new(&i18n("Tone and colour"));
-        box_.append(&item);
-        let s = EditableHScale::new(-5.0, 5.0, 0.1);
-        item.add_widget(&i18n("Exposure"), &s);
-        let s = EditableHScale::new(0.0, 100.0, 1.0);
-        item.add_widget(&i18n("Recovery"), &s);
-        let s = EditableHScale::new(0.0, 100.0, 1.0);
-        item.add_widget(&i18n("Fill Light"), &s);
-        let s = EditableHScale::new(0.0, 100.0, 1.0);
-        item.add_widget(&i18n("Blacks"), &s);
-        let s = EditableHScale::new(-100.0, 100.0, 1.0);
-        item.add_widget(&i18n("Brightness"), &s);
-        let s = EditableHScale::new(-100.0, 100.0, 1.0);
-        item.add_widget(&i18n("Contrast"), &s);
-        let s = EditableHScale::new(-100.0, 100.0, 1.0);
-        item.add_widget(&i18n("Saturation"), &s);
-        let s = EditableHScale::new(-100.0, 100.0, 1.0);
-        item.add_widget(&i18n("Vibrance"), &s);
-
-        ToolboxController {
-            imp_: ControllerImplCell::default(),
-            _name: "tools",
-            _long_name: i18n("Develop"),
-            _icon_name: "apply",
-            box_,
+        ctrl.box_.append(&item);
+        ctrl.add_scale(&item, &i18n("Exposure"), (-5.0, 5.0, 0.1), |s| {
+            &mut s.exposure
+        });
+        ctrl.add_scale(&item, &i18n("Recovery"), (0.0, 100.0, 1.0), |s| {
+            &mut s.recovery
+        });
+        ctrl.add_scale(&item, &i18n("Fill Light"), (0.0, 100.0, 1.0), |s| {
+            &mut s.fill_light
+        });
+        ctrl.add_scale(&item, &i18n("Blacks"), (0.0, 100.0, 1.0), |s| &mut s.blacks);
+        ctrl.add_scale(&item, &i18n("Brightness"), (-100.0, 100.0, 1.0), |s| {
+            &mut s.brightness
+        });
+        ctrl.add_scale(&item, &i18n("Contrast"), (-100.0, 100.0, 1.0), |s| {
+            &mut s.contrast
+        });
+        ctrl.add_scale(&item, &i18n("Saturation"), (-100.0, 100.0, 1.0), |s| {
+            &mut s.saturation
+        });
+        ctrl.add_scale(&item, &i18n("Vibrance"), (-100.0, 100.0, 1.0), |s| {
+            &mut s.vibrance
+        });
+
+        ctrl.set_develop_settings(&DevelopSettings::default());
+
+        ctrl
+    }
+
+    /// Add a scale for a setting. `range` is min, max and step.
+    fn add_scale(
+        self: &Rc<Self>,
+        item: &DrItem,
+        label: &str,
+        range: (f64, f64, f64),
+        setting: SettingFn,
+    ) {
+        let s = EditableHScale::new(range.0, range.1, range.2);
+        item.add_widget(label, &s);
+        let weak = Rc::downgrade(self);
+        s.connect_value_changed(move |_, value| {
+            if let Some(ctrl) = weak.upgrade() {
+                let settings = {
+                    let mut settings = ctrl.settings.borrow_mut();
+                    *setting(&mut settings) = value;
+                    settings.clone()
+                };
+                ctrl.emit(ToolboxMsg::DevelopChanged(settings));
+            }
+        });
+        self.scales.borrow_mut().push((s, setting));
+    }
+
+    /// Add the crop tool controls.
+    fn add_crop_tools(self: &Rc<Self>, item: &DrItem) {
+        let crop_button = gtk4::ToggleButton::with_label(&i18n("Crop"));
+        let straighten_button = gtk4::ToggleButton::with_label(&i18n("Straighten"));
+        straighten_button.set_sensitive(false);
+        let weak = Rc::downgrade(self);
+        crop_button.connect_toggled(glib::clone!(
+            #[weak]
+            straighten_button,
+            move |button| {
+                let active = button.is_active();
+                straighten_button.set_sensitive(active);
+                if !active {
+                    straighten_button.set_active(false);
+                }
+                if let Some(ctrl) = weak.upgrade() {
+                    ctrl.emit(ToolboxMsg::CropMode(active));
+                }
+            }
+        ));
+        let weak = Rc::downgrade(self);
+        straighten_button.connect_toggled(move |button| {
+            if let Some(ctrl) = weak.upgrade() {
+                ctrl.emit(ToolboxMsg::Straighten(button.is_active()));
+            }
+        });
+        let reset_button = gtk4::Button::with_label(&i18n("Reset"));
+        let weak = Rc::downgrade(self);
+        reset_button.connect_clicked(move |_| {
+            if let Some(ctrl) = weak.upgrade() {
+                ctrl.emit(ToolboxMsg::ResetCrop);
+            }
+        });
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
+        buttons.append(&crop_button);
+        buttons.append(&straighten_button);
+        buttons.append(&reset_button);
+        item.add_widget("", &buttons);
+
+        let aspect_combo = gtk4::DropDown::default();
+        let aspect_model = ComboModel::with_map(&[
+            (&i18n("Custom"), AspectRatio::Custom),
+            (&i18n("Original"), AspectRatio::Original),
+            ("1:1", AspectRatio::Square),
+            ("3:2", AspectRatio::R3x2),
+            ("4:5", AspectRatio::R4x5),
+            ("16:9", AspectRatio::R16x9),
+        ]);
+        let weak = Rc::downgrade(self);
+        aspect_model.bind(&aspect_combo, move |aspect| {
+            if let Some(ctrl) = weak.upgrade() {
+                ctrl.emit(ToolboxMsg::AspectRatio(*aspect));
+            }
+        });
+        item.add_widget(&i18n("Aspect"), &aspect_combo);
+
+        let guides_combo = gtk4::DropDown::default();
+        let guides_model = ComboModel::with_map(&[
+            (&i18n("Rule of thirds"), Guides::Thirds),
+            (&i18n("Golden ratio"), Guides::GoldenRatio),
+            (&i18n("None"), Guides::None),
+        ]);
+        let weak = Rc::downgrade(self);
+        guides_model.bind(&guides_combo, move |guides| {
+            if let Some(ctrl) = weak.upgrade() {
+                ctrl.emit(ToolboxMsg::Guides(*guides));
+            }
+        });
+        item.add_widget(&i18n("Guides"), &guides_combo);
+    }
+
+    /// Set the settings to display. Doesn't emit.
+    pub fn set_develop_settings(&self, settings: &DevelopSettings) {
+        let mut current = self.settings.borrow_mut();
+        *current = settings.clone();
+        for (scale, setting) in self.scales.borrow().iter() {
+            scale.set_value(*setting(&mut current));
         }
     }
 }
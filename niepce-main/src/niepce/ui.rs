@@ -25,6 +25,7 @@ pub mod image_grid_view;
 mod image_list_item;
 pub mod image_list_store;
 pub mod imagetoolbar;
+mod import_progress;
 pub mod library_cell_renderer;
 pub mod library_module;
 pub mod metadata_pane_controller;
@@ -43,6 +44,7 @@ pub use film_strip_controller::FilmStripController;
 pub use grid_view_module::GridViewModule;
 pub use image_grid_view::ImageGridView;
 pub use image_list_store::ImageListStore;
+pub use import_progress::ImportProgressPanel;
 pub use library_module::LibraryModule;
 pub use metadata_pane_controller::MetadataPaneController;
 pub use module_shell_widget::ModuleShellWidget;
@@ -31,4 +31,8 @@ pub enum ContentView {
     Album(LibraryId),
     /// Keyword with id
     Keyword(LibraryId),
+    /// Smart collection with id
+    SmartCollection(LibraryId),
+    /// Project with id
+    Project(LibraryId),
 }
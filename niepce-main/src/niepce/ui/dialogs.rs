@@ -20,8 +20,14 @@
 mod edit_labels;
 mod import;
 mod importlibrary;
+pub mod journal_history;
 pub mod preferences_dialog;
+mod smart_collection;
+mod watched_folders;
 
 pub use edit_labels::EditLabels;
 pub use import::ImportDialog;
 pub use importlibrary::ImportLibraryDialog;
+pub use journal_history::JournalHistoryDialog;
+pub use smart_collection::SmartCollectionDialog;
+pub use watched_folders::WatchedFoldersDialog;
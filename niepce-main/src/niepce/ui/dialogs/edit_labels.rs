@@ -19,25 +19,17 @@
 
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::{Arc, Weak};
+use std::sync::Arc;
 
 use adw::prelude::*;
-use gettextrs::gettext as i18n;
 use npc_fwk::{adw, glib, gtk4};
 
 use npc_engine::catalog;
-use npc_engine::libraryclient::{
-    ClientInterface, ClientInterfaceSync, LibraryClient, LibraryClientHost,
-};
+use npc_engine::libraryclient::{ClientInterface, LibraryClient, LibraryClientHost};
 use npc_fwk::base::RgbColour;
-use npc_fwk::toolkit::{
-    AppController, Controller, ControllerImplCell, DialogController, Storage, UiController,
-    UndoCommand, UndoTransaction,
-};
+use npc_fwk::toolkit::{Controller, ControllerImplCell, DialogController, UiController};
 use npc_fwk::{controller_imp_imp, send_async_local};
 
-use crate::NiepceApplication;
-
 const NUM_LABELS: usize = 5;
 
 pub enum InMsg {
@@ -49,7 +41,6 @@ pub enum InMsg {
 pub struct EditLabels {
     imp_: ControllerImplCell<InMsg, ()>,
     client: Arc<LibraryClient>,
-    app: Weak<NiepceApplication>,
     labels: Vec<catalog::Label>,
     colours: Vec<gtk4::ColorDialogButton>,
     entries: Vec<gtk4::Entry>,
@@ -87,7 +78,7 @@ impl DialogController for EditLabels {
 }
 
 impl EditLabels {
-    pub fn new(client: &Rc<LibraryClientHost>, app: Weak<NiepceApplication>) -> Rc<EditLabels> {
+    pub fn new(client: &Rc<LibraryClientHost>) -> Rc<EditLabels> {
         let builder = gtk4::Builder::from_resource("/net/figuiere/Niepce/ui/editlabels.ui");
         let provider = client.ui_provider();
         let mut labels = vec![];
@@ -102,7 +93,6 @@ impl EditLabels {
         let mut ctrl = EditLabels {
             imp_: ControllerImplCell::default(),
             client: client.client().clone(),
-            app,
             labels,
             entries: vec![],
             colours: vec![],
@@ -159,55 +149,15 @@ impl EditLabels {
     }
 
     fn update_labels(&self) {
-        let mut undo = UndoTransaction::new(&i18n("Change Labels"));
         let statuses = self.status.borrow();
         for status in statuses.iter().enumerate() {
             if !status.1 {
                 continue;
             }
-            let update = true;
             let new_name = self.entries[status.0].text().to_string();
             let new_colour: RgbColour = self.colours[status.0].rgba().into();
-            let current_name = self.labels[status.0].label().to_string();
-            let current_colour: RgbColour = self.colours[status.0].rgba().into();
             let label_id = self.labels[status.0].id();
-
-            let client_undo = self.client.clone();
-            let client_redo = self.client.clone();
-
-            let command = if update {
-                UndoCommand::new(
-                    Box::new(move || {
-                        client_redo.update_label(label_id, new_name.clone(), new_colour.clone());
-                        Storage::Void
-                    }),
-                    Box::new(move |_| {
-                        client_undo.update_label(
-                            label_id,
-                            current_name.clone(),
-                            current_colour.clone(),
-                        );
-                    }),
-                )
-            } else {
-                UndoCommand::new(
-                    Box::new(move || {
-                        client_redo
-                            .create_label_sync(new_name.clone(), new_colour.clone())
-                            .into()
-                    }),
-                    Box::new(move |label| {
-                        client_undo.delete_label(label.into());
-                    }),
-                )
-            };
-            undo.add(command);
-        }
-        if !undo.is_empty() {
-            undo.execute();
-            if let Some(app) = Weak::upgrade(&self.app) {
-                app.begin_undo(undo);
-            }
+            self.client.update_label(label_id, new_name, new_colour);
         }
     }
 }
@@ -29,9 +29,10 @@ use directory_importer_ui::DirectoryImporterUI;
 use importer_ui::{ImporterMsg, ImporterUI};
 
 use std::cell::{OnceCell, RefCell};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use gettextrs::gettext as i18n;
@@ -41,22 +42,38 @@ use i18n_format::i18n_format;
 use npc_fwk::{adw, gdk4, gio, glib, gtk4};
 use num_traits::ToPrimitive;
 
+use crate::niepce::ui::metadata_pane_controller::{self, MetadataOutputMsg};
 use crate::niepce::ui::{ImageGridView, MetadataPaneController};
 use dest_folders::DestFoldersIn;
-use npc_engine::importer::{DatePathFormat, ImportBackend, ImportRequest, ImportedFile, Importer};
-use npc_engine::libraryclient::LibraryClient;
+use npc_engine::NiepcePropertyBag;
+use npc_engine::catalog::filebundle::FileBundle;
+use npc_engine::catalog::{
+    Label, LibraryId, MetadataPreset, NiepceProperties as Np, NiepcePropertyIdx, StackingOptions,
+};
+use npc_engine::importer::{
+    BundleRenamer, DatePathFormat, ImportBackend, ImportRequest, ImportSelection, ImportedFile,
+    Importer, RenameTemplate,
+};
+use npc_engine::libraryclient::{ClientInterface, LibraryClient};
+use npc_fwk::PropertyValue;
 use npc_fwk::base::Executor;
+use npc_fwk::toolkit::widgets::MetadataPropertyBag;
 use npc_fwk::toolkit::{
     self, Controller, ControllerImplCell, DialogController, ListViewRow, Receiver, Sender,
     Thumbnail, UiController,
 };
 use npc_fwk::utils::normalize_for_display;
 use npc_fwk::{
-    Date, controller_imp_imp, dbg_out, err_out, send_async_any, sending_action, trace_out,
+    Date, DateExt, controller_imp_imp, dbg_out, err_out, send_async_any, sending_action, trace_out,
 };
 use thumb_item::ThumbItem;
 use thumb_item_row::ThumbItemRow;
 
+/// Default time between shots of a burst, in seconds.
+const STACK_TIME_GAP: i64 = 2;
+/// The format of the day to select by date.
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
 pub enum Event {
     /// Set Source `source` and `copy`
     SetSource(Option<String>, bool),
@@ -69,7 +86,45 @@ pub enum Event {
     DestChanged(PathBuf),
     /// The `DatePathFormat` has been changed.
     SetDatePathFormat(DatePathFormat),
-    PreviewReceived(String, Option<Thumbnail>, Option<Date>),
+    /// Toggle stacking the bursts.
+    StackBurstsToggled(bool),
+    /// Toggle stacking the exposure brackets.
+    StackBracketsToggled(bool),
+    /// Toggle importing the files already in the catalog.
+    ImportDuplicatesToggled(bool),
+    /// The files already in the catalog were found.
+    DuplicatesFound(Vec<PathBuf>),
+    /// The metadata presets were loaded.
+    PresetsLoaded(Vec<MetadataPreset>),
+    /// The metadata preset with id was selected. 0 for none.
+    PresetSelected(LibraryId),
+    /// The preset metadata were edited. The new values.
+    PresetMetadataChanged(MetadataPropertyBag),
+    /// The labels were loaded.
+    LabelsLoaded(Vec<Label>),
+    /// The preset label was changed. 0 for none.
+    PresetLabelChanged(LibraryId),
+    /// Request the name to save the preset.
+    SavePreset,
+    /// Save the preset with the name.
+    SavePresetAs(String),
+    DeletePreset,
+    /// Set the template to rename the files.
+    SetRenameTemplate(String),
+    /// Toggle the backup of the files copied.
+    BackupToggled(bool),
+    /// Request the backup destination.
+    ChooseBackupDir,
+    /// The backup destination was chosen.
+    BackupDirChanged(PathBuf),
+    /// The files selected in the grid changed.
+    SelectionChanged,
+    SelectAll,
+    SelectNone,
+    /// Select or unselect the files of the day.
+    SelectDate(String, bool),
+    /// Preview received for path, with the date and the camera model.
+    PreviewReceived(String, Option<Thumbnail>, Option<Date>, Option<String>),
     PreviewsDone,
     AppendFiles(Vec<Box<dyn ImportedFile>>),
     Cancel,
@@ -83,8 +138,18 @@ struct Widgets {
     dest_folders: Rc<dest_folders::DestFolders>,
     destination_help: gtk4::Label,
     images_list_model: gio::ListStore,
+    selection_model: gtk4::MultiSelection,
     image_count: gtk4::Label,
+    date_toggles: gtk4::FlowBox,
     preview_spinner: gtk4::Spinner,
+    rename_entry: gtk4::Entry,
+    backup_check: gtk4::CheckButton,
+    backup_label: gtk4::Label,
+    preset_combo: gtk4::DropDown,
+    preset_combo_model: Rc<toolkit::ComboModel<LibraryId>>,
+    preset_label_combo: gtk4::DropDown,
+    preset_label_combo_model: Rc<toolkit::ComboModel<LibraryId>>,
+    metadata_pane: Rc<MetadataPaneController>,
 
     importers: HashMap<String, Rc<dyn ImporterUI>>,
     current_importer: RefCell<Option<Rc<dyn ImporterUI>>>,
@@ -121,6 +186,8 @@ impl Widgets {
 
     fn clear_import_list(&self) {
         self.images_list_model.remove_all();
+        self.date_toggles.remove_all();
+        self.date_toggles.set_visible(false);
         //
         self.dest_folders.send(DestFoldersIn::Clear);
     }
@@ -151,6 +218,20 @@ struct State {
     copy: bool,
     sorting_disabled: bool,
     sorting_format: DatePathFormat,
+    stacking: StackingOptions,
+    /// The template to rename the files. Only for copy.
+    rename: Option<RenameTemplate>,
+    /// Whether to backup the files copied.
+    backup: bool,
+    backup_dir: Option<PathBuf>,
+    /// The files already in the catalog.
+    duplicates: Vec<PathBuf>,
+    import_duplicates: bool,
+    presets: Vec<MetadataPreset>,
+    /// The metadata to apply. Either a preset as selected, or edited.
+    preset: Option<MetadataPreset>,
+    /// The toggles to select by day.
+    date_toggles: BTreeMap<String, gtk4::ToggleButton>,
 }
 
 pub struct ImportDialog {
@@ -186,18 +267,50 @@ impl Controller for ImportDialog {
                 }
                 self.set_sorting_format(f)
             }
-            Event::PreviewReceived(path, thumbnail, date) => {
+            Event::StackBurstsToggled(toggle) => self.stack_bursts_toggled(toggle),
+            Event::StackBracketsToggled(toggle) => self.stack_brackets_toggled(toggle),
+            Event::ImportDuplicatesToggled(toggle) => self.import_duplicates_toggled(toggle),
+            Event::DuplicatesFound(duplicates) => self.duplicates_found(duplicates),
+            Event::PresetsLoaded(presets) => self.presets_loaded(presets),
+            Event::PresetSelected(id) => self.preset_selected(id),
+            Event::PresetMetadataChanged(properties) => self.preset_metadata_changed(&properties),
+            Event::LabelsLoaded(labels) => self.labels_loaded(&labels),
+            Event::PresetLabelChanged(id) => self.preset_label_changed(id),
+            Event::SavePreset => self.save_preset(),
+            Event::SavePresetAs(name) => self.save_preset_as(&name),
+            Event::DeletePreset => self.delete_preset(),
+            Event::SetRenameTemplate(template) => self.set_rename_template(&template),
+            Event::BackupToggled(toggle) => self.backup_toggled(toggle),
+            Event::ChooseBackupDir => self.choose_backup_dir(),
+            Event::BackupDirChanged(dir) => self.backup_dir_changed(dir),
+            Event::SelectionChanged => {
+                self.update_import_count();
+                self.update_dest_names();
+            }
+            Event::SelectAll => {
+                if let Some(widgets) = self.widgets.get() {
+                    widgets.selection_model.select_all();
+                }
+            }
+            Event::SelectNone => {
+                if let Some(widgets) = self.widgets.get() {
+                    widgets.selection_model.unselect_all();
+                }
+            }
+            Event::SelectDate(day, select) => self.select_date(&day, select),
+            Event::PreviewReceived(path, thumbnail, date, camera) => {
                 if let Some(widgets) = self.widgets.get() {
                     widgets
                         .dest_folders
                         .send(DestFoldersIn::PreviewReceived(date));
                 }
-                self.preview_received(&path, thumbnail, date)
+                self.preview_received(&path, thumbnail, date, camera)
             }
             Event::PreviewsDone => {
                 if let Some(widgets) = self.widgets.get() {
                     widgets.preview_spinner.stop();
                 }
+                self.update_dest_names();
             }
             Event::AppendFiles(files) => self.append_files_to_import(&files),
             Event::Cancel => self.close(),
@@ -232,6 +345,21 @@ impl DialogController for ImportDialog {
                 let sender = self.sender();
                 sending_action!(self.action_group, "Cancel", sender, Event::Cancel);
                 sending_action!(self.action_group, "Import", sender, Event::Import);
+                sending_action!(self.action_group, "SavePreset", sender, Event::SavePreset);
+                sending_action!(
+                    self.action_group,
+                    "DeletePreset",
+                    sender,
+                    Event::DeletePreset
+                );
+                sending_action!(
+                    self.action_group,
+                    "ChooseBackupDir",
+                    sender,
+                    Event::ChooseBackupDir
+                );
+                sending_action!(self.action_group, "SelectAll", sender, Event::SelectAll);
+                sending_action!(self.action_group, "SelectNone", sender, Event::SelectNone);
                 get_widget!(builder, gtk4::ListView, destination_folders);
                 let dest_folders = dest_folders::DestFolders::new(
                     self.client.clone(),
@@ -240,6 +368,7 @@ impl DialogController for ImportDialog {
                 );
                 get_widget!(builder, gtk4::Label, image_count);
                 get_widget!(builder, gtk4::Spinner, preview_spinner);
+                get_widget!(builder, gtk4::FlowBox, date_toggles);
                 let sender = self.sender();
                 dest_folders.set_forwarder(Some(Box::new(glib::clone!(move |event| {
                     use dest_folders::DestFoldersOut::*;
@@ -288,20 +417,93 @@ impl DialogController for ImportDialog {
                     date_sorting_combo.set_selected(sorting);
                 }
 
+                get_widget!(builder, gtk4::Entry, rename_entry);
+                let sender = self.sender();
+                rename_entry.connect_changed(move |entry| {
+                    send_async_any!(Event::SetRenameTemplate(entry.text().to_string()), sender);
+                });
+                rename_entry.set_text(&self.cfg.value("import_rename_template", ""));
+
+                get_widget!(builder, gtk4::CheckButton, backup_check);
+                get_widget!(builder, gtk4::Label, backup_label);
+                if let Some(ref backup_dir) = self.state.borrow().backup_dir {
+                    backup_label.set_label(&backup_dir.to_string_lossy());
+                }
+                let sender = self.sender();
+                backup_check.connect_toggled(move |check| {
+                    let is_active = check.is_active();
+                    send_async_any!(Event::BackupToggled(is_active), sender);
+                });
+                backup_check.set_active(
+                    bool::from_str(&self.cfg.value("import_backup", "false")).unwrap_or(false),
+                );
+
+                get_widget!(builder, gtk4::CheckButton, stack_bursts_check);
+                get_widget!(builder, gtk4::CheckButton, stack_brackets_check);
+                let sender = self.sender();
+                stack_bursts_check.connect_toggled(glib::clone!(
+                    #[strong]
+                    sender,
+                    move |check| {
+                        let is_active = check.is_active();
+                        send_async_any!(Event::StackBurstsToggled(is_active), sender);
+                    }
+                ));
+                stack_bursts_check.set_active(
+                    bool::from_str(&self.cfg.value("import_stack_bursts", "false"))
+                        .unwrap_or(false),
+                );
+                stack_brackets_check.connect_toggled(move |check| {
+                    let is_active = check.is_active();
+                    send_async_any!(Event::StackBracketsToggled(is_active), sender);
+                });
+                stack_brackets_check.set_active(
+                    bool::from_str(&self.cfg.value("import_stack_brackets", "false"))
+                        .unwrap_or(false),
+                );
+
+                get_widget!(builder, gtk4::CheckButton, import_duplicates_check);
+                let sender = self.sender();
+                import_duplicates_check.connect_toggled(move |check| {
+                    let is_active = check.is_active();
+                    send_async_any!(Event::ImportDuplicatesToggled(is_active), sender);
+                });
+
                 get_widget!(builder, gtk4::DropDown, preset_combo);
-                let string_list = toolkit::ComboModel::with_map(&[(&i18n("No preset"), "NONE")]);
-                string_list.bind(&preset_combo, |_| {});
+                let preset_combo_model = toolkit::ComboModel::with_map(&[(&i18n("No preset"), 0)]);
+                let sender = self.sender();
+                preset_combo_model.bind(&preset_combo, move |id| {
+                    send_async_any!(Event::PresetSelected(*id), sender);
+                });
+                get_widget!(builder, gtk4::DropDown, preset_label_combo);
+                let preset_label_combo_model =
+                    toolkit::ComboModel::with_map(&[(&i18n("No label"), 0)]);
+                let sender = self.sender();
+                preset_label_combo_model.bind(&preset_label_combo, move |id| {
+                    send_async_any!(Event::PresetLabelChanged(*id), sender);
+                });
 
                 get_widget!(builder, gtk4::ScrolledWindow, attributes_scrolled);
-                let metadata_pane = MetadataPaneController::new();
-                let w = metadata_pane.widget();
-                // add
-                attributes_scrolled.set_child(Some(w));
+                let metadata_pane = MetadataPaneController::with_format(
+                    metadata_pane_controller::get_preset_format(),
+                );
+                let sender = self.sender();
+                metadata_pane.set_forwarder(Some(Box::new(move |msg| match msg {
+                    MetadataOutputMsg::MetadataChanged(new, _) => {
+                        send_async_any!(Event::PresetMetadataChanged(new), sender);
+                    }
+                })));
+                attributes_scrolled.set_child(Some(metadata_pane.widget()));
 
                 get_widget!(builder, gtk4::ScrolledWindow, images_list_scrolled);
                 let images_list_model = gio::ListStore::new::<ThumbItem>();
-                let selection_model = gtk4::SingleSelection::new(Some(images_list_model.clone()));
-                let image_gridview = ImageGridView::new(selection_model, None, None);
+                // The files selected are imported.
+                let selection_model = gtk4::MultiSelection::new(Some(images_list_model.clone()));
+                let sender = self.sender();
+                selection_model.connect_selection_changed(move |_, _, _| {
+                    send_async_any!(Event::SelectionChanged, sender);
+                });
+                let image_gridview = ImageGridView::new(selection_model.clone(), None, None);
                 let factory = gtk4::SignalListItemFactory::new();
                 image_gridview.set_factory(Some(&factory));
                 factory.connect_setup(move |_, item| {
@@ -341,8 +543,18 @@ impl DialogController for ImportDialog {
                     dest_folders,
                     destination_help,
                     images_list_model,
+                    selection_model,
                     image_count,
+                    date_toggles,
                     preview_spinner,
+                    rename_entry,
+                    backup_check,
+                    backup_label,
+                    preset_combo,
+                    preset_combo_model,
+                    preset_label_combo,
+                    preset_label_combo_model,
+                    metadata_pane,
                     importers: HashMap::new(),
                     current_importer: RefCell::new(None),
                     importer_tx,
@@ -360,6 +572,12 @@ impl DialogController for ImportDialog {
                     import_source_combo.set_selected(selected as u32);
                 }
 
+                let sender = self.sender();
+                self.client.get_all_labels(Some(Box::new(move |labels| {
+                    send_async_any!(Event::LabelsLoaded(labels), sender);
+                })));
+                self.load_presets();
+
                 widgets
             })
             .dialog
@@ -374,6 +592,7 @@ impl ImportDialog {
                 .map(PathBuf::from)
                 .or_else(|| glib::user_special_dir(glib::UserDirectory::Pictures))
                 .unwrap_or_else(glib::home_dir),
+            backup_dir: cfg.value_opt("import_backup_dir").map(PathBuf::from),
             ..Default::default()
         };
         dbg_out!("base import dest dir {:?}", state.copy_dest_dir);
@@ -417,15 +636,59 @@ impl ImportDialog {
                     importer.backend(),
                 )
                 .set_sorting(self.sorting_format())
+                .set_stacking(self.state.borrow().stacking.clone())
+                .set_rename_template(self.state.borrow().rename.clone())
+                .set_backup_dir(self.backup_dir())
+                .set_skipped_files(self.skipped_files())
+                .set_selected_files(self.selected_files())
+                .set_metadata_preset(
+                    self.state
+                        .borrow()
+                        .preset
+                        .clone()
+                        .filter(|preset| !preset.is_empty()),
+                )
             })
     }
 
     fn update_import_count(&self) {
         if let Some(widgets) = self.widgets.get() {
             let import_count = self.state.borrow().import_count;
-            widgets
-                .image_count
-                .set_label(&i18n_format!("{} _Images to import", import_count));
+            let selected = self.selected_files();
+            let selected_count = selected
+                .as_ref()
+                .map(|selected| selected.len())
+                .unwrap_or(import_count);
+            let skipped = self
+                .skipped_files()
+                .iter()
+                .filter(|path| {
+                    self.images_list_map
+                        .borrow()
+                        .contains_key(&*path.to_string_lossy())
+                        && selected
+                            .as_ref()
+                            .is_none_or(|selected| selected.contains(*path))
+                })
+                .count();
+            let label = if skipped > 0 {
+                i18n_format!(
+                    "{} _Images to import, {} already in the catalog",
+                    selected_count - skipped,
+                    skipped
+                )
+            } else {
+                i18n_format!("{} _Images to import", selected_count)
+            };
+            if selected_count < import_count {
+                widgets.image_count.set_label(&i18n_format!(
+                    "{} ({} not selected)",
+                    label,
+                    import_count - selected_count
+                ));
+            } else {
+                widgets.image_count.set_label(&label);
+            }
         }
     }
 
@@ -435,9 +698,120 @@ impl ImportDialog {
         }
         self.images_list_map.borrow_mut().clear();
         self.state.borrow_mut().import_count = 0;
+        self.state.borrow_mut().duplicates.clear();
+        self.state.borrow_mut().date_toggles.clear();
         self.update_import_count();
     }
 
+    /// The files selected in the grid. `None` if all of them are.
+    fn selected_files(&self) -> Option<Vec<PathBuf>> {
+        let widgets = self.widgets.get()?;
+        let images_list_map = self.images_list_map.borrow();
+        let selected = images_list_map
+            .iter()
+            .filter(|(_, entry)| widgets.selection_model.is_selected(entry.idx))
+            .map(|(path, _)| PathBuf::from(path))
+            .collect::<Vec<_>>();
+        if selected.len() == images_list_map.len() {
+            None
+        } else {
+            Some(selected)
+        }
+    }
+
+    /// The selection of files to import.
+    fn selection(&self) -> ImportSelection {
+        ImportSelection::new(self.selected_files(), self.skipped_files())
+    }
+
+    /// Select the files of the `day` if `select`, otherwise unselect
+    /// them.
+    fn select_date(&self, day: &str, select: bool) {
+        let Some(widgets) = self.widgets.get() else {
+            return;
+        };
+        for idx in 0..widgets.images_list_model.n_items() {
+            let is_day = widgets
+                .images_list_model
+                .item(idx)
+                .and_downcast::<ThumbItem>()
+                .and_then(|item| item.date())
+                .is_some_and(|date| date.format(DAY_FORMAT).to_string() == day);
+            if !is_day {
+                continue;
+            }
+            if select {
+                widgets.selection_model.select_item(idx, false);
+            } else {
+                widgets.selection_model.unselect_item(idx);
+            }
+        }
+    }
+
+    /// Add the toggle to select the files of the day of `date`, if
+    /// needed.
+    fn add_date_toggle(&self, date: &Date) {
+        let Some(widgets) = self.widgets.get() else {
+            return;
+        };
+        let day = date.format(DAY_FORMAT).to_string();
+        let mut state = self.state.borrow_mut();
+        if state.date_toggles.contains_key(&day) {
+            return;
+        }
+        let toggle = gtk4::ToggleButton::with_label(&day);
+        toggle.set_active(true);
+        toggle.set_tooltip_text(Some(&i18n_format!("Select the images of {}", day)));
+        let sender = self.sender();
+        toggle.connect_toggled(glib::clone!(
+            #[strong]
+            day,
+            move |toggle| {
+                send_async_any!(Event::SelectDate(day.clone(), toggle.is_active()), sender);
+            }
+        ));
+        // Keep the days in order.
+        let position = state.date_toggles.range(..day.clone()).count();
+        widgets.date_toggles.insert(&toggle, position as i32);
+        widgets.date_toggles.set_visible(true);
+        state.date_toggles.insert(day, toggle);
+    }
+
+    /// The files not to import: the duplicates, unless requested.
+    fn skipped_files(&self) -> Vec<PathBuf> {
+        let state = self.state.borrow();
+        if state.import_duplicates {
+            vec![]
+        } else {
+            state.duplicates.clone()
+        }
+    }
+
+    fn import_duplicates_toggled(&self, toggle: bool) {
+        self.state.borrow_mut().import_duplicates = toggle;
+        self.update_import_count();
+        self.update_dest_names();
+    }
+
+    /// The files already in the catalog were found. Mark them.
+    fn duplicates_found(&self, duplicates: Vec<PathBuf>) {
+        if let Some(widgets) = self.widgets.get() {
+            let images_list_map = self.images_list_map.borrow();
+            for path in &duplicates {
+                if let Some(item) = images_list_map
+                    .get(&*path.to_string_lossy())
+                    .and_then(|entry| widgets.images_list_model.item(entry.idx))
+                    .and_downcast::<ThumbItem>()
+                {
+                    item.set_duplicate(true);
+                }
+            }
+        }
+        self.state.borrow_mut().duplicates.extend(duplicates);
+        self.update_import_count();
+        self.update_dest_names();
+    }
+
     /// The import source change: dir or camera.
     fn import_source_changed(&self, source: &str) {
         if let Some(widgets) = self.widgets.get() {
@@ -513,7 +887,10 @@ impl ImportDialog {
             widgets
                 .dest_folders
                 .send(DestFoldersIn::SortingChanged(self.sorting_format()));
+            widgets.rename_entry.set_sensitive(copy);
+            widgets.backup_check.set_sensitive(copy);
         });
+        self.update_dest_names();
     }
 
     fn handle_dest_changed(&self, dest_dir: Option<PathBuf>) {
@@ -545,6 +922,151 @@ impl ImportDialog {
             }
         }
         self.state.borrow_mut().full_dest_dir = dest_dir;
+        self.update_dest_names();
+    }
+
+    /// The backup destination, if the files copied are to be backed up.
+    fn backup_dir(&self) -> Option<PathBuf> {
+        let state = self.state.borrow();
+        state
+            .backup_dir
+            .clone()
+            .filter(|_| state.copy && state.backup)
+    }
+
+    fn backup_toggled(&self, toggle: bool) {
+        self.cfg.set_value("import_backup", &toggle.to_string());
+        self.state.borrow_mut().backup = toggle;
+        if toggle && self.state.borrow().backup_dir.is_none() {
+            self.choose_backup_dir();
+        }
+    }
+
+    fn choose_backup_dir(&self) {
+        #[allow(deprecated)]
+        let dialog = gtk4::FileChooserDialog::new(
+            Some(&i18n("Backup Destination")),
+            Some(self.dialog().upcast_ref::<gtk4::Window>()),
+            gtk4::FileChooserAction::SelectFolder,
+            &[
+                (&i18n("Select"), gtk4::ResponseType::Accept),
+                (&i18n("Cancel"), gtk4::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        let sender = self.sender();
+        #[allow(deprecated)]
+        dialog.connect_response(move |d, response| {
+            if response == gtk4::ResponseType::Accept {
+                #[allow(deprecated)]
+                if let Some(path) = d.file().as_ref().and_then(gio::prelude::FileExt::path) {
+                    send_async_any!(Event::BackupDirChanged(path), sender);
+                }
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+
+    fn backup_dir_changed(&self, dir: PathBuf) {
+        self.cfg
+            .set_value("import_backup_dir", &dir.to_string_lossy());
+        if let Some(widgets) = self.widgets.get() {
+            widgets.backup_label.set_label(&dir.to_string_lossy());
+        }
+        self.state.borrow_mut().backup_dir = Some(dir);
+    }
+
+    /// Set the template to rename the files. An invalid template is
+    /// flagged and ignored.
+    fn set_rename_template(&self, template: &str) {
+        let rename = if template.is_empty() {
+            Ok(None)
+        } else {
+            template.parse::<RenameTemplate>().map(Some)
+        };
+        if let Some(widgets) = self.widgets.get() {
+            match rename {
+                Err(ref err) => {
+                    widgets.rename_entry.add_css_class("error");
+                    widgets
+                        .rename_entry
+                        .set_tooltip_text(Some(&err.to_string()));
+                }
+                Ok(_) => {
+                    widgets.rename_entry.remove_css_class("error");
+                    widgets.rename_entry.set_tooltip_text(None);
+                }
+            }
+        }
+        if let Ok(rename) = rename {
+            self.cfg.set_value("import_rename_template", template);
+            self.state.borrow_mut().rename = rename;
+            self.update_dest_names();
+        }
+    }
+
+    /// Update the names displayed in the grid to the destination
+    /// names, if renaming.
+    fn update_dest_names(&self) {
+        let Some(widgets) = self.widgets.get() else {
+            return;
+        };
+        let state = self.state.borrow();
+        let rename = state.rename.as_ref().filter(|_| state.copy);
+        let images_list_map = self.images_list_map.borrow();
+        let item = |path: &Path| {
+            images_list_map
+                .get(&*path.to_string_lossy())
+                .and_then(|entry| widgets.images_list_model.item(entry.idx))
+                .and_downcast::<ThumbItem>()
+        };
+        let Some(rename) = rename else {
+            images_list_map
+                .keys()
+                .filter_map(|path| item(Path::new(path)))
+                .for_each(|item| item.set_dest_name(None));
+            return;
+        };
+
+        // Same as the import: the files are bundled in order.
+        let paths = images_list_map
+            .keys()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        let selection = self.selection();
+        let mut renamer = BundleRenamer::new(rename);
+        for bundle in FileBundle::filter_bundles(&paths) {
+            if !selection.includes(bundle.main()) {
+                bundle
+                    .all_files()
+                    .iter()
+                    .filter_map(|file| item(file))
+                    .for_each(|item| item.set_dest_name(None));
+                continue;
+            }
+            let main = item(bundle.main());
+            let date = main
+                .as_ref()
+                .and_then(|item| item.date())
+                .or_else(|| Some(Date::now()));
+            let camera = main.as_ref().and_then(|item| item.camera());
+            let dest_dir = images_list_map
+                .get(&*bundle.main().to_string_lossy())
+                .and_then(|entry| entry.dest.clone())
+                .or_else(|| state.full_dest_dir.clone())
+                .unwrap_or_default();
+            for (file, dest) in renamer.rename(&bundle, &dest_dir, date.as_ref(), camera.as_deref())
+            {
+                if let Some(item) = item(&file) {
+                    item.set_dest_name(
+                        dest.file_name()
+                            .map(|name| name.to_string_lossy())
+                            .as_deref(),
+                    );
+                }
+            }
+        }
     }
 
     /// Return the active sorting format. If sorting is disabled
@@ -558,6 +1080,192 @@ impl ImportDialog {
         }
     }
 
+    fn stack_bursts_toggled(&self, toggle: bool) {
+        self.cfg
+            .set_value("import_stack_bursts", &toggle.to_string());
+        self.state.borrow_mut().stacking.time_gap = if toggle {
+            Some(
+                self.cfg
+                    .value("import_stack_time_gap", "2")
+                    .parse()
+                    .unwrap_or(STACK_TIME_GAP),
+            )
+        } else {
+            None
+        };
+    }
+
+    fn stack_brackets_toggled(&self, toggle: bool) {
+        self.cfg
+            .set_value("import_stack_brackets", &toggle.to_string());
+        self.state.borrow_mut().stacking.brackets = toggle;
+    }
+
+    /// Request the metadata presets.
+    fn load_presets(&self) {
+        let sender = self.sender();
+        self.client
+            .get_all_metadata_presets(Box::new(move |presets| {
+                send_async_any!(Event::PresetsLoaded(presets), sender);
+            }));
+    }
+
+    /// The presets were loaded. Select the current one, by name, if
+    /// still there.
+    fn presets_loaded(&self, presets: Vec<MetadataPreset>) {
+        let Some(widgets) = self.widgets.get() else {
+            return;
+        };
+        let current = self
+            .state
+            .borrow()
+            .preset
+            .as_ref()
+            .map(|preset| preset.name().to_string());
+        self.state.borrow_mut().presets = presets.clone();
+        let model = &widgets.preset_combo_model;
+        widgets.preset_combo.set_selected(0);
+        while model.len() > 1 {
+            model.remove(1);
+        }
+        for preset in &presets {
+            model.push(preset.name(), preset.id());
+        }
+        if let Some(idx) = current
+            .and_then(|name| presets.iter().find(|preset| preset.name() == name))
+            .and_then(|preset| model.index_of(&preset.id()))
+        {
+            widgets.preset_combo.set_selected(idx as u32);
+        }
+    }
+
+    /// The preset `id` was selected. Display it for editing.
+    fn preset_selected(&self, id: LibraryId) {
+        let preset = self
+            .state
+            .borrow()
+            .presets
+            .iter()
+            .find(|preset| preset.id() == id)
+            .cloned();
+        let Some(widgets) = self.widgets.get() else {
+            return;
+        };
+        let mut properties = NiepcePropertyBag::default();
+        let mut label = 0;
+        for (meta, value) in preset.iter().flat_map(|preset| preset.properties()) {
+            match (meta, value) {
+                (Np::Index(NiepcePropertyIdx::NpXmpLabelProp), PropertyValue::Int(id)) => {
+                    label = *id as LibraryId;
+                }
+                _ => {
+                    properties.set_value(*meta, value.clone());
+                }
+            }
+        }
+        widgets.metadata_pane.display_properties(Some(&properties));
+        widgets.preset_label_combo.set_selected(
+            widgets
+                .preset_label_combo_model
+                .index_of(&label)
+                .unwrap_or(0) as u32,
+        );
+        self.state.borrow_mut().preset = preset;
+    }
+
+    /// The preset metadata were edited.
+    fn preset_metadata_changed(&self, properties: &MetadataPropertyBag) {
+        let mut state = self.state.borrow_mut();
+        let preset = state
+            .preset
+            .get_or_insert_with(|| MetadataPreset::new(0, "", vec![]));
+        for key in properties.keys() {
+            if let Some(value) = properties.get(key) {
+                preset.set_property(Np::from(*key), value.clone());
+            }
+        }
+    }
+
+    fn labels_loaded(&self, labels: &[Label]) {
+        if let Some(widgets) = self.widgets.get() {
+            for label in labels {
+                widgets
+                    .preset_label_combo_model
+                    .push(label.label(), label.id());
+            }
+        }
+    }
+
+    fn preset_label_changed(&self, id: LibraryId) {
+        let mut state = self.state.borrow_mut();
+        if id == 0 && state.preset.is_none() {
+            return;
+        }
+        let value = if id == 0 {
+            PropertyValue::Empty
+        } else {
+            PropertyValue::Int(id as i32)
+        };
+        state
+            .preset
+            .get_or_insert_with(|| MetadataPreset::new(0, "", vec![]))
+            .set_property(Np::Index(NiepcePropertyIdx::NpXmpLabelProp), value);
+    }
+
+    fn save_preset(&self) {
+        let name = self
+            .state
+            .borrow()
+            .preset
+            .as_ref()
+            .map(|preset| preset.name().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| i18n("Untitled preset"));
+        let sender = self.sender();
+        toolkit::request::request_name(
+            Some(self.dialog().upcast_ref::<gtk4::Window>()),
+            &i18n("Save Preset"),
+            &i18n("Preset _name:"),
+            Some(&name),
+            move |name| {
+                if !name.is_empty() {
+                    send_async_any!(Event::SavePresetAs(name.to_string()), sender);
+                }
+            },
+        );
+    }
+
+    fn save_preset_as(&self, name: &str) {
+        let properties = {
+            let mut state = self.state.borrow_mut();
+            let preset = state
+                .preset
+                .take()
+                .unwrap_or_else(|| MetadataPreset::new(0, "", vec![]));
+            let properties = preset.properties().to_vec();
+            state.preset = Some(MetadataPreset::new(preset.id(), name, properties.clone()));
+            properties
+        };
+        self.client
+            .save_metadata_preset(name.to_string(), properties);
+        self.load_presets();
+    }
+
+    fn delete_preset(&self) {
+        let id = self
+            .state
+            .borrow()
+            .preset
+            .as_ref()
+            .map(|preset| preset.id())
+            .unwrap_or(0);
+        if id > 0 {
+            self.client.delete_metadata_preset(id);
+            self.state.borrow_mut().preset = None;
+            self.load_presets();
+        }
+    }
+
     /// Set the date sorting format.
     fn set_sorting_format(&self, format: DatePathFormat) {
         let mut state = self.state.borrow_mut();
@@ -569,6 +1277,11 @@ impl ImportDialog {
 
     fn append_files_to_import(&self, files: &[Box<dyn ImportedFile>]) {
         let count = files.len();
+        let first = self
+            .widgets
+            .get()
+            .map(|widgets| widgets.images_list_model.n_items())
+            .unwrap_or(0);
         let paths: Vec<String> = files
             .iter()
             .map(|f| {
@@ -590,10 +1303,24 @@ impl ImportDialog {
             })
             .collect();
         self.state.borrow_mut().import_count += count;
-        self.update_import_count();
         if let Some(widgets) = self.widgets.get() {
+            // All the files are selected by default.
+            widgets
+                .selection_model
+                .select_range(first, count as u32, false);
             widgets.preview_spinner.start();
         }
+        self.update_import_count();
+
+        let sender = self.sender();
+        self.client.find_duplicates(
+            paths.iter().map(PathBuf::from).collect(),
+            Box::new(move |duplicates| {
+                if !duplicates.is_empty() {
+                    send_async_any!(Event::DuplicatesFound(duplicates), sender);
+                }
+            }),
+        );
 
         if let Some(importer) = self.importer()
             && let Some(source) = &self.state.borrow().source
@@ -603,10 +1330,10 @@ impl ImportDialog {
                 &self.thumbnail_task,
                 source,
                 paths,
-                Box::new(move |path, thumbnail, date| {
+                Box::new(move |path, thumbnail, date, camera| {
                     if let Some(path) = path {
                         npc_fwk::send_async_any!(
-                            Event::PreviewReceived(path, thumbnail, date),
+                            Event::PreviewReceived(path, thumbnail, date, camera),
                             sender
                         );
                     } else {
@@ -620,12 +1347,21 @@ impl ImportDialog {
     /// A preview was received. Update the UI to show it in the grid view.
     /// And eventually a date (original).
     /// If either `thumbnail` and `date` are `None` then this is a no-op.
-    fn preview_received(&self, path: &str, thumbnail: Option<Thumbnail>, date: Option<Date>) {
+    fn preview_received(
+        &self,
+        path: &str,
+        thumbnail: Option<Thumbnail>,
+        date: Option<Date>,
+        camera: Option<String>,
+    ) {
         if thumbnail.is_none() && date.is_none() {
             return;
         }
 
         trace_out!("preview and date received {:?}", date);
+        if let Some(ref date) = date {
+            self.add_date_toggle(date);
+        }
 
         let state = self.state.borrow();
         let dest_dir = &state.full_dest_dir;
@@ -653,6 +1389,7 @@ impl ImportDialog {
                             texture.upcast::<gdk4::Paintable>()
                         }));
                         item.set_date(date);
+                        item.set_camera(camera);
                     });
                 if self.state.borrow().copy {
                     if let Some(dest) = &dest {
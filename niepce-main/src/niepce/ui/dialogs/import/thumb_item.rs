@@ -34,7 +34,11 @@ impl ThumbItem {
         let obj: Self = glib::Object::builder()
             .property("name", imported_file.name().to_string())
             .build();
-        obj.imp().data.replace(Some(imp::ItemData { date: None }));
+        obj.imp().data.replace(Some(imp::ItemData {
+            orig_name: imported_file.name().to_string(),
+            date: None,
+            camera: None,
+        }));
 
         obj
     }
@@ -44,10 +48,41 @@ impl ThumbItem {
             data.date = date;
         }
     }
+
+    pub fn date(&self) -> Option<Date> {
+        self.imp().data.borrow().as_ref().and_then(|data| data.date)
+    }
+
+    pub fn set_camera(&self, camera: Option<String>) {
+        if let Some(ref mut data) = *self.imp().data.borrow_mut() {
+            data.camera = camera;
+        }
+    }
+
+    pub fn camera(&self) -> Option<String> {
+        self.imp()
+            .data
+            .borrow()
+            .as_ref()
+            .and_then(|data| data.camera.clone())
+    }
+
+    /// Set the name to display, after renaming. `None` to display the
+    /// original name.
+    pub fn set_dest_name(&self, name: Option<&str>) {
+        let orig_name = self
+            .imp()
+            .data
+            .borrow()
+            .as_ref()
+            .map(|data| data.orig_name.clone())
+            .unwrap_or_default();
+        self.set_name(name.unwrap_or(&orig_name));
+    }
 }
 
 mod imp {
-    use std::cell::RefCell;
+    use std::cell::{Cell, RefCell};
 
     use gio::subclass::prelude::*;
     use glib::Properties;
@@ -64,10 +99,15 @@ mod imp {
         pub(super) name: RefCell<String>,
         #[property(get, set, nullable)]
         pub(super) pixbuf: RefCell<Option<gdk4::Paintable>>,
+        /// Whether the file is already in the catalog.
+        #[property(get, set)]
+        pub(super) duplicate: Cell<bool>,
     }
 
     pub(super) struct ItemData {
+        pub(super) orig_name: String,
         pub(super) date: Option<Date>,
+        pub(super) camera: Option<String>,
     }
 
     #[glib::object_subclass]
@@ -46,6 +46,7 @@ impl ListViewRow<ThumbItem> for ThumbItemRow {
     fn bind(&self, thumb_item: &ThumbItem, _tree_list_row: Option<&gtk4::TreeListRow>) {
         self.bind_to_prop("filename", thumb_item, "name");
         self.bind_to_prop("image", thumb_item, "pixbuf");
+        self.bind_to_prop("duplicate", thumb_item, "duplicate");
     }
 
     fn bindings_mut(&self) -> RefMut<'_, Vec<glib::Binding>> {
@@ -62,6 +63,7 @@ impl Default for ThumbItemRow {
 mod imp {
     use std::cell::RefCell;
 
+    use gettextrs::gettext as i18n;
     use glib::Properties;
     use gtk4::prelude::*;
     use gtk4::subclass::prelude::*;
@@ -74,6 +76,8 @@ mod imp {
         pub(super) image: gtk4::Image,
         #[property(set = |row: &&Self, n| row.filename.set_label(n), type = String)]
         pub(super) filename: gtk4::Label,
+        #[property(set = |row: &&Self, d| row.set_duplicate(d), type = bool)]
+        pub(super) duplicate: gtk4::Label,
         pub(super) bindings: RefCell<Vec<glib::Binding>>,
     }
 
@@ -91,10 +95,23 @@ mod imp {
 
             self.obj().append(&self.image);
             self.obj().append(&self.filename);
+            self.obj().append(&self.duplicate);
             self.image.set_size_request(100, 100);
             self.image.set_pixel_size(100);
             // Adwaita class
             self.filename.add_css_class("caption");
+            self.duplicate.set_label(&i18n("Already in catalog"));
+            self.duplicate.add_css_class("caption");
+            self.duplicate.add_css_class("warning");
+            self.duplicate.set_visible(false);
+        }
+    }
+
+    impl ThumbItemRow {
+        /// Duplicates are shown dimmed, with a mention.
+        fn set_duplicate(&self, duplicate: bool) {
+            self.duplicate.set_visible(duplicate);
+            self.image.set_opacity(if duplicate { 0.5 } else { 1.0 });
         }
     }
 
@@ -58,6 +58,8 @@ struct ImportState {
     importer: Option<Box<dyn LibraryImporter>>,
     /// The widget to set the label to.
     importer_name_label: Option<gtk4::Label>,
+    /// The label for the import report.
+    report_label: Option<gtk4::Label>,
     /// The import button.
     import_file_button: Option<gtk4::Button>,
     /// The map for root folders.
@@ -249,6 +251,7 @@ impl ImportLibraryDialog {
         }
 
         if let Some(page) = builder.object::<gtk4::Widget>("done-page") {
+            dlg.state.borrow_mut().report_label = builder.object::<gtk4::Label>("done-report");
             assistant.insert_page(&page, -1);
             assistant.set_page_type(&page, gtk4::AssistantPageType::Summary);
             toolkit::assistant::set_page_index(&page, Page::Done.to_i32().unwrap());
@@ -328,10 +331,27 @@ impl ImportLibraryDialog {
     fn perform_import(&self) {
         dbg_out!("Perform import");
         self.state.borrow_mut().importer_root_remap();
+        let report_label = self.state.borrow().report_label.clone();
         if let Some(importer) = &mut self.state.borrow_mut().importer {
-            importer
-                .import_library(&self.client)
-                .expect("import library");
+            let text = match importer.import_library(&self.client) {
+                Ok(report) if report.is_empty() => String::new(),
+                Ok(report) => {
+                    let items = report
+                        .not_converted()
+                        .iter()
+                        .map(|(item, reason)| format!("{item}: {reason}"))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    format!("{}\n{items}", i18n("Not converted:"))
+                }
+                Err(err) => {
+                    err_out!("Import library failed: {err}");
+                    i18n_format!("Import failed: {}", err)
+                }
+            };
+            if let Some(label) = report_label {
+                label.set_text(&text);
+            }
         }
         self.set_page_complete(Page::Progress);
         #[allow(deprecated)]
@@ -366,7 +386,7 @@ impl ImportLibraryDialog {
     }
 
     fn select_file(&self) {
-        let message = i18n("Select Lightroom™ Library");
+        let message = i18n("Select Library to Import");
         #[allow(deprecated)]
         let file_dialog = gtk4::FileChooserDialog::new(
             Some(&message),
@@ -384,7 +404,7 @@ impl ImportLibraryDialog {
                 dbg_out!("Accept");
                 #[allow(deprecated)]
                 if let Some(file) = d.file().as_ref().and_then(gio::prelude::FileExt::path) {
-                    dbg_out!("Lr file: {:?}", file);
+                    dbg_out!("Library file: {:?}", file);
                     npc_fwk::send_async_local!(Command::SetFile(file), sender);
                 }
             }
@@ -0,0 +1,204 @@
+/*
+ * niepce - niepce/ui/dialogs/journal_history.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The dialog to display the catalog journal, ie the undo history.
+
+use std::rc::Rc;
+use std::sync::Arc;
+
+use adw::prelude::*;
+use chrono::{DateTime, Local};
+use gettextrs::gettext as i18n;
+use npc_fwk::{adw, glib, gtk4};
+
+use npc_engine::library::journal::JournalEntry;
+use npc_engine::libraryclient::{ClientInterface, LibraryClient};
+use npc_fwk::toolkit::{Controller, ControllerImplCell, DialogController, UiController};
+use npc_fwk::{controller_imp_imp, send_async_local};
+
+pub enum Event {
+    /// The journal entries, most recent first.
+    Listed(Vec<JournalEntry>),
+    Undo,
+    Redo,
+}
+
+/// The entry that undo would revert. `entries` are the most recent
+/// first. An entry that can't be undone blocks the ones before it.
+pub fn next_undo(entries: &[JournalEntry]) -> Option<&JournalEntry> {
+    entries
+        .iter()
+        .find(|entry| !entry.is_undone())
+        .filter(|entry| entry.is_reversible())
+}
+
+/// The entry that redo would apply. `entries` are the most recent
+/// first.
+pub fn next_redo(entries: &[JournalEntry]) -> Option<&JournalEntry> {
+    entries.iter().rev().find(|entry| entry.is_undone())
+}
+
+struct Widgets {
+    entries_list: gtk4::ListBox,
+    undo_button: gtk4::Button,
+    redo_button: gtk4::Button,
+}
+
+/// The journal history dialog. It is kept around, and hidden when
+/// closed.
+pub struct JournalHistoryDialog {
+    imp_: ControllerImplCell<Event, ()>,
+    client: Arc<LibraryClient>,
+    dialog: adw::Window,
+    widgets: Widgets,
+}
+
+impl Controller for JournalHistoryDialog {
+    type InMsg = Event;
+    type OutMsg = ();
+
+    controller_imp_imp!(imp_);
+
+    fn dispatch(&self, e: Event) {
+        match e {
+            Event::Listed(entries) => self.entries_listed(&entries),
+            Event::Undo => self.client.undo_journal(),
+            Event::Redo => self.client.redo_journal(),
+        }
+    }
+}
+
+impl UiController for JournalHistoryDialog {
+    fn widget(&self) -> &gtk4::Widget {
+        self.dialog.upcast_ref()
+    }
+}
+
+impl DialogController for JournalHistoryDialog {
+    fn dialog(&self) -> &adw::Window {
+        &self.dialog
+    }
+}
+
+impl JournalHistoryDialog {
+    pub fn new(client: Arc<LibraryClient>) -> Rc<JournalHistoryDialog> {
+        let (dialog, widgets) = Self::build_widgets();
+        let ctrl = Rc::new(JournalHistoryDialog {
+            imp_: ControllerImplCell::default(),
+            client,
+            dialog,
+            widgets,
+        });
+
+        <Self as DialogController>::start(&ctrl);
+        let sender = ctrl.sender();
+        ctrl.widgets.undo_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::Undo, sender)
+        ));
+        ctrl.widgets
+            .redo_button
+            .connect_clicked(move |_| send_async_local!(Event::Redo, sender));
+
+        ctrl
+    }
+
+    fn build_widgets() -> (adw::Window, Widgets) {
+        let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        let header = adw::HeaderBar::builder()
+            .title_widget(&gtk4::Label::new(Some(&i18n("History"))))
+            .build();
+        let undo_button = gtk4::Button::from_icon_name("edit-undo-symbolic");
+        undo_button.set_tooltip_text(Some(&i18n("Undo")));
+        undo_button.set_sensitive(false);
+        let redo_button = gtk4::Button::from_icon_name("edit-redo-symbolic");
+        redo_button.set_tooltip_text(Some(&i18n("Redo")));
+        redo_button.set_sensitive(false);
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+        buttons.add_css_class("linked");
+        buttons.append(&undo_button);
+        buttons.append(&redo_button);
+        header.pack_start(&buttons);
+        vbox.append(&header);
+
+        let entries_list = gtk4::ListBox::new();
+        entries_list.set_selection_mode(gtk4::SelectionMode::None);
+        entries_list.add_css_class("boxed-list");
+        entries_list.set_placeholder(Some(&gtk4::Label::new(Some(&i18n("No history")))));
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .child(&entries_list)
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .min_content_height(200)
+            .vexpand(true)
+            .margin_start(12)
+            .margin_end(12)
+            .margin_bottom(12)
+            .build();
+        vbox.append(&scrolled);
+
+        let dialog = adw::Window::builder()
+            .content(&vbox)
+            .default_width(400)
+            .default_height(500)
+            .hide_on_close(true)
+            .build();
+
+        (
+            dialog,
+            Widgets {
+                entries_list,
+                undo_button,
+                redo_button,
+            },
+        )
+    }
+
+    fn entries_listed(&self, entries: &[JournalEntry]) {
+        let list = &self.widgets.entries_list;
+        list.remove_all();
+        for entry in entries {
+            let date = DateTime::from_timestamp(entry.date(), 0)
+                .map(|date| date.with_timezone(&Local).format("%x %X").to_string())
+                .unwrap_or_default();
+            let subtitle = if entry.is_undone() {
+                format!("{date} — {}", i18n("Undone"))
+            } else if !entry.is_reversible() {
+                format!("{date} — {}", i18n("Can't be undone"))
+            } else {
+                date
+            };
+            let row = adw::ActionRow::builder()
+                .use_markup(false)
+                .title(entry.label())
+                .subtitle(subtitle)
+                .build();
+            if entry.is_undone() {
+                row.add_css_class("dim-label");
+            }
+            list.append(&row);
+        }
+        self.widgets
+            .undo_button
+            .set_sensitive(next_undo(entries).is_some());
+        self.widgets
+            .redo_button
+            .set_sensitive(next_redo(entries).is_some());
+    }
+}
@@ -0,0 +1,650 @@
+/*
+ * niepce - niepce/ui/dialogs/smart_collection.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The dialog to create or edit a smart collection rules.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use adw::prelude::*;
+use chrono::{Local, NaiveDate, TimeZone};
+use gettextrs::gettext as i18n;
+use npc_fwk::{adw, glib, gtk4};
+
+use npc_engine::catalog::smart_collection::{Comparison, SmartRule};
+use npc_engine::catalog::{Keyword, Label, LibraryId, SmartCollection};
+use npc_engine::libraryclient::{ClientInterface, LibraryClient};
+use npc_fwk::toolkit::{self, Controller, ControllerImplCell, DialogController, UiController};
+use npc_fwk::{controller_imp_imp, send_async_any, send_async_local};
+
+/// Format of the dates in the date range rule.
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub enum Event {
+    LabelsLoaded(Vec<Label>),
+    KeywordsLoaded(Vec<Keyword>),
+    AddRule,
+    RemoveRule(u32),
+    Save,
+    CloseRequest,
+}
+
+/// The kind of rule, in the order of the kind dropdown.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RuleKind {
+    Rating,
+    Label,
+    Flag,
+    Keywords,
+    CameraModel,
+    Lens,
+    DateRange,
+}
+
+impl RuleKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Rating => "rating",
+            Self::Label => "label",
+            Self::Flag => "flag",
+            Self::Keywords => "keywords",
+            Self::CameraModel => "model",
+            Self::Lens => "lens",
+            Self::DateRange => "date",
+        }
+    }
+
+    fn of(rule: &SmartRule) -> RuleKind {
+        match rule {
+            SmartRule::Rating(..) => Self::Rating,
+            SmartRule::Label(_) => Self::Label,
+            SmartRule::Flag(_) => Self::Flag,
+            SmartRule::Keywords(_) => Self::Keywords,
+            SmartRule::CameraModel(_) => Self::CameraModel,
+            SmartRule::Lens(_) => Self::Lens,
+            SmartRule::DateRange(..) => Self::DateRange,
+        }
+    }
+}
+
+/// The widgets of a rule row.
+struct RuleRow {
+    id: u32,
+    row: gtk4::Box,
+    kind: gtk4::DropDown,
+    kind_model: Rc<toolkit::ComboModel<RuleKind>>,
+    comparison: gtk4::DropDown,
+    comparison_model: Rc<toolkit::ComboModel<Comparison>>,
+    rating: gtk4::SpinButton,
+    label: gtk4::DropDown,
+    flag: gtk4::DropDown,
+    flag_model: Rc<toolkit::ComboModel<i32>>,
+    keywords_button: gtk4::MenuButton,
+    keywords_list: gtk4::ListBox,
+    /// The keywords checked.
+    keywords: RefCell<Vec<LibraryId>>,
+    camera_model: gtk4::Entry,
+    lens: gtk4::Entry,
+    date_start: gtk4::Entry,
+    date_end: gtk4::Entry,
+}
+
+impl RuleRow {
+    /// The rule from the row. `None` if the values are invalid.
+    fn rule(&self, labels: &toolkit::ComboModel<LibraryId>) -> Option<SmartRule> {
+        let kind = self.kind_model.value(self.kind.selected() as usize);
+        match kind {
+            RuleKind::Rating => Some(SmartRule::Rating(
+                self.comparison_model
+                    .value(self.comparison.selected() as usize),
+                self.rating.value_as_int(),
+            )),
+            RuleKind::Label => {
+                let selected = self.label.selected() as usize;
+                (selected < labels.len()).then(|| SmartRule::Label(labels.value(selected)))
+            }
+            RuleKind::Flag => Some(SmartRule::Flag(
+                self.flag_model.value(self.flag.selected() as usize),
+            )),
+            RuleKind::Keywords => {
+                let keywords = self.keywords.borrow().clone();
+                (!keywords.is_empty()).then_some(SmartRule::Keywords(keywords))
+            }
+            RuleKind::CameraModel => {
+                let text = self.camera_model.text();
+                (!text.is_empty()).then(|| SmartRule::CameraModel(text.to_string()))
+            }
+            RuleKind::Lens => {
+                let text = self.lens.text();
+                (!text.is_empty()).then(|| SmartRule::Lens(text.to_string()))
+            }
+            RuleKind::DateRange => {
+                let start = parse_date(&self.date_start.text(), false)?;
+                let end = parse_date(&self.date_end.text(), true)?;
+                Some(SmartRule::DateRange(start, end))
+            }
+        }
+    }
+
+    /// Set the row values from `rule`.
+    fn set_rule(&self, rule: &SmartRule, labels: &toolkit::ComboModel<LibraryId>) {
+        if let Some(idx) = self.kind_model.index_of(&RuleKind::of(rule)) {
+            self.kind.set_selected(idx as u32);
+        }
+        match rule {
+            SmartRule::Rating(cmp, rating) => {
+                if let Some(idx) = self.comparison_model.index_of(cmp) {
+                    self.comparison.set_selected(idx as u32);
+                }
+                self.rating.set_value(*rating as f64);
+            }
+            SmartRule::Label(label) => {
+                if let Some(idx) = labels.index_of(label) {
+                    self.label.set_selected(idx as u32);
+                }
+            }
+            SmartRule::Flag(flag) => {
+                if let Some(idx) = self.flag_model.index_of(flag) {
+                    self.flag.set_selected(idx as u32);
+                }
+            }
+            SmartRule::Keywords(keywords) => {
+                self.keywords.replace(keywords.clone());
+            }
+            SmartRule::CameraModel(model) => self.camera_model.set_text(model),
+            SmartRule::Lens(lens) => self.lens.set_text(lens),
+            SmartRule::DateRange(start, end) => {
+                self.date_start.set_text(&format_date(*start));
+                self.date_end.set_text(&format_date(*end));
+            }
+        }
+    }
+
+    /// Fill the keyword list with `keywords`, checking the ones of the rule.
+    fn set_keywords(self: &Rc<Self>, keywords: &Rc<Vec<Keyword>>) {
+        self.keywords_list.remove_all();
+        for keyword in keywords.iter() {
+            let id = keyword.id();
+            let check = gtk4::CheckButton::with_label(keyword.keyword());
+            check.set_active(self.keywords.borrow().contains(&id));
+            check.connect_toggled(glib::clone!(
+                #[weak(rename_to = row)]
+                self,
+                #[strong]
+                keywords,
+                move |check| {
+                    {
+                        let mut checked = row.keywords.borrow_mut();
+                        checked.retain(|k| *k != id);
+                        if check.is_active() {
+                            checked.push(id);
+                        }
+                    }
+                    row.update_keywords_label(&keywords);
+                }
+            ));
+            self.keywords_list.append(&check);
+        }
+        self.update_keywords_label(keywords);
+    }
+
+    fn update_keywords_label(&self, keywords: &[Keyword]) {
+        let checked = self.keywords.borrow();
+        let names = keywords
+            .iter()
+            .filter(|keyword| checked.contains(&keyword.id()))
+            .map(|keyword| keyword.keyword())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            self.keywords_button.set_label(&i18n("Choose…"));
+        } else {
+            self.keywords_button.set_label(&names.join(", "));
+        }
+    }
+}
+
+/// Parse a date, at the start of the day, or at its end if `end`.
+fn parse_date(text: &str, end: bool) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(text.trim(), DATE_FORMAT).ok()?;
+    let time = if end {
+        date.and_hms_opt(23, 59, 59)?
+    } else {
+        date.and_hms_opt(0, 0, 0)?
+    };
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.timestamp())
+}
+
+fn format_date(time: i64) -> String {
+    Local
+        .timestamp_opt(time, 0)
+        .single()
+        .map(|t| t.format(DATE_FORMAT).to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Default)]
+struct State {
+    rows: Vec<Rc<RuleRow>>,
+    next_id: u32,
+    keywords: Rc<Vec<Keyword>>,
+}
+
+struct Widgets {
+    name_entry: gtk4::Entry,
+    match_combo: gtk4::DropDown,
+    rules_box: gtk4::Box,
+    add_button: gtk4::Button,
+    save_button: gtk4::Button,
+}
+
+/// The smart collection editor. Creates a new smart collection, or
+/// update `collection`.
+pub struct SmartCollectionDialog {
+    imp_: ControllerImplCell<Event, ()>,
+    client: Arc<LibraryClient>,
+    /// The collection being edited.
+    collection: Option<SmartCollection>,
+    /// The labels, shared by the label dropdowns.
+    labels_model: Rc<toolkit::ComboModel<LibraryId>>,
+    /// Whether the labels are loaded. Until then the rules aren't set.
+    labels_loaded: Cell<bool>,
+    dialog: adw::Window,
+    widgets: Widgets,
+    state: RefCell<State>,
+}
+
+impl Controller for SmartCollectionDialog {
+    type InMsg = Event;
+    type OutMsg = ();
+
+    controller_imp_imp!(imp_);
+
+    fn dispatch(&self, e: Event) {
+        match e {
+            Event::LabelsLoaded(labels) => self.labels_loaded(&labels),
+            Event::KeywordsLoaded(keywords) => self.keywords_loaded(keywords),
+            Event::AddRule => {
+                self.add_rule(None);
+            }
+            Event::RemoveRule(id) => {
+                let mut state = self.state.borrow_mut();
+                if let Some(idx) = state.rows.iter().position(|row| row.id == id) {
+                    let row = state.rows.remove(idx);
+                    self.widgets.rules_box.remove(&row.row);
+                }
+            }
+            Event::Save => {
+                self.save();
+                self.close();
+            }
+            Event::CloseRequest => self.close(),
+        }
+    }
+}
+
+impl UiController for SmartCollectionDialog {
+    fn widget(&self) -> &gtk4::Widget {
+        self.dialog.upcast_ref()
+    }
+}
+
+impl DialogController for SmartCollectionDialog {
+    fn dialog(&self) -> &adw::Window {
+        &self.dialog
+    }
+}
+
+impl SmartCollectionDialog {
+    pub fn new(
+        client: Arc<LibraryClient>,
+        collection: Option<SmartCollection>,
+    ) -> Rc<SmartCollectionDialog> {
+        let (dialog, widgets) = Self::build_widgets(collection.is_some());
+        let ctrl = Rc::new(SmartCollectionDialog {
+            imp_: ControllerImplCell::default(),
+            client,
+            collection,
+            labels_model: toolkit::ComboModel::new(),
+            labels_loaded: Cell::new(false),
+            dialog,
+            widgets,
+            state: RefCell::default(),
+        });
+
+        <Self as DialogController>::start(&ctrl);
+        ctrl.connect_widgets();
+        if let Some(collection) = &ctrl.collection {
+            ctrl.widgets.name_entry.set_text(collection.name());
+            ctrl.widgets
+                .match_combo
+                .set_selected(if collection.match_all() { 0 } else { 1 });
+        }
+        let sender = ctrl.sender();
+        ctrl.client.get_all_labels(Some(Box::new(move |labels| {
+            send_async_any!(Event::LabelsLoaded(labels), sender);
+        })));
+
+        ctrl
+    }
+
+    fn build_widgets(edit: bool) -> (adw::Window, Widgets) {
+        let title = if edit {
+            i18n("Edit Smart Collection")
+        } else {
+            i18n("New Smart Collection")
+        };
+        let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        vbox.append(
+            &adw::HeaderBar::builder()
+                .title_widget(&gtk4::Label::new(Some(&title)))
+                .build(),
+        );
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.set_margin_bottom(12);
+
+        let grid = gtk4::Grid::builder()
+            .row_spacing(6)
+            .column_spacing(12)
+            .build();
+        let name_entry = gtk4::Entry::builder()
+            .text(i18n("Untitled smart collection"))
+            .activates_default(true)
+            .hexpand(true)
+            .build();
+        let label = gtk4::Label::with_mnemonic(&i18n("_Name:"));
+        label.set_mnemonic_widget(Some(&name_entry));
+        label.set_xalign(1.0);
+        grid.attach(&label, 0, 0, 1, 1);
+        grid.attach(&name_entry, 1, 0, 1, 1);
+
+        let match_combo =
+            gtk4::DropDown::from_strings(&[&i18n("All the rules"), &i18n("Any of the rules")]);
+        let label = gtk4::Label::with_mnemonic(&i18n("_Match:"));
+        label.set_mnemonic_widget(Some(&match_combo));
+        label.set_xalign(1.0);
+        grid.attach(&label, 0, 1, 1, 1);
+        grid.attach(&match_combo, 1, 1, 1, 1);
+        content.append(&grid);
+
+        let rules_box = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .child(&rules_box)
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .min_content_height(200)
+            .vexpand(true)
+            .build();
+        content.append(&scrolled);
+
+        let add_button = gtk4::Button::builder()
+            .label(i18n("_Add Rule"))
+            .use_underline(true)
+            .halign(gtk4::Align::Start)
+            .build();
+        let save_button = gtk4::Button::builder()
+            .label(if edit { i18n("_Save") } else { i18n("_Create") })
+            .use_underline(true)
+            .hexpand(true)
+            .halign(gtk4::Align::End)
+            .build();
+        save_button.add_css_class("suggested-action");
+        let buttons = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+        buttons.append(&add_button);
+        buttons.append(&save_button);
+        content.append(&buttons);
+        vbox.append(&content);
+
+        let dialog = adw::Window::builder()
+            .content(&vbox)
+            .default_width(600)
+            .default_height(400)
+            .default_widget(&save_button)
+            .build();
+
+        (
+            dialog,
+            Widgets {
+                name_entry,
+                match_combo,
+                rules_box,
+                add_button,
+                save_button,
+            },
+        )
+    }
+
+    fn connect_widgets(&self) {
+        let sender = self.sender();
+        self.widgets.add_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::AddRule, sender)
+        ));
+        self.widgets.save_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::Save, sender)
+        ));
+        self.widgets.name_entry.connect_changed(glib::clone!(
+            #[weak(rename_to = save_button)]
+            self.widgets.save_button,
+            move |entry| save_button.set_sensitive(!entry.text().is_empty())
+        ));
+        self.dialog.connect_close_request(move |_| {
+            send_async_local!(Event::CloseRequest, sender);
+            glib::Propagation::Proceed
+        });
+    }
+
+    /// The labels are needed to set the rules, then the keywords are loaded.
+    fn labels_loaded(&self, labels: &[Label]) {
+        for label in labels {
+            self.labels_model.push(label.label(), label.id());
+        }
+        self.labels_loaded.set(true);
+        if let Some(collection) = &self.collection {
+            for rule in collection.rules() {
+                self.add_rule(Some(rule));
+            }
+        } else {
+            self.add_rule(None);
+        }
+        let sender = self.sender();
+        self.client.get_all_keywords(Some(Box::new(move |keywords| {
+            send_async_any!(Event::KeywordsLoaded(keywords), sender);
+        })));
+    }
+
+    fn keywords_loaded(&self, mut keywords: Vec<Keyword>) {
+        keywords.sort_by(|a, b| a.keyword().cmp(b.keyword()));
+        let keywords = Rc::new(keywords);
+        let mut state = self.state.borrow_mut();
+        for row in &state.rows {
+            row.set_keywords(&keywords);
+        }
+        state.keywords = keywords;
+    }
+
+    fn add_rule(&self, rule: Option<&SmartRule>) {
+        let id = {
+            let mut state = self.state.borrow_mut();
+            state.next_id += 1;
+            state.next_id
+        };
+        let row = Rc::new(self.build_rule_row(id));
+        if let Some(rule) = rule {
+            row.set_rule(rule, &self.labels_model);
+        }
+        let mut state = self.state.borrow_mut();
+        row.set_keywords(&state.keywords);
+        self.widgets.rules_box.append(&row.row);
+        state.rows.push(row);
+    }
+
+    fn build_rule_row(&self, id: u32) -> RuleRow {
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+
+        let kind = gtk4::DropDown::builder().build();
+        let kind_model = toolkit::ComboModel::with_map(&[
+            (&i18n("Rating"), RuleKind::Rating),
+            (&i18n("Label"), RuleKind::Label),
+            (&i18n("Flag"), RuleKind::Flag),
+            (&i18n("Keywords"), RuleKind::Keywords),
+            (&i18n("Camera model"), RuleKind::CameraModel),
+            (&i18n("Lens"), RuleKind::Lens),
+            (&i18n("Date"), RuleKind::DateRange),
+        ]);
+        row.append(&kind);
+
+        let stack = gtk4::Stack::builder().hexpand(true).build();
+
+        let page = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        let comparison = gtk4::DropDown::builder().build();
+        let comparison_model = toolkit::ComboModel::with_map(&[
+            ("≥", Comparison::GreaterOrEqual),
+            ("=", Comparison::Equal),
+            ("≤", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("≠", Comparison::NotEqual),
+        ]);
+        comparison_model.bind(&comparison, |_| {});
+        let rating = gtk4::SpinButton::with_range(0.0, 5.0, 1.0);
+        rating.set_value(3.0);
+        page.append(&comparison);
+        page.append(&rating);
+        stack.add_named(&page, Some(RuleKind::Rating.name()));
+
+        let label = gtk4::DropDown::builder().build();
+        self.labels_model.bind(&label, |_| {});
+        stack.add_named(&label, Some(RuleKind::Label.name()));
+
+        let flag = gtk4::DropDown::builder().build();
+        let flag_model = toolkit::ComboModel::with_map(&[
+            (&i18n("Picked"), 1),
+            (&i18n("No flag"), 0),
+            (&i18n("Rejected"), -1),
+        ]);
+        flag_model.bind(&flag, |_| {});
+        stack.add_named(&flag, Some(RuleKind::Flag.name()));
+
+        let keywords_list = gtk4::ListBox::new();
+        keywords_list.set_selection_mode(gtk4::SelectionMode::None);
+        keywords_list.set_placeholder(Some(&gtk4::Label::new(Some(&i18n("No keyword")))));
+        let popover = gtk4::Popover::builder()
+            .child(
+                &gtk4::ScrolledWindow::builder()
+                    .child(&keywords_list)
+                    .hscrollbar_policy(gtk4::PolicyType::Never)
+                    .propagate_natural_height(true)
+                    .max_content_height(300)
+                    .build(),
+            )
+            .build();
+        let keywords_button = gtk4::MenuButton::builder()
+            .label(i18n("Choose…"))
+            .popover(&popover)
+            .build();
+        stack.add_named(&keywords_button, Some(RuleKind::Keywords.name()));
+
+        let camera_model = gtk4::Entry::new();
+        stack.add_named(&camera_model, Some(RuleKind::CameraModel.name()));
+        let lens = gtk4::Entry::new();
+        stack.add_named(&lens, Some(RuleKind::Lens.name()));
+
+        let page = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        let date_start = gtk4::Entry::builder()
+            .placeholder_text("YYYY-MM-DD")
+            .build();
+        let date_end = gtk4::Entry::builder()
+            .placeholder_text("YYYY-MM-DD")
+            .build();
+        page.append(&date_start);
+        page.append(&gtk4::Label::new(Some("–")));
+        page.append(&date_end);
+        stack.add_named(&page, Some(RuleKind::DateRange.name()));
+        row.append(&stack);
+
+        kind_model.bind(
+            &kind,
+            glib::clone!(
+                #[weak]
+                stack,
+                move |kind: &RuleKind| stack.set_visible_child_name(kind.name())
+            ),
+        );
+
+        let remove_button = gtk4::Button::from_icon_name("list-remove-symbolic");
+        remove_button.set_tooltip_text(Some(&i18n("Remove the rule")));
+        remove_button.add_css_class("flat");
+        let sender = self.sender();
+        remove_button.connect_clicked(move |_| send_async_local!(Event::RemoveRule(id), sender));
+        row.append(&remove_button);
+
+        RuleRow {
+            id,
+            row,
+            kind,
+            kind_model,
+            comparison,
+            comparison_model,
+            rating,
+            label,
+            flag,
+            flag_model,
+            keywords_button,
+            keywords_list,
+            keywords: RefCell::default(),
+            camera_model,
+            lens,
+            date_start,
+            date_end,
+        }
+    }
+
+    fn save(&self) {
+        if !self.labels_loaded.get() {
+            return;
+        }
+        let name = self.widgets.name_entry.text().to_string();
+        if name.is_empty() {
+            return;
+        }
+        let match_all = self.widgets.match_combo.selected() == 0;
+        let rules = self
+            .state
+            .borrow()
+            .rows
+            .iter()
+            .filter_map(|row| row.rule(&self.labels_model))
+            .collect::<Vec<_>>();
+        if let Some(collection) = &self.collection {
+            self.client
+                .update_smart_collection(collection.id(), name, rules, match_all);
+        } else {
+            self.client.create_smart_collection(name, rules, match_all);
+        }
+    }
+}
@@ -0,0 +1,384 @@
+/*
+ * niepce - niepce/ui/dialogs/watched_folders.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The dialog to manage the watched folders.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use adw::prelude::*;
+use gettextrs::gettext as i18n;
+use i18n_format::i18n_format;
+use npc_fwk::{adw, gio, glib, gtk4};
+
+use npc_engine::catalog::{LibraryId, MetadataPreset, WatchedFolder};
+use npc_engine::libraryclient::{ClientInterface, LibraryClient};
+use npc_fwk::toolkit::{self, Controller, ControllerImplCell, DialogController, UiController};
+use npc_fwk::{controller_imp_imp, send_async_any, send_async_local};
+
+pub enum Event {
+    FoldersLoaded(Vec<WatchedFolder>),
+    PresetsLoaded(Vec<MetadataPreset>),
+    /// Request the folder to watch.
+    ChooseSource,
+    SourceChanged(PathBuf),
+    CopyToggled(bool),
+    /// Request the folder to copy to.
+    ChooseDest,
+    DestChanged(PathBuf),
+    /// Watch the folder chosen.
+    Add,
+    /// Stop watching the folder.
+    Remove(LibraryId),
+}
+
+#[derive(Default)]
+struct State {
+    source: Option<PathBuf>,
+    copy: bool,
+    dest: Option<PathBuf>,
+    presets: Vec<MetadataPreset>,
+}
+
+struct Widgets {
+    folders_list: gtk4::ListBox,
+    source_button: gtk4::Button,
+    source_label: gtk4::Label,
+    copy_check: gtk4::CheckButton,
+    dest_button: gtk4::Button,
+    dest_label: gtk4::Label,
+    preset_combo: gtk4::DropDown,
+    preset_combo_model: Rc<toolkit::ComboModel<LibraryId>>,
+    add_button: gtk4::Button,
+}
+
+/// The watched folders dialog. Emit when the watched folders changed.
+pub struct WatchedFoldersDialog {
+    imp_: ControllerImplCell<Event, ()>,
+    client: Arc<LibraryClient>,
+    dialog: adw::Window,
+    widgets: Widgets,
+    state: RefCell<State>,
+}
+
+impl Controller for WatchedFoldersDialog {
+    type InMsg = Event;
+    type OutMsg = ();
+
+    controller_imp_imp!(imp_);
+
+    fn dispatch(&self, e: Event) {
+        match e {
+            Event::FoldersLoaded(folders) => self.folders_loaded(&folders),
+            Event::PresetsLoaded(presets) => self.presets_loaded(presets),
+            Event::ChooseSource => {
+                self.choose_folder(&i18n("Folder to Watch"), Event::SourceChanged)
+            }
+            Event::SourceChanged(source) => {
+                self.widgets
+                    .source_label
+                    .set_label(&source.to_string_lossy());
+                self.state.borrow_mut().source = Some(source);
+                self.update_add_button();
+            }
+            Event::CopyToggled(copy) => {
+                self.widgets.dest_button.set_sensitive(copy);
+                self.state.borrow_mut().copy = copy;
+                self.update_add_button();
+            }
+            Event::ChooseDest => self.choose_folder(&i18n("Copy Destination"), Event::DestChanged),
+            Event::DestChanged(dest) => {
+                self.widgets.dest_label.set_label(&dest.to_string_lossy());
+                self.state.borrow_mut().dest = Some(dest);
+                self.update_add_button();
+            }
+            Event::Add => self.add(),
+            Event::Remove(id) => {
+                self.client.delete_watched_folder(id);
+                self.folders_changed();
+            }
+        }
+    }
+}
+
+impl UiController for WatchedFoldersDialog {
+    fn widget(&self) -> &gtk4::Widget {
+        self.dialog.upcast_ref()
+    }
+}
+
+impl DialogController for WatchedFoldersDialog {
+    fn dialog(&self) -> &adw::Window {
+        &self.dialog
+    }
+}
+
+impl WatchedFoldersDialog {
+    pub fn new(client: Arc<LibraryClient>) -> Rc<WatchedFoldersDialog> {
+        let (dialog, widgets) = Self::build_widgets();
+        let ctrl = Rc::new(WatchedFoldersDialog {
+            imp_: ControllerImplCell::default(),
+            client,
+            dialog,
+            widgets,
+            state: RefCell::default(),
+        });
+
+        <Self as DialogController>::start(&ctrl);
+        ctrl.connect_widgets();
+        ctrl.load_folders();
+        let sender = ctrl.sender();
+        ctrl.client
+            .get_all_metadata_presets(Box::new(move |presets| {
+                send_async_any!(Event::PresetsLoaded(presets), sender);
+            }));
+
+        ctrl
+    }
+
+    fn build_widgets() -> (adw::Window, Widgets) {
+        let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        vbox.append(
+            &adw::HeaderBar::builder()
+                .title_widget(&gtk4::Label::new(Some(&i18n("Watched Folders"))))
+                .build(),
+        );
+
+        let content = gtk4::Box::new(gtk4::Orientation::Vertical, 12);
+        content.set_margin_start(12);
+        content.set_margin_end(12);
+        content.set_margin_bottom(12);
+        let help = gtk4::Label::new(Some(&i18n(
+            "The new images in the watched folders are imported automatically.",
+        )));
+        help.set_xalign(0.0);
+        help.set_wrap(true);
+        help.add_css_class("dim-label");
+        content.append(&help);
+
+        let folders_list = gtk4::ListBox::new();
+        folders_list.set_selection_mode(gtk4::SelectionMode::None);
+        folders_list.add_css_class("boxed-list");
+        folders_list.set_placeholder(Some(&gtk4::Label::new(Some(&i18n("No watched folder")))));
+        let scrolled = gtk4::ScrolledWindow::builder()
+            .child(&folders_list)
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .min_content_height(150)
+            .vexpand(true)
+            .build();
+        content.append(&scrolled);
+
+        let grid = gtk4::Grid::builder()
+            .row_spacing(6)
+            .column_spacing(12)
+            .build();
+        let source_label = gtk4::Label::new(Some(&i18n("Choose…")));
+        let source_button = gtk4::Button::builder()
+            .child(&source_label)
+            .hexpand(true)
+            .build();
+        let label = gtk4::Label::with_mnemonic(&i18n("_Folder:"));
+        label.set_mnemonic_widget(Some(&source_button));
+        label.set_xalign(1.0);
+        grid.attach(&label, 0, 0, 1, 1);
+        grid.attach(&source_button, 1, 0, 1, 1);
+
+        let copy_check = gtk4::CheckButton::with_mnemonic(&i18n("_Copy to:"));
+        let dest_label = gtk4::Label::new(Some(&i18n("Choose…")));
+        let dest_button = gtk4::Button::builder()
+            .child(&dest_label)
+            .sensitive(false)
+            .build();
+        grid.attach(&copy_check, 0, 1, 1, 1);
+        grid.attach(&dest_button, 1, 1, 1, 1);
+
+        let preset_combo = gtk4::DropDown::builder().build();
+        let preset_combo_model = toolkit::ComboModel::with_map(&[(&i18n("No preset"), 0)]);
+        let label = gtk4::Label::with_mnemonic(&i18n("_Preset:"));
+        label.set_mnemonic_widget(Some(&preset_combo));
+        label.set_xalign(1.0);
+        grid.attach(&label, 0, 2, 1, 1);
+        grid.attach(&preset_combo, 1, 2, 1, 1);
+        content.append(&grid);
+
+        let add_button = gtk4::Button::builder()
+            .label(i18n("_Watch"))
+            .use_underline(true)
+            .halign(gtk4::Align::End)
+            .sensitive(false)
+            .build();
+        add_button.add_css_class("suggested-action");
+        content.append(&add_button);
+        vbox.append(&content);
+
+        let dialog = adw::Window::builder()
+            .content(&vbox)
+            .default_width(500)
+            .default_height(450)
+            .build();
+
+        (
+            dialog,
+            Widgets {
+                folders_list,
+                source_button,
+                source_label,
+                copy_check,
+                dest_button,
+                dest_label,
+                preset_combo,
+                preset_combo_model,
+                add_button,
+            },
+        )
+    }
+
+    fn connect_widgets(&self) {
+        let sender = self.sender();
+        self.widgets.source_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::ChooseSource, sender)
+        ));
+        self.widgets.copy_check.connect_toggled(glib::clone!(
+            #[strong]
+            sender,
+            move |check| send_async_local!(Event::CopyToggled(check.is_active()), sender)
+        ));
+        self.widgets.dest_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::ChooseDest, sender)
+        ));
+        self.widgets
+            .add_button
+            .connect_clicked(move |_| send_async_local!(Event::Add, sender));
+    }
+
+    fn load_folders(&self) {
+        let sender = self.sender();
+        self.client
+            .get_all_watched_folders(Box::new(move |folders| {
+                send_async_any!(Event::FoldersLoaded(folders), sender);
+            }));
+    }
+
+    /// The watched folders changed. Reload them.
+    fn folders_changed(&self) {
+        self.load_folders();
+        self.emit(());
+    }
+
+    fn folders_loaded(&self, folders: &[WatchedFolder]) {
+        let list = &self.widgets.folders_list;
+        list.remove_all();
+        let presets = &self.state.borrow().presets;
+        for folder in folders {
+            let mut detail = folder
+                .dest()
+                .map(|dest| i18n_format!("Copied to {}", dest.display()))
+                .unwrap_or_else(|| i18n("Imported in place"));
+            if let Some(preset) = presets
+                .iter()
+                .find(|preset| preset.id() == folder.preset_id())
+            {
+                detail = i18n_format!("{}, with the preset {}", detail, preset.name());
+            }
+            let row = adw::ActionRow::builder()
+                .use_markup(false)
+                .title(folder.path().to_string_lossy())
+                .subtitle(detail)
+                .build();
+            let remove_button = gtk4::Button::from_icon_name("user-trash-symbolic");
+            remove_button.set_valign(gtk4::Align::Center);
+            remove_button.set_tooltip_text(Some(&i18n("Stop watching")));
+            remove_button.add_css_class("flat");
+            let sender = self.sender();
+            let id = folder.id();
+            remove_button.connect_clicked(move |_| send_async_local!(Event::Remove(id), sender));
+            row.add_suffix(&remove_button);
+            list.append(&row);
+        }
+    }
+
+    fn presets_loaded(&self, presets: Vec<MetadataPreset>) {
+        let model = &self.widgets.preset_combo_model;
+        for preset in &presets {
+            model.push(preset.name(), preset.id());
+        }
+        model.bind(&self.widgets.preset_combo, |_| {});
+        self.state.borrow_mut().presets = presets;
+        // The folders display the presets.
+        self.load_folders();
+    }
+
+    fn update_add_button(&self) {
+        let state = self.state.borrow();
+        self.widgets
+            .add_button
+            .set_sensitive(state.source.is_some() && (!state.copy || state.dest.is_some()));
+    }
+
+    fn add(&self) {
+        let state = self.state.borrow();
+        let Some(source) = state.source.clone() else {
+            return;
+        };
+        let dest = state.dest.clone().filter(|_| state.copy);
+        let selected = self.widgets.preset_combo.selected() as usize;
+        let preset_id = if selected < self.widgets.preset_combo_model.len() {
+            self.widgets.preset_combo_model.value(selected)
+        } else {
+            0
+        };
+        self.client.save_watched_folder(source, dest, preset_id);
+        self.folders_changed();
+    }
+
+    fn choose_folder<F>(&self, title: &str, event: F)
+    where
+        F: Fn(PathBuf) -> Event + 'static,
+    {
+        #[allow(deprecated)]
+        let dialog = gtk4::FileChooserDialog::new(
+            Some(title),
+            Some(self.dialog.upcast_ref::<gtk4::Window>()),
+            gtk4::FileChooserAction::SelectFolder,
+            &[
+                (&i18n("Select"), gtk4::ResponseType::Accept),
+                (&i18n("Cancel"), gtk4::ResponseType::Cancel),
+            ],
+        );
+        dialog.set_modal(true);
+        let sender = self.sender();
+        #[allow(deprecated)]
+        dialog.connect_response(move |d, response| {
+            if response == gtk4::ResponseType::Accept {
+                #[allow(deprecated)]
+                if let Some(path) = d.file().as_ref().and_then(gio::prelude::FileExt::path) {
+                    send_async_any!(event(path), sender);
+                }
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+}
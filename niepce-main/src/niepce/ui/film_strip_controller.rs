@@ -54,7 +54,8 @@ impl UiController for FilmStripController {
         &self
             .widgets
             .get_or_init(|| {
-                let thumb_strip_view = ThumbStripView::new(self.store.selection_model().clone());
+                let thumb_strip_view =
+                    ThumbStripView::new(self.store.selection_model().clone().upcast());
                 thumb_strip_view.set_item_height(120);
 
                 let thumb_nav = ThumbNav::new(&thumb_strip_view, ThumbNavMode::OneRow, true);
@@ -63,9 +63,7 @@ impl Controller for GridViewModule {
             GridMsg::ChangeRating(id, rating) => {
                 self.selection_controller.set_rating_of(id, rating)
             }
-            GridMsg::MetadataChanged(new, old) => {
-                self.selection_controller.set_properties(&new, &old)
-            }
+            GridMsg::MetadataChanged(new, _) => self.selection_controller.set_properties(&new),
         }
     }
 }
@@ -124,6 +122,7 @@ impl GridViewModule {
 
     fn build_widget(&mut self) {
         self.image_grid_view.set_vexpand(true);
+        self.image_grid_view.set_enable_rubberband(true);
         self.context_menu.set_parent(&*self.image_grid_view);
         self.context_menu.set_has_arrow(false);
         self.image_grid_view.connect_unrealize(glib::clone!(
@@ -37,7 +37,7 @@ pub struct ImageGridView {
 
 impl ImageGridView {
     pub fn new(
-        store: gtk4::SingleSelection,
+        store: impl IsA<gtk4::SelectionModel>,
         context_menu: Option<gtk4::PopoverMenu>,
         ui_provider: Option<Rc<UIDataProvider>>,
     ) -> Self {
@@ -18,7 +18,7 @@
  */
 
 use std::cell::{Cell, OnceCell, RefCell};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::sync::Arc;
 
 use gtk4::prelude::*;
@@ -44,25 +44,34 @@ enum CurrentContainer {
     Keyword(LibraryId),
     #[allow(dead_code)]
     Album(LibraryId),
+    #[allow(dead_code)]
+    SmartCollection(LibraryId),
+    #[allow(dead_code)]
+    Project(LibraryId),
+    Search,
 }
 
 /// The Image list store.
 /// It wraps the tree model/store.
 pub struct ImageListStore {
     store: gio::ListStore,
-    model: gtk4::SingleSelection,
+    model: gtk4::MultiSelection,
     config: Arc<Configuration>,
     current: Cell<CurrentContainer>,
+    /// Map the file id to the position in the store.
     idmap: RefCell<BTreeMap<LibraryId, u32>>,
+    /// All the items, in content order, including the ones hidden in
+    /// collapsed stacks.
+    items: RefCell<Vec<(LibraryId, ImageListItem)>>,
+    /// The stacks that are expanded.
+    expanded_stacks: RefCell<BTreeSet<LibraryId>>,
     image_loading_icon: OnceCell<gtk4::IconPaintable>,
 }
 
 impl ImageListStore {
     pub fn new(config: Arc<Configuration>) -> Self {
         let store = gio::ListStore::new::<ImageListItem>();
-        let model = gtk4::SingleSelection::new(Some(store.clone()));
-        model.set_autoselect(false);
-        model.set_can_unselect(true);
+        let model = gtk4::MultiSelection::new(Some(store.clone()));
 
         Self {
             store,
@@ -70,6 +79,8 @@ impl ImageListStore {
             config,
             current: Cell::new(CurrentContainer::None),
             idmap: RefCell::new(BTreeMap::new()),
+            items: RefCell::default(),
+            expanded_stacks: RefCell::default(),
             image_loading_icon: OnceCell::new(),
         }
     }
@@ -85,7 +96,7 @@ impl ImageListStore {
     }
 
     /// Return the `Gtk::SelectionModel`
-    pub fn selection_model(&self) -> &gtk4::SingleSelection {
+    pub fn selection_model(&self) -> &gtk4::MultiSelection {
         &self.model
     }
 
@@ -117,19 +128,109 @@ impl ImageListStore {
     pub fn clear_content(&self) {
         // clear the map before the list.
         self.idmap.borrow_mut().clear();
+        self.items.borrow_mut().clear();
+        self.expanded_stacks.borrow_mut().clear();
         self.store.remove_all();
     }
 
-    fn add_libfile(&self, f: &LibFile) {
+    fn new_item(&self, f: &LibFile) -> ImageListItem {
         let icon = self.get_loading_icon().clone();
-        let iter = self.add_row(Some(icon.upcast()), f.clone(), FileStatus::Ok);
-        self.idmap.borrow_mut().insert(f.id(), iter);
+        ImageListItem::new(Some(icon.upcast()), Some(f.clone()), FileStatus::Ok)
+    }
+
+    /// Add a file that isn't stacked.
+    fn add_libfile(&self, f: &LibFile) {
+        let item = self.new_item(f);
+        self.items.borrow_mut().push((f.id(), item.clone()));
+        self.store.append(&item);
+        self.idmap
+            .borrow_mut()
+            .insert(f.id(), self.store.n_items() - 1);
     }
 
     fn add_libfiles(&self, content: &[LibFile]) {
-        for f in content.iter() {
-            self.add_libfile(f);
+        self.items
+            .borrow_mut()
+            .extend(content.iter().map(|f| (f.id(), self.new_item(f))));
+        self.layout();
+    }
+
+    /// Fill the store with the items. A collapsed stack only shows its
+    /// top, or the first file if the top isn't there. An expanded stack
+    /// shows its files after the top.
+    fn layout(&self) {
+        let visible = self.visible_items();
+        {
+            let mut idmap = self.idmap.borrow_mut();
+            idmap.clear();
+            idmap.extend(
+                visible
+                    .iter()
+                    .enumerate()
+                    .map(|(pos, (id, _))| (*id, pos as u32)),
+            );
+        }
+        let visible = visible
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect::<Vec<_>>();
+        self.store.splice(0, self.store.n_items(), &visible);
+    }
+
+    fn visible_items(&self) -> Vec<(LibraryId, ImageListItem)> {
+        let items = self.items.borrow();
+        let expanded = self.expanded_stacks.borrow();
+        let mut tops = BTreeMap::<LibraryId, LibraryId>::new();
+        let mut stacked = BTreeMap::<LibraryId, Vec<(LibraryId, &ImageListItem)>>::new();
+        for (id, item) in items.iter() {
+            let Some(file) = item.file() else {
+                continue;
+            };
+            let stack_id = file.stack_id();
+            if stack_id == 0 {
+                continue;
+            }
+            if file.is_stack_top() {
+                tops.insert(stack_id, *id);
+            } else {
+                tops.entry(stack_id).or_insert(*id);
+            }
+            stacked.entry(stack_id).or_default().push((*id, item));
         }
+
+        let mut visible = vec![];
+        for (id, item) in items.iter() {
+            let stack_id = item.file().map(|file| file.stack_id()).unwrap_or(0);
+            if stack_id == 0 {
+                visible.push((*id, item));
+            } else if tops.get(&stack_id) == Some(id) {
+                visible.push((*id, item));
+                if expanded.contains(&stack_id) {
+                    if let Some(files) = stacked.get(&stack_id) {
+                        visible.extend(files.iter().filter(|(file_id, _)| file_id != id).copied());
+                    }
+                }
+            }
+        }
+
+        visible
+            .into_iter()
+            .map(|(id, item)| (id, item.clone()))
+            .collect()
+    }
+
+    /// Expand or collapse the stack `stack_id`.
+    pub fn toggle_stack(&self, stack_id: LibraryId) {
+        if stack_id == 0 {
+            return;
+        }
+        {
+            let mut expanded = self.expanded_stacks.borrow_mut();
+            if !expanded.remove(&stack_id) {
+                expanded.insert(stack_id);
+            }
+        }
+        self.layout();
     }
 
     /// Process the notification.
@@ -154,11 +255,17 @@ impl ImageListStore {
             }
             FolderContentQueried(ref c)
             | KeywordContentQueried(ref c)
-            | AlbumContentQueried(ref c) => {
+            | AlbumContentQueried(ref c)
+            | SmartCollectionContentQueried(ref c)
+            | ProjectContentQueried(ref c)
+            | SearchResultsQueried(ref c) => {
                 self.current.set(match *notification {
                     FolderContentQueried(_) => CurrentContainer::Folder(c.id),
                     KeywordContentQueried(_) => CurrentContainer::Keyword(c.id),
                     AlbumContentQueried(_) => CurrentContainer::Album(c.id),
+                    SmartCollectionContentQueried(_) => CurrentContainer::SmartCollection(c.id),
+                    ProjectContentQueried(_) => CurrentContainer::Project(c.id),
+                    SearchResultsQueried(_) => CurrentContainer::Search,
                     _ => CurrentContainer::None,
                 });
                 self.clear_content();
@@ -174,31 +281,69 @@ impl ImageListStore {
                     if param.from == current_folder {
                         // remove from list
                         dbg_out!("from this folder");
-                        if let Some(pos) = self.pos_from_id(param.file) {
-                            self.store.remove(pos);
-                            self.idmap.borrow_mut().remove(&param.file);
-                        }
+                        self.remove_file(param.file);
                     } else if param.to == current_folder {
                         // XXX add to list. but this isn't likely to happen atm.
                     }
                 }
                 true
             }
+            AddedVirtualCopy(ref file) => {
+                if matches!(self.current.get(), CurrentContainer::Folder(id) if id == file.folder_id())
+                {
+                    self.add_libfile(file);
+                    thumbnail_cache.request(&[file.as_ref().clone()]);
+                }
+                self.add_copy_count(file.master_id(), 1);
+                true
+            }
+            FileDeleted(id) => {
+                if let Some(file) = self.remove_file(id) {
+                    self.add_copy_count(file.master_id(), -1);
+                }
+                true
+            }
+            StackChanged(ref files) => {
+                for file in files {
+                    if let Some(item) = self.item(file.id()) {
+                        item.set_file(Some(file.clone()));
+                    }
+                }
+                self.layout();
+                true
+            }
             FileStatusChanged(ref status) => {
-                if let Some(pos) = self.idmap.borrow().get(&status.id) {
-                    if let Some(item) = self.store.item(*pos).and_downcast::<ImageListItem>() {
-                        item.set_file_status(status.status);
+                if let Some(item) = self.item(status.id) {
+                    item.set_file_status(status.status);
+                }
+                true
+            }
+            MissingFiles(ref ids) => {
+                for id in ids {
+                    if let Some(item) = self.item(*id) {
+                        item.set_file_status(FileStatus::Missing);
                     }
                 }
                 true
             }
+            FilesRelocated(ref files) => {
+                let mut relocated = vec![];
+                for file in files {
+                    if let Some(item) = self.item(file.id()) {
+                        item.set_file(Some(file.clone()));
+                        item.set_file_status(FileStatus::Ok);
+                        relocated.push(file.clone());
+                    }
+                }
+                // The thumbnails couldn't be loaded from the old location.
+                thumbnail_cache.request(&relocated);
+                true
+            }
             MetadataChanged(ref m) => {
                 dbg_out!("metadata changed {:?}", m.meta);
                 // only interested in a few props
                 if Self::is_property_interesting(m.meta) {
-                    if let Some(pos) = self.idmap.borrow().get(&m.id) {
-                        self.set_property(*pos, m);
-                    }
+                    self.set_property(m);
                 }
                 true
             }
@@ -211,6 +356,39 @@ impl ImageListStore {
         }
     }
 
+    /// Get the item for the file `id`, even if it is hidden in a stack.
+    fn item(&self, id: LibraryId) -> Option<ImageListItem> {
+        if let Some(pos) = self.pos_from_id(id) {
+            return self.store.item(pos).and_downcast::<ImageListItem>();
+        }
+        self.items
+            .borrow()
+            .iter()
+            .find(|(item_id, _)| *item_id == id)
+            .map(|(_, item)| item.clone())
+    }
+
+    /// Remove the file `id` from the store. Return the file removed.
+    fn remove_file(&self, id: LibraryId) -> Option<LibFile> {
+        let file = {
+            let mut items = self.items.borrow_mut();
+            let index = items.iter().position(|(item_id, _)| *item_id == id)?;
+            items.remove(index).1.file()
+        };
+        self.layout();
+        file
+    }
+
+    /// Change the copy count of the master `id` by `delta`.
+    fn add_copy_count(&self, id: LibraryId, delta: i32) {
+        if let Some(item) = self.item(id) {
+            if let Some(mut file) = item.file() {
+                file.set_copy_count(file.copy_count().saturating_add_signed(delta));
+                item.set_file(Some(file));
+            }
+        }
+    }
+
     pub fn get_file_id_at_pos(&self, pos: u32) -> LibraryId {
         self.store
             .item(pos)
@@ -221,36 +399,18 @@ impl ImageListStore {
     }
 
     pub fn file(&self, id: LibraryId) -> Option<LibFile> {
-        self.idmap.borrow().get(&id).and_then(|pos| {
-            self.store
-                .item(*pos)
-                .and_downcast_ref::<ImageListItem>()
-                .and_then(ImageListItem::file)
-        })
-    }
-
-    pub fn add_row(
-        &self,
-        thumbnail: Option<gdk4::Paintable>,
-        file: LibFile,
-        file_status: FileStatus,
-    ) -> u32 {
-        let item = ImageListItem::new(thumbnail, Some(file), file_status);
-        self.store.append(&item);
-        self.store.n_items() - 1
+        self.item(id).and_then(|item| item.file())
     }
 
     pub fn set_thumbnail(&self, id: LibraryId, thumb: &gdk4::Texture) {
-        if let Some(pos) = self.idmap.borrow().get(&id) {
-            if let Some(item) = self.store.item(*pos).and_downcast_ref::<ImageListItem>() {
-                let thumb = thumb.clone();
-                item.set_thumbnail(Some(thumb.upcast::<gdk4::Paintable>()));
-            }
+        if let Some(item) = self.item(id) {
+            let thumb = thumb.clone();
+            item.set_thumbnail(Some(thumb.upcast::<gdk4::Paintable>()));
         }
     }
 
-    pub fn set_property(&self, pos: u32, change: &MetadataChange) {
-        if let Some(item) = self.store.item(pos).and_downcast_ref::<ImageListItem>() {
+    pub fn set_property(&self, change: &MetadataChange) {
+        if let Some(item) = self.item(change.id) {
             if let Some(mut file) = item.file() {
                 assert!(file.id() == change.id);
                 let meta = change.meta;
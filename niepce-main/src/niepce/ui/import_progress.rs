@@ -0,0 +1,297 @@
+/*
+ * niepce - niepce/ui/import_progress.rs
+ *
+ * Copyright (C) 2025 Hubert Figuière
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! The panel showing the progress of the import jobs.
+
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use gettextrs::gettext as i18n;
+use gtk4::prelude::*;
+use i18n_format::i18n_format;
+use npc_fwk::{gio, glib, gtk4};
+
+use npc_engine::importer::{ImportJobId, ImportProgress, ImportSummary};
+use npc_engine::library::notification::LibNotification;
+use npc_fwk::toolkit::{Controller, ControllerImplCell, UiController};
+use npc_fwk::{dbg_out, send_async_local};
+
+pub enum Event {
+    Progress(ImportProgress),
+    Finished(Box<ImportSummary>),
+    /// Cancel the job.
+    Cancel(ImportJobId),
+    /// Show the details of the finished job.
+    Details(ImportJobId),
+    /// Remove the finished job.
+    Dismiss(ImportJobId),
+}
+
+/// The row of an import job.
+struct JobRow {
+    widget: gtk4::Box,
+    label: gtk4::Label,
+    progress_bar: gtk4::ProgressBar,
+    cancel_button: gtk4::Button,
+    details_button: gtk4::Button,
+    dismiss_button: gtk4::Button,
+    summary: Option<Box<ImportSummary>>,
+}
+
+struct Widgets {
+    widget_: gtk4::Widget,
+    vbox: gtk4::Box,
+}
+
+/// The import progress panel. Emit the ID of the job to cancel.
+pub struct ImportProgressPanel {
+    imp_: ControllerImplCell<Event, ImportJobId>,
+    widgets: OnceCell<Widgets>,
+    jobs: RefCell<HashMap<ImportJobId, JobRow>>,
+}
+
+impl Controller for ImportProgressPanel {
+    type InMsg = Event;
+    type OutMsg = ImportJobId;
+
+    npc_fwk::controller_imp_imp!(imp_);
+
+    fn dispatch(&self, e: Event) {
+        match e {
+            Event::Progress(progress) => self.progress(&progress),
+            Event::Finished(summary) => self.finished(summary),
+            Event::Cancel(job) => {
+                if let Some(row) = self.jobs.borrow().get(&job) {
+                    row.cancel_button.set_sensitive(false);
+                    row.label.set_label(&i18n("Cancelling…"));
+                }
+                self.emit(job);
+            }
+            Event::Details(job) => self.details(job),
+            Event::Dismiss(job) => self.dismiss(job),
+        }
+    }
+}
+
+impl UiController for ImportProgressPanel {
+    fn widget(&self) -> &gtk4::Widget {
+        &self
+            .widgets
+            .get_or_init(|| {
+                let vbox = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+                vbox.set_margin_start(6);
+                vbox.set_margin_end(6);
+                vbox.set_visible(false);
+                Widgets {
+                    widget_: vbox.clone().upcast(),
+                    vbox,
+                }
+            })
+            .widget_
+    }
+
+    fn actions(&self) -> Option<(&str, &gio::ActionGroup)> {
+        None
+    }
+}
+
+impl ImportProgressPanel {
+    pub fn new() -> Rc<ImportProgressPanel> {
+        let panel = Rc::new(ImportProgressPanel {
+            imp_: ControllerImplCell::default(),
+            widgets: OnceCell::new(),
+            jobs: RefCell::default(),
+        });
+
+        <Self as Controller>::start(&panel);
+
+        panel
+    }
+
+    pub fn on_lib_notification(&self, ln: &LibNotification) {
+        match ln {
+            LibNotification::ImportProgress(progress) => {
+                self.send(Event::Progress(progress.clone()))
+            }
+            LibNotification::ImportFinished(summary) => self.send(Event::Finished(summary.clone())),
+            _ => {}
+        }
+    }
+
+    /// Create the row for `job`.
+    fn add_row(&self, job: ImportJobId) -> JobRow {
+        let widget = gtk4::Box::new(gtk4::Orientation::Horizontal, 6);
+        let label = gtk4::Label::new(None);
+        label.set_xalign(0.0);
+        let progress_bar = gtk4::ProgressBar::new();
+        progress_bar.set_hexpand(true);
+        progress_bar.set_valign(gtk4::Align::Center);
+        let cancel_button = gtk4::Button::from_icon_name("process-stop-symbolic");
+        cancel_button.set_tooltip_text(Some(&i18n("Cancel the import")));
+        let details_button = gtk4::Button::with_label(&i18n("Details…"));
+        details_button.set_visible(false);
+        let dismiss_button = gtk4::Button::from_icon_name("window-close-symbolic");
+        dismiss_button.set_visible(false);
+        for widget_ in [
+            label.upcast_ref::<gtk4::Widget>(),
+            progress_bar.upcast_ref(),
+            cancel_button.upcast_ref(),
+            details_button.upcast_ref(),
+            dismiss_button.upcast_ref(),
+        ] {
+            widget.append(widget_);
+        }
+
+        let sender = self.sender();
+        cancel_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::Cancel(job), sender)
+        ));
+        details_button.connect_clicked(glib::clone!(
+            #[strong]
+            sender,
+            move |_| send_async_local!(Event::Details(job), sender)
+        ));
+        dismiss_button.connect_clicked(move |_| send_async_local!(Event::Dismiss(job), sender));
+
+        if let Some(widgets) = self.widgets.get() {
+            widgets.vbox.append(&widget);
+            widgets.vbox.set_visible(true);
+        }
+
+        JobRow {
+            widget,
+            label,
+            progress_bar,
+            cancel_button,
+            details_button,
+            dismiss_button,
+            summary: None,
+        }
+    }
+
+    fn progress(&self, progress: &ImportProgress) {
+        let mut jobs = self.jobs.borrow_mut();
+        let row = jobs
+            .entry(progress.job)
+            .or_insert_with(|| self.add_row(progress.job));
+        if !row.cancel_button.is_sensitive() {
+            // Cancelling.
+            return;
+        }
+        if progress.bundles_total > 0 {
+            row.label.set_label(&i18n_format!(
+                "Adding to the catalog: {} of {}",
+                progress.bundles_added,
+                progress.bundles_total
+            ));
+            row.progress_bar
+                .set_fraction(progress.bundles_added as f64 / progress.bundles_total as f64);
+        } else if progress.files_total > 0 {
+            row.label.set_label(&i18n_format!(
+                "Copying: {} of {} files ({})",
+                progress.files_copied,
+                progress.files_total,
+                glib::format_size(progress.bytes_copied)
+            ));
+            row.progress_bar
+                .set_fraction(progress.files_copied as f64 / progress.files_total as f64);
+        } else {
+            row.label.set_label(&i18n("Importing…"));
+            row.progress_bar.pulse();
+        }
+    }
+
+    fn finished(&self, summary: Box<ImportSummary>) {
+        dbg_out!("Import job {} finished", summary.job);
+        let mut jobs = self.jobs.borrow_mut();
+        let row = jobs
+            .entry(summary.job)
+            .or_insert_with(|| self.add_row(summary.job));
+        let mut label = i18n_format!(
+            "{} imported, {} skipped, {} failed",
+            summary.imported.len(),
+            summary.skipped.len(),
+            summary.failed.len()
+        );
+        if !summary.warnings.is_empty() {
+            label = i18n_format!("{}, {} with warnings", label, summary.warnings.len());
+        }
+        if summary.cancelled {
+            label = i18n_format!("Import cancelled: {}", label);
+        }
+        row.label.set_label(&label);
+        row.progress_bar.set_visible(false);
+        row.cancel_button.set_visible(false);
+        row.details_button.set_visible(
+            !summary.failed.is_empty()
+                || !summary.warnings.is_empty()
+                || summary.manifest.is_some(),
+        );
+        row.dismiss_button.set_visible(true);
+        row.summary = Some(summary);
+    }
+
+    /// Show the files that failed to be imported, and the warnings.
+    fn details(&self, job: ImportJobId) {
+        let jobs = self.jobs.borrow();
+        let Some(summary) = jobs.get(&job).and_then(|row| row.summary.as_ref()) else {
+            return;
+        };
+        let mut detail = summary
+            .failed
+            .iter()
+            .chain(summary.warnings.iter())
+            .map(|(file, err)| format!("{}: {err}", file.display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(ref manifest) = summary.manifest {
+            detail.push_str("\n\n");
+            detail.push_str(&i18n_format!(
+                "Import again to the same destination to resume. The progress is kept in {}.",
+                manifest.display()
+            ));
+        }
+        let dialog = gtk4::AlertDialog::builder()
+            .message(
+                if summary.failed.is_empty() && !summary.warnings.is_empty() {
+                    i18n_format!("{} files imported with warnings", summary.warnings.len())
+                } else {
+                    i18n_format!("{} files couldn't be imported", summary.failed.len())
+                },
+            )
+            .detail(detail)
+            .build();
+        let parent = self.widget().root().and_downcast::<gtk4::Window>();
+        dialog.show(parent.as_ref());
+    }
+
+    fn dismiss(&self, job: ImportJobId) {
+        if let Some(row) = self.jobs.borrow_mut().remove(&job)
+            && let Some(widgets) = self.widgets.get()
+        {
+            widgets.vbox.remove(&row.widget);
+        }
+        if let Some(widgets) = self.widgets.get() {
+            widgets.vbox.set_visible(!self.jobs.borrow().is_empty());
+        }
+    }
+}
@@ -264,6 +264,79 @@ impl LibraryCellRendererPriv {
         snapshot.restore();
     }
 
+    /// Draw the virtual copy badge: two stacked squares, top left.
+    /// For a master, the number of copies is drawn next to it.
+    /// Return the width drawn.
+    fn do_draw_copy_badge(cr: &cairo::Context, file: &LibFile, left: f32, r: &Rect) -> f32 {
+        const BADGE_SIZE: f64 = 10.0;
+        const BADGE_OFFSET: f64 = 4.0;
+        if !file.is_copy() && file.copy_count() == 0 {
+            return 0.0;
+        }
+        let x = (r.x() + CELL_PADDING + left) as f64;
+        let y = (r.y() + CELL_PADDING) as f64;
+
+        cr.set_line_width(1.0);
+        cr.rectangle(x + BADGE_OFFSET, y, BADGE_SIZE, BADGE_SIZE);
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        on_err_out!(cr.stroke());
+        cr.rectangle(x, y + BADGE_OFFSET, BADGE_SIZE, BADGE_SIZE);
+        let mut width = BADGE_OFFSET + BADGE_SIZE;
+        if file.is_copy() {
+            on_err_out!(cr.fill());
+        } else {
+            cr.set_source_rgb(0.0, 0.0, 0.0);
+            on_err_out!(cr.fill_preserve());
+            cr.set_source_rgb(1.0, 1.0, 1.0);
+            on_err_out!(cr.stroke());
+
+            let text = file.copy_count().to_string();
+            cr.set_font_size(BADGE_SIZE);
+            cr.move_to(x + width + 2.0, y + BADGE_OFFSET + BADGE_SIZE);
+            on_err_out!(cr.show_text(&text));
+            if let Ok(extents) = cr.text_extents(&text) {
+                width += 2.0 + extents.x_advance();
+            }
+        }
+
+        width as f32 + CELL_PADDING
+    }
+
+    /// Draw the stack badge on the top of a stack: the number of files
+    /// in the stack, top left.
+    fn do_draw_stack_badge(cr: &cairo::Context, file: &LibFile, left: f32, r: &Rect) {
+        const BADGE_HEIGHT: f64 = 14.0;
+        const BADGE_MARGIN: f64 = 3.0;
+        if !file.is_stack_top() {
+            return;
+        }
+        let text = file.stack_count().to_string();
+        cr.set_font_size(BADGE_HEIGHT - 2.0 * BADGE_MARGIN + 2.0);
+        let Ok(extents) = cr.text_extents(&text) else {
+            return;
+        };
+        let x = (r.x() + CELL_PADDING + left) as f64;
+        let y = (r.y() + CELL_PADDING) as f64;
+        let width = extents.x_advance() + 2.0 * BADGE_MARGIN;
+
+        cr.set_line_width(1.0);
+        // The cards behind.
+        cr.rectangle(x + 2.0, y - 2.0, width, BADGE_HEIGHT);
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        on_err_out!(cr.stroke());
+        cr.rectangle(x, y, width, BADGE_HEIGHT);
+        cr.set_source_rgb(0.0, 0.0, 0.0);
+        on_err_out!(cr.fill_preserve());
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        on_err_out!(cr.stroke());
+
+        cr.move_to(
+            x + BADGE_MARGIN,
+            y + (BADGE_HEIGHT - extents.height()) / 2.0 - extents.y_bearing(),
+        );
+        on_err_out!(cr.show_text(&text));
+    }
+
     fn do_draw_format_emblem(snapshot: &gtk4::Snapshot, emblem: &Texture, r: &Rect) -> f32 {
         let w = emblem.width() as f32;
         let h = emblem.height() as f32;
@@ -503,8 +576,16 @@ impl WidgetImpl for LibraryCellRendererPriv {
         }
 
         let status = self.status.get();
+        let mut left = 0.0;
         if self.draw_status.get() && status != FileStatus::Ok {
             Self::do_draw_status(snapshot, status, &r);
+            left = EMBLEMS.status_missing.width() as f32 + CELL_PADDING;
+        }
+        if let Some(f) = &*file {
+            if self.draw_emblem.get() {
+                left += Self::do_draw_copy_badge(&cr, f, left, &r);
+            }
+            Self::do_draw_stack_badge(&cr, f, left, &r);
         }
 
         if self.draw_emblem.get() {
@@ -25,15 +25,39 @@ use glib::SignalHandlerId;
 use gtk4::prelude::*;
 use npc_fwk::{glib, gtk4};
 
-use npc_engine::NiepcePropertySet;
 use npc_engine::catalog;
 use npc_engine::catalog::NiepcePropertyIdx;
+use npc_engine::{NiepcePropertyBag, NiepcePropertySet};
 use npc_fwk::toolkit::widgets::MetadataPropertyBag;
 use npc_fwk::toolkit::widgets::{MetaDT, MetadataFormat, MetadataSectionFormat, MetadataWidget};
 use npc_fwk::toolkit::{Controller, ControllerImplCell, UiController};
 use npc_fwk::{PropertyBag, dbg_out, send_async_local};
 
 lazy_static::lazy_static! {
+    static ref RIGHTS_FORMATS: Vec<MetadataFormat> = vec![
+        MetadataFormat{ label: i18n("Creator:"), id: NiepcePropertyIdx::NpDcCreatorProp as u32, type_:MetaDT::StringArray, readonly: false },
+        MetadataFormat{ label: i18n("Copyright:"), id: NiepcePropertyIdx::NpDcRightsProp as u32, type_:MetaDT::STRING, readonly: false },
+        MetadataFormat{ label: i18n("Credit:"), id: NiepcePropertyIdx::NpIptcCreditProp as u32, type_:MetaDT::STRING, readonly: false },
+        MetadataFormat{ label: i18n("Source:"), id: NiepcePropertyIdx::NpIptcSourceProp as u32, type_:MetaDT::STRING, readonly: false },
+    ];
+    /// The formats for editing a metadata preset.
+    static ref PRESET_FORMATS: Vec<MetadataSectionFormat> = vec![
+        MetadataSectionFormat{
+            section: i18n("Rights"),
+            formats: RIGHTS_FORMATS.clone(),
+        },
+        MetadataSectionFormat{
+            section: i18n("IPTC"),
+            formats: vec![
+                MetadataFormat{ label: i18n("Headline:"), id: NiepcePropertyIdx::NpIptcHeadlineProp as u32, type_:MetaDT::STRING, readonly: false },
+                MetadataFormat{ label: i18n("Caption:"), id: NiepcePropertyIdx::NpIptcDescriptionProp as u32, type_:MetaDT::TEXT, readonly: false },
+                MetadataFormat{ label: i18n("Keywords:"), id: NiepcePropertyIdx::NpIptcKeywordsProp as u32, type_:MetaDT::StringArray, readonly: false },
+                MetadataFormat{ label: i18n("City:"), id: NiepcePropertyIdx::NpIptcCityProp as u32, type_:MetaDT::STRING, readonly: false },
+                MetadataFormat{ label: i18n("State:"), id: NiepcePropertyIdx::NpIptcStateProp as u32, type_:MetaDT::STRING, readonly: false },
+                MetadataFormat{ label: i18n("Country:"), id: NiepcePropertyIdx::NpIptcCountryProp as u32, type_:MetaDT::STRING, readonly: false },
+            ]
+        },
+    ];
     static ref FORMATS: Vec<MetadataSectionFormat> = vec![
         MetadataSectionFormat{
             section: i18n("File Information"),
@@ -77,11 +101,14 @@ lazy_static::lazy_static! {
                 // FIXME change this type to the right one when there is a widget
                 MetadataFormat{ label: i18n("Label:"), id: NiepcePropertyIdx::NpXmpLabelProp as u32, type_:MetaDT::STRING, readonly: true },
                 MetadataFormat{ label: i18n("Keywords:"), id: NiepcePropertyIdx::NpIptcKeywordsProp as u32, type_:MetaDT::StringArray, readonly: false },
+                MetadataFormat{ label: i18n("City:"), id: NiepcePropertyIdx::NpIptcCityProp as u32, type_:MetaDT::STRING, readonly: false },
+                MetadataFormat{ label: i18n("State:"), id: NiepcePropertyIdx::NpIptcStateProp as u32, type_:MetaDT::STRING, readonly: false },
+                MetadataFormat{ label: i18n("Country:"), id: NiepcePropertyIdx::NpIptcCountryProp as u32, type_:MetaDT::STRING, readonly: false },
             ]
         },
         MetadataSectionFormat{
             section: i18n("Rights"),
-            formats: vec![]
+            formats: RIGHTS_FORMATS.clone(),
         },
         MetadataSectionFormat{
             section: i18n("Processing"),
@@ -96,6 +123,11 @@ pub fn get_format() -> &'static [MetadataSectionFormat] {
     &FORMATS
 }
 
+/// The format for editing a metadata preset.
+pub fn get_preset_format() -> &'static [MetadataSectionFormat] {
+    &PRESET_FORMATS
+}
+
 pub enum MetadataInputMsg {
     MetadataChanged(MetadataPropertyBag, MetadataPropertyBag),
 }
@@ -132,6 +164,11 @@ impl UiController for MetadataPaneController {
 
 impl MetadataPaneController {
     pub fn new() -> Rc<MetadataPaneController> {
+        Self::with_format(get_format())
+    }
+
+    /// Create the pane to display the sections in `formats`.
+    pub fn with_format(formats: &'static [MetadataSectionFormat]) -> Rc<MetadataPaneController> {
         let mut ctrl = MetadataPaneController {
             imp_: ControllerImplCell::default(),
             vbox: gtk4::Box::new(gtk4::Orientation::Vertical, 0),
@@ -140,7 +177,7 @@ impl MetadataPaneController {
             fileid: Cell::new(0),
         };
 
-        ctrl.build_widget();
+        ctrl.build_widget(formats);
 
         let ctrl = Rc::new(ctrl);
 
@@ -149,8 +186,7 @@ impl MetadataPaneController {
         ctrl
     }
 
-    fn build_property_set(&mut self) {
-        let formats = get_format();
+    fn build_property_set(&mut self, formats: &[MetadataSectionFormat]) {
         for current in formats {
             for format in &current.formats {
                 self.propset.insert(format.id.into());
@@ -158,9 +194,8 @@ impl MetadataPaneController {
         }
     }
 
-    fn build_widget(&mut self) {
-        self.build_property_set();
-        let formats = get_format();
+    fn build_widget(&mut self, formats: &[MetadataSectionFormat]) {
+        self.build_property_set(formats);
         for current in formats {
             let w = MetadataWidget::new(&current.section);
             self.vbox.append(&w);
@@ -184,9 +219,13 @@ impl MetadataPaneController {
     pub fn display(&self, id: catalog::LibraryId, metadata: Option<&catalog::LibMetadata>) {
         self.fileid.set(id);
         dbg_out!("displaying metadata");
-        if let Some(meta) = metadata {
-            let properties = meta.to_properties(&self.propset);
+        let properties = metadata.map(|meta| meta.to_properties(&self.propset));
+        self.display_properties(properties.as_ref());
+    }
 
+    /// Display the `properties`, or nothing if `None`.
+    pub fn display_properties(&self, properties: Option<&NiepcePropertyBag>) {
+        if let Some(properties) = properties {
             // XXX this is bad performance. The problem is the widget
             // is generic and uses generic properties.
             //
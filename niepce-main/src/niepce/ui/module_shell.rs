@@ -416,6 +416,90 @@ impl ModuleShell {
             None,
         );
 
+        let section = gio::Menu::new();
+        shell.menu.append_section(None, &section);
+        add_menu_action(
+            group,
+            "CreateVirtualCopy",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.create_virtual_copy()
+            ),
+            &section,
+            Some(&i18n("Create Virtual Copy")),
+            Some("shell"),
+            Some("<Primary>apostrophe"),
+        );
+        add_menu_action(
+            group,
+            "DeleteVirtualCopy",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.delete_virtual_copy()
+            ),
+            &section,
+            Some(&i18n("Delete Virtual Copy")),
+            Some("shell"),
+            None,
+        );
+
+        let section = gio::Menu::new();
+        shell.menu.append_section(None, &section);
+        add_menu_action(
+            group,
+            "CreateStack",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.create_stack()
+            ),
+            &section,
+            Some(&i18n("Group into Stack")),
+            Some("shell"),
+            Some("<Primary>g"),
+        );
+        add_menu_action(
+            group,
+            "DeleteStack",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.delete_stack()
+            ),
+            &section,
+            Some(&i18n("Unstack")),
+            Some("shell"),
+            Some("<Primary><Shift>g"),
+        );
+        add_menu_action(
+            group,
+            "SetStackTop",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.set_stack_top()
+            ),
+            &section,
+            Some(&i18n("Move to Top of Stack")),
+            Some("shell"),
+            None,
+        );
+        add_menu_action(
+            group,
+            "ToggleStack",
+            glib::clone!(
+                #[weak(rename_to = selection_controller)]
+                shell.selection_controller,
+                move |_, _| selection_controller.toggle_stack()
+            ),
+            &section,
+            Some(&i18n("Expand or Collapse Stack")),
+            Some("shell"),
+            Some("<Primary><Shift>s"),
+        );
+
         let section = gio::Menu::new();
         shell.menu.append_section(None, &section);
         add_menu_action(
@@ -23,24 +23,28 @@ use std::sync::Arc;
 
 use adw::prelude::*;
 use gettextrs::gettext as i18n;
+use i18n_format::i18n_format;
 use npc_fwk::{adw, gio, glib, gtk4};
 
 use npc_engine::catalog;
 use npc_engine::library::CatalogPreferences;
+use npc_engine::library::journal::JournalEntry;
 use npc_engine::library::notification::LibNotification;
 use npc_engine::libraryclient::{ClientInterface, ClientInterfaceSync, LibraryClientHost};
 use npc_fwk::base::Moniker;
 use npc_fwk::base::rgbcolour::RgbColour;
 use npc_fwk::toolkit::{
     self, AppController, Controller, ControllerImplCell, DialogController, UiController,
-    WindowController, WindowSize,
+    WindowController, WindowSize, gtk_utils,
 };
 use npc_fwk::{dbg_out, err_out};
 
 use super::dialogs::EditLabels;
+use super::dialogs::journal_history::{self, JournalHistoryDialog};
 use super::film_strip_controller::FilmStripController;
+use super::import_progress::ImportProgressPanel;
 use super::module_shell::ModuleShell;
-use super::workspace_controller::WorkspaceController;
+use super::workspace_controller::{Event as WorkspaceEvent, WorkspaceController};
 use crate::NiepceApplication;
 #[cfg(feature = "python")]
 use crate::python::NiepcePython;
@@ -50,6 +54,16 @@ pub enum Event {
     Delete,
     ToggleToolsVisible,
     EditLabels,
+    /// Undo the last catalog operation from the journal.
+    Undo,
+    /// Redo the last undone catalog operation.
+    Redo,
+    /// Show the journal history.
+    History,
+    JournalChanged,
+    JournalListed(Vec<JournalEntry>),
+    /// Undoing (`true`) or redoing the entry with the label failed.
+    JournalFailed(String, bool),
     PythonEditor,
     OpenCatalog(std::path::PathBuf),
     NewLibraryCreated,
@@ -122,8 +136,15 @@ impl Widgets {
     }
 }
 
+/// The undo and redo actions.
+struct JournalActions {
+    undo: gio::SimpleAction,
+    redo: gio::SimpleAction,
+}
+
 struct ShellWidgets {
     _workspace: Rc<WorkspaceController>,
+    _import_progress: Rc<ImportProgressPanel>,
     shell: Rc<ModuleShell>,
     _filmstrip: Rc<FilmStripController>,
 }
@@ -137,6 +158,8 @@ pub struct NiepceWindow {
 
     widgets: OnceCell<Widgets>,
     shell_widgets: OnceCell<ShellWidgets>,
+    journal_actions: OnceCell<JournalActions>,
+    history_dialog: RefCell<Option<Rc<JournalHistoryDialog>>>,
 }
 
 impl Controller for NiepceWindow {
@@ -155,6 +178,24 @@ impl Controller for NiepceWindow {
                 }
             }
             EditLabels => self.on_action_edit_labels(),
+            Undo => {
+                if let Some(client) = self.libraryclient.borrow().as_ref() {
+                    client.client().undo_journal();
+                }
+            }
+            Redo => {
+                if let Some(client) = self.libraryclient.borrow().as_ref() {
+                    client.client().redo_journal();
+                }
+            }
+            History => self.on_action_history(),
+            JournalChanged => {
+                if let Some(client) = self.libraryclient.borrow().as_ref() {
+                    client.client().get_journal();
+                }
+            }
+            JournalListed(entries) => self.journal_listed(entries),
+            JournalFailed(label, undo) => self.journal_failed(&label, undo),
             ToggleToolsVisible => {
                 // XXX todo
             }
@@ -281,8 +322,33 @@ impl UiController for NiepceWindow {
             )
         );
 
-        npc_fwk::toolkit::create_undo_action(self.app.clone(), group);
-        npc_fwk::toolkit::create_redo_action(self.app.clone(), group);
+        // Undo and redo go through the catalog journal.
+        let undo = gtk_utils::add_action(
+            group,
+            "Undo",
+            glib::clone!(
+                #[strong]
+                tx,
+                move |_, _| npc_fwk::send_async_local!(Event::Undo, tx)
+            ),
+            Some("win"),
+            Some("<control>Z"),
+        );
+        undo.set_enabled(false);
+        let redo = gtk_utils::add_action(
+            group,
+            "Redo",
+            glib::clone!(
+                #[strong]
+                tx,
+                move |_, _| npc_fwk::send_async_local!(Event::Redo, tx)
+            ),
+            Some("win"),
+            Some("<control><shift>Z"),
+        );
+        redo.set_enabled(false);
+        let _ = self.journal_actions.set(JournalActions { undo, redo });
+        npc_fwk::sending_action!(group, "History", tx, Event::History);
 
         action!(group, "Cut", move |_, _| {});
         action!(group, "Copy", move |_, _| {});
@@ -329,6 +395,8 @@ impl NiepceWindow {
 
             widgets: OnceCell::new(),
             shell_widgets: OnceCell::new(),
+            journal_actions: OnceCell::new(),
+            history_dialog: RefCell::new(None),
         });
 
         <Self as Controller>::start(&ctrl);
@@ -346,7 +414,7 @@ impl NiepceWindow {
             client.create_label(i18n("Label 2"), RgbColour::new(24929, 55769, 4369));
             client.create_label(i18n("Label 3"), RgbColour::new(4369, 50629, 55769));
             client.create_label(i18n("Label 4"), RgbColour::new(35209, 4369, 55769));
-            client.create_label(i18n("Label 5"), RgbColour::new(35209, 4369, 55769));
+            client.create_label(i18n("Label 5"), RgbColour::new(55769, 55769, 4369));
         }
     }
 
@@ -359,6 +427,10 @@ impl NiepceWindow {
             LabelChanged(label) => npc_fwk::send_async_local!(Event::LabelChanged(label), tx),
             LabelDeleted(label_id) => npc_fwk::send_async_local!(Event::LabelDeleted(label_id), tx),
             DatabaseReady => npc_fwk::send_async_local!(Event::DatabaseReady, tx),
+            JournalChanged => npc_fwk::send_async_local!(Event::JournalChanged, tx),
+            JournalListed(entries) => npc_fwk::send_async_local!(Event::JournalListed(entries), tx),
+            UndoFailed(label) => npc_fwk::send_async_local!(Event::JournalFailed(label, true), tx),
+            RedoFailed(label) => npc_fwk::send_async_local!(Event::JournalFailed(label, false), tx),
             DatabaseNeedUpgrade(version) => {
                 npc_fwk::send_async_local!(Event::DatabaseNeedUpgrade(version), tx)
             }
@@ -487,7 +559,8 @@ impl NiepceWindow {
         let client_host = self.libraryclient.borrow();
         let client_host = client_host.as_ref().unwrap();
 
-        client_host.client().get_all_labels();
+        client_host.client().get_all_labels(None);
+        client_host.client().get_journal();
 
         let module_shell = ModuleShell::new(client_host, self.app.weak().clone());
         let module_widget = module_shell.widget();
@@ -503,7 +576,7 @@ impl NiepceWindow {
         let configuration = self.configuration.borrow();
         let cfg = configuration.as_ref().unwrap();
         let client = client_host.client();
-        let workspace = WorkspaceController::new(self.app.weak().clone(), cfg.clone(), client);
+        let workspace = WorkspaceController::new(cfg.clone(), client);
         if let Some(actions) = workspace.actions() {
             self.window.insert_action_group(actions.0, Some(actions.1));
         }
@@ -542,9 +615,26 @@ impl NiepceWindow {
             }
         ));
 
+        let import_progress = ImportProgressPanel::new();
+        import_progress
+            .widget()
+            .insert_before(&widgets.vbox, Some(&widgets.statusbar));
+        import_progress.set_forwarder(Some(Box::new(glib::clone!(
+            #[weak]
+            workspace,
+            move |job| workspace.send(WorkspaceEvent::CancelImport(job))
+        ))));
+        if let Some(notif_center) = self.widgets.get().map(|w| &w.notif_center) {
+            let import_progress = import_progress.clone();
+            notif_center
+                .signal_notify
+                .connect(move |ln| import_progress.on_lib_notification(&ln));
+        }
+
         // `ShellWidget` isn't `Debug` so we can't unwrap.
         let _ = self.shell_widgets.set(ShellWidgets {
             _workspace: workspace.clone(),
+            _import_progress: import_progress,
             shell: module_shell,
             _filmstrip: filmstrip,
         });
@@ -552,10 +642,53 @@ impl NiepceWindow {
         workspace.startup();
     }
 
+    /// The journal was listed: update the undo state and the history.
+    fn journal_listed(&self, entries: Vec<JournalEntry>) {
+        if let Some(actions) = self.journal_actions.get() {
+            actions
+                .undo
+                .set_enabled(journal_history::next_undo(&entries).is_some());
+            actions
+                .redo
+                .set_enabled(journal_history::next_redo(&entries).is_some());
+        }
+        if let Some(dialog) = self.history_dialog.borrow().as_ref() {
+            dialog.send(journal_history::Event::Listed(entries));
+        }
+    }
+
+    /// Undoing, or redoing if not `undo`, the entry with `label`
+    /// failed. Nothing was changed.
+    fn journal_failed(&self, label: &str, undo: bool) {
+        let message = if undo {
+            i18n_format!("Couldn't undo \"{}\"", label)
+        } else {
+            i18n_format!("Couldn't redo \"{}\"", label)
+        };
+        let dialog = gtk4::AlertDialog::builder()
+            .message(message)
+            .detail(i18n("The catalog was left unchanged."))
+            .build();
+        dialog.show(Some(self.window()));
+    }
+
+    fn on_action_history(&self) {
+        let Some(client_host) = self.libraryclient.borrow().clone() else {
+            return;
+        };
+        let dialog = self
+            .history_dialog
+            .borrow_mut()
+            .get_or_insert_with(|| JournalHistoryDialog::new(client_host.client().clone()))
+            .clone();
+        dialog.run(Some(self.window()));
+        client_host.client().get_journal();
+    }
+
     fn on_action_edit_labels(&self) {
         dbg_out!("edit labels");
         if let Some(ref libclient) = *self.libraryclient.borrow() {
-            let editlabel_dialog = EditLabels::new(libclient, self.app.weak().clone());
+            let editlabel_dialog = EditLabels::new(libclient);
             editlabel_dialog.run_modal(Some(self.window()), WindowSize::Default, move |_| {});
         }
     }
@@ -18,10 +18,10 @@
  */
 
 use std::cell::Cell;
+use std::collections::BTreeSet;
 use std::rc::Rc;
 use std::sync::{Arc, Weak};
 
-use gettextrs::gettext as i18n;
 use gtk4::prelude::*;
 use npc_fwk::{glib, gtk4};
 
@@ -30,15 +30,13 @@ use super::image_list_store::ImageListStore;
 use crate::NiepceApplication;
 use npc_engine::ThumbnailCache;
 use npc_engine::catalog;
+use npc_engine::catalog::LibFile;
 use npc_engine::catalog::props::NiepceProperties as Np;
-use npc_engine::catalog::{LibFile, NiepcePropertyIdx};
 use npc_engine::library::notification::LibNotification;
 use npc_engine::libraryclient::{ClientInterface, LibraryClient, LibraryClientHost};
 use npc_fwk::send_async_local;
 use npc_fwk::toolkit::widgets::MetadataPropertyBag;
-use npc_fwk::toolkit::{
-    AppController, Controller, ControllerImplCell, UndoCommand, UndoTransaction,
-};
+use npc_fwk::toolkit::{AppController, Controller, ControllerImplCell};
 use npc_fwk::{PropertyValue, dbg_out, err_out};
 
 #[derive(PartialEq)]
@@ -60,7 +58,6 @@ pub enum SelectionOutMsg {
 pub struct SelectionController {
     imp_: ControllerImplCell<SelectionInMsg, SelectionOutMsg>,
     client: Arc<LibraryClient>,
-    app: Weak<NiepceApplication>,
     store: Rc<ImageListStore>,
     content: Cell<ContentView>,
 }
@@ -96,7 +93,6 @@ impl SelectionController {
         let controller = Rc::new(SelectionController {
             imp_: ControllerImplCell::default(),
             client: client_host.client().clone(),
-            app,
             store,
             content: Cell::default(),
         });
@@ -109,7 +105,12 @@ impl SelectionController {
                 #[strong]
                 sender,
                 move |model, _, _| {
-                    let pos = model.selected();
+                    let selection = model.selection();
+                    let pos = if selection.is_empty() {
+                        gtk4::INVALID_LIST_POSITION
+                    } else {
+                        selection.minimum()
+                    };
                     send_async_local!(SelectionInMsg::Selected(pos), sender);
                 }
             ));
@@ -132,15 +133,33 @@ impl SelectionController {
         self.store.file(id)
     }
 
-    pub fn selection(&self) -> Option<catalog::LibraryId> {
-        let pos = self.store.selection_model().selected();
-        if pos == gtk4::INVALID_LIST_POSITION {
+    /// Get the selected files, in the view order.
+    pub fn selection(&self) -> Vec<catalog::LibraryId> {
+        let selection = self.store.selection_model().selection();
+        (0..selection.size())
+            .map(|n| self.store.get_file_id_at_pos(selection.nth(n as u32)))
+            .filter(|id| *id > 0)
+            .collect()
+    }
+
+    /// Get the first selected file. This is the one displayed.
+    pub fn primary_selection(&self) -> Option<catalog::LibraryId> {
+        let selection = self.store.selection_model().selection();
+        if selection.is_empty() {
             None
         } else {
-            Some(self.store.get_file_id_at_pos(pos))
+            Some(self.store.get_file_id_at_pos(selection.minimum()))
         }
     }
 
+    /// Get the selected `LibFile`s.
+    fn selected_files(&self) -> Vec<LibFile> {
+        self.selection()
+            .iter()
+            .filter_map(|id| self.store.file(*id))
+            .collect()
+    }
+
     pub fn select_previous(&self) {
         self.selection_move(Direction::Backwards)
     }
@@ -150,7 +169,7 @@ impl SelectionController {
     }
 
     fn selection_move(&self, direction: Direction) {
-        let selection = self.selection();
+        let selection = self.primary_selection();
         if selection.is_none() {
             return;
         }
@@ -174,7 +193,7 @@ impl SelectionController {
         };
 
         if moved {
-            self.store.selection_model().set_selected(pos);
+            self.store.selection_model().select_item(pos, true);
         }
     }
 
@@ -184,60 +203,17 @@ impl SelectionController {
         err_out!("rotate is not implemented");
     }
 
-    fn set_one_metadata(
-        &self,
-        undo_label: &str,
-        file_id: catalog::LibraryId,
-        meta: NiepcePropertyIdx,
-        old_value: i32,
-        new_value: i32,
-    ) -> bool {
-        let client_undo = self.client.clone();
-        let client_redo = self.client.clone();
-        let app = Weak::upgrade(&self.app).unwrap();
-        npc_fwk::toolkit::undo_do_command(
-            &app,
-            undo_label,
-            Box::new(move || {
-                client_redo.set_metadata(file_id, Np::Index(meta), &PropertyValue::Int(new_value));
-                npc_fwk::toolkit::Storage::Void
-            }),
-            Box::new(move |_| {
-                client_undo.set_metadata(file_id, Np::Index(meta), &PropertyValue::Int(old_value));
-            }),
-        );
-        true
-    }
-
-    fn set_metadata(
-        &self,
-        undo_label: &str,
-        file_id: catalog::LibraryId,
-        props: &MetadataPropertyBag,
-        old: &MetadataPropertyBag,
-    ) -> bool {
-        let mut undo = UndoTransaction::new(undo_label);
-        for key in props.keys() {
-            let old_value = old.get(key).cloned().unwrap_or(PropertyValue::Empty);
-            let new_value = props.get(key).cloned().unwrap();
-            let key = *key;
-            let client_undo = self.client.clone();
-            let client_redo = self.client.clone();
-            let command = UndoCommand::new(
-                Box::new(move || {
-                    client_redo.set_metadata(file_id, Np::from(key), &new_value);
-                    npc_fwk::toolkit::Storage::Void
-                }),
-                Box::new(move |_| {
-                    client_undo.set_metadata(file_id, Np::from(key), &old_value);
-                }),
-            );
-            undo.add(command);
+    /// Set the metadata from `props` to the files `ids`, as one journal
+    /// entry.
+    fn set_metadata(&self, ids: &[catalog::LibraryId], props: &MetadataPropertyBag) {
+        let props = props
+            .keys()
+            .filter_map(|key| props.get(key).map(|value| (Np::from(*key), value.clone())))
+            .collect::<Vec<_>>();
+        if ids.is_empty() || props.is_empty() {
+            return;
         }
-        undo.execute();
-        let app = Weak::upgrade(&self.app).unwrap();
-        app.begin_undo(undo);
-        true
+        self.client.set_properties_many(ids, props);
     }
 
     pub fn set_label(&self, label: i32) {
@@ -251,43 +227,47 @@ impl SelectionController {
 
     /// Set rating of specific file.
     pub fn set_rating_of(&self, id: catalog::LibraryId, rating: i32) {
-        self.set_property_of(id, catalog::NiepcePropertyIdx::NpXmpRatingProp, rating);
+        self.set_property_of(&[id], catalog::NiepcePropertyIdx::NpXmpRatingProp, rating);
     }
 
     pub fn set_flag(&self, flag: i32) {
         self.set_property(catalog::NiepcePropertyIdx::NpNiepceFlagProp, flag);
     }
 
+    /// Set the property of the selection.
     fn set_property(&self, idx: catalog::NiepcePropertyIdx, value: i32) {
         dbg_out!("property {:?} = {}", idx, value);
-        if let Some(selection) = self.selection() {
-            self.set_property_of(selection, idx, value)
-        }
+        self.set_property_of(&self.selection(), idx, value)
     }
 
-    fn set_property_of(&self, id: catalog::LibraryId, idx: catalog::NiepcePropertyIdx, value: i32) {
-        if let Some(mut file) = self.store.file(id) {
-            dbg_out!("old property is {}", file.property(Np::Index(idx)));
-            let old_value = file.property(Np::Index(idx));
-            let action = match idx {
-                NiepcePropertyIdx::NpNiepceFlagProp => i18n("Set Flag"),
-                NiepcePropertyIdx::NpXmpRatingProp => i18n("Set Rating"),
-                NiepcePropertyIdx::NpXmpLabelProp => i18n("Set Label"),
-                _ => i18n("Set Property"),
-            };
-            self.set_one_metadata(&action, id, idx, old_value, value);
-            // we need to set the property here so that undo/redo works
-            // consistently.
-            file.set_property(Np::Index(idx), value);
-        } else {
-            err_out!("requested file {} not found!", id);
+    /// Set the property of the files `ids`, as one journal entry.
+    fn set_property_of(
+        &self,
+        ids: &[catalog::LibraryId],
+        idx: catalog::NiepcePropertyIdx,
+        value: i32,
+    ) {
+        let mut found = vec![];
+        for id in ids {
+            if let Some(mut file) = self.store.file(*id) {
+                dbg_out!("old property is {}", file.property(Np::Index(idx)));
+                file.set_property(Np::Index(idx), value);
+                found.push(*id);
+            } else {
+                err_out!("requested file {} not found!", id);
+            }
+        }
+        if found.is_empty() {
+            return;
         }
+        self.client
+            .set_metadata_many(&found, Np::Index(idx), &PropertyValue::Int(value));
     }
 
-    pub fn set_properties(&self, props: &MetadataPropertyBag, old: &MetadataPropertyBag) {
-        if let Some(selection) = self.selection() {
-            self.set_metadata(&i18n("Set Properties"), selection, props, old);
-        }
+    /// Set the properties from the metadata pane. They apply to the
+    /// selection.
+    pub fn set_properties(&self, props: &MetadataPropertyBag) {
+        self.set_metadata(&self.selection(), props);
     }
 
     pub fn content_will_change(&self, content: super::ContentView) {
@@ -296,8 +276,63 @@ impl SelectionController {
     }
 
     pub fn write_metadata(&self) {
-        if let Some(selection) = self.selection() {
-            self.client.write_metadata(selection);
+        for id in self.selection() {
+            self.client.write_metadata(id);
+        }
+    }
+
+    /// Create a virtual copy of each selected file.
+    pub fn create_virtual_copy(&self) {
+        for id in self.selection() {
+            self.client.create_virtual_copy(id);
+        }
+    }
+
+    /// Delete the selected virtual copies. Masters are left alone.
+    pub fn delete_virtual_copy(&self) {
+        for file in self.selected_files().iter().filter(|f| f.is_copy()) {
+            self.client.delete_virtual_copy(file.id());
+        }
+    }
+
+    /// Stack the selected files, in the view order. The first one is
+    /// the top.
+    pub fn create_stack(&self) {
+        let selection = self.selection();
+        if selection.len() > 1 {
+            self.client.create_stack(&selection);
+        }
+    }
+
+    /// Unstack the stacks of the selected files.
+    pub fn delete_stack(&self) {
+        let stacks = self
+            .selected_files()
+            .iter()
+            .map(LibFile::stack_id)
+            .filter(|stack_id| *stack_id > 0)
+            .collect::<BTreeSet<_>>();
+        for stack_id in stacks {
+            self.client.delete_stack(stack_id);
+        }
+    }
+
+    /// Set the primary selection as the top of its stack.
+    pub fn set_stack_top(&self) {
+        if let Some(file) = self.primary_selection().and_then(|id| self.store.file(id)) {
+            if file.stack_id() > 0 {
+                self.client.set_stack_top(file.stack_id(), file.id());
+            }
+        }
+    }
+
+    /// Expand or collapse the stack of the primary selection.
+    pub fn toggle_stack(&self) {
+        if let Some(file) = self.primary_selection().and_then(|id| self.store.file(id)) {
+            self.store.toggle_stack(file.stack_id());
+            if let Some(pos) = self.store.pos_from_id(file.id()) {
+                self.store.selection_model().select_item(pos, true);
+            }
         }
     }
 
@@ -305,64 +340,49 @@ impl SelectionController {
     /// What delete means depend on the view. In an album it removes from the album
     /// From a folder it moves to trash.
     pub fn delete_from_view(&self) {
-        if let Some(selection) = self.selection() {
-            if let Some(ref f) = self.store.file(selection) {
-                match self.content.get() {
-                    ContentView::Album(id) => {
-                        self.remove_from_album(id, f);
-                    }
-                    ContentView::Folder(_) => {
-                        self.move_file_to_trash(f);
-                    }
-                    // XXX handle remove from keyword.
-                    _ => {}
-                }
+        let files = self.selected_files();
+        if files.is_empty() {
+            return;
+        }
+        match self.content.get() {
+            ContentView::Album(id) => {
+                self.remove_from_album(id, &files);
+            }
+            ContentView::Project(id) => {
+                self.remove_from_project(id, &files);
+            }
+            ContentView::Folder(_) => {
+                self.move_files_to_trash(&files);
             }
+            // XXX handle remove from keyword.
+            _ => {}
         }
     }
 
-    /// Remove file `f` from `album`
-    fn remove_from_album(&self, album: catalog::LibraryId, f: &LibFile) {
-        let file_id = f.id();
-        let client_undo = self.client.clone();
-        let client_redo = self.client.clone();
-        let app = Weak::upgrade(&self.app).unwrap();
-        npc_fwk::toolkit::undo_do_command(
-            &app,
-            &i18n("Remove from album"),
-            Box::new(move || {
-                client_redo.remove_from_album(&[file_id], album);
-                npc_fwk::toolkit::Storage::Void
-            }),
-            Box::new(move |_| client_undo.add_to_album(&[file_id], album)),
-        );
-    }
-
-    /// Move the file `f` to the trash.
-    fn move_file_to_trash(&self, f: &LibFile) {
+    /// Remove `files` from `album`
+    fn remove_from_album(&self, album: catalog::LibraryId, files: &[LibFile]) {
+        let file_ids = files.iter().map(LibFile::id).collect::<Vec<_>>();
+        self.client.remove_from_album(&file_ids, album);
+    }
+
+    /// Remove `files` from `project`
+    fn remove_from_project(&self, project: catalog::LibraryId, files: &[LibFile]) {
+        let file_ids = files.iter().map(LibFile::id).collect::<Vec<_>>();
+        self.client.remove_from_project(&file_ids, project);
+    }
+
+    /// Move the `files` to the trash.
+    fn move_files_to_trash(&self, files: &[LibFile]) {
         let trash_folder = self.client.get_trash_id();
-        let file_id = f.id();
-        let from_folder = f.folder_id();
-        let client_undo = self.client.clone();
-        let client_redo = self.client.clone();
-        let app = Weak::upgrade(&self.app).unwrap();
-        npc_fwk::toolkit::undo_do_command(
-            &app,
-            &i18n("Move to Trash"),
-            Box::new(move || {
-                client_redo.move_file_to_folder(file_id, from_folder, trash_folder);
-                npc_fwk::toolkit::Storage::Void
-            }),
-            Box::new(move |_| client_undo.move_file_to_folder(file_id, trash_folder, from_folder)),
-        );
+        let file_ids = files.iter().map(LibFile::id).collect::<Vec<_>>();
+        self.client.move_files_to_folder(&file_ids, trash_folder);
     }
 
     /// Move selection to trash
     pub fn move_to_trash(&self) {
-        if let Some(selection) = self.selection() {
-            if let Some(ref f) = self.store.file(selection) {
-                self.move_file_to_trash(f);
-            }
+        let files = self.selected_files();
+        if !files.is_empty() {
+            self.move_files_to_trash(&files);
         }
     }
 }
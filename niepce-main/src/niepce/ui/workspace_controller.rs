@@ -21,21 +21,28 @@ mod ws_item_row;
 mod ws_list_item;
 mod ws_list_model;
 
-use std::cell::OnceCell;
+use std::cell::{Cell, OnceCell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::sync::{Arc, Weak};
 
 use adw::prelude::*;
 use gettextrs::gettext as i18n;
+use i18n_format::i18n_format;
 use npc_fwk::{adw, gdk4, gio, glib, gtk4};
 use num_derive::FromPrimitive;
 
 use super::ContentView;
 use npc_engine::catalog;
-use npc_engine::importer::ImportRequest;
+use npc_engine::catalog::{MetadataPreset, WatchedFolder};
+use npc_engine::importer::{
+    CopyReport, DirectoryImporter, FolderWatcher, ImportJob, ImportJobId, ImportRequest,
+};
 use npc_engine::library::notification::LibNotification;
+use npc_engine::library::synchronize::{SyncOptions, SyncReport};
 use npc_engine::libraryclient::{ClientInterface, LibraryClient};
-use npc_fwk::base::Signal;
+use npc_fwk::base::{Executor, Signal};
 use npc_fwk::toolkit::{
     self, Controller, ControllerImplCell, DialogController, ListViewRow, UiController, WindowSize,
 };
@@ -44,8 +51,6 @@ use ws_item_row::WsItemRow;
 use ws_list_item::{CountUpdate, Item};
 use ws_list_model::WorkspaceList;
 
-use crate::NiepceApplication;
-
 #[derive(Clone, Copy, Debug, Default, FromPrimitive, PartialEq)]
 #[repr(i32)]
 pub enum TreeItemType {
@@ -60,6 +65,8 @@ pub enum TreeItemType {
     Project,
     Keyword,
     Album,
+    SmartCollections,
+    SmartCollection,
 }
 
 pub enum Event {
@@ -69,6 +76,10 @@ pub enum Event {
     RowCollapsed(u32),
     NewFolder,
     NewAlbum,
+    NewSmartCollection,
+    /// Edit the rules of the current smart collection.
+    EditSmartCollection,
+    NewProject,
     /// Delete the current item.
     DeleteItem,
     /// Rename the current item.
@@ -77,24 +88,67 @@ pub enum Event {
     Import,
     /// Sent after the import is initiated
     PerformImport(ImportRequest),
+    /// Cancel the import job.
+    CancelImport(ImportJobId),
     /// Import a library
     ImportLibrary,
+    /// Manage the watched folders
+    WatchedFolders,
+    /// The watched folders were changed.
+    WatchedFoldersChanged,
+    /// The watched folders were loaded.
+    WatchedFoldersLoaded(Vec<WatchedFolder>),
+    /// New files landed in the watched folder.
+    WatchedFilesAdded(WatchedFolder, Vec<PathBuf>),
+    /// The new files of the watched folder that aren't duplicates.
+    WatchedFilesChecked(WatchedFolder, Vec<PathBuf>),
+    /// Import the new files of the watched folder, with the preset.
+    ImportWatchedFiles(WatchedFolder, Vec<PathBuf>, Option<MetadataPreset>),
+    /// Import keywords from a file
+    ImportKeywords,
+    /// Export keywords to a file
+    ExportKeywords,
+    /// Check the catalog for missing files
+    ScanMissingFiles,
+    /// Choose the new location of the selected folder
+    FindMissingFolder,
+    /// Choose a directory to search the missing files
+    RelinkMissingFiles,
+    /// Synchronize the selected folder with the storage
+    SynchronizeFolder,
+    /// Recount the smart collections after the metadata changed.
+    CountSmartCollections,
     /// `LibFile`s dropped onto workspace. (target, type, source)
     DropLibFile(catalog::LibraryId, TreeItemType, Vec<catalog::LibraryId>),
 }
 
+/// The results for the watched folders, from the library thread.
+enum WatchedMsg {
+    FoldersLoaded(Vec<WatchedFolder>),
+    FilesChecked(WatchedFolder, Vec<PathBuf>),
+    ImportFiles(WatchedFolder, Vec<PathBuf>, Option<MetadataPreset>),
+}
+
 pub struct WorkspaceController {
     imp_: ControllerImplCell<Event, ()>,
-    app: Weak<NiepceApplication>,
     cfg: Rc<toolkit::Configuration>,
     widgets: OnceCell<Widgets>,
     client: Weak<LibraryClient>,
     action_group: OnceCell<gio::ActionGroup>,
     pub selection_changed: Signal<ContentView>,
+    /// Whether the user requested a missing file scan.
+    scanning_missing: Cell<bool>,
+    /// The executors of the running import jobs.
+    import_jobs: RefCell<HashMap<ImportJobId, Executor>>,
+    /// The smart collections, to edit them.
+    smart_collections: RefCell<HashMap<catalog::LibraryId, catalog::SmartCollection>>,
+    /// Whether a recount of the smart collections is scheduled.
+    count_pending: Cell<bool>,
+    folder_watcher: OnceCell<Rc<FolderWatcher>>,
+    watched_tx: toolkit::Sender<WatchedMsg>,
 
     icon_trash: gio::Icon,
     icon_roll: gio::Icon,
-    // icon_project: gio::Icon,
 }
 
 struct Widgets {
@@ -105,12 +159,14 @@ struct Widgets {
 
     // position of the nodes in the rootstore
     folders_node: gtk4::TreeListRow,
-    // Projects are not implemented yet.
-    // project_node: gtk4::TreeListRow,
+    projects_node: gtk4::TreeListRow,
     keywords_node: gtk4::TreeListRow,
     albums_node: gtk4::TreeListRow,
+    smart_collections_node: gtk4::TreeListRow,
     icon_keyword: gio::Icon,
     icon_album: gio::Icon,
+    icon_project: gio::Icon,
+    icon_smart_collection: gio::Icon,
     // icon_folder: gio::Icon,
     cfg: std::rc::Weak<toolkit::Configuration>,
 }
@@ -123,6 +179,8 @@ impl Widgets {
         let icon_folder = gio::ThemedIcon::new("folder-symbolic").upcast();
         let icon_keyword = gio::ThemedIcon::new("tag-symbolic").upcast();
         let icon_album = gio::ThemedIcon::new("open-book-symbolic").upcast();
+        let icon_smart_collection = gio::ThemedIcon::new("system-search-symbolic").upcast();
+        let icon_project = gio::ThemedIcon::new("file-cabinet-symbolic").upcast();
 
         let main_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
 
@@ -164,7 +222,9 @@ impl Widgets {
                     if let Some(item) = tree_list_row.item() {
                         match item.downcast_ref::<Item>().unwrap().tree_item_type() {
                             TreeItemType::Folders
+                            | TreeItemType::Projects
                             | TreeItemType::Albums
+                            | TreeItemType::SmartCollections
                             | TreeItemType::Keywords => {
                                 // We connect the expanded notify signal only
                                 // for these top level tree item.
@@ -213,19 +273,24 @@ impl Widgets {
             &i18n("Pictures"),
             TreeItemType::Folders,
         );
-        // Projects are not implemented yet
-        // let project_node = Self::add_toplevel_item(
-        //     &treestore,
-        //     &self.icon_project,
-        //     &i18n("Projects"),
-        //     TreeItemType::Projects,
-        // );
+        let projects_node = WorkspaceController::add_toplevel_item(
+            &treemodel,
+            &icon_project,
+            &i18n("Projects"),
+            TreeItemType::Projects,
+        );
         let albums_node = WorkspaceController::add_toplevel_item(
             &treemodel,
             &icon_album,
             &i18n("Albums"),
             TreeItemType::Albums,
         );
+        let smart_collections_node = WorkspaceController::add_toplevel_item(
+            &treemodel,
+            &icon_smart_collection,
+            &i18n("Smart Collections"),
+            TreeItemType::SmartCollections,
+        );
         let keywords_node = WorkspaceController::add_toplevel_item(
             &treemodel,
             &icon_keyword,
@@ -251,10 +316,15 @@ impl Widgets {
         menu.append_section(None, &section);
         section.append(Some(&i18n("New Folder…")), Some("workspace.NewFolder"));
         section.append(Some(&i18n("New Album…")), Some("workspace.NewAlbum"));
-        // section.append(
-        //     Some(&i18n("New Project…")),
-        //     Some("workspace.NewProject"),
-        // );
+        section.append(
+            Some(&i18n("New Smart Collection…")),
+            Some("workspace.NewSmartCollection"),
+        );
+        section.append(Some(&i18n("New Project…")), Some("workspace.NewProject"));
+        section.append(
+            Some(&i18n("Edit Smart Collection…")),
+            Some("workspace.EditSmartCollection"),
+        );
         section.append(Some(&i18n("Rename…")), Some("workspace.RenameItem"));
         section.append(Some(&i18n("Delete")), Some("workspace.DeleteItem"));
 
@@ -265,6 +335,40 @@ impl Widgets {
             Some(&i18n("Import Library…")),
             Some("workspace.ImportLibrary"),
         );
+        section.append(
+            Some(&i18n("Watched Folders…")),
+            Some("workspace.WatchedFolders"),
+        );
+
+        let section = gio::Menu::new();
+        menu.append_section(None, &section);
+        section.append(
+            Some(&i18n("Check for Missing Files")),
+            Some("workspace.ScanMissingFiles"),
+        );
+        section.append(
+            Some(&i18n("Find Missing Folder…")),
+            Some("workspace.FindMissingFolder"),
+        );
+        section.append(
+            Some(&i18n("Relink Missing Files…")),
+            Some("workspace.RelinkMissingFiles"),
+        );
+        section.append(
+            Some(&i18n("Synchronize Folder…")),
+            Some("workspace.SynchronizeFolder"),
+        );
+
+        let section = gio::Menu::new();
+        menu.append_section(None, &section);
+        section.append(
+            Some(&i18n("Import Keywords…")),
+            Some("workspace.ImportKeywords"),
+        );
+        section.append(
+            Some(&i18n("Export Keywords…")),
+            Some("workspace.ExportKeywords"),
+        );
 
         add_btn.set_menu_model(Some(&menu));
 
@@ -314,12 +418,15 @@ impl Widgets {
             librarytree,
             treemodel,
             context_menu,
-            // project_node,
             folders_node: folders_node.unwrap(),
+            projects_node: projects_node.unwrap(),
             albums_node: albums_node.unwrap(),
             keywords_node: keywords_node.unwrap(),
+            smart_collections_node: smart_collections_node.unwrap(),
             icon_album,
             icon_keyword,
+            icon_project,
+            icon_smart_collection,
             cfg: Rc::downgrade(cfg),
         }
     }
@@ -374,7 +481,7 @@ impl Widgets {
             &self.icon_keyword,
             keyword.keyword(),
             keyword.id(),
-            0, // keyword.parent(),
+            keyword.parent(),
             TreeItemType::Keyword,
         )
         .is_some()
@@ -417,6 +524,75 @@ impl Widgets {
         }
     }
 
+    fn add_project_item(&self, project: &catalog::Project) {
+        let was_empty = self
+            .projects_node
+            .children()
+            .map(|children| children.n_items() == 0)
+            .unwrap_or(true);
+        if WorkspaceController::add_item(
+            &self.projects_node,
+            &self.icon_project,
+            project.name(),
+            project.id(),
+            0,
+            TreeItemType::Project,
+        )
+        .is_some()
+            && was_empty
+        {
+            self.expand_from_cfg("workspace_projects_expanded", &self.projects_node);
+        }
+    }
+
+    fn remove_project_item(&self, id: catalog::LibraryId) {
+        if let Some(store) = self
+            .projects_node
+            .children()
+            .and_then(|children| children.downcast::<WorkspaceList>().ok())
+        {
+            if let Err(err) = store.remove_by_id(id) {
+                err_out!("Couldn't remove project item {}: {:?}", id, err);
+            }
+        }
+    }
+
+    fn add_smart_collection_item(&self, collection: &catalog::SmartCollection) {
+        let was_empty = self
+            .smart_collections_node
+            .children()
+            .map(|children| children.n_items() == 0)
+            .unwrap_or(true);
+        if WorkspaceController::add_item(
+            &self.smart_collections_node,
+            &self.icon_smart_collection,
+            collection.name(),
+            collection.id(),
+            0,
+            TreeItemType::SmartCollection,
+        )
+        .is_some()
+            && was_empty
+        {
+            self.expand_from_cfg(
+                "workspace_smart_collections_expanded",
+                &self.smart_collections_node,
+            );
+        }
+    }
+
+    fn remove_smart_collection_item(&self, id: catalog::LibraryId) {
+        if let Some(store) = self
+            .smart_collections_node
+            .children()
+            .and_then(|children| children.downcast::<WorkspaceList>().ok())
+        {
+            if let Err(err) = store.remove_by_id(id) {
+                err_out!("Couldn't remove smart collection item {}: {:?}", id, err);
+            }
+        }
+    }
+
     fn expand_from_cfg(&self, key: &str, row: &gtk4::TreeListRow) {
         let expanded = self
             .cfg
@@ -431,8 +607,10 @@ impl Widgets {
     fn model_for_tree_item_type(&self, tree_item_type: TreeItemType) -> Option<WorkspaceList> {
         match tree_item_type {
             TreeItemType::Folders => self.folders_node.children(),
+            TreeItemType::Projects => self.projects_node.children(),
             TreeItemType::Keywords => self.keywords_node.children(),
             TreeItemType::Albums => self.albums_node.children(),
+            TreeItemType::SmartCollections => self.smart_collections_node.children(),
             _ => {
                 err_out!("model_for_tree_item_type: Incorrect node type {tree_item_type:?}");
                 None
@@ -477,11 +655,41 @@ impl Controller for WorkspaceController {
             RowCollapsed(pos) => self.row_expanded_collapsed(pos, false),
             NewFolder => self.action_new_folder(),
             NewAlbum => self.action_new_album(),
+            NewSmartCollection => self.action_edit_smart_collection(None),
+            EditSmartCollection => {
+                if let Some((TreeItemType::SmartCollection, id)) = self.selected_item_id() {
+                    self.action_edit_smart_collection(Some(id));
+                }
+            }
+            NewProject => self.action_new_project(),
             RenameItem => self.action_rename_item(),
             DeleteItem => self.action_delete_item(),
             Import => self.action_import(),
             PerformImport(request) => self.perform_file_import(&request),
+            CancelImport(job) => self.cancel_import(job),
             ImportLibrary => self.action_import_library(),
+            WatchedFolders => self.action_watched_folders(),
+            WatchedFoldersChanged => self.load_watched_folders(),
+            WatchedFoldersLoaded(folders) => {
+                if let Some(watcher) = self.folder_watcher.get() {
+                    watcher.set_folders(&folders);
+                }
+            }
+            WatchedFilesAdded(folder, files) => self.watched_files_added(folder, files),
+            WatchedFilesChecked(folder, files) => self.watched_files_checked(folder, files),
+            ImportWatchedFiles(folder, files, preset) => {
+                self.import_watched_files(&folder, files, preset)
+            }
+            ImportKeywords => self.action_keywords_file(false),
+            ExportKeywords => self.action_keywords_file(true),
+            ScanMissingFiles => self.action_scan_missing_files(),
+            FindMissingFolder => self.action_find_missing_folder(),
+            RelinkMissingFiles => self.action_relink_missing_files(),
+            SynchronizeFolder => self.action_synchronize_folder(),
+            CountSmartCollections => {
+                self.count_pending.set(false);
+                self.count_smart_collections();
+            }
             DropLibFile(target, type_, source) => self.action_drop_libfile(target, type_, source),
         }
     }
@@ -501,11 +709,20 @@ impl UiController for WorkspaceController {
             (self.action_group.get_or_init(|| {
                 let group = npc_fwk::sending_action_group!(
                     self.sender(),
-                    // ("NewProject", move |_, _| {});
+                    ("NewProject", Event::NewProject),
                     ("NewFolder", Event::NewFolder),
                     ("NewAlbum", Event::NewAlbum),
+                    ("NewSmartCollection", Event::NewSmartCollection),
+                    ("EditSmartCollection", Event::EditSmartCollection),
                     ("Import", Event::Import),
                     ("ImportLibrary", Event::ImportLibrary),
+                    ("WatchedFolders", Event::WatchedFolders),
+                    ("ImportKeywords", Event::ImportKeywords),
+                    ("ExportKeywords", Event::ExportKeywords),
+                    ("ScanMissingFiles", Event::ScanMissingFiles),
+                    ("FindMissingFolder", Event::FindMissingFolder),
+                    ("RelinkMissingFiles", Event::RelinkMissingFiles),
+                    ("SynchronizeFolder", Event::SynchronizeFolder),
                     ("RenameItem", Event::RenameItem),
                     ("DeleteItem", Event::DeleteItem)
                 );
@@ -517,25 +734,57 @@ impl UiController for WorkspaceController {
 
 impl WorkspaceController {
     pub fn new(
-        app: Weak<NiepceApplication>,
         cfg: Rc<toolkit::Configuration>,
         client: &Arc<LibraryClient>,
     ) -> Rc<WorkspaceController> {
+        let (watched_tx, watched_rx) = toolkit::channel();
         let ctrl = Rc::new(WorkspaceController {
             imp_: ControllerImplCell::default(),
-            app,
             cfg,
             widgets: OnceCell::new(),
             action_group: OnceCell::new(),
             selection_changed: Signal::default(),
+            scanning_missing: Cell::new(false),
+            import_jobs: RefCell::default(),
+            smart_collections: RefCell::default(),
+            count_pending: Cell::new(false),
+            folder_watcher: OnceCell::new(),
+            watched_tx,
             client: Arc::downgrade(client),
             icon_trash: gio::ThemedIcon::new("user-trash-symbolic").upcast(),
             icon_roll: gio::ThemedIcon::new("image-round-symbolic").upcast(),
-            // icon_project: gio::ThemedIcon::new("file-cabinet-symbolic").upcast(),
         });
 
         <Self as Controller>::start(&ctrl);
 
+        let sender = ctrl.sender();
+        let _ = ctrl
+            .folder_watcher
+            .set(FolderWatcher::new(Box::new(glib::clone!(
+                #[strong]
+                sender,
+                move |folder, files| {
+                    npc_fwk::send_async_local!(
+                        Event::WatchedFilesAdded(folder.clone(), files),
+                        sender
+                    );
+                }
+            ))));
+        toolkit::channels::receiver_attach(watched_rx, move |msg| match msg {
+            WatchedMsg::FoldersLoaded(folders) => {
+                npc_fwk::send_async_local!(Event::WatchedFoldersLoaded(folders), sender);
+            }
+            WatchedMsg::FilesChecked(folder, files) => {
+                npc_fwk::send_async_local!(Event::WatchedFilesChecked(folder, files), sender);
+            }
+            WatchedMsg::ImportFiles(folder, files, preset) => {
+                npc_fwk::send_async_local!(
+                    Event::ImportWatchedFiles(folder, files, preset),
+                    sender
+                );
+            }
+        });
+
         ctrl
     }
 
@@ -543,8 +792,11 @@ impl WorkspaceController {
     pub fn startup(&self) {
         if let Some(client) = self.client.upgrade() {
             client.get_all_folders(None);
-            client.get_all_keywords();
+            client.get_all_keywords(None);
             client.get_all_albums();
+            client.get_all_smart_collections();
+            client.get_all_projects();
+            self.load_watched_folders();
         } else {
             err_out!("couldn't get client");
         }
@@ -584,6 +836,14 @@ impl WorkspaceController {
                         client.query_album_content(id);
                         ContentView::Album(id)
                     }
+                    TreeItemType::SmartCollection => {
+                        client.query_smart_collection_content(id);
+                        ContentView::SmartCollection(id)
+                    }
+                    TreeItemType::Project => {
+                        client.query_project_content(id);
+                        ContentView::Project(id)
+                    }
                     _ => {
                         dbg_out!("Something selected of type {:?}", type_);
                         ContentView::Empty
@@ -607,6 +867,7 @@ impl WorkspaceController {
                 TreeItemType::Projects => Some("workspace_projects_expanded"),
                 TreeItemType::Keywords => Some("workspace_keywords_expanded"),
                 TreeItemType::Albums => Some("workspace_albums_expanded"),
+                TreeItemType::SmartCollections => Some("workspace_smart_collections_expanded"),
                 // Not an error. This is no-op
                 _ => None,
             } {
@@ -655,6 +916,25 @@ impl WorkspaceController {
         }
     }
 
+    fn action_rename_project(&self, project: catalog::LibraryId, name: &str) {
+        if let Some(client) = self.client.upgrade() {
+            let window = self
+                .widget()
+                .ancestor(gtk4::Window::static_type())
+                .and_downcast::<gtk4::Window>();
+            npc_fwk::toolkit::request::request_name(
+                window.as_ref(),
+                &i18n("Rename project"),
+                &i18n("Project _name:"),
+                Some(name),
+                move |name| {
+                    dbg_out!("Rename project {}", &name);
+                    client.rename_project(project, name.to_string());
+                },
+            );
+        }
+    }
+
     /// Rename the selected item
     fn action_rename_item(&self) {
         if let Some(item) = self.selected_item() {
@@ -663,6 +943,8 @@ impl WorkspaceController {
             let type_ = item.tree_item_type();
             match type_ {
                 TreeItemType::Album => self.action_rename_album(id, &name),
+                TreeItemType::Project => self.action_rename_project(id, &name),
+                TreeItemType::SmartCollection => self.action_edit_smart_collection(Some(id)),
                 _ => err_out!("Wrong type {:?}", type_),
             }
         }
@@ -674,6 +956,12 @@ impl WorkspaceController {
             match type_ {
                 TreeItemType::Folder => self.action_delete_folder(id),
                 TreeItemType::Album => self.action_delete_album(id),
+                TreeItemType::Project => self.action_delete_project(id),
+                TreeItemType::SmartCollection => {
+                    if let Some(client) = self.client.upgrade() {
+                        client.delete_smart_collection(id);
+                    }
+                }
                 _ => err_out!("Wrong type {:?}", type_),
             }
         }
@@ -729,6 +1017,36 @@ impl WorkspaceController {
         }
     }
 
+    fn action_new_project(&self) {
+        if let Some(client) = self.client.upgrade() {
+            let window = self
+                .widget()
+                .ancestor(gtk4::Window::static_type())
+                .and_downcast::<gtk4::Window>();
+            npc_fwk::toolkit::request::request_name(
+                window.as_ref(),
+                &i18n("New Project"),
+                &i18n("Project _name:"),
+                Some(&i18n("Untitled project")),
+                move |name| {
+                    client.create_project(name.to_string());
+                },
+            );
+        }
+    }
+
+    /// Edit the smart collection `id`, or create a new one if `None`.
+    fn action_edit_smart_collection(&self, id: Option<catalog::LibraryId>) {
+        use crate::niepce::ui::dialogs::SmartCollectionDialog;
+
+        if let Some(client) = self.client.upgrade() {
+            let collection = id.and_then(|id| self.smart_collections.borrow().get(&id).cloned());
+            let dialog = SmartCollectionDialog::new(client, collection);
+            let parent = self.widget().root().and_downcast::<gtk4::Window>();
+            dialog.run_modal(parent.as_ref(), WindowSize::Default, |_| {});
+        }
+    }
+
     fn action_delete_album(&self, id: catalog::LibraryId) {
         let window = self
             .widget()
@@ -761,6 +1079,38 @@ impl WorkspaceController {
         dialog.present();
     }
 
+    fn action_delete_project(&self, id: catalog::LibraryId) {
+        let window = self
+            .widget()
+            .ancestor(gtk4::Window::static_type())
+            .and_downcast::<gtk4::Window>();
+        let dialog = npc_fwk::toolkit::confirm::request(
+            &i18n("Delete selected project?"),
+            &i18n("The project will be deleted. The pictures will be kept."),
+            Some(i18n("_Delete")),
+            true,
+            window.as_ref(),
+        );
+        dialog.connect_response(
+            None,
+            glib::clone!(
+                #[strong]
+                dialog,
+                #[strong(rename_to = client)]
+                self.client,
+                move |_, response| {
+                    if response == "confirm" {
+                        if let Some(client) = client.upgrade() {
+                            client.delete_project(id);
+                        }
+                    }
+                    dialog.destroy();
+                }
+            ),
+        );
+        dialog.present();
+    }
+
     fn perform_file_import(&self, request: &ImportRequest) {
         // import
         // XXX change the API to provide more details.
@@ -771,15 +1121,45 @@ impl WorkspaceController {
 
         let importer = request.importer();
         if let Some(client) = self.client.upgrade() {
+            let executor = Executor::new("import".into());
+            let job = ImportJob::new(client.notif_sender().clone(), executor.terminator());
+            job.skip(request.skipped_files());
             let client = client.sender().clone();
+            let stacking = request.stacking().clone();
+            let preset = request.metadata_preset().cloned();
+            let import_job = job.clone();
             importer.do_import(
+                &executor,
                 request,
+                &job,
                 Box::new(
-                    move |base: &std::path::Path, files: &npc_fwk::utils::FileList| {
-                        client.import_files(base.to_path_buf(), files.0.clone());
+                    move |base: &std::path::Path,
+                          files: &npc_fwk::utils::FileList,
+                          report: Option<&CopyReport>| {
+                        if let Some(report) = report {
+                            import_job.copy_finished(report);
+                        }
+                        client.import_files(
+                            base.to_path_buf(),
+                            files.0.clone(),
+                            stacking.clone(),
+                            preset.clone(),
+                            Some(import_job.clone()),
+                        );
                     },
                 ),
             );
+            self.import_jobs.borrow_mut().insert(job.id(), executor);
+        }
+    }
+
+    /// Cancel the import `job`. The files already copied are still
+    /// added to the catalog. This doesn't wait: the job is removed
+    /// when the import is finished.
+    fn cancel_import(&self, job: ImportJobId) {
+        if let Some(executor) = self.import_jobs.borrow().get(&job) {
+            dbg_out!("Cancel import job {job}");
+            executor.request_cancel();
         }
     }
 
@@ -802,6 +1182,93 @@ impl WorkspaceController {
         }
     }
 
+    fn load_watched_folders(&self) {
+        if let Some(client) = self.client.upgrade() {
+            let tx = self.watched_tx.clone();
+            client.get_all_watched_folders(Box::new(move |folders| {
+                npc_fwk::send_async_any!(WatchedMsg::FoldersLoaded(folders), tx);
+            }));
+        }
+    }
+
+    fn action_watched_folders(&self) {
+        use crate::niepce::ui::dialogs::WatchedFoldersDialog;
+
+        if let Some(client) = self.client.upgrade() {
+            let dialog = WatchedFoldersDialog::new(client);
+            let parent = self.widget().root().and_downcast::<gtk4::Window>();
+            let tx = self.sender();
+            dialog.run_modal(parent.as_ref(), WindowSize::Default, move |_| {
+                npc_fwk::send_async_local!(Event::WatchedFoldersChanged, tx);
+            });
+        }
+    }
+
+    /// New `files` landed in the watched `folder`. Leave out those
+    /// already in the catalog.
+    fn watched_files_added(&self, folder: WatchedFolder, files: Vec<PathBuf>) {
+        dbg_out!("New files in watched folder {:?}", folder.path());
+        if let Some(client) = self.client.upgrade() {
+            let tx = self.watched_tx.clone();
+            client.find_duplicates(
+                files.clone(),
+                Box::new(move |duplicates| {
+                    let files = files
+                        .iter()
+                        .filter(|file| !duplicates.contains(file))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    if files.is_empty() {
+                        dbg_out!("Only duplicates in watched folder {:?}", folder.path());
+                        return;
+                    }
+                    npc_fwk::send_async_any!(WatchedMsg::FilesChecked(folder.clone(), files), tx);
+                }),
+            );
+        }
+    }
+
+    /// The new `files` of the watched `folder` aren't in the catalog.
+    /// Get its preset to import them.
+    fn watched_files_checked(&self, folder: WatchedFolder, files: Vec<PathBuf>) {
+        if folder.preset_id() == 0 {
+            self.import_watched_files(&folder, files, None);
+            return;
+        }
+        if let Some(client) = self.client.upgrade() {
+            let tx = self.watched_tx.clone();
+            client.get_all_metadata_presets(Box::new(move |presets| {
+                let preset = presets
+                    .into_iter()
+                    .find(|preset| preset.id() == folder.preset_id());
+                npc_fwk::send_async_any!(
+                    WatchedMsg::ImportFiles(folder.clone(), files.clone(), preset),
+                    tx
+                );
+            }));
+        }
+    }
+
+    /// Import the new `files` of the watched `folder`, in place or
+    /// copied to its destination.
+    fn import_watched_files(
+        &self,
+        folder: &WatchedFolder,
+        files: Vec<PathBuf>,
+        preset: Option<MetadataPreset>,
+    ) {
+        let mut importer = DirectoryImporter::default();
+        importer.set_copy(folder.copy());
+        let request = ImportRequest::new(
+            folder.path().to_string_lossy().to_string(),
+            folder.dest().unwrap_or(folder.path()),
+            Rc::new(importer),
+        )
+        .set_selected_files(Some(files))
+        .set_metadata_preset(preset);
+        self.perform_file_import(&request);
+    }
+
     fn action_import_library(&self) {
         use crate::niepce::ui::dialogs::ImportLibraryDialog;
 
@@ -817,6 +1284,233 @@ impl WorkspaceController {
         }
     }
 
+    /// Choose a keyword file, in the Lightroom keyword text format, to
+    /// import or `export` the keywords.
+    fn action_keywords_file(&self, export: bool) {
+        let Some(client) = self.client.upgrade() else {
+            return;
+        };
+        let parent = self
+            .widget()
+            .root()
+            .and_downcast_ref::<gtk4::Window>()
+            .cloned();
+        let (message, action, button) = if export {
+            (
+                i18n("Export Keywords"),
+                gtk4::FileChooserAction::Save,
+                i18n("Export"),
+            )
+        } else {
+            (
+                i18n("Import Keywords"),
+                gtk4::FileChooserAction::Open,
+                i18n("Import"),
+            )
+        };
+        #[allow(deprecated)]
+        let dialog = gtk4::FileChooserDialog::new(
+            Some(&message),
+            parent.as_ref(),
+            action,
+            &[
+                (&button, gtk4::ResponseType::Accept),
+                (&i18n("Cancel"), gtk4::ResponseType::Cancel),
+            ],
+        );
+        #[allow(deprecated)]
+        dialog.connect_response(move |d, response| {
+            if response == gtk4::ResponseType::Accept {
+                #[allow(deprecated)]
+                if let Some(path) = d.file().as_ref().and_then(gio::prelude::FileExt::path) {
+                    dbg_out!("keywords file: {:?}", path);
+                    if export {
+                        client.export_keywords(path);
+                    } else {
+                        client.import_keywords(path);
+                    }
+                }
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+
+    fn action_scan_missing_files(&self) {
+        if let Some(client) = self.client.upgrade() {
+            self.scanning_missing.set(true);
+            client.scan_missing_files();
+        }
+    }
+
+    /// Choose a directory, with the `title`, and call `f` with it.
+    fn choose_directory<F: Fn(std::path::PathBuf) + 'static>(&self, title: &str, f: F) {
+        let parent = self
+            .widget()
+            .root()
+            .and_downcast_ref::<gtk4::Window>()
+            .cloned();
+        #[allow(deprecated)]
+        let dialog = gtk4::FileChooserDialog::new(
+            Some(title),
+            parent.as_ref(),
+            gtk4::FileChooserAction::SelectFolder,
+            &[
+                (&i18n("Select"), gtk4::ResponseType::Accept),
+                (&i18n("Cancel"), gtk4::ResponseType::Cancel),
+            ],
+        );
+        #[allow(deprecated)]
+        dialog.connect_response(move |d, response| {
+            if response == gtk4::ResponseType::Accept {
+                #[allow(deprecated)]
+                if let Some(path) = d.file().as_ref().and_then(gio::prelude::FileExt::path) {
+                    f(path);
+                }
+            }
+            d.close();
+        });
+        dialog.present();
+    }
+
+    /// Choose the new location of the selected folder, like when the
+    /// drive was renamed.
+    fn action_find_missing_folder(&self) {
+        let Some((TreeItemType::Folder, id)) = self.selected_item_id() else {
+            return;
+        };
+        let client = self.client.clone();
+        self.choose_directory(&i18n("Find Missing Folder"), move |path| {
+            dbg_out!("remap folder {id} to {path:?}");
+            if let Some(client) = client.upgrade() {
+                client.remap_folder(id, path);
+            }
+        });
+    }
+
+    /// Choose a directory to search for the missing files.
+    fn action_relink_missing_files(&self) {
+        let client = self.client.clone();
+        self.choose_directory(&i18n("Relink Missing Files"), move |dir| {
+            if let Some(client) = client.upgrade() {
+                client.relink_missing_files(dir);
+            }
+        });
+    }
+
+    /// Synchronize the selected folder, and its subfolders. This does
+    /// a dry run first, applied once the report is confirmed.
+    fn action_synchronize_folder(&self) {
+        let Some((TreeItemType::Folder, id)) = self.selected_item_id() else {
+            return;
+        };
+        if let Some(client) = self.client.upgrade() {
+            client.synchronize_folder(
+                id,
+                true,
+                SyncOptions {
+                    dry_run: true,
+                    ..SyncOptions::default()
+                },
+            );
+        }
+    }
+
+    /// Show the dry run `report` of a folder synchronization and
+    /// apply it if confirmed.
+    fn folder_synchronized(&self, report: &SyncReport) {
+        if report.applied {
+            return;
+        }
+        let parent = self.widget().root().and_downcast::<gtk4::Window>();
+        if report.is_empty() {
+            let dialog = gtk4::AlertDialog::builder()
+                .message(i18n("The folder is in sync"))
+                .build();
+            dialog.show(parent.as_ref());
+            return;
+        }
+        let body = [
+            i18n_format!("{} new files will be imported.", report.added.len()),
+            i18n_format!("{} files are missing.", report.missing.len()),
+            i18n_format!(
+                "{} files have their XMP sidecar changed.",
+                report.xmp_changed.len()
+            ),
+        ]
+        .join("\n");
+        let dialog = npc_fwk::toolkit::confirm::request(
+            &i18n("Synchronize folder?"),
+            &body,
+            Some(i18n("_Synchronize")),
+            false,
+            parent.as_ref(),
+        );
+        if !report.missing.is_empty() {
+            dialog.add_response("remove", &i18n("_Remove Missing"));
+            dialog.set_response_appearance("remove", adw::ResponseAppearance::Destructive);
+        }
+        let folder = report.folder;
+        let recursive = report.recursive;
+        dialog.connect_response(
+            None,
+            glib::clone!(
+                #[strong]
+                dialog,
+                #[strong(rename_to = client)]
+                self.client,
+                move |_, response| {
+                    if response == "confirm" || response == "remove" {
+                        if let Some(client) = client.upgrade() {
+                            client.synchronize_folder(
+                                folder,
+                                recursive,
+                                SyncOptions {
+                                    dry_run: false,
+                                    remove_missing: response == "remove",
+                                },
+                            );
+                        }
+                    }
+                    dialog.destroy();
+                }
+            ),
+        );
+        dialog.present();
+    }
+
+    /// The folder to synchronize is missing on the storage.
+    fn folder_missing(&self) {
+        let dialog = gtk4::AlertDialog::builder()
+            .message(i18n("The folder is missing"))
+            .detail(i18n(
+                "Connect the drive, or use Find Missing Folder to locate it.",
+            ))
+            .build();
+        let parent = self.widget().root().and_downcast::<gtk4::Window>();
+        dialog.show(parent.as_ref());
+    }
+
+    /// Report the number of missing files.
+    fn missing_files(&self, count: usize) {
+        if !self.scanning_missing.replace(false) && count == 0 {
+            return;
+        }
+        let message = if count == 0 {
+            i18n("No missing files")
+        } else {
+            i18n_format!("{} files are missing", count)
+        };
+        let dialog = gtk4::AlertDialog::builder()
+            .message(message)
+            .detail(i18n(
+                "Use Find Missing Folder or Relink Missing Files to locate them.",
+            ))
+            .build();
+        let parent = self.widget().root().and_downcast::<gtk4::Window>();
+        dialog.show(parent.as_ref());
+    }
+
     /// A `LibFile` with `source` id was dropped onto `target` of `type_`.
     /// Act upon it.
     fn action_drop_libfile(
@@ -838,18 +1532,12 @@ impl WorkspaceController {
             }
             Album => {
                 if let Some(client) = self.client.upgrade() {
-                    let client_redo = client.clone();
-                    let redo_source = source.clone();
-                    let app = Weak::upgrade(&self.app).unwrap();
-                    npc_fwk::toolkit::undo_do_command(
-                        &app,
-                        &i18n("Add to Album"),
-                        Box::new(move || {
-                            client_redo.add_to_album(&redo_source, target);
-                            npc_fwk::toolkit::Storage::Void
-                        }),
-                        Box::new(move |_| client.remove_from_album(&source, target)),
-                    );
+                    client.add_to_album(&source, target);
+                }
+            }
+            Project => {
+                if let Some(client) = self.client.upgrade() {
+                    client.add_to_project(&source, target);
                 }
             }
             Keyword => {}
@@ -937,6 +1625,70 @@ impl WorkspaceController {
         }
     }
 
+    fn add_project_item(&self, project: &catalog::Project) {
+        if let Some(widgets) = self.widgets.get() {
+            widgets.add_project_item(project);
+            if let Some(client) = self.client.upgrade() {
+                client.count_project(project.id());
+            }
+        } else {
+            err_out!("couldn't get widgets");
+        }
+    }
+
+    fn remove_project_item(&self, id: catalog::LibraryId) {
+        if let Some(widgets) = self.widgets.get() {
+            widgets.remove_project_item(id);
+        } else {
+            err_out!("couldn't get widgets");
+        }
+    }
+
+    fn add_smart_collection_item(&self, collection: &catalog::SmartCollection) {
+        self.smart_collections
+            .borrow_mut()
+            .insert(collection.id(), collection.clone());
+        if let Some(widgets) = self.widgets.get() {
+            widgets.add_smart_collection_item(collection);
+            if let Some(client) = self.client.upgrade() {
+                client.count_smart_collection(collection.id());
+            }
+        } else {
+            err_out!("couldn't get widgets");
+        }
+    }
+
+    /// Schedule a recount of the smart collections when idle, once
+    /// for all the metadata changes notified until then.
+    fn schedule_count_smart_collections(&self) {
+        if self.count_pending.replace(true) {
+            return;
+        }
+        let sender = self.sender();
+        glib::idle_add_local_once(move || {
+            npc_fwk::send_async_local!(Event::CountSmartCollections, sender);
+        });
+    }
+
+    /// Recount the smart collections, as their content depends on the
+    /// metadata.
+    fn count_smart_collections(&self) {
+        if let Some(client) = self.client.upgrade() {
+            for id in self.smart_collections.borrow().keys() {
+                client.count_smart_collection(*id);
+            }
+        }
+    }
+
+    fn remove_smart_collection_item(&self, id: catalog::LibraryId) {
+        self.smart_collections.borrow_mut().remove(&id);
+        if let Some(widgets) = self.widgets.get() {
+            widgets.remove_smart_collection_item(id);
+        } else {
+            err_out!("couldn't get widgets");
+        }
+    }
+
     /// Add a toplevel item
     fn add_toplevel_item(
         treestore: &gtk4::TreeListModel,
@@ -1028,14 +1780,34 @@ impl WorkspaceController {
             LibNotification::AddedKeyword(k) => self.add_keyword_item(k),
             LibNotification::AddedAlbum(a) => self.add_album_item(a),
             LibNotification::AlbumDeleted(id) => self.remove_album_item(*id),
+            LibNotification::AddedProject(p) => self.add_project_item(p),
+            LibNotification::ProjectDeleted(id) => self.remove_project_item(*id),
+            LibNotification::AddedSmartCollection(c) => self.add_smart_collection_item(c),
+            LibNotification::SmartCollectionDeleted(id) => self.remove_smart_collection_item(*id),
+            LibNotification::SmartCollectionChanged(c) => {
+                self.smart_collections
+                    .borrow_mut()
+                    .insert(c.id(), c.clone());
+                if let Some(widgets) = self.widgets.get() {
+                    widgets.rename_item(TreeItemType::SmartCollections, c.id(), c.name());
+                }
+                if let Some(client) = self.client.upgrade() {
+                    client.count_smart_collection(c.id());
+                }
+            }
+            LibNotification::MetadataChanged(_) => self.schedule_count_smart_collections(),
             LibNotification::FolderCounted(count)
             | LibNotification::KeywordCounted(count)
-            | LibNotification::AlbumCounted(count) => {
+            | LibNotification::AlbumCounted(count)
+            | LibNotification::ProjectCounted(count)
+            | LibNotification::SmartCollectionCounted(count) => {
                 dbg_out!("count for container {} is {}", count.id, count.count);
                 let type_ = match ln {
                     LibNotification::FolderCounted(_) => TreeItemType::Folders,
                     LibNotification::KeywordCounted(_) => TreeItemType::Keywords,
                     LibNotification::AlbumCounted(_) => TreeItemType::Albums,
+                    LibNotification::ProjectCounted(_) => TreeItemType::Projects,
+                    LibNotification::SmartCollectionCounted(_) => TreeItemType::SmartCollections,
                     _ => unreachable!(),
                 };
                 if let Some(widgets) = self.widgets.get() {
@@ -1063,6 +1835,17 @@ impl WorkspaceController {
                     widgets.rename_item(TreeItemType::Albums, *id, name);
                 }
             }
+            LibNotification::ProjectRenamed(id, name) => {
+                if let Some(widgets) = self.widgets.get() {
+                    widgets.rename_item(TreeItemType::Projects, *id, name);
+                }
+            }
+            LibNotification::MissingFiles(ids) => self.missing_files(ids.len()),
+            LibNotification::FolderSynchronized(report) => self.folder_synchronized(report),
+            LibNotification::FolderMissing(_) => self.folder_missing(),
+            LibNotification::ImportFinished(summary) => {
+                self.import_jobs.borrow_mut().remove(&summary.job);
+            }
             LibNotification::FolderReparented(reparent) => {
                 if let Some(widgets) = self.widgets.get() {
                     let folders = &widgets.folders_node;
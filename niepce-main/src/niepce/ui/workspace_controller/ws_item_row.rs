@@ -63,7 +63,11 @@ impl ListViewRow<Item> for WsItemRow {
         expander.set_list_row(tree_list_row);
         match item.tree_item_type() {
             // The top levels always have the expander
-            TreeItemType::Folders | TreeItemType::Keywords | TreeItemType::Albums => {
+            TreeItemType::Folders
+            | TreeItemType::Projects
+            | TreeItemType::Keywords
+            | TreeItemType::Albums
+            | TreeItemType::SmartCollections => {
                 expander.set_hide_expander(false);
             }
             _ => {
@@ -162,7 +166,10 @@ mod imp {
                 self,
                 #[upgrade_or]
                 false,
-                move |_, _| matches!(this.type_.get(), TreeItemType::Album)
+                move |_, _| matches!(
+                    this.type_.get(),
+                    TreeItemType::Album | TreeItemType::Project
+                )
             ));
             drop_target.connect_drop(glib::clone!(
                 #[weak(rename_to = this)]
@@ -116,7 +116,12 @@ impl Item {
 
     pub fn create_children(&self) -> Option<&WorkspaceList> {
         match self.tree_item_type() {
-            TreeItemType::Trash | TreeItemType::Album => return None,
+            TreeItemType::Trash
+            | TreeItemType::Album
+            | TreeItemType::Project
+            | TreeItemType::SmartCollection => {
+                return None;
+            }
             _ => {}
         }
         Some(self.imp().children.get_or_init(WorkspaceList::new))